@@ -0,0 +1,308 @@
+use app::app::App;
+use app::app_folder::{AppFolder, ExecutionSummary, FolderStatus};
+use app::file_intent::Action;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+const EXIT_CODE_INVALID_USAGE: u8 = 1;
+// Returned when there are conflicts or when any file operation failed
+const EXIT_CODE_INCOMPLETE: u8 = 2;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum Command {
+    Scan,
+    Preview,
+    Execute,
+    Organize,
+    ApplyPlan,
+}
+
+impl Command {
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "scan" => Some(Command::Scan),
+            "preview" => Some(Command::Preview),
+            "execute" => Some(Command::Execute),
+            "organize" => Some(Command::Organize),
+            "apply-plan" => Some(Command::ApplyPlan),
+            _ => None,
+        }
+    }
+}
+
+struct Arguments {
+    command: Command,
+    root_path: String,
+    config_path: String,
+    folder_filter: Option<String>,
+    plan_path: Option<String>,
+    is_confirmed: bool,
+}
+
+fn print_usage() {
+    println!("Usage: cli_app <scan|preview|execute|organize|apply-plan> <folder_path> [options]");
+    println!("  scan                 Print the actions of every folder");
+    println!("  preview              Print the operations that would be performed");
+    println!("  execute              Perform the operations, requires --yes");
+    println!("  organize             Enable every rename then perform the operations, requires --yes");
+    println!("  apply-plan           Import each folder's plan.json then preview, or execute with --yes");
+    println!("Options:");
+    println!("  --config <path>      Path to the config folder, defaults to ./res");
+    println!("  --folder <name>      Only use folders whose name contains this");
+    println!("  --plan <path>        Plan to import instead of plan.json, requires a single folder");
+    println!("  --yes                Confirm execution of file changes");
+}
+
+fn parse_arguments(args: &[String]) -> Option<Arguments> {
+    let mut positional = Vec::new();
+    let mut config_path = Path::new("./res").to_string_lossy().to_string();
+    let mut folder_filter = None;
+    let mut plan_path = None;
+    let mut is_confirmed = false;
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config_path = args.next()?.clone(),
+            "--folder" => folder_filter = Some(args.next()?.clone()),
+            "--plan" => plan_path = Some(args.next()?.clone()),
+            "--yes" | "-y" => is_confirmed = true,
+            _ if arg.starts_with('-') => return None,
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if positional.len() != 2 {
+        return None;
+    }
+
+    Some(Arguments {
+        command: Command::from_str(positional[0].as_str())?,
+        root_path: positional[1].clone(),
+        config_path,
+        folder_filter,
+        plan_path,
+        is_confirmed,
+    })
+}
+
+async fn get_total_conflicts(folder: &AppFolder) -> usize {
+    folder.get_files().await
+        .to_iter()
+        .filter(|file| file.get_is_enabled() && file.get_action() == Action::Rename && file.get_is_conflict())
+        .count()
+}
+
+async fn print_folders_table(folders: &[Arc<AppFolder>]) {
+    let name_width = folders.iter().map(|folder| folder.get_folder_name().len()).max().unwrap_or(0).max(6);
+    print!("{:<name_width$} {:<8}", "Folder", "Status");
+    for action in Action::iterator() {
+        print!(" {:>9}", action.to_str());
+    }
+    println!(" {:>9}", "Conflicts");
+
+    for folder in folders.iter() {
+        print!("{:<name_width$} {:<8}", folder.get_folder_name(), folder.get_folder_status().to_str());
+        let action_count = *folder.get_file_tracker().read().await.get_action_count();
+        for action in Action::iterator() {
+            print!(" {:>9}", action_count[*action]);
+        }
+        println!(" {:>9}", get_total_conflicts(folder).await);
+    }
+}
+
+async fn print_folder_preview(folder: &AppFolder) {
+    let operations = folder.plan_file_changes().await;
+    if operations.is_empty() {
+        return;
+    }
+    println!("{}", folder.get_folder_name());
+    for operation in operations.iter() {
+        println!("  {}", operation);
+    }
+}
+
+async fn import_plans(folders: &[Arc<AppFolder>], plan_path: Option<&str>) -> Option<()> {
+    let plan_path = match plan_path {
+        Some(plan_path) => plan_path,
+        None => {
+            for folder in folders.iter() {
+                if tokio::fs::try_exists(folder.get_plan_path()).await.unwrap_or(false) {
+                    folder.import_plan_from_file().await;
+                }
+            }
+            return Some(());
+        },
+    };
+
+    // A single plan file can't be matched to multiple folders
+    if folders.len() != 1 {
+        eprintln!("--plan requires exactly one folder but {} were found, use --folder to select one", folders.len());
+        return None;
+    }
+    let data = match tokio::fs::read_to_string(plan_path).await {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("Failed to read plan from {}: {}", plan_path, err);
+            return None;
+        },
+    };
+    folders[0].import_plan(data.as_str()).await
+}
+
+async fn execute_folders(folders: &[Arc<AppFolder>]) -> ExecutionSummary {
+    let mut total_summary = ExecutionSummary::default();
+    for folder in folders.iter().filter(|folder| folder.get_folder_status() == FolderStatus::Pending) {
+        let summary = folder.execute_file_changes_and_rescan().await;
+        println!("{}: {}", folder.get_folder_name(), summary);
+        total_summary += &summary;
+    }
+    println!("Total: {}", total_summary);
+    total_summary
+}
+
+// Folders with renames that conflict aren't changed
+async fn organize_folders(folders: &[Arc<AppFolder>]) -> Option<ExecutionSummary> {
+    let mut total_summary = ExecutionSummary::default();
+    let mut is_organized = true;
+    for folder in folders.iter().filter(|folder| folder.get_folder_status() == FolderStatus::Pending) {
+        match folder.organize(false).await {
+            Some(summary) => {
+                println!("{}: {}", folder.get_folder_name(), summary);
+                total_summary += &summary;
+            },
+            None => is_organized = false,
+        }
+    }
+    println!("Total: {}", total_summary);
+    is_organized.then_some(total_summary)
+}
+
+async fn print_errors(app: &App, folders: &[Arc<AppFolder>]) {
+    for error in app.get_errors().read().await.iter() {
+        eprintln!("{:?}: {}", error.severity, error);
+    }
+    for folder in folders.iter() {
+        for error in folder.get_errors().read().await.iter() {
+            eprintln!("{:?}: {}: {}", error.severity, folder.get_folder_name(), error);
+        }
+        for error in folder.get_file_errors().read().await.iter() {
+            eprintln!("Error: {}: {}", folder.get_folder_name(), error);
+        }
+    }
+}
+
+async fn run(args: Arguments) -> ExitCode {
+    let app = match App::new(args.config_path.as_str()).await {
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!("Failed to create application: {}", err);
+            return ExitCode::from(EXIT_CODE_INVALID_USAGE);
+        },
+    };
+
+    // Login is only needed if the cache has to be fetched so a failure here isn't fatal
+    let is_credentials_saved = tokio::fs::try_exists(app.get_credentials_path().as_str()).await.unwrap_or(false);
+    let (res, _) = tokio::join!(
+        app.load_folders(args.root_path.clone()),
+        async {
+            match is_credentials_saved {
+                true => app.login().await,
+                false => None,
+            }
+        },
+    );
+    if res.is_none() {
+        print_errors(&app, &[]).await;
+        return ExitCode::from(EXIT_CODE_INVALID_USAGE);
+    }
+
+    let folders: Vec<Arc<AppFolder>> = app.get_folders().read().await
+        .iter()
+        .filter(|folder| match args.folder_filter.as_deref() {
+            Some(filter) => folder.get_folder_name().contains(filter),
+            None => true,
+        })
+        .cloned()
+        .collect();
+    let provider = app.get_provider().read().await.clone();
+    for folder in folders.iter() {
+        folder.perform_initial_load(provider.clone()).await;
+    }
+
+    let mut is_incomplete = false;
+    match args.command {
+        Command::Scan => {
+            print_folders_table(folders.as_slice()).await;
+        },
+        Command::Preview => {
+            for folder in folders.iter() {
+                print_folder_preview(folder).await;
+            }
+        },
+        Command::Execute if !args.is_confirmed => {
+            for folder in folders.iter() {
+                print_folder_preview(folder).await;
+            }
+            println!("Pass --yes to perform these changes");
+        },
+        Command::Execute => {
+            let total_summary = execute_folders(folders.as_slice()).await;
+            is_incomplete |= total_summary.failed > 0;
+        },
+        Command::Organize if !args.is_confirmed => {
+            print_folders_table(folders.as_slice()).await;
+            println!("Pass --yes to enable and perform every rename");
+        },
+        Command::Organize => {
+            match organize_folders(folders.as_slice()).await {
+                Some(total_summary) => is_incomplete |= total_summary.failed > 0,
+                None => is_incomplete = true,
+            }
+        },
+        Command::ApplyPlan => {
+            if import_plans(folders.as_slice(), args.plan_path.as_deref()).await.is_none() {
+                print_errors(&app, folders.as_slice()).await;
+                return ExitCode::from(EXIT_CODE_INVALID_USAGE);
+            }
+            if args.is_confirmed {
+                let total_summary = execute_folders(folders.as_slice()).await;
+                is_incomplete |= total_summary.failed > 0;
+            } else {
+                for folder in folders.iter() {
+                    print_folder_preview(folder).await;
+                }
+                println!("Pass --yes to perform these changes");
+            }
+        },
+    }
+
+    for folder in folders.iter() {
+        is_incomplete |= get_total_conflicts(folder).await > 0;
+    }
+    print_errors(&app, folders.as_slice()).await;
+
+    match is_incomplete {
+        true => ExitCode::from(EXIT_CODE_INCOMPLETE),
+        false => ExitCode::SUCCESS,
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    if args.contains(&"--help".to_owned()) || args.contains(&"-h".to_owned()) {
+        print_usage();
+        return ExitCode::SUCCESS;
+    }
+
+    let args = match parse_arguments(args.as_slice()) {
+        Some(args) => args,
+        None => {
+            print_usage();
+            return ExitCode::from(EXIT_CODE_INVALID_USAGE);
+        },
+    };
+    run(args).await
+}
@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const CACHE_FILE: &str = r#"{
+    "version": 1,
+    "series": { "id": 1, "seriesName": "Show" },
+    "episodes": [
+        { "id": 1, "airedSeason": 1, "airedEpisodeNumber": 1, "episodeName": "Pilot", "seriesId": 1 },
+        { "id": 2, "airedSeason": 1, "airedEpisodeNumber": 2, "episodeName": "Return", "seriesId": 1 }
+    ]
+}"#;
+
+const APP_CONFIG: &str = r#"{
+    "blacklist_extensions": ["nfo"],
+    "whitelist_folders": [],
+    "whitelist_filenames": [],
+    "whitelist_tags": []
+}"#;
+
+// A library with a config folder and a single series folder with a saved cache
+// Nothing is fetched since the cache is already saved and there are no credentials
+struct Library {
+    _dir: tempfile::TempDir,
+    config_path: PathBuf,
+    root_path: PathBuf,
+}
+
+impl Library {
+    fn new(files: &[&str]) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("res");
+        let root_path = dir.path().join("library");
+        let folder_path = root_path.join("Show");
+        std::fs::create_dir_all(&config_path).unwrap();
+        std::fs::create_dir_all(&folder_path).unwrap();
+        std::fs::write(config_path.join("app_config.json"), APP_CONFIG).unwrap();
+        std::fs::write(folder_path.join("tvdb_cache.json"), CACHE_FILE).unwrap();
+        for file in files {
+            std::fs::write(folder_path.join(file), "").unwrap();
+        }
+        Self { _dir: dir, config_path, root_path }
+    }
+
+    fn run(&self, args: &[&str]) -> (i32, String) {
+        let output = Command::new(env!("CARGO_BIN_EXE_cli_app"))
+            .arg(args[0])
+            .arg(&self.root_path)
+            .arg("--config")
+            .arg(&self.config_path)
+            .args(&args[1..])
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        (output.status.code().unwrap(), stdout)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        Path::new(&self.root_path).join("Show").join(path).exists()
+    }
+}
+
+#[test]
+fn scan_prints_action_counts() {
+    let library = Library::new(&["show.s01e01.mkv", "show.s01e02.mkv", "release.nfo"]);
+    let (code, stdout) = library.run(&["scan"]);
+    assert_eq!(code, 0);
+    let row = stdout.lines().find(|line| line.starts_with("Show")).unwrap();
+    let columns: Vec<&str> = row.split_whitespace().collect();
+    assert_eq!(columns[1], "Pending");
+    // Renames and deletes are the first two actions
+    assert_eq!(&columns[2..4], ["2", "1"]);
+}
+
+#[test]
+fn preview_does_not_change_files() {
+    let library = Library::new(&["show.s01e01.mkv"]);
+    let (code, stdout) = library.run(&["preview"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("Season 01/Show-S01E01-Pilot.mkv"), "{}", stdout);
+    assert!(library.exists("show.s01e01.mkv"));
+}
+
+#[test]
+fn execute_requires_confirmation() {
+    let library = Library::new(&["show.s01e01.mkv"]);
+    let (code, stdout) = library.run(&["execute"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("Pass --yes"));
+    assert!(library.exists("show.s01e01.mkv"));
+}
+
+#[test]
+fn execute_renames_files_and_rescans() {
+    let library = Library::new(&["show.s01e01.mkv", "show.s01e02.mkv"]);
+    let (code, stdout) = library.run(&["execute", "--yes"]);
+    assert_eq!(code, 0, "{}", stdout);
+    assert!(stdout.contains("Show: Renamed 2, deleted 0"), "{}", stdout);
+    assert!(library.exists("Season 01/Show-S01E01-Pilot.mkv"));
+    assert!(library.exists("Season 01/Show-S01E02-Return.mkv"));
+    assert!(!library.exists("show.s01e01.mkv"));
+
+    // Nothing is left to do once the files are renamed
+    let (code, stdout) = library.run(&["preview"]);
+    assert_eq!(code, 0);
+    assert!(!stdout.contains("Show"), "{}", stdout);
+}
+
+#[test]
+fn conflicts_give_incomplete_exit_code() {
+    let library = Library::new(&["show.s01e01.mkv", "show.s01e01.mp4", "Show.S01E01.mkv"]);
+    let (code, _) = library.run(&["scan"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn folder_filter_selects_folders() {
+    let library = Library::new(&["show.s01e01.mkv"]);
+    let (code, stdout) = library.run(&["scan", "--folder", "Other"]);
+    assert_eq!(code, 0);
+    assert!(!stdout.lines().any(|line| line.starts_with("Show")), "{}", stdout);
+}
+
+#[test]
+fn invalid_usage_exit_code() {
+    let output = Command::new(env!("CARGO_BIN_EXE_cli_app")).arg("rename").arg(".").output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let output = Command::new(env!("CARGO_BIN_EXE_cli_app")).arg("scan").output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn missing_plan_file_is_invalid_usage() {
+    let library = Library::new(&["show.s01e01.mkv"]);
+    let (code, _) = library.run(&["apply-plan", "--plan", "missing_plan.json"]);
+    assert_eq!(code, 1);
+    assert!(library.exists("show.s01e01.mkv"));
+}
+
+#[test]
+fn organize_renames_files_and_keeps_deletes() {
+    let library = Library::new(&["show.s01e01.mkv", "show.s01e02.mkv", "release.nfo"]);
+    let (code, stdout) = library.run(&["organize"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("Pass --yes"), "{}", stdout);
+    assert!(library.exists("show.s01e01.mkv"));
+
+    let (code, stdout) = library.run(&["organize", "--yes"]);
+    assert_eq!(code, 0, "{}", stdout);
+    assert!(stdout.contains("Show: Renamed 2, deleted 0"), "{}", stdout);
+    assert!(library.exists("Season 01/Show-S01E01-Pilot.mkv"));
+    assert!(library.exists("Season 01/Show-S01E02-Return.mkv"));
+    // Deletes start out disabled so they are never performed
+    assert!(library.exists("release.nfo"));
+}
+
+#[test]
+fn organize_with_conflicts_changes_nothing() {
+    let library = Library::new(&["show.s01e01.mkv", "show.s01e01.mp4", "Show.S01E01.mkv"]);
+    let (code, _) = library.run(&["organize", "--yes"]);
+    assert_eq!(code, 2);
+    assert!(library.exists("show.s01e01.mkv"));
+    assert!(library.exists("show.s01e01.mp4"));
+    assert!(library.exists("Show.S01E01.mkv"));
+}
@@ -1,16 +1,17 @@
 use app::app::App;
-use app::app_folder::FolderStatus;
 use eframe;
 use egui;
-use enum_map;
 use std::sync::Arc;
 use tokio;
+use tokio::sync::broadcast;
 use crate::helpers::render_invisible_width_widget;
 use crate::error_list::render_errors_list;
 use crate::settings_menu::{GuiSettings, render_settings_menu};
 use crate::app_folders_list::{GuiAppFoldersList, render_folders_list};
 use crate::app_folder::{GuiAppFolder, render_app_folder};
 use crate::app_series_search::{GuiSeriesSearch, render_series_search};
+use crate::app_series_matches::render_series_matches;
+use crate::folder_drop::{FolderDrop, check_dropped_folders, render_folder_drop_confirmation};
 
 pub struct GuiApp {
     pub(crate) app: Arc<App>,
@@ -18,6 +19,7 @@ pub struct GuiApp {
     pub(crate) gui_app_folder: GuiAppFolder,
     pub(crate) gui_series_search: GuiSeriesSearch,
     gui_settings: GuiSettings,
+    folder_drop: Option<FolderDrop>,
 
     is_force_refresh_thread_spawned: bool,
     is_gui_settings_opened: bool,
@@ -31,6 +33,7 @@ impl GuiApp {
             gui_app_folder: GuiAppFolder::new(),
             gui_series_search: GuiSeriesSearch::new(),
             gui_settings: GuiSettings::new(),
+            folder_drop: None,
             is_force_refresh_thread_spawned: false,
             is_gui_settings_opened: false,
         }
@@ -38,7 +41,7 @@ impl GuiApp {
 }
 
 impl GuiApp {
-    // Create a thread that refreshes ui when folders are updated
+    // Create a thread that refreshes ui when the app notifies a change
     fn setup_force_refresh_thread(&mut self, ctx: &egui::Context) {
         if self.is_force_refresh_thread_spawned {
             return;
@@ -46,42 +49,11 @@ impl GuiApp {
 
         self.is_force_refresh_thread_spawned = true;
         let ctx = ctx.clone();
-        let app = self.app.clone();
+        let mut changes = self.app.subscribe_changes();
         tokio::spawn(async move {
-            let mut old_busy_count = None;
-            let mut old_status_counts: enum_map::EnumMap<FolderStatus, usize> = enum_map::enum_map! { _ => 0 };
-            let mut new_status_counts: enum_map::EnumMap<FolderStatus, usize> = enum_map::enum_map! { _ => 0 };
-            loop {
-                let folders = app.get_folders().read().await;
-                let mut new_busy_count = 0;
-                for status in FolderStatus::iterator() {
-                    new_status_counts[*status] = 0;
-                }
-                for folder in folders.iter() {
-                    if folder.get_busy_lock().try_lock().is_err() {
-                        new_busy_count += 1;
-                    }
-                    let status = folder.get_folder_status().await;
-                    new_status_counts[status] += 1;
-                }
-                drop(folders);
-
-                // detect when folders have changed
-                let mut is_refresh = old_busy_count != Some(new_busy_count);
-                for status in FolderStatus::iterator() {
-                    if old_status_counts[*status] != new_status_counts[*status] {
-                        is_refresh = true;
-                    }
-                    old_status_counts[*status] = new_status_counts[*status];
-                }
-                old_busy_count = Some(new_busy_count);
-
-                // cap maximum refresh rate at 10fps in background
-                if is_refresh {
-                    ctx.request_repaint();
-                }
-                let duration = tokio::time::Duration::from_millis(100);
-                tokio::time::sleep(duration).await;
+            // Lagging means we missed changes so we refresh anyway
+            while let Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) = changes.recv().await {
+                ctx.request_repaint();
             }
         });
     }
@@ -91,6 +63,10 @@ impl eframe::App for GuiApp {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         self.gui_settings.update_frame(ctx, frame);
         self.setup_force_refresh_thread(ctx);
+        // A new drop replaces one that wasn't answered yet
+        if let Some(folder_drop) = check_dropped_folders(ctx, &self.app) {
+            self.folder_drop = Some(folder_drop);
+        }
 
         egui::SidePanel::left("Folders")
             .resizable(true)
@@ -101,7 +77,7 @@ impl eframe::App for GuiApp {
                         egui::TopBottomPanel::bottom("app_error_list")
                             .resizable(true)
                             .show_inside(ui, |ui| {
-                                render_errors_list(ui, errors.as_mut());
+                                render_errors_list(ui, &mut errors, &self.app);
                             });
                     }
                 } 
@@ -114,21 +90,16 @@ impl eframe::App for GuiApp {
 
         egui::CentralPanel::default()
             .show(ctx, |ui| {
-                let folders = self.app.get_folders().blocking_read();
-                let folder_index = *self.app.get_selected_folder_index().blocking_read();
-                let folder_index = match folder_index {
-                    Some(index) => index,
+                let folder = match self.app.get_selected_folder_blocking() {
+                    Some(folder) => folder,
                     None => {
                         ui.label("No folder selected");
                         return;
                     },
                 };
 
-                let folder = folders[folder_index].clone();
-                drop(folders);
-
-                let session = self.app.get_login_session().blocking_read();
-                render_app_folder(ui, session.as_ref(), &mut self.gui_app_folder, &folder);
+                let provider = self.app.get_provider().blocking_read();
+                render_app_folder(ui, provider.as_ref(), &mut self.gui_app_folder, &folder);
             });
 
         egui::Window::new("Series Search")
@@ -139,6 +110,16 @@ impl eframe::App for GuiApp {
                 render_series_search(ui, &mut self.gui_series_search, &self.app);
             });
         
+        egui::Window::new("Auto Match Series")
+            .collapsible(false)
+            .vscroll(false)
+            .open(&mut self.gui_app_folders_list.is_show_series_matches)
+            .show(ctx, |ui| {
+                render_series_matches(ui, &self.app);
+            });
+        
+        render_folder_drop_confirmation(ctx, &mut self.folder_drop, &self.app);
+
         egui::Window::new("Settings Menu")
             .collapsible(false)
             .vscroll(true)
@@ -1,12 +1,184 @@
-use app::bookmarks::Bookmark;
+use app::bookmarks::{Bookmark, BookmarkFilter, BookmarkTable};
+use app::file_intent::Action;
+use app::app_file::MutableAppFileList;
 use egui;
+use enum_map;
+use std::collections::BTreeSet;
+use crate::helpers::format_elapsed;
 
-pub fn render_file_bookmarks(ui: &mut egui::Ui, bookmark: &mut Bookmark) -> bool {
+#[derive(Default)]
+pub struct GuiBookmarkFilter {
+    pub filter: BookmarkFilter,
+    pub tag: Option<String>,
+}
+
+impl GuiBookmarkFilter {
+    pub fn is_match(&self, bookmark: Option<&Bookmark>) -> bool {
+        if !self.filter.is_match(bookmark) {
+            return false;
+        }
+        match self.tag.as_deref() {
+            None => true,
+            Some(tag) => bookmark.is_some_and(|bookmark| bookmark.has_tag(tag)),
+        }
+    }
+}
+
+pub fn get_bookmark_filter_counts(
+    files: &mut MutableAppFileList, bookmarks: &BookmarkTable, selected_action: Action,
+) -> enum_map::EnumMap<BookmarkFilter, usize> {
+    let mut counts: enum_map::EnumMap<BookmarkFilter, usize> = enum_map::enum_map! { _ => 0 };
+    let indices: Vec<usize> = files.get_file_tracker().get_action_indices(selected_action).iter().copied().collect();
+    for index in indices {
+        let file = match files.get(index) {
+            Some(file) => file,
+            None => continue,
+        };
+        let bookmark = bookmarks.get_for_file(file.get_src(), file.get_src_descriptor().as_ref());
+        for filter in BookmarkFilter::iterator() {
+            if filter.is_match(bookmark) {
+                counts[*filter] += 1;
+            }
+        }
+    }
+    counts
+}
+
+pub fn render_bookmark_filter(
+    ui: &mut egui::Ui,
+    selected_filter: &mut GuiBookmarkFilter, counts: &enum_map::EnumMap<BookmarkFilter, usize>,
+    all_tags: &BTreeSet<String>,
+) {
+    // Forget a tag once no bookmark uses it so files don't stay hidden
+    if selected_filter.tag.as_ref().is_some_and(|tag| !all_tags.contains(tag)) {
+        selected_filter.tag = None;
+    }
+
+    ui.horizontal(|ui| {
+        for filter in BookmarkFilter::iterator() {
+            let label = format!("{} ({})", filter.to_str(), counts[*filter]);
+            ui.selectable_value(&mut selected_filter.filter, *filter, label);
+        }
+        if all_tags.is_empty() {
+            return;
+        }
+        egui::ComboBox::from_id_source("bookmark_tag_filter")
+            .selected_text(selected_filter.tag.as_deref().unwrap_or("Any tag"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected_filter.tag, None, "Any tag");
+                for tag in all_tags {
+                    ui.selectable_value(&mut selected_filter.tag, Some(tag.clone()), tag.as_str());
+                }
+            });
+    });
+}
+
+fn render_tags_editor(
+    ui: &mut egui::Ui, id: egui::Id,
+    bookmark: &mut Bookmark, all_tags: &BTreeSet<String>,
+) -> bool {
+    let mut is_changed = false;
+    ui.horizontal_wrapped(|ui| {
+        let mut removed_tag = None;
+        for tag in &bookmark.tags {
+            if ui.small_button(format!("{} ✖", tag)).on_hover_text("Remove tag").clicked() {
+                removed_tag = Some(tag.clone());
+            }
+        }
+        if let Some(tag) = removed_tag {
+            is_changed = bookmark.remove_tag(tag.as_str()) || is_changed;
+        }
+    });
+
+    let mut buffer = ui.data_mut(|data| data.get_temp::<String>(id).unwrap_or_default());
+    let res = ui.add(egui::TextEdit::singleline(&mut buffer).hint_text("New tag"));
+    if res.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+        is_changed = bookmark.add_tag(buffer.as_str()) || is_changed;
+        buffer.clear();
+        res.request_focus();
+    }
+
+    let prefix = buffer.trim().to_lowercase();
+    let suggestions: Vec<&String> = all_tags
+        .iter()
+        .filter(|tag| !bookmark.has_tag(tag))
+        .filter(|tag| tag.to_lowercase().starts_with(prefix.as_str()))
+        .collect();
+    if !suggestions.is_empty() {
+        ui.horizontal_wrapped(|ui| {
+            for tag in suggestions {
+                if ui.small_button(tag.as_str()).on_hover_text("Add tag").clicked() {
+                    is_changed = bookmark.add_tag(tag.as_str()) || is_changed;
+                    buffer.clear();
+                }
+            }
+        });
+    }
+    ui.data_mut(|data| data.insert_temp(id, buffer));
+    is_changed
+}
+
+fn render_tags_popup(
+    ui: &mut egui::Ui, id_source: &str,
+    bookmark: &mut Bookmark, all_tags: &BTreeSet<String>, height: f32,
+) -> bool {
+    let id = egui::Id::new(("bookmark_tags", id_source));
+    let buffer_id = id.with("buffer");
+    let mut is_open = ui.data_mut(|data| data.get_temp::<bool>(id).unwrap_or(false));
+
+    let label = egui::RichText::new("🏷").strong().size(height).color(
+        match bookmark.tags.is_empty() {
+            true => egui::Color32::LIGHT_GRAY,
+            false => egui::Color32::LIGHT_BLUE,
+        }
+    );
+    let elem = egui::Label::new(label).sense(egui::Sense::click());
+    let mut res = ui.add(elem);
+    if !bookmark.tags.is_empty() {
+        res = res.on_hover_text(bookmark.tags.join(", "));
+    }
+    if res.clicked() {
+        is_open = !is_open;
+    }
+
+    let mut is_changed = false;
+    if is_open {
+        egui::Area::new(id)
+            .order(egui::Order::Foreground)
+            .fixed_pos(res.rect.left_bottom())
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(250.0);
+                    is_changed = render_tags_editor(ui, buffer_id, bookmark, all_tags);
+                    if ui.button("Close").clicked() {
+                        is_open = false;
+                    }
+                });
+            });
+        if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            is_open = false;
+        }
+    }
+    ui.data_mut(|data| data.insert_temp(id, is_open));
+    is_changed
+}
+
+// Flags are toggled through here so the bookmark remembers when it was changed
+fn toggle_flag(bookmark: &mut Bookmark, get_flag: impl FnOnce(&mut Bookmark) -> &mut bool) {
+    let flag = get_flag(bookmark);
+    *flag = !*flag;
+    bookmark.touch();
+}
+
+pub fn render_file_bookmarks(
+    ui: &mut egui::Ui, id_source: &str,
+    bookmark: &mut Bookmark, all_tags: &BTreeSet<String>,
+) -> bool {
     let height = ui.text_style_height(&egui::TextStyle::Monospace);
     let mut is_changed = false;
-    ui.horizontal(|ui| {
+    let res = ui.horizontal(|ui| {
         {
-            let value = &mut bookmark.is_favourite;
+            let value = bookmark.is_favourite;
             let label = egui::RichText::new("★").strong().size(height).color(
                 match value {
                     true => egui::Color32::GOLD,
@@ -15,12 +187,12 @@ pub fn render_file_bookmarks(ui: &mut egui::Ui, bookmark: &mut Bookmark) -> bool
             );
             let elem = egui::Label::new(label).sense(egui::Sense::click());
             if ui.add(elem).clicked() {
-                *value = !*value;
+                toggle_flag(bookmark, |bookmark| &mut bookmark.is_favourite);
                 is_changed = true;
             }
         }
         {
-            let value = &mut bookmark.is_unread;
+            let value = bookmark.is_unread;
             let label = egui::RichText::new("？").strong().size(height).color(
                 match value {
                     true => egui::Color32::DARK_RED,
@@ -29,12 +201,12 @@ pub fn render_file_bookmarks(ui: &mut egui::Ui, bookmark: &mut Bookmark) -> bool
             );
             let elem = egui::Label::new(label).sense(egui::Sense::click());
             if ui.add(elem).clicked() {
-                *value = !*value;
+                toggle_flag(bookmark, |bookmark| &mut bookmark.is_unread);
                 is_changed = true;
             }
         }
         {
-            let value = &mut bookmark.is_read;
+            let value = bookmark.is_read;
             let label = egui::RichText::new("✔").strong().size(height).color(
                 match value {
                     true => egui::Color32::DARK_GREEN,
@@ -43,10 +215,15 @@ pub fn render_file_bookmarks(ui: &mut egui::Ui, bookmark: &mut Bookmark) -> bool
             );
             let elem = egui::Label::new(label).sense(egui::Sense::click());
             if ui.add(elem).clicked() {
-                *value = !*value;
+                toggle_flag(bookmark, |bookmark| &mut bookmark.is_read);
                 is_changed = true;
             }
         }
+        is_changed = render_tags_popup(ui, id_source, bookmark, all_tags, height) || is_changed;
     });
+    if let Some(updated_at) = bookmark.updated_at.filter(|_| bookmark.is_any_selected()) {
+        let elapsed = format_elapsed(std::time::SystemTime::from(updated_at));
+        res.response.on_hover_text(format!("Bookmark changed {}", elapsed));
+    }
     is_changed
 }
@@ -0,0 +1,95 @@
+use app::app_folder::AppFolder;
+use egui;
+use std::sync::Arc;
+use tokio;
+use crate::app_folder_episode_cache_list::render_episode_search_table;
+use crate::fuzzy_search::{FuzzySearcher, render_search_bar};
+
+const MAX_PICKER_HEIGHT: f32 = 300.0;
+
+// Remembered separately for each folder
+pub struct GuiEpisodePicker {
+    searcher: FuzzySearcher,
+}
+
+impl GuiEpisodePicker {
+    pub fn new() -> Self {
+        Self {
+            searcher: FuzzySearcher::new(),
+        }
+    }
+}
+
+impl Default for GuiEpisodePicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The picker is opened from the context menu of any file list so the file is kept in egui's memory
+fn get_picker_file_id(folder: &AppFolder) -> egui::Id {
+    egui::Id::new("episode_picker_file").with(folder.get_folder_path())
+}
+
+pub fn open_episode_picker(ctx: &egui::Context, folder: &AppFolder, file_index: usize) {
+    ctx.data_mut(|data| data.insert_temp(get_picker_file_id(folder), file_index));
+}
+
+pub fn render_episode_picker(ctx: &egui::Context, picker: &mut GuiEpisodePicker, folder: &Arc<AppFolder>) {
+    let id = get_picker_file_id(folder);
+    let file_index: usize = match ctx.data(|data| data.get_temp(id)) {
+        Some(file_index) => file_index,
+        None => return,
+    };
+    // The file list could have been rescanned since the picker was opened
+    let (src, descriptor) = match folder.get_files_blocking().get(file_index) {
+        Some(file) => (file.get_src().to_string(), *file.get_src_descriptor()),
+        None => {
+            ctx.data_mut(|data| data.remove::<usize>(id));
+            return;
+        },
+    };
+
+    let is_not_busy = folder.get_busy_lock().try_lock().is_ok();
+    let mut is_open = true;
+    let mut is_closed = false;
+    egui::Window::new("Assign episode")
+        .collapsible(false)
+        .open(&mut is_open)
+        .show(ctx, |ui| {
+            ui.label(src.as_str());
+            render_search_bar(ui, &mut picker.searcher);
+            ui.separator();
+
+            let cache = folder.get_cache().blocking_read();
+            let cache = match cache.as_ref() {
+                Some(cache) => cache,
+                None => {
+                    ui.label("No cache loaded");
+                    return;
+                },
+            };
+            if cache.episodes.is_empty() {
+                ui.label("No episodes available");
+                return;
+            }
+
+            ui.set_max_height(MAX_PICKER_HEIGHT);
+            ui.add_enabled_ui(is_not_busy, |ui| {
+                let clicked_descriptor = render_episode_search_table(ui, &mut picker.searcher, cache, descriptor, |_, _, _| {});
+                if let Some(key) = clicked_descriptor {
+                    tokio::spawn({
+                        let folder = folder.clone();
+                        async move {
+                            folder.reassign_episode(file_index, key).await
+                        }
+                    });
+                    is_closed = true;
+                }
+            });
+        });
+
+    if !is_open || is_closed {
+        ctx.data_mut(|data| data.remove::<usize>(id));
+    }
+}
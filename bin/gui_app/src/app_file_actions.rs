@@ -1,10 +1,13 @@
 use app::app_file::MutableAppFile;
+use app::app_folder::AppFolder;
 use app::file_intent::Action;
 use egui;
 use lazy_static::lazy_static;
 use open as cross_open;
 use std::path::Path;
+use std::sync::Arc;
 use tokio;
+use crate::app_episode_picker::open_episode_picker;
 
 lazy_static! {
     static ref ACTION_SHORTCUTS: enum_map::EnumMap<Action, egui::KeyboardShortcut> = enum_map::enum_map!{
@@ -32,8 +35,9 @@ pub fn check_file_shortcuts(ui: &mut egui::Ui, file: &mut MutableAppFile<'_>) {
 
 pub fn render_file_context_menu(
     ui: &mut egui::Ui,
-    folder_path: &str, file: &mut MutableAppFile<'_>, is_not_busy: bool,
+    folder: &Arc<AppFolder>, file: &mut MutableAppFile<'_>, is_not_busy: bool,
 ) {
+    let folder_path = folder.get_folder_path();
     let current_action = file.get_action();
     if ui.button("Open file").clicked() {
         tokio::spawn({
@@ -79,4 +83,37 @@ pub fn render_file_context_menu(
             ui.close_menu();
         }
     }
+
+    // Fixes the destination when the episode parsed from the filename is wrong
+    if ui.button("Assign episode…").clicked() {
+        open_episode_picker(ui.ctx(), folder, file.get_index());
+        ui.close_menu();
+    }
+
+    // Avoid blocking since the ignore list is being modified in the background
+    let is_always_ignored = match folder.get_ignored_files().try_read() {
+        Ok(ignored_files) => ignored_files.contains(file.get_src()),
+        Err(_) => return,
+    };
+
+    ui.separator();
+
+    let label = match is_always_ignored {
+        true => "Stop always ignoring",
+        false => "Always ignore",
+    };
+    if ui.button(label).clicked() {
+        tokio::spawn({
+            let folder = folder.clone();
+            let src = file.get_src().to_string();
+            async move {
+                if is_always_ignored {
+                    folder.remove_from_ignore_list(src.as_str()).await
+                } else {
+                    folder.add_to_ignore_list(src.as_str()).await
+                }
+            }
+        });
+        ui.close_menu();
+    }
 }
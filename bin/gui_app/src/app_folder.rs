@@ -1,29 +1,55 @@
-use app::app_folder::AppFolder;
-use app::file_intent::Action;
+use app::app_folder::{AppFolder, ExecutionPlan, ExecutionProgress, ExecutionRecord};
+use app::tvdb_cache::EpisodeOrdering;
 use std::sync::Arc;
-use tvdb::api::LoginSession;
+use app::metadata_provider::MetadataProvider;
 use tokio;
+use tokio::sync::oneshot;
 use crate::fuzzy_search::FuzzySearcher;
-use crate::app_folder_files_tab_list::{FileTab, render_files_tab_list};
+use crate::app_folder_files_tab_list::{GuiFilesTabList, render_files_tab_list};
 use crate::app_folder_episode_cache_list::render_episode_cache_list;
-use crate::helpers::render_invisible_width_widget;
-use crate::tvdb_tables::{render_series_table, render_episode_table};
-use crate::error_list::render_errors_list;
+use crate::helpers::{format_bytes, render_invisible_width_widget};
+use crate::artwork::{ArtworkTextures, render_folder_artwork};
+use crate::tvdb_tables::{render_series_table, render_episode_table, render_actors_table};
+use crate::error_list::{render_errors, render_file_errors_list};
+
+const MAX_LISTED_PLANNED_OPERATIONS: usize = 100;
+
+// The plan is made in the background since it has to check the folder on disk
+struct PendingExecutionPlan {
+    folder: Arc<AppFolder>,
+    receiver: oneshot::Receiver<(ExecutionPlan, Vec<String>)>,
+}
+
+struct ExecuteConfirmation {
+    folder: Arc<AppFolder>,
+    plan: ExecutionPlan,
+    // Renames enabled by organizing which are disabled again if it is cancelled
+    organized_sources: Vec<String>,
+}
 
 pub struct GuiAppFolder {
     searcher: FuzzySearcher,
-    selected_tab: FileTab,
+    files_tab_list: GuiFilesTabList,
     is_show_episode_cache: bool,
     pub(crate) is_show_series_search: bool,
+    artwork: ArtworkTextures,
+    pending_execution_plan: Option<PendingExecutionPlan>,
+    execute_confirmation: Option<ExecuteConfirmation>,
+    // Plans that delete more files than the configured threshold are still confirmed
+    is_execute_confirmation_skipped: bool,
 }
 
 impl GuiAppFolder {
     pub fn new() -> Self {
         Self {
             searcher: FuzzySearcher::new(),
-            selected_tab: FileTab::FileAction(Action::Complete),
+            files_tab_list: GuiFilesTabList::new(),
             is_show_episode_cache: false,
             is_show_series_search: false,
+            artwork: ArtworkTextures::new(),
+            pending_execution_plan: None,
+            execute_confirmation: None,
+            is_execute_confirmation_skipped: false,
         }
     }
 }
@@ -34,13 +60,253 @@ impl Default for GuiAppFolder {
     }
 }
 
+fn render_execution_progress_bar(ui: &mut egui::Ui, progress: &ExecutionProgress) {
+    let total_progress: f32 = progress.total_completed as f32 / progress.total_operations as f32;
+    let mut text = format!("{}/{}", progress.total_completed, progress.total_operations);
+    if progress.total_bytes_to_copy > 0 {
+        const MB: f32 = 1024.0*1024.0;
+        let copied = progress.total_bytes_copied as f32 / MB;
+        let total = progress.total_bytes_to_copy as f32 / MB;
+        text.push_str(format!(" ({:.1}/{:.1} MB copied)", copied, total).as_str());
+    }
+    if let Some(file) = progress.current_file.as_deref() {
+        text.push_str(format!(" {}", file).as_str());
+    }
+    let elem = egui::ProgressBar::new(total_progress)
+        .text(text)
+        .desired_width(ui.available_width());
+    ui.add(elem);
+}
+
+fn render_execution_record(ui: &mut egui::Ui, record: &ExecutionRecord) {
+    let text = match record.dest.is_empty() {
+        true => format!("{} {}", record.action.to_str(), record.src),
+        false => format!("{} {} → {}", record.action.to_str(), record.src, record.dest),
+    };
+    match &record.result {
+        Ok(()) => {
+            ui.label(text);
+        },
+        Err(err) => {
+            ui.label(egui::RichText::new(format!("🗙 {}", text)).color(egui::Color32::DARK_RED))
+                .on_hover_text(err);
+        },
+    }
+}
+
+// Failures are listed so they can be fixed while successes are collapsed
+fn render_execution_records(ui: &mut egui::Ui, records: &[ExecutionRecord]) {
+    if records.is_empty() {
+        return;
+    }
+    egui::CollapsingHeader::new("Last run")
+        .id_source("last_execution_records")
+        .default_open(records.iter().any(|record| !record.is_ok()))
+        .show(ui, |ui| {
+            for record in records.iter().filter(|record| !record.is_ok()) {
+                render_execution_record(ui, record);
+            }
+            let total_succeeded = records.iter().filter(|record| record.is_ok()).count();
+            if total_succeeded == 0 {
+                return;
+            }
+            egui::CollapsingHeader::new(format!("{} succeeded", total_succeeded))
+                .id_source("last_execution_records_succeeded")
+                .default_open(false)
+                .show(ui, |ui| {
+                    for record in records.iter().filter(|record| record.is_ok()) {
+                        render_execution_record(ui, record);
+                    }
+                });
+        });
+}
+
+fn spawn_execute_file_changes(folder: &Arc<AppFolder>) {
+    let folder = folder.clone();
+    tokio::spawn(async move {
+        folder.execute_file_changes_and_rescan().await
+    });
+}
+
+fn spawn_execution_plan(ctx: &egui::Context, folder: &Arc<AppFolder>) -> PendingExecutionPlan {
+    let (sender, receiver) = oneshot::channel();
+    tokio::spawn({
+        let ctx = ctx.clone();
+        let folder = folder.clone();
+        async move {
+            let plan = folder.get_execution_plan().await;
+            let _ = sender.send((plan, Vec::new()));
+            ctx.request_repaint();
+        }
+    });
+    PendingExecutionPlan {
+        folder: folder.clone(),
+        receiver,
+    }
+}
+
+// The plan is only made if the renames could be enabled, otherwise the reason is added to the folder errors
+fn spawn_organize_plan(ctx: &egui::Context, folder: &Arc<AppFolder>, auto_skip_conflicts: bool) -> PendingExecutionPlan {
+    let (sender, receiver) = oneshot::channel();
+    tokio::spawn({
+        let ctx = ctx.clone();
+        let folder = folder.clone();
+        async move {
+            if let Some(organized_sources) = folder.enable_organize_renames(auto_skip_conflicts).await {
+                let plan = folder.get_execution_plan().await;
+                let _ = sender.send((plan, organized_sources));
+            }
+            ctx.request_repaint();
+        }
+    });
+    PendingExecutionPlan {
+        folder: folder.clone(),
+        receiver,
+    }
+}
+
+fn check_pending_execution_plan(gui: &mut GuiAppFolder) {
+    let pending = match gui.pending_execution_plan.as_mut() {
+        Some(pending) => pending,
+        None => return,
+    };
+    let (plan, organized_sources) = match pending.receiver.try_recv() {
+        Ok(res) => res,
+        Err(oneshot::error::TryRecvError::Empty) => return,
+        Err(oneshot::error::TryRecvError::Closed) => {
+            gui.pending_execution_plan = None;
+            return;
+        },
+    };
+    let folder = pending.folder.clone();
+    gui.pending_execution_plan = None;
+
+    let is_confirm_required = 
+        !gui.is_execute_confirmation_skipped || 
+        plan.total_deletes > folder.get_filter_rules().confirm_delete_threshold;
+    if is_confirm_required {
+        gui.execute_confirmation = Some(ExecuteConfirmation { folder, plan, organized_sources });
+    } else {
+        spawn_execute_file_changes(&folder);
+    }
+}
+
+fn render_execution_plan_summary(ui: &mut egui::Ui, confirmation: &ExecuteConfirmation) {
+    let plan = &confirmation.plan;
+    let filter_rules = confirmation.folder.get_filter_rules();
+    egui::Grid::new("execution_plan_summary")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Renames");
+            ui.label(plan.total_renames.to_string());
+            ui.end_row();
+            ui.label("Deletes");
+            let label = match plan.total_deletes {
+                0 => egui::RichText::new("0"),
+                total => egui::RichText::new(format!("{} ({})", total, format_bytes(plan.total_bytes_to_delete)))
+                    .color(egui::Color32::DARK_RED),
+            };
+            ui.label(label);
+            ui.end_row();
+            if plan.skipped_conflicts > 0 {
+                ui.label("Skipped conflicts");
+                ui.label(plan.skipped_conflicts.to_string());
+                ui.end_row();
+            }
+            if plan.skipped_invalid > 0 {
+                ui.label("Skipped invalid");
+                ui.label(plan.skipped_invalid.to_string());
+                ui.end_row();
+            }
+        });
+    if plan.total_deletes > 0 {
+        match filter_rules.use_trash {
+            true => ui.label("Deleted files are moved to the trash"),
+            false => ui.label(egui::RichText::new("Deleted files are permanently removed").color(egui::Color32::DARK_RED)),
+        };
+    }
+
+    egui::CollapsingHeader::new(format!("{} operations", plan.operations.len()))
+        .id_source("execution_plan_operations")
+        .default_open(false)
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for operation in plan.operations.iter().take(MAX_LISTED_PLANNED_OPERATIONS) {
+                    ui.label(operation.to_string());
+                }
+                let total_hidden = plan.operations.len().saturating_sub(MAX_LISTED_PLANNED_OPERATIONS);
+                if total_hidden > 0 {
+                    ui.weak(format!("and {} more", total_hidden));
+                }
+            });
+        });
+}
+
+fn render_execute_confirmation(ctx: &egui::Context, gui: &mut GuiAppFolder) {
+    let confirmation = match gui.execute_confirmation.as_ref() {
+        Some(confirmation) => confirmation,
+        None => return,
+    };
+
+    let folder = &confirmation.folder;
+    let is_not_busy = folder.get_busy_lock().try_lock().is_ok();
+    let is_empty = confirmation.plan.operations.is_empty();
+    let confirm_delete_threshold = folder.get_filter_rules().confirm_delete_threshold;
+    let mut is_open = true;
+    let mut is_closed = false;
+    let mut is_executed = false;
+    egui::Window::new("Execute changes")
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut is_open)
+        .show(ctx, |ui| {
+            ui.label(folder.get_folder_name());
+            render_execution_plan_summary(ui, confirmation);
+            ui.checkbox(&mut gui.is_execute_confirmation_skipped, "Don't ask again for this session")
+                .on_hover_text(format!("Deleting more than {} files always asks", confirm_delete_threshold));
+            ui.horizontal(|ui| {
+                let res = ui.add_enabled(is_not_busy && !is_empty, egui::Button::new("Execute"));
+                if res.clicked() {
+                    spawn_execute_file_changes(folder);
+                    is_executed = true;
+                    is_closed = true;
+                }
+                res.on_disabled_hover_ui(|ui| {
+                    if is_empty { ui.label("Nothing to execute"); }
+                    else if !is_not_busy { ui.label("Folder is busy"); }
+                });
+                if ui.button("Cancel").clicked() {
+                    is_closed = true;
+                }
+            });
+        });
+
+    if !is_open || is_closed {
+        let confirmation = gui.execute_confirmation.take();
+        // Renames are left as they were before organizing if nothing was executed
+        if let Some(confirmation) = confirmation.filter(|confirmation| !is_executed && !confirmation.organized_sources.is_empty()) {
+            tokio::spawn(async move {
+                confirmation.folder.disable_organize_renames(confirmation.organized_sources.as_slice()).await
+            });
+        }
+    }
+}
+
 fn render_folder_controls(
-    ui: &mut egui::Ui, session: Option<&Arc<LoginSession>>,
+    ui: &mut egui::Ui, provider: Option<&Arc<dyn MetadataProvider>>,
     gui: &mut GuiAppFolder, folder: &Arc<AppFolder>,
 ) {
     let is_not_busy = folder.get_busy_lock().try_lock().is_ok();
     let is_cache_loaded = folder.get_cache().blocking_read().is_some();
-    let is_logged_in = session.is_some();
+    let is_provider_ready = provider.is_some_and(|provider| provider.is_ready());
+    let rate_limit_delay = provider.and_then(|provider| provider.get_rate_limit_delay());
+    let is_not_rate_limited = rate_limit_delay.is_none();
+    // Enable the refresh buttons again once the delay is over
+    if let Some(delay) = rate_limit_delay {
+        ui.ctx().request_repaint_after(delay);
+    }
+    let execution_progress = folder.get_execution_progress().borrow().clone();
 
     ui.horizontal(|ui| {
         ui.add_enabled_ui(is_cache_loaded && is_not_busy, |ui| {
@@ -71,15 +337,15 @@ fn render_folder_controls(
             });
         });
         
-        ui.add_enabled_ui(is_cache_loaded && is_not_busy && is_logged_in, |ui| {
+        ui.add_enabled_ui(is_cache_loaded && is_not_busy && is_provider_ready && is_not_rate_limited, |ui| {
             let res = ui.button("Refresh cache from api");
             if res.clicked() {
-                if let Some(session) = session {
+                if let Some(provider) = provider {
                     tokio::spawn({
                         let folder = folder.clone();
-                        let session = session.clone();
+                        let provider = provider.clone();
                         async move {
-                            folder.refresh_cache_from_api(session).await?;
+                            folder.refresh_cache_from_api(provider).await?;
                             tokio::join!(
                                 folder.update_file_intents(),
                                 folder.save_cache_to_file(),
@@ -90,23 +356,111 @@ fn render_folder_controls(
                 }
             }
             res.on_disabled_hover_ui(|ui| {
-                if !is_cache_loaded   { ui.label("Cache is unloaded"); }
-                else if !is_not_busy  { ui.label("Folder is busy"); }
-                else if !is_logged_in { ui.label("Not logged in"); }
+                if !is_cache_loaded        { ui.label("Cache is unloaded"); }
+                else if !is_not_busy       { ui.label("Folder is busy"); }
+                else if !is_provider_ready { ui.label("Not logged in"); }
+                else if let Some(delay) = rate_limit_delay {
+                    ui.label(format!("Rate limited, try again in {}s", delay.as_secs_f64().ceil()));
+                }
+            });
+        });
+
+        ui.add_enabled_ui(is_cache_loaded && is_not_busy && is_provider_ready && is_not_rate_limited, |ui| {
+            let res = ui.button("Refresh seasons on disk");
+            if res.clicked() {
+                if let Some(provider) = provider {
+                    tokio::spawn({
+                        let folder = folder.clone();
+                        let provider = provider.clone();
+                        async move {
+                            folder.refresh_seasons_on_disk_from_api(provider).await?;
+                            tokio::join!(
+                                folder.update_file_intents(),
+                                folder.save_cache_to_file(),
+                            );
+                            Some(())
+                        }
+                    });
+                }
+            }
+            let res = res.on_hover_text("Only fetch the seasons of files in this folder");
+            res.on_disabled_hover_ui(|ui| {
+                if !is_cache_loaded        { ui.label("Cache is unloaded"); }
+                else if !is_not_busy       { ui.label("Folder is busy"); }
+                else if !is_provider_ready { ui.label("Not logged in"); }
+                else if let Some(delay) = rate_limit_delay {
+                    ui.label(format!("Rate limited, try again in {}s", delay.as_secs_f64().ceil()));
+                }
             });
         });
 
         ui.add_enabled_ui(is_not_busy, |ui| {
             let res = ui.button("Execute changes");
+            if res.clicked() {
+                gui.pending_execution_plan = Some(spawn_execution_plan(ui.ctx(), folder));
+            };
+            let res = res.on_hover_ui(|ui| {
+                if gui.is_execute_confirmation_skipped {
+                    ui.label("Confirmation is skipped for this session");
+                }
+                let filter_rules = folder.get_filter_rules();
+                if filter_rules.use_trash {
+                    ui.label("Deleted files are moved to the trash");
+                } else {
+                    ui.label("Deleted files are permanently removed");
+                }
+                if filter_rules.delete_empty_folders {
+                    ui.label("Empty folders are removed afterwards");
+                } else {
+                    ui.label("Empty folders are kept");
+                }
+                if filter_rules.prune_bookmarks_after_execute {
+                    ui.label("Bookmarks of missing files are removed afterwards");
+                }
+            });
+            res.on_disabled_hover_ui(|ui| {
+                if !is_not_busy { ui.label("Folder is busy"); }
+            });
+        });
+
+        let undo_journal = folder.get_undo_journal().blocking_read().clone();
+        ui.add_enabled_ui(undo_journal.is_some() && is_not_busy, |ui| {
+            let res = ui.button("Undo");
             if res.clicked() {
                 let folder = folder.clone();
                 tokio::spawn(async move {
-                    folder.execute_file_changes().await;
-                    folder.update_file_intents().await
+                    folder.undo_last_execution_and_rescan().await
                 });
-            };
+            }
+            let res = res.on_hover_ui(|ui| {
+                if let Some(journal) = undo_journal.as_ref() {
+                    let executed_at = journal.executed_at.format("%Y-%m-%d %H:%M UTC");
+                    ui.label(format!("Revert the {} renames executed at {}", journal.renames.len(), executed_at));
+                    if !journal.deletes.is_empty() {
+                        ui.label(format!("{} deleted files aren't restored", journal.deletes.len()));
+                    }
+                }
+            });
             res.on_disabled_hover_ui(|ui| {
-                if !is_not_busy { ui.label("Folder is busy"); }
+                if undo_journal.is_none() { ui.label("Nothing to undo"); }
+                else if !is_not_busy { ui.label("Folder is busy"); }
+            });
+        });
+
+        ui.add_enabled_ui(is_cache_loaded && is_not_busy, |ui| {
+            let res = ui.button("Organize");
+            if res.clicked() {
+                let auto_skip_conflicts = ui.input(|i| i.modifiers.shift);
+                gui.pending_execution_plan = Some(spawn_organize_plan(ui.ctx(), folder, auto_skip_conflicts));
+            }
+            let res = res.on_hover_ui(|ui| {
+                ui.label("Enable every rename and execute them");
+                ui.label("Deletes are only performed if they are already enabled");
+                ui.label("Hold shift to skip renames that conflict");
+            });
+            res.on_disabled_hover_ui(|ui| {
+                if !is_cache_loaded  { ui.label("Cache is unloaded"); }
+                else if !is_not_busy { ui.label("Folder is busy"); }
             });
         });
 
@@ -117,6 +471,67 @@ fn render_folder_controls(
             });
         }
 
+        ui.add_enabled_ui(is_cache_loaded && is_not_busy, |ui| {
+            let res = ui.button("Prune bookmarks");
+            if res.clicked() {
+                let folder = folder.clone();
+                tokio::spawn(async move {
+                    folder.prune_orphaned_bookmarks().await
+                });
+            }
+            let res = res.on_hover_text("Remove bookmarks of files that no longer exist");
+            res.on_disabled_hover_ui(|ui| {
+                if !is_cache_loaded  { ui.label("Cache is unloaded"); } 
+                else if !is_not_busy { ui.label("Folder is busy"); }
+            });
+        });
+
+        let res = ui.button("Export plan");
+        if res.clicked() {
+            let folder = folder.clone();
+            tokio::spawn(async move {
+                folder.export_plan_to_file().await
+            });
+        }
+        res.on_hover_text(format!("Write the planned actions to {}", folder.get_plan_path()));
+
+        ui.add_enabled_ui(is_not_busy, |ui| {
+            let res = ui.button("Import plan");
+            if res.clicked() {
+                let folder = folder.clone();
+                tokio::spawn(async move {
+                    folder.import_plan_from_file().await
+                });
+            }
+            let res = res.on_hover_text(format!("Read the edited actions from {}", folder.get_plan_path()));
+            res.on_disabled_hover_ui(|ui| {
+                if !is_not_busy { ui.label("Folder is busy"); }
+            });
+        });
+
+        ui.add_enabled_ui(is_not_busy, |ui| {
+            let current_ordering = folder.get_episode_ordering_blocking();
+            let mut selected_ordering = current_ordering;
+            egui::ComboBox::from_id_source("episode_ordering")
+                .selected_text(selected_ordering.to_str())
+                .show_ui(ui, |ui| {
+                    for ordering in EpisodeOrdering::iterator() {
+                        ui.selectable_value(&mut selected_ordering, *ordering, ordering.to_str());
+                    }
+                });
+            if selected_ordering != current_ordering {
+                let folder = folder.clone();
+                tokio::spawn(async move {
+                    folder.set_episode_ordering(selected_ordering).await;
+                    folder.update_file_intents().await
+                });
+            }
+        });
+
+        if !is_not_busy && ui.button("Cancel").clicked() {
+            folder.cancel_current_operation();
+        }
+
         ui.toggle_value(&mut gui.is_show_series_search, "Search series");
         ui.add_enabled_ui(is_cache_loaded, |ui| {
             let res = ui.toggle_value(&mut gui.is_show_episode_cache, "Search episodes");
@@ -125,9 +540,26 @@ fn render_folder_controls(
             });
         });
     });
+
+    if !execution_progress.is_idle() {
+        render_execution_progress_bar(ui, &execution_progress);
+        // Progress updates don't change the busy state so we need to keep refreshing
+        ui.ctx().request_repaint();
+    } else if let Some(summary) = folder.get_last_execution_summary().blocking_read().as_ref() {
+        let label = egui::RichText::new(summary.to_string());
+        let label = match summary.failed {
+            0 => label,
+            _ => label.color(egui::Color32::DARK_RED),
+        };
+        ui.label(label);
+        render_execution_records(ui, folder.get_last_execution_records().blocking_read().as_slice());
+    }
 }
 
-fn render_folder_info(ui: &mut egui::Ui, folder: &Arc<AppFolder>) {
+fn render_folder_info(
+    ui: &mut egui::Ui, provider: Option<&Arc<dyn MetadataProvider>>,
+    gui: &mut GuiAppFolder, folder: &Arc<AppFolder>,
+) {
     render_invisible_width_widget(ui);
 
     let cache = folder.get_cache().blocking_read();
@@ -140,9 +572,37 @@ fn render_folder_info(ui: &mut egui::Ui, folder: &Arc<AppFolder>) {
     };
     
     ui.heading("Series");
+    if let Some(poster) = cache.series.poster.as_deref().filter(|poster| !poster.is_empty()) {
+        render_folder_artwork(ui, &mut gui.artwork, folder, provider, poster);
+    }
     ui.push_id("series_table", |ui| {
         render_series_table(ui, &cache.series);
     });
+    if let Some(summary) = cache.episodes_summary.as_ref() {
+        let on_disk = folder.get_episodes_on_disk_count_blocking();
+        ui.label(format!("You have {} of {} aired episodes", on_disk, summary.aired_episodes));
+    }
+    if let Some(age) = cache.get_age() {
+        let days = age.num_days();
+        ui.label(format!("Metadata {} {} old", days, if days == 1 { "day" } else { "days" }));
+    }
+
+    egui::CollapsingHeader::new("Cast")
+        .default_open(true)
+        .show(ui, |ui| {
+            match cache.actors.as_ref() {
+                Some(actors) => render_actors_table(ui, actors.as_slice()),
+                None => {
+                    tokio::spawn({
+                        let folder = folder.clone();
+                        async move {
+                            folder.load_actors_from_file().await
+                        }
+                    });
+                    ui.label("Cast not in cache, refresh the cache from the api to fetch it");
+                },
+            }
+        });
 
     ui.separator();
 
@@ -156,42 +616,41 @@ fn render_folder_info(ui: &mut egui::Ui, folder: &Arc<AppFolder>) {
         },
     };
 
-    let episode_index = match cache.episode_cache.get(&key) {
-        Some(index) => *index,
+    let episode = match cache.get_episode(&key) {
+        Some(episode) => episode,
         None => {
             ui.label("Episode not in cache");
             return;
         },
     };
-
-    let episode = match cache.episodes.get(episode_index) {
-        Some(episode) => episode,   
-        None => {
-            ui.colored_label(egui::Color32::DARK_RED, "Episode index out of range of episodes list");
-            return;
-        },
-    };
     
+    if let Some(image_filename) = episode.image_filename.as_deref().filter(|filename| !filename.is_empty()) {
+        render_folder_artwork(ui, &mut gui.artwork, folder, provider, image_filename);
+    }
     ui.push_id("episodes_table", |ui| {
         render_episode_table(ui, episode);
     });
 }
 
 pub fn render_app_folder(
-    ui: &mut egui::Ui, session: Option<&Arc<LoginSession>>,
+    ui: &mut egui::Ui, provider: Option<&Arc<dyn MetadataProvider>>,
     gui: &mut GuiAppFolder, folder: &Arc<AppFolder>,
 ) {
     tokio::spawn({
         let folder = folder.clone();
+        let provider = provider.cloned();
         async move {
-            folder.perform_initial_load().await
+            folder.perform_initial_load(provider).await
         }
     });
 
+    check_pending_execution_plan(gui);
+    render_execute_confirmation(ui.ctx(), gui);
+
     egui::TopBottomPanel::top("folder_controls")
         .resizable(false)
         .show_inside(ui, |ui| {
-            render_folder_controls(ui, session, gui, folder);
+            render_folder_controls(ui, provider, gui, folder);
         });
     
     egui::SidePanel::right("folder_info")
@@ -199,7 +658,7 @@ pub fn render_app_folder(
         .show_inside(ui, |ui| {
             ui.push_id("folder_info", |ui| {
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    render_folder_info(ui, folder);
+                    render_folder_info(ui, provider, gui, folder);
                 });
             });
         });
@@ -207,12 +666,16 @@ pub fn render_app_folder(
     egui::CentralPanel::default()
         .frame(egui::Frame::none())
         .show_inside(ui, |ui| {
+            let is_file_errors = folder.get_file_errors().try_read().map(|errors| !errors.is_empty()).unwrap_or(false);
             if let Ok(mut errors) = folder.get_errors().try_write() {
-                if !errors.is_empty() {
+                if !errors.is_empty() || is_file_errors {
                     egui::TopBottomPanel::bottom("folder_error_list")
                         .resizable(true)
                         .show_inside(ui, |ui| {
-                            render_errors_list(ui, errors.as_mut());
+                            egui::ScrollArea::vertical().show(ui, |ui| {
+                                render_file_errors_list(ui, folder);
+                                render_errors(ui, &mut errors);
+                            });
                         });
                 }
             } 
@@ -226,7 +689,7 @@ pub fn render_app_folder(
                     ui.push_id(id, |ui| {
                         egui::ScrollArea::vertical().show(ui, |ui| {
                             if !gui.is_show_episode_cache {
-                                render_files_tab_list(ui, &mut gui.selected_tab, &mut gui.searcher, folder);
+                                render_files_tab_list(ui, &mut gui.files_tab_list, &mut gui.searcher, folder);
                             } else {
                                 render_episode_cache_list(ui, &mut gui.searcher, folder);
                             }
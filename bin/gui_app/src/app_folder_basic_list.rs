@@ -6,17 +6,94 @@ use tokio;
 use crate::fuzzy_search::{FuzzySearcher, render_search_bar};
 use crate::clipped_selectable::ClippedSelectableLabel;
 use crate::app_file_actions::{check_file_shortcuts, render_file_context_menu};
-use crate::app_bookmarks::render_file_bookmarks;
+use crate::app_bookmarks::{GuiBookmarkFilter, render_file_bookmarks, render_bookmark_filter, get_bookmark_filter_counts};
+
+// Changes the action of every file that is shown in the list
+pub struct GuiBulkAction {
+    pub action: Action,
+    // Deleting shown files is confirmed since the search could match more files than intended
+    pub is_confirming_delete: bool,
+}
+
+impl GuiBulkAction {
+    pub fn new() -> Self {
+        Self {
+            action: Action::Delete,
+            is_confirming_delete: false,
+        }
+    }
+}
+
+impl Default for GuiBulkAction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Returns whether the action should be applied to the shown files
+fn render_bulk_action_bar(
+    ui: &mut egui::Ui,
+    bulk_action: &mut GuiBulkAction, selected_action: Action, total_shown: usize, is_not_busy: bool,
+) -> bool {
+    let mut is_apply = false;
+    ui.add_enabled_ui(is_not_busy, |ui| {
+        ui.horizontal(|ui| {
+            let old_action = bulk_action.action;
+            egui::ComboBox::from_id_source("bulk_action")
+                .selected_text(bulk_action.action.to_str())
+                .show_ui(ui, |ui| {
+                    for action in Action::iterator().filter(|action| **action != selected_action) {
+                        ui.selectable_value(&mut bulk_action.action, *action, action.to_str());
+                    }
+                });
+            if bulk_action.action != old_action {
+                bulk_action.is_confirming_delete = false;
+            }
+
+            let is_enabled = total_shown > 0 && bulk_action.action != selected_action;
+            let res = ui.add_enabled(is_enabled, egui::Button::new(format!("Apply to {} shown", total_shown)));
+            if res.clicked() {
+                match bulk_action.action {
+                    Action::Delete => bulk_action.is_confirming_delete = true,
+                    _ => is_apply = true,
+                }
+            }
+            res.on_disabled_hover_ui(|ui| {
+                if !is_not_busy { ui.label("Folder is busy"); }
+                else if total_shown == 0 { ui.label("No files shown"); }
+            });
+        });
+
+        if bulk_action.is_confirming_delete && bulk_action.action == Action::Delete {
+            ui.horizontal(|ui| {
+                let label = format!("Mark {} shown files to be deleted?", total_shown);
+                ui.label(egui::RichText::new(label).color(egui::Color32::DARK_RED));
+                if ui.add_enabled(total_shown > 0, egui::Button::new("Confirm")).clicked() {
+                    is_apply = true;
+                    bulk_action.is_confirming_delete = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    bulk_action.is_confirming_delete = false;
+                }
+            });
+        }
+    });
+    is_apply
+}
 
 pub fn render_files_basic_list(
     ui: &mut egui::Ui, 
-    searcher: &mut FuzzySearcher, selected_action: Action, folder: &Arc<AppFolder>,
+    searcher: &mut FuzzySearcher, bookmark_filter: &mut GuiBookmarkFilter, bulk_action: &mut GuiBulkAction,
+    selected_action: Action, folder: &Arc<AppFolder>,
 ) {
     let file_tracker = folder.get_file_tracker().blocking_read();
     let mut files = folder.get_mut_files_blocking();
     let mut bookmarks = folder.get_bookmarks().blocking_write();
     let mut is_bookmarks_changed = false;
 
+    let bookmark_counts = get_bookmark_filter_counts(&mut files, &bookmarks, selected_action);
+    let all_tags = bookmarks.all_tags();
+    render_bookmark_filter(ui, bookmark_filter, &bookmark_counts, &all_tags);
     render_search_bar(ui, searcher);
 
     if file_tracker.get_action_count()[selected_action] == 0 {
@@ -25,34 +102,68 @@ pub fn render_files_basic_list(
     }
 
     let is_not_busy = folder.get_busy_lock().try_lock().is_ok();
+    let shown_indices: Vec<usize> = file_tracker
+        .get_action_indices(selected_action)
+        .iter()
+        .copied()
+        .filter(|index| match files.get(*index) {
+            Some(file) => {
+                bookmark_filter.is_match(bookmarks.get_for_file(file.get_src(), file.get_src_descriptor().as_ref())) &&
+                searcher.search(file.get_src())
+            },
+            None => false,
+        })
+        .collect();
+
+    if render_bulk_action_bar(ui, bulk_action, selected_action, shown_indices.len(), is_not_busy) {
+        // The same filter as the shown files so files that are hidden are never changed
+        files.set_action_where(|file| {
+            file.get_action() == selected_action &&
+            bookmark_filter.is_match(bookmarks.get_for_file(file.get_src(), file.get_src_descriptor().as_ref())) &&
+            searcher.search(file.get_src())
+        }, bulk_action.action);
+    }
+
     let selected_descriptor = *folder.get_selected_descriptor().blocking_read();
+    let ignored_files = folder.get_ignored_files().try_read().ok();
     egui::ScrollArea::vertical().show(ui, |ui| {
         let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
         ui.with_layout(layout, |ui| {
-            let mut files_iter = files.to_iter();
-            while let Some(mut file) = files_iter.next_mut() {
-                let action = file.get_action();
-                if action != selected_action {
-                    continue;
-                }
-
-                if !searcher.search(file.get_src()) {
-                    continue;
-                }
+            for index in shown_indices.iter().copied() {
+                let mut file = match files.get(index) {
+                    Some(file) => file,
+                    None => continue,
+                };
 
                 ui.horizontal(|ui| {
                     {
+                        // Only insert a bookmark once it is changed so viewing a file doesn't add one
                         let src = file.get_src();
-                        let bookmark = bookmarks.get_mut_with_insert(src);
-                        is_bookmarks_changed = render_file_bookmarks(ui, bookmark) || is_bookmarks_changed;
+                        let descriptor = file.get_src_descriptor().as_ref();
+                        let mut bookmark = bookmarks.get_for_file(src, descriptor).cloned().unwrap_or_default();
+                        if render_file_bookmarks(ui, src, &mut bookmark, &all_tags) {
+                            *bookmarks.get_for_file_mut_with_insert(src, descriptor) = bookmark;
+                            is_bookmarks_changed = true;
+                        }
                     }
                     let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
                     ui.with_layout(layout, |ui| {
                         let src = file.get_src();
                         let descriptor = file.get_src_descriptor();
                         let is_selected = descriptor.is_some() && *descriptor == selected_descriptor;
-                        let elem = ClippedSelectableLabel::new(is_selected, src);
-                        let res = ui.add(elem);
+                        let is_always_ignored = ignored_files
+                            .as_ref()
+                            .map(|ignored_files| ignored_files.contains(src))
+                            .unwrap_or(false);
+                        let mut label = egui::RichText::new(src);
+                        if is_always_ignored {
+                            label = label.italics();
+                        }
+                        let elem = ClippedSelectableLabel::new(is_selected, label);
+                        let mut res = ui.add(elem);
+                        if is_always_ignored {
+                            res = res.on_hover_text("Always ignored");
+                        }
                         if res.clicked() {
                             if is_selected {
                                 *folder.get_selected_descriptor().blocking_write() = None;
@@ -64,7 +175,7 @@ pub fn render_files_basic_list(
                             check_file_shortcuts(ui, &mut file);
                         }
                         res.context_menu(|ui| {
-                            render_file_context_menu(ui, folder.get_folder_path(), &mut file, is_not_busy);
+                            render_file_context_menu(ui, folder, &mut file, is_not_busy);
                         });
                     });
                 });
@@ -1,38 +1,58 @@
 use std::sync::Arc;
 use app::file_intent::Action;
-use app::app_folder::AppFolder;
+use app::app_folder::{AppFolder, ConflictResolution};
 use egui;
 use egui_extras::{TableBuilder, Column};
+use tokio;
 use crate::clipped_selectable::ClippedSelectableLabel;
 use crate::app_file_actions::{check_file_shortcuts, render_file_context_menu};
 
+fn render_conflict_resolution_controls(
+    ui: &mut egui::Ui, 
+    conflict_resolution: &mut ConflictResolution, folder: &Arc<AppFolder>, is_not_busy: bool,
+) {
+    ui.add_enabled_ui(is_not_busy, |ui| {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("conflict_resolution")
+                .selected_text(conflict_resolution.to_str())
+                .show_ui(ui, |ui| {
+                    for resolution in ConflictResolution::iterator() {
+                        ui.selectable_value(conflict_resolution, *resolution, resolution.to_str());
+                    }
+                });
+            if ui.button("Resolve").clicked() {
+                let folder = folder.clone();
+                let conflict_resolution = *conflict_resolution;
+                tokio::spawn(async move {
+                    folder.auto_resolve_conflicts(conflict_resolution).await
+                });
+            }
+        });
+    });
+}
+
 pub fn render_files_conflicts_list(
     ui: &mut egui::Ui, 
-    folder: &Arc<AppFolder>,
+    conflict_resolution: &mut ConflictResolution, folder: &Arc<AppFolder>,
 ) {
     let file_tracker = folder.get_file_tracker().blocking_read();
     let mut files = folder.get_mut_files_blocking(); 
     let is_not_busy = folder.get_busy_lock().try_lock().is_ok();
     let selected_descriptor = *folder.get_selected_descriptor().blocking_read();
+
+    let conflicts = file_tracker.get_conflicts();
+    if !conflicts.is_empty() {
+        render_conflict_resolution_controls(ui, conflict_resolution, folder, is_not_busy);
+        ui.separator();
+    }
     
     // link the column widths across all of the tables
     let mut column_widths: Option<[f32;3]> = None;
     let mut is_add_separator = false;
-    let mut total_conflicts = 0;
-    for (row_id, (dest, indices)) in file_tracker.get_pending_writes().iter().enumerate() {
-        let mut total_files = indices.len();
-        if total_files == 0 {
-            continue;
-        }
-        let source_index = file_tracker.get_source_index(dest.as_str());
-        if source_index.is_some() {
-            total_files += 1;
-        }
-        let is_conflict = total_files > 1;
-        if !is_conflict {
-            continue;
-        }
-        total_conflicts += 1;
+    for (row_id, conflict) in conflicts.iter().enumerate() {
+        let dest = conflict.dest.as_str();
+        let indices = &conflict.writer_indices;
+        let source_index = conflict.source_index;
 
         ui.push_id(row_id, |ui| {
             if is_add_separator {
@@ -103,7 +123,7 @@ pub fn render_files_conflicts_list(
                                     check_file_shortcuts(ui, &mut file);
                                 }
                                 res.context_menu(|ui| {
-                                    render_file_context_menu(ui, folder.get_folder_path(), &mut file, is_not_busy);
+                                    render_file_context_menu(ui, folder, &mut file, is_not_busy);
                                 });
                                 current_column_widths[1] = ui.available_width();
                             });
@@ -127,8 +147,8 @@ pub fn render_files_conflicts_list(
                     };
 
                     if let Some(index) = source_index {
-                        if !indices.contains(index) {
-                            render_entry(*index);
+                        if !indices.contains(&index) {
+                            render_entry(index);
                         }
                     }
 
@@ -139,7 +159,7 @@ pub fn render_files_conflicts_list(
         });
     }
 
-    if total_conflicts == 0 {
+    if conflicts.is_empty() {
         ui.heading("No conflicts");
     }
 }
@@ -1,14 +1,16 @@
 use std::sync::Arc;
+use app::app_file::FileSortMode;
 use app::file_intent::Action;
 use app::app_folder::AppFolder;
 use egui;
 use crate::fuzzy_search::{FuzzySearcher, render_search_bar};
 use crate::clipped_selectable::ClippedSelectableLabel;
 use crate::app_file_actions::{check_file_shortcuts, render_file_context_menu};
+use crate::helpers::{format_bytes, render_file_sort_mode};
 
 pub fn render_files_delete_list(
     ui: &mut egui::Ui, 
-    searcher: &mut FuzzySearcher, folder: &Arc<AppFolder>,
+    searcher: &mut FuzzySearcher, sort_mode: &mut FileSortMode, folder: &Arc<AppFolder>,
 ) {
     let file_tracker = folder.get_file_tracker().blocking_read();
     let is_not_busy = folder.get_busy_lock().try_lock().is_ok();
@@ -22,10 +24,21 @@ pub fn render_files_delete_list(
             is_deselect_all = ui.button("Deselect all").clicked();
         });
     });
+    ui.horizontal(|ui| {
+        render_file_sort_mode(ui, sort_mode);
+    });
 
     render_search_bar(ui, searcher);
 
     let mut files = folder.get_mut_files_blocking(); 
+    // Only the files that are listed are selected
+    if is_select_all || is_deselect_all {
+        files.set_enabled_where(|file| {
+            file.get_action() == Action::Delete &&
+            searcher.search(file.get_src())
+        }, is_select_all);
+    }
+
     if file_tracker.get_action_count()[Action::Delete] == 0 {
         ui.heading(format!("No {}s", Action::Delete.to_str().to_lowercase()));
         return;
@@ -34,13 +47,11 @@ pub fn render_files_delete_list(
     egui::ScrollArea::vertical().show(ui, |ui| {
         let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
         ui.with_layout(layout, |ui| {
-            let mut files_iter = files.to_iter();
-            while let Some(mut file) = files_iter.next_mut() {
-                let action = file.get_action();
-                if action != Action::Delete {
-                    continue;
-                }
-
+            for index in files.get_sorted_action_indices(Action::Delete, *sort_mode) {
+                let mut file = match files.get(index) {
+                    Some(file) => file,
+                    None => continue,
+                };
                 if !searcher.search(file.get_src()) {
                     continue;
                 }
@@ -52,20 +63,23 @@ pub fn render_files_delete_list(
                             file.set_is_enabled(is_enabled);
                         }
                     });
-                    if is_select_all {
-                        file.set_is_enabled(true);
-                    }
-                    if is_deselect_all {
-                        file.set_is_enabled(false);
-                    }
+                    ui.weak(format_bytes(file.get_size()));
 
                     let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
                     ui.with_layout(layout, |ui| {
                         let src = file.get_src();
                         let descriptor = file.get_src_descriptor();
                         let is_selected = descriptor.is_some() && *descriptor == selected_descriptor;
-                        let elem = ClippedSelectableLabel::new(is_selected, src);
-                        let res = ui.add(elem);
+                        let last_error = file.get_last_error().map(|error| error.to_string());
+                        let label = match last_error {
+                            Some(_) => egui::RichText::new(format!("⚠ {}", src)).color(egui::Color32::DARK_RED),
+                            None => egui::RichText::new(src),
+                        };
+                        let elem = ClippedSelectableLabel::new(is_selected, label);
+                        let mut res = ui.add(elem);
+                        if let Some(error) = last_error {
+                            res = res.on_hover_text(error);
+                        }
                         if res.clicked() {
                             if is_selected {
                                 *folder.get_selected_descriptor().blocking_write() = None;
@@ -77,7 +91,7 @@ pub fn render_files_delete_list(
                             check_file_shortcuts(ui, &mut file);
                         }
                         res.context_menu(|ui| {
-                            render_file_context_menu(ui, folder.get_folder_path(), &mut file, is_not_busy);
+                            render_file_context_menu(ui, folder, &mut file, is_not_busy);
                         });
                     });
 
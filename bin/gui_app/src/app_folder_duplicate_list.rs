@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use app::file_descriptor::{find_resolution, find_tags};
+use app::file_intent::Action;
+use app::app_folder::{AppFolder, DuplicateResolution};
+use egui;
+use tokio;
+use crate::clipped_selectable::ClippedSelectableLabel;
+use crate::app_file_actions::{check_file_shortcuts, render_file_context_menu};
+use crate::helpers::{format_bytes, format_elapsed};
+
+fn render_duplicate_resolution_controls(
+    ui: &mut egui::Ui,
+    duplicate_resolution: &mut DuplicateResolution, folder: &Arc<AppFolder>, is_not_busy: bool,
+) {
+    ui.add_enabled_ui(is_not_busy, |ui| {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("duplicate_resolution")
+                .selected_text(duplicate_resolution.to_str())
+                .show_ui(ui, |ui| {
+                    for resolution in DuplicateResolution::iterator() {
+                        ui.selectable_value(duplicate_resolution, *resolution, resolution.to_str());
+                    }
+                });
+            let res = ui.button("Resolve all");
+            let res = res.on_hover_text("Mark every other file of each episode to be deleted");
+            if res.clicked() {
+                let folder = folder.clone();
+                let duplicate_resolution = *duplicate_resolution;
+                tokio::spawn(async move {
+                    folder.resolve_duplicates(duplicate_resolution, None).await
+                });
+            }
+        });
+    });
+}
+
+pub fn render_files_duplicates_list(
+    ui: &mut egui::Ui,
+    duplicate_resolution: &mut DuplicateResolution, folder: &Arc<AppFolder>,
+) {
+    let groups = folder.get_duplicate_groups().blocking_read();
+    let mut files = folder.get_mut_files_blocking();
+    let is_not_busy = folder.get_busy_lock().try_lock().is_ok();
+    let selected_descriptor = *folder.get_selected_descriptor().blocking_read();
+
+    if groups.is_empty() {
+        ui.heading("No duplicates");
+        return;
+    }
+
+    render_duplicate_resolution_controls(ui, duplicate_resolution, folder, is_not_busy);
+    ui.separator();
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
+        ui.with_layout(layout, |ui| {
+            for (row_id, group) in groups.iter().enumerate() {
+                ui.push_id(row_id, |ui| {
+                    if row_id > 0 {
+                        ui.separator();
+                    }
+
+                    ui.horizontal(|ui| {
+                        let label = format!("S{:02}E{:02}", group.key.season, group.key.episode);
+                        ui.label(egui::RichText::new(label).strong().size(13.0));
+                        ui.add_enabled_ui(is_not_busy, |ui| {
+                            if ui.small_button(duplicate_resolution.to_str()).clicked() {
+                                let folder = folder.clone();
+                                let duplicate_resolution = *duplicate_resolution;
+                                let key = group.key;
+                                tokio::spawn(async move {
+                                    folder.resolve_duplicates(duplicate_resolution, Some(key)).await
+                                });
+                            }
+                        });
+                    });
+
+                    for index in group.indices.iter().copied() {
+                        let mut file = match files.get(index) {
+                            Some(file) => file,
+                            None => continue,
+                        };
+                        let action = file.get_action();
+                        ui.horizontal(|ui| {
+                            ui.add_enabled_ui(is_not_busy && (action == Action::Rename || action == Action::Delete), |ui| {
+                                let mut is_enabled = file.get_is_enabled();
+                                if ui.checkbox(&mut is_enabled, "").clicked() {
+                                    file.set_is_enabled(is_enabled);
+                                }
+                            });
+                            ui.weak(action.to_str());
+                            ui.weak(format_bytes(file.get_size()));
+                            if let Some(modified) = file.get_modified() {
+                                ui.weak(format_elapsed(modified));
+                            }
+                            let mut tags = find_tags(file.get_src());
+                            if let Some(resolution) = find_resolution(file.get_src()) {
+                                if !tags.iter().any(|tag| tag.eq_ignore_ascii_case(resolution.as_str())) {
+                                    tags.insert(0, resolution);
+                                }
+                            }
+                            for tag in tags {
+                                ui.label(egui::RichText::new(tag).small());
+                            }
+
+                            let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
+                            ui.with_layout(layout, |ui| {
+                                let descriptor = file.get_src_descriptor();
+                                let is_selected = descriptor.is_some() && *descriptor == selected_descriptor;
+                                let elem = ClippedSelectableLabel::new(is_selected, file.get_src());
+                                let res = ui.add(elem);
+                                if res.clicked() {
+                                    if is_selected {
+                                        *folder.get_selected_descriptor().blocking_write() = None;
+                                    } else {
+                                        *folder.get_selected_descriptor().blocking_write() = *descriptor;
+                                    }
+                                }
+                                if is_not_busy && res.hovered() {
+                                    check_file_shortcuts(ui, &mut file);
+                                }
+                                res.context_menu(|ui| {
+                                    render_file_context_menu(ui, folder, &mut file, is_not_busy);
+                                });
+                            });
+                        });
+                    }
+                });
+            }
+        });
+    });
+}
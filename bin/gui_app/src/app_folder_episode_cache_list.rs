@@ -1,78 +1,137 @@
 use app::app_folder::AppFolder;
-use app::tvdb_cache::EpisodeKey;
+use app::tvdb_cache::{EpisodeKey, TvdbCache};
 use egui;
 use egui_extras::{Column, TableBuilder};
 use std::sync::Arc;
+use tokio;
+use crate::app_bookmarks::render_file_bookmarks;
 use crate::fuzzy_search::{FuzzySearcher, render_search_bar};
 use crate::clipped_selectable::ClippedSelectableLabel;
 
-pub fn render_episode_cache_list(ui: &mut egui::Ui, searcher: &mut FuzzySearcher, folder: &Arc<AppFolder>) {
-    render_search_bar(ui, searcher);
-
-    let cache = folder.get_cache().blocking_read();
-    let cache = match cache.as_ref() {
-        Some(cache) => cache,
-        None => {
-            ui.label("No cache loaded");
-            return;
-        },
-    };
-
-    let episodes = &cache.episodes;
-    if episodes.is_empty() {
-        ui.label("No episodes available");
-        return;
-    }
-    
+// Episodes matching the search grouped by season
+// The first column is filled by the caller and the clicked episode is returned
+pub fn render_episode_search_table(
+    ui: &mut egui::Ui, searcher: &mut FuzzySearcher, cache: &TvdbCache, selected_descriptor: Option<EpisodeKey>,
+    mut render_first_column: impl FnMut(&mut egui::Ui, EpisodeKey, &str),
+) -> Option<EpisodeKey> {
     // Create a string that we can search for each episode
     let mut episode_name = String::new();
-    let selected_descriptor = *folder.get_selected_descriptor().blocking_read();
+    let mut clicked_descriptor = None;
     let row_height = 18.0;
     let cell_layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
     TableBuilder::new(ui)
         .striped(true)
         .resizable(true)
         .cell_layout(cell_layout)
+        .column(Column::auto().resizable(false))
         .column(Column::remainder().resizable(true).clip(true))
         .column(Column::auto().resizable(false))
         .header(row_height, |mut header| {
+            header.col(|_| {});
             header.col(|ui| { ui.strong("Name"); });
             header.col(|ui| { ui.strong("First Aired"); });
         })
         .body(|mut body| {
-            for entry in episodes {
-                use std::fmt::Write;
-                episode_name.clear();
-                let _ = write!(episode_name, "S{:02}E{:02}", entry.season, entry.episode);
-                if let Some(name) = entry.name.as_deref() {
-                    let _ = write!(episode_name, " {}", name);
-                }
-                if !searcher.search(episode_name.as_str()) {
-                    continue;
-                }
+            for season in cache.get_seasons() {
+                // Seasons without any search results don't get a header
+                let mut is_header_shown = false;
+                for entry in cache.get_season_episodes(season) {
+                    use std::fmt::Write;
+                    episode_name.clear();
+                    // Follow the folder's ordering so the same descriptor refers to the same episode everywhere
+                    let descriptor = cache.get_episode_key(entry);
+                    let _ = write!(episode_name, "S{:02}E{:02}", descriptor.season, descriptor.episode);
+                    if let Some(name) = entry.name.as_deref() {
+                        let _ = write!(episode_name, " {}", name);
+                    }
+                    if !searcher.search(episode_name.as_str()) {
+                        continue;
+                    }
 
-                body.row(row_height, |mut row| {
-                    row.col(|ui| { 
-                        let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
-                        ui.with_layout(layout, |ui| {
-                            let descriptor = EpisodeKey { season: entry.season, episode: entry.episode };
-                            let is_selected = Some(descriptor) == selected_descriptor;
-                            let elem = ClippedSelectableLabel::new(is_selected, episode_name.as_str());
-                            let res = ui.add(elem);
-                            if res.clicked() {
-                                if is_selected {
-                                    *folder.get_selected_descriptor().blocking_write() = None;
-                                } else {
-                                    *folder.get_selected_descriptor().blocking_write() = Some(descriptor);
+                    if !is_header_shown {
+                        is_header_shown = true;
+                        body.row(row_height, |mut row| {
+                            row.col(|_| {});
+                            row.col(|ui| {
+                                match season {
+                                    0 => ui.strong("Specials"),
+                                    season => ui.strong(format!("Season {}", season)),
+                                };
+                            });
+                            row.col(|_| {});
+                        });
+                    }
+
+                    body.row(row_height, |mut row| {
+                        row.col(|ui| {
+                            render_first_column(ui, descriptor, episode_name.as_str());
+                        });
+                        row.col(|ui| { 
+                            let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
+                            ui.with_layout(layout, |ui| {
+                                let is_selected = Some(descriptor) == selected_descriptor;
+                                let elem = ClippedSelectableLabel::new(is_selected, episode_name.as_str());
+                                if ui.add(elem).clicked() {
+                                    clicked_descriptor = Some(descriptor);
                                 }
-                            }
+                            });
+                        });
+                        row.col(|ui| {
+                            let label = entry.first_aired.as_deref().unwrap_or("Unknown");
+                            ui.label(label);
                         });
                     });
-                    row.col(|ui| {
-                        let label = entry.first_aired.as_deref().unwrap_or("Unknown");
-                        ui.label(label);
-                    });
-                });
+                }
             }
         });
+    clicked_descriptor
+}
+
+pub fn render_episode_cache_list(ui: &mut egui::Ui, searcher: &mut FuzzySearcher, folder: &Arc<AppFolder>) {
+    render_search_bar(ui, searcher);
+
+    let cache = folder.get_cache().blocking_read();
+    let cache = match cache.as_ref() {
+        Some(cache) => cache,
+        None => {
+            ui.label("No cache loaded");
+            return;
+        },
+    };
+
+    if cache.episodes.is_empty() {
+        ui.label("No episodes available");
+        return;
+    }
+    
+    // Episodes can be bookmarked even if there is no file for them
+    let mut bookmarks = folder.get_bookmarks().blocking_write();
+    let all_tags = bookmarks.all_tags();
+    let mut is_bookmarks_changed = false;
+
+    let selected_descriptor = *folder.get_selected_descriptor().blocking_read();
+    let clicked_descriptor = render_episode_search_table(ui, searcher, cache, selected_descriptor, |ui, descriptor, episode_name| {
+        let mut bookmark = bookmarks.get_episode(&descriptor).cloned().unwrap_or_default();
+        if render_file_bookmarks(ui, episode_name, &mut bookmark, &all_tags) {
+            *bookmarks.get_episode_mut_with_insert(&descriptor) = bookmark;
+            is_bookmarks_changed = true;
+        }
+    });
+    if let Some(descriptor) = clicked_descriptor {
+        let mut selected_descriptor = folder.get_selected_descriptor().blocking_write();
+        if *selected_descriptor == Some(descriptor) {
+            *selected_descriptor = None;
+        } else {
+            *selected_descriptor = Some(descriptor);
+        }
+    }
+
+    if is_bookmarks_changed {
+        tokio::spawn({
+            let folder = folder.clone();
+            async move {
+                folder.save_bookmarks_to_file().await
+            }
+        });
+    }
 }
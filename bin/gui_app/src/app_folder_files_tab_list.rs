@@ -1,49 +1,82 @@
-use app::app_folder::AppFolder;
+use app::app_folder::{AppFolder, ConflictResolution, DuplicateResolution};
+use app::app_file::FileSortMode;
 use app::file_intent::Action;
+use app::tvdb_cache::MissingEpisodeFilter;
 use std::sync::Arc;
 
-use crate::app_folder_basic_list::render_files_basic_list;
+use crate::app_bookmarks::GuiBookmarkFilter;
+use crate::app_episode_picker::{GuiEpisodePicker, render_episode_picker};
+use crate::app_folder_basic_list::{GuiBulkAction, render_files_basic_list};
 use crate::app_folder_conflict_list::render_files_conflicts_list;
 use crate::app_folder_delete_list::render_files_delete_list;
+use crate::app_folder_duplicate_list::render_files_duplicates_list;
+use crate::app_folder_missing_list::render_missing_episodes_list;
 use crate::app_folder_rename_list::render_files_rename_list;
 use crate::fuzzy_search::FuzzySearcher;
+use crate::helpers::format_bytes;
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum FileTab {
     FileAction(Action),
     Conflicts,
+    Duplicates,
+    Missing,
+}
+
+// Remembered separately for each folder
+pub struct GuiFilesTabList {
+    pub selected_tab: FileTab,
+    pub bookmark_filter: GuiBookmarkFilter,
+    pub sort_modes: enum_map::EnumMap<Action, FileSortMode>,
+    pub conflict_resolution: ConflictResolution,
+    pub duplicate_resolution: DuplicateResolution,
+    pub missing_filter: MissingEpisodeFilter,
+    pub bulk_action: GuiBulkAction,
+    pub episode_picker: GuiEpisodePicker,
+}
+
+impl GuiFilesTabList {
+    pub fn new() -> Self {
+        Self {
+            selected_tab: FileTab::FileAction(Action::Complete),
+            bookmark_filter: GuiBookmarkFilter::default(),
+            sort_modes: enum_map::EnumMap::default(),
+            conflict_resolution: ConflictResolution::SuffixCounter,
+            duplicate_resolution: DuplicateResolution::KeepLargest,
+            missing_filter: MissingEpisodeFilter::default(),
+            bulk_action: GuiBulkAction::new(),
+            episode_picker: GuiEpisodePicker::new(),
+        }
+    }
+}
+
+impl Default for GuiFilesTabList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 lazy_static::lazy_static! {
-    static ref FILE_TABS: [FileTab;6] = [
+    static ref FILE_TABS: [FileTab;8] = [
         FileTab::FileAction(Action::Complete), 
         FileTab::FileAction(Action::Rename), 
         FileTab::FileAction(Action::Delete), 
         FileTab::FileAction(Action::Ignore), 
         FileTab::FileAction(Action::Whitelist), 
-        FileTab::Conflicts
+        FileTab::Conflicts,
+        FileTab::Duplicates,
+        FileTab::Missing,
     ];
 }
 
-fn render_files_tab_bar(ui: &mut egui::Ui, selected_tab: &mut FileTab, folder: &Arc<AppFolder>) {
+fn render_files_tab_bar(
+    ui: &mut egui::Ui, 
+    selected_tab: &mut FileTab, missing_filter: &MissingEpisodeFilter, folder: &Arc<AppFolder>,
+) {
+    let total_missing = folder.get_missing_episodes_blocking(*missing_filter).len();
     let file_tracker = folder.get_file_tracker().blocking_read();
-    let total_conflicts = {
-        let mut total_conflicts = 0;
-        for (dest, indices) in file_tracker.get_pending_writes() {
-            let mut total_files = indices.len();
-            if total_files == 0 {
-                continue;
-            }
-            if file_tracker.get_source_index(dest.as_str()).is_some() {
-                total_files += 1;
-            }
-            let is_conflict = total_files > 1;
-            if is_conflict {
-                total_conflicts += 1;
-            }
-        }
-        total_conflicts
-    };
+    let total_conflicts = file_tracker.get_conflict_count();
+    let total_duplicates = folder.get_duplicate_groups().blocking_read().len();
 
     ui.horizontal(|ui| {
         let old_selected_tab = *selected_tab;
@@ -51,9 +84,17 @@ fn render_files_tab_bar(ui: &mut egui::Ui, selected_tab: &mut FileTab, folder: &
             let tab = *tab;
             let label = match tab {
                 FileTab::Conflicts => format!("Conflicts {}", total_conflicts),
+                FileTab::Duplicates => format!("Duplicates {}", total_duplicates),
+                FileTab::Missing => format!("Missing {}", total_missing),
                 FileTab::FileAction(action) => {
                     let count = file_tracker.get_action_count()[action];
-                    format!("{} {}", action.to_str(), count)
+                    match action {
+                        Action::Delete | Action::Rename => {
+                            let total_bytes = file_tracker.get_action_bytes()[action];
+                            format!("{} {} ({})", action.to_str(), count, format_bytes(total_bytes))
+                        },
+                        _ => format!("{} {}", action.to_str(), count),
+                    }
                 },
             };
 
@@ -67,30 +108,44 @@ fn render_files_tab_bar(ui: &mut egui::Ui, selected_tab: &mut FileTab, folder: &
 
 pub fn render_files_tab_list(
     ui: &mut egui::Ui,
-    selected_tab: &mut FileTab, searcher: &mut FuzzySearcher, folder: &Arc<AppFolder>,
+    gui: &mut GuiFilesTabList, searcher: &mut FuzzySearcher, folder: &Arc<AppFolder>,
 ) {
-    render_files_tab_bar(ui, selected_tab, folder);
+    let GuiFilesTabList { 
+        selected_tab, bookmark_filter, sort_modes, conflict_resolution, duplicate_resolution, missing_filter, bulk_action,
+        episode_picker,
+    } = gui;
+    let old_selected_tab = *selected_tab;
+    render_files_tab_bar(ui, selected_tab, missing_filter, folder);
+    // The confirmation was for the files shown in the previous tab
+    if *selected_tab != old_selected_tab {
+        bulk_action.is_confirming_delete = false;
+    }
     ui.separator();
     
     let id = match selected_tab {
         FileTab::FileAction(action) => format!("file_list_{}", action.to_str().to_lowercase()),
         FileTab::Conflicts => "file_list_conflicts".to_string(),
+        FileTab::Duplicates => "file_list_duplicates".to_string(),
+        FileTab::Missing => "file_list_missing".to_string(),
     };
     
     ui.push_id(id, |ui| {
         match selected_tab {
             FileTab::FileAction(action) => match action {
-                Action::Rename => render_files_rename_list(ui, searcher, folder),
-                Action::Delete => render_files_delete_list(ui, searcher, folder),
-                _ => render_files_basic_list(ui, searcher, *action, folder),
+                Action::Rename => render_files_rename_list(ui, searcher, &mut sort_modes[Action::Rename], bookmark_filter, folder),
+                Action::Delete => render_files_delete_list(ui, searcher, &mut sort_modes[Action::Delete], folder),
+                _ => render_files_basic_list(ui, searcher, bookmark_filter, bulk_action, *action, folder),
             },
             FileTab::Conflicts => {
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    render_files_conflicts_list(ui, folder);
+                    render_files_conflicts_list(ui, conflict_resolution, folder);
                 });
             },
+            FileTab::Duplicates => render_files_duplicates_list(ui, duplicate_resolution, folder),
+            FileTab::Missing => render_missing_episodes_list(ui, missing_filter, folder),
         };
     });
 
     folder.flush_file_changes_blocking();
+    render_episode_picker(ui.ctx(), episode_picker, folder);
 }
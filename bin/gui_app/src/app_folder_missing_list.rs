@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use app::app_folder::AppFolder;
+use app::tvdb_cache::MissingEpisodeFilter;
+use egui;
+use egui_extras::{TableBuilder, Column};
+use crate::clipped_selectable::ClippedSelectableLabel;
+
+pub fn render_missing_episodes_list(
+    ui: &mut egui::Ui,
+    filter: &mut MissingEpisodeFilter, folder: &Arc<AppFolder>,
+) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut filter.is_exclude_specials, "Exclude specials");
+        ui.checkbox(&mut filter.is_exclude_unaired, "Exclude unaired");
+    });
+
+    let missing_episodes = folder.get_missing_episodes_blocking(*filter);
+    let cache = folder.get_cache().blocking_read();
+    let cache = match cache.as_ref() {
+        Some(cache) => cache,
+        None => {
+            ui.heading("No cache loaded");
+            return;
+        },
+    };
+
+    if missing_episodes.is_empty() {
+        ui.heading("No missing episodes");
+        return;
+    }
+
+    let selected_descriptor = *folder.get_selected_descriptor().blocking_read();
+    let row_height = 18.0;
+    let cell_layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
+    TableBuilder::new(ui)
+        .striped(true)
+        .resizable(true)
+        .cell_layout(cell_layout)
+        .column(Column::auto().resizable(false))
+        .column(Column::remainder().resizable(true).clip(true))
+        .column(Column::auto().resizable(false))
+        .header(row_height, |mut header| {
+            header.col(|ui| { ui.strong("Episode"); });
+            header.col(|ui| { ui.strong("Name"); });
+            header.col(|ui| { ui.strong("First Aired"); });
+        })
+        .body(|mut body| {
+            for descriptor in missing_episodes {
+                let episode = match cache.get_episode(&descriptor) {
+                    Some(episode) => episode,
+                    None => continue,
+                };
+                body.row(row_height, |mut row| {
+                    row.col(|ui| {
+                        let is_selected = Some(descriptor) == selected_descriptor;
+                        let label = format!("S{:02}E{:02}", descriptor.season, descriptor.episode);
+                        let res = ui.add(ClippedSelectableLabel::new(is_selected, label));
+                        if res.clicked() {
+                            if is_selected {
+                                *folder.get_selected_descriptor().blocking_write() = None;
+                            } else {
+                                *folder.get_selected_descriptor().blocking_write() = Some(descriptor);
+                            }
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.label(episode.name.as_deref().unwrap_or(""));
+                    });
+                    row.col(|ui| {
+                        ui.label(episode.first_aired.as_deref().unwrap_or("Unknown"));
+                    });
+                });
+            }
+        });
+}
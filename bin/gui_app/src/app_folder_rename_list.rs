@@ -1,15 +1,39 @@
 use std::sync::Arc;
+use app::app_file::FileSortMode;
 use app::file_intent::Action;
 use app::app_folder::AppFolder;
+use app::rename_diff::diff_segments;
 use egui;
 use egui_extras::{TableBuilder, Column};
 use crate::fuzzy_search::{FuzzySearcher, render_search_bar};
 use crate::clipped_selectable::ClippedSelectableLabel;
 use crate::app_file_actions::{check_file_shortcuts, render_file_context_menu};
+use crate::app_bookmarks::{GuiBookmarkFilter, render_bookmark_filter, get_bookmark_filter_counts};
+use crate::helpers::{format_bytes, render_file_sort_mode};
+
+const CHANGED_BACKGROUND: egui::Color32 = egui::Color32::from_rgba_premultiplied(0, 80, 0, 80);
+
+// Parts of the destination that differ from the source are highlighted so small changes stand out
+fn get_dest_layout_job(ui: &egui::Ui, src: &str, dest: &str, is_error: bool) -> egui::text::LayoutJob {
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let color = match is_error {
+        true => egui::Color32::DARK_RED,
+        false => ui.visuals().text_color(),
+    };
+    let mut job = egui::text::LayoutJob::default();
+    for (segment, is_changed) in diff_segments(src, dest) {
+        let background = match is_changed {
+            true => CHANGED_BACKGROUND,
+            false => egui::Color32::TRANSPARENT,
+        };
+        job.append(segment, 0.0, egui::TextFormat { font_id: font_id.clone(), color, background, ..Default::default() });
+    }
+    job
+}
 
 pub fn render_files_rename_list(
     ui: &mut egui::Ui, 
-    searcher: &mut FuzzySearcher, folder: &Arc<AppFolder>,
+    searcher: &mut FuzzySearcher, sort_mode: &mut FileSortMode, bookmark_filter: &mut GuiBookmarkFilter, folder: &Arc<AppFolder>,
 ) {
     let file_tracker = folder.get_file_tracker().blocking_read();
     let is_not_busy = folder.get_busy_lock().try_lock().is_ok();
@@ -23,15 +47,35 @@ pub fn render_files_rename_list(
             is_deselect_all = ui.button("Deselect all").clicked();
         });
     });
+    ui.horizontal(|ui| {
+        render_file_sort_mode(ui, sort_mode);
+    });
 
+    let mut files = folder.get_mut_files_blocking(); 
+    let bookmarks = folder.get_bookmarks().blocking_read();
+    let bookmark_counts = get_bookmark_filter_counts(&mut files, &bookmarks, Action::Rename);
+    render_bookmark_filter(ui, bookmark_filter, &bookmark_counts, &bookmarks.all_tags());
     render_search_bar(ui, searcher);
 
-    let mut files = folder.get_mut_files_blocking(); 
+    // Only the files that are listed are selected
+    if is_select_all || is_deselect_all {
+        files.set_enabled_where(|file| {
+            file.get_action() == Action::Rename &&
+            bookmark_filter.is_match(bookmarks.get_for_file(file.get_src(), file.get_src_descriptor().as_ref())) &&
+            searcher.search(file.get_src())
+        }, is_select_all);
+    }
+
     if file_tracker.get_action_count()[Action::Rename] == 0 {
         ui.heading("No renames");
         return;
     }
    
+    // The destination being edited and whether its text edit has been focused yet
+    // Editing stops once the text edit loses focus
+    let editing_id = ui.id().with("editing_dest");
+    let mut editing_dest: Option<(usize, bool)> = ui.data(|data| data.get_temp(editing_id)).flatten();
+
     let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
     ui.with_layout(layout, |ui| {
         let cell_layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
@@ -41,18 +85,22 @@ pub fn render_files_rename_list(
             .resizable(true)
             .cell_layout(cell_layout)
             .column(Column::initial(0.0).resizable(false).clip(false))
+            .column(Column::auto().resizable(false))
             .column(Column::auto().resizable(true).clip(true))
             .column(Column::remainder().resizable(false).clip(true))
             .header(row_height, |mut header| {
                 header.col(|_| {});
+                header.col(|ui| { ui.strong("Size"); });
                 header.col(|ui| { ui.strong("Source"); });
                 header.col(|ui| { ui.strong("Destination"); });
             })
             .body(|mut body| {
-                let mut files_iter = files.to_iter();
-                while let Some(mut file) = files_iter.next_mut() {
-                    let action = file.get_action();
-                    if action != Action::Rename {
+                for index in files.get_sorted_action_indices(Action::Rename, *sort_mode) {
+                    let mut file = match files.get(index) {
+                        Some(file) => file,
+                        None => continue,
+                    };
+                    if !bookmark_filter.is_match(bookmarks.get_for_file(file.get_src(), file.get_src_descriptor().as_ref())) {
                         continue;
                     }
 
@@ -60,13 +108,6 @@ pub fn render_files_rename_list(
                         continue;
                     }
 
-                    if is_select_all {
-                        file.set_is_enabled(true);
-                    }
-                    if is_deselect_all {
-                        file.set_is_enabled(false);
-                    }
-
                     body.row(row_height, |mut row| {
                         row.col(|ui| {
                             ui.add_enabled_ui(is_not_busy, |ui| {
@@ -76,17 +117,27 @@ pub fn render_files_rename_list(
                                 }
                             });
                         });
+                        row.col(|ui| {
+                            ui.label(format_bytes(file.get_size()));
+                        });
                         row.col(|ui| {
                             let descriptor = file.get_src_descriptor();
                             let is_selected = descriptor.is_some() && *descriptor == selected_descriptor;
                             let is_conflict = file.get_is_conflict();
                             let src = file.get_src();
-                            let mut label = egui::RichText::new(src);
-                            if is_conflict {
+                            let last_error = file.get_last_error().map(|error| error.to_string());
+                            let mut label = match last_error {
+                                Some(_) => egui::RichText::new(format!("⚠ {}", src)),
+                                None => egui::RichText::new(src),
+                            };
+                            if is_conflict || last_error.is_some() {
                                 label = label.color(egui::Color32::DARK_RED)
                             }
                             let elem = ClippedSelectableLabel::new(is_selected, label);
-                            let res = ui.add(elem);
+                            let mut res = ui.add(elem);
+                            if let Some(error) = last_error {
+                                res = res.on_hover_text(error);
+                            }
                             if res.clicked() {
                                 if is_selected {
                                     *folder.get_selected_descriptor().blocking_write() = None;
@@ -98,16 +149,42 @@ pub fn render_files_rename_list(
                                 check_file_shortcuts(ui, &mut file);
                             }
                             res.context_menu(|ui| {
-                                render_file_context_menu(ui, folder.get_folder_path(), &mut file, is_not_busy);
+                                render_file_context_menu(ui, folder, &mut file, is_not_busy);
                             });
                         });
                         row.col(|ui| {
                             ui.add_enabled_ui(is_not_busy, |ui| {
-                                let mut dest_edit_buffer = file.get_dest().to_string();
-                                let elem = egui::TextEdit::singleline(&mut dest_edit_buffer);
-                                let res = ui.add_sized(ui.available_size(), elem);
-                                if res.changed() {
-                                    file.set_dest(dest_edit_buffer);
+                                let dest_error = file.get_dest_error().map(|error| error.to_string());
+                                let res = match editing_dest {
+                                    Some((editing_index, is_focused)) if editing_index == index => {
+                                        let mut dest_edit_buffer = file.get_dest().to_string();
+                                        let mut elem = egui::TextEdit::singleline(&mut dest_edit_buffer);
+                                        if dest_error.is_some() {
+                                            elem = elem.text_color(egui::Color32::DARK_RED);
+                                        }
+                                        let res = ui.add_sized(ui.available_size(), elem);
+                                        if res.changed() {
+                                            file.set_dest(dest_edit_buffer);
+                                        }
+                                        match (is_focused, res.has_focus()) {
+                                            (false, false) => res.request_focus(),
+                                            (false, true) => editing_dest = Some((index, true)),
+                                            (true, false) => editing_dest = None,
+                                            (true, true) => {},
+                                        }
+                                        res
+                                    },
+                                    _ => {
+                                        let job = get_dest_layout_job(ui, file.get_src(), file.get_dest(), dest_error.is_some());
+                                        let res = ui.add(ClippedSelectableLabel::new(false, job));
+                                        if res.clicked() {
+                                            editing_dest = Some((index, false));
+                                        }
+                                        res
+                                    },
+                                };
+                                if let Some(error) = dest_error {
+                                    res.on_hover_text(format!("⚠ {}", error));
                                 }
                             });
                         });
@@ -116,4 +193,5 @@ pub fn render_files_rename_list(
                 }
             });
     });
+    ui.data_mut(|data| data.insert_temp(editing_id, editing_dest));
 }
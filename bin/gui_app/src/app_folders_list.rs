@@ -1,12 +1,22 @@
-use app::app::App;
-use app::app_folder::FolderStatus;
+use app::app::{App, LoginState, ScanProgress};
+use app::app_folder::{AppFolder, FolderSortMode, FolderStatus};
+use app::change_event::{ChangeEvent, ChangeKind};
+use app::folder_list::{FolderNavigation, get_listed_folder_indices, get_navigation_index, get_sorted_folder_indices};
+use app::file_intent::Action;
+use app::metadata_provider::MetadataProvider;
 use egui;
 use enum_map;
 use open as cross_open;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use rfd;
 use tokio;
+use tokio::sync::broadcast;
 use crate::fuzzy_search::{FuzzySearcher, render_search_bar};
 use crate::clipped_selectable::ClippedSelectableLabel;
+use crate::helpers::format_bytes;
+use crate::login_form::{GuiLoginForm, render_login_form};
 
 lazy_static::lazy_static! {
     static ref FOLDER_STATUS_ICONS: enum_map::EnumMap<FolderStatus, egui::RichText> = enum_map::enum_map! {
@@ -17,9 +27,57 @@ lazy_static::lazy_static! {
     };
 }
 
+struct PendingChanges {
+    total_folders: usize,
+    total_renames: usize,
+    total_deletes: usize,
+}
+
+// Everything other than the folders themselves that decides which folders are listed
+#[derive(Clone, PartialEq, Eq)]
+struct FolderListQuery {
+    search: String,
+    filters: enum_map::EnumMap<FolderStatus, bool>,
+    is_errors_only: bool,
+    sort_mode: FolderSortMode,
+}
+
+struct BatchExecuteConfirmation {
+    // Every folder is executed if there are no selected paths
+    folder_paths: Option<Vec<String>>,
+    changes: PendingChanges,
+}
+
+struct FolderExecuteConfirmation {
+    folder: Arc<AppFolder>,
+    changes: PendingChanges,
+}
+
 pub struct GuiAppFoldersList {
     searcher: FuzzySearcher,
     filters: enum_map::EnumMap<FolderStatus, bool>,
+    // Only list folders that have errors or warnings regardless of their status
+    is_errors_only: bool,
+    pub(crate) is_show_series_matches: bool,
+    // Changes are counted when execute all is clicked so the user can confirm them
+    batch_execute_confirmation: Option<BatchExecuteConfirmation>,
+    execute_folder_confirmation: Option<FolderExecuteConfirmation>,
+    login_form: GuiLoginForm,
+    // Paths of the folders in the order they were last listed
+    // This is kept while the list is hovered so rows don't move under the cursor as scans finish
+    folder_order: Vec<String>,
+    is_list_hovered: bool,
+    // Indices of the listed folders are kept between frames and rebuilt when the query or a folder changes
+    listed_folder_indices: Vec<usize>,
+    listed_query: Option<FolderListQuery>,
+    is_listed_outdated: bool,
+    change_receiver: Option<broadcast::Receiver<ChangeEvent>>,
+    // Folders picked with ctrl or shift click for batch actions, separate from the folder that is shown
+    selected_folder_paths: HashSet<String>,
+    // Shift click selects every listed folder between this one and the clicked one
+    anchor_folder_path: Option<String>,
+    // Only one folder picker can be open at a time
+    is_picking_root_path: Arc<AtomicBool>,
 }
 
 impl GuiAppFoldersList {
@@ -27,6 +85,20 @@ impl GuiAppFoldersList {
         Self {
             searcher: FuzzySearcher::new(),
             filters: enum_map::enum_map! { _ => true },
+            is_errors_only: false,
+            is_show_series_matches: false,
+            batch_execute_confirmation: None,
+            execute_folder_confirmation: None,
+            login_form: GuiLoginForm::new(),
+            folder_order: Vec::new(),
+            is_list_hovered: false,
+            listed_folder_indices: Vec::new(),
+            listed_query: None,
+            is_listed_outdated: false,
+            change_receiver: None,
+            selected_folder_paths: HashSet::new(),
+            anchor_folder_path: None,
+            is_picking_root_path: Arc::new(AtomicBool::new(false)),
         }
     }
 }
@@ -54,8 +126,424 @@ fn render_folder_status(ui: &mut egui::Ui, status: FolderStatus, is_busy: bool)
     }
 }
 
+fn get_pending_changes(folders: &[Arc<AppFolder>]) -> PendingChanges {
+    let mut changes = PendingChanges {
+        total_folders: 0,
+        total_renames: 0,
+        total_deletes: 0,
+    };
+    for folder in folders.iter().filter(|folder| folder.get_folder_status() == FolderStatus::Pending) {
+        changes.total_folders += 1;
+        let files = folder.get_files_blocking();
+        for file in files.to_iter().filter(|file| file.get_is_enabled()) {
+            match file.get_action() {
+                Action::Rename if !file.get_is_conflict() => changes.total_renames += 1,
+                Action::Delete => changes.total_deletes += 1,
+                _ => {},
+            }
+        }
+    }
+    changes
+}
+
+fn render_batch_execute_confirmation(ctx: &egui::Context, gui: &mut GuiAppFoldersList, app: &Arc<App>) {
+    let confirmation = match gui.batch_execute_confirmation.as_ref() {
+        Some(confirmation) => confirmation,
+        None => return,
+    };
+
+    let changes = &confirmation.changes;
+    let title = match confirmation.folder_paths {
+        Some(_) => "Execute selected changes",
+        None => "Execute all changes",
+    };
+    let mut is_open = true;
+    let mut is_closed = false;
+    egui::Window::new(title)
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut is_open)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Rename {} files and delete {} files across {} folders?",
+                changes.total_renames, changes.total_deletes, changes.total_folders,
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Execute").clicked() {
+                    tokio::spawn({
+                        let app = app.clone();
+                        let folder_paths = confirmation.folder_paths.clone();
+                        async move {
+                            match folder_paths {
+                                Some(folder_paths) => app.execute_pending_changes_for_folders(folder_paths.as_slice()).await,
+                                None => app.execute_all_pending_changes().await,
+                            }
+                        }
+                    });
+                    is_closed = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    is_closed = true;
+                }
+            });
+        });
+
+    if !is_open || is_closed {
+        gui.batch_execute_confirmation = None;
+    }
+}
+
+fn render_selected_folders_controls(
+    ui: &mut egui::Ui,
+    gui: &mut GuiAppFoldersList, app: &Arc<App>, provider: Option<&Arc<dyn MetadataProvider>>, folders: &[Arc<AppFolder>],
+    is_busy: bool,
+) {
+    let is_provider_ready = provider.is_some_and(|provider| provider.is_ready());
+    let rate_limit_delay = provider.and_then(|provider| provider.get_rate_limit_delay());
+    let get_folder_paths = |gui: &GuiAppFoldersList| -> Vec<String> {
+        gui.selected_folder_paths.iter().cloned().collect()
+    };
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label(format!("{} selected", gui.selected_folder_paths.len()));
+        ui.add_enabled_ui(!is_busy, |ui| {
+            let res = ui.button("Rescan");
+            if res.clicked() {
+                tokio::spawn({
+                    let app = app.clone();
+                    let folder_paths = get_folder_paths(gui);
+                    async move {
+                        app.update_file_intents_for_folders(folder_paths.as_slice()).await
+                    }
+                });
+            }
+            res.on_disabled_hover_ui(|ui| {
+                ui.label("Folders are busy");
+            });
+
+            let res = ui.button("Execute…");
+            if res.clicked() {
+                let selected_folders: Vec<Arc<AppFolder>> = folders
+                    .iter()
+                    .filter(|folder| gui.selected_folder_paths.contains(folder.get_folder_path()))
+                    .cloned()
+                    .collect();
+                gui.batch_execute_confirmation = Some(BatchExecuteConfirmation {
+                    folder_paths: Some(get_folder_paths(gui)),
+                    changes: get_pending_changes(selected_folders.as_slice()),
+                });
+            }
+            res.on_disabled_hover_ui(|ui| {
+                ui.label("Folders are busy");
+            });
+
+            ui.add_enabled_ui(is_provider_ready && rate_limit_delay.is_none(), |ui| {
+                let res = ui.button("Refresh metadata");
+                if res.clicked() {
+                    tokio::spawn({
+                        let app = app.clone();
+                        let folder_paths = get_folder_paths(gui);
+                        async move {
+                            app.refresh_caches_for_folders(folder_paths.as_slice()).await
+                        }
+                    });
+                }
+                res.on_disabled_hover_ui(|ui| {
+                    match rate_limit_delay {
+                        Some(delay) if is_provider_ready => ui.label(format!("Rate limited, try again in {}s", delay.as_secs_f64().ceil())),
+                        _ => ui.label("Not logged in"),
+                    };
+                });
+            });
+        });
+
+        let res = ui.button("Clear");
+        if res.clicked() {
+            gui.selected_folder_paths.clear();
+        }
+        res.on_hover_text("Escape also clears the selection");
+    });
+}
+
+// Arrow keys, home and end move the shown folder and enter toggles it like a click
+// Keys are left alone while a text field such as the search bar has focus
+// Returns the folder that was moved to so it can be scrolled into view
+fn check_folders_list_keys(
+    ui: &mut egui::Ui, gui: &mut GuiAppFoldersList, app: &Arc<App>, listed_paths: &[&str],
+) -> Option<String> {
+    if ui.memory(|memory| memory.focus().is_some()) {
+        return None;
+    }
+    let (navigation, is_enter) = ui.input(|i| {
+        let navigation = if i.key_pressed(egui::Key::ArrowUp) {
+            Some(FolderNavigation::Previous)
+        } else if i.key_pressed(egui::Key::ArrowDown) {
+            Some(FolderNavigation::Next)
+        } else if i.key_pressed(egui::Key::Home) {
+            Some(FolderNavigation::First)
+        } else if i.key_pressed(egui::Key::End) {
+            Some(FolderNavigation::Last)
+        } else {
+            None
+        };
+        (navigation, i.key_pressed(egui::Key::Enter))
+    });
+
+    // Navigation starts from the shown folder or the last one that was picked if none is shown
+    let current_path = app.get_selected_folder_path().blocking_read().clone().or_else(|| gui.anchor_folder_path.clone());
+    let current_index = current_path.as_ref().and_then(|current_path| listed_paths.iter().position(|path| path == current_path));
+
+    if let Some(navigation) = navigation {
+        let index = get_navigation_index(listed_paths.len(), current_index, navigation)?;
+        let folder_path = listed_paths[index].to_string();
+        gui.selected_folder_paths.clear();
+        gui.anchor_folder_path = Some(folder_path.clone());
+        *app.get_selected_folder_path().blocking_write() = Some(folder_path.clone());
+        return Some(folder_path);
+    }
+
+    if is_enter {
+        let anchor_path = gui.anchor_folder_path.clone()?;
+        if listed_paths.contains(&anchor_path.as_str()) {
+            on_folder_clicked(gui, app, anchor_path.as_str(), listed_paths, egui::Modifiers::NONE);
+        }
+    }
+    None
+}
+
+// Ctrl click toggles a folder and shift click selects a range of the listed folders
+// The shown folder is only changed by a plain click
+fn on_folder_clicked(
+    gui: &mut GuiAppFoldersList, app: &Arc<App>, folder_path: &str, listed_paths: &[&str],
+    modifiers: egui::Modifiers,
+) {
+    if modifiers.shift {
+        let anchor = gui.anchor_folder_path.clone().or_else(|| app.get_selected_folder_path().blocking_read().clone());
+        let anchor_index = anchor.and_then(|anchor| listed_paths.iter().position(|path| *path == anchor));
+        let clicked_index = listed_paths.iter().position(|path| *path == folder_path);
+        if let (Some(anchor_index), Some(clicked_index)) = (anchor_index, clicked_index) {
+            let range = anchor_index.min(clicked_index)..=anchor_index.max(clicked_index);
+            gui.selected_folder_paths = listed_paths[range].iter().map(|path| path.to_string()).collect();
+        } else {
+            gui.selected_folder_paths.insert(folder_path.to_string());
+        }
+        return;
+    }
+
+    gui.anchor_folder_path = Some(folder_path.to_string());
+    if modifiers.command {
+        // The shown folder is part of the selection it is started from
+        if gui.selected_folder_paths.is_empty() {
+            if let Some(selected_folder_path) = app.get_selected_folder_path().blocking_read().clone() {
+                gui.selected_folder_paths.insert(selected_folder_path);
+            }
+        }
+        if !gui.selected_folder_paths.remove(folder_path) {
+            gui.selected_folder_paths.insert(folder_path.to_string());
+        }
+        return;
+    }
+
+    gui.selected_folder_paths.clear();
+    let mut selected_folder_path = app.get_selected_folder_path().blocking_write();
+    if selected_folder_path.as_deref() != Some(folder_path) {
+        *selected_folder_path = Some(folder_path.to_string());
+    } else {
+        *selected_folder_path = None;
+    }
+}
+
+fn render_execute_folder_confirmation(ctx: &egui::Context, gui: &mut GuiAppFoldersList) {
+    let confirmation = match gui.execute_folder_confirmation.as_ref() {
+        Some(confirmation) => confirmation,
+        None => return,
+    };
+
+    let folder = &confirmation.folder;
+    let changes = &confirmation.changes;
+    let is_not_busy = folder.get_busy_lock().try_lock().is_ok();
+    let mut is_open = true;
+    let mut is_closed = false;
+    egui::Window::new("Execute folder changes")
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut is_open)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Rename {} files and delete {} files in {}?",
+                changes.total_renames, changes.total_deletes, folder.get_folder_name(),
+            ));
+            ui.horizontal(|ui| {
+                let res = ui.add_enabled(is_not_busy, egui::Button::new("Execute"));
+                if res.clicked() {
+                    tokio::spawn({
+                        let folder = folder.clone();
+                        async move {
+                            folder.execute_file_changes_and_rescan().await
+                        }
+                    });
+                    is_closed = true;
+                }
+                res.on_disabled_hover_ui(|ui| {
+                    ui.label("Folder is busy");
+                });
+                if ui.button("Cancel").clicked() {
+                    is_closed = true;
+                }
+            });
+        });
+
+    if !is_open || is_closed {
+        gui.execute_folder_confirmation = None;
+    }
+}
+
+// Mirrors the controls in the folder view so a folder doesn't have to be selected first
+fn render_folder_context_menu(
+    ui: &mut egui::Ui,
+    gui: &mut GuiAppFoldersList, app: &Arc<App>, provider: Option<&Arc<dyn MetadataProvider>>, folder: &Arc<AppFolder>,
+    is_busy: bool,
+) {
+    let is_folder_busy = folder.get_busy_lock().try_lock().is_err();
+    let is_cache_loaded = folder.get_cache().try_read().is_ok_and(|cache| cache.is_some());
+    let is_provider_ready = provider.is_some_and(|provider| provider.is_ready());
+    let rate_limit_delay = provider.and_then(|provider| provider.get_rate_limit_delay());
+
+    if ui.button("Open folder").clicked() {
+        tokio::spawn({
+            let folder_path_str = folder.get_folder_path().to_string();
+            async move {
+                cross_open::that(folder_path_str)
+            }
+        });
+        ui.close_menu();
+    }
+
+    let res = ui.add_enabled(!is_folder_busy, egui::Button::new("Rescan files"));
+    if res.clicked() {
+        tokio::spawn({
+            let folder = folder.clone();
+            async move {
+                folder.update_file_intents().await
+            }
+        });
+        ui.close_menu();
+    }
+    res.on_disabled_hover_ui(|ui| {
+        ui.label("Folder is busy");
+    });
+
+    let is_pending = folder.get_folder_status() == FolderStatus::Pending;
+    let res = ui.add_enabled(!is_folder_busy && is_pending, egui::Button::new("Execute changes…"));
+    if res.clicked() {
+        gui.execute_folder_confirmation = Some(FolderExecuteConfirmation {
+            folder: folder.clone(),
+            changes: get_pending_changes(std::slice::from_ref(folder)),
+        });
+        ui.close_menu();
+    }
+    res.on_disabled_hover_ui(|ui| {
+        if is_folder_busy { ui.label("Folder is busy"); }
+        else if !is_pending { ui.label("No pending changes"); }
+    });
+
+    let res = ui.add_enabled(
+        !is_folder_busy && is_cache_loaded && is_provider_ready && rate_limit_delay.is_none(),
+        egui::Button::new("Refresh metadata"),
+    );
+    if res.clicked() {
+        if let Some(provider) = provider {
+            tokio::spawn({
+                let folder = folder.clone();
+                let provider = provider.clone();
+                async move {
+                    folder.refresh_cache_from_api(provider).await?;
+                    tokio::join!(
+                        folder.update_file_intents(),
+                        folder.save_cache_to_file(),
+                    );
+                    Some(())
+                }
+            });
+        }
+        ui.close_menu();
+    }
+    res.on_disabled_hover_ui(|ui| {
+        if is_folder_busy { ui.label("Folder is busy"); }
+        else if !is_cache_loaded { ui.label("Cache is unloaded"); }
+        else if !is_provider_ready { ui.label("Not logged in"); }
+        else if let Some(delay) = rate_limit_delay {
+            ui.label(format!("Rate limited, try again in {}s", delay.as_secs_f64().ceil()));
+        }
+    });
+
+    let res = ui.add_enabled(!is_folder_busy, egui::Button::new("Load bookmarks"));
+    if res.clicked() {
+        tokio::spawn({
+            let folder = folder.clone();
+            async move {
+                folder.load_bookmarks_from_file().await
+            }
+        });
+        ui.close_menu();
+    }
+    res.on_disabled_hover_ui(|ui| {
+        ui.label("Folder is busy");
+    });
+
+    let has_errors = folder.has_errors();
+    let res = ui.add_enabled(has_errors, egui::Button::new("Clear errors"));
+    if res.clicked() {
+        folder.clear_errors_blocking();
+        // Clearing errors doesn't send a change so the folder has to be filtered again here
+        gui.is_listed_outdated = true;
+        ui.close_menu();
+    }
+    res.on_disabled_hover_ui(|ui| {
+        ui.label("Folder has no errors");
+    });
+
+    let res = ui.add_enabled(!is_busy, egui::Button::new("Remove from list"));
+    if res.clicked() {
+        tokio::spawn({
+            let app = app.clone();
+            let folder_path_str = folder.get_folder_path().to_string();
+            async move {
+                app.remove_folder(folder_path_str.as_str()).await
+            }
+        });
+        ui.close_menu();
+    }
+    res.on_disabled_hover_ui(|ui| {
+        ui.label("Folders are busy");
+    });
+}
+
+// The picked folder is remembered as the last root path so it is opened on the next launch
+fn spawn_root_path_picker(gui: &GuiAppFoldersList, app: &Arc<App>) {
+    if gui.is_picking_root_path.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    tokio::spawn({
+        let app = app.clone();
+        let is_picking_root_path = gui.is_picking_root_path.clone();
+        async move {
+            let root_path = app.get_root_path().read().await.clone();
+            let picked_folder = rfd::AsyncFileDialog::new()
+                .set_title("Open library")
+                .set_directory(root_path)
+                .pick_folder()
+                .await;
+            is_picking_root_path.store(false, Ordering::Relaxed);
+            // Nothing is changed if the picker was cancelled
+            let root_path = picked_folder?.path().to_string_lossy().to_string();
+            app.load_folders(root_path).await
+        }
+    });
+}
+
 fn render_folders_controls(
-    ui: &mut egui::Ui, app: &Arc<App>,
+    ui: &mut egui::Ui, gui: &mut GuiAppFoldersList, app: &Arc<App>, folders: &[Arc<AppFolder>],
     is_show_settings: &mut bool, is_busy: bool
 ) {
     ui.horizontal(|ui| {
@@ -73,6 +561,41 @@ fn render_folders_controls(
                 ui.label("Folders are busy");
             });
 
+            let is_logged_in = app.is_provider_ready_blocking();
+            let rate_limit_delay = app.get_rate_limit_delay_blocking();
+            if let Some(delay) = rate_limit_delay {
+                ui.ctx().request_repaint_after(delay);
+            }
+            ui.add_enabled_ui(is_logged_in && rate_limit_delay.is_none(), |ui| {
+                let res = ui.button("Refresh updated");
+                if res.clicked() {
+                    tokio::spawn({
+                        let app = app.clone();
+                        async move {
+                            app.refresh_stale_caches().await
+                        }
+                    });
+                }
+                let res = res.on_hover_text("Fetch the cache again for series which were updated since the last check");
+                res.on_disabled_hover_ui(|ui| {
+                    match rate_limit_delay {
+                        Some(delay) if is_logged_in => ui.label(format!("Rate limited, try again in {}s", delay.as_secs_f64().ceil())),
+                        _ => ui.label("Not logged in"),
+                    };
+                });
+            });
+
+            let is_picking_root_path = gui.is_picking_root_path.load(Ordering::Relaxed);
+            let res = ui.add_enabled(!is_picking_root_path, egui::Button::new("Open library…"));
+            if res.clicked() {
+                spawn_root_path_picker(gui, app);
+            }
+            let res = res.on_hover_text("Pick the folder which contains the series folders");
+            res.on_disabled_hover_ui(|ui| {
+                if is_picking_root_path { ui.label("Folder picker is already open"); }
+                else { ui.label("Folders are busy"); }
+            });
+
             let res = ui.button("Reload structure");
             if res.clicked() {
                 tokio::spawn({
@@ -85,28 +608,70 @@ fn render_folders_controls(
             res.on_disabled_hover_ui(|ui| {
                 ui.label("Folders are busy");
             });
+
+            let res = ui.button("Execute all");
+            if res.clicked() {
+                gui.batch_execute_confirmation = Some(BatchExecuteConfirmation {
+                    folder_paths: None,
+                    changes: get_pending_changes(folders),
+                });
+            }
+            res.on_disabled_hover_ui(|ui| {
+                ui.label("Folders are busy");
+            });
         });
 
-        if ui.button("Login").clicked() {
+        let mut is_watching = app.is_watching_blocking();
+        let res = ui.toggle_value(&mut is_watching, "Watch");
+        let res = res.on_hover_text("Rescan folders when files change on disk");
+        if res.clicked() {
             tokio::spawn({
                 let app = app.clone();
                 async move {
-                    app.login().await
+                    if is_watching {
+                        app.start_watching().await
+                    } else {
+                        app.stop_watching().await;
+                        Some(())
+                    }
                 }
             });
         }
 
-        let is_logged_in = app.get_login_session().blocking_read().is_some();
-        let login_icon = match is_logged_in {
-            true => egui::RichText::new("✔").strong().color(egui::Color32::DARK_GREEN),
-            false => egui::RichText::new("🗙").strong().color(egui::Color32::DARK_RED),
-        };
-        ui.label(login_icon).on_hover_ui(|ui| {
-            if is_logged_in {
-                ui.label("Login successful");
+        let res = ui.toggle_value(&mut gui.is_show_series_matches, "Auto match");
+        res.on_hover_text("Match folders without a cache to a series");
+
+        if ui.button("Login").clicked() {
+            // Credentials have to be typed in if they haven't been saved
+            let is_credentials_saved = std::path::Path::new(app.get_credentials_path().as_str()).exists();
+            if is_credentials_saved || !app.get_provider_kind_blocking().is_login_required() {
+                tokio::spawn({
+                    let app = app.clone();
+                    async move {
+                        app.login().await?;
+                        app.start_token_refresh().await;
+                        Some(())
+                    }
+                });
             } else {
-                ui.label("Logged out");
+                gui.login_form.is_open = true;
             }
+        }
+
+        let login_state = app.get_login_state_blocking();
+        let login_icon = match login_state {
+            LoginState::LoggedIn => egui::RichText::new("✔").strong().color(egui::Color32::DARK_GREEN),
+            LoginState::LoggedOut => egui::RichText::new("🗙").strong().color(egui::Color32::DARK_RED),
+            LoginState::Expired => egui::RichText::new("⚠").strong().color(egui::Color32::BROWN),
+            LoginState::NotRequired => egui::RichText::new("✔").strong().color(egui::Color32::DARK_GREEN),
+        };
+        ui.label(login_icon).on_hover_ui(|ui| {
+            match login_state {
+                LoginState::LoggedIn => ui.label("Login successful"),
+                LoginState::LoggedOut => ui.label("Logged out"),
+                LoginState::Expired => ui.label("Session expired, login again"),
+                LoginState::NotRequired => ui.label(format!("{} (no login required)", app.get_provider_kind_blocking().to_str())),
+            };
         });
 
         if ui.selectable_label(*is_show_settings, "⛭").clicked() {
@@ -115,10 +680,12 @@ fn render_folders_controls(
     });
 }
 
-fn render_folders_progress_bar(ui: &mut egui::Ui, total_finished: usize, total_folders: usize) {
-    let total_progress: f32 = total_finished as f32 / total_folders as f32;
+fn render_scan_progress_bar(ui: &mut egui::Ui, progress: &ScanProgress) {
+    let total_progress: f32 = progress.total_completed as f32 / progress.total_queued as f32;
+    let total_scanning = progress.total_started - progress.total_completed;
+    let text = format!("Scanned {}/{} folders ({} in progress)", progress.total_completed, progress.total_queued, total_scanning);
     let elem = egui::ProgressBar::new(total_progress)
-        .text(format!("{}/{}", total_finished, total_folders))
+        .text(text)
         .desired_width(ui.available_width())
         .desired_height(ui.spacing().interact_size.y);
     ui.add(elem);
@@ -128,6 +695,7 @@ fn render_folders_status_filter(
     ui: &mut egui::Ui,
     status_counts: &enum_map::EnumMap<FolderStatus, usize>,
     filters: &mut enum_map::EnumMap<FolderStatus, bool>,
+    is_errors_only: &mut bool, total_errors: usize,
 ) {
     let layout = egui::Layout::left_to_right(egui::Align::Min)
         .with_main_justify(true)
@@ -147,10 +715,176 @@ fn render_folders_status_filter(
                         ui.end_row();
                     }
                 }
+                let checkbox = egui::Checkbox::new(is_errors_only, format!("Errors only ({})", total_errors));
+                ui.add(checkbox).on_hover_text("Only list folders with errors or warnings");
+                ui.end_row();
             });
     });
 }
 
+// The previous order is reused while the list is hovered and only folders that weren't listed are added to the end
+fn get_folder_order(gui: &mut GuiAppFoldersList, folders: &[Arc<AppFolder>], sort_mode: FolderSortMode) -> Vec<usize> {
+    let folder_indices: HashMap<&str, usize> = folders
+        .iter()
+        .enumerate()
+        .map(|(index, folder)| (folder.get_folder_path(), index))
+        .collect();
+    let mut indices: Vec<usize> = match gui.is_list_hovered {
+        true => gui.folder_order.iter().filter_map(|path| folder_indices.get(path.as_str()).copied()).collect(),
+        false => get_sorted_folder_indices(folders, sort_mode),
+    };
+    if indices.len() != folders.len() {
+        let mut is_listed = vec![false; folders.len()];
+        for index in indices.iter() {
+            is_listed[*index] = true;
+        }
+        indices.extend((0..folders.len()).filter(|index| !is_listed[*index]));
+    }
+    gui.folder_order = indices.iter().map(|index| folders[*index].get_folder_path().to_string()).collect();
+    indices
+}
+
+// Changes to folders are held back while the list is hovered so rows don't move under the cursor
+// Loading folders changes their indices so the list is always rebuilt then
+fn update_listed_folders(gui: &mut GuiAppFoldersList, app: &Arc<App>, folders: &[Arc<AppFolder>]) {
+    let mut is_folders_changed = false;
+    let change_receiver = gui.change_receiver.get_or_insert_with(|| app.subscribe_changes());
+    loop {
+        match change_receiver.try_recv() {
+            Ok(event) => {
+                gui.is_listed_outdated = true;
+                is_folders_changed |= event.kind == ChangeKind::Folders;
+            },
+            // Lagging means we missed changes so we rebuild anyway
+            Err(broadcast::error::TryRecvError::Lagged(_)) => {
+                gui.is_listed_outdated = true;
+                is_folders_changed = true;
+            },
+            Err(_) => break,
+        }
+    }
+
+    let query = FolderListQuery {
+        search: gui.searcher.get_search_text().to_string(),
+        filters: gui.filters,
+        is_errors_only: gui.is_errors_only,
+        sort_mode: app.get_folder_sort_mode_blocking(),
+    };
+    let is_query_changed = gui.listed_query.as_ref() != Some(&query);
+    let is_outdated = gui.is_listed_outdated && !gui.is_list_hovered;
+    // The folders could have been loaded before their change was received
+    let is_out_of_range = gui.listed_folder_indices.iter().any(|index| *index >= folders.len());
+    if !is_query_changed && !is_outdated && !is_folders_changed && !is_out_of_range {
+        return;
+    }
+
+    let folder_order = get_folder_order(gui, folders, query.sort_mode);
+    let searcher = &mut gui.searcher;
+    gui.listed_folder_indices = get_listed_folder_indices(
+        folders, folder_order.as_slice(), &gui.filters, gui.is_errors_only,
+        |folder_name| searcher.search(folder_name),
+    );
+    gui.listed_query = Some(query);
+    // The order is kept while hovered so it has to be sorted again afterwards
+    gui.is_listed_outdated = gui.is_listed_outdated && gui.is_list_hovered;
+}
+
+// Every row has the same height so only the visible rows need to be laid out
+fn get_folder_row_height(ui: &egui::Ui) -> f32 {
+    let text_height = ui.text_style_height(&egui::TextStyle::Body) + 2.0*ui.spacing().button_padding.y;
+    text_height.max(ui.spacing().interact_size.y)
+}
+
+fn render_folder_sort_mode(ui: &mut egui::Ui, app: &Arc<App>) {
+    let mut sort_mode = app.get_folder_sort_mode_blocking();
+    let previous_sort_mode = sort_mode;
+    egui::ComboBox::from_id_source("folder_sort_mode")
+        .selected_text(format!("Sort by {}", sort_mode.to_str().to_lowercase()))
+        .show_ui(ui, |ui| {
+            for mode in FolderSortMode::iterator() {
+                ui.selectable_value(&mut sort_mode, *mode, mode.to_str());
+            }
+        });
+    if sort_mode != previous_sort_mode {
+        tokio::spawn({
+            let app = app.clone();
+            async move {
+                app.set_folder_sort_mode(sort_mode).await
+            }
+        });
+    }
+}
+
+fn render_folder_errors_marker(ui: &mut egui::Ui, folder: &AppFolder) {
+    if !folder.has_errors() {
+        return;
+    }
+    let label = egui::RichText::new("⚠").small().color(egui::Color32::BROWN);
+    ui.label(label).on_hover_ui(|ui| {
+        if let Ok(errors) = folder.get_errors().try_read() {
+            ui.label(format!("{} errors or warnings", errors.len()));
+            if let Some(error) = errors.iter().last() {
+                ui.label(error.to_string());
+            }
+        }
+    });
+}
+
+// Counts are skipped while the folder is being written to so rendering never waits on it
+fn render_folder_action_counts(ui: &mut egui::Ui, folder: &AppFolder) {
+    let counts = match folder.try_get_action_counts() {
+        Some(counts) => counts,
+        None => return,
+    };
+    let total_renames = counts.action_count[Action::Rename];
+    let total_deletes = counts.action_count[Action::Delete];
+    if total_renames == 0 && total_deletes == 0 && counts.total_conflicts == 0 {
+        return;
+    }
+
+    let res = ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 2.0;
+        // Added from the right so they are in reverse order
+        if counts.total_conflicts > 0 {
+            ui.label(egui::RichText::new("!").strong().color(egui::Color32::RED));
+        }
+        ui.weak(format!("({}R {}D)", total_renames, total_deletes));
+    });
+    res.response.on_hover_ui(|ui| {
+        egui::Grid::new("folder_action_counts").num_columns(2).show(ui, |ui| {
+            for action in Action::iterator() {
+                ui.label(action.to_str());
+                ui.label(counts.action_count[*action].to_string());
+                ui.end_row();
+            }
+            if counts.total_conflicts > 0 {
+                ui.colored_label(egui::Color32::RED, "Conflicts");
+                ui.colored_label(egui::Color32::RED, counts.total_conflicts.to_string());
+                ui.end_row();
+            }
+        });
+    });
+}
+
+// Uses whatever bookmarks are already loaded so rendering never reads the bookmarks file
+fn render_folder_bookmark_counts(ui: &mut egui::Ui, folder: &AppFolder) {
+    let (total_favourite, total_unread) = match folder.get_bookmarks().try_read() {
+        Ok(bookmarks) => (
+            bookmarks.count_by(|bookmark| bookmark.is_favourite),
+            bookmarks.count_by(|bookmark| bookmark.is_unread),
+        ),
+        Err(_) => return,
+    };
+    if total_favourite > 0 {
+        let label = egui::RichText::new(format!("★{}", total_favourite)).small().color(egui::Color32::GOLD);
+        ui.label(label).on_hover_text(format!("{} favourites", total_favourite));
+    }
+    if total_unread > 0 {
+        let label = egui::RichText::new(format!("？{}", total_unread)).small().color(egui::Color32::DARK_RED);
+        ui.label(label).on_hover_text(format!("{} unread", total_unread));
+    }
+}
+
 pub fn render_folders_list(
     ui: &mut egui::Ui,
     gui: &mut GuiAppFoldersList, app: &Arc<App>, is_show_settings: &mut bool,
@@ -158,16 +892,39 @@ pub fn render_folders_list(
     let folders = app.get_folders().blocking_read();
     let is_busy = app.get_folders_busy_lock().try_lock().is_err();
     let mut status_counts: enum_map::EnumMap<FolderStatus, usize> = enum_map::enum_map! { _ => 0 };
+    let mut total_errors = 0;
     for folder in folders.iter() {
-        let status = folder.get_folder_status_blocking();
+        let status = folder.get_folder_status();
         status_counts[status] += 1; 
+        if folder.has_errors() {
+            total_errors += 1;
+        }
     }
 
-    render_folders_controls(ui, app, is_show_settings, is_busy);
-    render_folders_progress_bar(ui, status_counts[FolderStatus::Done], folders.len());
+    render_folders_controls(ui, gui, app, folders.as_slice(), is_show_settings, is_busy);
+    render_batch_execute_confirmation(ui.ctx(), gui, app);
+    render_execute_folder_confirmation(ui.ctx(), gui);
+    render_login_form(ui.ctx(), &mut gui.login_form, app);
+    let scan_progress = app.get_scan_progress().borrow().clone();
+    if !scan_progress.is_idle() {
+        render_scan_progress_bar(ui, &scan_progress);
+        // Progress updates aren't sent as change events so we need to keep refreshing
+        ui.ctx().request_repaint();
+    }
+    if let Ok(summary) = app.get_last_execution_summary().try_read() {
+        if let Some(summary) = summary.as_ref() {
+            let label = egui::RichText::new(summary.to_string());
+            let label = match summary.summary.failed {
+                0 => label,
+                _ => label.color(egui::Color32::DARK_RED),
+            };
+            ui.label(label);
+        }
+    }
     ui.separator();
-    render_folders_status_filter(ui, &status_counts, &mut gui.filters);
+    render_folders_status_filter(ui, &status_counts, &mut gui.filters, &mut gui.is_errors_only, total_errors);
     render_search_bar(ui, &mut gui.searcher);
+    render_folder_sort_mode(ui, app);
 
     if folders.is_empty() {
         if is_busy {
@@ -178,52 +935,91 @@ pub fn render_folders_list(
         return;
     }
  
-    egui::ScrollArea::vertical().show(ui, |ui| {
-        let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
-        ui.with_layout(layout, |ui| {
-            let selected_index = *app.get_selected_folder_index().blocking_read();
-            for (index, folder) in folders.iter().enumerate() {
-                let label = folder.get_folder_name();
-                if !gui.searcher.search(label) {
-                    continue;
-                }
+    update_listed_folders(gui, app, folders.as_slice());
+    let listed_folders: Vec<&Arc<AppFolder>> = gui.listed_folder_indices
+        .iter()
+        .filter_map(|index| folders.get(*index))
+        .collect();
+    let listed_paths: Vec<&str> = listed_folders.iter().map(|folder| folder.get_folder_path()).collect();
+    let provider = app.get_provider().blocking_read().clone();
 
-                let status = folder.get_folder_status_blocking();
-                if !gui.filters[status] {
-                    continue;
-                }
+    // Folders that were removed from the list can't be acted on
+    if !gui.selected_folder_paths.is_empty() {
+        let folder_paths: HashSet<&str> = folders.iter().map(|folder| folder.get_folder_path()).collect();
+        gui.selected_folder_paths.retain(|path| folder_paths.contains(path.as_str()));
+    }
+    if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        gui.selected_folder_paths.clear();
+    }
+    if gui.selected_folder_paths.len() > 1 {
+        render_selected_folders_controls(ui, gui, app, provider.as_ref(), folders.as_slice(), is_busy);
+    }
+    let scroll_to_index = check_folders_list_keys(ui, gui, app, listed_paths.as_slice())
+        .and_then(|folder_path| listed_paths.iter().position(|path| *path == folder_path));
 
+    let row_height = get_folder_row_height(ui);
+    let res = egui::ScrollArea::vertical().show_rows(ui, row_height, listed_folders.len(), |ui, row_range| {
+        // Rows outside of the view aren't laid out so where they would be is worked out instead
+        if let Some(index) = scroll_to_index {
+            let row_height_with_spacing = row_height + ui.spacing().item_spacing.y;
+            let top = ui.max_rect().top() + (index as f32 - row_range.start as f32)*row_height_with_spacing;
+            let rect = egui::Rect::from_x_y_ranges(ui.max_rect().x_range(), top..=top+row_height);
+            ui.scroll_to_rect(rect, None);
+        }
+        let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
+        ui.with_layout(layout, |ui| {
+            let selected_folder_path = app.get_selected_folder_path().blocking_read().clone();
+            for folder in listed_folders[row_range].iter().copied() {
+                let status = folder.get_folder_status();
                 ui.horizontal(|ui| {
-                    let is_busy = folder.get_busy_lock().try_lock().is_err();
-                    render_folder_status(ui, status, is_busy);
-                    let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
-                    ui.with_layout(layout, |ui| {
-                        let is_selected = selected_index == Some(index);
-                        let elem = ClippedSelectableLabel::new(is_selected, folder.get_folder_name());
-                        let res = ui.add(elem);
-                        if res.clicked() {
-                            let mut selected_index = app.get_selected_folder_index().blocking_write();
-                            if !is_selected {
-                                *selected_index = Some(index);
-                            } else {
-                                *selected_index = None;
+                    let is_folder_busy = folder.get_busy_lock().try_lock().is_err();
+                    render_folder_status(ui, status, is_folder_busy);
+                    render_folder_bookmark_counts(ui, folder);
+                    render_folder_errors_marker(ui, folder);
+                    // Laid out from the right so the counts are kept after the name which fills the rest of the row
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        render_folder_action_counts(ui, folder);
+                        let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
+                        ui.with_layout(layout, |ui| {
+                            let is_selected = selected_folder_path.as_deref() == Some(folder.get_folder_path());
+                            let elem = ClippedSelectableLabel::new(is_selected, folder.get_folder_name());
+                            let mut res = ui.add(elem);
+                            // Outlined so it can be told apart from the folder that is shown
+                            if gui.selected_folder_paths.contains(folder.get_folder_path()) {
+                                let stroke = ui.visuals().selection.stroke;
+                                ui.painter().rect_stroke(res.rect, 2.0, stroke);
                             }
-                        }
-                        res.context_menu(|ui| {
-                            if ui.button("Open folder").clicked() {
-                                tokio::spawn({
-                                    let folder_path_str = folder.get_folder_path().to_string();
-                                    async move {
-                                        cross_open::that(folder_path_str)
+                            if status == FolderStatus::Pending {
+                                if let Ok(file_tracker) = folder.get_file_tracker().try_read() {
+                                    let total_bytes = file_tracker.get_action_bytes()[Action::Delete];
+                                    if total_bytes > 0 {
+                                        res = res.on_hover_text(format!("{} can be reclaimed", format_bytes(total_bytes)));
                                     }
+                                }
+                            }
+                            let aired_episodes = folder.get_cache().try_read().ok().and_then(|cache| {
+                                Some(cache.as_ref()?.episodes_summary.as_ref()?.aired_episodes)
+                            });
+                            // Only counted while hovering since it goes through every file
+                            if let Some(aired_episodes) = aired_episodes.filter(|_| !is_folder_busy) {
+                                res = res.on_hover_ui(|ui| {
+                                    let on_disk = folder.get_episodes_on_disk_count_blocking();
+                                    ui.label(format!("{} of {} aired episodes", on_disk, aired_episodes));
                                 });
-                                ui.close_menu();
                             }
+                            if res.clicked() {
+                                let modifiers = ui.input(|i| i.modifiers);
+                                on_folder_clicked(gui, app, folder.get_folder_path(), listed_paths.as_slice(), modifiers);
+                            }
+                            res.context_menu(|ui| {
+                                render_folder_context_menu(ui, gui, app, provider.as_ref(), folder, is_busy);
+                            });
                         });
                     });
                 });
             }
         });
     });
+    gui.is_list_hovered = ui.rect_contains_pointer(res.inner_rect);
 }
 
@@ -0,0 +1,96 @@
+use app::app::App;
+use app::series_matcher::SeriesMatch;
+use egui;
+use std::sync::Arc;
+use tokio;
+
+fn render_series_matches_controls(ui: &mut egui::Ui, app: &Arc<App>, is_not_busy: bool) {
+    let is_logged_in = app.is_provider_ready_blocking();
+    ui.horizontal(|ui| {
+        ui.add_enabled_ui(is_not_busy && is_logged_in, |ui| {
+            let res = ui.button("Find matches");
+            if res.clicked() {
+                tokio::spawn({
+                    let app = app.clone();
+                    async move {
+                        app.update_series_matches().await
+                    }
+                });
+            }
+            let res = res.on_hover_text("Search for a series for each folder without a cache");
+            res.on_disabled_hover_ui(|ui| {
+                if !is_logged_in     { ui.label("Not logged in"); }
+                else if !is_not_busy { ui.label("Matches are busy"); }
+            });
+
+            let res = ui.button("Apply");
+            if res.clicked() {
+                tokio::spawn({
+                    let app = app.clone();
+                    async move {
+                        app.apply_series_matches().await
+                    }
+                });
+            }
+            let res = res.on_hover_text("Load the cache for each selected folder");
+            res.on_disabled_hover_ui(|ui| {
+                if !is_logged_in     { ui.label("Not logged in"); }
+                else if !is_not_busy { ui.label("Matches are busy"); }
+            });
+        });
+    });
+}
+
+fn render_series_candidates(ui: &mut egui::Ui, result: &SeriesMatch) {
+    for series in result.get_candidates() {
+        ui.label(format!("{} ({})", series.get_display_name(), series.first_aired.as_deref().unwrap_or("Unknown")));
+    }
+}
+
+pub fn render_series_matches(ui: &mut egui::Ui, app: &Arc<App>) {
+    let is_not_busy = app.get_series_matches_busy_lock().try_lock().is_ok();
+    render_series_matches_controls(ui, app, is_not_busy);
+    ui.separator();
+
+    if !is_not_busy {
+        ui.spinner();
+        return;
+    }
+
+    let mut series_matches = app.get_series_matches().blocking_write();
+    let proposals = match series_matches.as_mut() {
+        Some(proposals) => proposals,
+        None => {
+            ui.label("No matches have been searched for yet");
+            return;
+        },
+    };
+
+    if proposals.is_empty() {
+        ui.label("All folders have a cache");
+        return;
+    }
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("series_matches")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for proposal in proposals.iter_mut() {
+                    let is_matched = matches!(proposal.result, SeriesMatch::Matched(_));
+                    ui.add_enabled(is_matched, egui::Checkbox::without_text(&mut proposal.is_enabled));
+                    ui.label(proposal.folder.get_folder_name());
+                    let label = match &proposal.result {
+                        SeriesMatch::Matched(series) => egui::RichText::new(series.get_display_name()),
+                        SeriesMatch::Ambiguous(_) => egui::RichText::new("Ambiguous").color(egui::Color32::DARK_RED),
+                        SeriesMatch::NotFound(_) => egui::RichText::new("Not found").color(egui::Color32::DARK_RED),
+                    };
+                    ui.label(label).on_hover_ui(|ui| {
+                        ui.label(format!("Searched for '{}'", proposal.query));
+                        render_series_candidates(ui, &proposal.result);
+                    });
+                    ui.end_row();
+                }
+            });
+    });
+}
@@ -8,10 +8,12 @@ use crate::fuzzy_search::{FuzzySearcher, render_search_bar};
 use crate::clipped_selectable::ClippedSelectableLabel;
 use crate::helpers::render_invisible_width_widget;
 use crate::tvdb_tables::render_series_table;
+use crate::artwork::{ArtworkTextures, render_folder_artwork};
 
 pub struct GuiSeriesSearch {
     search_string: String,
     searcher: FuzzySearcher,
+    artwork: ArtworkTextures,
 }
 
 impl GuiSeriesSearch {
@@ -19,6 +21,7 @@ impl GuiSeriesSearch {
         Self {
             search_string: "".to_string(),
             searcher: FuzzySearcher::new(),
+            artwork: ArtworkTextures::new(),
         }
     }
 }
@@ -54,16 +57,9 @@ fn render_series_search_list(
         return;
     }
     
-    let folders = app.get_folders().blocking_read();
-    let folder_index = *app.get_selected_folder_index().blocking_read();
-    let folder = match folder_index {
-        None => None,
-        Some(index) => folders.get(index).cloned(),
-    };
-    drop(folders);
-    let session = app.get_login_session().blocking_read();
+    let folder = app.get_selected_folder_blocking();
     let is_folder_selected = folder.is_some();
-    let is_logged_in = session.is_some();
+    let is_logged_in = app.is_provider_ready_blocking();
     let is_not_busy = match folder.as_ref() {
         None => false,
         Some(folder) => folder.get_busy_lock().try_lock().is_ok(),
@@ -92,7 +88,7 @@ fn render_series_search_list(
                 .body(|mut body| {
                     let selected_index = *app.get_selected_series_index().blocking_read();
                     for (index, entry) in series.iter().enumerate() {
-                        if !gui.searcher.search(entry.name.as_str()) {
+                        if !gui.searcher.search(entry.get_display_name().as_str()) {
                             continue;
                         }
 
@@ -101,7 +97,7 @@ fn render_series_search_list(
                                 let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
                                 ui.with_layout(layout, |ui| {
                                     let is_selected = Some(index) == selected_index;
-                                    let elem = ClippedSelectableLabel::new(is_selected, entry.name.as_str());
+                                    let elem = ClippedSelectableLabel::new(is_selected, entry.get_display_name().as_str());
                                     let res = ui.add(elem);
                                     if res.clicked() {
                                         if is_selected {
@@ -126,23 +122,9 @@ fn render_series_search_list(
                                     if res.clicked() {
                                         tokio::spawn({
                                             let series_id = entry.id;
-                                            let folder = folder.clone();
-                                            let session = session.clone();
+                                            let app = app.clone();
                                             async move {
-                                                if let Some(folder) = folder {
-                                                    if let Some(session) = session {
-                                                        folder.load_cache_from_api(session, series_id).await?;
-                                                        tokio::join!(
-                                                            folder.update_file_intents(),
-                                                            folder.save_cache_to_file(),
-                                                        );
-                                                        Some(())
-                                                    } else {
-                                                        None
-                                                    }
-                                                } else {
-                                                    None
-                                                }
+                                                app.set_series_to_current_folder(series_id).await
                                             }
                                         });
                                     }
@@ -162,8 +144,23 @@ fn render_series_search_list(
 
 }
 
+// Artwork is stored in the selected folder since that is what the search is for
+fn render_series_poster(ui: &mut egui::Ui, artwork: &mut ArtworkTextures, app: &Arc<App>, series: &Series) {
+    let poster = match series.poster.as_deref().filter(|poster| !poster.is_empty()) {
+        Some(poster) => poster,
+        None => return,
+    };
+    let folder = match app.get_selected_folder_blocking() {
+        Some(folder) => folder,
+        None => return,
+    };
+    let provider = app.get_provider().blocking_read().clone();
+    render_folder_artwork(ui, artwork, &folder, provider.as_ref(), poster);
+}
+
 fn render_series_search_info_panel(
     ui: &mut egui::Ui, 
+    gui: &mut GuiSeriesSearch, app: &Arc<App>,
     series_list: Option<&Vec<Series>>, selected_index: Option<usize>,
 ) {
     render_invisible_width_widget(ui);
@@ -192,6 +189,7 @@ fn render_series_search_info_panel(
         },
     };
     
+    render_series_poster(ui, &mut gui.artwork, app, series);
     render_series_table(ui, series);
 }
 
@@ -207,7 +205,7 @@ fn render_series_search_bar(
             .with_main_wrap(false)
             .with_main_align(egui::Align::LEFT);
         ui.with_layout(layout, |ui| {
-            let is_logged_in = app.get_login_session().blocking_read().is_some();
+            let is_logged_in = app.is_provider_ready_blocking();
             let mut is_pressed = false;
             ui.add_enabled_ui(is_logged_in, |ui| {
                 let res = ui.button("Search");
@@ -248,7 +246,7 @@ pub fn render_series_search(
     egui::SidePanel::right("search_series_info")
         .resizable(true)
         .show_inside(ui, |ui| {
-            render_series_search_info_panel(ui, series.as_ref(), selected_index); 
+            render_series_search_info_panel(ui, gui, app, series.as_ref(), selected_index); 
         });
 
     egui::CentralPanel::default()
@@ -0,0 +1,119 @@
+use app::app_folder::AppFolder;
+use app::metadata_provider::MetadataProvider;
+use egui;
+use egui_extras::RetainedImage;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio;
+use tokio::sync::oneshot;
+
+const MAX_ARTWORK_HEIGHT: f32 = 300.0;
+const PLACEHOLDER_HEIGHT: f32 = 150.0;
+// Only the artwork of the last few folders and episodes is kept decoded
+const MAX_ARTWORK_TEXTURES: usize = 8;
+
+enum ArtworkTexture {
+    Decoding(oneshot::Receiver<Option<RetainedImage>>),
+    // Images which fail to decode aren't shown instead of being decoded again
+    Decoded(Option<RetainedImage>),
+}
+
+// Images are decoded in the background and kept by the folder and artwork path they came from
+pub struct ArtworkTextures {
+    // Most recently shown is last
+    textures: VecDeque<(String, ArtworkTexture)>,
+}
+
+fn spawn_decode(ctx: &egui::Context, key: &str, data: Arc<Vec<u8>>) -> ArtworkTexture {
+    let (sender, receiver) = oneshot::channel();
+    let ctx = ctx.clone();
+    let key = key.to_string();
+    tokio::task::spawn_blocking(move || {
+        let image = RetainedImage::from_image_bytes(key, data.as_slice()).ok();
+        let _ = sender.send(image);
+        ctx.request_repaint();
+    });
+    ArtworkTexture::Decoding(receiver)
+}
+
+pub fn render_artwork_placeholder(ui: &mut egui::Ui) {
+    let size = egui::vec2(ui.available_width(), PLACEHOLDER_HEIGHT);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    ui.painter().rect_filled(rect, 2.0, ui.visuals().faint_bg_color);
+    ui.put(rect, egui::Spinner::new());
+}
+
+impl ArtworkTextures {
+    pub fn new() -> Self {
+        Self {
+            textures: VecDeque::new(),
+        }
+    }
+
+    fn get_texture(&mut self, ctx: &egui::Context, key: &str, data: Arc<Vec<u8>>) -> &mut ArtworkTexture {
+        let texture = match self.textures.iter().position(|(other, _)| other == key) {
+            Some(index) => self.textures.remove(index).map(|(_, texture)| texture),
+            None => None,
+        };
+        let texture = texture.unwrap_or_else(|| spawn_decode(ctx, key, data));
+        if self.textures.len() >= MAX_ARTWORK_TEXTURES {
+            self.textures.pop_front();
+        }
+        self.textures.push_back((key.to_string(), texture));
+        let index = self.textures.len() - 1;
+        &mut self.textures[index].1
+    }
+
+    pub fn render(&mut self, ui: &mut egui::Ui, key: &str, data: Arc<Vec<u8>>) {
+        let texture = self.get_texture(ui.ctx(), key, data);
+        if let ArtworkTexture::Decoding(receiver) = texture {
+            match receiver.try_recv() {
+                Ok(image) => *texture = ArtworkTexture::Decoded(image),
+                Err(oneshot::error::TryRecvError::Empty) => {},
+                Err(oneshot::error::TryRecvError::Closed) => *texture = ArtworkTexture::Decoded(None),
+            }
+        }
+        match texture {
+            ArtworkTexture::Decoding(_) => render_artwork_placeholder(ui),
+            ArtworkTexture::Decoded(Some(image)) => {
+                let max_size = egui::vec2(ui.available_width(), MAX_ARTWORK_HEIGHT);
+                image.show_max_size(ui, max_size);
+            },
+            ArtworkTexture::Decoded(None) => {},
+        }
+    }
+}
+
+impl Default for ArtworkTextures {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Artwork is fetched in the background and nothing is shown if it is missing or can't be fetched
+pub fn render_folder_artwork(
+    ui: &mut egui::Ui, artwork: &mut ArtworkTextures,
+    folder: &Arc<AppFolder>, provider: Option<&Arc<dyn MetadataProvider>>, relative_path: &str,
+) {
+    let image_cache = folder.get_image_cache();
+    if let Some(data) = image_cache.get_cached(relative_path) {
+        let key = format!("{}/{}", image_cache.get_folder_path(), relative_path);
+        artwork.render(ui, key.as_str(), data);
+        return;
+    }
+    if image_cache.is_loading(relative_path) {
+        render_artwork_placeholder(ui);
+        return;
+    }
+    tokio::spawn({
+        let ctx = ui.ctx().clone();
+        let folder = folder.clone();
+        let provider = provider.cloned();
+        let relative_path = relative_path.to_string();
+        async move {
+            // The placeholder is also removed if the artwork couldn't be fetched
+            folder.get_image_cache().get_or_fetch(provider.as_deref(), relative_path.as_str()).await;
+            ctx.request_repaint();
+        }
+    });
+}
@@ -1,20 +1,95 @@
+use std::sync::Arc;
+use app::app::App;
+use app::app_folder::AppFolder;
+use app::error_log::{ErrorLog, Severity};
 use egui;
+use tokio;
+use crate::helpers::format_elapsed;
 
-pub fn render_errors_list(ui: &mut egui::Ui, errors: &mut Vec<String>) {
+pub fn render_errors_list(ui: &mut egui::Ui, errors: &mut ErrorLog, app: &Arc<App>) {
     egui::ScrollArea::vertical().show(ui, |ui| {
-        let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
-        ui.with_layout(layout, |ui| {
-            let mut selected_index = None;
-            for (index, error) in errors.iter().enumerate().rev() {
-                if ui.selectable_label(false, error.as_str()).clicked() {
-                    selected_index = Some(index);
+        render_errors(ui, errors);
+        let res = ui.small_button("Save report");
+        let res = res.on_hover_text("Save the errors of the app and every folder to errors.json");
+        if res.clicked() {
+            tokio::spawn({
+                let app = app.clone();
+                async move {
+                    app.save_errors_to_file().await
                 }
-            }
+            });
+        }
+    });
+}
 
-            if let Some(index) = selected_index {
-                errors.remove(index);  
+pub fn render_errors(ui: &mut egui::Ui, errors: &mut ErrorLog) {
+    if errors.is_empty() {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        if ui.small_button("Clear warnings").clicked() {
+            errors.clear_severity(Severity::Warning);
+        }
+        if ui.small_button("Clear all").clicked() {
+            errors.clear();
+        }
+    });
+
+    let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
+    ui.with_layout(layout, |ui| {
+        let mut selected_index = None;
+        for (index, error) in errors.iter().enumerate().rev() {
+            let color = match error.severity {
+                Severity::Warning => egui::Color32::BROWN,
+                Severity::Error => egui::Color32::DARK_RED,
+            };
+            let label = egui::RichText::new(format!("[{}] {}", format_elapsed(error.timestamp), error)).color(color);
+            let res = ui.selectable_label(false, label);
+            if res.on_hover_text(error.severity.to_str()).clicked() {
+                selected_index = Some(index);
             }
-        });
+        }
+
+        if let Some(index) = selected_index {
+            errors.remove(index);  
+        }
     });
 }
 
+pub fn render_file_errors_list(ui: &mut egui::Ui, folder: &Arc<AppFolder>) {
+    let mut dismiss_index = None;
+    let mut selected_file_index = None;
+    if let Ok(file_errors) = folder.get_file_errors().try_read() {
+        let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
+        ui.with_layout(layout, |ui| {
+            for (index, error) in file_errors.iter().enumerate().rev() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("Dismiss").clicked() {
+                        dismiss_index = Some(index);
+                    }
+                    let label = egui::RichText::new(error.to_string()).color(egui::Color32::DARK_RED);
+                    let res = ui.selectable_label(false, label);
+                    let res = match error.file_index {
+                        Some(_) => res.on_hover_text("Select file"),
+                        None => res,
+                    };
+                    if res.clicked() {
+                        selected_file_index = error.file_index;
+                    }
+                });
+            }
+        });
+    }
+
+    if let Some(index) = dismiss_index {
+        folder.dismiss_file_error_blocking(index);
+    }
+
+    if let Some(index) = selected_file_index {
+        let descriptor = folder.get_files_blocking().get(index).and_then(|file| *file.get_src_descriptor());
+        if descriptor.is_some() {
+            *folder.get_selected_descriptor().blocking_write() = descriptor;
+        }
+    }
+}
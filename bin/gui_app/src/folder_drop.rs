@@ -0,0 +1,116 @@
+use app::app::App;
+use egui;
+use std::sync::Arc;
+use tokio;
+
+// Folders dropped onto the window which are kept until the user picks how they are opened
+pub struct FolderDrop {
+    folder_paths: Vec<String>,
+}
+
+// Only folders can be opened so anything else that is dropped is ignored with a warning
+pub fn check_dropped_folders(ctx: &egui::Context, app: &App) -> Option<FolderDrop> {
+    let dropped_paths: Vec<std::path::PathBuf> = ctx.input(|i| {
+        i.raw.dropped_files.iter().filter_map(|file| file.path.clone()).collect()
+    });
+    if dropped_paths.is_empty() {
+        return None;
+    }
+
+    let mut folder_paths = Vec::new();
+    for path in dropped_paths {
+        if path.is_dir() {
+            folder_paths.push(path.to_string_lossy().to_string());
+            continue;
+        }
+        let message = format!("Cannot open '{}' since it isn't a folder", path.display());
+        app.get_errors().blocking_write().push_warn("Drop folder", message);
+    }
+    if folder_paths.is_empty() {
+        return None;
+    }
+    Some(FolderDrop { folder_paths })
+}
+
+fn spawn_add_folders(app: &Arc<App>, folder_paths: Vec<String>) {
+    tokio::spawn({
+        let app = app.clone();
+        async move {
+            for folder_path in folder_paths.iter() {
+                app.add_folder(folder_path.as_str()).await;
+            }
+        }
+    });
+}
+
+pub fn render_folder_drop_confirmation(ctx: &egui::Context, folder_drop: &mut Option<FolderDrop>, app: &Arc<App>) {
+    let folder_paths = match folder_drop.as_ref() {
+        Some(folder_drop) => &folder_drop.folder_paths,
+        None => return,
+    };
+
+    // Folders can't be added while all of them are being loaded or updated
+    let is_busy = app.get_folders_busy_lock().try_lock().is_err();
+    let mut is_open = true;
+    let mut is_closed = false;
+    egui::Window::new("Open dropped folders")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .open(&mut is_open)
+        .show(ctx, |ui| {
+            match folder_paths.as_slice() {
+                [folder_path] => {
+                    ui.label(folder_path.as_str());
+                    ui.horizontal(|ui| {
+                        if ui.button("Open as library root").clicked() {
+                            tokio::spawn({
+                                let app = app.clone();
+                                let folder_path = folder_path.clone();
+                                async move {
+                                    app.load_folders(folder_path).await
+                                }
+                            });
+                            is_closed = true;
+                        }
+                        let res = ui.add_enabled(!is_busy, egui::Button::new("Add as single folder"));
+                        if res.clicked() {
+                            spawn_add_folders(app, vec![folder_path.clone()]);
+                            is_closed = true;
+                        }
+                        res.on_disabled_hover_ui(|ui| {
+                            ui.label("Folders are busy");
+                        });
+                        if ui.button("Cancel").clicked() {
+                            is_closed = true;
+                        }
+                    });
+                },
+                folder_paths => {
+                    ui.label(format!("Add {} folders?", folder_paths.len()));
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        for folder_path in folder_paths.iter() {
+                            ui.weak(folder_path.as_str());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let res = ui.add_enabled(!is_busy, egui::Button::new("Add all"));
+                        if res.clicked() {
+                            spawn_add_folders(app, folder_paths.to_vec());
+                            is_closed = true;
+                        }
+                        res.on_disabled_hover_ui(|ui| {
+                            ui.label("Folders are busy");
+                        });
+                        if ui.button("Cancel").clicked() {
+                            is_closed = true;
+                        }
+                    });
+                },
+            }
+        });
+
+    if !is_open || is_closed {
+        *folder_drop = None;
+    }
+}
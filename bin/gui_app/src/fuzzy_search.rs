@@ -35,6 +35,10 @@ impl FuzzySearcher {
         }
     }
 
+    pub fn get_search_text(&self) -> &str {
+        self.search_edit_line.as_str()
+    }
+
     pub fn search(&mut self, input: &str) -> bool {
         if self.search_edit_line_filtered.is_empty() {
             return true;
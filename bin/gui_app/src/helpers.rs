@@ -1,5 +1,16 @@
+use app::app_file::FileSortMode;
 use egui;
 
+pub fn render_file_sort_mode(ui: &mut egui::Ui, sort_mode: &mut FileSortMode) {
+    egui::ComboBox::from_id_source("file_sort_mode")
+        .selected_text(format!("Sort by {}", sort_mode.to_str().to_lowercase()))
+        .show_ui(ui, |ui| {
+            for mode in FileSortMode::iterator() {
+                ui.selectable_value(sort_mode, *mode, mode.to_str());
+            }
+        });
+}
+
 pub fn render_invisible_width_widget(ui: &mut egui::Ui) {
     let layout = egui::Layout::top_down(egui::Align::Min).with_cross_justify(true);
     ui.with_layout(layout, |ui| {
@@ -9,3 +20,32 @@ pub fn render_invisible_width_widget(ui: &mut egui::Ui) {
     });
 }
 
+
+pub fn format_bytes(total_bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = total_bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len()-1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} {}", total_bytes, UNITS[unit]),
+        _ => format!("{:.1} {}", value, UNITS[unit]),
+    }
+}
+
+// Show how long ago something happened, i.e. "5m ago" or "3w ago"
+pub fn format_elapsed(timestamp: std::time::SystemTime) -> String {
+    let seconds = timestamp.elapsed().map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+    match seconds {
+        0..=9 => "just now".to_string(),
+        10..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        86400..=1209599 => format!("{}d ago", seconds / 86400),
+        1209600..=5183999 => format!("{}w ago", seconds / 604800),
+        5184000..=31535999 => format!("{}mo ago", seconds / 2592000),
+        _ => format!("{}y ago", seconds / 31536000),
+    }
+}
@@ -5,18 +5,25 @@ pub mod error_list;
 pub mod tvdb_tables;
 pub mod frame_history;
 pub mod settings_menu;
+pub mod login_form;
+pub mod artwork;
 
 pub mod app_bookmarks;
 pub mod app_file_actions;
+pub mod app_episode_picker;
 pub mod app_folder_basic_list;
 pub mod app_folder_conflict_list;
+pub mod app_folder_duplicate_list;
 pub mod app_folder_delete_list;
 pub mod app_folder_rename_list;
 pub mod app_folder_files_tab_list;
 pub mod app_folder_episode_cache_list;
+pub mod app_folder_missing_list;
 pub mod app_folder;
 
 pub mod app_folders_list;
 pub mod app_series_search;
+pub mod app_series_matches;
+pub mod folder_drop;
 
 pub mod app;
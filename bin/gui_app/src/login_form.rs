@@ -0,0 +1,126 @@
+use app::app::App;
+use egui;
+use std::sync::Arc;
+use tokio;
+use tvdb::api::LoginInfo;
+use tvdb::models::ProviderKind;
+
+pub struct GuiLoginForm {
+    api_key: String,
+    user_key: String,
+    username: String,
+    tmdb_api_key: String,
+    is_remember: bool,
+    is_submitted: bool,
+    pub(crate) is_open: bool,
+}
+
+impl GuiLoginForm {
+    pub fn new() -> Self {
+        Self {
+            api_key: "".to_string(),
+            user_key: "".to_string(),
+            username: "".to_string(),
+            tmdb_api_key: "".to_string(),
+            is_remember: true,
+            is_submitted: false,
+            is_open: false,
+        }
+    }
+}
+
+impl GuiLoginForm {
+    // Empty fields are left out so api keys that don't need them can login
+    fn get_login_info(&self) -> LoginInfo {
+        let get_optional = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+        LoginInfo {
+            apikey: self.api_key.trim().to_string(),
+            userkey: get_optional(self.user_key.as_str()),
+            username: get_optional(self.username.as_str()),
+        }
+    }
+}
+
+impl Default for GuiLoginForm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The form stays open with the typed values until the login succeeds so mistakes can be corrected
+pub fn render_login_form(ctx: &egui::Context, gui: &mut GuiLoginForm, app: &Arc<App>) {
+    let is_logged_in = app.get_provider().blocking_read().is_some();
+    let provider_kind = app.get_provider_kind_blocking();
+    if gui.is_submitted && is_logged_in {
+        gui.is_submitted = false;
+        gui.is_open = false;
+    }
+
+    let mut is_open = gui.is_open;
+    egui::Window::new(format!("Login to {}", provider_kind.to_str()))
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut is_open)
+        .show(ctx, |ui| {
+            egui::Grid::new("login_form")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    match provider_kind {
+                        ProviderKind::Tvdb => {
+                            ui.label("Api key");
+                            ui.text_edit_singleline(&mut gui.api_key);
+                            ui.end_row();
+                            ui.label("User key");
+                            ui.add(egui::TextEdit::singleline(&mut gui.user_key).hint_text("Optional"));
+                            ui.end_row();
+                            ui.label("Username");
+                            ui.add(egui::TextEdit::singleline(&mut gui.username).hint_text("Optional"));
+                            ui.end_row();
+                        },
+                        ProviderKind::Tmdb => {
+                            ui.label("Api key");
+                            ui.text_edit_singleline(&mut gui.tmdb_api_key);
+                            ui.end_row();
+                        },
+                        ProviderKind::TvMaze => {
+                            ui.label("No login required");
+                            ui.end_row();
+                        },
+                    }
+                });
+
+            let res = ui.checkbox(&mut gui.is_remember, "Remember");
+            res.on_hover_text(format!("Save credentials to {}", app.get_credentials_path()));
+
+            if ui.button("Login").clicked() {
+                gui.is_submitted = true;
+                match provider_kind {
+                    ProviderKind::Tvdb => tokio::spawn({
+                        let app = app.clone();
+                        let login_info = gui.get_login_info();
+                        let is_remember = gui.is_remember;
+                        async move {
+                            app.login_with(login_info, is_remember).await?;
+                            app.start_token_refresh().await;
+                            Some(())
+                        }
+                    }),
+                    ProviderKind::Tmdb => tokio::spawn({
+                        let app = app.clone();
+                        let api_key = gui.tmdb_api_key.clone();
+                        let is_remember = gui.is_remember;
+                        async move {
+                            app.login_with_api_key(api_key.as_str(), is_remember).await
+                        }
+                    }),
+                    ProviderKind::TvMaze => tokio::spawn({
+                        let app = app.clone();
+                        async move {
+                            app.login().await
+                        }
+                    }),
+                };
+            }
+        });
+    gui.is_open = is_open;
+}
@@ -32,58 +32,73 @@ impl eframe::App for FailedGuiApp {
 }
 
 fn print_usage() {
-    println!("Usage: gui_app <folder_path> [config_path]");
+    println!("Usage: gui_app [folder_path] [config_path]");
+    println!("If no folder path is given then the last loaded folder is used");
+    println!("A folder can also be opened from the gui");
 }
 
 #[tokio::main]
 async fn main() -> Result<(), eframe::Error> {
     let args: Vec<String> = std::env::args().collect();
-    if args.len() <= 1 {
-        print_usage();
-        return Ok(());
-    };
-
     if args.contains(&"--help".to_owned()) || args.contains(&"-h".to_owned()) {
         print_usage();
         return Ok(());
     }
     
-    let root_path = &args[1];
     let default_config_path = Path::new("./res").to_string_lossy().to_string();
     let config_path = args.get(2).unwrap_or(&default_config_path);
 
     let native_options = eframe::NativeOptions::default();
     let app = App::new(config_path.as_str()).await;
+
+    // Fallback to the last loaded root path if none was given
+    // Without either the gui is opened without folders so one can be picked
+    let root_path = match (args.get(1), app.as_ref()) {
+        (Some(root_path), _) => Some(root_path.clone()),
+        (None, Ok(app)) => app.get_settings().read().await.get_last_root_path().map(|path| path.to_string()),
+        // Show the error in the gui
+        (None, Err(_)) => None,
+    };
     
     tokio::task::block_in_place(move || {
         eframe::run_native(
             "Torrent Renamer", 
             native_options, 
-            Box::new({
-                let root_path = root_path.clone();
-                move |_| {
-                    let app = match app {
-                        Ok(app) => Arc::new(app),
-                        Err(err) => {
-                            let message = format!("Failed to create application: {}", err);
-                            return Box::new(FailedGuiApp::new(message));
-                        },
-                    };
+            Box::new(move |_| {
+                let app = match app {
+                    Ok(app) => Arc::new(app),
+                    Err(err) => {
+                        let message = format!("Failed to create application: {}", err);
+                        return Box::new(FailedGuiApp::new(message));
+                    },
+                };
 
-                    tokio::spawn({
-                        let app = app.clone();
-                        async move {
-                            let (res_0, res_1) = tokio::join!(
-                                app.load_folders(root_path),
-                                app.login(),
-                            );
-                            res_0.or(res_1)
+                tokio::spawn({
+                    let app = app.clone();
+                    async move {
+                        let load_folders = async {
+                            match root_path {
+                                Some(root_path) => app.load_folders(root_path).await,
+                                None => Some(()),
+                            }
+                        };
+                        let (res_0, res_1) = tokio::join!(
+                            load_folders,
+                            app.login(),
+                        );
+                        if res_1.is_some() {
+                            app.start_token_refresh().await;
+                        }
+                        // Folders are scanned after logging in so missing caches can be fetched
+                        if res_0.is_some() {
+                            app.update_file_intents_for_all_folders().await;
                         }
-                    });
+                        res_0.or(res_1)
+                    }
+                });
 
-                    let gui = GuiApp::new(app);
-                    Box::new(gui)
-                }
+                let gui = GuiApp::new(app);
+                Box::new(gui)
             }),
         )
     })
@@ -1,8 +1,9 @@
 use egui;
-use tvdb::models::{Series, Episode};
+use tvdb::models::{Actor, Series, Episode};
 use open as cross_open;
 
 const IMDB_PREFIX: &str = "https://www.imdb.com/title";
+const TOTAL_TOP_BILLED_ACTORS: usize = 10;
 
 pub fn render_series_table(ui: &mut egui::Ui, series: &Series) {
     let layout = egui::Layout::left_to_right(egui::Align::Min)
@@ -18,7 +19,7 @@ pub fn render_series_table(ui: &mut egui::Ui, series: &Series) {
                 ui.end_row();
 
                 ui.strong("Name");
-                let gui_label = egui::Label::new(series.name.as_str()).wrap(true);
+                let gui_label = egui::Label::new(series.get_display_name()).wrap(true);
                 ui.add(gui_label);
                 ui.end_row();
 
@@ -80,6 +81,18 @@ pub fn render_episode_table(ui: &mut egui::Ui, episode: &Episode) {
                 ui.label(format!("S{:02}E{:02}", episode.season, episode.episode));
                 ui.end_row();
 
+                if let (Some(season), Some(number)) = (episode.dvd_season, episode.dvd_episode) {
+                    ui.strong("DVD index");
+                    ui.label(format!("S{:02}E{:02}", season, number));
+                    ui.end_row();
+                }
+
+                if let Some(number) = episode.absolute_number {
+                    ui.strong("Absolute");
+                    ui.label(format!("{}", number));
+                    ui.end_row();
+                }
+
                 ui.strong("Name");
                 ui.label(episode.name.as_deref().unwrap_or("None"));
                 ui.end_row();
@@ -89,6 +102,12 @@ pub fn render_episode_table(ui: &mut egui::Ui, episode: &Episode) {
                 ui.label(label);
                 ui.end_row();
 
+                if let Some(runtime) = episode.runtime {
+                    ui.strong("Runtime");
+                    ui.label(format!("{} min", runtime));
+                    ui.end_row();
+                }
+
                 ui.strong("Overview");
                 let label = episode.overview.as_deref().unwrap_or("Unknown");
                 let gui_label = egui::Label::new(label).wrap(true);
@@ -110,3 +129,23 @@ pub fn render_episode_table(ui: &mut egui::Ui, episode: &Episode) {
             });
     });
 }
+
+// Actors are expected to be sorted by billing order
+pub fn render_actors_table(ui: &mut egui::Ui, actors: &[Actor]) {
+    if actors.is_empty() {
+        ui.label("No cast listed");
+        return;
+    }
+    egui::Grid::new("actors_table")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            for actor in actors.iter().take(TOTAL_TOP_BILLED_ACTORS) {
+                ui.strong(actor.name.as_str());
+                let label = actor.role.as_deref().filter(|role| !role.is_empty()).unwrap_or("Unknown");
+                let gui_label = egui::Label::new(label).wrap(true);
+                ui.add(gui_label);
+                ui.end_row();
+            }
+        });
+}
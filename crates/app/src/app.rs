@@ -1,21 +1,131 @@
+use globset;
 use reqwest;
 use serde;
 use serde_json;
 use tokio;
-use tokio::sync::{RwLock, Mutex};
-use tvdb::api::LoginSession;
-use tvdb::models::Series;
-use crate::file_intent::FilterRules;
-use crate::app_folder::AppFolder;
+use tokio::sync::{broadcast, watch, RwLock, Mutex};
+use tvdb::api::{LoginInfo, LoginSession, LoginToken, SessionOptions};
+use tvdb::models::{ProviderKind, Series};
+use tvdb::tmdb::TmdbSession;
+use tvdb::tvmaze::TvMazeSession;
+use crate::file_intent::{FilterRules, InvalidGlobError, build_folder_ignore_globs, parse_folder_ignore_file};
+use crate::app_folder::{
+    AppFolder, ExecutionSummary, FolderSortMode, FolderStatus, PATH_STR_FOLDER_IGNORE, PATH_STR_SERIES_MAPPING,
+    PATH_STR_SERIES_MAPPING_TMDB, PATH_STR_SERIES_MAPPING_TVMAZE, get_relative_folder_name, is_app_metadata_path,
+};
+use crate::series_matcher::{SeriesMatch, get_series_search_query, match_series};
+use crate::folder_watcher::{EventDebouncer, FolderWatcher, WatchTarget, WATCH_DEBOUNCE_DELAY, is_ignored_watch_event};
+use crate::atomic_write::atomic_write;
+use crate::error_log::ErrorLog;
+use crate::change_event::{BusyLockGuard, ChangeEvent, ChangeKind, CHANGE_EVENT_CAPACITY, send_change};
+use crate::app_settings::{AppSettings, load_settings_from_file, save_settings_to_file};
+use crate::metadata_provider::{MetadataProvider, get_rate_limit_message};
+use crate::series_cache_registry::{SeriesCacheKey, SeriesCacheRegistry};
+use crate::series_mapping::{SeriesMapping, load_series_mapping_from_file, save_series_mapping_to_file};
+use futures::stream::{self, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use notify::{self, Watcher};
 use thiserror;
 
+#[serde_with::skip_serializing_none]
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct Credentials {
-    #[serde(rename="credentials")]
-    pub login_info: tvdb::api::LoginInfo,     
-    // TODO: Reuse tokens if possible to avoid login requests on startup
+    #[serde(rename="credentials", default)]
+    pub login_info: Option<tvdb::api::LoginInfo>,
+    // Reused on startup to avoid a login request
     pub token: Option<String>,
+    // Tmdb only needs an api key which doesn't expire
+    pub tmdb_api_key: Option<String>,
+}
+
+const MAX_CONCURRENT_SERIES_SEARCHES: usize = 4;
+// Executing too many folders at once causes the disk to thrash
+const MAX_CONCURRENT_FOLDER_EXECUTIONS: usize = 4;
+const MAX_CONCURRENT_FOLDER_SCANS: usize = 4;
+// Tokens expire after 24 hours so they are refreshed well before then
+const TOKEN_REFRESH_INTERVAL: Duration = Duration::from_secs(12*60*60);
+// Failed refreshes are retried after this delay which doubles after each failure
+const TOKEN_REFRESH_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum LoginState {
+    LoggedOut,
+    LoggedIn,
+    // The token was rejected and we couldn't login again
+    Expired,
+    // The provider can be used without any credentials
+    NotRequired,
+}
+
+impl LoginState {
+    pub fn iterator() -> std::slice::Iter<'static, Self> {
+        static STATES: [LoginState;4] = [
+            LoginState::LoggedOut,
+            LoginState::LoggedIn,
+            LoginState::Expired,
+            LoginState::NotRequired,
+        ];
+        STATES.iter()
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            LoginState::LoggedOut => "Logged out",
+            LoginState::LoggedIn => "Logged in",
+            LoginState::Expired => "Session expired",
+            LoginState::NotRequired => "No login required",
+        }
+    }
+}
+
+fn get_login_state(provider: Option<&Arc<dyn MetadataProvider>>) -> LoginState {
+    match provider {
+        None => LoginState::LoggedOut,
+        Some(provider) if !provider.get_kind().is_login_required() => LoginState::NotRequired,
+        Some(provider) if !provider.is_ready() => LoginState::Expired,
+        Some(_) => LoginState::LoggedIn,
+    }
+}
+
+pub struct SeriesMatchProposal {
+    pub folder: Arc<AppFolder>,
+    pub query: String,
+    pub result: SeriesMatch,
+    pub is_enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchExecutionSummary {
+    pub total_folders: usize,
+    pub skipped_busy_folders: usize,
+    pub summary: ExecutionSummary,
+}
+
+impl std::fmt::Display for BatchExecutionSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Executed {} folders: {}", self.total_folders, self.summary)?;
+        if self.skipped_busy_folders > 0 {
+            write!(f, ", skipped {} busy folders", self.skipped_busy_folders)?;
+        }
+        Ok(())
+    }
+}
+
+// Folders are all queued when a scan over many folders starts and is reset once every folder is completed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanProgress {
+    pub total_queued: usize,
+    pub total_started: usize,
+    pub total_completed: usize,
+}
+
+impl ScanProgress {
+    pub fn is_idle(&self) -> bool {
+        self.total_queued == 0
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -24,64 +134,231 @@ pub enum AppInitError {
     IOFilterRulesLoad(std::io::Error),
     #[error("json decode on filter rules: {}", .0)]
     JsonDecodeFilterRules(serde_json::Error),
+    #[error("invalid glob in filter rules: {}", .0)]
+    InvalidGlob(InvalidGlobError),
+    #[error("failed to create http client: {}", .0)]
+    HttpClient(reqwest::Error),
 }
 
 pub struct App {
-    filter_rules: Arc<FilterRules>,
+    filter_rules: RwLock<Arc<FilterRules>>,
     config_path: String,
+    settings: RwLock<AppSettings>,
 
     client: Arc<reqwest::Client>,
+    session_options: SessionOptions,
     login_session: RwLock<Option<Arc<LoginSession>>>,
+    // Used for all metadata requests, which is the login session unless another provider was set
+    provider: RwLock<Option<Arc<dyn MetadataProvider>>>,
     
     root_path: RwLock<String>,
     folders: RwLock<Vec<Arc<AppFolder>>>,
-    selected_folder_index: RwLock<Option<usize>>,
+    series_mapping: RwLock<SeriesMapping>,
+    cache_registry: Arc<SeriesCacheRegistry>,
+    // Folders are selected by their path so the selection survives the folder list changing
+    selected_folder_path: RwLock<Option<String>>,
     folders_busy_lock: Mutex<()>,
+    change_sender: broadcast::Sender<ChangeEvent>,
+    last_execution_summary: RwLock<Option<BatchExecutionSummary>>,
+    // Only scans over many folders are tracked, scans of a single folder don't change this
+    scan_progress: watch::Sender<ScanProgress>,
+    watcher: Mutex<Option<FolderWatcher>>,
+    token_refresher: Mutex<Option<tokio::task::JoinHandle<()>>>,
 
     series: RwLock<Option<Vec<Series>>>,
     selected_series_index: RwLock<Option<usize>>,
     series_busy_lock: Mutex<()>,
 
-    errors: RwLock<Vec<String>>,
+    series_matches: RwLock<Option<Vec<SeriesMatchProposal>>>,
+    series_matches_busy_lock: Mutex<()>,
+
+    errors: RwLock<ErrorLog>,
 }
 
 impl App {
     pub async fn new(config_path: &str) -> Result<App, AppInitError> {
         let filter_rules_str = tokio::fs::read_to_string(format!("{}/app_config.json", config_path)).await;
         let filter_rules_str = filter_rules_str.map_err(AppInitError::IOFilterRulesLoad)?;
-        let filter_rules: FilterRules = serde_json::from_str(filter_rules_str.as_str())
+        let mut filter_rules: FilterRules = serde_json::from_str(filter_rules_str.as_str())
             .map_err(AppInitError::JsonDecodeFilterRules)?;
+        filter_rules.compile_globs().map_err(AppInitError::InvalidGlob)?;
+
+        // Settings are optional so we start with the defaults if they can't be loaded
+        let mut errors = ErrorLog::new();
+        let settings = match load_settings_from_file(get_settings_path(config_path).as_str()).await {
+            Ok(settings) => settings,
+            Err(err) => {
+                errors.push_warn("Settings", format!("Failed to load app settings from file: {}", err));
+                AppSettings::default()
+            },
+        };
+        if let Some(use_trash) = settings.use_trash {
+            filter_rules.use_trash = use_trash;
+        }
+        if let Some(folder_scan_depth) = settings.folder_scan_depth {
+            filter_rules.folder_scan_depth = folder_scan_depth;
+        }
+        // The app is still usable out of the box with a provider that doesn't need credentials
+        let is_credentials_saved = Path::new(get_credentials_path(config_path).as_str()).exists();
+        if !is_credentials_saved && filter_rules.provider.is_login_required() {
+            filter_rules.provider = ProviderKind::TvMaze;
+        }
+        let client = Arc::new(tvdb::api::build_client().map_err(AppInitError::HttpClient)?);
+        let provider: Option<Arc<dyn MetadataProvider>> = match filter_rules.provider {
+            ProviderKind::TvMaze => Some(Arc::new(TvMazeSession::new(client.clone()))),
+            _ => None,
+        };
 
         Ok(App {
-            filter_rules: Arc::new(filter_rules),
+            filter_rules: RwLock::new(Arc::new(filter_rules)),
             config_path: config_path.to_string(),
+            settings: RwLock::new(settings),
 
-            client: Arc::new(reqwest::Client::new()),
+            client,
+            session_options: SessionOptions::default(),
             login_session: RwLock::new(None),
+            provider: RwLock::new(provider),
             
             root_path: RwLock::new(".".to_string()),
             folders: RwLock::new(Vec::new()),
-            selected_folder_index: RwLock::new(None),
+            series_mapping: RwLock::new(SeriesMapping::new()),
+            cache_registry: Arc::new(SeriesCacheRegistry::new()),
+            selected_folder_path: RwLock::new(None),
             folders_busy_lock: Mutex::new(()),
+            change_sender: broadcast::channel(CHANGE_EVENT_CAPACITY).0,
+            last_execution_summary: RwLock::new(None),
+            scan_progress: watch::Sender::new(ScanProgress::default()),
+            watcher: Mutex::new(None),
+            token_refresher: Mutex::new(None),
 
             series: RwLock::new(None),
             selected_series_index: RwLock::new(None),
             series_busy_lock: Mutex::new(()),
 
-            errors: RwLock::new(Vec::new()),
+            series_matches: RwLock::new(None),
+            series_matches_busy_lock: Mutex::new(()),
+
+            errors: RwLock::new(errors),
         })
     }
 }
 
+fn get_credentials_path(config_path: &str) -> String {
+    format!("{}/credentials.json", config_path)
+}
+
+fn get_settings_path(config_path: &str) -> String {
+    format!("{}/app_settings.json", config_path)
+}
+
+// Folders of the same series share the cache that was fetched first instead of fetching it again
+// Only folders whose series is in updated_ids are refreshed if it is given
+async fn refresh_folder_caches(
+    provider: &Arc<dyn MetadataProvider>, folders: &[Arc<AppFolder>], updated_ids: Option<&HashSet<u32>>,
+) -> bool {
+    let mut is_all_refreshed = true;
+    let mut refreshed_keys = HashSet::<SeriesCacheKey>::new();
+    for folder in folders.iter() {
+        // Caches from another api can't be refreshed with this one
+        let series_id = match folder.get_cache().read().await.as_ref() {
+            Some(cache) if cache.get_provider() == provider.get_kind() => cache.series.id,
+            _ => continue,
+        };
+        if updated_ids.is_some_and(|ids| !ids.contains(&series_id)) {
+            continue;
+        }
+        let key = folder.get_loaded_cache_key().await;
+        let is_shared = match key.as_ref() {
+            Some(key) if refreshed_keys.contains(key) => folder.load_cache_from_registry().await.is_some(),
+            _ => false,
+        };
+        if !is_shared {
+            if folder.refresh_cache_from_api(provider.clone()).await.is_none() {
+                is_all_refreshed = false;
+                continue;
+            }
+            refreshed_keys.extend(key);
+        }
+        tokio::join!(
+            folder.update_file_intents(),
+            folder.save_cache_to_file(),
+        );
+    }
+    is_all_refreshed
+}
+
+// Series ids from each api are mapped separately since they refer to different series
+fn get_series_mapping_path(root_path: &str, provider: ProviderKind) -> String {
+    let filename = match provider {
+        ProviderKind::Tvdb => PATH_STR_SERIES_MAPPING,
+        ProviderKind::Tmdb => PATH_STR_SERIES_MAPPING_TMDB,
+        ProviderKind::TvMaze => PATH_STR_SERIES_MAPPING_TVMAZE,
+    };
+    Path::new(root_path)
+        .join(filename)
+        .to_string_lossy()
+        .to_string()
+}
+
+fn get_series_label(series: &Series) -> String {
+    format!("{} ({})", series.get_display_name(), series.first_aired.as_deref().unwrap_or("Unknown"))
+}
+
+fn get_watch_target(path: &Path, root_path: &Path, folder_scan_depth: usize, folders: &[Arc<AppFolder>]) -> Option<WatchTarget> {
+    for folder in folders {
+        let folder_path = Path::new(folder.get_folder_path());
+        // The folder itself was removed or renamed
+        if path == folder_path && !path.exists() {
+            return Some(WatchTarget::RemoveFolder(folder.get_folder_path().to_string()));
+        }
+        if path.starts_with(folder_path) {
+            return Some(WatchTarget::Folder(folder.get_folder_path().to_string()));
+        }
+    }
+    // New folders only show up as directories outside of the existing folders
+    if !path.is_dir() {
+        return None;
+    }
+    let relative_path = match path.strip_prefix(root_path) {
+        Ok(relative_path) => relative_path,
+        Err(_) => return Some(WatchTarget::Root),
+    };
+    // Directories above the scan depth can contain any number of new folders
+    if relative_path.components().count() < folder_scan_depth {
+        return Some(WatchTarget::Root);
+    }
+    // Directories created inside of a new folder belong to that folder
+    let folder_path = relative_path
+        .components()
+        .take(folder_scan_depth)
+        .fold(root_path.to_path_buf(), |path, component| path.join(component));
+    folder_path.to_str().map(|path| WatchTarget::AddFolder(path.to_string()))
+}
+
+fn get_token_refresh_delay(total_failures: u32) -> Duration {
+    match total_failures {
+        0 => TOKEN_REFRESH_INTERVAL,
+        _ => TOKEN_REFRESH_RETRY_DELAY
+            .saturating_mul(2u32.saturating_pow(total_failures - 1))
+            .min(TOKEN_REFRESH_INTERVAL),
+    }
+}
+
 impl App {
     pub async fn login(&self) -> Option<()> {
-        let credentials_str = tokio::fs::read_to_string(format!("{}/credentials.json", self.config_path.as_str())).await;
+        if !self.get_provider_kind().await.is_login_required() {
+            self.set_login_session(None).await;
+            self.set_provider(Some(Arc::new(TvMazeSession::new(self.client.clone())))).await;
+            return Some(());
+        }
+
+        let credentials_str = tokio::fs::read_to_string(self.get_credentials_path()).await;
         
         let credentials_str = match credentials_str {
             Ok(data) => data,
             Err(err) => {
                 let message = format!("Login failed since credentials could not be loaded from file: {}", err);
-                self.errors.write().await.push(message);
+                self.errors.write().await.push_error("Login", message);
                 return None;
             },
         };
@@ -89,28 +366,296 @@ impl App {
         let credentials: Credentials = match serde_json::from_str(credentials_str.as_str()) {
             Ok(data) => data,
             Err(err) => {
-                let message = format!("Login failed since credentials could not be deserialised from json: {}", err);
-                self.errors.write().await.push(message);
+                let message = format!("Login failed since credentials file is malformed: {}", err);
+                self.errors.write().await.push_error("Login", message);
+                return None;
+            },
+        };
+
+        if self.get_provider_kind().await == ProviderKind::Tmdb {
+            let api_key = match credentials.tmdb_api_key {
+                Some(api_key) => api_key,
+                None => {
+                    self.errors.write().await.push_error("Login", "Login failed since credentials are missing the tmdb api key");
+                    return None;
+                },
+            };
+            return self.login_with_api_key(api_key.as_str(), false).await;
+        }
+
+        let login_info = match credentials.login_info.clone() {
+            Some(login_info) => login_info,
+            None => {
+                self.errors.write().await.push_error("Login", "Login failed since credentials are missing the tvdb login info");
                 return None;
             },
         };
-        let token = tvdb::api::login(self.client.as_ref(), &credentials.login_info).await;
+        if login_info.apikey.is_empty() {
+            self.errors.write().await.push_error("Login", "Login failed since credentials are missing the tvdb api key");
+            return None;
+        }
+        if let Some(token) = credentials.token.clone() {
+            let res = self.login_with_token(&credentials, &login_info, token).await;
+            if res.is_some() {
+                return Some(());
+            }
+        }
+        // The stored token couldn't be refreshed so we login from scratch and store the new token
+        self.login_with(login_info, true).await
+    }
+
+    // Refreshing a stored token validates it and extends its expiry
+    async fn login_with_token(&self, credentials: &Credentials, login_info: &LoginInfo, token: String) -> Option<()> {
+        let session = LoginSession::new(self.client.clone(), &LoginToken { token }, Some(login_info.clone()), self.session_options.clone());
+        session.refresh_token().await.ok()?;
+        let credentials = Credentials {
+            token: Some(session.get_token().await.token),
+            ..credentials.clone()
+        };
+        self.set_login_session(Some(Arc::new(session))).await;
+        self.save_credentials_to_file(&credentials).await
+    }
+
+    // Credentials are written to file with the new token if they are remembered
+    pub async fn login_with(&self, login_info: LoginInfo, is_remember: bool) -> Option<()> {
+        let token = tvdb::api::login(self.client.as_ref(), self.session_options.base_url.as_str(), &login_info).await;
         let token = match token {
             Ok(token) => token,
             Err(err) => {
-                let message = format!("Login failed at tvdb api: {}", err);
-                self.errors.write().await.push(message);
+                let message = match err.is_unauthorized() && login_info.is_key_only() {
+                    // Older api keys still require the user key and username
+                    true => format!("Login failed at tvdb api with only an api key, the user key and username may be required: {}", err),
+                    false => format!("Login failed at tvdb api: {}", err),
+                };
+                self.errors.write().await.push_error("Login", message);
                 // If login failed at this point it's possible credentials were invalidated externally
-                *self.login_session.write().await = None;
+                self.set_login_session(None).await;
+                return None;
+            },
+        };
+
+        let session = LoginSession::new(self.client.clone(), &token, Some(login_info.clone()), self.session_options.clone());
+        self.set_login_session(Some(Arc::new(session))).await;
+
+        if is_remember {
+            // Keep the tmdb api key so switching between apis doesn't require typing it in again
+            let tmdb_api_key = self.load_credentials_from_file().await.and_then(|credentials| credentials.tmdb_api_key);
+            let credentials = Credentials {
+                login_info: Some(login_info),
+                token: Some(token.token),
+                tmdb_api_key,
+            };
+            self.save_credentials_to_file(&credentials).await?;
+        }
+        Some(())
+    }
+
+    // The api key is checked with a request since tmdb has no login
+    pub async fn login_with_api_key(&self, api_key: &str, is_remember: bool) -> Option<()> {
+        let session = TmdbSession::new(self.client.clone(), api_key);
+        if let Err(err) = session.get_configuration().await {
+            let message = format!("Login failed at tmdb api: {}", err);
+            self.errors.write().await.push_error("Login", message);
+            self.set_login_session(None).await;
+            return None;
+        }
+        // Clear the tvdb session so the token refresh doesn't keep it alive
+        self.set_login_session(None).await;
+        self.set_provider(Some(Arc::new(session))).await;
+
+        if is_remember {
+            let credentials = match self.load_credentials_from_file().await {
+                Some(credentials) => Credentials {
+                    tmdb_api_key: Some(api_key.to_string()),
+                    ..credentials
+                },
+                None => Credentials {
+                    login_info: None,
+                    token: None,
+                    tmdb_api_key: Some(api_key.to_string()),
+                },
+            };
+            self.save_credentials_to_file(&credentials).await?;
+        }
+        Some(())
+    }
+
+    // Requests are sent to a local server instead of the apis
+    #[cfg(test)]
+    pub(crate) fn set_test_server(&mut self, client: Arc<reqwest::Client>, session_options: SessionOptions) {
+        self.client = client;
+        self.session_options = session_options;
+    }
+
+    async fn set_login_session(&self, session: Option<Arc<LoginSession>>) {
+        let (mut login_session, mut provider) = tokio::join!(
+            self.login_session.write(),
+            self.provider.write(),
+        );
+        *provider = session.clone().map(|session| session as Arc<dyn MetadataProvider>);
+        *login_session = session;
+    }
+
+    // Replaces the login session as the source of metadata, i.e. with another api
+    pub async fn set_provider(&self, provider: Option<Arc<dyn MetadataProvider>>) {
+        *self.provider.write().await = provider;
+    }
+
+    pub fn get_provider(&self) -> &RwLock<Option<Arc<dyn MetadataProvider>>> {
+        &self.provider
+    }
+
+    pub fn is_provider_ready_blocking(&self) -> bool {
+        self.provider.blocking_read().as_ref().is_some_and(|provider| provider.is_ready())
+    }
+
+    pub async fn is_provider_ready(&self) -> bool {
+        self.provider.read().await.as_ref().is_some_and(|provider| provider.is_ready())
+    }
+
+    pub fn get_rate_limit_delay_blocking(&self) -> Option<Duration> {
+        self.provider.blocking_read().as_ref().and_then(|provider| provider.get_rate_limit_delay())
+    }
+
+    // Keep the session alive by refreshing its token periodically
+    // This only needs to be started once since it waits for a session if there isn't one
+    pub async fn start_token_refresh(self: &Arc<Self>) {
+        let mut token_refresher = self.token_refresher.lock().await;
+        if token_refresher.is_some() {
+            return;
+        }
+        *token_refresher = Some(tokio::spawn({
+            let app = self.clone();
+            async move {
+                app.run_token_refresh().await
+            }
+        }));
+    }
+
+    async fn run_token_refresh(&self) {
+        let mut total_failures: u32 = 0;
+        loop {
+            tokio::time::sleep(get_token_refresh_delay(total_failures)).await;
+            total_failures = self.refresh_login_token(total_failures).await;
+        }
+    }
+
+    // Returns the number of refreshes that have failed in a row
+    async fn refresh_login_token(&self, total_failures: u32) -> u32 {
+        let session = match self.login_session.read().await.clone() {
+            Some(session) => session,
+            None => return 0,
+        };
+        // The session logs in again by itself if the token was rejected
+        match session.refresh_token().await {
+            Ok(()) => {
+                self.save_refreshed_token(&session).await;
+                0
+            },
+            Err(err) => {
+                let total_failures = total_failures+1;
+                let message = format!("Failed to refresh login token (attempt {}): {}", total_failures, err);
+                self.errors.write().await.push_warn("Login", message);
+                total_failures
+            },
+        }
+    }
+
+    // Missing or invalid credentials are treated as not being remembered
+    async fn load_credentials_from_file(&self) -> Option<Credentials> {
+        let credentials_str = tokio::fs::read_to_string(self.get_credentials_path()).await.ok()?;
+        serde_json::from_str(credentials_str.as_str()).ok()
+    }
+
+    // Only update the token if the credentials were remembered
+    async fn save_refreshed_token(&self, session: &LoginSession) -> Option<()> {
+        let mut credentials = self.load_credentials_from_file().await?;
+        credentials.token = Some(session.get_token().await.token);
+        self.save_credentials_to_file(&credentials).await
+    }
+
+    pub fn get_login_state_blocking(&self) -> LoginState {
+        get_login_state(self.provider.blocking_read().as_ref())
+    }
+
+    pub async fn get_login_state(&self) -> LoginState {
+        get_login_state(self.provider.read().await.as_ref())
+    }
+
+    pub fn get_folder_sort_mode_blocking(&self) -> FolderSortMode {
+        self.settings.blocking_read().folder_sort_mode.unwrap_or_default()
+    }
+
+    pub fn get_provider_kind_blocking(&self) -> ProviderKind {
+        self.filter_rules.blocking_read().provider
+    }
+
+    pub async fn get_provider_kind(&self) -> ProviderKind {
+        self.filter_rules.read().await.provider
+    }
+
+    async fn save_credentials_to_file(&self, credentials: &Credentials) -> Option<()> {
+        let credentials_str = match serde_json::to_string_pretty(credentials) {
+            Ok(data) => data,
+            Err(err) => {
+                let message = format!("Failed to save credentials since they could not be serialised to json: {}", err);
+                self.errors.write().await.push_error("Login", message);
+                return None;
+            },
+        };
+
+        if let Err(err) = atomic_write(self.get_credentials_path(), credentials_str).await {
+            let message = format!("Failed to save credentials to file: {}", err);
+            self.errors.write().await.push_error("Login", message);
+            return None;
+        }
+        Some(())
+    }
+
+    // Write the errors of the app and every folder to a single file that can be attached to bug reports
+    pub async fn save_errors_to_file(&self) -> Option<()> {
+        let report = {
+            let app_errors = self.errors.read().await;
+            let folders = self.folders.read().await;
+            let mut folder_errors = serde_json::Map::new();
+            for folder in folders.iter() {
+                let errors = folder.get_errors().read().await;
+                if errors.is_empty() {
+                    continue;
+                }
+                match serde_json::to_value(&*errors) {
+                    Ok(value) => folder_errors.insert(folder.get_folder_name().to_string(), value),
+                    Err(_) => continue,
+                };
+            }
+            serde_json::to_string_pretty(&serde_json::json!({
+                "app": &*app_errors,
+                "folders": folder_errors,
+            }))
+        };
+
+        let report = match report {
+            Ok(report) => report,
+            Err(err) => {
+                let message = format!("Failed to encode errors to json: {}", err);
+                self.errors.write().await.push_error("Error report", message);
                 return None;
             },
         };
 
-        let session = LoginSession::new(self.client.clone(), &token);
-        *self.login_session.write().await = Some(Arc::new(session));
+        let path = format!("{}/errors.json", self.config_path.as_str());
+        if let Err(err) = tokio::fs::write(path.as_str(), report).await {
+            let message = format!("Failed to write errors to '{}': {}", path, err);
+            self.errors.write().await.push_error("Error report", message);
+            return None;
+        }
         Some(())
     }
 
+    pub fn get_credentials_path(&self) -> String {
+        get_credentials_path(self.config_path.as_str())
+    }
+
     pub fn get_login_session(&self) -> &RwLock<Option<Arc<LoginSession>>> {
         &self.login_session
     }
@@ -121,17 +666,191 @@ impl App {
     }
 
     pub async fn load_folders(&self, root_path: String) -> Option<()> {
-        let _busy_lock = self.folders_busy_lock.lock().await;
+        let _busy_lock = self.lock_folders_busy().await;
         // NOTE: If for some reason the folder load failed we can still reattempt 
         *self.root_path.write().await = root_path.clone();
+        self.settings.write().await.push_root_path(root_path.as_str());
+        self.save_settings_to_file().await;
+
+        // Folders can still be loaded without their series mapping
+        let series_mapping_path = get_series_mapping_path(root_path.as_str(), self.get_provider_kind().await);
+        let series_mapping = match load_series_mapping_from_file(series_mapping_path.as_str()).await {
+            Ok(series_mapping) => series_mapping,
+            Err(err) => {
+                let message = format!("Failed to load series mapping from file: {}", err);
+                self.errors.write().await.push_warn("Series mapping", message);
+                SeriesMapping::new()
+            },
+        };
+
+        let filter_rules = self.filter_rules.read().await.clone();
+        let ignored_folder_globs = self.load_folder_ignore_file(root_path.as_str()).await;
+        // A depth of 0 means the root is the only folder
+        let mut folder_paths = vec![root_path.clone()];
+        for _ in 0..filter_rules.folder_scan_depth {
+            let mut sub_folder_paths = Vec::new();
+            for folder_path in folder_paths.iter() {
+                sub_folder_paths.extend(self.read_sub_folders(folder_path.as_str()).await?);
+            }
+            // Ignoring a folder also ignores any folders below it
+            sub_folder_paths.retain(|path| {
+                let folder_name = get_relative_folder_name(root_path.as_str(), path.as_str());
+                !filter_rules.is_folder_ignored(folder_name.as_str()) && !ignored_folder_globs.is_match(folder_name.as_str())
+            });
+            folder_paths = sub_folder_paths;
+        }
 
+        // Folders that are loaded the same way are kept so their files and running operations aren't lost
+        let existing_folders: HashMap<String, Arc<AppFolder>> = self.folders.read().await
+            .iter()
+            .map(|folder| (folder.get_folder_path().to_string(), folder.clone()))
+            .collect();
         let mut new_folders = Vec::new();
-        let entries = tokio::fs::read_dir(root_path.as_str()).await; 
+        for path in folder_paths.iter() {
+            let folder_name = get_relative_folder_name(root_path.as_str(), path.as_str());
+            let mapped_series_id = series_mapping.get(folder_name.as_str()).copied();
+            let existing_folder = existing_folders.get(path.as_str()).filter(|folder| {
+                folder.get_folder_name() == folder_name &&
+                folder.get_mapped_series_id() == mapped_series_id &&
+                std::ptr::eq(folder.get_filter_rules(), filter_rules.as_ref())
+            });
+            if let Some(folder) = existing_folder {
+                new_folders.push(folder.clone());
+                continue;
+            }
+            let mut folder = AppFolder::new(root_path.as_str(), path.as_str(), filter_rules.clone());
+            folder.set_mapped_series_id(mapped_series_id);
+            folder.set_change_sender(self.change_sender.clone());
+            folder.set_cache_registry(self.cache_registry.clone());
+            new_folders.push(Arc::new(folder));
+        }
+        
+        new_folders.sort_by(|a, b| {
+            let a = a.as_ref();
+            let b = b.as_ref();
+            let a_name = a.get_folder_name();
+            let b_name = b.get_folder_name();
+            a_name.partial_cmp(b_name).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let (mut folders, mut selected_folder_path) = tokio::join!(
+            self.folders.write(),
+            self.selected_folder_path.write(),
+        );
+        *folders = new_folders;
+        let is_selection_kept = selected_folder_path
+            .as_deref()
+            .map(|path| folders.iter().any(|folder| folder.get_folder_path() == path))
+            .unwrap_or(false);
+        if !is_selection_kept {
+            *selected_folder_path = None;
+        }
+        *self.series_mapping.write().await = series_mapping;
+        self.notify_change(ChangeKind::Folders);
+        Some(())
+    }
+
+    // Folders can't be added while all of them are being loaded or updated
+    pub async fn add_folder(&self, folder_path: &str) -> Option<()> {
+        let folder = {
+            let _busy_lock = match self.folders_busy_lock.try_lock() {
+                Ok(lock) => lock,
+                Err(_) => {
+                    let message = format!("Cannot add folder '{}' while folders are busy", folder_path);
+                    self.errors.write().await.push_warn("Add folder", message);
+                    return None;
+                },
+            };
+
+            if !matches!(tokio::fs::metadata(folder_path).await, Ok(metadata) if metadata.is_dir()) {
+                let message = format!("Cannot add folder '{}' since it isn't a directory", folder_path);
+                self.errors.write().await.push_error("Add folder", message);
+                return None;
+            }
+
+            let root_path = self.root_path.read().await.clone();
+            let filter_rules = self.filter_rules.read().await.clone();
+            let folder_name = get_relative_folder_name(root_path.as_str(), folder_path);
+            let ignored_folder_globs = self.load_folder_ignore_file(root_path.as_str()).await;
+            if filter_rules.is_folder_ignored(folder_name.as_str()) || ignored_folder_globs.is_match(folder_name.as_str()) {
+                return Some(());
+            }
+            let mut folder = AppFolder::new(root_path.as_str(), folder_path, filter_rules);
+            folder.set_mapped_series_id(self.get_mapped_series_id(folder.get_folder_name()).await);
+            folder.set_change_sender(self.change_sender.clone());
+            folder.set_cache_registry(self.cache_registry.clone());
+            let folder = Arc::new(folder);
+
+            let mut folders = self.folders.write().await;
+            let index = match folders.binary_search_by(|other| other.get_folder_name().cmp(folder.get_folder_name())) {
+                Ok(_) => return Some(()),
+                Err(index) => index,
+            };
+            folders.insert(index, folder.clone());
+            folder
+        };
+        self.notify_change(ChangeKind::Folders);
+
+        let provider = self.provider.read().await.clone();
+        folder.perform_initial_load(provider).await;
+        Some(())
+    }
+
+    pub async fn remove_folder(&self, folder_path: &str) -> Option<()> {
+        {
+            let _busy_lock = match self.folders_busy_lock.try_lock() {
+                Ok(lock) => lock,
+                Err(_) => {
+                    let message = format!("Cannot remove folder '{}' while folders are busy", folder_path);
+                    self.errors.write().await.push_warn("Remove folder", message);
+                    return None;
+                },
+            };
+
+            let (mut folders, mut selected_folder_path) = tokio::join!(
+                self.folders.write(),
+                self.selected_folder_path.write(),
+            );
+            let index = folders.iter().position(|folder| folder.get_folder_path() == folder_path)?;
+            folders.remove(index);
+            if selected_folder_path.as_deref() == Some(folder_path) {
+                *selected_folder_path = None;
+            }
+        }
+        self.notify_change(ChangeKind::Folders);
+        Some(())
+    }
+
+    // A missing or invalid ignore file doesn't stop the folders from being loaded
+    async fn load_folder_ignore_file(&self, root_path: &str) -> globset::GlobSet {
+        let path = Path::new(root_path).join(PATH_STR_FOLDER_IGNORE);
+        let data = match tokio::fs::read_to_string(path).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return globset::GlobSet::empty(),
+            Err(err) => {
+                let message = format!("Failed to read {}: {}", PATH_STR_FOLDER_IGNORE, err);
+                self.errors.write().await.push_warn("Load folders", message);
+                return globset::GlobSet::empty();
+            },
+        };
+        match build_folder_ignore_globs(&parse_folder_ignore_file(data.as_str())) {
+            Ok(globs) => globs,
+            Err(err) => {
+                let message = format!("Invalid {}: {}", PATH_STR_FOLDER_IGNORE, err);
+                self.errors.write().await.push_warn("Load folders", message);
+                globset::GlobSet::empty()
+            },
+        }
+    }
+
+    async fn read_sub_folders(&self, root_path: &str) -> Option<Vec<String>> {
+        let mut sub_folders = Vec::new();
+        let entries = tokio::fs::read_dir(root_path).await; 
         let mut entries = match entries {
             Ok(entries) => entries,
             Err(err) => {
-                let message = format!("Error on loading folders from '{}': {}", root_path.as_str(), err);
-                self.errors.write().await.push(message);
+                let message = format!("Error on loading folders from '{}': {}", root_path, err);
+                self.errors.write().await.push_error("Load folders", message);
                 return None;
             },
         };
@@ -140,8 +859,8 @@ impl App {
             let entry_opt = match entries.next_entry().await {
                 Ok(entry_opt) => entry_opt,
                 Err(err) => {
-                    let message = format!("Error during iteraton when getting next entry from folder '{}': {}", root_path.as_str(), err);
-                    self.errors.write().await.push(message);
+                    let message = format!("Error during iteraton when getting next entry from folder '{}': {}", root_path, err);
+                    self.errors.write().await.push_error("Load folders", message);
                     return None;
                 },
             };
@@ -155,9 +874,9 @@ impl App {
             let file_type = match entry.file_type().await {
                 Ok(file_type) => file_type,
                 Err(err) => {
-                    let path_str = path.to_str().unwrap_or(root_path.as_str());
+                    let path_str = path.to_str().unwrap_or(root_path);
                     let message = format!("Error during iteration when getting file type from folder '{}': {}", path_str, err);
-                    self.errors.write().await.push(message);
+                    self.errors.write().await.push_error("Load folders", message);
                     return None;
                 },
             };
@@ -167,50 +886,213 @@ impl App {
             }
 
             if let Some(path) = path.to_str() {
-                let folder = AppFolder::new(root_path.as_str(), path, self.filter_rules.clone());
-                new_folders.push(Arc::new(folder));
+                sub_folders.push(path.to_string());
             }
         }
-        
-        new_folders.sort_by(|a, b| {
-            let a = a.as_ref();
-            let b = b.as_ref();
-            let a_name = a.get_folder_name();
-            let b_name = b.get_folder_name();
-            a_name.partial_cmp(b_name).unwrap_or(std::cmp::Ordering::Equal)
-        });
+        Some(sub_folders)
+    }
 
-        let (mut folders, mut selected_folder_index) = tokio::join!(
-            self.folders.write(),
-            self.selected_folder_index.write(),
-        );
-        *folders = new_folders;
-        *selected_folder_index = None;
-        Some(())
+    pub async fn get_mapped_series_id(&self, folder_name: &str) -> Option<u32> {
+        self.series_mapping.read().await.get(folder_name).copied()
     }
 
-    pub async fn update_search_series(&self, search: String) -> Option<()> {
-        let _busy_lock = self.series_busy_lock.lock().await;
-        let login_session = self.login_session.read().await;
-        let session = match login_session.as_ref() {
-            Some(session) => session,
-            None => {
-                let message = "Login session is required to update the series search results";
-                self.errors.write().await.push(message.to_string());
-                return None;
-            },
-        };
-        let search_results = match session.search_series(&search).await {
-            Ok(results) => results,
-            Err(err) => {
-                let message = format!("Failed to get series search results due to api error: {}", err);
-                self.errors.write().await.push(message);
-                return None;
-            },
-        };
+    // The mapping is saved immediately so it survives the folder's cache being deleted
+    pub async fn set_series_mapping(&self, folder_name: &str, series_id: u32) -> Option<()> {
+        // Hold the lock while saving so concurrent updates don't overwrite each other's file
+        let mut series_mapping = self.series_mapping.write().await;
+        if series_mapping.get(folder_name) == Some(&series_id) {
+            return Some(());
+        }
+        series_mapping.insert(folder_name.to_string(), series_id);
 
-        let (mut series, mut series_index) = tokio::join!(
-            self.series.write(),
+        let root_path = self.root_path.read().await.clone();
+        let series_mapping_path = get_series_mapping_path(root_path.as_str(), self.get_provider_kind().await);
+        let res = save_series_mapping_to_file(series_mapping_path.as_str(), &series_mapping).await;
+        if let Err(err) = res {
+            let message = format!("Failed to save series mapping to file: {}", err);
+            self.errors.write().await.push_error("Series mapping", message);
+            return None;
+        }
+        Some(())
+    }
+
+    pub async fn save_settings_to_file(&self) -> Option<()> {
+        let settings = self.settings.read().await.clone();
+        let res = save_settings_to_file(get_settings_path(self.config_path.as_str()).as_str(), &settings).await;
+        if let Err(err) = res {
+            let message = format!("Failed to save app settings to file: {}", err);
+            self.errors.write().await.push_error("Settings", message);
+            return None;
+        }
+        Some(())
+    }
+
+    // Changes to the filter rules only apply to folders on the next load
+    pub async fn set_use_trash(&self, use_trash: bool) -> Option<()> {
+        Arc::make_mut(&mut *self.filter_rules.write().await).use_trash = use_trash;
+        self.settings.write().await.use_trash = Some(use_trash);
+        self.save_settings_to_file().await
+    }
+
+    pub async fn set_folder_scan_depth(&self, folder_scan_depth: usize) -> Option<()> {
+        Arc::make_mut(&mut *self.filter_rules.write().await).folder_scan_depth = folder_scan_depth;
+        self.settings.write().await.folder_scan_depth = Some(folder_scan_depth);
+        self.save_settings_to_file().await
+    }
+
+    pub async fn set_folder_sort_mode(&self, folder_sort_mode: FolderSortMode) -> Option<()> {
+        self.settings.write().await.folder_sort_mode = Some(folder_sort_mode);
+        self.save_settings_to_file().await
+    }
+
+    // Folders are rescanned when files in them change on disk
+    pub async fn start_watching(self: &Arc<Self>) -> Option<()> {
+        let root_path = self.root_path.read().await.clone();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+        });
+        let mut watcher = match watcher {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                let message = format!("Failed to create filesystem watcher: {}", err);
+                self.errors.write().await.push_error("Watcher", message);
+                return None;
+            },
+        };
+        if let Err(err) = watcher.watch(Path::new(root_path.as_str()), notify::RecursiveMode::Recursive) {
+            let message = format!("Failed to watch root path '{}': {}", root_path, err);
+            self.errors.write().await.push_error("Watcher", message);
+            return None;
+        }
+
+        let task = tokio::spawn({
+            let app = self.clone();
+            async move {
+                app.run_watcher(rx).await
+            }
+        });
+        *self.watcher.lock().await = Some(FolderWatcher::new(watcher, task));
+        Some(())
+    }
+
+    pub async fn stop_watching(&self) {
+        *self.watcher.lock().await = None;
+    }
+
+    pub fn is_watching_blocking(&self) -> bool {
+        self.watcher.blocking_lock().is_some()
+    }
+
+    async fn run_watcher(&self, mut rx: tokio::sync::mpsc::UnboundedReceiver<notify::Result<notify::Event>>) {
+        let mut debouncer = EventDebouncer::new(WATCH_DEBOUNCE_DELAY);
+        loop {
+            let deadline = debouncer.get_next_deadline();
+            tokio::select! {
+                res = rx.recv() => match res {
+                    Some(Ok(event)) => self.push_watch_event(&mut debouncer, &event).await,
+                    Some(Err(err)) => {
+                        let message = format!("Filesystem watcher error: {}", err);
+                        self.errors.write().await.push_error("Watcher", message);
+                    },
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now).into()), if deadline.is_some() => {},
+            }
+
+            for target in debouncer.pop_ready(Instant::now()) {
+                // Busy folders are checked again later so we don't rescan in the middle of an operation
+                if !self.update_watch_target(&target).await {
+                    debouncer.push(target, Instant::now());
+                }
+            }
+        }
+    }
+
+    async fn push_watch_event(&self, debouncer: &mut EventDebouncer<WatchTarget>, event: &notify::Event) {
+        if is_ignored_watch_event(event) {
+            return;
+        }
+        let root_path = self.root_path.read().await.clone();
+        let folder_scan_depth = self.filter_rules.read().await.folder_scan_depth;
+        let folders = self.folders.read().await;
+        let now = Instant::now();
+        for path in event.paths.iter() {
+            if is_app_metadata_path(path) {
+                continue;
+            }
+            if let Some(target) = get_watch_target(path, Path::new(root_path.as_str()), folder_scan_depth, &folders) {
+                debouncer.push(target, now);
+            }
+        }
+    }
+
+    // Returns false if the target was busy and should be updated later
+    async fn update_watch_target(&self, target: &WatchTarget) -> bool {
+        match target {
+            WatchTarget::Root => {
+                if self.folders_busy_lock.try_lock().is_err() {
+                    return false;
+                }
+                self.load_folders_from_existing_root_path().await;
+            },
+            WatchTarget::AddFolder(folder_path) => {
+                if self.folders_busy_lock.try_lock().is_err() {
+                    return false;
+                }
+                self.add_folder(folder_path.as_str()).await;
+            },
+            WatchTarget::RemoveFolder(folder_path) => {
+                if self.folders_busy_lock.try_lock().is_err() {
+                    return false;
+                }
+                self.remove_folder(folder_path.as_str()).await;
+            },
+            WatchTarget::Folder(folder_path) => {
+                let folder = self.folders.read().await
+                    .iter()
+                    .find(|folder| folder.get_folder_path() == folder_path)
+                    .cloned();
+                let folder = match folder {
+                    Some(folder) => folder,
+                    None => return true,
+                };
+                if folder.get_busy_lock().try_lock().is_err() {
+                    return false;
+                }
+                // Folders without a cache are scanned when they are first loaded
+                if folder.get_cache().read().await.is_none() {
+                    return true;
+                }
+                folder.update_file_intents_incremental().await;
+            },
+        }
+        true
+    }
+
+    pub async fn update_search_series(&self, search: String) -> Option<()> {
+        let _busy_lock = self.series_busy_lock.lock().await;
+        let provider = match self.provider.read().await.clone() {
+            Some(provider) => provider,
+            None => {
+                let message = "Login session is required to update the series search results";
+                self.errors.write().await.push_warn("Series search", message);
+                return None;
+            },
+        };
+        let language = self.filter_rules.read().await.language.clone();
+        let search_results = match provider.search(search.as_str(), language.as_deref()).await {
+            Ok(results) => results,
+            Err(err) => {
+                let message = get_rate_limit_message(provider.get_kind(), "searching for series", &err)
+                    .unwrap_or_else(|| format!("Failed to get series search results due to api error: {}", err));
+                self.errors.write().await.push_error("Series search", message);
+                return None;
+            },
+        };
+
+        let (mut series, mut series_index) = tokio::join!(
+            self.series.write(),
             self.selected_series_index.write(),
         );
         *series = Some(search_results);
@@ -218,21 +1100,315 @@ impl App {
         Some(())
     }
 
+    // Load the series into the selected folder and remember it for the next time the folder is loaded
+    pub async fn set_series_to_current_folder(&self, series_id: u32) -> Option<()> {
+        let folder = match self.get_selected_folder().await {
+            Some(folder) => folder,
+            None => {
+                self.errors.write().await.push_warn("Series search", "A folder must be selected to set its series");
+                return None;
+            },
+        };
+        let provider = match self.provider.read().await.clone() {
+            Some(provider) => provider,
+            None => {
+                self.errors.write().await.push_warn("Series search", "Login session is required to set the series of a folder");
+                return None;
+            },
+        };
+
+        folder.load_cache_from_api(provider, series_id).await?;
+        tokio::join!(
+            folder.update_file_intents(),
+            folder.save_cache_to_file(),
+            self.set_series_mapping(folder.get_folder_name(), series_id),
+        );
+        Some(())
+    }
+
+    // Propose a series for each unidentified folder so they can be confirmed before being applied
+    pub async fn update_series_matches(&self) -> Option<()> {
+        let _busy_lock = self.series_matches_busy_lock.lock().await;
+        let provider = match self.provider.read().await.clone() {
+            Some(provider) => provider,
+            None => {
+                let message = "Login session is required to automatically match series";
+                self.errors.write().await.push_warn("Auto match", message);
+                return None;
+            },
+        };
+
+        let folders = self.folders.read().await.clone();
+        let proposals: Vec<Option<SeriesMatchProposal>> = stream::iter(folders)
+            .map(|folder| self.find_series_match(provider.clone(), folder))
+            .buffer_unordered(MAX_CONCURRENT_SERIES_SEARCHES)
+            .collect()
+            .await;
+        let mut proposals: Vec<SeriesMatchProposal> = proposals.into_iter().flatten().collect();
+        proposals.sort_by(|a, b| a.folder.get_folder_name().cmp(b.folder.get_folder_name()));
+        *self.series_matches.write().await = Some(proposals);
+        Some(())
+    }
+
+    async fn find_series_match(&self, provider: Arc<dyn MetadataProvider>, folder: Arc<AppFolder>) -> Option<SeriesMatchProposal> {
+        if !folder.is_unidentified().await {
+            return None;
+        }
+
+        let query = get_series_search_query(folder.get_folder_name());
+        let language = folder.get_language().await;
+        let search_results = match provider.search(query.as_str(), language.as_deref()).await {
+            Ok(results) => results,
+            Err(err) => {
+                let message = get_rate_limit_message(provider.get_kind(), "searching for series", &err)
+                    .unwrap_or_else(|| format!("Failed to search for series '{}' due to api error: {}", query, err));
+                folder.get_errors().write().await.push_error("Auto match", message);
+                return None;
+            },
+        };
+
+        let result = match_series(folder.get_folder_name(), search_results.as_slice());
+        let candidates = result.get_candidates()
+            .iter()
+            .map(get_series_label)
+            .collect::<Vec<String>>()
+            .join(", ");
+        let message = match result {
+            SeriesMatch::Matched(_) => None,
+            SeriesMatch::Ambiguous(_) => Some(format!("Couldn't automatically match series '{}' since multiple series are close: {}", query, candidates)),
+            SeriesMatch::NotFound(_) => Some(format!("Couldn't automatically match series '{}' to any search results: {}", query, candidates)),
+        };
+        if let Some(message) = message {
+            folder.get_errors().write().await.push_warn("Auto match", message);
+        }
+
+        let is_enabled = matches!(result, SeriesMatch::Matched(_));
+        Some(SeriesMatchProposal { folder, query, result, is_enabled })
+    }
+
+    // Load the cache for each enabled proposal that matched a single series
+    pub async fn apply_series_matches(&self) -> Option<()> {
+        let _busy_lock = self.series_matches_busy_lock.lock().await;
+        let provider = match self.provider.read().await.clone() {
+            Some(provider) => provider,
+            None => {
+                let message = "Login session is required to apply series matches";
+                self.errors.write().await.push_warn("Auto match", message);
+                return None;
+            },
+        };
+
+        let matches: Vec<(Arc<AppFolder>, u32)> = match self.series_matches.write().await.take() {
+            Some(proposals) => proposals
+                .into_iter()
+                .filter(|proposal| proposal.is_enabled)
+                .filter_map(|proposal| match proposal.result {
+                    SeriesMatch::Matched(series) => Some((proposal.folder, series.id)),
+                    _ => None,
+                })
+                .collect(),
+            None => return Some(()),
+        };
+
+        stream::iter(matches)
+            .map(|(folder, series_id)| {
+                let provider = provider.clone();
+                async move {
+                    folder.load_cache_from_api(provider, series_id).await?;
+                    tokio::join!(
+                        folder.update_file_intents(),
+                        folder.save_cache_to_file(),
+                        self.set_series_mapping(folder.get_folder_name(), series_id),
+                    );
+                    Some(())
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_SERIES_SEARCHES)
+            .collect::<Vec<Option<()>>>()
+            .await;
+        Some(())
+    }
+
+    pub async fn auto_match_unidentified_folders(&self) -> Option<()> {
+        self.update_series_matches().await?;
+        self.apply_series_matches().await
+    }
+
+    // Folders are returned in the order they are listed and paths that aren't loaded are ignored
+    async fn get_folders_by_paths(&self, folder_paths: &[String]) -> Vec<Arc<AppFolder>> {
+        let folder_paths: HashSet<&str> = folder_paths.iter().map(|path| path.as_str()).collect();
+        self.folders.read().await
+            .iter()
+            .filter(|folder| folder_paths.contains(folder.get_folder_path()))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn update_file_intents_for_folders(&self, folder_paths: &[String]) -> Option<()> {
+        let _busy_lock = self.lock_folders_busy().await;
+        let folders = self.get_folders_by_paths(folder_paths).await;
+        self.update_file_intents(folders).await;
+        Some(())
+    }
+
     pub async fn update_file_intents_for_all_folders(&self) -> Option<()> {
         // Allow the folder to be read while it is busy
         // Disallow load_folders(...) while we are performing an update on all folders
-        let _busy_lock = self.folders_busy_lock.lock().await;
-        {
-            let folders = self.folders.read().await;
-            for folder in folders.iter() {
-                let res = folder.perform_initial_load().await;
-                // Initial load already occured, we therefore just rescan the folder
-                if res.is_none() {
-                    folder.update_file_intents().await;
+        let _busy_lock = self.lock_folders_busy().await;
+        let folders = self.folders.read().await.clone();
+        self.update_file_intents(folders).await;
+        Some(())
+    }
+
+    async fn update_file_intents(&self, folders: Vec<Arc<AppFolder>>) {
+        let provider = self.provider.read().await.clone();
+        self.scan_progress.send_replace(ScanProgress {
+            total_queued: folders.len(),
+            ..ScanProgress::default()
+        });
+        stream::iter(folders)
+            .for_each_concurrent(MAX_CONCURRENT_FOLDER_SCANS, |folder| {
+                let provider = provider.clone();
+                async move {
+                    self.scan_progress.send_modify(|progress| progress.total_started += 1);
+                    // Initial load already occured, we therefore just rescan the folder for changes
+                    if folder.perform_initial_load(provider).await.is_none() {
+                        folder.update_file_intents_incremental().await;
+                    }
+                    self.scan_progress.send_modify(|progress| progress.total_completed += 1);
+                }
+            })
+            .await;
+        self.scan_progress.send_replace(ScanProgress::default());
+    }
+
+    // Only folders whose series was updated since the last check are fetched again
+    // Every loaded cache is fetched if we have never checked before
+    pub async fn refresh_stale_caches(&self) -> Option<()> {
+        let _busy_lock = self.lock_folders_busy().await;
+        let provider = match self.provider.read().await.clone() {
+            Some(provider) => provider,
+            None => {
+                self.errors.write().await.push_warn("Refresh updated", "Couldn't check for updated series since we aren't logged in");
+                return None;
+            },
+        };
+
+        // Taken before the query so updates made during the refresh are found next time
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let last_refresh_time = self.settings.read().await.last_cache_refresh_time;
+        let updated_ids: Option<HashSet<u32>> = match last_refresh_time {
+            None => None,
+            Some(from_time) => match provider.get_updated_series(from_time).await {
+                Ok(ids) => Some(ids.into_iter().collect()),
+                Err(err) => {
+                    let message = get_rate_limit_message(provider.get_kind(), "getting updated series", &err)
+                        .unwrap_or_else(|| format!("Failed to get updated series: {}", err));
+                    self.errors.write().await.push_error("Refresh updated", message);
+                    return None;
+                },
+            },
+        };
+
+        let folders = self.folders.read().await.clone();
+        let is_all_refreshed = refresh_folder_caches(&provider, folders.as_slice(), updated_ids.as_ref()).await;
+
+        // Failed folders are checked again next time
+        if !is_all_refreshed {
+            return None;
+        }
+        self.settings.write().await.last_cache_refresh_time = Some(now);
+        self.save_settings_to_file().await
+    }
+
+    // Every selected folder is fetched again even if its series wasn't updated
+    pub async fn refresh_caches_for_folders(&self, folder_paths: &[String]) -> Option<()> {
+        let _busy_lock = self.lock_folders_busy().await;
+        let provider = match self.provider.read().await.clone() {
+            Some(provider) => provider,
+            None => {
+                self.errors.write().await.push_warn("Refresh metadata", "Couldn't refresh the selected folders since we aren't logged in");
+                return None;
+            },
+        };
+        let folders = self.get_folders_by_paths(folder_paths).await;
+        refresh_folder_caches(&provider, folders.as_slice(), None).await.then_some(())
+    }
+
+    // Execute changes in every folder that has pending changes
+    pub async fn execute_all_pending_changes(&self) -> BatchExecutionSummary {
+        let _busy_lock = self.lock_folders_busy().await;
+        let folders = self.folders.read().await.clone();
+        self.execute_pending_changes(folders).await
+    }
+
+    pub async fn execute_pending_changes_for_folders(&self, folder_paths: &[String]) -> BatchExecutionSummary {
+        let _busy_lock = self.lock_folders_busy().await;
+        let folders = self.get_folders_by_paths(folder_paths).await;
+        self.execute_pending_changes(folders).await
+    }
+
+    async fn execute_pending_changes(&self, folders: Vec<Arc<AppFolder>>) -> BatchExecutionSummary {
+        let folders: Vec<Arc<AppFolder>> = folders
+            .into_iter()
+            .filter(|folder| folder.get_folder_status() == FolderStatus::Pending)
+            .collect();
+
+        // Busy folders are skipped instead of waiting on them since their changes might be outdated
+        let summaries: Vec<Option<ExecutionSummary>> = stream::iter(folders)
+            .map(|folder| async move {
+                if folder.get_busy_lock().try_lock().is_err() {
+                    return None;
                 }
+                Some(folder.execute_file_changes_and_rescan().await)
+            })
+            .buffer_unordered(MAX_CONCURRENT_FOLDER_EXECUTIONS)
+            .collect()
+            .await;
+
+        let mut batch_summary = BatchExecutionSummary::default();
+        for summary in summaries.iter() {
+            match summary {
+                Some(summary) => {
+                    batch_summary.total_folders += 1;
+                    batch_summary.summary += summary;
+                },
+                None => batch_summary.skipped_busy_folders += 1,
             }
         }
-        Some(())
+        *self.last_execution_summary.write().await = Some(batch_summary.clone());
+        batch_summary
+    }
+
+    pub fn get_scan_progress(&self) -> watch::Receiver<ScanProgress> {
+        self.scan_progress.subscribe()
+    }
+
+    pub fn get_last_execution_summary(&self) -> &RwLock<Option<BatchExecutionSummary>> {
+        &self.last_execution_summary
+    }
+
+    // Receives an event whenever the folders or their files change so they don't need to be polled
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.change_sender.subscribe()
+    }
+
+    fn notify_change(&self, kind: ChangeKind) {
+        send_change(Some(&self.change_sender), ChangeEvent {
+            folder_path: None,
+            kind,
+        });
+    }
+
+    async fn lock_folders_busy(&self) -> BusyLockGuard<'_> {
+        let event = ChangeEvent {
+            folder_path: None,
+            kind: ChangeKind::Busy,
+        };
+        BusyLockGuard::lock(&self.folders_busy_lock, Some(&self.change_sender), event).await
     }
 
     pub fn get_folders_busy_lock(&self) -> &Mutex<()> {
@@ -243,8 +1419,32 @@ impl App {
         &self.folders
     }
 
-    pub fn get_selected_folder_index(&self) -> &RwLock<Option<usize>> {
-        &self.selected_folder_index 
+    pub fn get_cache_registry(&self) -> &Arc<SeriesCacheRegistry> {
+        &self.cache_registry
+    }
+
+    pub fn get_selected_folder_path(&self) -> &RwLock<Option<String>> {
+        &self.selected_folder_path
+    }
+
+    pub fn get_folder_by_path_blocking(&self, folder_path: &str) -> Option<Arc<AppFolder>> {
+        let folders = self.folders.blocking_read();
+        folders.iter().find(|folder| folder.get_folder_path() == folder_path).cloned()
+    }
+
+    pub async fn get_folder_by_path(&self, folder_path: &str) -> Option<Arc<AppFolder>> {
+        let folders = self.folders.read().await;
+        folders.iter().find(|folder| folder.get_folder_path() == folder_path).cloned()
+    }
+
+    pub fn get_selected_folder_blocking(&self) -> Option<Arc<AppFolder>> {
+        let selected_folder_path = self.selected_folder_path.blocking_read().clone()?;
+        self.get_folder_by_path_blocking(selected_folder_path.as_str())
+    }
+
+    pub async fn get_selected_folder(&self) -> Option<Arc<AppFolder>> {
+        let selected_folder_path = self.selected_folder_path.read().await.clone()?;
+        self.get_folder_by_path(selected_folder_path.as_str()).await
     }
 
     pub fn get_series(&self) -> &RwLock<Option<Vec<Series>>> {
@@ -259,7 +1459,1019 @@ impl App {
         &self.series_busy_lock
     }
 
-    pub fn get_errors(&self) -> &RwLock<Vec<String>> {
+    pub fn get_series_matches(&self) -> &RwLock<Option<Vec<SeriesMatchProposal>>> {
+        &self.series_matches
+    }
+
+    pub fn get_series_matches_busy_lock(&self) -> &Mutex<()> {
+        &self.series_matches_busy_lock
+    }
+
+    pub fn get_root_path(&self) -> &RwLock<String> {
+        &self.root_path
+    }
+
+    pub fn get_settings(&self) -> &RwLock<AppSettings> {
+        &self.settings
+    }
+
+    pub fn get_errors(&self) -> &RwLock<ErrorLog> {
         &self.errors
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::file_intent::Action;
+    use crate::metadata_provider::MockProvider;
+    use crate::test_fixtures::{create_files, new_app, new_mock_provider, write_cache_file};
+    use crate::tvdb_cache::TvdbCache;
+    use tvdb::test_server::{TestResponse, TestServer, new_test_client};
+
+    async fn get_folder_paths(app: &App) -> Vec<String> {
+        app.get_folders().read().await.iter().map(|folder| folder.get_folder_path().to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn reload_keeps_selected_folder_when_sibling_is_added() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Alpha/a.mkv", "Charlie/c.mkv"]);
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let app = new_app(config_dir.path(), json!({})).await;
+
+        app.load_folders(root_path.clone()).await.unwrap();
+        let folder_paths = get_folder_paths(&app).await;
+        assert_eq!(folder_paths.len(), 2);
+        let selected_path = folder_paths[1].clone();
+        *app.get_selected_folder_path().write().await = Some(selected_path.clone());
+        let selected_folder = app.get_selected_folder().await.unwrap();
+
+        // The new sibling is sorted before the selected folder which changes its index
+        create_files(root_dir.path(), &["Bravo/b.mkv"]);
+        app.load_folders(root_path.clone()).await.unwrap();
+        let folder_paths = get_folder_paths(&app).await;
+        assert_eq!(folder_paths.len(), 3);
+        assert_eq!(folder_paths[2], selected_path);
+        assert_eq!(app.get_selected_folder_path().read().await.as_deref(), Some(selected_path.as_str()));
+        let reloaded_folder = app.get_selected_folder().await.unwrap();
+        assert!(Arc::ptr_eq(&selected_folder, &reloaded_folder));
+
+        // The selection is cleared once the folder is gone
+        std::fs::remove_dir_all(selected_path.as_str()).unwrap();
+        app.load_folders(root_path).await.unwrap();
+        assert_eq!(get_folder_paths(&app).await.len(), 2);
+        assert!(app.get_selected_folder_path().read().await.is_none());
+    }
+
+    async fn get_folder_names(app: &App) -> Vec<String> {
+        app.get_folders().read().await.iter().map(|folder| folder.get_folder_name().to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn depth_two_skips_genre_folders() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &[
+            "Drama/Show A/a.mkv", "Drama/Show B/b.mkv", "Drama/readme.txt", "Comedy/Show C/c.mkv", "loose.mkv",
+        ]);
+        let app = new_app(config_dir.path(), json!({ "folder_scan_depth": 2 })).await;
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+        assert_eq!(get_folder_names(&app).await, vec!["Comedy/Show C", "Drama/Show A", "Drama/Show B"]);
+    }
+
+    #[tokio::test]
+    async fn depth_zero_loads_root_as_folder() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Season 01/a.mkv", "b.mkv"]);
+        let app = new_app(config_dir.path(), json!({ "folder_scan_depth": 0 })).await;
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        app.load_folders(root_path.clone()).await.unwrap();
+        assert_eq!(get_folder_paths(&app).await, vec![root_path]);
+    }
+
+    fn new_watch_event(path: &Path) -> notify::Result<notify::Event> {
+        use notify::event::{CreateKind, EventKind};
+        Ok(notify::Event::new(EventKind::Create(CreateKind::Any)).add_path(path.to_path_buf()))
+    }
+
+    #[tokio::test]
+    async fn injected_watch_events_add_new_folder_after_quiet_period() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Show A/a.mkv"]);
+        let app = Arc::new(new_app(config_dir.path(), json!({})).await);
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let task = tokio::spawn({
+            let app = app.clone();
+            async move { app.run_watcher(rx).await }
+        });
+
+        // The app's own metadata writes don't trigger a reload
+        tx.send(new_watch_event(&root_dir.path().join("Show A").join("bookmarks.json"))).unwrap();
+        create_files(root_dir.path(), &["Show B/b.mkv"]);
+        for _ in 0..3 {
+            tx.send(new_watch_event(&root_dir.path().join("Show B"))).unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(get_folder_names(&app).await, vec!["Show A"]);
+
+        let is_added = async {
+            while get_folder_names(&app).await.len() < 2 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        };
+        tokio::time::timeout(WATCH_DEBOUNCE_DELAY * 3, is_added).await.unwrap();
+        assert_eq!(get_folder_names(&app).await, vec!["Show A", "Show B"]);
+
+        // The watcher stops once the event stream is closed
+        drop(tx);
+        tokio::time::timeout(Duration::from_secs(1), task).await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn execute_all_aggregates_pending_folders() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &[
+            "Show A/show.s01e01.mkv", "Show A/show.s01e02.mkv",
+            "Show B/show.s01e02.mkv",
+            "Show C/Season 01/Show-S01E01-Pilot.mkv",
+        ]);
+        for name in ["Show A", "Show B", "Show C"] {
+            write_cache_file(&root_dir.path().join(name), "Show", &[(1, 1, Some("Pilot")), (1, 2, Some("Return"))]);
+        }
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+        for folder in app.get_folders().read().await.iter() {
+            folder.perform_initial_load(None).await.unwrap();
+        }
+        let statuses: Vec<FolderStatus> = app.get_folders().read().await.iter().map(|folder| folder.get_folder_status()).collect();
+        assert_eq!(statuses, vec![FolderStatus::Pending, FolderStatus::Pending, FolderStatus::Done]);
+
+        let batch_summary = app.execute_all_pending_changes().await;
+        assert_eq!(batch_summary.total_folders, 2);
+        assert_eq!(batch_summary.skipped_busy_folders, 0);
+        assert_eq!((batch_summary.summary.renamed, batch_summary.summary.failed), (3, 0));
+        assert!(root_dir.path().join("Show A/Season 01/Show-S01E02-Return.mkv").exists());
+        assert!(root_dir.path().join("Show B/Season 01/Show-S01E02-Return.mkv").exists());
+        let statuses: Vec<FolderStatus> = app.get_folders().read().await.iter().map(|folder| folder.get_folder_status()).collect();
+        assert_eq!(statuses, vec![FolderStatus::Done; 3]);
+        assert_eq!(app.get_last_execution_summary().read().await.as_ref().map(|summary| summary.total_folders), Some(2));
+    }
+
+    // Requests fail straight away so the tests don't wait on retries
+    fn new_test_session_options(server: &TestServer) -> SessionOptions {
+        SessionOptions {
+            base_url: server.get_url().to_string(),
+            retry_policy: tvdb::api::RetryPolicy { max_attempts: 1, ..Default::default() },
+            ..SessionOptions::default()
+        }
+    }
+
+    async fn new_tvdb_app(config_path: &Path, server: &TestServer, token: Option<&str>) -> App {
+        let credentials = json!({ "credentials": { "apikey": "key" }, "token": token });
+        std::fs::write(config_path.join("credentials.json"), credentials.to_string()).unwrap();
+        let mut app = new_app(config_path, json!({ "provider": "tvdb" })).await;
+        app.set_test_server(new_test_client(), new_test_session_options(server));
+        app
+    }
+
+    fn read_saved_token(config_path: &Path) -> Option<String> {
+        let data = std::fs::read_to_string(config_path.join("credentials.json")).unwrap();
+        serde_json::from_str::<Credentials>(data.as_str()).unwrap().token
+    }
+
+    fn new_token_response(token: &str) -> TestResponse {
+        TestResponse::new(200, json!({ "token": token }).to_string())
+    }
+
+    fn new_unauthorized_response() -> TestResponse {
+        TestResponse::new(401, r#"{"Error": "Not authorized"}"#)
+    }
+
+    #[tokio::test]
+    async fn valid_token_is_refreshed_without_login() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/refresh_token" if request.get_bearer_token() == Some("stored") => new_token_response("refreshed"),
+            "/login" => new_token_response("fresh"),
+            _ => new_unauthorized_response(),
+        }).await;
+        let app = new_tvdb_app(config_dir.path(), &server, Some("stored")).await;
+
+        app.login().await.unwrap();
+        assert_eq!(server.get_total_requests("/login"), 0);
+        assert_eq!(read_saved_token(config_dir.path()).as_deref(), Some("refreshed"));
+        let session = app.login_session.read().await.clone().unwrap();
+        assert_eq!(session.get_token().await.token, "refreshed");
+        assert!(app.is_provider_ready().await);
+    }
+
+    #[tokio::test]
+    async fn expired_token_falls_back_to_login() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/login" => new_token_response("fresh"),
+            _ => new_unauthorized_response(),
+        }).await;
+        let app = new_tvdb_app(config_dir.path(), &server, Some("expired")).await;
+
+        app.login().await.unwrap();
+        assert_eq!(server.get_total_requests("/refresh_token"), 1);
+        assert_eq!(server.get_total_requests("/login"), 1);
+        assert_eq!(read_saved_token(config_dir.path()).as_deref(), Some("fresh"));
+        assert!(app.is_provider_ready().await);
+    }
+
+    #[tokio::test]
+    async fn rejected_login_leaves_no_session() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let server = TestServer::start(|_| new_unauthorized_response()).await;
+        let app = new_tvdb_app(config_dir.path(), &server, Some("expired")).await;
+
+        assert!(app.login().await.is_none());
+        assert!(app.login_session.read().await.is_none());
+        assert!(!app.is_provider_ready().await);
+        // The stored credentials are left for the user to fix
+        assert_eq!(read_saved_token(config_dir.path()).as_deref(), Some("expired"));
+        assert!(app.get_errors().read().await.iter().any(|error| error.to_string().contains("Login failed")));
+    }
+
+    async fn get_cache_series_name(folder: &AppFolder) -> Option<String> {
+        folder.get_cache().read().await.as_ref().map(|cache| cache.series.name.clone())
+    }
+
+    #[tokio::test]
+    async fn saved_cache_takes_precedence_over_mapping() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Local/show.s01e01.mkv", "Mapped/show.s01e01.mkv"]);
+        write_cache_file(&root_dir.path().join("Local"), "Local Show", &[(1, 1, Some("Pilot"))]);
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+        app.set_series_mapping("Local", 2).await.unwrap();
+        app.set_series_mapping("Mapped", 2).await.unwrap();
+        // Reloading picks up the mapping for each folder
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+
+        let mut provider = new_mock_provider(2, "Mapped Show", &[(1, 1, Some("Pilot"))]);
+        provider.kind = app.get_provider_kind().await;
+        let provider = provider.into_provider();
+        let folders = app.get_folders().read().await.clone();
+        for folder in folders.iter() {
+            assert_eq!(folder.get_mapped_series_id(), Some(2));
+            folder.perform_initial_load(Some(provider.clone())).await.unwrap();
+        }
+        assert_eq!(get_cache_series_name(&folders[0]).await.as_deref(), Some("Local Show"));
+        assert_eq!(get_cache_series_name(&folders[1]).await.as_deref(), Some("Mapped Show"));
+        // The fetched cache is saved so the mapping isn't needed next time
+        assert!(root_dir.path().join("Mapped/tvdb_cache.json").exists());
+    }
+
+    #[tokio::test]
+    async fn series_mapping_persists_between_apps() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Show/show.s01e01.mkv"]);
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_path.clone()).await.unwrap();
+        assert_eq!(app.get_mapped_series_id("Show").await, None);
+        app.set_series_mapping("Show", 42).await.unwrap();
+        drop(app);
+
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_path).await.unwrap();
+        assert_eq!(app.get_mapped_series_id("Show").await, Some(42));
+        assert_eq!(app.get_folders().read().await[0].get_mapped_series_id(), Some(42));
+    }
+
+    #[tokio::test]
+    async fn added_folder_is_inserted_in_sorted_order() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Alpha/a.mkv", "Charlie/c.mkv"]);
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_path.clone()).await.unwrap();
+        let selected_path = format!("{}/Charlie", root_path);
+        *app.get_selected_folder_path().write().await = Some(selected_path.clone());
+        let selected_folder = app.get_selected_folder().await.unwrap();
+
+        create_files(root_dir.path(), &["Bravo/b.mkv", "Delta/d.mkv"]);
+        app.add_folder(format!("{}/Delta", root_path).as_str()).await.unwrap();
+        app.add_folder(format!("{}/Bravo", root_path).as_str()).await.unwrap();
+        // Adding an existing folder doesn't duplicate it
+        app.add_folder(format!("{}/Bravo", root_path).as_str()).await.unwrap();
+        assert_eq!(get_folder_names(&app).await, vec!["Alpha", "Bravo", "Charlie", "Delta"]);
+
+        assert_eq!(app.get_selected_folder_path().read().await.as_deref(), Some(selected_path.as_str()));
+        assert!(Arc::ptr_eq(&selected_folder, &app.get_selected_folder().await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn removing_folder_only_clears_its_own_selection() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Alpha/a.mkv", "Bravo/b.mkv", "Charlie/c.mkv"]);
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_path.clone()).await.unwrap();
+        let selected_path = format!("{}/Charlie", root_path);
+        *app.get_selected_folder_path().write().await = Some(selected_path.clone());
+
+        app.remove_folder(format!("{}/Alpha", root_path).as_str()).await.unwrap();
+        assert_eq!(get_folder_names(&app).await, vec!["Bravo", "Charlie"]);
+        assert_eq!(app.get_selected_folder_path().read().await.as_deref(), Some(selected_path.as_str()));
+        assert_eq!(app.get_selected_folder().await.unwrap().get_folder_name(), "Charlie");
+
+        app.remove_folder(selected_path.as_str()).await.unwrap();
+        assert_eq!(get_folder_names(&app).await, vec!["Bravo"]);
+        assert!(app.get_selected_folder_path().read().await.is_none());
+        // Unknown folders are ignored
+        assert!(app.remove_folder(selected_path.as_str()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn add_and_remove_are_rejected_while_folders_are_busy() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Alpha/a.mkv"]);
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_path.clone()).await.unwrap();
+        create_files(root_dir.path(), &["Bravo/b.mkv"]);
+
+        let busy_lock = app.get_folders_busy_lock().lock().await;
+        assert!(app.add_folder(format!("{}/Bravo", root_path).as_str()).await.is_none());
+        assert!(app.remove_folder(format!("{}/Alpha", root_path).as_str()).await.is_none());
+        assert_eq!(app.get_errors().read().await.len(), 2);
+        drop(busy_lock);
+
+        assert_eq!(get_folder_names(&app).await, vec!["Alpha"]);
+        app.add_folder(format!("{}/Bravo", root_path).as_str()).await.unwrap();
+        assert_eq!(get_folder_names(&app).await, vec!["Alpha", "Bravo"]);
+    }
+
+    #[tokio::test]
+    async fn ignore_file_and_config_exclude_folders() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &[
+            "Show A/a.mkv", "Show B/b.mkv", "_incoming/c.mkv", "Music/d.mp3", "Temp 1/e.mkv", "Temp 2/f.mkv",
+        ]);
+        std::fs::write(root_dir.path().join(".renamerignore"), "# Downloads\n\n_incoming\nTemp*\n").unwrap();
+        let app = new_app(config_dir.path(), json!({ "ignore_folders": ["Music"] })).await;
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+        assert_eq!(get_folder_names(&app).await, vec!["Show A", "Show B"]);
+        assert_eq!(app.get_errors().read().await.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn missing_ignore_file_loads_all_folders() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Show A/a.mkv", "_incoming/c.mkv"]);
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+        assert_eq!(get_folder_names(&app).await, vec!["Show A", "_incoming"]);
+        assert_eq!(app.get_errors().read().await.len(), 0);
+    }
+
+    #[test]
+    fn token_refresh_backs_off_after_failures() {
+        assert_eq!(get_token_refresh_delay(0), TOKEN_REFRESH_INTERVAL);
+        assert_eq!(get_token_refresh_delay(1), TOKEN_REFRESH_RETRY_DELAY);
+        assert_eq!(get_token_refresh_delay(2), TOKEN_REFRESH_RETRY_DELAY*2);
+        assert_eq!(get_token_refresh_delay(3), TOKEN_REFRESH_RETRY_DELAY*4);
+        assert_eq!(get_token_refresh_delay(100), TOKEN_REFRESH_INTERVAL);
+    }
+
+    #[tokio::test]
+    async fn periodic_refresh_saves_new_token() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/login" => new_token_response("fresh"),
+            "/refresh_token" if request.get_bearer_token() == Some("fresh") => new_token_response("refreshed"),
+            _ => new_unauthorized_response(),
+        }).await;
+        let app = new_tvdb_app(config_dir.path(), &server, None).await;
+        // Nothing is refreshed without a session
+        assert_eq!(app.refresh_login_token(3).await, 0);
+        assert_eq!(server.get_total_requests("/refresh_token"), 0);
+
+        app.login().await.unwrap();
+        assert_eq!(app.refresh_login_token(0).await, 0);
+        assert_eq!(server.get_total_requests("/refresh_token"), 1);
+        assert_eq!(server.get_total_requests("/login"), 1);
+        assert_eq!(read_saved_token(config_dir.path()).as_deref(), Some("refreshed"));
+        assert_eq!(app.get_login_state().await, LoginState::LoggedIn);
+    }
+
+    #[tokio::test]
+    async fn failed_refresh_expires_session_until_login_succeeds() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let is_rejected = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let server = TestServer::start({
+            let is_rejected = is_rejected.clone();
+            move |request| match request.path.as_str() {
+                "/login" if !is_rejected.load(std::sync::atomic::Ordering::Relaxed) => new_token_response("fresh"),
+                _ => new_unauthorized_response(),
+            }
+        }).await;
+        let app = new_tvdb_app(config_dir.path(), &server, None).await;
+        app.login().await.unwrap();
+
+        is_rejected.store(true, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(app.refresh_login_token(0).await, 1);
+        assert_eq!(app.refresh_login_token(1).await, 2);
+        assert_eq!(app.get_login_state().await, LoginState::Expired);
+        assert!(app.get_errors().read().await.iter().any(|error| error.to_string().contains("attempt 2")));
+
+        // The session recovers by logging in again once the api accepts it
+        is_rejected.store(false, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(app.refresh_login_token(2).await, 0);
+        assert_eq!(app.get_login_state().await, LoginState::LoggedIn);
+        assert_eq!(server.get_total_requests("/login"), 4);
+    }
+
+    #[tokio::test]
+    async fn unauthorized_request_logs_in_again() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let total_logins = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let server = TestServer::start({
+            let total_logins = total_logins.clone();
+            move |request| match request.path.as_str() {
+                "/login" => {
+                    let total = total_logins.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+                    new_token_response(format!("token-{}", total).as_str())
+                },
+                "/series/1" if request.get_bearer_token() == Some("token-2") => {
+                    TestResponse::new(200, json!({ "data": { "id": 1, "seriesName": "Show" } }).to_string())
+                },
+                _ => new_unauthorized_response(),
+            }
+        }).await;
+        let app = new_tvdb_app(config_dir.path(), &server, None).await;
+        app.login().await.unwrap();
+
+        // The first token is rejected and can't be refreshed so the session logs in again
+        let provider = app.get_provider().read().await.clone().unwrap();
+        let series = provider.get_series(1, None).await.unwrap();
+        assert_eq!(series.name, "Show");
+        assert_eq!(server.get_total_requests("/series/1"), 2);
+        assert_eq!(server.get_total_requests("/refresh_token"), 1);
+        assert_eq!(server.get_total_requests("/login"), 2);
+        assert_eq!(app.get_login_state().await, LoginState::LoggedIn);
+    }
+
+    // Each folder is named after its series and has a saved cache for it
+    async fn new_app_with_series_folders(config_path: &Path, root_path: &Path, series: &[(u32, &str)]) -> App {
+        for (series_id, name) in series {
+            let folder_path = root_path.join(name);
+            create_files(folder_path.as_path(), &["show.s01e01.mkv"]);
+            write_cache_file(folder_path.as_path(), name, &[(1, 1, Some("Pilot"))]);
+            let cache_path = folder_path.join("tvdb_cache.json");
+            let mut cache_file: serde_json::Value = serde_json::from_str(std::fs::read_to_string(&cache_path).unwrap().as_str()).unwrap();
+            cache_file["series"]["id"] = json!(series_id);
+            cache_file["episodes"][0]["seriesId"] = json!(series_id);
+            std::fs::write(&cache_path, cache_file.to_string()).unwrap();
+        }
+        let app = new_app(config_path, json!({})).await;
+        app.load_folders(root_path.to_string_lossy().to_string()).await.unwrap();
+        for folder in app.get_folders().read().await.iter() {
+            folder.perform_initial_load(None).await.unwrap();
+        }
+        app
+    }
+
+    // Serves every series with a new name so refreshed caches can be told apart
+    fn new_updated_provider(series: &[(u32, &str)], updated_series: &[u32]) -> MockProvider {
+        let mut provider = MockProvider::new();
+        for (series_id, name) in series {
+            let updated = new_mock_provider(*series_id, format!("{} Updated", name).as_str(), &[(1, 1, Some("Pilot"))]);
+            provider.series.extend(updated.series);
+            provider.episodes.extend(updated.episodes);
+            provider.episodes_summaries.extend(updated.episodes_summaries);
+        }
+        provider.updated_series = updated_series.to_vec();
+        provider
+    }
+
+    async fn get_cache_series_names(app: &App) -> Vec<String> {
+        let folders = app.get_folders().read().await.clone();
+        let mut names = Vec::new();
+        for folder in folders.iter() {
+            names.push(get_cache_series_name(folder).await.unwrap_or_default());
+        }
+        names
+    }
+
+    #[tokio::test]
+    async fn only_updated_series_are_refreshed() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let series = [(11, "Alpha"), (12, "Bravo"), (13, "Charlie")];
+        let app = new_app_with_series_folders(config_dir.path(), root_dir.path(), &series).await;
+        app.set_provider(Some(new_updated_provider(&series, &[12, 99]).into_provider())).await;
+        app.get_settings().write().await.last_cache_refresh_time = Some(1_700_000_000);
+
+        app.refresh_stale_caches().await.unwrap();
+        assert_eq!(get_cache_series_names(&app).await, vec!["Alpha", "Bravo Updated", "Charlie"]);
+        assert!(app.get_settings().read().await.last_cache_refresh_time.unwrap() > 1_700_000_000);
+        assert_eq!(app.get_errors().read().await.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn first_refresh_updates_every_series() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let series = [(11, "Alpha"), (12, "Bravo")];
+        let app = new_app_with_series_folders(config_dir.path(), root_dir.path(), &series).await;
+        app.set_provider(Some(new_updated_provider(&series, &[]).into_provider())).await;
+        app.get_settings().write().await.last_cache_refresh_time = None;
+
+        app.refresh_stale_caches().await.unwrap();
+        assert_eq!(get_cache_series_names(&app).await, vec!["Alpha Updated", "Bravo Updated"]);
+        assert!(app.get_settings().read().await.last_cache_refresh_time.is_some());
+    }
+
+    #[tokio::test]
+    async fn setting_series_loads_cache_and_mapping() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Show/show.s01e01.mkv"]);
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+        let mut provider = new_mock_provider(7, "Show", &[(1, 1, Some("Pilot"))]);
+        provider.kind = app.get_provider_kind().await;
+        app.set_provider(Some(provider.into_provider())).await;
+
+        // A folder has to be selected first
+        assert!(app.set_series_to_current_folder(7).await.is_none());
+        let folder = app.get_folders().read().await[0].clone();
+        *app.get_selected_folder_path().write().await = Some(folder.get_folder_path().to_string());
+        // Unknown series leave the folder unchanged
+        assert!(app.set_series_to_current_folder(8).await.is_none());
+        assert!(folder.get_cache().read().await.is_none());
+        assert_eq!(app.get_errors().read().await.len(), 1);
+        assert!(folder.has_errors());
+
+        app.set_series_to_current_folder(7).await.unwrap();
+        assert_eq!(folder.get_cache().read().await.as_ref().unwrap().series.id, 7);
+        let files = folder.get_files().await;
+        let file = files.to_iter().find(|file| file.get_src() == "show.s01e01.mkv").unwrap();
+        assert_eq!(file.get_dest(), "Season 01/Show-S01E01-Pilot.mkv");
+        assert!(root_dir.path().join("Show/tvdb_cache.json").exists());
+        assert_eq!(app.get_mapped_series_id("Show").await, Some(7));
+    }
+
+    #[tokio::test]
+    async fn app_without_credentials_needs_no_login() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.login().await.unwrap();
+        assert_eq!(app.get_provider_kind().await, ProviderKind::TvMaze);
+        assert_eq!(app.get_login_state().await, LoginState::NotRequired);
+        assert!(app.is_provider_ready().await);
+    }
+
+    #[tokio::test]
+    async fn series_mappings_are_saved_per_provider() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Show/show.s01e01.mkv"]);
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_path.clone()).await.unwrap();
+        app.set_series_mapping("Show", 82).await.unwrap();
+        assert!(root_dir.path().join(PATH_STR_SERIES_MAPPING_TVMAZE).exists());
+        assert!(!root_dir.path().join(PATH_STR_SERIES_MAPPING).exists());
+
+        // The id would refer to a different series in another api
+        std::fs::write(config_dir.path().join("credentials.json"), json!({ "tmdb_api_key": "key" }).to_string()).unwrap();
+        let app = new_app(config_dir.path(), json!({ "provider": "tmdb" })).await;
+        app.load_folders(root_path).await.unwrap();
+        assert_eq!(app.get_provider_kind().await, ProviderKind::Tmdb);
+        assert_eq!(app.get_mapped_series_id("Show").await, None);
+        app.set_series_mapping("Show", 1399).await.unwrap();
+        assert!(root_dir.path().join(PATH_STR_SERIES_MAPPING_TMDB).exists());
+    }
+
+    async fn get_folder_caches(app: &App) -> Vec<Arc<TvdbCache>> {
+        let folders = app.get_folders().read().await.clone();
+        let mut caches = Vec::new();
+        for folder in folders.iter() {
+            caches.push(folder.get_cache().read().await.clone().unwrap());
+        }
+        caches
+    }
+
+    #[tokio::test]
+    async fn folders_of_the_same_series_share_cache() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let series = [(21, "Show (Season 1-5)"), (21, "Show (Season 6-10)"), (22, "Other")];
+        let app = new_app_with_series_folders(config_dir.path(), root_dir.path(), &series).await;
+
+        let caches = get_folder_caches(&app).await;
+        assert_eq!(caches.len(), 3);
+        let index = caches.iter().position(|cache| cache.series.id == 22).unwrap();
+        let shared: Vec<&Arc<TvdbCache>> = caches.iter().filter(|cache| cache.series.id == 21).collect();
+        assert!(Arc::ptr_eq(shared[0], shared[1]));
+        assert!(!Arc::ptr_eq(shared[0], &caches[index]));
+        assert_eq!(app.get_cache_registry().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn refresh_updates_every_folder_of_the_series() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let series = [(21, "Show (Season 1-5)"), (21, "Show (Season 6-10)")];
+        let app = new_app_with_series_folders(config_dir.path(), root_dir.path(), &series).await;
+        let old_cache = get_folder_caches(&app).await[0].clone();
+        app.set_provider(Some(new_updated_provider(&[(21, "Show")], &[]).into_provider())).await;
+
+        app.refresh_stale_caches().await.unwrap();
+        assert_eq!(get_cache_series_names(&app).await, vec!["Show Updated", "Show Updated"]);
+        // Fetched once and shared instead of each folder fetching its own copy
+        let caches = get_folder_caches(&app).await;
+        assert!(Arc::ptr_eq(&caches[0], &caches[1]));
+        assert!(!Arc::ptr_eq(&caches[0], &old_cache));
+        drop(old_cache);
+        assert_eq!(app.get_cache_registry().len(), 1);
+        assert_eq!(app.get_errors().read().await.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn reload_keeps_busy_folder_and_its_pending_operation() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let series = [(1, "Alpha"), (2, "Bravo")];
+        let app = new_app_with_series_folders(config_dir.path(), root_dir.path(), &series).await;
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let bravo_path = root_dir.path().join("Bravo").to_string_lossy().to_string();
+        *app.get_selected_folder_path().write().await = Some(bravo_path.clone());
+        let folder = app.get_selected_folder().await.unwrap();
+
+        // Unsaved changes to the files only exist in memory
+        folder.get_mut_files().await.get(0).unwrap().set_action(Action::Ignore);
+        folder.flush_file_changes().await;
+
+        // The fetch is started while the folder is busy so it finishes after the reload
+        let busy_lock = folder.get_busy_lock().lock().await;
+        let provider = new_updated_provider(&series, &[]).into_provider();
+        let task = tokio::spawn({
+            let folder = folder.clone();
+            async move { folder.load_cache_from_api(provider, 2).await }
+        });
+        create_files(root_dir.path().join("Aaron").as_path(), &["show.s01e01.mkv"]);
+        app.load_folders(root_path).await.unwrap();
+
+        let folders = app.get_folders().read().await.clone();
+        assert_eq!(folders.len(), 3);
+        assert_eq!(folders[2].get_folder_path(), bravo_path);
+        assert!(Arc::ptr_eq(&folders[2], &folder));
+        assert!(folder.get_busy_lock().try_lock().is_err());
+        assert_eq!(app.get_selected_folder_path().read().await.as_deref(), Some(bravo_path.as_str()));
+        assert!(Arc::ptr_eq(&app.get_selected_folder().await.unwrap(), &folder));
+        assert_eq!(folder.get_files().await.get(0).unwrap().get_action(), Action::Ignore);
+
+        drop(busy_lock);
+        task.await.unwrap().unwrap();
+        // The result lands in the same folder even though its index changed
+        assert_eq!(get_cache_series_names(&app).await, vec!["", "Alpha", "Bravo Updated"]);
+        let selected_folder = app.get_selected_folder().await.unwrap();
+        assert_eq!(get_cache_series_name(&selected_folder).await.as_deref(), Some("Bravo Updated"));
+    }
+
+    async fn get_login_errors(app: &App) -> Vec<String> {
+        app.get_errors().read().await
+            .iter()
+            .filter(|error| error.context == "Login")
+            .map(|error| error.message.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn key_only_credentials_login_and_are_saved_as_is() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/login" => new_token_response("fresh"),
+            _ => new_unauthorized_response(),
+        }).await;
+        let app = new_tvdb_app(config_dir.path(), &server, None).await;
+
+        app.login().await.unwrap();
+        assert!(app.is_provider_ready().await);
+        let requests = server.get_requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(requests[0].body.as_str()).unwrap(), json!({ "apikey": "key" }));
+
+        let data = std::fs::read_to_string(config_dir.path().join("credentials.json")).unwrap();
+        let credentials: serde_json::Value = serde_json::from_str(data.as_str()).unwrap();
+        assert_eq!(credentials["credentials"], json!({ "apikey": "key" }));
+        assert_eq!(credentials["token"], json!("fresh"));
+    }
+
+    #[tokio::test]
+    async fn malformed_credentials_are_told_apart_from_missing_fields() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let server = TestServer::start(|_| new_unauthorized_response()).await;
+        let app = new_tvdb_app(config_dir.path(), &server, None).await;
+        let cases = [
+            (r#"{"credentials": {"userkey": "user key"}}"#, "Login failed since credentials file is malformed"),
+            (r#"{"credentials": "#, "Login failed since credentials file is malformed"),
+            (r#"{"token": "stored"}"#, "Login failed since credentials are missing the tvdb login info"),
+            (r#"{"credentials": {"apikey": ""}}"#, "Login failed since credentials are missing the tvdb api key"),
+        ];
+        for (data, message) in cases {
+            std::fs::write(config_dir.path().join("credentials.json"), data).unwrap();
+            app.get_errors().write().await.clear();
+            assert!(app.login().await.is_none(), "{}", data);
+            let errors = get_login_errors(&app).await;
+            assert_eq!(errors.len(), 1, "{}", data);
+            assert!(errors[0].starts_with(message), "{}: {}", data, errors[0]);
+        }
+        assert!(server.get_requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejected_key_only_login_mentions_missing_user_key() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let server = TestServer::start(|_| new_unauthorized_response()).await;
+        let app = new_tvdb_app(config_dir.path(), &server, None).await;
+        assert!(app.login().await.is_none());
+        let errors = get_login_errors(&app).await;
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("the user key and username may be required"), "{}", errors[0]);
+
+        // The hint isn't given when the user key and username were sent
+        let login_info = LoginInfo { apikey: "key".to_string(), userkey: Some("user key".to_string()), username: Some("user".to_string()) };
+        app.get_errors().write().await.clear();
+        assert!(app.login_with(login_info, false).await.is_none());
+        let errors = get_login_errors(&app).await;
+        assert_eq!(errors.len(), 1);
+        assert!(!errors[0].contains("may be required"), "{}", errors[0]);
+    }
+
+    #[tokio::test]
+    async fn picked_root_path_is_reopened_after_restart() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let shows_dir = tempfile::tempdir().unwrap();
+        let anime_dir = tempfile::tempdir().unwrap();
+        create_files(shows_dir.path(), &["Show/show.s01e01.mkv"]);
+        create_files(anime_dir.path(), &["Anime A/a.mkv", "Anime B/b.mkv"]);
+        let shows_path = shows_dir.path().to_string_lossy().to_string();
+        let anime_path = anime_dir.path().to_string_lossy().to_string();
+
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(shows_path.clone()).await.unwrap();
+        app.set_folder_scan_depth(1).await.unwrap();
+        app.set_use_trash(false).await.unwrap();
+        // Picking another library replaces the folders of the previous one
+        app.load_folders(anime_path.clone()).await.unwrap();
+        assert_eq!(get_folder_names(&app).await, vec!["Anime A", "Anime B"]);
+        drop(app);
+
+        let app = new_app(config_dir.path(), json!({})).await;
+        let settings = app.get_settings().read().await.clone();
+        assert_eq!(settings.get_last_root_path(), Some(anime_path.as_str()));
+        assert_eq!(settings.recent_root_paths, vec![anime_path.clone(), shows_path.clone()]);
+        assert_eq!((settings.folder_scan_depth, settings.use_trash), (Some(1), Some(false)));
+        assert!(app.get_errors().read().await.is_empty());
+
+        // Reopening an older library moves it to the front
+        app.load_folders(shows_path.clone()).await.unwrap();
+        drop(app);
+        let app = new_app(config_dir.path(), json!({})).await;
+        assert_eq!(app.get_settings().read().await.recent_root_paths, vec![shows_path, anime_path]);
+    }
+
+    #[tokio::test]
+    async fn corrupt_settings_are_replaced_on_next_save() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        std::fs::write(config_dir.path().join("app_settings.json"), "{").unwrap();
+        let app = new_app(config_dir.path(), json!({})).await;
+        assert_eq!(app.get_settings().read().await.clone(), AppSettings::default());
+        assert!(app.get_errors().read().await.iter().any(|error| error.context == "Settings"));
+
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        app.load_folders(root_path.clone()).await.unwrap();
+        drop(app);
+        let app = new_app(config_dir.path(), json!({})).await;
+        assert_eq!(app.get_settings().read().await.get_last_root_path(), Some(root_path.as_str()));
+    }
+
+    async fn wait_for_scan_progress(receiver: &mut watch::Receiver<ScanProgress>, predicate: impl Fn(&ScanProgress) -> bool) -> ScanProgress {
+        let progress = tokio::time::timeout(Duration::from_secs(5), receiver.wait_for(|progress| predicate(progress)))
+            .await
+            .unwrap()
+            .unwrap();
+        progress.clone()
+    }
+
+    #[tokio::test]
+    async fn scan_progress_counts_folders_through_concurrency_limit() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let total_folders = MAX_CONCURRENT_FOLDER_SCANS + 2;
+        let paths: Vec<String> = (0..total_folders).map(|index| format!("Show {}/show.s01e01.mkv", index)).collect();
+        create_files(root_dir.path(), paths.iter().map(|path| path.as_str()).collect::<Vec<&str>>().as_slice());
+        let app = Arc::new(new_app(config_dir.path(), json!({})).await);
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+        app.update_file_intents_for_all_folders().await.unwrap();
+        let mut receiver = app.get_scan_progress();
+        assert!(receiver.borrow_and_update().is_idle());
+
+        // Scans wait on the busy folders so the counters can be checked part way through
+        let folders = app.get_folders().read().await.clone();
+        let mut busy_locks = Vec::new();
+        for folder in folders.iter() {
+            busy_locks.push(folder.get_busy_lock().lock().await);
+        }
+        let task = tokio::spawn({
+            let app = app.clone();
+            async move { app.update_file_intents_for_all_folders().await }
+        });
+        let progress = wait_for_scan_progress(&mut receiver, |progress| progress.total_started == MAX_CONCURRENT_FOLDER_SCANS).await;
+        assert_eq!(progress, ScanProgress { total_queued: total_folders, total_started: MAX_CONCURRENT_FOLDER_SCANS, total_completed: 0 });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(receiver.borrow().total_started, MAX_CONCURRENT_FOLDER_SCANS);
+
+        // Each completed folder lets another one start
+        drop(busy_locks.remove(0));
+        let progress = wait_for_scan_progress(&mut receiver, |progress| progress.total_completed == 1).await;
+        assert_eq!(progress.total_queued, total_folders);
+        assert!(progress.total_started <= MAX_CONCURRENT_FOLDER_SCANS + 1);
+        let progress = wait_for_scan_progress(&mut receiver, |progress| progress.total_started == MAX_CONCURRENT_FOLDER_SCANS + 1).await;
+        assert_eq!(progress.total_completed, 1);
+
+        busy_locks.clear();
+        task.await.unwrap().unwrap();
+        // Progress is reset once every folder is done so the bar is hidden
+        assert!(receiver.borrow_and_update().is_idle());
+        assert_eq!(*receiver.borrow(), ScanProgress::default());
+    }
+
+    #[tokio::test]
+    async fn single_folder_rescan_doesnt_move_scan_progress() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Show A/show.s01e01.mkv", "Show B/show.s01e01.mkv"]);
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_dir.path().to_string_lossy().to_string()).await.unwrap();
+        let mut receiver = app.get_scan_progress();
+        receiver.borrow_and_update();
+
+        let folder = app.get_folders().read().await[0].clone();
+        folder.perform_initial_load(None).await;
+        folder.update_file_intents().await;
+        assert!(!receiver.has_changed().unwrap());
+        assert!(receiver.borrow().is_idle());
+    }
+
+    #[tokio::test]
+    async fn added_folder_is_loaded_with_its_mapping() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Alpha/a.mkv"]);
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_path.clone()).await.unwrap();
+        app.set_provider(Some(new_mock_provider(7, "Bravo Show", &[(1, 1, Some("Pilot"))]).into_provider())).await;
+        app.set_series_mapping("Bravo", 7).await.unwrap();
+        let mut receiver = app.subscribe_changes();
+
+        create_files(root_dir.path(), &["Bravo/show.s01e01.mkv"]);
+        let folder_path = format!("{}/Bravo", root_path);
+        app.add_folder(folder_path.as_str()).await.unwrap();
+        let folder = app.get_folder_by_path(folder_path.as_str()).await.unwrap();
+        assert_eq!((folder.get_folder_name(), folder.get_mapped_series_id()), ("Bravo", Some(7)));
+        // The folder is listed before it is loaded so it shows up straight away
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event, ChangeEvent { folder_path: None, kind: ChangeKind::Folders });
+
+        assert_eq!(get_cache_series_name(&folder).await.as_deref(), Some("Bravo Show"));
+        assert_eq!(folder.get_folder_status(), FolderStatus::Pending);
+        let files = folder.get_files().await;
+        assert_eq!(files.get(0).unwrap().get_dest(), "Season 01/Bravo.Show-S01E01-Pilot.mkv");
+    }
+
+    #[tokio::test]
+    async fn only_unignored_directories_are_added() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Alpha/a.mkv", "loose.mkv", "Music/a.mp3", "_incoming/b.mkv"]);
+        std::fs::write(root_dir.path().join(".renamerignore"), "_incoming\n").unwrap();
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let app = new_app(config_dir.path(), json!({ "ignore_folders": ["Music"] })).await;
+        app.load_folders(root_path.clone()).await.unwrap();
+        assert!(app.get_errors().read().await.is_empty());
+
+        for path in ["loose.mkv", "Missing"] {
+            assert!(app.add_folder(format!("{}/{}", root_path, path).as_str()).await.is_none(), "{}", path);
+        }
+        let errors: Vec<String> = app.get_errors().read().await.iter().map(|error| error.message.clone()).collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|error| error.ends_with("since it isn't a directory")), "{:?}", errors);
+
+        // Ignored folders are skipped the same way loading the folders does
+        for path in ["Music", "_incoming"] {
+            app.add_folder(format!("{}/{}", root_path, path).as_str()).await.unwrap();
+        }
+        assert_eq!(get_folder_names(&app).await, vec!["Alpha"]);
+    }
+
+    #[tokio::test]
+    async fn unidentified_folders_are_matched_after_confirmation() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        create_files(root_dir.path(), &["Bravo.Show.2010/show.s01e01.mkv", "Doctor Who/a.mkv"]);
+        write_cache_file(root_dir.path().join("Alpha").as_path(), "Alpha", &[(1, 1, Some("Pilot"))]);
+        let root_path = root_dir.path().to_string_lossy().to_string();
+        let app = new_app(config_dir.path(), json!({})).await;
+        app.load_folders(root_path.clone()).await.unwrap();
+
+        let mut provider = new_mock_provider(7, "Bravo Show", &[(1, 1, Some("Pilot"))]);
+        for (id, first_aired) in [(8, "1963-11-23"), (9, "2005-03-26")] {
+            let series = serde_json::from_value(json!({ "id": id, "seriesName": "Doctor Who", "firstAired": first_aired })).unwrap();
+            provider.series.push(series);
+        }
+        app.set_provider(Some(provider.into_provider())).await;
+
+        // Matches are proposed first so they can be confirmed, folders with a cache are skipped
+        app.update_series_matches().await.unwrap();
+        let proposals: Vec<(String, String, bool, Vec<u32>)> = app.get_series_matches().read().await
+            .iter()
+            .flatten()
+            .map(|proposal| (
+                proposal.folder.get_folder_name().to_string(),
+                proposal.query.clone(),
+                proposal.is_enabled,
+                proposal.result.get_candidates().iter().map(|series| series.id).collect(),
+            ))
+            .collect();
+        assert_eq!(proposals, vec![
+            ("Bravo.Show.2010".to_string(), "Bravo Show".to_string(), true, vec![7]),
+            ("Doctor Who".to_string(), "Doctor Who".to_string(), false, vec![8, 9]),
+        ]);
+        let ambiguous_folder = app.get_folder_by_path(format!("{}/Doctor Who", root_path).as_str()).await.unwrap();
+        let errors: Vec<String> = ambiguous_folder.get_errors().read().await.iter().map(|error| error.message.clone()).collect();
+        assert_eq!(errors, vec!["Couldn't automatically match series 'Doctor Who' since multiple series are close: Doctor Who (1963-11-23), Doctor Who (2005-03-26)"]);
+        let matched_folder = app.get_folder_by_path(format!("{}/Bravo.Show.2010", root_path).as_str()).await.unwrap();
+        assert!(matched_folder.is_unidentified().await);
+
+        // Only enabled proposals with a single match are applied
+        app.apply_series_matches().await.unwrap();
+        assert!(app.get_series_matches().read().await.is_none());
+        assert_eq!(get_cache_series_name(&matched_folder).await.as_deref(), Some("Bravo Show"));
+        assert!(!matched_folder.is_unidentified().await);
+        assert_eq!(matched_folder.get_files().await.get(0).unwrap().get_dest(), "Season 01/Bravo.Show-S01E01-Pilot.mkv");
+        assert!(ambiguous_folder.is_unidentified().await);
+    }
+
+    #[tokio::test]
+    async fn batch_actions_only_change_selected_folders() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let series = [(11, "Alpha"), (12, "Bravo"), (13, "Charlie")];
+        let app = new_app_with_series_folders(config_dir.path(), root_dir.path(), &series).await;
+        app.set_provider(Some(new_updated_provider(&series, &[]).into_provider())).await;
+        let paths = get_folder_paths(&app).await;
+        let selected = vec![paths[0].clone(), paths[2].clone(), format!("{}/Missing", root_dir.path().to_string_lossy())];
+
+        // Series are fetched again for the selected folders even though none of them were updated
+        app.refresh_caches_for_folders(selected.as_slice()).await.unwrap();
+        assert_eq!(get_cache_series_names(&app).await, vec!["Alpha Updated", "Bravo", "Charlie Updated"]);
+
+        create_files(root_dir.path(), &["Alpha/show.s01e02.mkv", "Bravo/show.s01e02.mkv"]);
+        app.update_file_intents_for_folders(&selected[..1]).await.unwrap();
+        let folders = app.get_folders().read().await.clone();
+        let mut total_files = Vec::new();
+        for folder in folders.iter() {
+            total_files.push(folder.get_files().await.len());
+        }
+        assert_eq!(total_files, vec![2, 1, 1]);
+
+        // Busy folders in the selection are skipped
+        let busy_lock = folders[0].get_busy_lock().lock().await;
+        let batch_summary = app.execute_pending_changes_for_folders(selected.as_slice()).await;
+        assert_eq!((batch_summary.total_folders, batch_summary.skipped_busy_folders), (1, 1));
+        assert_eq!((batch_summary.summary.renamed, batch_summary.summary.failed), (1, 0));
+        drop(busy_lock);
+        assert!(root_dir.path().join("Charlie/Season 01/Charlie.Updated-S01E01-Pilot.mkv").exists());
+        assert!(root_dir.path().join("Alpha/show.s01e01.mkv").exists());
+        assert!(root_dir.path().join("Bravo/show.s01e01.mkv").exists());
+        let statuses: Vec<FolderStatus> = folders.iter().map(|folder| folder.get_folder_status()).collect();
+        assert_eq!(statuses, vec![FolderStatus::Pending, FolderStatus::Pending, FolderStatus::Done]);
+        assert_eq!(app.get_last_execution_summary().read().await.as_ref(), Some(&batch_summary));
+    }
+}
@@ -1,20 +1,54 @@
-use std::collections::{HashMap,HashSet};
+use std::collections::{BTreeSet,HashMap,HashSet};
+use std::time::SystemTime;
 use tokio::sync::{RwLockReadGuard, RwLockWriteGuard};
+use crate::destination::{PathError, validate_destination};
 use crate::file_intent::Action;
 use crate::tvdb_cache::EpisodeKey;
 
+#[derive(Clone)]
 pub(crate) struct AppFile {
     pub(crate) src: String,
     pub(crate) src_descriptor: Option<EpisodeKey>,
     pub(crate) action: Action,
     pub(crate) dest: String,
+    // Checked whenever the destination changes so invalid renames are shown before they are executed
+    pub(crate) dest_error: Option<PathError>,
     pub(crate) is_enabled: bool,
+    pub(crate) last_error: Option<String>,
+    // Used to detect if the file was changed between scans
+    pub(crate) modified: Option<SystemTime>,
+    pub(crate) size: u64,
 }
 
 pub struct FileTracker {
     pending_writes: HashMap<String, HashSet<usize>>,
     existing_sources: HashMap<String, usize>,
+    // Files that are enabled to be renamed or deleted will no longer occupy their source
+    vacated_sources: HashSet<usize>,
     action_count: enum_map::EnumMap<Action, usize>,
+    // Total size of files for each action regardless of whether they are enabled
+    action_bytes: enum_map::EnumMap<Action, u64>,
+    // Lists only need to visit the files with their action
+    action_indices: enum_map::EnumMap<Action, BTreeSet<usize>>,
+    // Rebuilt when changes are flushed instead of every time the conflicts are read
+    conflicts: Vec<ConflictGroup>,
+    is_conflicts_outdated: bool,
+    // Paths are compared by their folded case on case-insensitive filesystems
+    is_case_insensitive: bool,
+    // Longest destination that still fits in the path length limit once joined to the folder
+    max_dest_length: usize,
+}
+
+// Files that would be written to the same destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictGroup {
+    // Used to look up the destination in the tracker, this is lowercase on case-insensitive filesystems
+    pub key: String,
+    // Destination as it was written by the first file in the group
+    pub dest: String,
+    // Existing file at the destination which may or may not be moved away beforehand
+    pub source_index: Option<usize>,
+    pub writer_indices: Vec<usize>,
 }
 
 // We queue all our changes to our files so we can iterate over them while submitting changes
@@ -24,6 +58,24 @@ pub(crate) enum FileChange {
     SetAction(usize, Action),
     IsEnabled(usize, bool),
     Destination(usize, String),
+    // The episode of a file only changes when it is assigned to a different one
+    Descriptor(usize, Option<EpisodeKey>),
+}
+
+// Editing a destination pushes a change every keystroke
+// Consecutive changes of the same kind to the same file are replaced since only the last one has an effect
+fn push_file_change(change_queue: &mut Vec<FileChange>, change: FileChange) {
+    let is_redundant = match (change_queue.last(), &change) {
+        (Some(FileChange::SetAction(last, _)), FileChange::SetAction(index, _)) => last == index,
+        (Some(FileChange::IsEnabled(last, _)), FileChange::IsEnabled(index, _)) => last == index,
+        (Some(FileChange::Destination(last, _)), FileChange::Destination(index, _)) => last == index,
+        (Some(FileChange::Descriptor(last, _)), FileChange::Descriptor(index, _)) => last == index,
+        _ => false,
+    };
+    if is_redundant {
+        change_queue.pop();
+    }
+    change_queue.push(change);
 }
 
 pub struct ImmutableAppFileList<'a> {
@@ -62,63 +114,191 @@ pub struct ImmutableAppFileIterator<'a> {
     file_tracker: &'a FileTracker,
 }
 
+// Windows and macOS filesystems are case-insensitive by default
+const IS_CASE_INSENSITIVE_FILESYSTEM: bool = cfg!(any(target_os = "windows", target_os = "macos"));
+
+// Paths that only differ by case refer to the same file so they must share the same key
+fn fold_path_case(path: &str, is_case_insensitive: bool) -> String {
+    match is_case_insensitive {
+        true => path.to_lowercase(),
+        false => path.to_string(),
+    }
+}
+
+pub(crate) fn get_path_key(path: &str) -> String {
+    fold_path_case(path, IS_CASE_INSENSITIVE_FILESYSTEM)
+}
+
 impl AppFile {
-    pub(crate) fn new(src: String, src_descriptor: Option<EpisodeKey>, action: Action, dest: String) -> Self {
+    pub(crate) fn new(
+        src: String, src_descriptor: Option<EpisodeKey>, action: Action, dest: String,
+        modified: Option<SystemTime>, size: u64,
+    ) -> Self {
         Self {
             src,
             src_descriptor,
             action,
             dest,
+            dest_error: None,
             is_enabled: false,
+            last_error: None,
+            modified,
+            size,
         }
     }
+
+    // Destinations are validated once the file is tracked since the length limit depends on the folder
+    pub(crate) fn update_dest_error(&mut self, max_dest_length: usize) {
+        self.dest_error = validate_destination(self.dest.as_str(), max_dest_length).err();
+    }
+
+    // Files without a modification time are always treated as changed
+    pub(crate) fn is_unchanged(&self, modified: Option<SystemTime>, size: u64) -> bool {
+        self.modified.is_some() && self.modified == modified && self.size == size
+    }
 }
 
 impl FileTracker {
     pub(crate) fn new() -> Self {
+        Self::with_case_folding(IS_CASE_INSENSITIVE_FILESYSTEM)
+    }
+
+    pub(crate) fn with_case_folding(is_case_insensitive: bool) -> Self {
         Self {
             pending_writes: HashMap::new(),
             existing_sources: HashMap::new(),
+            vacated_sources: HashSet::new(),
             action_count: enum_map::enum_map!{ _ => 0 },
+            action_bytes: enum_map::enum_map!{ _ => 0 },
+            action_indices: enum_map::enum_map!{ _ => BTreeSet::new() },
+            conflicts: Vec::new(),
+            is_conflicts_outdated: false,
+            is_case_insensitive,
+            max_dest_length: usize::MAX,
         }
     }
 
+    pub(crate) fn set_max_dest_length(&mut self, max_dest_length: usize) {
+        self.max_dest_length = max_dest_length;
+    }
+
+    pub(crate) fn get_max_dest_length(&self) -> usize {
+        self.max_dest_length
+    }
+
+    pub fn get_path_key(&self, path: &str) -> String {
+        fold_path_case(path, self.is_case_insensitive)
+    }
+
     pub(crate) fn clear(&mut self) {
         self.pending_writes.clear();
         self.existing_sources.clear();
+        self.vacated_sources.clear();
         self.action_count.clear();
+        self.action_bytes.clear();
+        for indices in self.action_indices.values_mut() {
+            indices.clear();
+        }
+        self.conflicts.clear();
+        self.is_conflicts_outdated = false;
+    }
+
+    // Adds a file from a new scan to the totals for its action
+    pub(crate) fn insert_file_action(&mut self, file: &AppFile, index: usize) {
+        self.action_count[file.action] += 1;
+        self.action_bytes[file.action] += file.size;
+        self.action_indices[file.action].insert(index);
+    }
+
+    fn move_file_action(&mut self, file: &AppFile, index: usize, old_action: Action) {
+        self.action_count[old_action] -= 1;
+        self.action_count[file.action] += 1;
+        self.action_bytes[old_action] -= file.size;
+        self.action_bytes[file.action] += file.size;
+        self.action_indices[old_action].remove(&index);
+        self.action_indices[file.action].insert(index);
     }
 
     pub(crate) fn insert_existing_source(&mut self, src: &str, index: usize) {
-        self.existing_sources.insert(src.to_string(), index);
+        self.existing_sources.insert(self.get_path_key(src), index);
+        self.is_conflicts_outdated = true;
     }
 
     fn add_pending_write(&mut self, dest: &str, index: usize) {
-        let entries = match self.pending_writes.get_mut(dest) {
+        let key = self.get_path_key(dest);
+        let entries = match self.pending_writes.get_mut(key.as_str()) {
             Some(entries) => entries,
-            None => self.pending_writes.entry(dest.to_string()).or_insert(HashSet::new()),
+            None => self.pending_writes.entry(key).or_default(),
         };
         entries.insert(index);
+        self.is_conflicts_outdated = true;
     }
 
     fn remove_pending_write(&mut self, dest: &str, index: usize) {
-        let entries = match self.pending_writes.get_mut(dest) {
+        let key = self.get_path_key(dest);
+        let entries = match self.pending_writes.get_mut(key.as_str()) {
             Some(entries) => entries,
-            None => self.pending_writes.entry(dest.to_string()).or_insert(HashSet::new()),
+            None => self.pending_writes.entry(key).or_default(),
         };
         entries.remove(&index);
+        self.is_conflicts_outdated = true;
+    }
+
+    fn update_vacated_source(&mut self, file: &AppFile, index: usize) {
+        let is_vacated = file.is_enabled && (file.action == Action::Rename || file.action == Action::Delete);
+        let is_changed = match is_vacated {
+            true => self.vacated_sources.insert(index),
+            false => self.vacated_sources.remove(&index),
+        };
+        self.is_conflicts_outdated |= is_changed;
     }
 
-    fn check_if_write_conflicts(&self, dest: &str) -> bool {
+    // Sorted by destination so the groups are listed in the same order every time
+    // The key is only used for lookups so the destination keeps the case the files were given
+    fn rebuild_conflicts(&mut self, file_list: &[AppFile]) {
+        let mut conflicts: Vec<ConflictGroup> = self.pending_writes
+            .iter()
+            .filter(|(key, indices)| !indices.is_empty() && self.check_if_write_conflicts(key.as_str()))
+            .map(|(key, indices)| {
+                let mut writer_indices: Vec<usize> = indices.iter().copied().collect();
+                writer_indices.sort_unstable();
+                let dest = writer_indices
+                    .first()
+                    .and_then(|index| file_list.get(*index))
+                    .map(|file| file.dest.clone())
+                    .unwrap_or_else(|| key.clone());
+                ConflictGroup {
+                    key: key.clone(),
+                    dest,
+                    source_index: self.existing_sources.get(key.as_str()).copied(),
+                    writer_indices,
+                }
+            })
+            .collect();
+        conflicts.sort_unstable_by(|a, b| a.dest.cmp(&b.dest));
+        self.conflicts = conflicts;
+        self.is_conflicts_outdated = false;
+    }
+
+    pub fn get_conflicts(&self) -> &[ConflictGroup] {
+        self.conflicts.as_slice()
+    }
+
+    pub fn get_conflict_count(&self) -> usize {
+        self.conflicts.len()
+    }
+
+    pub fn check_if_write_conflicts(&self, dest: &str) -> bool {
+        let key = self.get_path_key(dest);
         let mut total_files = 0;
-        if self.existing_sources.get(dest).is_some() {
-            total_files += 1;
-        }
-        // NOTE: Exit early to avoid extra table lookup
-        if total_files > 1 {
-            return true;
+        // An existing file doesn't conflict if it is moved or deleted beforehand
+        // This includes case only renames which have their own source as the destination
+        if let Some(index) = self.existing_sources.get(key.as_str()) {
+            if !self.vacated_sources.contains(index) {
+                total_files += 1;
+            }
         }
-        if let Some(entries) = self.pending_writes.get(dest) {
+        if let Some(entries) = self.pending_writes.get(key.as_str()) {
             total_files += entries.len();
         } 
 
@@ -129,23 +309,32 @@ impl FileTracker {
         &self.pending_writes
     }
 
+    pub fn check_if_source_vacated(&self, index: usize) -> bool {
+        self.vacated_sources.contains(&index)
+    }
+
     pub fn get_source_index(&self, src: &str) -> Option<&usize> {
-        self.existing_sources.get(src)
+        self.existing_sources.get(self.get_path_key(src).as_str())
     }
 
     pub fn get_action_count(&self) -> &enum_map::EnumMap<Action, usize> {
         &self.action_count
     }
 
-    pub fn get_action_count_mut(&mut self) -> &mut enum_map::EnumMap<Action, usize> {
-        &mut self.action_count
+    pub fn get_action_bytes(&self) -> &enum_map::EnumMap<Action, u64> {
+        &self.action_bytes
+    }
+
+    // Indices into the file list in ascending order
+    pub fn get_action_indices(&self, action: Action) -> &BTreeSet<usize> {
+        &self.action_indices[action]
     }
 }
 
 pub(crate) fn flush_file_changes_acquired(
-    mut file_list: RwLockWriteGuard<'_, Vec<AppFile>>,  
-    mut file_tracker: RwLockWriteGuard<'_, FileTracker>,
-    mut change_queue: RwLockWriteGuard<'_, Vec<FileChange>>,
+    file_list: &mut [AppFile],  
+    file_tracker: &mut FileTracker,
+    change_queue: &mut Vec<FileChange>,
 ) -> usize {
     let mut total_changes: usize = 0;
     for file_change in change_queue.iter() {
@@ -165,8 +354,7 @@ pub(crate) fn flush_file_changes_acquired(
                     continue;
                 }
 
-                file_tracker.action_count[old_action] -= 1usize;
-                file_tracker.action_count[new_action] += 1usize;
+                file_tracker.move_file_action(file, index, old_action);
 
                 if !file.is_enabled {
                     continue;
@@ -224,6 +412,7 @@ pub(crate) fn flush_file_changes_acquired(
                 if !file.is_enabled || file.action != Action::Rename {
                     file.dest.clear();
                     file.dest.push_str(new_dest.as_str());
+                    file.update_dest_error(file_tracker.get_max_dest_length());
                     continue
                 }
 
@@ -232,15 +421,89 @@ pub(crate) fn flush_file_changes_acquired(
 
                 file.dest.clear();
                 file.dest.push_str(new_dest.as_str());
+                file.update_dest_error(file_tracker.get_max_dest_length());
                 total_changes += 1;
             },
+            // This doesn't change any pending writes
+            FileChange::Descriptor(index, new_descriptor) => {
+                if let Some(file) = file_list.get_mut(*index) {
+                    file.src_descriptor = *new_descriptor;
+                }
+            },
+        }
+    }
+
+    // Enabled renames and deletes vacate their source which changes whether other files conflict
+    for file_change in change_queue.iter() {
+        let index = match file_change {
+            FileChange::SetAction(index, _) | FileChange::IsEnabled(index, _) | FileChange::Destination(index, _) => *index,
+            FileChange::Descriptor(..) => continue,
+        };
+        if let Some(file) = file_list.get(index) {
+            file_tracker.update_vacated_source(file, index);
         }
     }
 
     change_queue.clear();
+    if file_tracker.is_conflicts_outdated {
+        file_tracker.rebuild_conflicts(file_list);
+    }
     total_changes
 }
 
+// Order in which files are listed, this doesn't change the index of each file in the folder
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub enum FileSortMode {
+    #[default]
+    Source,
+    Destination,
+    Episode,
+    SizeDesc,
+}
+
+impl FileSortMode {
+    pub fn iterator() -> std::slice::Iter<'static, Self> {
+        static MODES: [FileSortMode;4] = [
+            FileSortMode::Source,
+            FileSortMode::Destination,
+            FileSortMode::Episode,
+            FileSortMode::SizeDesc,
+        ];
+        MODES.iter()
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            FileSortMode::Source => "Source",
+            FileSortMode::Destination => "Destination",
+            FileSortMode::Episode => "Episode",
+            FileSortMode::SizeDesc => "Largest first",
+        }
+    }
+}
+
+// Files that compare equal are ordered by their source so the order doesn't jump around between frames
+// Files without an episode are listed after all episodes
+fn get_sorted_indices(file_list: &[AppFile], mut indices: Vec<usize>, mode: FileSortMode) -> Vec<usize> {
+    indices.sort_by(|a, b| {
+        let a = &file_list[*a];
+        let b = &file_list[*b];
+        let ordering = match mode {
+            FileSortMode::Source => std::cmp::Ordering::Equal,
+            FileSortMode::Destination => a.dest.cmp(&b.dest),
+            FileSortMode::Episode => match (a.src_descriptor, b.src_descriptor) {
+                (Some(a), Some(b)) => (a.season, a.episode).cmp(&(b.season, b.episode)),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            },
+            FileSortMode::SizeDesc => b.size.cmp(&a.size),
+        };
+        ordering.then_with(|| a.src.cmp(&b.src))
+    });
+    indices
+}
+
 impl<'a> MutableAppFileList<'a> {
     pub(crate) fn new(
         file_list: RwLockReadGuard<'a, Vec<AppFile>>,
@@ -260,6 +523,70 @@ impl<'a> MutableAppFileList<'a> {
         })
     }
 
+    pub fn get_file_tracker(&self) -> &FileTracker {
+        &self.file_tracker
+    }
+
+    pub fn get_sorted_indices(&self, mode: FileSortMode) -> Vec<usize> {
+        let indices = (0..self.file_list.len()).collect();
+        get_sorted_indices(self.file_list.as_slice(), indices, mode)
+    }
+
+    pub fn get_sorted_action_indices(&self, action: Action, mode: FileSortMode) -> Vec<usize> {
+        let indices = self.file_tracker.get_action_indices(action).iter().copied().collect();
+        get_sorted_indices(self.file_list.as_slice(), indices, mode)
+    }
+
+    pub fn change_queue_len(&self) -> usize {
+        self.change_queue.len()
+    }
+
+    pub fn get_indices_where(&self, mut predicate: impl FnMut(&ImmutableAppFile<'_>) -> bool) -> Vec<usize> {
+        self.file_list
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| predicate(&ImmutableAppFile { file, file_tracker: &self.file_tracker }))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // Changes are queued for every matching file since the queue may already contain changes to them
+    // These return the number of files that changes were queued for
+    pub fn set_enabled_for_indices(&mut self, indices: &[usize], is_enabled: bool) -> usize {
+        let mut total_queued = 0;
+        for index in indices.iter().copied() {
+            if let Some(mut file) = self.get(index) {
+                file.set_is_enabled(is_enabled);
+                total_queued += 1;
+            }
+        }
+        total_queued
+    }
+
+    pub fn set_enabled_where(&mut self, predicate: impl FnMut(&ImmutableAppFile<'_>) -> bool, is_enabled: bool) -> usize {
+        let indices = self.get_indices_where(predicate);
+        self.set_enabled_for_indices(indices.as_slice(), is_enabled)
+    }
+
+    pub fn set_action_where(&mut self, predicate: impl FnMut(&ImmutableAppFile<'_>) -> bool, action: Action) -> usize {
+        let indices = self.get_indices_where(predicate);
+        self.set_action_for_indices(indices.as_slice(), action)
+    }
+
+    pub fn set_action_for_indices(&mut self, indices: &[usize], action: Action) -> usize {
+        let mut total_queued = 0;
+        for index in indices.iter().copied() {
+            // Avoid disabling deletes that were already selected
+            let mut file = match self.get(index) {
+                Some(file) if file.get_action() != action => file,
+                _ => continue,
+            };
+            file.set_action(action);
+            total_queued += 1;
+        }
+        total_queued
+    }
+
     pub fn to_iter(&mut self) -> MutableAppFileIterator<'_> {
         MutableAppFileIterator {
             index: 0,
@@ -294,6 +621,20 @@ impl<'a> ImmutableAppFileList<'a> {
         })
     }
 
+    pub fn get_file_tracker(&self) -> &FileTracker {
+        &self.file_tracker
+    }
+
+    pub fn get_sorted_indices(&self, mode: FileSortMode) -> Vec<usize> {
+        let indices = (0..self.file_list.len()).collect();
+        get_sorted_indices(self.file_list.as_slice(), indices, mode)
+    }
+
+    pub fn get_sorted_action_indices(&self, action: Action, mode: FileSortMode) -> Vec<usize> {
+        let indices = self.file_tracker.get_action_indices(action).iter().copied().collect();
+        get_sorted_indices(self.file_list.as_slice(), indices, mode)
+    }
+
     pub fn to_iter(&self) -> ImmutableAppFileIterator<'_> {
         ImmutableAppFileIterator {
             index: 0,
@@ -362,6 +703,26 @@ macro_rules! generate_app_file_getters {
                 self.file.is_enabled
             }
 
+            pub fn get_size(&self) -> u64 {
+                self.file.size
+            }
+
+            pub fn get_modified(&self) -> Option<SystemTime> {
+                self.file.modified
+            }
+
+            pub fn get_last_error(&self) -> Option<&str> {
+                self.file.last_error.as_deref()
+            }
+
+            // Other actions don't use their destination
+            pub fn get_dest_error(&self) -> Option<&PathError> {
+                match self.file.action {
+                    Action::Rename => self.file.dest_error.as_ref(),
+                    _ => None,
+                }
+            }
+
             pub fn get_is_conflict(&self) -> bool {
                 let file = &self.file;
                 if !file.is_enabled || file.action != Action::Rename {
@@ -377,25 +738,402 @@ generate_app_file_getters!(ImmutableAppFile);
 generate_app_file_getters!(MutableAppFile);
 
 impl MutableAppFile<'_> {
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
     pub fn set_action(&mut self, new_action: Action) {
-        self.change_queue.push(FileChange::SetAction(self.index, new_action));
+        push_file_change(self.change_queue, FileChange::SetAction(self.index, new_action));
         // Automatically set destination to src is not set
         if self.file.action != Action::Rename && new_action == Action::Rename && self.file.dest.is_empty() {
-            self.change_queue.push(FileChange::Destination(self.index, self.file.src.to_owned())); 
+            push_file_change(self.change_queue, FileChange::Destination(self.index, self.file.src.to_owned())); 
         }
         // Automatically disable enabled if we are deleting it
         if new_action == Action::Delete {
-            self.change_queue.push(FileChange::IsEnabled(self.index, false));
+            push_file_change(self.change_queue, FileChange::IsEnabled(self.index, false));
         }
     }
 
     pub fn set_is_enabled(&mut self, new_is_enabled: bool) {
         let change = FileChange::IsEnabled(self.index, new_is_enabled);
-        self.change_queue.push(change);
+        push_file_change(self.change_queue, change);
     }
 
     pub fn set_dest(&mut self, new_dest: String) {
         let change = FileChange::Destination(self.index, new_dest);
-        self.change_queue.push(change);
+        push_file_change(self.change_queue, change);
+    }
+
+    // The destination isn't updated so the caller has to set it for the new episode
+    pub(crate) fn set_src_descriptor(&mut self, new_descriptor: Option<EpisodeKey>) {
+        let change = FileChange::Descriptor(self.index, new_descriptor);
+        push_file_change(self.change_queue, change);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_file(src: &str, action: Action, dest: &str) -> AppFile {
+        AppFile::new(src.to_string(), None, action, dest.to_string(), None, 0)
+    }
+
+    // Seeds the tracker the same way a scan does and enables every rename
+    fn track_files(file_list: &mut [AppFile], mut file_tracker: FileTracker) -> FileTracker {
+        for (index, file) in file_list.iter_mut().enumerate() {
+            file.update_dest_error(file_tracker.get_max_dest_length());
+            file_tracker.insert_existing_source(file.src.as_str(), index);
+            file_tracker.insert_file_action(file, index);
+        }
+        let mut change_queue: Vec<FileChange> = file_list
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| file.action == Action::Rename)
+            .map(|(index, _)| FileChange::IsEnabled(index, true))
+            .collect();
+        flush_file_changes_acquired(file_list, &mut file_tracker, &mut change_queue);
+        file_tracker
+    }
+
+    #[test]
+    fn case_only_rename_is_not_a_conflict() {
+        let mut file_list = vec![
+            new_file("season 01/show-s01e01.mkv", Action::Rename, "Season 01/Show-S01E01.mkv"),
+        ];
+        let file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(true));
+        assert!(file_tracker.get_conflicts().is_empty());
+        assert!(!file_tracker.check_if_write_conflicts("Season 01/Show-S01E01.mkv"));
+        assert_eq!(file_tracker.get_source_index("SEASON 01/SHOW-S01E01.MKV"), Some(&0));
+    }
+
+    #[test]
+    fn rename_onto_existing_file_differing_by_case_conflicts() {
+        let files = || vec![
+            new_file("Show-S01E01.mkv", Action::Complete, ""),
+            new_file("show.s01e01.repack.mkv", Action::Rename, "show-s01e01.mkv"),
+        ];
+
+        let mut file_list = files();
+        let file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(true));
+        let conflicts = file_tracker.get_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].source_index, Some(0));
+        assert_eq!(conflicts[0].writer_indices, vec![1]);
+
+        let mut file_list = files();
+        let file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(false));
+        assert!(file_tracker.get_conflicts().is_empty());
+    }
+
+    #[test]
+    fn two_sources_colliding_on_folded_destination() {
+        let files = || vec![
+            new_file("a.mkv", Action::Rename, "Season 01/Show-S01E01.mkv"),
+            new_file("b.mkv", Action::Rename, "season 01/show-s01e01.mkv"),
+        ];
+
+        let mut file_list = files();
+        let file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(true));
+        let conflicts = file_tracker.get_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].key, "season 01/show-s01e01.mkv");
+        // The destination keeps its case so it can be shown and written to disk
+        assert_eq!(conflicts[0].dest, "Season 01/Show-S01E01.mkv");
+        assert_eq!(conflicts[0].source_index, None);
+        assert_eq!(conflicts[0].writer_indices, vec![0, 1]);
+
+        let mut file_list = files();
+        let file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(false));
+        assert!(file_tracker.get_conflicts().is_empty());
+    }
+
+    #[test]
+    fn destination_over_length_limit_is_flagged() {
+        let mut file_list = vec![
+            new_file("a.mkv", Action::Rename, "Season 01/Show-S01E01.mkv"),
+            new_file("b.mkv", Action::Rename, "Season 01/Show-S01E02-A.Much.Longer.Title.mkv"),
+        ];
+        let mut file_tracker = FileTracker::with_case_folding(false);
+        file_tracker.set_max_dest_length(30);
+        let mut file_tracker = track_files(&mut file_list, file_tracker);
+        assert_eq!(file_list[0].dest_error, None);
+        assert_eq!(file_list[1].dest_error, Some(PathError::TooLong(15)));
+
+        // Edited destinations are checked against the same limit
+        let mut change_queue = vec![
+            FileChange::Destination(0, "Season 01/Show-S01E01-Pilot.Extended.mkv".to_string()),
+            FileChange::Destination(1, "Season 01/Show-S01E02.mkv".to_string()),
+        ];
+        flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+        assert_eq!(file_list[0].dest_error, Some(PathError::TooLong(10)));
+        assert_eq!(file_list[1].dest_error, None);
+    }
+
+    fn new_sized_file(src: &str, descriptor: Option<(u32, u32)>, dest: &str, size: u64) -> AppFile {
+        let descriptor = descriptor.map(|(season, episode)| EpisodeKey { season, episode });
+        AppFile::new(src.to_string(), descriptor, Action::Rename, dest.to_string(), None, size)
+    }
+
+    // Listed out of order so every mode has to reorder them
+    fn new_sort_list() -> Vec<AppFile> {
+        vec![
+            new_sized_file("d.s01e02.mkv", Some((1, 2)), "Season 01/E02.mkv", 300),
+            new_sized_file("notes.txt", None, "Extras/notes.txt", 10),
+            new_sized_file("b.s02e01.mkv", Some((2, 1)), "Season 02/E01.mkv", 300),
+            new_sized_file("a.s01e02.mkv", Some((1, 2)), "Season 01/E02.mkv", 500),
+            new_sized_file("c.s01e01.mkv", Some((1, 1)), "Season 01/E01.mkv", 100),
+            new_sized_file("extra.txt", None, "Extras/extra.txt", 300),
+        ]
+    }
+
+    fn get_sorted_sources(file_list: &[AppFile], mode: FileSortMode) -> Vec<&str> {
+        let indices = get_sorted_indices(file_list, (0..file_list.len()).collect(), mode);
+        indices.iter().map(|index| file_list[*index].src.as_str()).collect()
+    }
+
+    #[test]
+    fn files_are_sorted_by_source() {
+        let file_list = new_sort_list();
+        assert_eq!(
+            get_sorted_sources(file_list.as_slice(), FileSortMode::Source),
+            vec!["a.s01e02.mkv", "b.s02e01.mkv", "c.s01e01.mkv", "d.s01e02.mkv", "extra.txt", "notes.txt"],
+        );
+    }
+
+    #[test]
+    fn files_with_same_destination_are_ordered_by_source() {
+        let file_list = new_sort_list();
+        assert_eq!(
+            get_sorted_sources(file_list.as_slice(), FileSortMode::Destination),
+            vec!["extra.txt", "notes.txt", "c.s01e01.mkv", "a.s01e02.mkv", "d.s01e02.mkv", "b.s02e01.mkv"],
+        );
+    }
+
+    #[test]
+    fn files_without_episode_are_sorted_last() {
+        let file_list = new_sort_list();
+        assert_eq!(
+            get_sorted_sources(file_list.as_slice(), FileSortMode::Episode),
+            vec!["c.s01e01.mkv", "a.s01e02.mkv", "d.s01e02.mkv", "b.s02e01.mkv", "extra.txt", "notes.txt"],
+        );
+    }
+
+    #[test]
+    fn largest_files_are_sorted_first() {
+        let file_list = new_sort_list();
+        assert_eq!(
+            get_sorted_sources(file_list.as_slice(), FileSortMode::SizeDesc),
+            vec!["a.s01e02.mkv", "b.s02e01.mkv", "d.s01e02.mkv", "extra.txt", "c.s01e01.mkv", "notes.txt"],
+        );
+    }
+
+    #[test]
+    fn sorting_subset_keeps_file_indices() {
+        let file_list = new_sort_list();
+        // Only the listed files are sorted and their indices still refer to the whole list
+        let indices = get_sorted_indices(file_list.as_slice(), vec![5, 1, 0], FileSortMode::SizeDesc);
+        assert_eq!(indices, vec![0, 5, 1]);
+        let indices = get_sorted_indices(file_list.as_slice(), vec![5, 1, 0], FileSortMode::Episode);
+        assert_eq!(indices, vec![0, 5, 1]);
+    }
+
+    #[test]
+    fn conflict_groups_are_sorted_by_destination() {
+        let mut file_list = vec![
+            new_file("a.mkv", Action::Rename, "Season 02/E01.mkv"),
+            new_file("b.mkv", Action::Rename, "Season 01/E01.mkv"),
+            new_file("c.mkv", Action::Rename, "Season 02/E01.mkv"),
+            new_file("d.mkv", Action::Rename, "Season 01/E01.mkv"),
+            new_file("e.mkv", Action::Rename, "Season 01/E02.mkv"),
+        ];
+        let file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(false));
+        let groups: Vec<(&str, Option<usize>, Vec<usize>)> = file_tracker.get_conflicts()
+            .iter()
+            .map(|group| (group.dest.as_str(), group.source_index, group.writer_indices.clone()))
+            .collect();
+        assert_eq!(groups, vec![
+            ("Season 01/E01.mkv", None, vec![1, 3]),
+            ("Season 02/E01.mkv", None, vec![0, 2]),
+        ]);
+        assert_eq!(file_tracker.get_conflict_count(), 2);
+    }
+
+    #[test]
+    fn existing_source_conflicts_unless_vacated() {
+        let mut file_list = vec![
+            new_file("Season 01/E01.mkv", Action::Complete, ""),
+            new_file("a.mkv", Action::Rename, "Season 01/E01.mkv"),
+        ];
+        let mut file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(false));
+        assert_eq!(file_tracker.get_conflicts(), &[ConflictGroup {
+            key: "Season 01/E01.mkv".to_string(),
+            dest: "Season 01/E01.mkv".to_string(),
+            source_index: Some(0),
+            writer_indices: vec![1],
+        }]);
+
+        // Moving the existing file out of the way resolves the conflict
+        let mut change_queue = vec![
+            FileChange::SetAction(0, Action::Rename),
+            FileChange::Destination(0, "Season 01/E01-Old.mkv".to_string()),
+            FileChange::IsEnabled(0, true),
+        ];
+        flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+        assert!(file_tracker.get_conflicts().is_empty());
+        assert_eq!(file_tracker.get_conflict_count(), 0);
+    }
+
+    #[test]
+    fn disabled_writer_leaves_conflict_group() {
+        let mut file_list = vec![
+            new_file("a.mkv", Action::Rename, "E01.mkv"),
+            new_file("b.mkv", Action::Rename, "E01.mkv"),
+            new_file("c.mkv", Action::Rename, "E01.mkv"),
+        ];
+        let mut file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(false));
+        assert_eq!(file_tracker.get_conflicts()[0].writer_indices, vec![0, 1, 2]);
+
+        let mut change_queue = vec![FileChange::IsEnabled(1, false)];
+        flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+        assert_eq!(file_tracker.get_conflicts()[0].writer_indices, vec![0, 2]);
+
+        let mut change_queue = vec![FileChange::IsEnabled(2, false)];
+        flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+        assert!(file_tracker.get_conflicts().is_empty());
+    }
+
+    type FileSnapshot = (Vec<(Action, String, bool)>, Vec<usize>, Vec<ConflictGroup>, Vec<(String, Vec<usize>)>);
+
+    fn get_snapshot(file_list: &[AppFile], file_tracker: &FileTracker) -> FileSnapshot {
+        let files = file_list.iter().map(|file| (file.action, file.dest.clone(), file.is_enabled)).collect();
+        let action_count = Action::iterator().map(|action| file_tracker.get_action_count()[*action]).collect();
+        let mut pending_writes: Vec<(String, Vec<usize>)> = file_tracker.get_pending_writes()
+            .iter()
+            .filter(|(_, indices)| !indices.is_empty())
+            .map(|(dest, indices)| {
+                let mut indices: Vec<usize> = indices.iter().copied().collect();
+                indices.sort();
+                (dest.clone(), indices)
+            })
+            .collect();
+        pending_writes.sort();
+        (files, action_count, file_tracker.get_conflicts().to_vec(), pending_writes)
+    }
+
+    fn new_coalesce_list() -> Vec<AppFile> {
+        vec![
+            new_file("a.mkv", Action::Ignore, ""),
+            new_file("b.mkv", Action::Rename, "Season 01/B.mkv"),
+            new_file("c.nfo", Action::Delete, ""),
+        ]
+    }
+
+    // Flushes the changes as they were pushed and again after coalescing them
+    fn assert_coalesced_flush_matches(changes: impl Fn() -> Vec<FileChange>, expected_len: usize) {
+        let mut file_list = new_coalesce_list();
+        let mut file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(false));
+        let mut change_queue = changes();
+        flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+        let expected = get_snapshot(&file_list, &file_tracker);
+
+        let mut file_list = new_coalesce_list();
+        let mut file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(false));
+        let mut change_queue = Vec::new();
+        for change in changes() {
+            push_file_change(&mut change_queue, change);
+        }
+        assert_eq!(change_queue.len(), expected_len);
+        flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+        assert_eq!(get_snapshot(&file_list, &file_tracker), expected);
+    }
+
+    #[test]
+    fn coalesced_keystrokes_flush_the_same() {
+        assert_coalesced_flush_matches(|| vec![
+            FileChange::Destination(1, "S".to_string()),
+            FileChange::Destination(1, "Se".to_string()),
+            FileChange::Destination(1, "Season 01/E01.mkv".to_string()),
+            FileChange::IsEnabled(1, false),
+            FileChange::IsEnabled(1, true),
+        ], 2);
+    }
+
+    #[test]
+    fn coalesced_action_then_destination_flush_the_same() {
+        // Renaming a file without a destination pushes its source before the user types a new one
+        assert_coalesced_flush_matches(|| vec![
+            FileChange::SetAction(0, Action::Rename),
+            FileChange::Destination(0, "a.mkv".to_string()),
+            FileChange::Destination(0, "Season 01/B".to_string()),
+            FileChange::Destination(0, "Season 01/B.mkv".to_string()),
+            FileChange::IsEnabled(0, true),
+        ], 3);
+    }
+
+    #[test]
+    fn coalesced_changes_keep_order_across_files_and_kinds() {
+        assert_coalesced_flush_matches(|| vec![
+            FileChange::SetAction(2, Action::Rename),
+            FileChange::Destination(2, "c.nfo".to_string()),
+            FileChange::SetAction(2, Action::Ignore),
+            FileChange::SetAction(1, Action::Delete),
+            FileChange::IsEnabled(1, false),
+            FileChange::SetAction(1, Action::Rename),
+            FileChange::Destination(0, "Season 01/B.mkv".to_string()),
+            FileChange::SetAction(0, Action::Rename),
+            FileChange::SetAction(0, Action::Rename),
+            FileChange::IsEnabled(0, true),
+            FileChange::IsEnabled(1, true),
+        ], 10);
+    }
+
+    #[test]
+    fn auto_destination_is_kept_when_typing_after_renaming() {
+        let mut file_list = new_coalesce_list();
+        let file_tracker = tokio::sync::RwLock::new(track_files(&mut file_list, FileTracker::with_case_folding(false)));
+        let file_list = tokio::sync::RwLock::new(file_list);
+        let change_queue = tokio::sync::RwLock::new(Vec::new());
+        {
+            let mut files = MutableAppFileList::new(
+                file_list.try_read().unwrap(),
+                file_tracker.try_read().unwrap(),
+                change_queue.try_write().unwrap(),
+            );
+            let mut file = files.get(0).unwrap();
+            file.set_action(Action::Rename);
+            for length in 1..="Season 01/A.mkv".len() {
+                file.set_dest("Season 01/A.mkv"[..length].to_string());
+            }
+            assert_eq!(files.change_queue_len(), 2);
+        }
+        let mut file_list = file_list.into_inner();
+        let mut file_tracker = file_tracker.into_inner();
+        let mut change_queue = change_queue.into_inner();
+        flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+        assert_eq!((file_list[0].action, file_list[0].dest.as_str()), (Action::Rename, "Season 01/A.mkv"));
+        assert_eq!(file_tracker.get_action_count()[Action::Rename], 2);
+        assert!(change_queue.is_empty());
+    }
+
+    #[test]
+    fn edited_destination_with_invalid_characters_is_flagged() {
+        let mut file_list = vec![new_file("a.mkv", Action::Rename, "Season 01/Show-S01E01.mkv")];
+        let mut file_tracker = track_files(&mut file_list, FileTracker::with_case_folding(false));
+        assert_eq!(file_list[0].dest_error, None);
+
+        let edits = [
+            ("Season 01/Show: Pilot.mkv", Some(PathError::InvalidCharacters("Show: Pilot.mkv".to_string()))),
+            ("Season 01/NUL.mkv", Some(PathError::ReservedName("NUL.mkv".to_string()))),
+            ("../Show-S01E01.mkv", Some(PathError::OutsideFolder)),
+            ("Season 01/Show-S01E01-Pilot.mkv", None),
+        ];
+        for (dest, error) in edits {
+            let mut change_queue = vec![FileChange::Destination(0, dest.to_string())];
+            flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+            // The destination is kept as typed so the user can fix it
+            assert_eq!(file_list[0].dest, dest);
+            assert_eq!(file_list[0].dest_error, error, "{}", dest);
+        }
     }
 }
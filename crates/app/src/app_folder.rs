@@ -1,27 +1,81 @@
 use async_recursion;
+use async_trait;
+use chrono;
 use enum_map;
+use filetime;
 use futures;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde;
 use serde_json;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path;
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{broadcast, Mutex, RwLock, watch};
 use tokio;
-use tvdb::api::LoginSession;
-use tvdb::models::{Episode, Series};
+use trash;
+use tvdb::models::{Actor, Episode, EpisodesSummary, Series};
 use walkdir;
 use crate::app_file::{
     AppFile, FileChange, 
     MutableAppFileList, ImmutableAppFileList, 
     FileTracker, 
-    flush_file_changes_acquired,
+    flush_file_changes_acquired, get_path_key,
 };
+use crate::atomic_write::{atomic_write, TEMPORARY_WRITE_SUFFIX};
+use crate::error_log::ErrorLog;
+use crate::change_event::{BusyLockGuard, ChangeEvent, ChangeKind, send_change};
 use crate::bookmarks::{BookmarkTable, deserialize_bookmarks, serialize_bookmarks};
-use crate::file_intent::{FilterRules, Action, get_file_intent};
-use crate::tvdb_cache::{EpisodeKey, TvdbCache};
+use crate::file_intent::{FilterRules, Action, get_file_intent, get_file_intent_for_episode};
+use crate::image_cache::{ImageCache, PATH_STR_ARTWORK_FOLDER};
+use crate::destination::get_max_destination_length;
+use crate::metadata_provider::{MetadataProvider, get_rate_limit_message};
+use crate::series_cache_registry::{SeriesCacheKey, SeriesCacheRegistry};
+use crate::undo_journal::UndoJournal;
+use crate::tvdb_cache::{CacheFile, CacheMeta, CACHE_FILE_VERSION, EpisodeKey, find_foreign_episodes, EpisodeOrdering, MissingEpisodeFilter, TvdbCache};
 
 const PATH_STR_BOOKMARKS: &str = "bookmarks.json";
+const PATH_STR_IGNORED_FILES: &str = "ignored_files.json";
 const PATH_STR_EPISODES_DATA: &str = "episodes.json";
 const PATH_STR_SERIES_DATA: &str = "series.json";
+const PATH_STR_ACTORS_DATA: &str = "actors.json";
+const PATH_STR_EPISODES_SUMMARY_DATA: &str = "episodes_summary.json";
+const PATH_STR_CACHE_META: &str = "cache_meta.json";
+const PATH_STR_CACHE_DATA: &str = "tvdb_cache.json";
+const PATH_STR_PLAN: &str = "plan.json";
+const PATH_STR_UNDO_JOURNAL: &str = "undo_journal.json";
+const PATH_STR_FOLDER_CONFIG: &str = "folder_config.json";
+// Stored at the root path instead of in each folder
+pub(crate) const PATH_STR_SERIES_MAPPING: &str = "series_mapping.json";
+pub(crate) const PATH_STR_SERIES_MAPPING_TMDB: &str = "series_mapping_tmdb.json";
+pub(crate) const PATH_STR_SERIES_MAPPING_TVMAZE: &str = "series_mapping_tvmaze.json";
+pub(crate) const PATH_STR_FOLDER_IGNORE: &str = ".renamerignore";
+// Files used by the app at the root of the folder which are never renamed or deleted
+const RESERVED_FILENAMES: [&str; 15] = [
+    PATH_STR_BOOKMARKS, PATH_STR_EPISODES_DATA, PATH_STR_IGNORED_FILES, PATH_STR_PLAN, PATH_STR_SERIES_DATA,
+    PATH_STR_SERIES_MAPPING, PATH_STR_SERIES_MAPPING_TMDB, PATH_STR_SERIES_MAPPING_TVMAZE, PATH_STR_FOLDER_IGNORE,
+    PATH_STR_FOLDER_CONFIG, PATH_STR_ACTORS_DATA, PATH_STR_EPISODES_SUMMARY_DATA, PATH_STR_CACHE_META,
+    PATH_STR_CACHE_DATA, PATH_STR_UNDO_JOURNAL,
+];
+const MAX_CONCURRENT_FILE_OPERATIONS: usize = 32;
+const COPY_CHUNK_SIZE: usize = 1024*1024;
+const TEMPORARY_RENAME_SUFFIX: &str = ".renaming";
+
+// Temporary files left behind by an interrupted write are also reserved
+fn is_reserved_filename(filename: &str) -> bool {
+    let filename = filename.strip_suffix(TEMPORARY_WRITE_SUFFIX).unwrap_or(filename);
+    RESERVED_FILENAMES.contains(&filename)
+}
+
+// Files written by the app itself which shouldn't trigger a rescan when they change
+pub fn is_app_metadata_path(path: &path::Path) -> bool {
+    let filename = match path.file_name() {
+        Some(filename) => filename.to_string_lossy(),
+        None => return false,
+    };
+    let is_artwork = path.components().any(|component| component.as_os_str() == PATH_STR_ARTWORK_FOLDER);
+    is_artwork || is_reserved_filename(filename.as_ref()) || filename.ends_with(TEMPORARY_RENAME_SUFFIX)
+}
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, enum_map::Enum)]
 pub enum FolderStatus {
@@ -52,35 +106,376 @@ impl FolderStatus {
     }
 }
 
+// Order in which folders are listed, this doesn't change the order of the loaded folders
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all="snake_case")]
+pub enum FolderSortMode {
+    #[default]
+    Name,
+    Status,
+    PendingDesc,
+}
+
+impl FolderSortMode {
+    pub fn iterator() -> std::slice::Iter<'static, Self> {
+        static MODES: [FolderSortMode;3] = [
+            FolderSortMode::Name,
+            FolderSortMode::Status,
+            FolderSortMode::PendingDesc,
+        ];
+        MODES.iter()
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            FolderSortMode::Name => "Name",
+            FolderSortMode::Status => "Status",
+            FolderSortMode::PendingDesc => "Most pending",
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ConflictResolution {
+    SuffixCounter,
+    KeepLargest,
+    DisableAll,
+}
+
+impl ConflictResolution {
+    pub fn iterator() -> std::slice::Iter<'static, Self> {
+        static RESOLUTIONS: [ConflictResolution;3] = [
+            ConflictResolution::SuffixCounter,
+            ConflictResolution::KeepLargest,
+            ConflictResolution::DisableAll,
+        ];
+        RESOLUTIONS.iter()
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            ConflictResolution::SuffixCounter => "Add counter suffix",
+            ConflictResolution::KeepLargest => "Keep largest",
+            ConflictResolution::DisableAll => "Disable all",
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum DuplicateResolution {
+    KeepLargest,
+    KeepNewest,
+}
+
+impl DuplicateResolution {
+    pub fn iterator() -> std::slice::Iter<'static, Self> {
+        static RESOLUTIONS: [DuplicateResolution;2] = [
+            DuplicateResolution::KeepLargest,
+            DuplicateResolution::KeepNewest,
+        ];
+        RESOLUTIONS.iter()
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            DuplicateResolution::KeepLargest => "Keep largest",
+            DuplicateResolution::KeepNewest => "Keep newest",
+        }
+    }
+}
+
+// Video files of the same episode, i.e. the original release and a repack
+// The indices refer to the file list and are found again whenever the folder is rescanned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub key: EpisodeKey,
+    pub indices: Vec<usize>,
+}
+
+// Subtitles share the episode of their video so they aren't duplicates of it
+const SUBTITLE_EXTENSIONS: [&str;7] = ["srt", "ass", "ssa", "sub", "idx", "vtt", "sup"];
+
+fn is_subtitle_file(src: &str) -> bool {
+    path::Path::new(src)
+        .extension()
+        .map(|extension| extension.to_string_lossy())
+        .map(|extension| SUBTITLE_EXTENSIONS.iter().any(|subtitle| subtitle.eq_ignore_ascii_case(&extension)))
+        .unwrap_or(false)
+}
+
+fn find_duplicate_groups(file_list: &[AppFile]) -> Vec<DuplicateGroup> {
+    let mut episode_indices = HashMap::<EpisodeKey, Vec<usize>>::new();
+    for (index, file) in file_list.iter().enumerate() {
+        if file.action != Action::Rename && file.action != Action::Complete {
+            continue;
+        }
+        let key = match file.src_descriptor {
+            Some(key) => key,
+            None => continue,
+        };
+        if is_subtitle_file(file.src.as_str()) {
+            continue;
+        }
+        episode_indices.entry(key).or_default().push(index);
+    }
+    let mut groups: Vec<DuplicateGroup> = episode_indices
+        .into_iter()
+        .filter(|(_, indices)| indices.len() > 1)
+        .map(|(key, indices)| DuplicateGroup { key, indices })
+        .collect();
+    groups.sort_by_key(|group| (group.key.season, group.key.episode));
+    groups
+}
+
+// Insert a counter before the extension, i.e. "Show-S01E01.mkv" becomes "Show-S01E01 (1).mkv"
+fn add_counter_suffix(dest: &str, counter: usize) -> String {
+    let filename_start = dest.rfind('/').map(|index| index + 1).unwrap_or(0);
+    match dest[filename_start..].rfind('.') {
+        Some(index) if index > 0 => {
+            let (stem, extension) = dest.split_at(filename_start + index);
+            format!("{} ({}){}", stem, counter, extension)
+        },
+        _ => format!("{} ({})", dest, counter),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedOperation {
+    Rename { src: String, dest: String },
+    Delete { src: String },
+    CreateDir { path: String },
+    RemoveEmptyDir { path: String },
+}
+
+impl PlannedOperation {
+    pub fn get_path(&self) -> &str {
+        match self {
+            PlannedOperation::Rename { src, .. } => src.as_str(),
+            PlannedOperation::Delete { src } => src.as_str(),
+            PlannedOperation::CreateDir { path } => path.as_str(),
+            PlannedOperation::RemoveEmptyDir { path } => path.as_str(),
+        }
+    }
+}
+
+impl std::fmt::Display for PlannedOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlannedOperation::Rename { src, dest } => write!(f, "rename {} to {}", src, dest),
+            PlannedOperation::Delete { src } => write!(f, "delete {}", src),
+            PlannedOperation::CreateDir { path } => write!(f, "create folder {}", path),
+            PlannedOperation::RemoveEmptyDir { path } => write!(f, "remove empty folder {}", path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionProgress {
+    pub total_completed: usize,
+    pub total_operations: usize,
+    pub current_file: Option<String>,
+    // Files moved across filesystems are copied
+    pub total_bytes_copied: u64,
+    pub total_bytes_to_copy: u64,
+}
+
+impl ExecutionProgress {
+    pub fn is_idle(&self) -> bool {
+        self.total_operations == 0
+    }
+}
+
+// Summary of what execute_file_changes(...) would do so it can be confirmed before anything is changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionPlan {
+    pub operations: Vec<PlannedOperation>,
+    pub total_renames: usize,
+    pub total_deletes: usize,
+    pub total_bytes_to_delete: u64,
+    pub skipped_conflicts: usize,
+    pub skipped_invalid: usize,
+}
+
+// Counts from the last scan of a folder which are cheap enough to show for every folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderActionCounts {
+    pub action_count: enum_map::EnumMap<Action, usize>,
+    pub total_conflicts: usize,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionSummary {
+    pub renamed: usize,
+    pub deleted: usize,
+    pub failed: usize,
+    pub skipped_conflicts: usize,
+    pub skipped_invalid: usize,
+    pub removed_dirs: usize,
+    // Bookmarks of files that are gone after the rescan following execution
+    pub pruned_bookmarks: usize,
+}
+
+impl std::ops::AddAssign<&ExecutionSummary> for ExecutionSummary {
+    fn add_assign(&mut self, other: &ExecutionSummary) {
+        self.renamed += other.renamed;
+        self.deleted += other.deleted;
+        self.failed += other.failed;
+        self.skipped_conflicts += other.skipped_conflicts;
+        self.skipped_invalid += other.skipped_invalid;
+        self.removed_dirs += other.removed_dirs;
+        self.pruned_bookmarks += other.pruned_bookmarks;
+    }
+}
+
+impl std::fmt::Display for ExecutionSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Renamed {}, deleted {}", self.renamed, self.deleted)?;
+        if self.removed_dirs > 0 {
+            write!(f, ", removed {} empty folders", self.removed_dirs)?;
+        }
+        if self.pruned_bookmarks > 0 {
+            write!(f, ", pruned {} bookmarks of missing files", self.pruned_bookmarks)?;
+        }
+        if self.skipped_conflicts > 0 {
+            write!(f, ", skipped {} conflicts", self.skipped_conflicts)?;
+        }
+        if self.skipped_invalid > 0 {
+            write!(f, ", skipped {} invalid destinations", self.skipped_invalid)?;
+        }
+        if self.failed > 0 {
+            write!(f, ", {} failed", self.failed)?;
+        }
+        Ok(())
+    }
+}
+
+// The outcome of renaming or deleting a file during the last execution
+// Renames through a temporary path are recorded once with the original source and final destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionRecord {
+    pub src: String,
+    pub dest: String,
+    pub action: Action,
+    pub result: Result<(), String>,
+}
+
+impl ExecutionRecord {
+    pub fn is_ok(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+// An error that was caused by a specific file or folder in the folder
+// The index refers to the current file list and is updated whenever the folder is rescanned
+#[derive(Debug, Clone)]
+pub struct FileError {
+    pub file_index: Option<usize>,
+    pub src: String,
+    pub message: String,
+    pub kind: std::io::ErrorKind,
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// A row of an exported plan which can be edited externally and imported back
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct PlanEntry {
+    pub src: String,
+    pub action: Action,
+    #[serde(default)]
+    pub dest: String,
+    pub is_enabled: bool,
+}
+
+// Settings which override the app config for a single folder
+#[serde_with::skip_serializing_none]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct FolderConfig {
+    // Sent as the Accept-Language header when fetching the cache, i.e. "ja"
+    pub language: Option<String>,
+    // Defaults to the aired order if not set
+    pub episode_ordering: Option<EpisodeOrdering>,
+}
+
+// Destinations have to stay inside of the folder
+fn is_valid_plan_dest(dest: &str) -> bool {
+    let path = path::Path::new(dest);
+    !dest.is_empty() && path.components().all(|component| matches!(component, path::Component::Normal(_)))
+}
+
+// Folders are named by their path relative to the root using '/' as the separator
+pub(crate) fn get_relative_folder_name(root_path: &str, folder_path: &str) -> String {
+    match path::Path::new(folder_path).strip_prefix(root_path) {
+        Ok(name) => name.to_string_lossy().to_string(),
+        Err(_) => folder_path.to_string(),
+    }.replace(std::path::MAIN_SEPARATOR, "/")
+}
+
 pub struct AppFolder {
     folder_path: String,
     folder_name: String,
     bookmarks_path: String,
+    ignored_files_path: String,
+    plan_path: String,
+    undo_journal_path: String,
+    folder_config_path: String,
     series_path: String,
     episodes_path: String,
+    actors_path: String,
+    episodes_summary_path: String,
+    cache_meta_path: String,
+    cache_path: String,
 
     filter_rules: Arc<FilterRules>,
-    cache: RwLock<Option<TvdbCache>>,
+    cache: RwLock<Option<Arc<TvdbCache>>>,
+    cache_registry: Option<Arc<SeriesCacheRegistry>>,
+    is_actors_load_attempted: AtomicBool,
+    image_cache: ImageCache,
+    mapped_series_id: Option<u32>,
 
     file_list: RwLock<Vec<AppFile>>,
     file_tracker: RwLock<FileTracker>,
     change_queue: RwLock<Vec<FileChange>>,
 
     bookmarks: RwLock<BookmarkTable>,
+    ignored_files: RwLock<BTreeSet<String>>,
+    folder_config: RwLock<FolderConfig>,
 
-    errors: RwLock<Vec<String>>,
+    errors: RwLock<ErrorLog>,
+    file_errors: RwLock<Vec<FileError>>,
     busy_lock: Mutex<()>,
+    execution_progress: watch::Sender<ExecutionProgress>,
+    is_cancelled: AtomicBool,
+    is_full_rescan_required: AtomicBool,
+    last_execution_summary: RwLock<Option<ExecutionSummary>>,
+    undo_journal: RwLock<Option<UndoJournal>>,
+    last_execution_records: RwLock<Vec<ExecutionRecord>>,
+    duplicate_groups: RwLock<Vec<DuplicateGroup>>,
     selected_descriptor: RwLock<Option<EpisodeKey>>,
     is_initial_load: Mutex<bool>,
-    is_file_count_init: Mutex<bool>,
+    is_file_count_init: AtomicBool,
+    is_file_list_outdated: AtomicBool,
+    folder_status: watch::Sender<FolderStatus>,
+    change_sender: Option<broadcast::Sender<ChangeEvent>>,
+    file_operations: Box<dyn FileOperations>,
 }
 
 impl AppFolder {
     pub fn new(root_path: &str, folder_path: &str, filter_rules: Arc<FilterRules>) -> Self {
-        let folder_name = match path::Path::new(folder_path).strip_prefix(root_path) {
-            Ok(name) => name.to_string_lossy().to_string(), 
-            Err(_) => folder_path.to_string(),
-        }.replace(std::path::MAIN_SEPARATOR, "/");
+        let folder_name = get_relative_folder_name(root_path, folder_path);
+        // The root itself can be a folder so we name it after its directory
+        let folder_name = match folder_name.is_empty() {
+            true => path::Path::new(folder_path)
+                .canonicalize()
+                .ok()
+                .and_then(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+                .unwrap_or(folder_path.to_string()),
+            false => folder_name,
+        };
 
         let get_filepath = |filename: &str| -> String {
             path::Path::new(folder_path)
@@ -92,42 +487,131 @@ impl AppFolder {
 
         let series_path = get_filepath(PATH_STR_SERIES_DATA);
         let episodes_path = get_filepath(PATH_STR_EPISODES_DATA);
+        let actors_path = get_filepath(PATH_STR_ACTORS_DATA);
+        let episodes_summary_path = get_filepath(PATH_STR_EPISODES_SUMMARY_DATA);
+        let cache_meta_path = get_filepath(PATH_STR_CACHE_META);
+        let cache_path = get_filepath(PATH_STR_CACHE_DATA);
         let bookmarks_path = get_filepath(PATH_STR_BOOKMARKS);
+        let ignored_files_path = get_filepath(PATH_STR_IGNORED_FILES);
+        let plan_path = get_filepath(PATH_STR_PLAN);
+        let undo_journal_path = get_filepath(PATH_STR_UNDO_JOURNAL);
+        let folder_config_path = get_filepath(PATH_STR_FOLDER_CONFIG);
 
         Self {
             folder_path: folder_path.to_string(),
             folder_name,
             series_path,
             episodes_path,
+            actors_path,
+            episodes_summary_path,
+            cache_meta_path,
+            cache_path,
             bookmarks_path,
+            ignored_files_path,
+            plan_path,
+            undo_journal_path,
+            folder_config_path,
 
             filter_rules,
             cache: RwLock::new(None),
+            cache_registry: None,
+            is_actors_load_attempted: AtomicBool::new(false),
+            image_cache: ImageCache::new(get_filepath(PATH_STR_ARTWORK_FOLDER).as_str()),
+            mapped_series_id: None,
 
             file_list: RwLock::new(Vec::new()),
-            file_tracker: RwLock::new(FileTracker::new()),
+            file_tracker: RwLock::new(new_file_tracker(folder_path)),
             change_queue:RwLock::new(Vec::new()),
 
             bookmarks: RwLock::new(BookmarkTable::new()),
+            ignored_files: RwLock::new(BTreeSet::new()),
+            folder_config: RwLock::new(FolderConfig::default()),
 
-            errors: RwLock::new(Vec::new()),
+            errors: RwLock::new(ErrorLog::new()),
+            file_errors: RwLock::new(Vec::new()),
             busy_lock: Mutex::new(()),
+            execution_progress: watch::Sender::new(ExecutionProgress::default()),
+            is_cancelled: AtomicBool::new(false),
+            is_full_rescan_required: AtomicBool::new(true),
+            last_execution_summary: RwLock::new(None),
+            undo_journal: RwLock::new(None),
+            last_execution_records: RwLock::new(Vec::new()),
+            duplicate_groups: RwLock::new(Vec::new()),
             selected_descriptor: RwLock::new(None),
             is_initial_load: Mutex::new(false),
-            is_file_count_init: Mutex::new(false),
+            is_file_count_init: AtomicBool::new(false),
+            is_file_list_outdated: AtomicBool::new(false),
+            folder_status: watch::Sender::new(FolderStatus::Unknown),
+            change_sender: None,
+            file_operations: Box::new(TokioFileOperations),
         }
     }
 }
 
+// The path length limit only applies on Windows
+fn new_file_tracker(folder_path: &str) -> FileTracker {
+    let mut file_tracker = FileTracker::new();
+    if cfg!(windows) {
+        let folder_path = std::path::absolute(folder_path).unwrap_or(path::PathBuf::from(folder_path));
+        file_tracker.set_max_dest_length(get_max_destination_length(folder_path.to_string_lossy().as_ref()));
+    }
+    file_tracker
+}
+
+// Shared by every folder visited while searching for file intents
+struct FileIntentSearch<'a> {
+    root_path: &'a str,
+    cache: &'a TvdbCache,
+    rules: &'a FilterRules,
+    previous_files: &'a HashMap<String, AppFile>,
+    is_cancelled: &'a AtomicBool,
+    // Followed links can lead back to a folder that was already searched
+    visited_folders: std::sync::Mutex<HashSet<path::PathBuf>>,
+}
+
+impl FileIntentSearch<'_> {
+    async fn mark_folder_visited(&self, folder: &path::Path) -> Result<bool, std::io::Error> {
+        let folder = tokio::fs::canonicalize(folder).await?;
+        let mut visited_folders = self.visited_folders.lock().unwrap_or_else(|err| err.into_inner());
+        Ok(visited_folders.insert(folder))
+    }
+}
+
 #[async_recursion::async_recursion]
-async fn recursive_search_file_intents(root_path: &str, curr_folder: &str, cache: &TvdbCache, intents: &mut Vec<AppFile>, rules: &FilterRules) -> Result<(), std::io::Error> {
+async fn recursive_search_file_intents(
+    search: &FileIntentSearch<'_>, curr_folder: &str, intents: &mut Vec<AppFile>, warnings: &mut Vec<String>,
+) -> Result<(), std::io::Error> {
+    let FileIntentSearch { root_path, cache, rules, previous_files, is_cancelled, .. } = *search;
+    if rules.follow_symlinks && !search.mark_folder_visited(path::Path::new(curr_folder)).await? {
+        return Ok(());
+    }
     let mut entries = tokio::fs::read_dir(curr_folder).await?;
     while let Some(entry) = entries.next_entry().await? {
+        if is_cancelled.load(Ordering::Relaxed) {
+            return Err(std::io::Error::from(std::io::ErrorKind::Interrupted));
+        }
+        // Links are skipped unless enabled since they can point outside of the folder
         let file_type = entry.file_type().await?;
+        let is_symlink = file_type.is_symlink();
+        let file_type = match is_symlink {
+            false => file_type,
+            true if rules.follow_symlinks => match tokio::fs::metadata(entry.path()).await {
+                Ok(metadata) => metadata.file_type(),
+                Err(err) => {
+                    warnings.push(format!("Couldn't follow link '{}': {}", entry.path().to_string_lossy(), err));
+                    continue;
+                },
+            },
+            true => continue,
+        };
         if file_type.is_dir() {
             let path = entry.path();
+            // Downloaded artwork isn't part of the folder's files
+            if curr_folder == root_path && entry.file_name() == PATH_STR_ARTWORK_FOLDER {
+                continue;
+            }
             if let Some(sub_folder) = path.to_str() {
-                recursive_search_file_intents(root_path, sub_folder, cache, intents, rules).await?;
+                recursive_search_file_intents(search, sub_folder, intents, warnings).await?;
             };
             continue;
         }
@@ -140,12 +624,41 @@ async fn recursive_search_file_intents(root_path: &str, curr_folder: &str, cache
             };
 
             if let Some(rel_path) = rel_path.to_str() {
+                let src = rel_path.to_string().replace(std::path::MAIN_SEPARATOR, "/");
+                if is_reserved_filename(src.as_str()) {
+                    continue;
+                }
+
+                // A file whose metadata can't be read is still listed, it just never reuses its previous intent
+                let metadata = match is_symlink {
+                    true => tokio::fs::metadata(path.as_path()).await,
+                    false => entry.metadata().await,
+                };
+                let (modified, size) = match metadata {
+                    Ok(metadata) => (metadata.modified().ok(), metadata.len()),
+                    Err(err) => {
+                        warnings.push(format!("Couldn't read metadata of '{}': {}", src, err));
+                        (None, 0)
+                    },
+                };
+
+                // Reuse the previous intent so any changes made by the user are kept
+                if let Some(previous) = previous_files.get(src.as_str()) {
+                    if previous.is_unchanged(modified, size) {
+                        let mut app_file = previous.clone();
+                        app_file.last_error = None;
+                        intents.push(app_file);
+                        continue;
+                    }
+                }
+
                 let intent = get_file_intent(rel_path, rules, cache);
                 let app_file = AppFile::new(
-                    rel_path.to_string().replace(std::path::MAIN_SEPARATOR, "/"),
+                    src,
                     intent.descriptor,
                     intent.action,
                     intent.dest.replace(std::path::MAIN_SEPARATOR, "/"),
+                    modified, size,
                 );
                 intents.push(app_file);
             }
@@ -155,17 +668,336 @@ async fn recursive_search_file_intents(root_path: &str, curr_folder: &str, cache
     Ok(())
 }
 
-fn check_folder_empty(path: &path::Path) -> bool {
-    for entry in walkdir::WalkDir::new(path).into_iter().flatten() {
-        if entry.file_type().is_file() {
+// Deleted files are moved to the trash if possible so they can be recovered
+// Some mounts such as network attached storage don't have a trash so we fall back to a permanent delete
+// The trash error is returned when the fallback occurs so it can be reported
+async fn delete_path(path: path::PathBuf, is_use_trash: bool) -> Result<Option<trash::Error>, std::io::Error> {
+    let trash_error = match is_use_trash {
+        false => None,
+        true => {
+            let res = tokio::task::spawn_blocking({
+                let path = path.clone();
+                move || trash::delete(path)
+            }).await.map_err(std::io::Error::other)?;
+            match res {
+                Ok(()) => return Ok(None),
+                Err(err) => Some(err),
+            }
+        },
+    };
+
+    // Only the link itself is removed and never what it points to
+    let file_type = tokio::fs::symlink_metadata(&path).await?.file_type();
+    if file_type.is_symlink() {
+        remove_symlink(path.as_path()).await?;
+    } else if file_type.is_dir() {
+        tokio::fs::remove_dir_all(&path).await?;
+    } else {
+        tokio::fs::remove_file(&path).await?;
+    }
+    Ok(trash_error)
+}
+
+// Links to folders and junctions on Windows are removed as folders
+async fn remove_symlink(path: &path::Path) -> Result<(), std::io::Error> {
+    match tokio::fs::remove_file(path).await {
+        Err(_) if cfg!(windows) => tokio::fs::remove_dir(path).await,
+        res => res,
+    }
+}
+
+// Filesystem operations whose failures are hard to reproduce, e.g. renames across devices
+#[async_trait::async_trait]
+pub(crate) trait FileOperations: Send + Sync {
+    async fn rename(&self, src: &path::Path, dest: &path::Path) -> Result<(), std::io::Error>;
+}
+
+struct TokioFileOperations;
+
+#[async_trait::async_trait]
+impl FileOperations for TokioFileOperations {
+    async fn rename(&self, src: &path::Path, dest: &path::Path) -> Result<(), std::io::Error> {
+        tokio::fs::rename(src, dest).await
+    }
+}
+
+// Files are copied in chunks so that the copy can be cancelled and its progress reported
+// The copy has to match the expected size of the source otherwise it is incomplete
+// A partial copy is removed on failure so the destination is either complete or left untouched
+async fn copy_file_chunked<F>(
+    src: &path::Path, dest: &path::Path, src_size: u64, is_cancelled: &AtomicBool, mut on_copied: F,
+) -> Result<(), std::io::Error>
+where F: FnMut(u64)
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut src_file = tokio::fs::File::open(src).await?;
+    let mut dest_file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)
+        .await?;
+
+    let res = async {
+        let mut buffer = vec![0u8; COPY_CHUNK_SIZE];
+        loop {
+            if is_cancelled.load(Ordering::Relaxed) {
+                return Err(std::io::Error::from(std::io::ErrorKind::Interrupted));
+            }
+            let total_read = src_file.read(buffer.as_mut_slice()).await?;
+            if total_read == 0 {
+                break;
+            }
+            dest_file.write_all(&buffer[..total_read]).await?;
+            on_copied(total_read as u64);
+        }
+        dest_file.flush().await?;
+
+        let dest_size = tokio::fs::metadata(dest).await?.len();
+        if dest_size != src_size {
+            let message = format!("copied {} bytes but source has {} bytes", dest_size, src_size);
+            return Err(std::io::Error::other(message));
+        }
+        Ok(())
+    }.await;
+    drop(dest_file);
+
+    if let Err(err) = res {
+        let _ = tokio::fs::remove_file(dest).await;
+        return Err(err);
+    }
+    Ok(())
+}
+
+// A copied file has its times set to when the copy occured so we have to restore them from the source
+async fn copy_file_times(src: &path::Path, dest: &path::Path) -> Result<(), std::io::Error> {
+    let metadata = tokio::fs::metadata(src).await?;
+    let accessed = filetime::FileTime::from_last_access_time(&metadata);
+    let modified = filetime::FileTime::from_last_modification_time(&metadata);
+    let dest = dest.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        filetime::set_file_times(dest, accessed, modified)
+    }).await.map_err(std::io::Error::other)?
+}
+
+fn describe_rename_error(err: std::io::Error) -> std::io::Error {
+    // ERROR_SHARING_VIOLATION occurs on Windows if the file is opened by another program such as a media player
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    if cfg!(target_os = "windows") && err.raw_os_error() == Some(ERROR_SHARING_VIOLATION) {
+        return std::io::Error::new(err.kind(), "file is being used by another program");
+    }
+    err
+}
+
+// A folder is empty if every file in it will be removed or is ignored by the filter rules
+// Excluded folders are never empty so that they and their parents are kept
+fn check_folder_empty<F>(root: &path::Path, folder: &path::Path, rules: &FilterRules, is_file_removed: F) -> bool
+where F: Fn(&str) -> bool
+{
+    // A folder that is a link is listed as a file so it is never empty
+    let walker = walkdir::WalkDir::new(folder)
+        .follow_links(false)
+        .follow_root_links(false)
+        .into_iter()
+        .flatten();
+    for entry in walker {
+        let rel_path = match entry.path().strip_prefix(root) {
+            Ok(rel_path) => rel_path.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "/"),
+            Err(_) => return false,
+        };
+        let rel_path = path::Path::new(rel_path.as_str());
+        if entry.file_type().is_dir() {
+            if rules.is_empty_folder_excluded(rel_path) {
+                return false;
+            }
+            continue;
+        }
+        if rules.is_ignored_for_emptiness(rel_path) {
+            continue;
+        }
+        if !is_file_removed(rel_path.to_string_lossy().as_ref()) {
             return false;
         }
     }
     true
 }
 
+fn is_temporary_rename(operation: &PlannedOperation) -> bool {
+    match operation {
+        PlannedOperation::Rename { dest, .. } => dest.ends_with(TEMPORARY_RENAME_SUFFIX),
+        _ => false,
+    }
+}
+
+// Renames are ordered so that a file is only moved once its destination has been vacated
+// - Chains such as A→B and B→C are performed as B→C then A→B
+// - Cycles such as A→B and B→A are broken by moving one of the files to a temporary path first
+// - Deletes are performed last unless they vacate the destination of a rename
+// Operations within a stage don't depend on each other so they can be performed concurrently
+fn order_file_changes<F>(
+    renames: Vec<(String, String)>, deletes: Vec<String>, mut get_temporary_path: F,
+) -> Vec<Vec<PlannedOperation>>
+where F: FnMut(&str) -> String
+{
+    let mut operations: Vec<PlannedOperation> = renames
+        .into_iter()
+        .map(|(src, dest)| PlannedOperation::Rename { src, dest })
+        .chain(deletes.into_iter().map(|src| PlannedOperation::Delete { src }))
+        .collect();
+
+    let mut vacated_by = HashMap::<String, usize>::new();
+    for (index, operation) in operations.iter().enumerate() {
+        if let PlannedOperation::Rename { src, .. } | PlannedOperation::Delete { src } = operation {
+            vacated_by.insert(get_path_key(src), index);
+        }
+    }
+
+    let mut dependencies: Vec<Option<usize>> = operations
+        .iter()
+        .enumerate()
+        .map(|(index, operation)| match operation {
+            PlannedOperation::Rename { dest, .. } => {
+                vacated_by.get(get_path_key(dest).as_str()).copied().filter(|other| *other != index)
+            },
+            _ => None,
+        })
+        .collect();
+
+    // Each rename depends on at most one operation so cycles are found by following the dependencies
+    let mut visited_from: Vec<Option<usize>> = vec![None; operations.len()];
+    for start in 0..operations.len() {
+        let mut curr = Some(start);
+        while let Some(index) = curr {
+            if visited_from[index].is_some() {
+                break;
+            }
+            visited_from[index] = Some(start);
+            curr = dependencies[index];
+        }
+
+        // We have a cycle if we returned to an operation visited from the same start
+        let index = match curr {
+            Some(index) if visited_from[index] == Some(start) => index,
+            _ => continue,
+        };
+        let (src, dest) = match &operations[index] {
+            PlannedOperation::Rename { src, dest } => (src.clone(), dest.clone()),
+            _ => continue,
+        };
+
+        let temporary_path = get_temporary_path(src.as_str());
+        let temporary_index = operations.len();
+        for dependency in dependencies.iter_mut() {
+            if *dependency == Some(index) {
+                *dependency = Some(temporary_index);
+            }
+        }
+        operations[index] = PlannedOperation::Rename { src: temporary_path.clone(), dest };
+        operations.push(PlannedOperation::Rename { src, dest: temporary_path });
+        dependencies.push(None);
+        visited_from.push(Some(start));
+    }
+
+    // Operations are performed in the stage after the operation they depend on
+    let mut stages: Vec<Option<usize>> = vec![None; operations.len()];
+    for start in 0..operations.len() {
+        let mut chain = Vec::new();
+        let mut stage = 0;
+        let mut curr = Some(start);
+        while let Some(index) = curr {
+            if let Some(dependency_stage) = stages[index] {
+                stage = dependency_stage + 1;
+                break;
+            }
+            chain.push(index);
+            curr = dependencies[index];
+        }
+        for index in chain.into_iter().rev() {
+            stages[index] = Some(stage);
+            stage += 1;
+        }
+    }
+
+    let required_deletes: HashSet<usize> = dependencies.iter().flatten().copied().collect();
+    let total_rename_stages = operations
+        .iter()
+        .zip(stages.iter())
+        .filter(|(operation, _)| matches!(operation, PlannedOperation::Rename { .. }))
+        .filter_map(|(_, stage)| *stage)
+        .max()
+        .map(|stage| stage + 1)
+        .unwrap_or(0);
+
+    let mut ordered_operations: Vec<Vec<PlannedOperation>> = Vec::new();
+    ordered_operations.resize_with(total_rename_stages + 1, Vec::new);
+    for (index, (operation, stage)) in operations.into_iter().zip(stages).enumerate() {
+        let stage = match operation {
+            PlannedOperation::Delete { .. } if !required_deletes.contains(&index) => total_rename_stages,
+            _ => stage.unwrap_or(0),
+        };
+        ordered_operations[stage].push(operation);
+    }
+    ordered_operations.retain(|operations| !operations.is_empty());
+    ordered_operations
+}
+
+// Seasons of the files which were parsed with an episode descriptor
+fn get_descriptor_seasons(files: &ImmutableAppFileList<'_>) -> BTreeSet<u32> {
+    files
+        .to_iter()
+        .filter_map(|file| file.get_src_descriptor().map(|key| key.season))
+        .collect()
+}
+
+// Episodes are considered present if there is a file that is or will be named after it
+// Multiple files for the same episode, i.e. a video and its subtitles, only count once
+fn get_existing_episodes(files: &ImmutableAppFileList<'_>) -> HashSet<EpisodeKey> {
+    let file_tracker = files.get_file_tracker();
+    file_tracker.get_action_indices(Action::Complete)
+        .iter()
+        .chain(file_tracker.get_action_indices(Action::Rename).iter())
+        .filter_map(|index| files.get(*index))
+        .filter_map(|file| *file.get_src_descriptor())
+        .collect()
+}
+
+fn find_missing_episodes(
+    cache: Option<&TvdbCache>, files: &ImmutableAppFileList<'_>, filter: MissingEpisodeFilter,
+) -> Vec<EpisodeKey> {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return Vec::new(),
+    };
+    let existing = get_existing_episodes(files);
+    let today = chrono::Local::now().date_naive();
+    cache.get_missing_episodes(&existing, filter, today)
+}
+
+fn get_folder_status_from_tracker(file_tracker: &FileTracker) -> FolderStatus {
+    let action_count = file_tracker.get_action_count();
+    let file_count = Action::iterator()
+        .map(|action| action_count[*action])
+        .reduce(|acc, v| acc + v);
+    let file_count = match file_count {
+        Some(count) => count,
+        None => return FolderStatus::Unknown,
+    };
+    
+    if file_count == 0 {
+        return FolderStatus::Empty;
+    }
+
+    let pending_count = action_count[Action::Delete] + action_count[Action::Rename];
+    if pending_count > 0 {
+        return FolderStatus::Pending;
+    }
+
+    FolderStatus::Done
+}
+
 impl AppFolder {
-    pub async fn perform_initial_load(&self) -> Option<()> {
+    // The cache is fetched using the series mapping if it hasn't been saved to the folder
+    pub async fn perform_initial_load(&self, provider: Option<Arc<dyn MetadataProvider>>) -> Option<()> {
         {
             let mut is_loaded = self.is_initial_load.lock().await;
             if *is_loaded {
@@ -173,84 +1005,233 @@ impl AppFolder {
             }
             *is_loaded = true;
         }
+        // The language is needed if the cache has to be fetched
+        self.load_folder_config_from_file().await;
         let (res_0, res_1) = tokio::join!(
             async {
-                self.load_cache_from_file().await?;
+                // The ignore list is needed to determine the file intents
+                let (_, res) = tokio::join!(
+                    self.load_ignored_files_from_file(),
+                    self.load_initial_cache(provider),
+                );
+                res?;
                 self.update_file_intents().await
             },
-            self.load_bookmarks_from_file(),
+            async {
+                let (res, _) = tokio::join!(self.load_bookmarks_from_file(), self.load_undo_journal_from_file());
+                res
+            },
         );
         res_0.or(res_1)
     }
 
-    pub fn get_folder_status_blocking(&self) -> FolderStatus {
-        if !*self.is_file_count_init.blocking_lock() {
-            return FolderStatus::Unknown; 
+    // A missing config means the folder uses the app config
+    pub async fn load_folder_config_from_file(&self) -> Option<()> {
+        let data = match tokio::fs::read_to_string(self.folder_config_path.as_str()).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Some(()),
+            Err(err) => {
+                let message = format!("IO error while reading folder config: {}", err);
+                self.errors.write().await.push_error("Folder config", message);
+                return None;
+            },
+        };
+        match serde_json::from_str(data.as_str()) {
+            Ok(config) => {
+                *self.folder_config.write().await = config;
+                Some(())
+            },
+            Err(err) => {
+                let message = format!("JSON decoding error reading folder config: {}", err);
+                self.errors.write().await.push_error("Folder config", message);
+                None
+            },
         }
+    }
 
-        let file_tracker = self.file_tracker.blocking_read();
-        let action_count = file_tracker.get_action_count();
-        let file_count = Action::iterator()
-            .map(|action| action_count[*action])
-            .reduce(|acc, v| acc + v);
-        let file_count = match file_count {
-            Some(count) => count,
-            None => return FolderStatus::Unknown,
+    pub async fn save_folder_config_to_file(&self) -> Option<()> {
+        let data = match serde_json::to_string_pretty(&*self.folder_config.read().await) {
+            Ok(data) => data,
+            Err(err) => {
+                let message = format!("JSON encoding error writing folder config: {}", err);
+                self.errors.write().await.push_error("Folder config", message);
+                return None;
+            },
         };
-        
-        if file_count == 0 {
-            return FolderStatus::Empty;
+        if let Err(err) = atomic_write(self.folder_config_path.as_str(), data).await {
+            let message = format!("IO error while writing folder config: {}", err);
+            self.errors.write().await.push_error("Folder config", message);
+            return None;
         }
+        Some(())
+    }
 
-        let pending_count = action_count[Action::Delete] + action_count[Action::Rename];
-        if pending_count > 0 {
-            return FolderStatus::Pending;
-        }
+    // The folder's language takes precedence over the app config
+    pub async fn get_language(&self) -> Option<String> {
+        self.folder_config.read().await.language.clone()
+            .or_else(|| self.filter_rules.language.clone())
+    }
+
+    pub async fn set_language(&self, language: Option<String>) -> Option<()> {
+        self.folder_config.write().await.language = language;
+        self.save_folder_config_to_file().await
+    }
+
+    pub async fn get_episode_ordering(&self) -> EpisodeOrdering {
+        self.folder_config.read().await.episode_ordering.unwrap_or_default()
+    }
 
-        FolderStatus::Done
+    pub fn get_episode_ordering_blocking(&self) -> EpisodeOrdering {
+        self.folder_config.blocking_read().episode_ordering.unwrap_or_default()
     }
 
-    pub async fn get_folder_status(&self) -> FolderStatus {
-        if !*self.is_file_count_init.lock().await {
-            return FolderStatus::Unknown; 
+    // The file intents have to be updated afterwards since descriptors refer to different episodes
+    pub async fn set_episode_ordering(&self, ordering: EpisodeOrdering) -> Option<()> {
+        let _busy_lock = self.lock_busy().await;
+        self.folder_config.write().await.episode_ordering = Some(ordering);
+        let mut cache_guard = self.cache.write().await;
+        if let Some(cache) = cache_guard.as_ref().filter(|cache| cache.get_ordering() != ordering) {
+            // Folders using the other ordering might still share the current cache
+            let mut new_cache = TvdbCache::clone(cache);
+            new_cache.set_ordering(ordering);
+            self.push_duplicate_episodes_warning(&new_cache).await;
+            *cache_guard = Some(self.share_cache(new_cache).await);
         }
+        drop(cache_guard);
+        self.is_full_rescan_required.store(true, Ordering::Relaxed);
+        self.notify_change(ChangeKind::Cache);
+        self.save_folder_config_to_file().await
+    }
 
-        let file_tracker = self.file_tracker.read().await;
-        let action_count = file_tracker.get_action_count();
-        let file_count = Action::iterator()
-            .map(|action| action_count[*action])
-            .reduce(|acc, v| acc + v);
-        let file_count = match file_count {
-            Some(count) => count,
-            None => return FolderStatus::Unknown,
-        };
-        
-        if file_count == 0 {
-            return FolderStatus::Empty;
+    async fn load_initial_cache(&self, provider: Option<Arc<dyn MetadataProvider>>) -> Option<()> {
+        // A saved cache always takes precedence over the series mapping
+        let is_cache_saved = self.is_cache_saved().await;
+        match (is_cache_saved, provider, self.mapped_series_id) {
+            (false, Some(provider), Some(series_id)) => {
+                self.load_cache_from_api(provider, series_id).await?;
+                self.save_cache_to_file().await
+            },
+            (_, provider, _) => {
+                self.load_cache_from_file().await?;
+                if let Some(provider) = provider {
+                    self.refresh_cache_if_old(provider).await;
+                }
+                Some(())
+            },
         }
+    }
 
-        let pending_count = action_count[Action::Delete] + action_count[Action::Rename];
-        if pending_count > 0 {
-            return FolderStatus::Pending;
+    // Caches without a fetch time are left alone since we don't know how old they are
+    async fn refresh_cache_if_old(&self, provider: Arc<dyn MetadataProvider>) -> Option<()> {
+        let max_days = self.filter_rules.auto_refresh_after_days?;
+        {
+            let cache_guard = self.cache.read().await;
+            let cache = cache_guard.as_ref()?;
+            // Caches from another api are refreshed manually after picking the series again
+            if cache.get_provider() != provider.get_kind() {
+                return None;
+            }
+            let age = cache.get_age()?;
+            if age < chrono::Duration::days(i64::from(max_days)) {
+                return None;
+            }
         }
+        self.refresh_cache_from_api(provider).await?;
+        self.save_cache_to_file().await
+    }
 
-        FolderStatus::Done
+    pub async fn get_cache_age(&self) -> Option<chrono::Duration> {
+        self.cache.read().await.as_ref()?.get_age()
     }
-    
-    pub async fn load_bookmarks_from_file(&self) -> Option<()> {
-        let bookmarks_data = tokio::fs::read_to_string(self.bookmarks_path.as_str()).await;
-        if let Err(err) = bookmarks_data.as_ref() {
-            let message = format!("IO while reading bookmarks: {}", err);
-            self.errors.write().await.push(message);
+
+    pub fn get_cache_age_blocking(&self) -> Option<chrono::Duration> {
+        self.cache.blocking_read().as_ref()?.get_age()
+    }
+
+    // Folders without a saved cache have never been matched to a series
+    pub async fn is_unidentified(&self) -> bool {
+        if self.cache.read().await.is_some() {
+            return false;
         }
+        !self.is_cache_saved().await
+    }
 
-        let bookmarks_data = bookmarks_data.as_ref().ok()?;
+    // Folders that haven't been migrated only have the legacy series file
+    async fn is_cache_saved(&self) -> bool {
+        let (is_cache, is_series) = tokio::join!(
+            tokio::fs::try_exists(self.cache_path.as_str()),
+            tokio::fs::try_exists(self.series_path.as_str()),
+        );
+        matches!(is_cache, Ok(true)) || matches!(is_series, Ok(true))
+    }
+
+    pub fn get_missing_episodes_blocking(&self, filter: MissingEpisodeFilter) -> Vec<EpisodeKey> {
+        let cache = self.cache.blocking_read();
+        let files = self.get_files_blocking();
+        find_missing_episodes(cache.as_deref(), &files, filter)
+    }
+
+    pub async fn get_missing_episodes(&self, filter: MissingEpisodeFilter) -> Vec<EpisodeKey> {
+        let cache = self.cache.read().await;
+        let files = self.get_files().await;
+        find_missing_episodes(cache.as_deref(), &files, filter)
+    }
+
+    // Number of distinct episodes with a file, compared against the aired count in the summary
+    pub fn get_episodes_on_disk_count_blocking(&self) -> usize {
+        get_existing_episodes(&self.get_files_blocking()).len()
+    }
+
+    pub async fn get_episodes_on_disk_count(&self) -> usize {
+        get_existing_episodes(&self.get_files().await).len()
+    }
+
+    // The status is cached so it can be read every frame without locking the folder
+    pub fn get_folder_status(&self) -> FolderStatus {
+        *self.folder_status.borrow()
+    }
+
+    // Nothing is returned while the folder is being written to or if it hasn't been scanned yet
+    pub fn try_get_action_counts(&self) -> Option<FolderActionCounts> {
+        if !self.is_file_count_init.load(Ordering::Relaxed) {
+            return None;
+        }
+        let file_tracker = self.file_tracker.try_read().ok()?;
+        Some(FolderActionCounts {
+            action_count: *file_tracker.get_action_count(),
+            total_conflicts: file_tracker.get_conflict_count(),
+        })
+    }
+
+    fn update_folder_status(&self, file_tracker: &FileTracker) {
+        if !self.is_file_count_init.load(Ordering::Relaxed) {
+            return;
+        }
+        let new_status = get_folder_status_from_tracker(file_tracker);
+        self.folder_status.send_if_modified(|status| {
+            let is_changed = *status != new_status;
+            *status = new_status;
+            is_changed
+        });
+    }
+    
+    // Most folders have no bookmarks so a missing file isn't reported as an error
+    pub async fn load_bookmarks_from_file(&self) -> Option<()> {
+        let bookmarks_data = match tokio::fs::read_to_string(self.bookmarks_path.as_str()).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Some(()),
+            Err(err) => {
+                let message = format!("IO while reading bookmarks: {}", err);
+                self.errors.write().await.push_error("Bookmarks", message);
+                return None;
+            },
+        };
 
         let bookmarks = match deserialize_bookmarks(bookmarks_data.as_str()) {
             Ok(bookmarks) => bookmarks,
             Err(err) => {
                 let message = format!("JSON decoding error reading bookmarks from file: {}", err); 
-                self.errors.write().await.push(message);
+                self.errors.write().await.push_error("Bookmarks", message);
                 return None;
             },
         };
@@ -267,23 +1248,255 @@ impl AppFolder {
 
         if let Err(err) = bookmarks_data.as_ref() {
             let message = format!("JSON encoding error writing bookmarks to file: {}", err);
-            self.errors.write().await.push(message);
+            self.errors.write().await.push_error("Bookmarks", message);
             return None;
         }
 
         let bookmarks_data = bookmarks_data.as_ref().ok()?;
-        let res = tokio::fs::write(self.bookmarks_path.as_str(), bookmarks_data).await;
+        let res = atomic_write(self.bookmarks_path.as_str(), bookmarks_data).await;
 
         if let Err(err) = res {
             let message = format!("IO error while writing bookmarks to file: {}", err);
-            self.errors.write().await.push(message);
+            self.errors.write().await.push_error("Bookmarks", message);
+            return None;
+        };
+        Some(())
+    }
+
+    // Remove bookmarks of files that no longer exist, returning the number of bookmarks removed
+    // Only done after a scan of the folder so files that haven't been scanned yet keep their bookmarks
+    pub async fn prune_orphaned_bookmarks(&self) -> usize {
+        let _busy_lock = self.lock_busy().await;
+        let is_scanned = self.is_file_count_init.load(Ordering::Relaxed);
+        let is_outdated = self.is_file_list_outdated.load(Ordering::Relaxed);
+        if !is_scanned || is_outdated {
+            let message = "Couldn't prune bookmarks since the folder hasn't been scanned since it was last changed";
+            self.errors.write().await.push_warn("Bookmarks", message);
+            return 0;
+        }
+
+        let total_pruned = {
+            let file_list = self.file_list.read().await;
+            let sources: HashSet<&str> = file_list.iter().map(|file| file.src.as_str()).collect();
+            self.bookmarks.write().await.retain(|id| sources.contains(id))
+        };
+        if total_pruned > 0 {
+            self.save_bookmarks_to_file().await;
+        }
+        total_pruned
+    }
+
+    // Missing ignore lists are treated as empty since most folders won't have one
+    pub async fn load_ignored_files_from_file(&self) -> Option<()> {
+        let ignored_files_data = match tokio::fs::read_to_string(self.ignored_files_path.as_str()).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Some(()),
+            Err(err) => {
+                let message = format!("IO error while reading ignored files: {}", err);
+                self.errors.write().await.push_error("Ignore list", message);
+                return None;
+            },
+        };
+
+        let ignored_files: BTreeSet<String> = match serde_json::from_str(ignored_files_data.as_str()) {
+            Ok(ignored_files) => ignored_files,
+            Err(err) => {
+                let message = format!("JSON decoding error reading ignored files from file: {}", err);
+                self.errors.write().await.push_error("Ignore list", message);
+                return None;
+            },
+        };
+
+        *self.ignored_files.write().await = ignored_files;
+        Some(())
+    }
+
+    pub async fn save_ignored_files_to_file(&self) -> Option<()> {
+        let ignored_files_data = {
+            let ignored_files = self.ignored_files.read().await;
+            serde_json::to_string_pretty(&*ignored_files)
+        };
+
+        let ignored_files_data = match ignored_files_data {
+            Ok(data) => data,
+            Err(err) => {
+                let message = format!("JSON encoding error writing ignored files to file: {}", err);
+                self.errors.write().await.push_error("Ignore list", message);
+                return None;
+            },
+        };
+
+        let res = atomic_write(self.ignored_files_path.as_str(), ignored_files_data).await;
+        if let Err(err) = res {
+            let message = format!("IO error while writing ignored files to file: {}", err);
+            self.errors.write().await.push_error("Ignore list", message);
             return None;
         };
         Some(())
     }
 
+    pub async fn export_plan(&self) -> Result<String, serde_json::Error> {
+        let files = self.get_files().await;
+        let plan: Vec<PlanEntry> = files
+            .to_iter()
+            .map(|file| PlanEntry {
+                src: file.get_src().to_string(),
+                action: file.get_action(),
+                dest: file.get_dest().to_string(),
+                is_enabled: file.get_is_enabled(),
+            })
+            .collect();
+        serde_json::to_string_pretty(&plan)
+    }
+
+    // Rows are matched to files by their source and rows without a matching file are skipped
+    pub async fn import_plan(&self, data: &str) -> Option<()> {
+        let _busy_lock = self.lock_busy().await;
+        let plan: Vec<PlanEntry> = match serde_json::from_str(data) {
+            Ok(plan) => plan,
+            Err(err) => {
+                let message = format!("JSON decoding error reading plan: {}", err);
+                self.errors.write().await.push_error("Import plan", message);
+                return None;
+            },
+        };
+
+        let mut unmatched = Vec::new();
+        let mut invalid = Vec::new();
+        {
+            let mut files = self.get_mut_files().await;
+            for entry in plan.into_iter() {
+                if entry.action == Action::Rename && !is_valid_plan_dest(entry.dest.as_str()) {
+                    invalid.push(entry.src);
+                    continue;
+                }
+                let index = files.get_file_tracker().get_source_index(entry.src.as_str()).copied();
+                let mut file = match index.and_then(|index| files.get(index)) {
+                    Some(file) => file,
+                    None => {
+                        unmatched.push(entry.src);
+                        continue;
+                    },
+                };
+                file.set_action(entry.action);
+                if entry.action == Action::Rename {
+                    file.set_dest(entry.dest);
+                }
+                file.set_is_enabled(entry.is_enabled);
+            }
+        }
+        self.flush_file_changes().await;
+
+        if !unmatched.is_empty() {
+            let message = format!("Skipped {} rows without a matching file: {}", unmatched.len(), unmatched.join(", "));
+            self.errors.write().await.push_warn("Import plan", message);
+        }
+        if !invalid.is_empty() {
+            let message = format!("Skipped {} renames with a destination outside of the folder: {}", invalid.len(), invalid.join(", "));
+            self.errors.write().await.push_warn("Import plan", message);
+        }
+        Some(())
+    }
+
+    pub async fn export_plan_to_file(&self) -> Option<()> {
+        let data = match self.export_plan().await {
+            Ok(data) => data,
+            Err(err) => {
+                let message = format!("JSON encoding error writing plan to file: {}", err);
+                self.errors.write().await.push_error("Export plan", message);
+                return None;
+            },
+        };
+
+        if let Err(err) = atomic_write(self.plan_path.as_str(), data).await {
+            let message = format!("IO error while writing plan to file: {}", err);
+            self.errors.write().await.push_error("Export plan", message);
+            return None;
+        }
+        Some(())
+    }
+
+    pub async fn import_plan_from_file(&self) -> Option<()> {
+        let data = match tokio::fs::read_to_string(self.plan_path.as_str()).await {
+            Ok(data) => data,
+            Err(err) => {
+                let message = format!("IO error while reading plan: {}", err);
+                self.errors.write().await.push_error("Import plan", message);
+                return None;
+            },
+        };
+        self.import_plan(data.as_str()).await
+    }
+
+    pub async fn add_to_ignore_list(&self, src: &str) -> Option<()> {
+        let _busy_lock = self.lock_busy().await;
+        let is_inserted = self.ignored_files.write().await.insert(src.to_string());
+        if !is_inserted {
+            return Some(());
+        }
+
+        {
+            let mut files = self.get_mut_files().await;
+            let index = files.get_file_tracker().get_source_index(src).copied();
+            if let Some(mut file) = index.and_then(|index| files.get(index)) {
+                file.set_action(Action::Ignore);
+            }
+        }
+        self.flush_file_changes().await;
+        self.save_ignored_files_to_file().await
+    }
+
+    // The file is given the intent it would have had if it was never ignored
+    // This includes an episode that was assigned to it before it was ignored
+    pub async fn remove_from_ignore_list(&self, src: &str) -> Option<()> {
+        let _busy_lock = self.lock_busy().await;
+        let is_removed = self.ignored_files.write().await.remove(src);
+        if !is_removed {
+            return Some(());
+        }
+
+        {
+            let cache_guard = self.cache.read().await;
+            let mut files = self.get_mut_files().await;
+            let index = files.get_file_tracker().get_source_index(src).copied();
+            let file = index.and_then(|index| files.get(index));
+            if let (Some(cache), Some(mut file)) = (cache_guard.as_ref(), file) {
+                let native_src = src.replace('/', std::path::MAIN_SEPARATOR_STR);
+                let key = *file.get_src_descriptor();
+                let intent = get_file_intent_for_episode(native_src.as_str(), key, &self.filter_rules, cache);
+                file.set_action(intent.action);
+                file.set_dest(intent.dest.replace(std::path::MAIN_SEPARATOR, "/"));
+                if intent.action == Action::Rename {
+                    file.set_is_enabled(true);
+                }
+            }
+        }
+        self.flush_file_changes().await;
+        self.save_ignored_files_to_file().await
+    }
+
     pub async fn update_file_intents(&self) -> Option<()> {
-        let _busy_lock = self.busy_lock.lock().await;
+        self.update_file_intents_with_previous(false).await
+    }
+
+    // Only recompute the intents of files that were added or modified since the last scan
+    // Falls back to a full rescan if the cache was changed since the intents depend on it
+    pub async fn update_file_intents_incremental(&self) -> Option<()> {
+        self.update_file_intents_with_previous(true).await
+    }
+
+    async fn update_file_intents_with_previous(&self, is_incremental: bool) -> Option<()> {
+        let _busy_lock = self.lock_busy().await;
+        self.is_cancelled.store(false, Ordering::Relaxed);
+
+        let is_full_rescan = !is_incremental || self.is_full_rescan_required.load(Ordering::Relaxed);
+        let previous_files: HashMap<String, AppFile> = match is_full_rescan {
+            true => HashMap::new(),
+            false => self.file_list.read().await
+                .iter()
+                .map(|file| (file.src.clone(), file.clone()))
+                .collect(),
+        };
 
         let mut new_file_list = Vec::<AppFile>::new();
         {
@@ -292,26 +1505,76 @@ impl AppFolder {
                 Some(cache) => cache,
                 None => {
                     let message = "Couldn't update file intents since cache is unloaded";
-                    self.errors.write().await.push(message.to_string()); 
+                    self.errors.write().await.push_warn("Update file intents", message); 
                     return None;
                 },
             };
+            let search = FileIntentSearch {
+                root_path: self.folder_path.as_str(),
+                cache,
+                rules: &self.filter_rules,
+                previous_files: &previous_files,
+                is_cancelled: &self.is_cancelled,
+                visited_folders: std::sync::Mutex::new(HashSet::new()),
+            };
+            let mut warnings = Vec::new();
             let res = recursive_search_file_intents(
-                self.folder_path.as_str(), self.folder_path.as_str(), cache, 
-                &mut new_file_list, &self.filter_rules,
+                &search, self.folder_path.as_str(), &mut new_file_list, &mut warnings,
             ).await;
+            if !warnings.is_empty() {
+                let mut errors = self.errors.write().await;
+                for message in warnings {
+                    errors.push_warn("Update file intents", message);
+                }
+            }
             if let Err(err) = res {
+                // Keep the existing file list when cancelled so it remains consistent
+                if err.kind() == std::io::ErrorKind::Interrupted && self.is_cancelled.load(Ordering::Relaxed) {
+                    let message = "Cancelled update of file intents";
+                    self.errors.write().await.push_warn("Update file intents", message);
+                    return None;
+                }
+
                 let message = format!("IO error while reading files for intent update: {}", err);
-                self.errors.write().await.push(message);
+                self.errors.write().await.push_error("Update file intents", message);
                 return None;
             }
         }
 
+        // Files in the ignore list are always ignored regardless of their intent
+        {
+            let ignored_files = self.ignored_files.read().await;
+            for file in new_file_list.iter_mut() {
+                if ignored_files.contains(&file.src) {
+                    file.action = Action::Ignore;
+                    file.dest.clear();
+                }
+            }
+        }
+
         new_file_list.sort_unstable_by(|a,b| {
             let a_name = a.src.as_str();
             let b_name = b.src.as_str();
             a_name.partial_cmp(b_name).unwrap_or(std::cmp::Ordering::Equal)
         });
+
+        // Files start off disabled so that enabling them through the change queue updates the tracker
+        // New renames are enabled automatically while reused files keep their previous state
+        let is_enabled_list: Vec<bool> = new_file_list
+            .iter_mut()
+            .map(|file| {
+                let is_reused = previous_files
+                    .get(file.src.as_str())
+                    .map(|previous| previous.is_unchanged(file.modified, file.size))
+                    .unwrap_or(false);
+                let is_enabled = match is_reused {
+                    true => file.is_enabled,
+                    false => file.action == Action::Rename,
+                };
+                file.is_enabled = false;
+                is_enabled
+            })
+            .collect();
         
         {
             let mut file_list = self.file_list.write().await;
@@ -321,32 +1584,142 @@ impl AppFolder {
             file_tracker.clear();
 
             // seed conflict table
-            for (index, file) in file_list.iter().enumerate() {
+            for (index, file) in file_list.iter_mut().enumerate() {
+                file.update_dest_error(file_tracker.get_max_dest_length());
                 file_tracker.insert_existing_source(file.src.as_str(), index);
-                let action_count = file_tracker.get_action_count_mut();
-                action_count[file.action] += 1usize;
+                file_tracker.insert_file_action(file, index);
+            }
+            *self.duplicate_groups.write().await = find_duplicate_groups(file_list.as_slice());
+
+            // file indices change between scans so errors are matched to files using their source
+            let mut file_errors = self.file_errors.write().await;
+            for error in file_errors.iter_mut() {
+                error.file_index = file_tracker.get_source_index(error.src.as_str()).copied();
+                if let Some(file) = error.file_index.and_then(|index| file_list.get_mut(index)) {
+                    file.last_error = Some(error.message.clone());
+                }
             }
         }
 
         {
-            // automatically enable renames
             let mut files = self.get_mut_files().await;
-            let mut files_iter = files.to_iter();
-            while let Some(mut file) = files_iter.next_mut() {
-                if file.get_action() == Action::Rename {
+            for (index, is_enabled) in is_enabled_list.into_iter().enumerate() {
+                if !is_enabled {
+                    continue;
+                }
+                if let Some(mut file) = files.get(index) {
                     file.set_is_enabled(true);
                 }
             }
         }
         
+        self.is_file_count_init.store(true, Ordering::Relaxed);
+        self.is_file_list_outdated.store(false, Ordering::Relaxed);
         self.flush_file_changes().await;
-        *self.is_file_count_init.lock().await = true;
+        if is_full_rescan {
+            self.is_full_rescan_required.store(false, Ordering::Relaxed);
+        }
+        // The file list was replaced even if no changes were flushed
+        self.notify_change(ChangeKind::Files);
         Some(())
     }
 
+    // The legacy series and episodes files are migrated to a single cache file when they are loaded
     pub async fn load_cache_from_file(&self) -> Option<()> {
-        let _busy_lock = self.busy_lock.lock().await;
+        let _busy_lock = self.lock_busy().await;
+
+        let (series, mut episodes, fetched_at, is_legacy) = match self.read_cache_file().await {
+            Some(file) => (file.series, file.episodes, file.fetched_at, false),
+            None => {
+                let (series, episodes) = self.read_legacy_cache_files().await?;
+                let fetched_at = self.read_cache_meta_from_file().await.map(|meta| meta.fetched_at);
+                (series, episodes, fetched_at, true)
+            },
+        };
+
+        // Treat the folder as unloaded so file intents aren't determined from another series
+        if self.remove_foreign_episodes(&series, &mut episodes).await.is_none() {
+            *self.cache.write().await = None;
+            self.is_full_rescan_required.store(true, Ordering::Relaxed);
+            self.notify_change(ChangeKind::Cache);
+            return None;
+        }
+
+        let mut new_cache = TvdbCache::new(series, episodes, self.get_episode_ordering().await);
+        new_cache.fetched_at = fetched_at;
+        new_cache.episodes_summary = self.read_episodes_summary_from_file().await;
+        self.push_duplicate_episodes_warning(&new_cache).await;
+
+        if is_legacy {
+            match new_cache.to_cache_file_string() {
+                Ok(data) => {
+                    if self.write_cache_file(data).await.is_some() {
+                        self.remove_legacy_cache_files().await;
+                    }
+                },
+                Err(err) => {
+                    let message = format!("JSON encode error when migrating legacy cache: {}", err);
+                    self.errors.write().await.push_error("Cache", message);
+                },
+            }
+        }
+
+        let new_cache = self.share_cache(new_cache).await;
+        let mut cache = self.cache.write().await;
+        *cache = Some(new_cache);
+        // Actors are read from file when they are first needed
+        self.is_actors_load_attempted.store(false, Ordering::Relaxed);
+        self.is_full_rescan_required.store(true, Ordering::Relaxed);
+        self.notify_change(ChangeKind::Cache);
+        Some(())
+    }
+
+    // Episodes copied from another folder would rename files with the titles of another series
+    // A few bad entries are dropped but mostly foreign episodes means the whole cache is wrong
+    async fn remove_foreign_episodes(&self, series: &Series, episodes: &mut Vec<Episode>) -> Option<()> {
+        let foreign = match find_foreign_episodes(series.id, episodes.as_slice()) {
+            Some(foreign) => foreign,
+            None => return Some(()),
+        };
+        if foreign.is_rejected() {
+            let message = format!("Refusing to load cache since {}", foreign);
+            self.errors.write().await.push_error("Cache", message);
+            return None;
+        }
+        let message = format!("Ignoring episodes from another series since {}", foreign);
+        self.errors.write().await.push_warn("Cache", message);
+        episodes.retain(|episode| episode.series_id.is_none_or(|id| id == series.id));
+        Some(())
+    }
+
+    // A missing or unreadable cache file falls back to the legacy files so it isn't fatal
+    async fn read_cache_file(&self) -> Option<CacheFile> {
+        let data = match tokio::fs::read_to_string(self.cache_path.as_str()).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                let message = format!("IO error while reading cache: {}", err);
+                self.push_file_error(PATH_STR_CACHE_DATA, err.kind(), message).await;
+                return None;
+            },
+        };
+        let file: CacheFile = match serde_json::from_str(data.as_str()) {
+            Ok(file) => file,
+            Err(err) => {
+                let message = format!("JSON decoding error reading cache from file: {}", err);
+                self.push_file_error(PATH_STR_CACHE_DATA, std::io::ErrorKind::InvalidData, message).await;
+                return None;
+            },
+        };
+        if file.version > CACHE_FILE_VERSION {
+            let message = format!("Cache file has version {} which is newer than the supported version {}", file.version, CACHE_FILE_VERSION);
+            self.push_file_error(PATH_STR_CACHE_DATA, std::io::ErrorKind::InvalidData, message).await;
+            return None;
+        }
+        Some(file)
+    }
 
+    async fn read_legacy_cache_files(&self) -> Option<(Series, Vec<Episode>)> {
         let (series_data, episodes_data) = tokio::join!(
             tokio::fs::read_to_string(self.series_path.as_str()),
             tokio::fs::read_to_string(self.episodes_path.as_str())
@@ -354,12 +1727,12 @@ impl AppFolder {
         
         if let Err(err) = series_data.as_ref() {
             let message = format!("IO error while reading series cache: {}", err);
-            self.errors.write().await.push(message);
+            self.push_file_error(PATH_STR_SERIES_DATA, err.kind(), message).await;
         }
 
         if let Err(err) = episodes_data.as_ref() {
             let message = format!("IO error while reading episodes cache: {}", err);
-            self.errors.write().await.push(message);
+            self.push_file_error(PATH_STR_EPISODES_DATA, err.kind(), message).await;
         }
 
         let series_data = series_data.as_ref().ok()?;
@@ -369,7 +1742,7 @@ impl AppFolder {
             Ok(series) => series,
             Err(err) => {
                 let message = format!("JSON decoding error reading series from file: {}", err);
-                self.errors.write().await.push(message);
+                self.push_file_error(PATH_STR_SERIES_DATA, std::io::ErrorKind::InvalidData, message).await;
                 return None;
             },
         };
@@ -378,123 +1751,467 @@ impl AppFolder {
             Ok(episodes) => episodes,
             Err(err) => {
                 let message = format!("JSON decoding error reading episodes from file: {}", err);
-                self.errors.write().await.push(message);
+                self.push_file_error(PATH_STR_EPISODES_DATA, std::io::ErrorKind::InvalidData, message).await;
                 return None;
             },
         };
+        Some((series, episodes))
+    }
+
+    // Write to a temporary file first so a failed write doesn't corrupt the existing cache
+    async fn write_cache_file(&self, data: String) -> Option<()> {
+        if let Err(err) = atomic_write(self.cache_path.as_str(), data).await {
+            let message = format!("IO error while saving cache: {}", err);
+            self.push_file_error(PATH_STR_CACHE_DATA, err.kind(), message).await;
+            return None;
+        }
+        Some(())
+    }
 
+    // The cache file replaces these so keeping them around would only leave stale copies
+    async fn remove_legacy_cache_files(&self) {
+        for (filename, path) in [
+            (PATH_STR_SERIES_DATA, self.series_path.as_str()),
+            (PATH_STR_EPISODES_DATA, self.episodes_path.as_str()),
+            (PATH_STR_CACHE_META, self.cache_meta_path.as_str()),
+        ] {
+            match tokio::fs::remove_file(path).await {
+                Ok(()) => {},
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {},
+                Err(err) => {
+                    let message = format!("IO error while removing legacy cache file: {}", err);
+                    self.push_file_error(filename, err.kind(), message).await;
+                },
+            }
+        }
+    }
+
+    async fn get_cache_key(&self, cache: &TvdbCache) -> SeriesCacheKey {
+        SeriesCacheKey {
+            provider: cache.get_provider(),
+            series_id: cache.series.id,
+            ordering: cache.get_ordering(),
+            language: self.get_language().await,
+        }
+    }
+
+    pub async fn get_loaded_cache_key(&self) -> Option<SeriesCacheKey> {
+        let cache = self.cache.read().await.clone()?;
+        Some(self.get_cache_key(&cache).await)
+    }
+
+    // Folders of the same series use the newest loaded cache so it is only kept in memory once
+    async fn share_cache(&self, cache: TvdbCache) -> Arc<TvdbCache> {
+        match self.cache_registry.as_ref() {
+            Some(registry) => registry.register(self.get_cache_key(&cache).await, cache),
+            None => Arc::new(cache),
+        }
+    }
+
+    // Used after another folder of the same series has fetched the cache from the api
+    pub async fn load_cache_from_registry(&self) -> Option<()> {
+        let _busy_lock = self.lock_busy().await;
+        let registry = self.cache_registry.as_ref()?;
+        let current = self.cache.read().await.clone()?;
+        let shared = registry.get(&self.get_cache_key(&current).await)?;
+        if Arc::ptr_eq(&current, &shared) {
+            return Some(());
+        }
         let mut cache = self.cache.write().await;
-        *cache = Some(TvdbCache::new(series, episodes));
+        *cache = Some(shared);
+        self.is_actors_load_attempted.store(false, Ordering::Relaxed);
+        self.is_full_rescan_required.store(true, Ordering::Relaxed);
+        self.notify_change(ChangeKind::Cache);
         Some(())
     }
 
-    pub async fn load_cache_from_api(&self, session: Arc<LoginSession>, series_id: u32) -> Option<()> {
-        let _busy_lock = self.busy_lock.lock().await;
+    // Only one of the episodes sharing a key can be matched to files so list the rest
+    async fn push_duplicate_episodes_warning(&self, cache: &TvdbCache) {
+        let duplicates = cache.get_duplicate_episodes();
+        if duplicates.is_empty() {
+            return;
+        }
+        let entries: Vec<String> = duplicates.iter().map(|(key, ids)| {
+            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+            format!("S{:02}E{:02} (ids {})", key.season, key.episode, ids.join(", "))
+        }).collect();
+        let message = format!("Series has episodes with the same number, using the lowest id for: {}", entries.join(", "));
+        self.errors.write().await.push_warn("Cache", message);
+    }
 
-        let (series_res, episodes_res) = tokio::join!(
-            session.get_series(series_id),
-            session.get_episodes(series_id),
-        );
+    // Caches saved before the fetch time was recorded don't have a meta file so a missing file isn't an error
+    async fn read_cache_meta_from_file(&self) -> Option<CacheMeta> {
+        let data = match tokio::fs::read_to_string(self.cache_meta_path.as_str()).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                let message = format!("IO error while reading cache meta: {}", err);
+                self.push_file_error(PATH_STR_CACHE_META, err.kind(), message).await;
+                return None;
+            },
+        };
+        match serde_json::from_str(data.as_str()) {
+            Ok(meta) => Some(meta),
+            Err(err) => {
+                let message = format!("JSON decoding error reading cache meta from file: {}", err);
+                self.push_file_error(PATH_STR_CACHE_META, std::io::ErrorKind::InvalidData, message).await;
+                None
+            },
+        }
+    }
 
-        let series = match series_res {
-            Ok(series) => series,
+    // Caches saved before the summary was fetched don't have a summary file so a missing file isn't an error
+    async fn read_episodes_summary_from_file(&self) -> Option<EpisodesSummary> {
+        let data = match tokio::fs::read_to_string(self.episodes_summary_path.as_str()).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
             Err(err) => {
-                let message = format!("Api error while fetching series: {}", err);
-                self.errors.write().await.push(message);
+                let message = format!("IO error while reading episodes summary cache: {}", err);
+                self.push_file_error(PATH_STR_EPISODES_SUMMARY_DATA, err.kind(), message).await;
                 return None;
             },
         };
+        match serde_json::from_str(data.as_str()) {
+            Ok(summary) => Some(summary),
+            Err(err) => {
+                let message = format!("JSON decoding error reading episodes summary from file: {}", err);
+                self.push_file_error(PATH_STR_EPISODES_SUMMARY_DATA, std::io::ErrorKind::InvalidData, message).await;
+                None
+            },
+        }
+    }
 
-        let episodes = match episodes_res {
-            Ok(episodes) => episodes,
+    // Caches saved before actors were fetched don't have an actors file so a missing file isn't an error
+    pub async fn load_actors_from_file(&self) -> Option<()> {
+        if self.cache.read().await.as_ref()?.actors.is_some() {
+            return Some(());
+        }
+        if self.is_actors_load_attempted.swap(true, Ordering::Relaxed) {
+            return None;
+        }
+
+        let data = match tokio::fs::read_to_string(self.actors_path.as_str()).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                let message = format!("IO error while reading actors cache: {}", err);
+                self.push_file_error(PATH_STR_ACTORS_DATA, err.kind(), message).await;
+                return None;
+            },
+        };
+
+        let actors: Vec<Actor> = match serde_json::from_str(data.as_str()) {
+            Ok(actors) => actors,
             Err(err) => {
-                let message = format!("Api error while fetching episodes: {}", err);
-                self.errors.write().await.push(message);
+                let message = format!("JSON decoding error reading actors from file: {}", err);
+                self.push_file_error(PATH_STR_ACTORS_DATA, std::io::ErrorKind::InvalidData, message).await;
                 return None;
             },
         };
 
         let mut cache = self.cache.write().await;
-        *cache = Some(TvdbCache::new(series, episodes));
+        Arc::make_mut(cache.as_mut()?).set_actors(actors);
+        self.notify_change(ChangeKind::Cache);
         Some(())
     }
 
-    pub async fn refresh_cache_from_api(&self, session: Arc<LoginSession>) -> Option<()> {
-        let series_id = {
-            let cache_guard = self.cache.read().await;
-            match cache_guard.as_ref() {
-                Some(cache) => cache.series.id,
-                None => {
-                    let message = "Couldn't refresh cache since it requires an existing loaded cache".to_string();
-                    self.errors.write().await.push(message);
-                    return None;
-                },
-            }
+    pub async fn load_cache_from_api(&self, provider: Arc<dyn MetadataProvider>, series_id: u32) -> Option<()> {
+        let _busy_lock = self.lock_busy().await;
+
+        let language = self.get_language().await;
+        let (series_res, episodes_res, actors_res, summary_res) = tokio::join!(
+            provider.get_series(series_id, language.as_deref()),
+            provider.get_episodes(series_id, language.as_deref()),
+            provider.get_actors(series_id),
+            provider.get_episodes_summary(series_id),
+        );
+
+        let mut series = match series_res {
+            Ok(series) => series,
+            Err(err) => {
+                let message = get_rate_limit_message(provider.get_kind(), "fetching series", &err)
+                    .unwrap_or_else(|| format!("Api error while fetching series: {}", err));
+                self.errors.write().await.push_error("Cache", message);
+                return None;
+            },
         };
-        self.load_cache_from_api(session, series_id).await
-    }
 
-    pub async fn save_cache_to_file(&self) -> Option<()> {
-        let _busy_lock = self.busy_lock.lock().await;
+        let mut episodes = match episodes_res {
+            Ok(episodes) => episodes,
+            Err(err) => {
+                let message = get_rate_limit_message(provider.get_kind(), "fetching episodes", &err)
+                    .unwrap_or_else(|| format!("Api error while fetching episodes: {}", err));
+                self.errors.write().await.push_error("Cache", message);
+                return None;
+            },
+        };
+        // The api shouldn't do this but the loaded cache is kept if it does
+        self.remove_foreign_episodes(&series, &mut episodes).await?;
 
-        let (series_str, episodes_str) = {
+        // Tag the cache so the series id isn't used with a different api later
+        series.provider = Some(provider.get_kind());
+        let mut new_cache = TvdbCache::new(series, episodes, self.get_episode_ordering().await);
+        new_cache.fetched_at = Some(chrono::Utc::now());
+        // The cast is only shown as extra info so the cache is still usable without it
+        match actors_res {
+            Ok(actors) => new_cache.set_actors(actors),
+            Err(err) => {
+                let message = get_rate_limit_message(provider.get_kind(), "fetching actors", &err)
+                    .unwrap_or_else(|| format!("Api error while fetching actors: {}", err));
+                self.errors.write().await.push_warn("Cache", message);
+            },
+        }
+        match summary_res {
+            Ok(summary) => new_cache.episodes_summary = Some(summary),
+            Err(err) => {
+                let message = get_rate_limit_message(provider.get_kind(), "fetching episodes summary", &err)
+                    .unwrap_or_else(|| format!("Api error while fetching episodes summary: {}", err));
+                self.errors.write().await.push_warn("Cache", message);
+            },
+        }
+        self.push_duplicate_episodes_warning(&new_cache).await;
+
+        let new_cache = self.share_cache(new_cache).await;
+        let mut cache = self.cache.write().await;
+        *cache = Some(new_cache);
+        self.is_actors_load_attempted.store(false, Ordering::Relaxed);
+        self.is_full_rescan_required.store(true, Ordering::Relaxed);
+        self.notify_change(ChangeKind::Cache);
+        Some(())
+    }
+
+    // Only the provided seasons are fetched and merged into the loaded cache
+    // This avoids fetching every episode of long running series when only a few seasons are on disk
+    pub async fn load_cache_seasons_from_api(&self, provider: Arc<dyn MetadataProvider>, seasons: &[u32]) -> Option<()> {
+        let _busy_lock = self.lock_busy().await;
+
+        let series_id = match self.cache.read().await.as_ref() {
+            Some(cache) if cache.get_provider() != provider.get_kind() => {
+                let message = format!("Couldn't fetch seasons since the cache is from {}", cache.get_provider().to_str());
+                self.errors.write().await.push_warn("Cache", message);
+                return None;
+            },
+            Some(cache) => cache.series.id,
+            None => {
+                let message = "Couldn't fetch seasons since it requires an existing loaded cache".to_string();
+                self.errors.write().await.push_warn("Cache", message);
+                return None;
+            },
+        };
+
+        let language = self.get_language().await;
+        let ordering = self.get_episode_ordering().await;
+        let results = futures::future::join_all(seasons.iter().map(|season| {
+            provider.get_season_episodes(series_id, *season, ordering, language.as_deref())
+        })).await;
+
+        let mut episodes: Vec<Episode> = Vec::new();
+        for (season, res) in seasons.iter().zip(results) {
+            match res {
+                Ok(season_episodes) => episodes.extend(season_episodes),
+                Err(err) => {
+                    let action = format!("fetching episodes for season {}", season);
+                    let message = get_rate_limit_message(provider.get_kind(), action.as_str(), &err)
+                        .unwrap_or_else(|| format!("Api error while {}: {}", action, err));
+                    self.errors.write().await.push_error("Cache", message);
+                    return None;
+                },
+            }
+        }
+
+        let mut cache_guard = self.cache.write().await;
+        // The merged cache is no longer the same as the one other folders share
+        let cache = Arc::make_mut(cache_guard.as_mut()?);
+        cache.merge_episodes(episodes);
+        self.push_duplicate_episodes_warning(cache).await;
+        self.is_full_rescan_required.store(true, Ordering::Relaxed);
+        self.notify_change(ChangeKind::Cache);
+        Some(())
+    }
+
+    fn get_series_poster_path(&self) -> Option<String> {
+        let cache = self.cache.try_read().ok()?;
+        cache.as_ref()?.series.poster.clone().filter(|poster| !poster.is_empty())
+    }
+
+    // Returns None if the poster hasn't been loaded yet
+    pub fn get_series_poster(&self) -> Option<Arc<Vec<u8>>> {
+        self.image_cache.get_cached(self.get_series_poster_path()?.as_str())
+    }
+
+    pub async fn load_series_poster(&self, provider: Option<Arc<dyn MetadataProvider>>) -> Option<Arc<Vec<u8>>> {
+        let poster = self.cache.read().await.as_ref()?.series.poster.clone().filter(|poster| !poster.is_empty())?;
+        self.image_cache.get_or_fetch(provider.as_deref(), poster.as_str()).await
+    }
+
+    pub fn get_image_cache(&self) -> &ImageCache {
+        &self.image_cache
+    }
+
+    pub async fn get_seasons_on_disk(&self) -> BTreeSet<u32> {
+        let files = self.get_files().await;
+        get_descriptor_seasons(&files)
+    }
+
+    pub async fn refresh_seasons_on_disk_from_api(&self, provider: Arc<dyn MetadataProvider>) -> Option<()> {
+        let seasons: Vec<u32> = self.get_seasons_on_disk().await.into_iter().collect();
+        if seasons.is_empty() {
+            let message = "Couldn't find any files with a season to fetch".to_string();
+            self.errors.write().await.push_warn("Cache", message);
+            return None;
+        }
+        self.load_cache_seasons_from_api(provider, seasons.as_slice()).await
+    }
+
+    pub async fn refresh_cache_from_api(&self, provider: Arc<dyn MetadataProvider>) -> Option<()> {
+        let series_id = {
+            let cache_guard = self.cache.read().await;
+            match cache_guard.as_ref() {
+                // The series id would refer to a different series in another api
+                Some(cache) if cache.get_provider() != provider.get_kind() => {
+                    let message = format!("Couldn't refresh cache since it is from {}", cache.get_provider().to_str());
+                    self.errors.write().await.push_warn("Cache", message);
+                    return None;
+                },
+                Some(cache) => cache.series.id,
+                None => {
+                    let message = "Couldn't refresh cache since it requires an existing loaded cache".to_string();
+                    self.errors.write().await.push_warn("Cache", message);
+                    return None;
+                },
+            }
+        };
+        self.load_cache_from_api(provider, series_id).await
+    }
+
+    pub async fn save_cache_to_file(&self) -> Option<()> {
+        let _busy_lock = self.lock_busy().await;
+
+        let (cache_str, actors_str, summary_str) = {
             let cache_guard = self.cache.read().await;
             let cache = match cache_guard.as_ref() {
                 Some(cache) => cache,
                 None => {
                     let message = "Couldn't save cache to file since it is unloaded".to_string();
-                    self.errors.write().await.push(message);
+                    self.errors.write().await.push_warn("Cache", message);
                     return None;
                 },
             };
-            let series_str = match serde_json::to_string_pretty(&cache.series) {
+            let cache_str = match cache.to_cache_file_string() {
                 Ok(data) => data,
                 Err(err) => {
-                    let message = format!("JSON encode error when saving series cache: {}", err);
-                    self.errors.write().await.push(message);
+                    let message = format!("JSON encode error when saving cache: {}", err);
+                    self.errors.write().await.push_error("Cache", message);
                     return None;
                 },
             };
-            let episodes_str = match serde_json::to_string_pretty(&cache.episodes) {
-                Ok(data) => data,
-                Err(err) => {
-                    let message = format!("JSON encode error when saving episodes cache: {}", err);
-                    self.errors.write().await.push(message);
+            let actors_str = match cache.actors.as_ref().map(serde_json::to_string_pretty) {
+                None => None,
+                Some(Ok(data)) => Some(data),
+                Some(Err(err)) => {
+                    let message = format!("JSON encode error when saving actors cache: {}", err);
+                    self.errors.write().await.push_error("Cache", message);
                     return None;
                 },
             };
-            (series_str, episodes_str)
+            let summary_str = match cache.episodes_summary.as_ref().map(serde_json::to_string_pretty) {
+                None => None,
+                Some(Ok(data)) => Some(data),
+                Some(Err(err)) => {
+                    let message = format!("JSON encode error when saving episodes summary cache: {}", err);
+                    self.errors.write().await.push_error("Cache", message);
+                    return None;
+                },
+            };
+            (cache_str, actors_str, summary_str)
         };
 
-        let (res_0, res_1) = tokio::join!(
-            tokio::fs::write(self.series_path.as_str(), series_str),
-            tokio::fs::write(self.episodes_path.as_str(), episodes_str),
+        let (res_0, res_1, res_2) = tokio::join!(
+            self.write_cache_file(cache_str),
+            async {
+                match actors_str {
+                    Some(actors_str) => atomic_write(self.actors_path.as_str(), actors_str).await,
+                    None => Ok(()),
+                }
+            },
+            async {
+                match summary_str {
+                    Some(summary_str) => atomic_write(self.episodes_summary_path.as_str(), summary_str).await,
+                    None => Ok(()),
+                }
+            },
         );
 
-        if let Err(err) = res_0.as_ref() {
-            let message = format!("IO error while saving series cache: {}", err);
-            self.errors.write().await.push(message);
+        // Legacy files that failed to load would otherwise be left behind
+        if res_0.is_some() {
+            self.remove_legacy_cache_files().await;
         }
 
         if let Err(err) = res_1.as_ref() {
-            let message = format!("IO error while saving episodes cache: {}", err);
-            self.errors.write().await.push(message);
+            let message = format!("IO error while saving actors cache: {}", err);
+            self.push_file_error(PATH_STR_ACTORS_DATA, err.kind(), message).await;
+        }
+
+        if let Err(err) = res_2.as_ref() {
+            let message = format!("IO error while saving episodes summary cache: {}", err);
+            self.push_file_error(PATH_STR_EPISODES_SUMMARY_DATA, err.kind(), message).await;
         }
         
-        if res_0.is_err() || res_1.is_err() {
+        if res_0.is_none() || res_1.is_err() || res_2.is_err() {
             return None;
         }
         Some(())
     }
 
-    pub async fn execute_file_changes(&self) {
-        let _busy_lock = self.busy_lock.lock().await;
+    // Determine the operations that execute_file_changes(...) would perform without modifying anything
+    // Paths are relative to the folder, where an empty path refers to the folder itself
+    pub async fn plan_file_changes(&self) -> Vec<PlannedOperation> {
+        self.plan_file_change_stages().await.into_iter().flatten().collect()
+    }
+
+    pub async fn get_execution_plan(&self) -> ExecutionPlan {
+        let mut plan = ExecutionPlan::default();
+        {
+            let files = self.get_files().await;
+            for file in files.to_iter().filter(|file| file.get_is_enabled()) {
+                match file.get_action() {
+                    Action::Delete => {
+                        plan.total_deletes += 1;
+                        plan.total_bytes_to_delete += file.get_size();
+                    },
+                    Action::Rename if !file.get_is_conflict() && file.get_dest_error().is_none() => {
+                        plan.total_renames += 1;
+                    },
+                    _ => {},
+                }
+            }
+        }
+        (plan.skipped_conflicts, plan.skipped_invalid) = self.get_skipped_renames().await;
+        plan.operations = self.plan_file_changes().await;
+        plan
+    }
 
-        use std::pin::Pin;
-        use std::future::Future;
-        type F = Pin<Box<dyn Future<Output = Result<(), std::io::Error>> + Send>>;
+    // Enabled renames that aren't performed since they conflict or have an invalid destination
+    async fn get_skipped_renames(&self) -> (usize, usize) {
+        let files = self.get_files().await;
+        let skipped_conflicts = files
+            .to_iter()
+            .filter(|file| file.get_is_enabled() && file.get_action() == Action::Rename && file.get_is_conflict())
+            .count();
+        let skipped_invalid = files
+            .to_iter()
+            .filter(|file| file.get_is_enabled() && file.get_action() == Action::Rename && !file.get_is_conflict())
+            .filter(|file| file.get_dest_error().is_some())
+            .count();
+        (skipped_conflicts, skipped_invalid)
+    }
 
-        let mut tasks = Vec::<F>::new();
+    // Operations are grouped into stages which are performed in order
+    async fn plan_file_change_stages(&self) -> Vec<Vec<PlannedOperation>> {
+        let mut renames = Vec::<(String, String)>::new();
+        let mut deletes = Vec::<String>::new();
+        let mut removed_sources = HashSet::<String>::new();
+        let mut new_parent_dirs = Vec::<String>::new();
         {
             let files = self.get_files().await;
             for file in files.to_iter() {
@@ -503,48 +2220,81 @@ impl AppFolder {
                 }
 
                 if file.get_action() == Action::Delete {
-                    let src = path::Path::new(&self.folder_path).join(file.get_src());
-                    tasks.push(Box::pin({
-                        async move {
-                            tokio::fs::remove_file(src).await
-                        }
-                    }));
+                    removed_sources.insert(file.get_src().to_string());
+                    deletes.push(file.get_src().to_string());
                     continue;
                 }
 
-                if file.get_action() == Action::Rename && !file.get_is_conflict() {
-                    tasks.push(Box::pin({
-                        let src = path::Path::new(&self.folder_path).join(file.get_src());
-                        let dest = path::Path::new(&self.folder_path).join(file.get_dest());
-                        async move {
-                            let parent_dir = dest.parent().expect("Invalid filepath");
-                            tokio::fs::create_dir_all(parent_dir).await?;
-                            tokio::fs::rename(src, dest).await
+                if file.get_action() == Action::Rename && !file.get_is_conflict() && file.get_dest_error().is_none() {
+                    removed_sources.insert(file.get_src().to_string());
+                    renames.push((file.get_src().to_string(), file.get_dest().to_string()));
+                    if let Some(parent) = path::Path::new(file.get_dest()).parent() {
+                        let parent = parent.to_string_lossy().to_string();
+                        if !parent.is_empty() && !new_parent_dirs.contains(&parent) {
+                            new_parent_dirs.push(parent);
                         }
-                    }));
+                    }
                     continue;
                 }
             }
         }
-        
-        {
-            let mut errors = self.errors.write().await;
-            for res in futures::future::join_all(tasks).await.into_iter() {
-                if let Err(err) = res {
-                    let message = format!("IO error while executing file changes: {}", err);
-                    errors.push(message);
+
+        // Folders are created before files are moved into them, and removed once files are moved out of them
+        let mut create_dirs = Vec::new();
+        for parent in new_parent_dirs.iter() {
+            let parent_path = path::Path::new(self.folder_path.as_str()).join(parent);
+            if !tokio::fs::try_exists(parent_path).await.unwrap_or(false) {
+                create_dirs.push(PlannedOperation::CreateDir { path: parent.clone() });
+            }
+        }
+
+        // Ordering is synchronous so we find the sources that need a temporary path before checking the disk
+        let mut cycle_sources = Vec::<String>::new();
+        order_file_changes(renames.clone(), deletes.clone(), |src| {
+            cycle_sources.push(src.to_string());
+            String::new()
+        });
+        let mut used_paths: HashSet<String> = renames.iter().map(|(src, _)| get_path_key(src)).collect();
+        let mut temporary_paths = HashMap::<String, String>::new();
+        for src in cycle_sources {
+            for i in 0.. {
+                let temporary_path = match i {
+                    0 => format!("{}{}", src, TEMPORARY_RENAME_SUFFIX),
+                    i => format!("{}.{}{}", src, i, TEMPORARY_RENAME_SUFFIX),
                 };
+                if used_paths.contains(&get_path_key(temporary_path.as_str())) {
+                    continue;
+                }
+                let full_path = path::Path::new(self.folder_path.as_str()).join(temporary_path.as_str());
+                if tokio::fs::try_exists(full_path).await.unwrap_or(false) {
+                    continue;
+                }
+                used_paths.insert(get_path_key(temporary_path.as_str()));
+                temporary_paths.insert(src, temporary_path);
+                break;
             }
         }
+        let file_changes = order_file_changes(renames, deletes, |src| {
+            temporary_paths
+                .remove(src)
+                .unwrap_or_else(|| format!("{}{}", src, TEMPORARY_RENAME_SUFFIX))
+        });
 
-        // Automatically delete empty folders
-        self.delete_empty_folders().await;
-    }
+        let mut plan = Vec::new();
+        plan.push(create_dirs);
+        plan.extend(file_changes);
+        plan.retain(|operations| !operations.is_empty());
+
+        if !self.filter_rules.delete_empty_folders {
+            return plan;
+        }
 
-    async fn delete_empty_folders(&self) {
-        let mut tasks = Vec::new();
+        let mut remove_dirs = Vec::new();
 
+        // Folders are deleted if they would have no files left in them
+        // The series folder itself is always kept even if every file in it is renamed
         let walker = walkdir::WalkDir::new(self.folder_path.as_str())
+            .min_depth(1)
             .max_depth(1)
             .follow_links(false)
             .into_iter()
@@ -554,111 +2304,3194 @@ impl AppFolder {
                 continue;
             }
 
-            let is_empty = check_folder_empty(entry.path());
+            let rel_path = match entry.path().strip_prefix(self.folder_path.as_str()) {
+                Ok(rel_path) => rel_path.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "/"),
+                Err(_) => continue,
+            };
+
+            let is_written_to = new_parent_dirs.iter().any(|parent| {
+                path::Path::new(parent.as_str()).starts_with(rel_path.as_str())
+            });
+            if is_written_to {
+                continue;
+            }
+
+            let is_empty = check_folder_empty(
+                path::Path::new(self.folder_path.as_str()), entry.path(), &self.filter_rules,
+                |src| removed_sources.contains(src),
+            );
             if !is_empty {
                 continue;
             }
 
-            tasks.push({
-                async move {
-                    tokio::fs::remove_dir_all(entry.path()).await
-                }
-            });
+            remove_dirs.push(PlannedOperation::RemoveEmptyDir { path: rel_path });
         }
 
-        let mut errors = self.errors.write().await;
-        for res in futures::future::join_all(tasks).await.into_iter() {
-            if let Err(err) = res {
-                let message = format!("IO error while deleting empty folders: {}", err);
-                errors.push(message);
-            };
+        if !remove_dirs.is_empty() {
+            plan.push(remove_dirs);
         }
-    }
-    
-    // getters
-    pub fn get_folder_path(&self) -> &str {
-        self.folder_path.as_str() 
+        plan
     }
 
-    pub fn get_folder_name(&self) -> &str {
-        self.folder_name.as_str() 
+    // The folder is searched again afterwards since its files no longer match what is on disk
+    pub async fn execute_file_changes_and_rescan(&self) -> ExecutionSummary {
+        let summary = self.execute_file_changes().await;
+        self.rescan_after_execution(summary).await
     }
 
-    pub fn get_file_tracker(&self) -> &RwLock<FileTracker> {
-        &self.file_tracker
+    async fn rescan_after_execution(&self, mut summary: ExecutionSummary) -> ExecutionSummary {
+        let is_rescanned = self.update_file_intents().await.is_some();
+        if is_rescanned && self.filter_rules.prune_bookmarks_after_execute {
+            summary.pruned_bookmarks = self.prune_orphaned_bookmarks().await;
+        }
+        summary
     }
 
-    pub fn get_busy_lock(&self) -> &Mutex<()> {
-        &self.busy_lock
+    // Every rename that can be performed is enabled and executed in one step
+    // Deletes are never enabled so files are only removed if they were already enabled
+    // The folder stays busy between enabling and executing so the enabled renames can't be changed in between
+    pub async fn organize(&self, auto_skip_conflicts: bool) -> Option<ExecutionSummary> {
+        let summary = {
+            let _busy_lock = self.lock_busy().await;
+            self.enable_organize_renames_while_busy(auto_skip_conflicts).await?;
+            self.execute_file_changes_while_busy().await
+        };
+        Some(self.rescan_after_execution(summary).await)
     }
 
-    pub fn get_errors(&self) -> &RwLock<Vec<String>> {
-        &self.errors
+    // Returns the sources of the renames that were enabled so they can be disabled again if organizing is cancelled
+    // Enabled renames that conflict stop the folder from being organized unless they are skipped
+    pub async fn enable_organize_renames(&self, auto_skip_conflicts: bool) -> Option<Vec<String>> {
+        let _busy_lock = self.lock_busy().await;
+        self.enable_organize_renames_while_busy(auto_skip_conflicts).await
     }
 
-    pub fn get_selected_descriptor(&self) -> &RwLock<Option<EpisodeKey>> {
-        &self.selected_descriptor
+    // Files that are no longer renames are left alone
+    pub async fn disable_organize_renames(&self, sources: &[String]) {
+        let _busy_lock = self.lock_busy().await;
+        {
+            let mut files = self.get_mut_files().await;
+            for src in sources.iter() {
+                let index = files.get_file_tracker().get_source_index(src.as_str()).copied();
+                if let Some(mut file) = index.and_then(|index| files.get(index)) {
+                    if file.get_action() == Action::Rename {
+                        file.set_is_enabled(false);
+                    }
+                }
+            }
+        }
+        self.flush_file_changes().await;
     }
 
-    pub fn get_cache(&self) -> &RwLock<Option<TvdbCache>> {
-        &self.cache
-    }
+    async fn enable_organize_renames_while_busy(&self, auto_skip_conflicts: bool) -> Option<Vec<String>> {
+        let mut enabled_indices = {
+            let mut files = self.get_mut_files().await;
+            let indices = files.get_indices_where(|file| {
+                !file.get_is_enabled() && file.get_action() == Action::Rename && file.get_dest_error().is_none()
+            });
+            files.set_enabled_for_indices(indices.as_slice(), true);
+            indices
+        };
+        self.flush_file_changes().await;
 
-    pub fn get_bookmarks(&self) -> &RwLock<BookmarkTable> {
-        &self.bookmarks
+        // Conflicts can only be known once the renames are enabled since enabled files vacate their source
+        loop {
+            let (total_conflicts, conflict_indices) = {
+                let files = self.get_files().await;
+                let total_conflicts = files
+                    .to_iter()
+                    .filter(|file| file.get_is_enabled() && file.get_action() == Action::Rename && file.get_is_conflict())
+                    .count();
+                let conflict_indices: Vec<usize> = enabled_indices
+                    .iter()
+                    .copied()
+                    .filter(|index| files.get(*index).map(|file| file.get_is_conflict()).unwrap_or(false))
+                    .collect();
+                (total_conflicts, conflict_indices)
+            };
+
+            if total_conflicts > 0 && !auto_skip_conflicts {
+                self.get_mut_files().await.set_enabled_for_indices(enabled_indices.as_slice(), false);
+                self.flush_file_changes().await;
+                let message = format!("Cannot organize folder since {} renames conflict, resolve them or skip them", total_conflicts);
+                self.errors.write().await.push_warn("Organize", message);
+                return None;
+            }
+            if conflict_indices.is_empty() {
+                break;
+            }
+
+            // Skipped renames are disabled otherwise their source would be overwritten by renames that expect it to be vacated
+            // Disabling them can cause other renames to conflict so this is repeated until none of them do
+            self.get_mut_files().await.set_enabled_for_indices(conflict_indices.as_slice(), false);
+            self.flush_file_changes().await;
+            enabled_indices.retain(|index| !conflict_indices.contains(index));
+        }
+        let files = self.get_files().await;
+        let sources = enabled_indices
+            .iter()
+            .filter_map(|index| files.get(*index).map(|file| file.get_src().to_string()))
+            .collect();
+        Some(sources)
     }
 
-    pub async fn get_files(&self) -> ImmutableAppFileList {
-        let file_list = self.file_list.read().await;
-        let file_tracker = self.file_tracker.read().await;
-        ImmutableAppFileList::new(file_list, file_tracker)
+    pub async fn execute_file_changes(&self) -> ExecutionSummary {
+        let _busy_lock = self.lock_busy().await;
+        self.execute_file_changes_while_busy().await
     }
 
-    pub async fn get_mut_files(&self) -> MutableAppFileList {
-        let file_list = self.file_list.read().await;
-        let file_tracker = self.file_tracker.read().await;
-        let change_queue = self.change_queue.write().await;
-        MutableAppFileList::new(file_list, file_tracker, change_queue)
+    async fn execute_file_changes_while_busy(&self) -> ExecutionSummary {
+        self.is_cancelled.store(false, Ordering::Relaxed);
+
+        // The sources in the file list no longer match the folder until it is rescanned
+        self.is_file_list_outdated.store(true, Ordering::Relaxed);
+
+        let mut summary = ExecutionSummary::default();
+        (summary.skipped_conflicts, summary.skipped_invalid) = self.get_skipped_renames().await;
+
+        let stages = self.plan_file_change_stages().await;
+        let total_operations = stages.iter().map(|operations| operations.len()).sum();
+        self.last_execution_records.write().await.clear();
+        // Files renamed through a temporary path are recorded against their original source and final destination
+        let mut temporary_sources = HashMap::<&str, &str>::new();
+        let mut temporary_dests = HashMap::<&str, &str>::new();
+        for operation in stages.iter().flatten() {
+            if let PlannedOperation::Rename { src, dest } = operation {
+                if is_temporary_rename(operation) {
+                    temporary_sources.insert(dest.as_str(), src.as_str());
+                } else if src.ends_with(TEMPORARY_RENAME_SUFFIX) {
+                    temporary_dests.insert(src.as_str(), dest.as_str());
+                }
+            }
+        }
+        let mut is_bookmarks_changed = false;
+        self.execution_progress.send_replace(ExecutionProgress {
+            total_operations,
+            ..ExecutionProgress::default()
+        });
+
+        for operations in stages.iter() {
+            if self.is_cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+
+            // Limit the number of operations in flight so that cancellation stops the remaining operations
+            let mut pending_operations = operations.iter();
+            let mut tasks = FuturesUnordered::new();
+            loop {
+                while tasks.len() < MAX_CONCURRENT_FILE_OPERATIONS && !self.is_cancelled.load(Ordering::Relaxed) {
+                    let operation = match pending_operations.next() {
+                        Some(operation) => operation,
+                        None => break,
+                    };
+                    tasks.push(async move {
+                        let res = self.execute_planned_operation(operation).await;
+                        (operation, res)
+                    });
+                }
+
+                let (operation, res) = match tasks.next().await {
+                    Some(task) => task,
+                    None => break,
+                };
+
+                self.execution_progress.send_modify(|progress| {
+                    progress.total_completed += 1;
+                    progress.current_file = Some(operation.get_path().to_string());
+                });
+
+                // Bookmarks are keyed by path so they have to follow renamed files, including temporary renames
+                if let (Ok(_), PlannedOperation::Rename { src, dest }) = (&res, operation) {
+                    is_bookmarks_changed |= self.bookmarks.write().await.rename_key(src, dest);
+                }
+
+                if res.is_ok() {
+                    match operation {
+                        PlannedOperation::Rename { .. } if is_temporary_rename(operation) => {},
+                        PlannedOperation::Rename { .. } => summary.renamed += 1,
+                        PlannedOperation::Delete { .. } => summary.deleted += 1,
+                        PlannedOperation::RemoveEmptyDir { .. } => summary.removed_dirs += 1,
+                        PlannedOperation::CreateDir { .. } => {},
+                    }
+                }
+
+                let record = match operation {
+                    // Successful temporary renames are recorded once the file reaches its destination
+                    PlannedOperation::Rename { .. } if is_temporary_rename(operation) && res.is_ok() => None,
+                    PlannedOperation::Rename { src, dest } => Some(ExecutionRecord {
+                        src: temporary_sources.get(src.as_str()).copied().unwrap_or(src).to_string(),
+                        dest: temporary_dests.get(dest.as_str()).copied().unwrap_or(dest).to_string(),
+                        action: Action::Rename,
+                        result: Ok(()),
+                    }),
+                    PlannedOperation::Delete { src } => Some(ExecutionRecord {
+                        src: src.to_string(),
+                        dest: String::new(),
+                        action: Action::Delete,
+                        result: Ok(()),
+                    }),
+                    PlannedOperation::CreateDir { .. } | PlannedOperation::RemoveEmptyDir { .. } => None,
+                };
+                if let Some(mut record) = record {
+                    if let Err(err) = &res {
+                        record.result = Err(err.to_string());
+                    }
+                    self.last_execution_records.write().await.push(record);
+                }
+
+                match res {
+                    Ok(None) => {},
+                    Ok(Some(err)) => {
+                        let message = format!("Trash unavailable so '{}' was permanently deleted: {}", operation, err);
+                        self.errors.write().await.push_warn("Execute", message);
+                    },
+                    Err(err) => {
+                        summary.failed += 1;
+                        let message = format!("IO error while executing '{}': {}", operation, err);
+                        self.push_file_error(operation.get_path(), err.kind(), message).await;
+                    },
+                };
+            }
+        }
+
+        if is_bookmarks_changed {
+            self.save_bookmarks_to_file().await;
+        }
+
+        let progress = self.execution_progress.send_replace(ExecutionProgress::default());
+        if self.is_cancelled.load(Ordering::Relaxed) {
+            let message = format!(
+                "Cancelled execution of file changes after {}/{} operations", 
+                progress.total_completed, total_operations,
+            );
+            self.errors.write().await.push_warn("Execute", message);
+        }
+
+        // An execution that didn't change anything keeps the journal of the one before it
+        let journal = {
+            let records = self.last_execution_records.read().await;
+            UndoJournal::from_records(records.as_slice(), &summary, chrono::Utc::now())
+        };
+        if !journal.is_empty() {
+            self.save_undo_journal_to_file(journal).await;
+        }
+
+        *self.last_execution_summary.write().await = Some(summary.clone());
+        summary
     }
-    
-    pub fn get_files_blocking(&self) -> ImmutableAppFileList {
-        let file_list = self.file_list.blocking_read();
-        let file_tracker = self.file_tracker.blocking_read();
-        ImmutableAppFileList::new(file_list, file_tracker)
+
+    // Renamed files are moved back to their source so deletes aren't undone
+    // Files are moved to a temporary path first so that swapped or chained renames can be reverted
+    pub async fn undo_last_execution(&self) -> Option<ExecutionSummary> {
+        let _busy_lock = self.lock_busy().await;
+        let journal = match self.undo_journal.read().await.clone() {
+            Some(journal) => journal,
+            None => {
+                self.errors.write().await.push_warn("Undo", "There is no execution to undo");
+                return None;
+            },
+        };
+        self.is_file_list_outdated.store(true, Ordering::Relaxed);
+
+        let mut summary = ExecutionSummary::default();
+        let mut moved_renames = Vec::new();
+        for rename in journal.renames.iter() {
+            let temporary = format!("{}{}", rename.dest, TEMPORARY_RENAME_SUFFIX);
+            let operation = PlannedOperation::Rename { src: rename.dest.clone(), dest: temporary.clone() };
+            match self.execute_planned_operation(&operation).await {
+                Ok(_) => moved_renames.push((rename, temporary)),
+                Err(err) => {
+                    summary.failed += 1;
+                    let message = format!("IO error while undoing rename of '{}': {}", rename.dest, err);
+                    self.push_file_error(rename.dest.as_str(), err.kind(), message).await;
+                },
+            }
+        }
+
+        let folder_path = path::Path::new(self.folder_path.as_str());
+        let mut is_bookmarks_changed = false;
+        for (rename, temporary) in moved_renames {
+            let operation = PlannedOperation::Rename { src: temporary.clone(), dest: rename.src.clone() };
+            let res = async {
+                if let Some(parent) = folder_path.join(rename.src.as_str()).parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                self.execute_planned_operation(&operation).await
+            }.await;
+            match res {
+                Ok(_) => {
+                    summary.renamed += 1;
+                    is_bookmarks_changed |= self.bookmarks.write().await.rename_key(&rename.dest, &rename.src);
+                },
+                Err(err) => {
+                    summary.failed += 1;
+                    // The file is put back where the execution left it
+                    let restore = PlannedOperation::Rename { src: temporary, dest: rename.dest.clone() };
+                    let _ = self.execute_planned_operation(&restore).await;
+                    let message = format!("IO error while undoing rename of '{}': {}", rename.dest, err);
+                    self.push_file_error(rename.dest.as_str(), err.kind(), message).await;
+                },
+            }
+        }
+
+        if is_bookmarks_changed {
+            self.save_bookmarks_to_file().await;
+        }
+        self.remove_undo_journal_file().await;
+        *self.last_execution_summary.write().await = Some(summary.clone());
+        Some(summary)
     }
 
-    pub fn get_mut_files_blocking(&self) -> MutableAppFileList {
-        let file_list = self.file_list.blocking_read();
-        let file_tracker = self.file_tracker.blocking_read();
-        let change_queue = self.change_queue.blocking_write();
-        MutableAppFileList::new(file_list, file_tracker, change_queue)
+    pub async fn undo_last_execution_and_rescan(&self) -> Option<ExecutionSummary> {
+        let summary = self.undo_last_execution().await?;
+        self.update_file_intents().await;
+        Some(summary)
     }
-    
-    pub fn get_files_try_blocking(&self) -> Option<ImmutableAppFileList> {
-        let file_list = self.file_list.try_read().ok()?;
-        let file_tracker = self.file_tracker.try_read().ok()?;
-        Some(ImmutableAppFileList::new(file_list, file_tracker))
+
+    // A missing journal means there is nothing to undo
+    pub async fn load_undo_journal_from_file(&self) -> Option<()> {
+        let data = match tokio::fs::read_to_string(self.undo_journal_path.as_str()).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                let message = format!("IO error while reading undo journal: {}", err);
+                self.errors.write().await.push_error("Undo", message);
+                return None;
+            },
+        };
+        match serde_json::from_str(data.as_str()) {
+            Ok(journal) => {
+                *self.undo_journal.write().await = Some(journal);
+                Some(())
+            },
+            Err(err) => {
+                let message = format!("JSON decoding error reading undo journal: {}", err);
+                self.errors.write().await.push_error("Undo", message);
+                None
+            },
+        }
     }
 
-    pub fn get_mut_files_try_blocking(&self) -> Option<MutableAppFileList> {
-        let file_list = self.file_list.try_read().ok()?;
-        let file_tracker = self.file_tracker.try_read().ok()?;
-        let change_queue = self.change_queue.try_write().ok()?;
-        Some(MutableAppFileList::new(file_list, file_tracker, change_queue))
+    async fn save_undo_journal_to_file(&self, journal: UndoJournal) -> Option<()> {
+        // The journal is kept in memory even if it couldn't be saved so the execution can still be undone
+        let data = serde_json::to_string_pretty(&journal);
+        *self.undo_journal.write().await = Some(journal);
+        let data = match data {
+            Ok(data) => data,
+            Err(err) => {
+                let message = format!("JSON encoding error writing undo journal to file: {}", err);
+                self.errors.write().await.push_error("Undo", message);
+                return None;
+            },
+        };
+        if let Err(err) = atomic_write(self.undo_journal_path.as_str(), data).await {
+            let message = format!("IO error while writing undo journal to file: {}", err);
+            self.errors.write().await.push_error("Undo", message);
+            return None;
+        }
+        Some(())
     }
 
-    pub async fn flush_file_changes(&self) -> usize {
-        let file_list = self.file_list.write().await;
-        let file_tracker = self.file_tracker.write().await;
-        let change_queue = self.change_queue.write().await;
-        flush_file_changes_acquired(file_list, file_tracker, change_queue)
+    async fn remove_undo_journal_file(&self) {
+        *self.undo_journal.write().await = None;
+        match tokio::fs::remove_file(self.undo_journal_path.as_str()).await {
+            Ok(()) => {},
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {},
+            Err(err) => {
+                let message = format!("IO error while removing undo journal: {}", err);
+                self.errors.write().await.push_error("Undo", message);
+            },
+        }
     }
 
-    pub fn flush_file_changes_blocking(&self) -> usize {
-        let file_list = self.file_list.blocking_write();
-        let file_tracker = self.file_tracker.blocking_write();
-        let change_queue = self.change_queue.blocking_write();
-        flush_file_changes_acquired(file_list, file_tracker, change_queue)
+    async fn move_across_devices(&self, src: &path::Path, dest: &path::Path) -> Result<(), std::io::Error> {
+        let src_size = tokio::fs::metadata(src).await?.len();
+        self.execution_progress.send_modify(|progress| {
+            progress.total_bytes_to_copy += src_size;
+        });
+        copy_file_chunked(src, dest, src_size, &self.is_cancelled, |total_copied| {
+            self.execution_progress.send_modify(|progress| {
+                progress.total_bytes_copied += total_copied;
+            });
+        }).await?;
+
+        if self.filter_rules.preserve_timestamps {
+            if let Err(err) = copy_file_times(src, dest).await {
+                let message = format!("Failed to preserve timestamps of {}: {}", dest.to_string_lossy(), err);
+                self.errors.write().await.push_warn("Execute", message);
+            }
+        }
+        tokio::fs::remove_file(src).await
     }
-}
 
+    // Resolve all conflicting renames at once, returning the number of conflicts that were resolved
+    // If the destination is occupied by an existing file then that file is kept over any of the renames
+    pub async fn auto_resolve_conflicts(&self, strategy: ConflictResolution) -> usize {
+        let mut new_dests = Vec::<(usize, String)>::new();
+        let mut disabled_indices = Vec::<usize>::new();
+        let total_resolved = {
+            let files = self.get_files().await;
+            let file_tracker = files.get_file_tracker();
+            let conflicts = file_tracker.get_conflicts();
+
+            let mut used_dests = HashSet::<String>::new();
+            for conflict in conflicts.iter() {
+                let indices = &conflict.writer_indices;
+                let is_occupied = conflict.source_index
+                    .map(|index| !file_tracker.check_if_source_vacated(index))
+                    .unwrap_or(false);
+                let kept_index = match (is_occupied, strategy) {
+                    (true, _) | (_, ConflictResolution::DisableAll) => None,
+                    (false, ConflictResolution::SuffixCounter) => indices.first().copied(),
+                    (false, ConflictResolution::KeepLargest) => indices
+                        .iter()
+                        .copied()
+                        .max_by_key(|index| files.get(*index).map(|file| file.get_size()).unwrap_or(0)),
+                };
+
+                let mut counter = 0;
+                for index in indices.iter().copied() {
+                    if Some(index) == kept_index {
+                        continue;
+                    }
+                    if strategy != ConflictResolution::SuffixCounter {
+                        disabled_indices.push(index);
+                        continue;
+                    }
+                    // The counter is added to the file's own destination so it keeps its case
+                    let dest = match files.get(index) {
+                        Some(file) => file.get_dest().to_string(),
+                        None => continue,
+                    };
+                    // Skip over any counters that would cause another conflict
+                    let new_dest = loop {
+                        counter += 1;
+                        let new_dest = add_counter_suffix(dest.as_str(), counter);
+                        let key = file_tracker.get_path_key(new_dest.as_str());
+                        let is_used = used_dests.contains(&key) ||
+                            file_tracker.get_source_index(new_dest.as_str()).is_some() ||
+                            file_tracker.get_pending_writes().get(&key).map(|indices| !indices.is_empty()).unwrap_or(false);
+                        if !is_used {
+                            used_dests.insert(key);
+                            break new_dest;
+                        }
+                    };
+                    new_dests.push((index, new_dest));
+                }
+            }
+            conflicts.len()
+        };
+
+        {
+            let mut files = self.get_mut_files().await;
+            for (index, new_dest) in new_dests {
+                if let Some(mut file) = files.get(index) {
+                    file.set_dest(new_dest);
+                }
+            }
+            for index in disabled_indices {
+                if let Some(mut file) = files.get(index) {
+                    file.set_is_enabled(false);
+                }
+            }
+        }
+        self.flush_file_changes().await;
+        total_resolved
+    }
+
+    // All but the kept file of each group are marked to be deleted
+    // Only the group of the given episode is resolved if one is provided
+    pub async fn resolve_duplicates(&self, strategy: DuplicateResolution, key: Option<EpisodeKey>) -> usize {
+        let mut deleted_indices = Vec::<usize>::new();
+        {
+            let groups = self.duplicate_groups.read().await;
+            let files = self.get_files().await;
+            for group in groups.iter().filter(|group| key.map(|key| key == group.key).unwrap_or(true)) {
+                let kept_index = match strategy {
+                    DuplicateResolution::KeepLargest => group.indices
+                        .iter()
+                        .copied()
+                        .max_by_key(|index| files.get(*index).map(|file| file.get_size()).unwrap_or(0)),
+                    DuplicateResolution::KeepNewest => group.indices
+                        .iter()
+                        .copied()
+                        .max_by_key(|index| files.get(*index).and_then(|file| file.get_modified())),
+                };
+                deleted_indices.extend(group.indices.iter().copied().filter(|index| Some(*index) != kept_index));
+            }
+        }
+
+        let total_deleted = self.get_mut_files().await.set_action_for_indices(deleted_indices.as_slice(), Action::Delete);
+        self.flush_file_changes().await;
+        total_deleted
+    }
+
+    // Used when the episode parsed from the filename is wrong
+    // The destination is made the same way as a scan would for the given episode
+    pub async fn reassign_episode(&self, file_index: usize, key: EpisodeKey) -> Option<()> {
+        let src = self.get_files().await.get(file_index)?.get_src().to_string();
+        let intent = {
+            let cache_guard = self.cache.read().await;
+            let cache = match cache_guard.as_ref() {
+                Some(cache) => cache,
+                None => {
+                    let message = "Couldn't assign episode since cache is unloaded";
+                    self.errors.write().await.push_warn("Assign episode", message);
+                    return None;
+                },
+            };
+            let native_src = src.replace('/', std::path::MAIN_SEPARATOR_STR);
+            get_file_intent_for_episode(native_src.as_str(), Some(key), &self.filter_rules, cache)
+        };
+        if intent.action != Action::Rename && intent.action != Action::Complete {
+            let message = format!(
+                "Couldn't make a valid destination for '{}' as S{:02}E{:02}",
+                src, key.season, key.episode,
+            );
+            self.errors.write().await.push_warn("Assign episode", message);
+            return None;
+        }
+
+        {
+            let mut files = self.get_mut_files().await;
+            let mut file = files.get(file_index)?;
+            file.set_src_descriptor(Some(key));
+            // The action is changed first since changing to a rename can also set the destination
+            file.set_action(intent.action);
+            file.set_dest(intent.dest.replace(std::path::MAIN_SEPARATOR, "/"));
+            if intent.action == Action::Rename {
+                file.set_is_enabled(true);
+            }
+        }
+        self.flush_file_changes().await;
+        // Duplicates are grouped by the episode of each file
+        let file_list = self.file_list.read().await;
+        *self.duplicate_groups.write().await = find_duplicate_groups(file_list.as_slice());
+        Some(())
+    }
+
+    async fn push_file_error(&self, src: &str, kind: std::io::ErrorKind, message: String) {
+        let file_index = self.file_tracker.read().await.get_source_index(src).copied();
+        if let Some(index) = file_index {
+            if let Some(file) = self.file_list.write().await.get_mut(index) {
+                file.last_error = Some(message.clone());
+            }
+        }
+        self.file_errors.write().await.push(FileError {
+            file_index,
+            src: src.to_string(),
+            message,
+            kind,
+        });
+    }
+
+    pub fn dismiss_file_error_blocking(&self, index: usize) {
+        // NOTE: The file errors lock is released before the file list is locked
+        //       since update_file_intents(...) acquires them in the opposite order
+        let (file_index, last_error) = {
+            let mut file_errors = self.file_errors.blocking_write();
+            if index >= file_errors.len() {
+                return;
+            }
+            let error = file_errors.remove(index);
+            let file_index = match error.file_index {
+                Some(file_index) => file_index,
+                None => return,
+            };
+            // show the most recent error that is still remaining for the file
+            let last_error = file_errors
+                .iter()
+                .rev()
+                .find(|other| other.file_index == Some(file_index))
+                .map(|other| other.message.clone());
+            (file_index, last_error)
+        };
+        if let Some(file) = self.file_list.blocking_write().get_mut(file_index) {
+            file.last_error = last_error;
+        }
+    }
+
+    pub fn cancel_current_operation(&self) {
+        self.is_cancelled.store(true, Ordering::Relaxed);
+    }
+
+    async fn execute_planned_operation(&self, operation: &PlannedOperation) -> Result<Option<trash::Error>, std::io::Error> {
+        let folder_path = path::Path::new(self.folder_path.as_str());
+        match operation {
+            PlannedOperation::CreateDir { path } => {
+                tokio::fs::create_dir_all(folder_path.join(path)).await?;
+                Ok(None)
+            },
+            PlannedOperation::Rename { src, dest } => {
+                let dest_path = folder_path.join(dest);
+                // Renaming onto an existing file would silently replace it
+                // A case only rename refers to the same file on case-insensitive filesystems
+                let is_case_rename = get_path_key(src) == get_path_key(dest);
+                if !is_case_rename && tokio::fs::try_exists(&dest_path).await? {
+                    let message = format!("destination {} already exists", dest);
+                    return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, message));
+                }
+                let src_path = folder_path.join(src);
+                match self.file_operations.rename(src_path.as_path(), dest_path.as_path()).await {
+                    Ok(()) => {},
+                    // Renames only work within the same filesystem so we have to copy the file instead
+                    Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+                        self.move_across_devices(src_path.as_path(), dest_path.as_path()).await?;
+                    },
+                    Err(err) => return Err(describe_rename_error(err)),
+                };
+                Ok(None)
+            },
+            PlannedOperation::Delete { src } => {
+                delete_path(folder_path.join(src), self.filter_rules.use_trash).await
+            },
+            PlannedOperation::RemoveEmptyDir { path } => {
+                // A planned change could have failed and left files in the folder
+                let path = folder_path.join(path);
+                if !check_folder_empty(folder_path, path.as_path(), &self.filter_rules, |_| false) {
+                    return Err(std::io::Error::new(std::io::ErrorKind::DirectoryNotEmpty, "folder is no longer empty"));
+                }
+                delete_path(path, self.filter_rules.use_trash).await
+            },
+        }
+    }
+    
+    // getters
+    pub fn get_folder_path(&self) -> &str {
+        self.folder_path.as_str() 
+    }
+
+    pub fn set_change_sender(&mut self, sender: broadcast::Sender<ChangeEvent>) {
+        self.change_sender = Some(sender);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_file_operations(&mut self, file_operations: Box<dyn FileOperations>) {
+        self.file_operations = file_operations;
+    }
+
+    pub fn set_cache_registry(&mut self, registry: Arc<SeriesCacheRegistry>) {
+        self.cache_registry = Some(registry);
+    }
+
+    fn notify_change(&self, kind: ChangeKind) {
+        send_change(self.change_sender.as_ref(), ChangeEvent {
+            folder_path: Some(self.folder_path.clone()),
+            kind,
+        });
+    }
+
+    async fn lock_busy(&self) -> BusyLockGuard<'_> {
+        let event = ChangeEvent {
+            folder_path: Some(self.folder_path.clone()),
+            kind: ChangeKind::Busy,
+        };
+        BusyLockGuard::lock(&self.busy_lock, self.change_sender.as_ref(), event).await
+    }
+
+    pub fn get_mapped_series_id(&self) -> Option<u32> {
+        self.mapped_series_id
+    }
+
+    pub fn set_mapped_series_id(&mut self, series_id: Option<u32>) {
+        self.mapped_series_id = series_id;
+    }
+
+    pub fn get_plan_path(&self) -> &str {
+        self.plan_path.as_str()
+    }
+
+    pub fn get_folder_name(&self) -> &str {
+        self.folder_name.as_str() 
+    }
+
+    pub fn get_filter_rules(&self) -> &FilterRules {
+        self.filter_rules.as_ref()
+    }
+
+    pub fn get_file_tracker(&self) -> &RwLock<FileTracker> {
+        &self.file_tracker
+    }
+
+    pub fn get_busy_lock(&self) -> &Mutex<()> {
+        &self.busy_lock
+    }
+
+    pub fn get_errors(&self) -> &RwLock<ErrorLog> {
+        &self.errors
+    }
+
+    // Doesn't wait on the errors so it can be checked for every folder while rendering
+    // Errors that are being written to are only reported once the write finishes
+    pub fn has_errors(&self) -> bool {
+        self.errors.try_read().is_ok_and(|errors| !errors.is_empty())
+    }
+
+    pub async fn clear_errors(&self) {
+        self.errors.write().await.clear();
+    }
+
+    pub fn clear_errors_blocking(&self) {
+        self.errors.blocking_write().clear();
+    }
+
+    pub fn get_file_errors(&self) -> &RwLock<Vec<FileError>> {
+        &self.file_errors
+    }
+
+    pub fn get_execution_progress(&self) -> watch::Receiver<ExecutionProgress> {
+        self.execution_progress.subscribe()
+    }
+
+    pub fn get_last_execution_summary(&self) -> &RwLock<Option<ExecutionSummary>> {
+        &self.last_execution_summary
+    }
+
+    pub fn get_undo_journal(&self) -> &RwLock<Option<UndoJournal>> {
+        &self.undo_journal
+    }
+
+    pub fn get_last_execution_records(&self) -> &RwLock<Vec<ExecutionRecord>> {
+        &self.last_execution_records
+    }
+
+    pub fn get_duplicate_groups(&self) -> &RwLock<Vec<DuplicateGroup>> {
+        &self.duplicate_groups
+    }
+
+    pub fn get_selected_descriptor(&self) -> &RwLock<Option<EpisodeKey>> {
+        &self.selected_descriptor
+    }
+
+    pub fn get_cache(&self) -> &RwLock<Option<Arc<TvdbCache>>> {
+        &self.cache
+    }
+
+    pub fn get_bookmarks(&self) -> &RwLock<BookmarkTable> {
+        &self.bookmarks
+    }
+
+    pub fn get_ignored_files(&self) -> &RwLock<BTreeSet<String>> {
+        &self.ignored_files
+    }
+
+    pub async fn get_files(&self) -> ImmutableAppFileList<'_> {
+        let file_list = self.file_list.read().await;
+        let file_tracker = self.file_tracker.read().await;
+        ImmutableAppFileList::new(file_list, file_tracker)
+    }
+
+    pub async fn get_mut_files(&self) -> MutableAppFileList<'_> {
+        let file_list = self.file_list.read().await;
+        let file_tracker = self.file_tracker.read().await;
+        let change_queue = self.change_queue.write().await;
+        MutableAppFileList::new(file_list, file_tracker, change_queue)
+    }
+    
+    pub fn get_files_blocking(&self) -> ImmutableAppFileList<'_> {
+        let file_list = self.file_list.blocking_read();
+        let file_tracker = self.file_tracker.blocking_read();
+        ImmutableAppFileList::new(file_list, file_tracker)
+    }
+
+    pub fn get_mut_files_blocking(&self) -> MutableAppFileList<'_> {
+        let file_list = self.file_list.blocking_read();
+        let file_tracker = self.file_tracker.blocking_read();
+        let change_queue = self.change_queue.blocking_write();
+        MutableAppFileList::new(file_list, file_tracker, change_queue)
+    }
+    
+    pub fn get_files_try_blocking(&self) -> Option<ImmutableAppFileList<'_>> {
+        let file_list = self.file_list.try_read().ok()?;
+        let file_tracker = self.file_tracker.try_read().ok()?;
+        Some(ImmutableAppFileList::new(file_list, file_tracker))
+    }
+
+    pub fn get_mut_files_try_blocking(&self) -> Option<MutableAppFileList<'_>> {
+        let file_list = self.file_list.try_read().ok()?;
+        let file_tracker = self.file_tracker.try_read().ok()?;
+        let change_queue = self.change_queue.try_write().ok()?;
+        Some(MutableAppFileList::new(file_list, file_tracker, change_queue))
+    }
+
+    pub async fn flush_file_changes(&self) -> usize {
+        let mut file_list = self.file_list.write().await;
+        let mut file_tracker = self.file_tracker.write().await;
+        let mut change_queue = self.change_queue.write().await;
+        // Changes that don't affect conflicts still change the files
+        let is_changed = !change_queue.is_empty();
+        let total_changes = flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+        self.update_folder_status(&file_tracker);
+        if is_changed {
+            self.notify_change(ChangeKind::Files);
+        }
+        total_changes
+    }
+
+    pub fn flush_file_changes_blocking(&self) -> usize {
+        let mut file_list = self.file_list.blocking_write();
+        let mut file_tracker = self.file_tracker.blocking_write();
+        let mut change_queue = self.change_queue.blocking_write();
+        // Changes that don't affect conflicts still change the files
+        let is_changed = !change_queue.is_empty();
+        let total_changes = flush_file_changes_acquired(&mut file_list, &mut file_tracker, &mut change_queue);
+        self.update_folder_status(&file_tracker);
+        if is_changed {
+            self.notify_change(ChangeKind::Files);
+        }
+        total_changes
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::test_fixtures::{assert_tracker_matches_files, create_files, new_cache, new_dvd_order_cache, new_episode, new_filter_rules, new_mock_provider, new_series, write_cache_file};
+    use crate::error_log::{AppError, Severity};
+    use std::collections::BTreeMap;
+    use crate::app_file::{FileSortMode, ImmutableAppFile};
+
+    fn new_file(src: &str, action: Action, dest: &str, size: u64) -> AppFile {
+        AppFile::new(src.to_string(), None, action, dest.to_string(), None, size)
+    }
+
+    // Folder with the given files as if they were scanned, with every rename enabled
+    async fn new_folder_with_files(files: Vec<AppFile>, file_tracker: FileTracker) -> AppFolder {
+        let folder = AppFolder::new("", "Show", Arc::new(new_filter_rules(json!({}))));
+        {
+            let mut file_list = folder.file_list.write().await;
+            let mut tracker = folder.file_tracker.write().await;
+            *file_list = files;
+            *tracker = file_tracker;
+            for (index, file) in file_list.iter_mut().enumerate() {
+                file.update_dest_error(tracker.get_max_dest_length());
+                tracker.insert_existing_source(file.src.as_str(), index);
+                tracker.insert_file_action(file, index);
+            }
+        }
+        folder.get_mut_files().await.set_enabled_where(|file| file.get_action() == Action::Rename, true);
+        folder.flush_file_changes().await;
+        folder
+    }
+
+    async fn get_file_states(folder: &AppFolder) -> Vec<(String, bool)> {
+        folder.get_files().await
+            .to_iter()
+            .map(|file| (file.get_dest().to_string(), file.get_is_enabled()))
+            .collect()
+    }
+
+    // Three renames to the same destination where only the case differs
+    async fn new_three_way_conflict() -> AppFolder {
+        let files = vec![
+            new_file("a.mkv", Action::Rename, "Season 01/Show-S01E01.mkv", 1),
+            new_file("b.mkv", Action::Rename, "season 01/show-s01e01.mkv", 3),
+            new_file("c.mkv", Action::Rename, "SEASON 01/SHOW-S01E01.mkv", 2),
+        ];
+        let folder = new_folder_with_files(files, FileTracker::with_case_folding(true)).await;
+        assert_eq!(folder.get_file_tracker().read().await.get_conflict_count(), 1);
+        folder
+    }
+
+    #[tokio::test]
+    async fn auto_resolve_suffix_counter_keeps_case_of_each_file() {
+        let folder = new_three_way_conflict().await;
+        assert_eq!(folder.auto_resolve_conflicts(ConflictResolution::SuffixCounter).await, 1);
+        assert_eq!(get_file_states(&folder).await, vec![
+            ("Season 01/Show-S01E01.mkv".to_string(), true),
+            ("season 01/show-s01e01 (1).mkv".to_string(), true),
+            ("SEASON 01/SHOW-S01E01 (2).mkv".to_string(), true),
+        ]);
+        assert_eq!(folder.get_file_tracker().read().await.get_conflict_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn auto_resolve_keep_largest() {
+        let folder = new_three_way_conflict().await;
+        assert_eq!(folder.auto_resolve_conflicts(ConflictResolution::KeepLargest).await, 1);
+        let is_enabled: Vec<bool> = get_file_states(&folder).await.into_iter().map(|(_, is_enabled)| is_enabled).collect();
+        assert_eq!(is_enabled, vec![false, true, false]);
+        assert_eq!(folder.get_file_tracker().read().await.get_conflict_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn auto_resolve_disable_all() {
+        let folder = new_three_way_conflict().await;
+        assert_eq!(folder.auto_resolve_conflicts(ConflictResolution::DisableAll).await, 1);
+        let is_enabled: Vec<bool> = get_file_states(&folder).await.into_iter().map(|(_, is_enabled)| is_enabled).collect();
+        assert_eq!(is_enabled, vec![false, false, false]);
+        assert_eq!(folder.get_file_tracker().read().await.get_conflict_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn auto_resolve_suffix_counter_keeps_existing_file_and_skips_used_counters() {
+        let files = vec![
+            new_file("Show-S01E01 (1).mkv", Action::Ignore, "", 1),
+            new_file("Show-S01E01.mkv", Action::Complete, "", 1),
+            new_file("a.mkv", Action::Rename, "Show-S01E01.mkv", 1),
+            new_file("b.mkv", Action::Rename, "Show-S01E01.mkv", 1),
+        ];
+        let folder = new_folder_with_files(files, FileTracker::with_case_folding(false)).await;
+        assert_eq!(folder.auto_resolve_conflicts(ConflictResolution::SuffixCounter).await, 1);
+        let states = get_file_states(&folder).await;
+        assert_eq!(states[2], ("Show-S01E01 (2).mkv".to_string(), true));
+        assert_eq!(states[3], ("Show-S01E01 (3).mkv".to_string(), true));
+        assert_eq!(folder.get_file_tracker().read().await.get_conflict_count(), 0);
+    }
+
+    // A folder named "Show" in the root which is scanned with a cache of two episodes
+    async fn new_scanned_folder(root: &path::Path, files: &[(&str, &str)], rules: serde_json::Value) -> AppFolder {
+        let folder_path = root.join("Show");
+        for (file, data) in files {
+            let path = folder_path.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, data).unwrap();
+        }
+        let folder = AppFolder::new(
+            root.to_string_lossy().as_ref(),
+            folder_path.to_string_lossy().as_ref(),
+            Arc::new(new_filter_rules(rules)),
+        );
+        *folder.cache.write().await = Some(Arc::new(new_cache("Show", &[(1, 1, Some("Pilot")), (1, 2, Some("Return"))])));
+        folder.update_file_intents().await.unwrap();
+        folder
+    }
+
+    fn read_folder_file(folder: &AppFolder, file: &str) -> Option<String> {
+        std::fs::read_to_string(path::Path::new(folder.get_folder_path()).join(file)).ok()
+    }
+
+    // Files without a descriptor are renamed to the given destinations
+    async fn set_renames(folder: &AppFolder, renames: &[(&str, &str)]) {
+        {
+            let mut files = folder.get_mut_files().await;
+            for (src, dest) in renames {
+                let index = files.get_indices_where(|file| file.get_src() == *src)[0];
+                let mut file = files.get(index).unwrap();
+                file.set_action(Action::Rename);
+                file.set_dest(dest.to_string());
+                file.set_is_enabled(true);
+            }
+        }
+        folder.flush_file_changes().await;
+    }
+
+    #[tokio::test]
+    async fn undo_reverts_renames_but_not_deletes() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("show.s01e02.mkv", "2"), ("release.nfo", "")];
+        let folder = new_scanned_folder(root.path(), &files, json!({ "blacklist_extensions": ["nfo"] })).await;
+        folder.get_mut_files().await.set_enabled_where(|file| file.get_action() == Action::Delete, true);
+        folder.flush_file_changes().await;
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!((summary.renamed, summary.deleted, summary.failed), (2, 1, 0));
+        assert_eq!(read_folder_file(&folder, "Season 01/Show-S01E01-Pilot.mkv").as_deref(), Some("1"));
+        assert!(read_folder_file(&folder, PATH_STR_UNDO_JOURNAL).is_some());
+        let journal = folder.get_undo_journal().read().await.clone().unwrap();
+        assert_eq!(journal.renames.len(), 2);
+        assert_eq!(journal.deletes, vec!["release.nfo".to_string()]);
+        assert_eq!(journal.summary, summary);
+
+        let summary = folder.undo_last_execution_and_rescan().await.unwrap();
+        assert_eq!((summary.renamed, summary.failed), (2, 0));
+        assert_eq!(read_folder_file(&folder, "show.s01e01.mkv").as_deref(), Some("1"));
+        assert_eq!(read_folder_file(&folder, "show.s01e02.mkv").as_deref(), Some("2"));
+        assert_eq!(read_folder_file(&folder, "release.nfo"), None);
+        assert_eq!(read_folder_file(&folder, "Season 01/Show-S01E01-Pilot.mkv"), None);
+        // The journal is used up so the same execution can't be undone twice
+        assert_eq!(read_folder_file(&folder, PATH_STR_UNDO_JOURNAL), None);
+        assert!(folder.get_undo_journal().read().await.is_none());
+        assert!(folder.undo_last_execution().await.is_none());
+        assert_eq!(folder.get_files().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn undo_reverts_swapped_renames() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("a.mkv", "a"), ("b.mkv", "b")], json!({})).await;
+        set_renames(&folder, &[("a.mkv", "b.mkv"), ("b.mkv", "a.mkv")]).await;
+
+        let summary = folder.execute_file_changes().await;
+        assert_eq!((summary.renamed, summary.failed), (2, 0));
+        assert_eq!(read_folder_file(&folder, "a.mkv").as_deref(), Some("b"));
+        assert_eq!(read_folder_file(&folder, "b.mkv").as_deref(), Some("a"));
+
+        let summary = folder.undo_last_execution().await.unwrap();
+        assert_eq!((summary.renamed, summary.failed), (2, 0));
+        assert_eq!(read_folder_file(&folder, "a.mkv").as_deref(), Some("a"));
+        assert_eq!(read_folder_file(&folder, "b.mkv").as_deref(), Some("b"));
+    }
+
+    #[tokio::test]
+    async fn undo_leaves_files_that_were_changed_since() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("show.s01e02.mkv", "2")];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        folder.execute_file_changes().await;
+
+        // One renamed file is gone and the source of the other is taken by a new file
+        let folder_path = path::Path::new(folder.get_folder_path());
+        std::fs::remove_file(folder_path.join("Season 01/Show-S01E01-Pilot.mkv")).unwrap();
+        std::fs::write(folder_path.join("show.s01e02.mkv"), "new").unwrap();
+
+        let summary = folder.undo_last_execution().await.unwrap();
+        assert_eq!((summary.renamed, summary.failed), (0, 2));
+        assert_eq!(read_folder_file(&folder, "show.s01e02.mkv").as_deref(), Some("new"));
+        assert_eq!(read_folder_file(&folder, "Season 01/Show-S01E02-Return.mkv").as_deref(), Some("2"));
+        assert_eq!(folder.get_file_errors().read().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn undo_journal_is_loaded_from_file() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1")], json!({})).await;
+        folder.execute_file_changes().await;
+        let journal = folder.get_undo_journal().read().await.clone();
+        assert!(journal.is_some());
+
+        let other = AppFolder::new("", folder.get_folder_path(), folder.filter_rules.clone());
+        assert!(other.load_undo_journal_from_file().await.is_some());
+        assert_eq!(*other.get_undo_journal().read().await, journal);
+
+        // Executing nothing keeps the journal so the earlier execution can still be undone
+        folder.update_file_intents().await;
+        folder.execute_file_changes().await;
+        assert_eq!(*folder.get_undo_journal().read().await, journal);
+        assert!(folder.undo_last_execution().await.is_some());
+        assert_eq!(read_folder_file(&folder, "show.s01e01.mkv").as_deref(), Some("1"));
+    }
+
+    fn to_renames(renames: &[(&str, &str)]) -> Vec<(String, String)> {
+        renames.iter().map(|(src, dest)| (src.to_string(), dest.to_string())).collect()
+    }
+
+    fn get_test_temporary_path(src: &str) -> String {
+        format!("{}{}", src, TEMPORARY_RENAME_SUFFIX)
+    }
+
+    // Performs the stages on a map of paths to their original name
+    // Each stage is run forwards and backwards to check its operations don't depend on each other
+    fn simulate_stages(files: &[&str], stages: &[Vec<PlannedOperation>]) -> BTreeMap<String, String> {
+        let apply = |state: &mut BTreeMap<String, String>, operation: &PlannedOperation| match operation {
+            PlannedOperation::Rename { src, dest } => {
+                assert!(!state.contains_key(dest), "{} overwrites an existing file", operation);
+                let data = state.remove(src).unwrap_or_else(|| panic!("{} has no source", operation));
+                state.insert(dest.clone(), data);
+            },
+            PlannedOperation::Delete { src } => {
+                assert!(state.remove(src).is_some(), "{} has no source", operation);
+            },
+            _ => {},
+        };
+        let mut state: BTreeMap<String, String> = files.iter().map(|file| (file.to_string(), file.to_string())).collect();
+        for stage in stages.iter() {
+            let mut reversed = state.clone();
+            for operation in stage.iter() {
+                apply(&mut state, operation);
+            }
+            for operation in stage.iter().rev() {
+                apply(&mut reversed, operation);
+            }
+            assert_eq!(state, reversed);
+        }
+        state
+    }
+
+    fn to_state(files: &[(&str, &str)]) -> BTreeMap<String, String> {
+        files.iter().map(|(path, data)| (path.to_string(), data.to_string())).collect()
+    }
+
+    #[test]
+    fn swap_is_broken_with_temporary_path() {
+        let stages = order_file_changes(to_renames(&[("a", "b"), ("b", "a")]), vec![], get_test_temporary_path);
+        assert_eq!(stages.len(), 3);
+        assert_eq!(stages.concat().len(), 3);
+        let state = simulate_stages(&["a", "b"], stages.as_slice());
+        assert_eq!(state, to_state(&[("a", "b"), ("b", "a")]));
+    }
+
+    #[test]
+    fn chain_is_performed_from_the_end() {
+        let renames = to_renames(&[("a", "b"), ("b", "c"), ("c", "d")]);
+        let stages = order_file_changes(renames, vec![], get_test_temporary_path);
+        assert_eq!(stages, vec![
+            vec![PlannedOperation::Rename { src: "c".to_string(), dest: "d".to_string() }],
+            vec![PlannedOperation::Rename { src: "b".to_string(), dest: "c".to_string() }],
+            vec![PlannedOperation::Rename { src: "a".to_string(), dest: "b".to_string() }],
+        ]);
+        let state = simulate_stages(&["a", "b", "c"], stages.as_slice());
+        assert_eq!(state, to_state(&[("b", "a"), ("c", "b"), ("d", "c")]));
+    }
+
+    #[test]
+    fn three_file_cycle() {
+        let renames = to_renames(&[("a", "b"), ("b", "c"), ("c", "a")]);
+        let stages = order_file_changes(renames, vec![], get_test_temporary_path);
+        assert_eq!(stages.concat().len(), 4);
+        let state = simulate_stages(&["a", "b", "c"], stages.as_slice());
+        assert_eq!(state, to_state(&[("a", "c"), ("b", "a"), ("c", "b")]));
+    }
+
+    #[test]
+    fn delete_of_rename_destination_is_performed_first() {
+        let renames = to_renames(&[("a", "b")]);
+        let deletes = vec!["b".to_string(), "c".to_string()];
+        let stages = order_file_changes(renames, deletes, get_test_temporary_path);
+        assert_eq!(stages, vec![
+            vec![PlannedOperation::Delete { src: "b".to_string() }],
+            vec![PlannedOperation::Rename { src: "a".to_string(), dest: "b".to_string() }],
+            vec![PlannedOperation::Delete { src: "c".to_string() }],
+        ]);
+        let state = simulate_stages(&["a", "b", "c"], stages.as_slice());
+        assert_eq!(state, to_state(&[("b", "a")]));
+    }
+
+    #[tokio::test]
+    async fn temporary_path_skips_existing_files() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("a.mkv", "a"), ("b.mkv", "b"), ("a.mkv.renaming", "left over"), ("b.mkv.renaming", "left over")];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        set_renames(&folder, &[("a.mkv", "b.mkv"), ("b.mkv", "a.mkv")]).await;
+
+        let stages = folder.plan_file_change_stages().await;
+        let temporary_paths: Vec<&str> = stages
+            .iter()
+            .flatten()
+            .filter(|operation| is_temporary_rename(operation))
+            .map(|operation| operation.get_path())
+            .collect();
+        assert_eq!(temporary_paths.len(), 1);
+        let temporary_path = match &stages[0][0] {
+            PlannedOperation::Rename { dest, .. } => dest.as_str(),
+            operation => panic!("{} isn't a rename", operation),
+        };
+        assert!(temporary_path.ends_with(".1.renaming"), "{}", temporary_path);
+
+        let summary = folder.execute_file_changes().await;
+        assert_eq!((summary.renamed, summary.failed), (2, 0));
+        assert_eq!(read_folder_file(&folder, "a.mkv").as_deref(), Some("b"));
+        assert_eq!(read_folder_file(&folder, "b.mkv").as_deref(), Some("a"));
+        assert_eq!(read_folder_file(&folder, "a.mkv.renaming").as_deref(), Some("left over"));
+    }
+
+    #[tokio::test]
+    async fn chunked_copy_reports_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dest) = (dir.path().join("src.mkv"), dir.path().join("dest.mkv"));
+        let data: Vec<u8> = (0..COPY_CHUNK_SIZE * 5 / 2).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&src, &data).unwrap();
+
+        let mut copied = Vec::new();
+        let is_cancelled = AtomicBool::new(false);
+        copy_file_chunked(&src, &dest, data.len() as u64, &is_cancelled, |total| copied.push(total)).await.unwrap();
+        assert_eq!(copied.iter().sum::<u64>(), data.len() as u64);
+        assert!(copied.len() >= 3);
+        assert_eq!(std::fs::read(&dest).unwrap(), data);
+        // Only the move removes the source once the copy is done
+        assert!(src.exists());
+    }
+
+    #[tokio::test]
+    async fn cancelled_copy_removes_partial_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dest) = (dir.path().join("src.mkv"), dir.path().join("dest.mkv"));
+        std::fs::write(&src, vec![1u8; COPY_CHUNK_SIZE * 3]).unwrap();
+
+        let is_cancelled = AtomicBool::new(false);
+        let res = copy_file_chunked(&src, &dest, (COPY_CHUNK_SIZE * 3) as u64, &is_cancelled, |_| {
+            // The first chunk is written before the copy is cancelled
+            is_cancelled.store(true, Ordering::Relaxed);
+        }).await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::Interrupted);
+        assert!(!dest.exists());
+        assert!(src.exists());
+    }
+
+    #[tokio::test]
+    async fn incomplete_copy_is_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dest) = (dir.path().join("src.mkv"), dir.path().join("dest.mkv"));
+        std::fs::write(&src, "data").unwrap();
+
+        let is_cancelled = AtomicBool::new(false);
+        let err = copy_file_chunked(&src, &dest, 5, &is_cancelled, |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("copied 4 bytes but source has 5 bytes"), "{}", err);
+        assert!(!dest.exists());
+        assert!(src.exists());
+    }
+
+    #[tokio::test]
+    async fn copy_never_replaces_existing_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let (src, dest) = (dir.path().join("src.mkv"), dir.path().join("dest.mkv"));
+        std::fs::write(&src, "new").unwrap();
+        std::fs::write(&dest, "existing").unwrap();
+
+        let is_cancelled = AtomicBool::new(false);
+        let err = copy_file_chunked(&src, &dest, 3, &is_cancelled, |_| {}).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&dest).unwrap(), "existing");
+
+        // A missing source doesn't create the destination
+        let missing = dir.path().join("missing.mkv");
+        let other = dir.path().join("other.mkv");
+        let err = copy_file_chunked(&missing, &other, 0, &is_cancelled, |_| {}).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(!other.exists());
+    }
+
+    #[tokio::test]
+    async fn plan_matches_execution() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [
+            ("show.s01e01.mkv", "1"),
+            ("Old/show.s01e02.mkv", "2"),
+            ("Old/release.nfo", ""),
+            ("Season 01/Show-S01E03.mkv", "3"),
+        ];
+        let folder = new_scanned_folder(root.path(), &files, json!({ "blacklist_extensions": ["nfo"] })).await;
+        folder.get_mut_files().await.set_enabled_where(|file| file.get_action() == Action::Delete, true);
+        folder.flush_file_changes().await;
+
+        let plan = folder.plan_file_changes().await;
+        assert_eq!(plan, vec![
+            PlannedOperation::Rename {
+                src: "Old/show.s01e02.mkv".to_string(),
+                dest: "Season 01/Show-S01E02-Return.mkv".to_string(),
+            },
+            PlannedOperation::Rename {
+                src: "show.s01e01.mkv".to_string(),
+                dest: "Season 01/Show-S01E01-Pilot.mkv".to_string(),
+            },
+            PlannedOperation::Delete { src: "Old/release.nfo".to_string() },
+            PlannedOperation::RemoveEmptyDir { path: "Old".to_string() },
+        ]);
+        // Planning doesn't touch the disk
+        assert!(read_folder_file(&folder, "Old/release.nfo").is_some());
+
+        let summary = folder.execute_file_changes().await;
+        assert_eq!((summary.renamed, summary.deleted, summary.removed_dirs, summary.failed), (2, 1, 1, 0));
+        let folder_path = path::Path::new(folder.get_folder_path());
+        for operation in plan.iter() {
+            match operation {
+                PlannedOperation::Rename { src, dest } => {
+                    assert!(!folder_path.join(src).exists());
+                    assert!(folder_path.join(dest).exists());
+                },
+                PlannedOperation::Delete { src } => assert!(!folder_path.join(src).exists()),
+                PlannedOperation::RemoveEmptyDir { path } => assert!(!folder_path.join(path).exists()),
+                PlannedOperation::CreateDir { path } => assert!(folder_path.join(path).is_dir()),
+            }
+        }
+        let records = folder.get_last_execution_records().read().await.clone();
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|record| record.is_ok()));
+    }
+
+    async fn disable_all_files(folder: &AppFolder) {
+        folder.get_mut_files().await.set_enabled_where(|_| true, false);
+        folder.flush_file_changes().await;
+    }
+
+    #[tokio::test]
+    async fn organize_enables_every_valid_rename() {
+        let files = vec![
+            new_file("a.mkv", Action::Rename, "Season 01/Show-S01E01.mkv", 0),
+            new_file("b.mkv", Action::Rename, "Season 01/Show-S01E02.mkv", 0),
+            new_file("c.nfo", Action::Delete, "", 0),
+            new_file("d.nfo", Action::Delete, "", 0),
+            new_file("e.mkv", Action::Rename, "Season 01/CON.mkv", 0),
+            new_file("Season 01/Show-S01E03.mkv", Action::Complete, "", 0),
+        ];
+        let folder = new_folder_with_files(files, FileTracker::with_case_folding(false)).await;
+        disable_all_files(&folder).await;
+        folder.get_mut_files().await.set_enabled_where(|file| file.get_src() == "d.nfo", true);
+        folder.flush_file_changes().await;
+
+        assert_eq!(folder.enable_organize_renames(false).await, Some(vec!["a.mkv".to_string(), "b.mkv".to_string()]));
+        let is_enabled: Vec<bool> = folder.get_files().await.to_iter().map(|file| file.get_is_enabled()).collect();
+        // Deletes keep whether they were enabled and invalid destinations are left disabled
+        assert_eq!(is_enabled, vec![true, true, false, true, false, false]);
+    }
+
+    #[tokio::test]
+    async fn organize_stops_on_conflicts_unless_skipped() {
+        let files = vec![
+            new_file("a.mkv", Action::Rename, "Season 01/Show-S01E01.mkv", 0),
+            new_file("b.mkv", Action::Rename, "Season 01/Show-S01E01.mkv", 0),
+            new_file("c.mkv", Action::Rename, "Season 01/Show-S01E02.mkv", 0),
+            new_file("d.nfo", Action::Delete, "", 0),
+        ];
+        let folder = new_folder_with_files(files, FileTracker::with_case_folding(false)).await;
+        disable_all_files(&folder).await;
+
+        assert_eq!(folder.enable_organize_renames(false).await, None);
+        assert!(get_file_states(&folder).await.iter().all(|(_, is_enabled)| !is_enabled));
+        assert!(folder.get_errors().read().await.iter().any(|error| error.to_string().contains("2 renames conflict")));
+
+        assert_eq!(folder.enable_organize_renames(true).await, Some(vec!["c.mkv".to_string()]));
+        let is_enabled: Vec<bool> = get_file_states(&folder).await.into_iter().map(|(_, is_enabled)| is_enabled).collect();
+        assert_eq!(is_enabled, vec![false, false, true, false]);
+        assert_eq!(folder.get_file_tracker().read().await.get_conflict_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn organize_notifies_busy_changes() {
+        let files = vec![new_file("a.mkv", Action::Rename, "Season 01/Show-S01E01.mkv", 0)];
+        let mut folder = new_folder_with_files(files, FileTracker::with_case_folding(false)).await;
+        let (sender, mut receiver) = broadcast::channel(16);
+        folder.set_change_sender(sender);
+
+        folder.enable_organize_renames(false).await;
+        let mut total_busy_events = 0;
+        while let Ok(event) = receiver.try_recv() {
+            if event.kind == ChangeKind::Busy {
+                total_busy_events += 1;
+            }
+        }
+        // Sent once when the lock is taken and again when it is released
+        assert_eq!(total_busy_events, 2);
+    }
+
+    fn get_change_events(receiver: &mut broadcast::Receiver<ChangeEvent>) -> Vec<ChangeEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn destination_flush_notifies_change() {
+        let files = vec![new_file("a.mkv", Action::Rename, "Season 01/Show-S01E01.mkv", 0)];
+        let mut folder = new_folder_with_files(files, FileTracker::new()).await;
+        let (sender, mut receiver) = broadcast::channel(16);
+        folder.set_change_sender(sender);
+
+        folder.get_mut_files().await.get(0).unwrap().set_dest("Season 01/Edited.mkv".to_string());
+        assert!(get_change_events(&mut receiver).is_empty());
+        assert_eq!(folder.flush_file_changes().await, 1);
+        let events = get_change_events(&mut receiver);
+        assert_eq!(events, vec![ChangeEvent {
+            folder_path: Some(folder.get_folder_path().to_string()),
+            kind: ChangeKind::Files,
+        }]);
+    }
+
+    #[tokio::test]
+    async fn flush_without_changes_is_silent() {
+        let files = vec![new_file("a.mkv", Action::Rename, "Season 01/Show-S01E01.mkv", 0)];
+        let mut folder = new_folder_with_files(files, FileTracker::new()).await;
+        let (sender, mut receiver) = broadcast::channel(16);
+        folder.set_change_sender(sender);
+
+        assert_eq!(folder.flush_file_changes().await, 0);
+        // Acquiring the files without editing them doesn't queue anything
+        drop(folder.get_mut_files().await);
+        assert_eq!(folder.flush_file_changes().await, 0);
+        assert!(get_change_events(&mut receiver).is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_without_trash_removes_permanently() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("release.nfo");
+        let folder = dir.path().join("Extras");
+        std::fs::write(&file, "").unwrap();
+        std::fs::create_dir_all(folder.join("Deleted")).unwrap();
+        std::fs::write(folder.join("Deleted/scene.mkv"), "").unwrap();
+
+        assert!(delete_path(file.clone(), false).await.unwrap().is_none());
+        assert!(!file.exists());
+        assert!(delete_path(folder.clone(), false).await.unwrap().is_none());
+        assert!(!folder.exists());
+
+        let err = delete_path(dir.path().join("missing.nfo"), false).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn execute_deletes_without_trash() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("release.nfo", "")];
+        let rules = json!({ "blacklist_extensions": ["nfo"], "use_trash": false });
+        let folder = new_scanned_folder(root.path(), &files, rules).await;
+        folder.get_mut_files().await.set_enabled_where(|file| file.get_action() == Action::Delete, true);
+        folder.flush_file_changes().await;
+
+        let summary = folder.execute_file_changes().await;
+        assert_eq!((summary.deleted, summary.failed), (1, 0));
+        assert_eq!(read_folder_file(&folder, "release.nfo"), None);
+        // The trash fallback warning is only given when the trash was used
+        assert!(folder.get_errors().read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn execution_progress_increases_monotonically() {
+        let root = tempfile::tempdir().unwrap();
+        let names: Vec<String> = (1..=40).map(|index| format!("file{:02}.mkv", index)).collect();
+        let files: Vec<(&str, &str)> = names.iter().map(|name| (name.as_str(), "")).collect();
+        let folder = new_scanned_folder(root.path(), files.as_slice(), json!({})).await;
+        let renames: Vec<(String, String)> = names.iter().map(|name| (name.clone(), format!("Renamed/{}", name))).collect();
+        let renames: Vec<(&str, &str)> = renames.iter().map(|(src, dest)| (src.as_str(), dest.as_str())).collect();
+        set_renames(&folder, renames.as_slice()).await;
+
+        let folder = Arc::new(folder);
+        let mut receiver = folder.get_execution_progress();
+        let mut task = tokio::spawn({
+            let folder = folder.clone();
+            async move { folder.execute_file_changes().await }
+        });
+
+        // Updates can be coalesced so only the order of the values we see is checked
+        let mut observed = Vec::new();
+        let observe = async {
+            loop {
+                tokio::select! {
+                    biased;
+                    res = receiver.changed() => {
+                        let progress = receiver.borrow_and_update().clone();
+                        if res.is_ok() && !progress.is_idle() {
+                            observed.push((progress.total_completed, progress.total_operations));
+                        }
+                    },
+                    res = &mut task => break res.unwrap(),
+                }
+            }
+        };
+        let summary = tokio::time::timeout(std::time::Duration::from_secs(10), observe).await.unwrap();
+        assert_eq!(summary.renamed, 40);
+
+        // The folder for the renames is created first
+        assert!(observed.iter().all(|(_, total_operations)| *total_operations == 41));
+        assert!(observed.windows(2).all(|pair| pair[0].0 <= pair[1].0), "{:?}", observed);
+        assert!(observed.iter().all(|(total_completed, total_operations)| total_completed <= total_operations));
+        assert!(folder.get_execution_progress().borrow().is_idle());
+    }
+
+    #[tokio::test]
+    async fn cancelled_scan_returns_early_and_keeps_file_list() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "")], json!({})).await;
+        // A deep tree where every level has to be read one after the other
+        let mut deep_folder = path::PathBuf::from(folder.get_folder_path());
+        for depth in 0..64 {
+            deep_folder.push(format!("level{}", depth));
+            std::fs::create_dir_all(&deep_folder).unwrap();
+            for index in 0..4 {
+                std::fs::write(deep_folder.join(format!("file{}.mkv", index)), "").unwrap();
+            }
+        }
+
+        let folder = Arc::new(folder);
+        let task = tokio::spawn({
+            let folder = folder.clone();
+            async move { folder.update_file_intents().await }
+        });
+        // The scan clears the cancel flag when it starts so we wait until it holds the busy lock
+        let start = std::time::Instant::now();
+        while folder.get_busy_lock().try_lock().is_ok() {
+            tokio::task::yield_now().await;
+        }
+        folder.cancel_current_operation();
+        let res = tokio::time::timeout(std::time::Duration::from_secs(5), task).await.unwrap().unwrap();
+        assert!(res.is_none());
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        // The list from the previous scan is kept
+        let files = folder.get_files().await;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files.get(0).unwrap().get_src(), "show.s01e01.mkv");
+        drop(files);
+        assert!(folder.get_errors().read().await.iter().any(|error| error.to_string().contains("Cancelled")));
+    }
+
+    #[tokio::test]
+    async fn existing_destination_survives_execution() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "new")], json!({})).await;
+        // Another batch created the destination after the scan
+        let dest = "Season 01/Show-S01E01-Pilot.mkv";
+        let dest_path = path::Path::new(folder.get_folder_path()).join(dest);
+        std::fs::create_dir_all(dest_path.parent().unwrap()).unwrap();
+        std::fs::write(dest_path, "old").unwrap();
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!((summary.renamed, summary.failed), (0, 1));
+        assert_eq!(read_folder_file(&folder, dest).as_deref(), Some("old"));
+        assert_eq!(read_folder_file(&folder, "show.s01e01.mkv").as_deref(), Some("new"));
+
+        let records = folder.get_last_execution_records().read().await.clone();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].src, "show.s01e01.mkv");
+        let error = records[0].result.as_ref().unwrap_err();
+        assert!(error.contains(dest), "{}", error);
+
+        // The skipped file can still be resolved from the rename list
+        let files = folder.get_files().await;
+        let file = files.to_iter().find(|file| file.get_src() == "show.s01e01.mkv").unwrap();
+        assert_eq!(file.get_action(), Action::Rename);
+        assert_eq!(file.get_dest(), dest);
+    }
+
+    // Renames fail with the given error as if the destination was on another filesystem
+    struct FailingRename(std::io::ErrorKind);
+
+    #[async_trait::async_trait]
+    impl FileOperations for FailingRename {
+        async fn rename(&self, _src: &path::Path, _dest: &path::Path) -> Result<(), std::io::Error> {
+            Err(std::io::Error::new(self.0, "injected rename error"))
+        }
+    }
+
+    #[tokio::test]
+    async fn cross_device_rename_falls_back_to_copy() {
+        let root = tempfile::tempdir().unwrap();
+        let mut folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "episode data")], json!({})).await;
+        folder.set_file_operations(Box::new(FailingRename(std::io::ErrorKind::CrossesDevices)));
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!((summary.renamed, summary.failed), (1, 0));
+        assert_eq!(read_folder_file(&folder, "Season 01/Show-S01E01-Pilot.mkv").as_deref(), Some("episode data"));
+        assert_eq!(read_folder_file(&folder, "show.s01e01.mkv"), None);
+    }
+
+    #[tokio::test]
+    async fn cross_device_move_reports_copied_bytes() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "episode data")], json!({})).await;
+        let folder_path = path::Path::new(folder.get_folder_path());
+        folder.move_across_devices(&folder_path.join("show.s01e01.mkv"), &folder_path.join("moved.mkv")).await.unwrap();
+        assert_eq!(read_folder_file(&folder, "moved.mkv").as_deref(), Some("episode data"));
+        assert_eq!(read_folder_file(&folder, "show.s01e01.mkv"), None);
+        let progress = folder.get_execution_progress().borrow().clone();
+        assert_eq!(progress.total_bytes_copied, "episode data".len() as u64);
+        assert_eq!(progress.total_bytes_to_copy, "episode data".len() as u64);
+    }
+
+    #[tokio::test]
+    async fn other_rename_errors_do_not_copy() {
+        let root = tempfile::tempdir().unwrap();
+        let mut folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "episode data")], json!({})).await;
+        folder.set_file_operations(Box::new(FailingRename(std::io::ErrorKind::PermissionDenied)));
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!((summary.renamed, summary.failed), (0, 1));
+        assert_eq!(read_folder_file(&folder, "Season 01/Show-S01E01-Pilot.mkv"), None);
+        assert_eq!(read_folder_file(&folder, "show.s01e01.mkv").as_deref(), Some("episode data"));
+        assert_eq!(folder.get_execution_progress().borrow().total_bytes_to_copy, 0);
+    }
+
+    const OLD_MTIME: i64 = 978_307_200;
+
+    async fn move_across_devices_with_old_mtime(preserve_timestamps: bool) -> filetime::FileTime {
+        let root = tempfile::tempdir().unwrap();
+        let rules = json!({ "preserve_timestamps": preserve_timestamps });
+        let mut folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "episode data")], rules).await;
+        folder.set_file_operations(Box::new(FailingRename(std::io::ErrorKind::CrossesDevices)));
+        let src_path = path::Path::new(folder.get_folder_path()).join("show.s01e01.mkv");
+        filetime::set_file_mtime(src_path, filetime::FileTime::from_unix_time(OLD_MTIME, 0)).unwrap();
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!(summary.renamed, 1);
+        assert!(!folder.has_errors());
+        let dest_path = path::Path::new(folder.get_folder_path()).join("Season 01/Show-S01E01-Pilot.mkv");
+        filetime::FileTime::from_last_modification_time(&std::fs::metadata(dest_path).unwrap())
+    }
+
+    #[tokio::test]
+    async fn copy_preserves_modification_time() {
+        let modified = move_across_devices_with_old_mtime(true).await;
+        // Some filesystems only store modification times to the nearest 2 seconds
+        assert!((modified.unix_seconds() - OLD_MTIME).abs() <= 2, "{}", modified);
+    }
+
+    #[tokio::test]
+    async fn copy_without_preserving_has_new_modification_time() {
+        let modified = move_across_devices_with_old_mtime(false).await;
+        assert!(modified.unix_seconds() > OLD_MTIME + 2, "{}", modified);
+    }
+
+    async fn execute_with_empty_folders(root: &path::Path, rules: serde_json::Value) -> AppFolder {
+        let files = [("show.s01e01.mkv", ""), ("Junk/Thumbs.db", ""), ("Notes/notes.txt", "")];
+        let folder_path = root.join("Show");
+        std::fs::create_dir_all(folder_path.join("Specials")).unwrap();
+        std::fs::create_dir_all(folder_path.join("Empty")).unwrap();
+        let folder = new_scanned_folder(root, &files, rules).await;
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!((summary.renamed, summary.failed), (1, 0));
+        folder
+    }
+
+    fn folder_exists(folder: &AppFolder, rel_path: &str) -> bool {
+        path::Path::new(folder.get_folder_path()).join(rel_path).is_dir()
+    }
+
+    #[tokio::test]
+    async fn empty_folders_are_removed_unless_excluded() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = execute_with_empty_folders(root.path(), json!({
+            "use_trash": false,
+            "empty_folder_exclusions": ["Specials"],
+            "ignore_files_for_emptiness": ["Thumbs.db"],
+        })).await;
+        assert!(folder_exists(&folder, "Specials"));
+        assert!(!folder_exists(&folder, "Junk"));
+        assert!(!folder_exists(&folder, "Empty"));
+        assert!(folder_exists(&folder, "Notes"));
+        assert!(folder_exists(&folder, "Season 01"));
+    }
+
+    #[tokio::test]
+    async fn empty_folders_are_kept_when_cleanup_is_disabled() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = execute_with_empty_folders(root.path(), json!({
+            "use_trash": false,
+            "delete_empty_folders": false,
+            "ignore_files_for_emptiness": ["Thumbs.db"],
+        })).await;
+        for rel_path in ["Specials", "Junk", "Empty", "Notes"] {
+            assert!(folder_exists(&folder, rel_path), "{}", rel_path);
+        }
+    }
+
+    // Only renames of the given file fail
+    struct FailingRenameOf(&'static str);
+
+    #[async_trait::async_trait]
+    impl FileOperations for FailingRenameOf {
+        async fn rename(&self, src: &path::Path, dest: &path::Path) -> Result<(), std::io::Error> {
+            if src.ends_with(self.0) {
+                return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "injected rename error"));
+            }
+            tokio::fs::rename(src, dest).await
+        }
+    }
+
+    async fn get_file_index(folder: &AppFolder, src: &str) -> usize {
+        folder.get_files().await.get_file_tracker().get_source_index(src).copied().unwrap()
+    }
+
+    #[tokio::test]
+    async fn file_error_is_matched_to_file_after_rescan() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("aaa.nfo", ""), ("show.s01e01.mkv", ""), ("show.s01e02.mkv", "")];
+        let mut folder = new_scanned_folder(root.path(), &files, json!({ "use_trash": false, "blacklist_extensions": ["nfo"] })).await;
+        folder.set_file_operations(Box::new(FailingRenameOf("show.s01e02.mkv")));
+        folder.get_mut_files().await.set_enabled_where(|file| file.get_action() == Action::Delete, true);
+        folder.flush_file_changes().await;
+        let old_index = get_file_index(&folder, "show.s01e02.mkv").await;
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!((summary.renamed, summary.deleted, summary.failed), (1, 1, 1));
+
+        // The other files were moved so the failed file has a new index
+        let new_index = get_file_index(&folder, "show.s01e02.mkv").await;
+        assert_ne!(old_index, new_index);
+        let file_errors = folder.get_file_errors().read().await.clone();
+        assert_eq!(file_errors.len(), 1);
+        assert_eq!(file_errors[0].src, "show.s01e02.mkv");
+        assert_eq!(file_errors[0].file_index, Some(new_index));
+        assert_eq!(file_errors[0].kind, std::io::ErrorKind::PermissionDenied);
+
+        let files = folder.get_files().await;
+        let error = files.get(new_index).unwrap().get_last_error().map(str::to_string);
+        assert!(error.unwrap().contains("injected rename error"));
+        assert_eq!(files.to_iter().filter(|file| file.get_last_error().is_some()).count(), 1);
+    }
+
+    async fn get_file_dest(folder: &AppFolder, src: &str) -> String {
+        let files = folder.get_files().await;
+        let file = files.to_iter().find(|file| file.get_src() == src).unwrap();
+        file.get_dest().to_string()
+    }
+
+    async fn new_folder_with_edited_dest(root: &path::Path) -> AppFolder {
+        let files = [("show.s01e01.mkv", "1"), ("show.s01e02.mkv", "2")];
+        let folder = new_scanned_folder(root, &files, json!({})).await;
+        set_renames(&folder, &[("show.s01e01.mkv", "Edited/Pilot.mkv")]).await;
+        folder
+    }
+
+    #[tokio::test]
+    async fn edited_destination_survives_incremental_rescan() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_folder_with_edited_dest(root.path()).await;
+        // New files are still picked up
+        std::fs::write(path::Path::new(folder.get_folder_path()).join("show.s01e03.mkv"), "3").unwrap();
+        folder.update_file_intents_incremental().await.unwrap();
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Edited/Pilot.mkv");
+        assert_eq!(get_file_dest(&folder, "show.s01e02.mkv").await, "Season 01/Show-S01E02-Return.mkv");
+        assert_eq!(folder.get_files().await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn edited_destination_is_reset_by_full_rescan() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_folder_with_edited_dest(root.path()).await;
+        folder.update_file_intents().await.unwrap();
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 01/Show-S01E01-Pilot.mkv");
+    }
+
+    #[tokio::test]
+    async fn modified_file_is_recomputed_by_incremental_rescan() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_folder_with_edited_dest(root.path()).await;
+        std::fs::write(path::Path::new(folder.get_folder_path()).join("show.s01e01.mkv"), "modified").unwrap();
+        folder.update_file_intents_incremental().await.unwrap();
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 01/Show-S01E01-Pilot.mkv");
+    }
+
+    #[tokio::test]
+    async fn folder_with_only_metadata_files_is_empty() {
+        let root = tempfile::tempdir().unwrap();
+        let files: Vec<(&str, &str)> = RESERVED_FILENAMES.iter().map(|filename| (*filename, "{}")).collect();
+        let folder = new_scanned_folder(root.path(), files.as_slice(), json!({ "blacklist_extensions": ["json"] })).await;
+        assert_eq!(folder.get_files().await.len(), 0);
+        assert_eq!(folder.get_folder_status(), FolderStatus::Empty);
+    }
+
+    #[tokio::test]
+    async fn metadata_files_are_only_reserved_at_the_folder_root() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [(PATH_STR_SERIES_DATA, "{}"), ("Extras/series.json", "{}")];
+        let folder = new_scanned_folder(root.path(), &files, json!({ "blacklist_extensions": ["json"] })).await;
+        let srcs: Vec<String> = folder.get_files().await.to_iter().map(|file| file.get_src().to_string()).collect();
+        assert_eq!(srcs, vec!["Extras/series.json"]);
+        assert_eq!(folder.get_folder_status(), FolderStatus::Pending);
+    }
+
+    async fn get_file_action(folder: &AppFolder, src: &str) -> Action {
+        let files = folder.get_files().await;
+        let file = files.to_iter().find(|file| file.get_src() == src).unwrap();
+        file.get_action()
+    }
+
+    #[tokio::test]
+    async fn forced_ignore_survives_full_rescan() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", ""), ("show.s01e02.mkv", "")], json!({})).await;
+        folder.add_to_ignore_list("show.s01e01.mkv").await.unwrap();
+        assert_eq!(get_file_action(&folder, "show.s01e01.mkv").await, Action::Ignore);
+
+        folder.update_file_intents().await.unwrap();
+        assert_eq!(get_file_action(&folder, "show.s01e01.mkv").await, Action::Ignore);
+        assert_eq!(get_file_action(&folder, "show.s01e02.mkv").await, Action::Rename);
+
+        // The ignore list is read back from the folder
+        let reloaded = new_scanned_folder(root.path(), &[], json!({})).await;
+        reloaded.load_ignored_files_from_file().await.unwrap();
+        reloaded.update_file_intents().await.unwrap();
+        assert_eq!(get_file_action(&reloaded, "show.s01e01.mkv").await, Action::Ignore);
+
+        reloaded.remove_from_ignore_list("show.s01e01.mkv").await.unwrap();
+        reloaded.update_file_intents().await.unwrap();
+        assert_eq!(get_file_action(&reloaded, "show.s01e01.mkv").await, Action::Rename);
+    }
+
+    #[tokio::test]
+    async fn complete_and_renamed_files_are_not_missing() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("Season 01/Show-S01E01-Pilot.mkv", ""), ("show.s01e02.mkv", "")];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        assert_eq!(get_file_action(&folder, "Season 01/Show-S01E01-Pilot.mkv").await, Action::Complete);
+        // The episodes in the test cache don't have air dates
+        let filter = MissingEpisodeFilter { is_exclude_specials: true, is_exclude_unaired: false };
+        assert!(folder.get_missing_episodes(filter).await.is_empty());
+
+        std::fs::remove_file(path::Path::new(folder.get_folder_path()).join("show.s01e02.mkv")).unwrap();
+        folder.update_file_intents().await.unwrap();
+        assert_eq!(folder.get_missing_episodes(filter).await, vec![EpisodeKey { season: 1, episode: 2 }]);
+    }
+
+    async fn get_action_bytes(folder: &AppFolder) -> [u64; 3] {
+        let files = folder.get_files().await;
+        let bytes = files.get_file_tracker().get_action_bytes();
+        [bytes[Action::Rename], bytes[Action::Delete], bytes[Action::Ignore]]
+    }
+
+    async fn set_file_action(folder: &AppFolder, index: usize, action: Action) {
+        folder.get_mut_files().await.get(index).unwrap().set_action(action);
+        folder.flush_file_changes().await;
+    }
+
+    #[tokio::test]
+    async fn action_bytes_ignore_enabled_state() {
+        let files = vec![
+            new_file("a.mkv", Action::Rename, "A.mkv", 100),
+            new_file("b.nfo", Action::Delete, "", 30),
+            new_file("c.txt", Action::Ignore, "", 5),
+        ];
+        let folder = new_folder_with_files(files, FileTracker::new()).await;
+        assert_eq!(get_action_bytes(&folder).await, [100, 30, 5]);
+
+        folder.get_mut_files().await.set_enabled_where(|_| true, true);
+        folder.flush_file_changes().await;
+        assert_eq!(get_action_bytes(&folder).await, [100, 30, 5]);
+        disable_all_files(&folder).await;
+        assert_eq!(get_action_bytes(&folder).await, [100, 30, 5]);
+    }
+
+    #[tokio::test]
+    async fn action_bytes_follow_set_action() {
+        let files = vec![
+            new_file("a.mkv", Action::Rename, "A.mkv", 100),
+            new_file("b.nfo", Action::Delete, "", 30),
+            new_file("c.txt", Action::Ignore, "", 5),
+        ];
+        let folder = new_folder_with_files(files, FileTracker::new()).await;
+        set_file_action(&folder, 0, Action::Delete).await;
+        assert_eq!(get_action_bytes(&folder).await, [0, 130, 5]);
+        set_file_action(&folder, 1, Action::Ignore).await;
+        assert_eq!(get_action_bytes(&folder).await, [0, 100, 35]);
+        // Setting the same action again doesn't count the file twice
+        set_file_action(&folder, 1, Action::Ignore).await;
+        assert_eq!(get_action_bytes(&folder).await, [0, 100, 35]);
+        set_file_action(&folder, 0, Action::Rename).await;
+        assert_eq!(get_action_bytes(&folder).await, [100, 0, 35]);
+    }
+
+    #[tokio::test]
+    async fn folder_status_goes_from_unknown_to_pending_to_done() {
+        let root = tempfile::tempdir().unwrap();
+        create_files(root.path(), &["Show/show.s01e01.mkv"]);
+        let folder = AppFolder::new(
+            root.path().to_string_lossy().as_ref(),
+            root.path().join("Show").to_string_lossy().as_ref(),
+            Arc::new(new_filter_rules(json!({}))),
+        );
+        assert_eq!(folder.get_folder_status(), FolderStatus::Unknown);
+
+        *folder.cache.write().await = Some(Arc::new(new_cache("Show", &[(1, 1, Some("Pilot"))])));
+        folder.update_file_intents().await.unwrap();
+        assert_eq!(folder.get_folder_status(), FolderStatus::Pending);
+
+        // Pending depends on the actions of the files and not whether they are enabled
+        disable_all_files(&folder).await;
+        assert_eq!(folder.get_folder_status(), FolderStatus::Pending);
+
+        // The rename being completed is the same as it being executed
+        set_file_action(&folder, 0, Action::Complete).await;
+        assert_eq!(folder.get_folder_status(), FolderStatus::Done);
+        set_file_action(&folder, 0, Action::Rename).await;
+        assert_eq!(folder.get_folder_status(), FolderStatus::Pending);
+
+        folder.get_mut_files().await.set_enabled_where(|_| true, true);
+        folder.flush_file_changes().await;
+        folder.execute_file_changes_and_rescan().await;
+        assert_eq!(folder.get_folder_status(), FolderStatus::Done);
+    }
+
+    async fn assert_folder_tracker_is_consistent(folder: &AppFolder) {
+        let file_list = folder.file_list.read().await;
+        let file_tracker = folder.file_tracker.read().await;
+        assert_tracker_matches_files(file_list.as_slice(), &file_tracker);
+    }
+
+    #[tokio::test]
+    async fn imported_plan_updates_tracker() {
+        let files = vec![
+            new_file("a.mkv", Action::Rename, "Season 01/A.mkv", 10),
+            new_file("b.mkv", Action::Rename, "Season 01/B.mkv", 20),
+            new_file("c.nfo", Action::Delete, "", 30),
+            new_file("d.mkv", Action::Ignore, "", 40),
+        ];
+        let folder = new_folder_with_files(files, FileTracker::new()).await;
+        let data = folder.export_plan().await.unwrap();
+        let mut plan: Vec<PlanEntry> = serde_json::from_str(data.as_str()).unwrap();
+        assert_eq!(plan.len(), 4);
+
+        // a and d are renamed to the same destination so they conflict
+        plan[0].dest = "Season 01/Edited.mkv".to_string();
+        plan[1].action = Action::Ignore;
+        plan[2].is_enabled = true;
+        plan[3] = PlanEntry { src: "d.mkv".to_string(), action: Action::Rename, dest: "Season 01/Edited.mkv".to_string(), is_enabled: true };
+        plan.push(PlanEntry { src: "gone.mkv".to_string(), action: Action::Rename, dest: "Season 01/Gone.mkv".to_string(), is_enabled: true });
+        plan.push(PlanEntry { src: "b.mkv".to_string(), action: Action::Rename, dest: "../Escape.mkv".to_string(), is_enabled: true });
+        folder.import_plan(serde_json::to_string(&plan).unwrap().as_str()).await.unwrap();
+
+        assert_eq!(get_file_dest(&folder, "a.mkv").await, "Season 01/Edited.mkv");
+        assert_eq!(get_file_action(&folder, "b.mkv").await, Action::Ignore);
+        let files = folder.get_files().await;
+        assert!(files.to_iter().all(|file| file.get_is_enabled()));
+        assert_eq!(files.get_file_tracker().get_conflict_count(), 1);
+        drop(files);
+        assert_folder_tracker_is_consistent(&folder).await;
+
+        // Rows without a file and rows leaving the folder are reported
+        let errors: Vec<String> = folder.get_errors().read().await.iter().map(|error| error.to_string()).collect();
+        assert!(errors.iter().any(|error| error.contains("gone.mkv")), "{:?}", errors);
+        assert!(errors.iter().any(|error| error.contains("b.mkv")), "{:?}", errors);
+    }
+
+    #[tokio::test]
+    async fn cache_without_actors_file_loads() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1")], json!({})).await;
+        assert!(folder.load_actors_from_file().await.is_none());
+        assert!(folder.get_cache().read().await.as_ref().unwrap().actors.is_none());
+        assert!(!folder.has_errors());
+    }
+
+    #[tokio::test]
+    async fn actors_file_is_loaded_in_billing_order() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1")], json!({})).await;
+        let actors = json!([
+            { "name": "Ron Glass" },
+            { "name": "Summer Glau", "role": "River Tam", "sortOrder": 3 },
+            { "name": "Nathan Fillion", "role": "Malcolm Reynolds", "sortOrder": 0 },
+        ]);
+        std::fs::write(path::Path::new(folder.get_folder_path()).join(PATH_STR_ACTORS_DATA), actors.to_string()).unwrap();
+
+        folder.load_actors_from_file().await.unwrap();
+        let cache = folder.get_cache().read().await.clone().unwrap();
+        let names: Vec<&str> = cache.actors.as_ref().unwrap().iter().map(|actor| actor.name.as_str()).collect();
+        assert_eq!(names, vec!["Nathan Fillion", "Summer Glau", "Ron Glass"]);
+    }
+
+    #[tokio::test]
+    async fn seasons_on_disk_are_found_from_descriptors() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("Season 03/show.s03e02.mkv", "2"), ("show.s03e04.srt", "3"), ("notes.txt", "4")];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        assert_eq!(folder.get_seasons_on_disk().await, BTreeSet::from([1, 3]));
+    }
+
+    #[tokio::test]
+    async fn only_seasons_on_disk_are_merged() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("show.s03e01.mkv", "2")];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        // The ids match the episodes of the loaded cache so the first season replaces them
+        let mut provider = new_mock_provider(1, "Show", &[
+            (1, 1, Some("Pilot (renamed)")), (1, 2, Some("Return")), (2, 1, Some("Second")), (3, 1, Some("Third")),
+        ]);
+        for (index, episode) in provider.episodes.get_mut(&1).unwrap().iter_mut().enumerate() {
+            episode.id = index as u32 + 1;
+        }
+
+        folder.refresh_seasons_on_disk_from_api(provider.into_provider()).await.unwrap();
+        let cache = folder.get_cache().read().await.clone().unwrap();
+        assert_eq!(cache.get_seasons(), vec![1, 3]);
+        let ids: Vec<u32> = cache.episodes.iter().map(|episode| episode.id).collect();
+        assert_eq!(ids, vec![1, 2, 4]);
+        assert_eq!(cache.get_episode(&EpisodeKey { season: 1, episode: 1 }).unwrap().name.as_deref(), Some("Pilot (renamed)"));
+        assert_eq!(cache.get_episode(&EpisodeKey { season: 3, episode: 1 }).unwrap().name.as_deref(), Some("Third"));
+        assert!(!folder.has_errors());
+    }
+
+    #[tokio::test]
+    async fn switching_to_dvd_order_updates_destinations() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1")], json!({})).await;
+        *folder.cache.write().await = Some(Arc::new(new_dvd_order_cache(EpisodeOrdering::Aired)));
+        folder.update_file_intents().await.unwrap();
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 01/Show-S01E01-The.Train.Job.mkv");
+
+        folder.set_episode_ordering(EpisodeOrdering::Dvd).await.unwrap();
+        folder.update_file_intents().await.unwrap();
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 01/Show-S01E01-Serenity.mkv");
+        assert_eq!(folder.get_cache().read().await.as_ref().unwrap().get_ordering(), EpisodeOrdering::Dvd);
+
+        // The ordering is saved with the folder
+        let reloaded = AppFolder::new(
+            root.path().to_string_lossy().as_ref(),
+            folder.get_folder_path(),
+            Arc::new(new_filter_rules(json!({}))),
+        );
+        reloaded.load_folder_config_from_file().await;
+        assert_eq!(reloaded.get_episode_ordering().await, EpisodeOrdering::Dvd);
+    }
+
+    #[tokio::test]
+    async fn refresh_replaces_cache_from_provider() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e02.mkv", "1")], json!({})).await;
+        let provider = new_mock_provider(1, "Show", &[(1, 1, Some("Pilot")), (1, 2, Some("Homecoming"))]).into_provider();
+
+        folder.refresh_cache_from_api(provider).await.unwrap();
+        folder.update_file_intents().await.unwrap();
+        let cache = folder.get_cache().read().await.clone().unwrap();
+        assert_eq!(cache.episodes.len(), 2);
+        assert_eq!(get_file_dest(&folder, "show.s01e02.mkv").await, "Season 01/Show-S01E02-Homecoming.mkv");
+        assert!(!folder.has_errors());
+    }
+
+    #[tokio::test]
+    async fn refresh_requires_cache_from_same_provider() {
+        let folder_path = tempfile::tempdir().unwrap();
+        let folder = AppFolder::new("", folder_path.path().to_string_lossy().as_ref(), Arc::new(new_filter_rules(json!({}))));
+        let provider = new_mock_provider(1, "Show", &[(1, 1, Some("Pilot"))]);
+        assert!(folder.refresh_cache_from_api(provider.into_provider()).await.is_none());
+        assert!(folder.get_cache().read().await.is_none());
+
+        *folder.cache.write().await = Some(Arc::new(new_cache("Show", &[(1, 1, Some("Pilot"))])));
+        let mut provider = new_mock_provider(1, "Other", &[(1, 1, Some("Other"))]);
+        provider.kind = tvdb::models::ProviderKind::TvMaze;
+        assert!(folder.refresh_cache_from_api(provider.into_provider()).await.is_none());
+        assert_eq!(folder.get_cache().read().await.as_ref().unwrap().series.name, "Show");
+        assert_eq!(folder.get_errors().read().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn duplicate_files_count_once_towards_coverage() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [
+            ("show.s01e01.mkv", "1"),
+            ("show.s01e01.720p.mkv", "1"),
+            ("Season 01/Show-S01E02-Return.mkv", "2"),
+            ("release.nfo", ""),
+        ];
+        let folder = new_scanned_folder(root.path(), &files, json!({ "blacklist_extensions": ["nfo"] })).await;
+        {
+            let files = folder.get_files().await;
+            let tracker = files.get_file_tracker();
+            assert_eq!(tracker.get_action_count()[Action::Rename], 2);
+            assert_eq!(tracker.get_action_count()[Action::Complete], 1);
+        }
+        assert_eq!(folder.get_episodes_on_disk_count().await, 2);
+    }
+
+    #[tokio::test]
+    async fn refresh_stores_episodes_summary() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1")], json!({})).await;
+        let mut provider = new_mock_provider(1, "Show", &[(1, 1, Some("Pilot")), (1, 2, Some("Return"))]);
+        let summary = EpisodesSummary { aired_seasons: vec![1], aired_episodes: 2, ..EpisodesSummary::default() };
+        provider.episodes_summaries.insert(1, summary.clone());
+
+        folder.refresh_cache_from_api(provider.into_provider()).await.unwrap();
+        let cache = folder.get_cache().read().await.clone().unwrap();
+        assert_eq!(cache.episodes_summary.as_ref(), Some(&summary));
+        assert_eq!(folder.get_episodes_on_disk_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn duplicate_episodes_are_warned_and_lowest_id_is_used() {
+        let root = tempfile::tempdir().unwrap();
+        let folder_path = root.path().join("Show");
+        create_files(folder_path.as_path(), &["show.s01e01.mkv"]);
+        write_cache_file(folder_path.as_path(), "Show", &[(1, 1, Some("Pilot")), (1, 2, Some("Return")), (1, 1, Some("Pilot (extended)"))]);
+        let folder = AppFolder::new(
+            root.path().to_string_lossy().as_ref(),
+            folder_path.to_string_lossy().as_ref(),
+            Arc::new(new_filter_rules(json!({}))),
+        );
+
+        folder.load_cache_from_file().await.unwrap();
+        folder.update_file_intents().await.unwrap();
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 01/Show-S01E01-Pilot.mkv");
+
+        let errors = folder.get_errors().read().await;
+        let warnings: Vec<&AppError> = errors.iter_severity(Severity::Warning).collect();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].context, "Cache");
+        assert!(warnings[0].message.ends_with("using the lowest id for: S01E01 (ids 1, 3)"), "{}", warnings[0].message);
+        assert_eq!(errors.iter_severity(Severity::Error).count(), 0);
+    }
+
+    fn new_cache_folder(root: &path::Path, rules: serde_json::Value) -> AppFolder {
+        let folder_path = root.join("Show");
+        create_files(folder_path.as_path(), &["show.s01e01.mkv"]);
+        AppFolder::new(
+            root.to_string_lossy().as_ref(),
+            folder_path.to_string_lossy().as_ref(),
+            Arc::new(new_filter_rules(rules)),
+        )
+    }
+
+    // Written in the current cache file format with the fetch time set the given number of days ago
+    fn write_aged_cache_file(folder: &AppFolder, age_days: i64) {
+        let fetched_at = chrono::Utc::now() - chrono::Duration::days(age_days);
+        let cache_file = json!({
+            "version": CACHE_FILE_VERSION,
+            "fetched_at": fetched_at,
+            "series": new_series(1, "Show"),
+            "episodes": [new_episode(1, 1, 1, Some("Outdated"))],
+        });
+        std::fs::write(path::Path::new(folder.get_folder_path()).join(PATH_STR_CACHE_DATA), cache_file.to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn saved_cache_keeps_fetch_time() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        write_aged_cache_file(&folder, 42);
+        folder.load_cache_from_file().await.unwrap();
+        assert_eq!(folder.get_cache_age().await.map(|age| age.num_days()), Some(42));
+
+        // The fetch time is written back unchanged
+        folder.save_cache_to_file().await.unwrap();
+        let other = new_cache_folder(root.path(), json!({}));
+        other.load_cache_from_file().await.unwrap();
+        assert_eq!(other.get_cache_age().await.map(|age| age.num_days()), Some(42));
+    }
+
+    #[tokio::test]
+    async fn legacy_cache_has_unknown_age() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        let folder_path = path::Path::new(folder.get_folder_path());
+        let cache = new_cache("Show", &[(1, 1, Some("Pilot"))]);
+        std::fs::write(folder_path.join(PATH_STR_SERIES_DATA), serde_json::to_string(&cache.series).unwrap()).unwrap();
+        std::fs::write(folder_path.join(PATH_STR_EPISODES_DATA), serde_json::to_string(&cache.episodes).unwrap()).unwrap();
+
+        folder.load_cache_from_file().await.unwrap();
+        assert_eq!(folder.get_cache().read().await.as_ref().unwrap().series.name, "Show");
+        assert_eq!(folder.get_cache_age().await, None);
+        assert!(!folder.has_errors());
+    }
+
+    #[tokio::test]
+    async fn legacy_cache_uses_meta_file_for_age() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        let folder_path = path::Path::new(folder.get_folder_path());
+        let cache = new_cache("Show", &[(1, 1, Some("Pilot"))]);
+        let meta = CacheMeta { fetched_at: chrono::Utc::now() - chrono::Duration::days(3) };
+        std::fs::write(folder_path.join(PATH_STR_SERIES_DATA), serde_json::to_string(&cache.series).unwrap()).unwrap();
+        std::fs::write(folder_path.join(PATH_STR_EPISODES_DATA), serde_json::to_string(&cache.episodes).unwrap()).unwrap();
+        std::fs::write(folder_path.join(PATH_STR_CACHE_META), serde_json::to_string(&meta).unwrap()).unwrap();
+
+        folder.load_cache_from_file().await.unwrap();
+        assert_eq!(folder.get_cache_age().await.map(|age| age.num_days()), Some(3));
+    }
+
+    #[tokio::test]
+    async fn old_cache_is_refreshed_on_initial_load() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({ "auto_refresh_after_days": 30 }));
+        write_aged_cache_file(&folder, 42);
+        let provider = new_mock_provider(1, "Show", &[(1, 1, Some("Pilot"))]).into_provider();
+
+        folder.perform_initial_load(Some(provider)).await.unwrap();
+        assert_eq!(folder.get_cache_age().await.map(|age| age.num_days()), Some(0));
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 01/Show-S01E01-Pilot.mkv");
+        // The refreshed cache is saved so it isn't fetched again next time
+        let other = new_cache_folder(root.path(), json!({}));
+        other.load_cache_from_file().await.unwrap();
+        assert_eq!(other.get_cache_age().await.map(|age| age.num_days()), Some(0));
+    }
+
+    #[tokio::test]
+    async fn recent_or_unconfigured_cache_isnt_refreshed() {
+        for (age_days, rules) in [(10, json!({ "auto_refresh_after_days": 30 })), (42, json!({}))] {
+            let root = tempfile::tempdir().unwrap();
+            let folder = new_cache_folder(root.path(), rules);
+            write_aged_cache_file(&folder, age_days);
+            let provider = new_mock_provider(1, "Show", &[(1, 1, Some("Pilot"))]).into_provider();
+
+            folder.perform_initial_load(Some(provider)).await.unwrap();
+            assert_eq!(folder.get_cache_age().await.map(|age| age.num_days()), Some(age_days));
+            assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 01/Show-S01E01-Outdated.mkv");
+        }
+    }
+
+    fn write_legacy_cache_files(folder: &AppFolder, cache: &TvdbCache) {
+        let folder_path = path::Path::new(folder.get_folder_path());
+        std::fs::write(folder_path.join(PATH_STR_SERIES_DATA), serde_json::to_string_pretty(&cache.series).unwrap()).unwrap();
+        std::fs::write(folder_path.join(PATH_STR_EPISODES_DATA), serde_json::to_string_pretty(&cache.episodes).unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn legacy_cache_is_migrated() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        write_legacy_cache_files(&folder, &new_cache("Show", &[(1, 1, Some("Pilot")), (1, 2, Some("Return"))]));
+
+        folder.load_cache_from_file().await.unwrap();
+        assert!(!folder.has_errors());
+        assert!(read_folder_file(&folder, PATH_STR_SERIES_DATA).is_none());
+        assert!(read_folder_file(&folder, PATH_STR_EPISODES_DATA).is_none());
+        let file: CacheFile = serde_json::from_str(read_folder_file(&folder, PATH_STR_CACHE_DATA).unwrap().as_str()).unwrap();
+        assert_eq!(file.version, CACHE_FILE_VERSION);
+        assert_eq!(file.series.name, "Show");
+        assert_eq!(file.episodes.len(), 2);
+
+        // Loaded from the new file afterwards
+        let other = new_cache_folder(root.path(), json!({}));
+        other.load_cache_from_file().await.unwrap();
+        assert_eq!(other.get_cache().read().await.as_ref().unwrap().episodes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn corrupt_cache_file_falls_back_to_legacy() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        write_legacy_cache_files(&folder, &new_cache("Show", &[(1, 1, Some("Pilot"))]));
+        std::fs::write(path::Path::new(folder.get_folder_path()).join(PATH_STR_CACHE_DATA), r#"{"version": 1, "ser"#).unwrap();
+
+        folder.load_cache_from_file().await.unwrap();
+        assert_eq!(folder.get_cache().read().await.as_ref().unwrap().series.name, "Show");
+        // The corrupt file is reported and then replaced by the migrated cache
+        let file_errors = folder.get_file_errors().read().await.clone();
+        assert_eq!(file_errors.len(), 1);
+        assert_eq!(file_errors[0].src, PATH_STR_CACHE_DATA);
+        assert_eq!(file_errors[0].kind, std::io::ErrorKind::InvalidData);
+        assert!(file_errors[0].message.starts_with("JSON decoding error reading cache from file"));
+        let file: CacheFile = serde_json::from_str(read_folder_file(&folder, PATH_STR_CACHE_DATA).unwrap().as_str()).unwrap();
+        assert_eq!(file.series.name, "Show");
+    }
+
+    #[tokio::test]
+    async fn newer_cache_version_isnt_loaded() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        let cache_file = json!({ "version": CACHE_FILE_VERSION+1, "series": new_series(1, "Show"), "episodes": [] });
+        std::fs::write(path::Path::new(folder.get_folder_path()).join(PATH_STR_CACHE_DATA), cache_file.to_string()).unwrap();
+
+        assert!(folder.load_cache_from_file().await.is_none());
+        assert!(folder.get_cache().read().await.is_none());
+        // The newer file is left for the version that wrote it
+        assert_eq!(read_folder_file(&folder, PATH_STR_CACHE_DATA), Some(cache_file.to_string()));
+    }
+
+    #[tokio::test]
+    async fn saved_cache_leaves_no_temporary_file() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        *folder.cache.write().await = Some(Arc::new(new_cache("Show", &[(1, 1, Some("Pilot"))])));
+        folder.save_cache_to_file().await.unwrap();
+        let temporary_file = format!("{}{}", PATH_STR_CACHE_DATA, crate::atomic_write::TEMPORARY_WRITE_SUFFIX);
+        assert!(read_folder_file(&folder, temporary_file.as_str()).is_none());
+        assert!(read_folder_file(&folder, PATH_STR_CACHE_DATA).is_some());
+    }
+
+    fn write_foreign_cache_file(folder: &AppFolder, series_id: u32, episode_series_ids: &[u32]) {
+        let episodes: Vec<Episode> = episode_series_ids.iter().enumerate().map(|(index, series_id)| Episode {
+            series_id: Some(*series_id),
+            ..new_episode(index as u32 + 1, 1, index as u32 + 1, Some("Pilot"))
+        }).collect();
+        let cache_file = json!({ "version": CACHE_FILE_VERSION, "series": new_series(series_id, "Show"), "episodes": episodes });
+        std::fs::write(path::Path::new(folder.get_folder_path()).join(PATH_STR_CACHE_DATA), cache_file.to_string()).unwrap();
+    }
+
+    async fn get_error_messages(folder: &AppFolder, severity: Severity) -> Vec<String> {
+        folder.get_errors().read().await.iter_severity(severity).map(|error| error.message.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn cache_with_episodes_of_another_series_is_rejected() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        *folder.cache.write().await = Some(Arc::new(new_cache("Show", &[(1, 1, Some("Pilot"))])));
+        write_foreign_cache_file(&folder, 5, &[7, 7, 5]);
+
+        assert!(folder.load_cache_from_file().await.is_none());
+        // The folder is treated as unloaded instead of keeping the previous cache
+        assert!(folder.get_cache().read().await.is_none());
+        let errors = get_error_messages(&folder, Severity::Error).await;
+        assert_eq!(errors, vec!["Refusing to load cache since 2 of 3 episodes belong to series 7 instead of series 5"]);
+    }
+
+    #[tokio::test]
+    async fn few_episodes_of_another_series_are_dropped() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        let mut series_ids = vec![5; 10];
+        series_ids.push(7);
+        write_foreign_cache_file(&folder, 5, series_ids.as_slice());
+
+        folder.load_cache_from_file().await.unwrap();
+        assert_eq!(folder.get_cache().read().await.as_ref().unwrap().episodes.len(), 10);
+        let warnings = get_error_messages(&folder, Severity::Warning).await;
+        assert_eq!(warnings, vec!["Ignoring episodes from another series since 1 of 11 episodes belong to series 7 instead of series 5"]);
+    }
+
+    #[tokio::test]
+    async fn api_episodes_of_another_series_keep_loaded_cache() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        *folder.cache.write().await = Some(Arc::new(new_cache("Show", &[(1, 1, Some("Pilot"))])));
+        let mut provider = new_mock_provider(1, "Show", &[(1, 1, Some("Other")), (1, 2, Some("Other"))]);
+        for episode in provider.episodes.get_mut(&1).unwrap().iter_mut() {
+            episode.series_id = Some(2);
+        }
+
+        assert!(folder.load_cache_from_api(provider.into_provider(), 1).await.is_none());
+        let cache = folder.get_cache().read().await.clone().unwrap();
+        assert_eq!(cache.episodes[0].name.as_deref(), Some("Pilot"));
+        let errors = get_error_messages(&folder, Severity::Error).await;
+        assert_eq!(errors, vec!["Refusing to load cache since 2 of 2 episodes belong to series 2 instead of series 1"]);
+    }
+
+    #[tokio::test]
+    async fn bookmark_follows_renamed_file() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1"), ("show.s01e02.mkv", "2")], json!({})).await;
+        folder.get_bookmarks().write().await.get_mut_with_insert("show.s01e01.mkv").is_favourite = true;
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!((summary.renamed, summary.failed), (2, 0));
+        {
+            let bookmarks = folder.get_bookmarks().read().await;
+            assert!(bookmarks.get("show.s01e01.mkv").is_none());
+            assert!(bookmarks.get("Season 01/Show-S01E01-Pilot.mkv").unwrap().is_favourite);
+            // Files that weren't bookmarked don't get one
+            assert!(bookmarks.get("Season 01/Show-S01E02-Return.mkv").is_none());
+        }
+        // The moved bookmark is saved so it is found after reloading
+        let saved = deserialize_bookmarks(read_folder_file(&folder, PATH_STR_BOOKMARKS).unwrap().as_str()).unwrap();
+        assert!(saved.get("Season 01/Show-S01E01-Pilot.mkv").unwrap().is_favourite);
+        assert_eq!(saved.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn bookmark_follows_undone_rename() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1")], json!({})).await;
+        folder.get_bookmarks().write().await.get_mut_with_insert("show.s01e01.mkv").is_unread = true;
+        folder.execute_file_changes_and_rescan().await;
+
+        folder.undo_last_execution_and_rescan().await.unwrap();
+        let bookmarks = folder.get_bookmarks().read().await;
+        assert!(bookmarks.get("show.s01e01.mkv").unwrap().is_unread);
+        assert!(bookmarks.get("Season 01/Show-S01E01-Pilot.mkv").is_none());
+    }
+
+    #[tokio::test]
+    async fn prune_removes_only_orphaned_bookmarks() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1"), ("extras/notes.txt", "")], json!({})).await;
+        {
+            let mut bookmarks = folder.get_bookmarks().write().await;
+            bookmarks.get_mut_with_insert("show.s01e01.mkv").is_favourite = true;
+            bookmarks.get_mut_with_insert("extras/notes.txt").is_read = true;
+            bookmarks.get_mut_with_insert("deleted.mkv").is_unread = true;
+            bookmarks.get_mut_with_insert("extras/old.txt").is_read = true;
+            bookmarks.get_episode_mut_with_insert(&EpisodeKey { season: 3, episode: 1 }).is_favourite = true;
+        }
+
+        assert_eq!(folder.prune_orphaned_bookmarks().await, 2);
+        {
+            let bookmarks = folder.get_bookmarks().read().await;
+            let mut ids: Vec<&str> = bookmarks.iter().map(|(id, _)| id).collect();
+            ids.sort();
+            assert_eq!(ids, vec!["extras/notes.txt", "show.s01e01.mkv"]);
+            // Episodes might be downloaded again so their bookmarks are kept
+            assert!(bookmarks.get_episode(&EpisodeKey { season: 3, episode: 1 }).is_some());
+        }
+        let saved = deserialize_bookmarks(read_folder_file(&folder, PATH_STR_BOOKMARKS).unwrap().as_str()).unwrap();
+        assert_eq!(saved.len(), 3);
+        // Nothing is left to prune
+        assert_eq!(folder.prune_orphaned_bookmarks().await, 0);
+    }
+
+    #[tokio::test]
+    async fn unscanned_folder_isnt_pruned() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        folder.get_bookmarks().write().await.get_mut_with_insert("show.s01e01.mkv").is_favourite = true;
+        folder.get_bookmarks().write().await.get_mut_with_insert("deleted.mkv").is_favourite = true;
+
+        assert_eq!(folder.prune_orphaned_bookmarks().await, 0);
+        assert_eq!(folder.get_bookmarks().read().await.len(), 2);
+        assert!(read_folder_file(&folder, PATH_STR_BOOKMARKS).is_none());
+        let warnings = get_error_messages(&folder, Severity::Warning).await;
+        assert_eq!(warnings, vec!["Couldn't prune bookmarks since the folder hasn't been scanned since it was last changed"]);
+    }
+
+    #[tokio::test]
+    async fn bookmarks_are_pruned_after_execute_if_enabled() {
+        for (is_enabled, total_pruned) in [(true, 1), (false, 0)] {
+            let root = tempfile::tempdir().unwrap();
+            let rules = json!({ "prune_bookmarks_after_execute": is_enabled });
+            let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1")], rules).await;
+            folder.get_bookmarks().write().await.get_mut_with_insert("show.s01e01.mkv").is_favourite = true;
+            folder.get_bookmarks().write().await.get_mut_with_insert("deleted.mkv").is_favourite = true;
+
+            let summary = folder.execute_file_changes_and_rescan().await;
+            assert_eq!(summary.pruned_bookmarks, total_pruned);
+            // The renamed file keeps its bookmark either way
+            assert!(folder.get_bookmarks().read().await.get("Season 01/Show-S01E01-Pilot.mkv").is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn interrupted_bookmarks_save_keeps_saved_bookmarks() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        folder.get_bookmarks().write().await.get_mut_with_insert("a.mkv").is_favourite = true;
+        folder.save_bookmarks_to_file().await.unwrap();
+        // Left behind by a save that was interrupted before it could replace the file
+        let temporary_file = format!("{}{}", PATH_STR_BOOKMARKS, crate::atomic_write::TEMPORARY_WRITE_SUFFIX);
+        std::fs::write(path::Path::new(folder.get_folder_path()).join(temporary_file.as_str()), r#"[{"id": "b.m"#).unwrap();
+
+        let other = new_cache_folder(root.path(), json!({}));
+        other.load_bookmarks_from_file().await.unwrap();
+        assert!(other.get_bookmarks().read().await.get("a.mkv").unwrap().is_favourite);
+
+        other.get_bookmarks().write().await.get_mut_with_insert("b.mkv").is_read = true;
+        other.save_bookmarks_to_file().await.unwrap();
+        let saved = deserialize_bookmarks(read_folder_file(&other, PATH_STR_BOOKMARKS).unwrap().as_str()).unwrap();
+        assert_eq!(saved.len(), 2);
+        assert!(read_folder_file(&other, temporary_file.as_str()).is_none());
+    }
+
+    async fn get_file_sizes(folder: &AppFolder) -> BTreeMap<String, u64> {
+        let files = folder.get_files().await;
+        files.to_iter().map(|file| (file.get_src().to_string(), file.get_size())).collect()
+    }
+
+    #[tokio::test]
+    async fn sizes_survive_sort_and_tracker_rebuild() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [
+            ("show.s01e02.mkv", "x".repeat(200)),
+            ("b.nfo", "x".repeat(7)),
+            ("show.s01e01.mkv", "x".repeat(50)),
+            ("a.nfo", "".to_string()),
+        ];
+        let files: Vec<(&str, &str)> = files.iter().map(|(src, data)| (*src, data.as_str())).collect();
+        let folder = new_scanned_folder(root.path(), files.as_slice(), json!({ "blacklist_extensions": ["nfo"] })).await;
+
+        let expected = BTreeMap::from([
+            ("a.nfo".to_string(), 0),
+            ("b.nfo".to_string(), 7),
+            ("show.s01e01.mkv".to_string(), 50),
+            ("show.s01e02.mkv".to_string(), 200),
+        ]);
+        assert_eq!(get_file_sizes(&folder).await, expected);
+        {
+            let files = folder.get_files().await;
+            // Files are listed by their source regardless of the order they were found in
+            let sources: Vec<String> = files.to_iter().map(|file| file.get_src().to_string()).collect();
+            assert_eq!(sources, vec!["a.nfo", "b.nfo", "show.s01e01.mkv", "show.s01e02.mkv"]);
+            assert!(files.to_iter().all(|file| file.get_modified().is_some()));
+            let tracker = files.get_file_tracker();
+            assert_eq!(tracker.get_action_bytes()[Action::Rename], 250);
+            assert_eq!(tracker.get_action_bytes()[Action::Delete], 7);
+        }
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+
+        // Changing actions moves the size to the other action
+        set_file_action(&folder, get_file_index(&folder, "show.s01e02.mkv").await, Action::Ignore).await;
+        assert_eq!(get_file_sizes(&folder).await, expected);
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+        assert_eq!(folder.get_files().await.get_file_tracker().get_action_bytes()[Action::Rename], 50);
+    }
+
+    #[tokio::test]
+    async fn sizes_are_updated_by_rescan() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1"), ("show.s01e02.mkv", "22")], json!({})).await;
+        std::fs::write(path::Path::new(folder.get_folder_path()).join("show.s01e02.mkv"), "x".repeat(1000)).unwrap();
+
+        folder.update_file_intents_incremental().await.unwrap();
+        let sizes = get_file_sizes(&folder).await;
+        assert_eq!(sizes.values().copied().collect::<Vec<u64>>(), vec![1, 1000]);
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!(summary.renamed, 2);
+        let sizes = get_file_sizes(&folder).await;
+        assert_eq!(sizes.get("Season 01/Show-S01E02-Return.mkv"), Some(&1000));
+        assert_eq!(sizes.get("Season 01/Show-S01E01-Pilot.mkv"), Some(&1));
+    }
+
+    async fn get_enabled_sources(folder: &AppFolder, action: Action) -> Vec<String> {
+        let files = folder.get_files().await;
+        files.to_iter()
+            .filter(|file| file.get_action() == action && file.get_is_enabled())
+            .map(|file| file.get_src().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn bulk_changes_update_tracker_after_flush() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [
+            ("show.s01e01.mkv", "1"), ("show.s01e02.mkv", "2"),
+            ("Extras/interview.mkv", "333"), ("Extras/making.of.mkv", "4444"), ("trailer.mkv", "55555"),
+        ];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        {
+            let files = folder.get_files().await;
+            let tracker = files.get_file_tracker();
+            assert_eq!((tracker.get_action_count()[Action::Rename], tracker.get_action_count()[Action::Ignore]), (2, 3));
+        }
+
+        // Deletes start out disabled so nothing is deleted by accident
+        let total_queued = folder.get_mut_files().await
+            .set_action_where(|file| file.get_action() == Action::Ignore && file.get_src().starts_with("Extras/"), Action::Delete);
+        assert_eq!(total_queued, 2);
+        folder.flush_file_changes().await;
+        {
+            let files = folder.get_files().await;
+            let tracker = files.get_file_tracker();
+            assert_eq!((tracker.get_action_count()[Action::Delete], tracker.get_action_count()[Action::Ignore]), (2, 1));
+            assert_eq!(tracker.get_action_bytes()[Action::Delete], 7);
+        }
+        assert!(get_enabled_sources(&folder, Action::Delete).await.is_empty());
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+
+        // Files that already have the action aren't changed
+        let delete_indices = folder.get_files().await.get_file_tracker().get_action_indices(Action::Delete).iter().copied().collect::<Vec<usize>>();
+        assert_eq!(folder.get_mut_files().await.set_action_for_indices(delete_indices.as_slice(), Action::Delete), 0);
+        assert_eq!(folder.get_mut_files().await.change_queue_len(), 0);
+        assert_eq!(folder.get_mut_files().await.set_enabled_where(|file| file.get_action() == Action::Delete, true), 2);
+        folder.flush_file_changes().await;
+        assert_eq!(get_enabled_sources(&folder, Action::Delete).await, vec!["Extras/interview.mkv", "Extras/making.of.mkv"]);
+
+        let rename_indices = folder.get_files().await.get_file_tracker().get_action_indices(Action::Rename).iter().copied().collect::<Vec<usize>>();
+        let mut indices = rename_indices.clone();
+        indices.push(100);
+        assert_eq!(folder.get_mut_files().await.set_enabled_for_indices(indices.as_slice(), false), 2);
+        folder.flush_file_changes().await;
+        assert!(get_enabled_sources(&folder, Action::Rename).await.is_empty());
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+
+        // Renames without a destination are given their source
+        assert_eq!(folder.get_mut_files().await.set_action_where(|file| file.get_src() == "trailer.mkv", Action::Rename), 1);
+        folder.flush_file_changes().await;
+        assert_eq!(get_file_dest(&folder, "trailer.mkv").await, "trailer.mkv");
+        assert_eq!(folder.get_files().await.get_file_tracker().get_action_count()[Action::Rename], 3);
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn execution_records_failed_rename_into_read_only_folder() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let files = [("a.mkv", "a"), ("b.mkv", "b"), ("release.nfo", "")];
+        let rules = json!({ "blacklist_extensions": ["nfo"], "use_trash": false });
+        let folder = new_scanned_folder(root.path(), &files, rules).await;
+        let locked_path = path::Path::new(folder.get_folder_path()).join("Locked");
+        std::fs::create_dir(&locked_path).unwrap();
+        std::fs::set_permissions(&locked_path, std::fs::Permissions::from_mode(0o555)).unwrap();
+        // Permissions aren't enforced for privileged users so the rename wouldn't fail
+        let probe_path = locked_path.join("probe");
+        if std::fs::write(&probe_path, "").is_ok() {
+            std::fs::remove_file(probe_path).unwrap();
+            return;
+        }
+
+        set_renames(&folder, &[("a.mkv", "Locked/A.mkv"), ("b.mkv", "Season 01/B.mkv")]).await;
+        folder.get_mut_files().await.set_enabled_where(|file| file.get_action() == Action::Delete, true);
+        folder.flush_file_changes().await;
+        let summary = folder.execute_file_changes_and_rescan().await;
+        std::fs::set_permissions(&locked_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!((summary.renamed, summary.deleted, summary.failed), (1, 1, 1));
+
+        let mut records = folder.get_last_execution_records().read().await.clone();
+        records.sort_by(|a, b| a.src.cmp(&b.src));
+        let summaries: Vec<(&str, &str, Action, bool)> = records
+            .iter()
+            .map(|record| (record.src.as_str(), record.dest.as_str(), record.action, record.is_ok()))
+            .collect();
+        assert_eq!(summaries, vec![
+            ("a.mkv", "Locked/A.mkv", Action::Rename, false),
+            ("b.mkv", "Season 01/B.mkv", Action::Rename, true),
+            ("release.nfo", "", Action::Delete, true),
+        ]);
+        assert!(!records[0].result.as_ref().unwrap_err().is_empty());
+        assert_eq!(read_folder_file(&folder, "a.mkv").as_deref(), Some("a"));
+        assert_eq!(read_folder_file(&folder, "Season 01/B.mkv").as_deref(), Some("b"));
+
+        // Records are replaced by the next execution
+        set_renames(&folder, &[("a.mkv", "Season 01/A.mkv")]).await;
+        let summary = folder.execute_file_changes().await;
+        assert_eq!((summary.renamed, summary.failed), (1, 0));
+        let records = folder.get_last_execution_records().read().await.clone();
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].src.as_str(), records[0].dest.as_str()), ("a.mkv", "Season 01/A.mkv"));
+        assert!(records[0].is_ok());
+    }
+
+    async fn get_action_sources(folder: &AppFolder, action: Action) -> Vec<String> {
+        let files = folder.get_files().await;
+        files.get_sorted_action_indices(action, FileSortMode::Source)
+            .into_iter()
+            .map(|index| files.get(index).unwrap().get_src().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn action_indices_follow_every_action_change() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("trailer.mkv", "22"), ("release.nfo", "")];
+        let rules = json!({ "blacklist_extensions": ["nfo"] });
+        let folder = new_scanned_folder(root.path(), &files, rules).await;
+        assert_eq!(get_action_sources(&folder, Action::Ignore).await, vec!["trailer.mkv"]);
+
+        let index = get_file_index(&folder, "trailer.mkv").await;
+        for action in Action::iterator().chain(Action::iterator().rev()) {
+            folder.get_mut_files().await.get(index).unwrap().set_action(*action);
+            folder.flush_file_changes().await;
+            assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+            assert!(get_action_sources(&folder, *action).await.contains(&"trailer.mkv".to_string()), "{:?}", action);
+        }
+
+        // Changing the action back and forth before a flush only moves the file once
+        {
+            let mut files = folder.get_mut_files().await;
+            let mut file = files.get(index).unwrap();
+            file.set_action(Action::Delete);
+            file.set_is_enabled(true);
+            file.set_action(Action::Rename);
+        }
+        folder.flush_file_changes().await;
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+        assert!(get_action_sources(&folder, Action::Delete).await.contains(&"release.nfo".to_string()));
+        assert!(!get_action_sources(&folder, Action::Delete).await.contains(&"trailer.mkv".to_string()));
+    }
+
+    #[tokio::test]
+    async fn action_indices_are_reseeded_by_rescan() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("show.s01e02.mkv", "2"), ("trailer.mkv", "22"), ("release.nfo", "")];
+        let rules = json!({ "blacklist_extensions": ["nfo"] });
+        let folder = new_scanned_folder(root.path(), &files, rules).await;
+        set_file_action(&folder, get_file_index(&folder, "trailer.mkv").await, Action::Delete).await;
+
+        // Files are removed and added so indices shift between scans
+        let folder_path = path::Path::new(folder.get_folder_path());
+        std::fs::remove_file(folder_path.join("show.s01e01.mkv")).unwrap();
+        std::fs::write(folder_path.join("another.nfo"), "").unwrap();
+        for is_incremental in [false, true] {
+            match is_incremental {
+                true => folder.update_file_intents_incremental().await.unwrap(),
+                false => folder.update_file_intents().await.unwrap(),
+            };
+            assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+            let file_tracker = folder.file_tracker.read().await;
+            let total_indices: usize = Action::iterator().map(|action| file_tracker.get_action_indices(*action).len()).sum();
+            drop(file_tracker);
+            assert_eq!(total_indices, 4);
+            assert_eq!(get_action_sources(&folder, Action::Delete).await, vec!["another.nfo", "release.nfo"]);
+            assert_eq!(get_action_sources(&folder, Action::Rename).await, vec!["show.s01e02.mkv"]);
+            assert_eq!(get_action_sources(&folder, Action::Ignore).await, vec!["trailer.mkv"]);
+        }
+    }
+
+    fn new_episode_file(src: &str, key: Option<(u32, u32)>, action: Action) -> AppFile {
+        let key = key.map(|(season, episode)| EpisodeKey { season, episode });
+        AppFile::new(src.to_string(), key, action, String::new(), None, 0)
+    }
+
+    #[test]
+    fn duplicates_are_grouped_by_episode() {
+        let file_list = vec![
+            new_episode_file("show.s01e02.mkv", Some((1, 2)), Action::Rename),
+            new_episode_file("show.s01e01.mkv", Some((1, 1)), Action::Rename),
+            new_episode_file("show.s01e01.srt", Some((1, 1)), Action::Rename),
+            new_episode_file("show.s01e01.proper.mkv", Some((1, 1)), Action::Complete),
+            new_episode_file("show.s01e02.SRT", Some((1, 2)), Action::Rename),
+            new_episode_file("show.s01e03.mkv", Some((1, 3)), Action::Rename),
+            new_episode_file("show.s01e03.sample.mkv", Some((1, 3)), Action::Delete),
+            new_episode_file("extras.mkv", None, Action::Rename),
+            new_episode_file("more.extras.mkv", None, Action::Rename),
+            new_episode_file("show.s02e01.mkv", Some((2, 1)), Action::Rename),
+            new_episode_file("show.s02e01.720p.mkv", Some((2, 1)), Action::Ignore),
+            new_episode_file("Season 01/show.s01e02.mkv", Some((1, 2)), Action::Complete),
+        ];
+        assert_eq!(find_duplicate_groups(file_list.as_slice()), vec![
+            DuplicateGroup { key: EpisodeKey { season: 1, episode: 1 }, indices: vec![1, 3] },
+            DuplicateGroup { key: EpisodeKey { season: 1, episode: 2 }, indices: vec![0, 11] },
+        ]);
+    }
+
+    fn get_file_actions(files: &ImmutableAppFileList<'_>) -> Vec<(String, Action, bool)> {
+        let mut actions: Vec<(String, Action, bool)> = files.to_iter()
+            .map(|file| (file.get_src().to_string(), file.get_action(), file.get_is_enabled()))
+            .collect();
+        actions.sort_by(|a, b| a.0.cmp(&b.0));
+        actions
+    }
+
+    #[tokio::test]
+    async fn keep_largest_deletes_smaller_duplicates() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [
+            ("show.s01e01.mkv", "1"), ("show.s01e01.proper.mkv", "333"), ("show.s01e01.srt", "22"),
+            ("show.s01e02.mkv", "4444"), ("show.s01e02.repack.mkv", "55"), ("show.s01e02.720p.mkv", "6"),
+        ];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        let groups = folder.get_duplicate_groups().read().await.clone();
+        let sources: Vec<Vec<String>> = {
+            let files = folder.get_files().await;
+            groups.iter()
+                .map(|group| {
+                    let mut sources: Vec<String> = group.indices.iter().map(|index| files.get(*index).unwrap().get_src().to_string()).collect();
+                    sources.sort();
+                    sources
+                })
+                .collect()
+        };
+        assert_eq!(sources, vec![
+            vec!["show.s01e01.mkv", "show.s01e01.proper.mkv"],
+            vec!["show.s01e02.720p.mkv", "show.s01e02.mkv", "show.s01e02.repack.mkv"],
+        ]);
+
+        // Only the given episode is resolved
+        assert_eq!(folder.resolve_duplicates(DuplicateResolution::KeepLargest, Some(EpisodeKey { season: 1, episode: 1 })).await, 1);
+        assert_eq!(folder.get_files().await.get_file_tracker().get_action_count()[Action::Delete], 1);
+        assert_eq!(folder.resolve_duplicates(DuplicateResolution::KeepLargest, None).await, 2);
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+
+        // Deletes aren't enabled so the user has to confirm them
+        let actions = get_file_actions(&folder.get_files().await);
+        let deleted: Vec<(&str, bool)> = actions.iter()
+            .filter(|(_, action, _)| *action == Action::Delete)
+            .map(|(src, _, is_enabled)| (src.as_str(), *is_enabled))
+            .collect();
+        assert_eq!(deleted, vec![("show.s01e01.mkv", false), ("show.s01e02.720p.mkv", false), ("show.s01e02.repack.mkv", false)]);
+        assert!(actions.iter().any(|(src, action, _)| src == "show.s01e01.srt" && *action == Action::Rename));
+    }
+
+    #[tokio::test]
+    async fn keep_newest_deletes_older_duplicates() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "large"), ("show.s01e01.repack.mkv", "")];
+        let folder_path = root.path().join("Show");
+        std::fs::create_dir_all(&folder_path).unwrap();
+        for (index, (file, data)) in files.iter().enumerate() {
+            let path = folder_path.join(file);
+            std::fs::write(&path, data).unwrap();
+            filetime::set_file_mtime(&path, filetime::FileTime::from_unix_time(1_700_000_000 + index as i64 * 60, 0)).unwrap();
+        }
+        let folder = new_scanned_folder(root.path(), &[], json!({})).await;
+        assert_eq!(folder.get_duplicate_groups().read().await.len(), 1);
+
+        assert_eq!(folder.resolve_duplicates(DuplicateResolution::KeepNewest, None).await, 1);
+        let actions = get_file_actions(&folder.get_files().await);
+        assert_eq!(actions.iter().map(|(src, action, _)| (src.as_str(), *action)).collect::<Vec<_>>(), vec![
+            ("show.s01e01.mkv", Action::Delete),
+            ("show.s01e01.repack.mkv", Action::Rename),
+        ]);
+        // Resolving again doesn't queue the same delete twice
+        assert_eq!(folder.resolve_duplicates(DuplicateResolution::KeepNewest, None).await, 0);
+    }
+
+    async fn get_sorted_sources(folder: &AppFolder) -> Vec<String> {
+        let mut sources: Vec<String> = folder.get_files().await.to_iter().map(|file| file.get_src().to_string()).collect();
+        sources.sort();
+        sources
+    }
+
+    // Files outside of the series folder which are linked to from inside it
+    #[cfg(unix)]
+    fn create_linked_folder(root: &path::Path) -> path::PathBuf {
+        let outside_path = root.join("Outside");
+        create_files(outside_path.as_path(), &["outside.s01e02.mkv", "Nested/outside.s01e03.mkv"]);
+        let folder_path = root.join("Show");
+        std::fs::create_dir_all(folder_path.join("Extras")).unwrap();
+        std::os::unix::fs::symlink(&outside_path, folder_path.join("Linked")).unwrap();
+        std::os::unix::fs::symlink(outside_path.join("outside.s01e02.mkv"), folder_path.join("linked.s01e02.mkv")).unwrap();
+        std::os::unix::fs::symlink(&outside_path, folder_path.join("Extras/Linked")).unwrap();
+        // Following this would never end without tracking visited folders
+        std::os::unix::fs::symlink(&folder_path, folder_path.join("Extras/Loop")).unwrap();
+        outside_path
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn linked_files_and_folders_are_skipped_by_default() {
+        let root = tempfile::tempdir().unwrap();
+        create_linked_folder(root.path());
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", ""), ("Extras/release.nfo", "")], json!({})).await;
+        assert_eq!(get_sorted_sources(&folder).await, vec!["Extras/release.nfo", "show.s01e01.mkv"]);
+        assert!(folder.get_errors().read().await.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn linked_folders_are_searched_once_if_followed() {
+        let root = tempfile::tempdir().unwrap();
+        create_linked_folder(root.path());
+        let folder_path = root.path().join("Show");
+        std::os::unix::fs::symlink(root.path().join("Missing"), folder_path.join("Broken")).unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "")], json!({ "follow_symlinks": true })).await;
+        // The outside folder is linked twice but its files are only listed under the first link found
+        let sources = get_sorted_sources(&folder).await;
+        assert!(sources.contains(&"linked.s01e02.mkv".to_string()), "{:?}", sources);
+        assert!(sources.contains(&"show.s01e01.mkv".to_string()), "{:?}", sources);
+        assert_eq!(sources.iter().filter(|src| src.ends_with("outside.s01e03.mkv")).count(), 1, "{:?}", sources);
+        assert!(sources.iter().all(|src| !src.starts_with("Extras/Loop")), "{:?}", sources);
+
+        let warnings = get_error_messages(&folder, Severity::Warning).await;
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("Couldn't follow link"), "{}", warnings[0]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn deleting_link_keeps_its_target() {
+        let root = tempfile::tempdir().unwrap();
+        let outside_path = create_linked_folder(root.path());
+        let folder_path = root.path().join("Show");
+        assert!(delete_path(folder_path.join("Linked"), false).await.unwrap().is_none());
+        assert!(delete_path(folder_path.join("linked.s01e02.mkv"), false).await.unwrap().is_none());
+        assert!(std::fs::symlink_metadata(folder_path.join("Linked")).is_err());
+        assert!(std::fs::symlink_metadata(folder_path.join("linked.s01e02.mkv")).is_err());
+        assert!(outside_path.join("outside.s01e02.mkv").is_file());
+        assert!(outside_path.join("Nested/outside.s01e03.mkv").is_file());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn empty_folder_cleanup_doesnt_traverse_links() {
+        let root = tempfile::tempdir().unwrap();
+        let outside_path = create_linked_folder(root.path());
+        let folder_path = root.path().join("Show");
+        // This folder only has a link to an empty folder which is still a file to keep
+        std::fs::create_dir_all(root.path().join("Empty")).unwrap();
+        std::fs::create_dir_all(folder_path.join("Links")).unwrap();
+        std::os::unix::fs::symlink(root.path().join("Empty"), folder_path.join("Links/Empty")).unwrap();
+        let files = [("show.s01e01.mkv", ""), ("Extras/release.nfo", ""), ("Removed/release.nfo", "")];
+        let rules = json!({ "use_trash": false, "blacklist_extensions": ["nfo"], "delete_empty_folders": true });
+        let folder = new_scanned_folder(root.path(), &files, rules).await;
+        folder.get_mut_files().await.set_enabled_where(|file| file.get_action() == Action::Delete, true);
+        folder.flush_file_changes().await;
+
+        let plan = folder.plan_file_changes().await;
+        let removed_dirs: Vec<&str> = plan.iter()
+            .filter_map(|operation| match operation {
+                PlannedOperation::RemoveEmptyDir { path } => Some(path.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(removed_dirs, vec!["Removed"]);
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!((summary.deleted, summary.removed_dirs, summary.failed), (2, 1, 0));
+        assert!(!folder_exists(&folder, "Removed"));
+        assert!(std::fs::symlink_metadata(folder_path.join("Links/Empty")).unwrap().file_type().is_symlink());
+        assert!(std::fs::symlink_metadata(folder_path.join("Extras/Linked")).unwrap().file_type().is_symlink());
+        assert!(std::fs::symlink_metadata(folder_path.join("Linked")).unwrap().file_type().is_symlink());
+        assert!(root.path().join("Empty").is_dir());
+        assert!(outside_path.join("outside.s01e02.mkv").is_file());
+        assert!(outside_path.join("Nested/outside.s01e03.mkv").is_file());
+    }
+
+    #[tokio::test]
+    async fn rescan_follows_execution() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("show.s01e02.mkv", "2")];
+        let mut folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        disable_all_files(&folder).await;
+        set_renames(&folder, &[("show.s01e01.mkv", "Season 01/Show-S01E01-Pilot.mkv")]).await;
+        let (sender, mut receiver) = broadcast::channel(16);
+        folder.set_change_sender(sender);
+
+        let summary = folder.execute_file_changes_and_rescan().await;
+        assert_eq!((summary.renamed, summary.failed), (1, 0));
+        // The rescan only starts once the execution has released the folder
+        let kinds: Vec<ChangeKind> = get_change_events(&mut receiver).into_iter().map(|event| event.kind).collect();
+        assert_eq!(kinds[..3], [ChangeKind::Busy, ChangeKind::Busy, ChangeKind::Busy]);
+        assert_eq!(kinds.iter().position(|kind| *kind == ChangeKind::Files), Some(3));
+        assert_eq!(kinds.last(), Some(&ChangeKind::Busy));
+
+        // The file list matches the disk after execution
+        let files = folder.get_files().await;
+        let mut states: Vec<(String, Action)> = files.to_iter().map(|file| (file.get_src().to_string(), file.get_action())).collect();
+        states.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(states, vec![
+            ("Season 01/Show-S01E01-Pilot.mkv".to_string(), Action::Complete),
+            ("show.s01e02.mkv".to_string(), Action::Rename),
+        ]);
+        drop(files);
+        assert_eq!(folder.get_last_execution_records().read().await.len(), 1);
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+    }
+
+    #[tokio::test]
+    async fn execution_without_rescan_keeps_previous_files() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "1")], json!({})).await;
+        set_renames(&folder, &[("show.s01e01.mkv", "Season 01/Show-S01E01-Pilot.mkv")]).await;
+        let summary = folder.execute_file_changes().await;
+        assert_eq!(summary.renamed, 1);
+        assert_eq!(get_sorted_sources(&folder).await, vec!["show.s01e01.mkv"]);
+
+        folder.update_file_intents().await.unwrap();
+        assert_eq!(get_sorted_sources(&folder).await, vec!["Season 01/Show-S01E01-Pilot.mkv"]);
+    }
+
+    #[tokio::test]
+    async fn action_counts_are_only_read_when_available() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        // The counts of an unscanned folder would all be zero which isn't the same as having nothing to do
+        assert_eq!(folder.try_get_action_counts(), None);
+
+        let files = [("show.s01e01.mkv", ""), ("show.s01e02.mkv", ""), ("trailer.mkv", ""), ("release.nfo", "")];
+        let folder = new_scanned_folder(root.path(), &files, json!({ "blacklist_extensions": ["nfo"] })).await;
+        let counts = folder.try_get_action_counts().unwrap();
+        assert_eq!(
+            (counts.action_count[Action::Rename], counts.action_count[Action::Delete], counts.action_count[Action::Ignore]),
+            (2, 1, 1),
+        );
+        assert_eq!(counts.total_conflicts, 0);
+
+        // A folder being written to doesn't block the caller
+        let file_tracker = folder.file_tracker.write().await;
+        assert_eq!(folder.try_get_action_counts(), None);
+        drop(file_tracker);
+        assert!(folder.try_get_action_counts().is_some());
+    }
+
+    #[tokio::test]
+    async fn action_counts_follow_flushed_changes_and_conflicts() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", ""), ("show.s01e02.mkv", ""), ("trailer.mkv", "")];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        let dest = get_file_dest(&folder, "show.s01e01.mkv").await;
+        set_renames(&folder, &[("show.s01e02.mkv", dest.as_str()), ("trailer.mkv", dest.as_str())]).await;
+        set_file_action(&folder, get_file_index(&folder, "show.s01e01.mkv").await, Action::Delete).await;
+
+        let counts = folder.try_get_action_counts().unwrap();
+        assert_eq!((counts.action_count[Action::Rename], counts.action_count[Action::Delete]), (2, 1));
+        assert_eq!(counts.action_count[Action::Ignore], 0);
+        assert_eq!(counts.total_conflicts, 1);
+        assert_eq!(counts.total_conflicts, folder.get_files().await.get_file_tracker().get_conflicts().len());
+
+        disable_all_files(&folder).await;
+        assert_eq!(folder.try_get_action_counts().unwrap().total_conflicts, 0);
+    }
+
+    #[tokio::test]
+    async fn loaded_folder_without_optional_files_has_no_errors() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_cache_folder(root.path(), json!({}));
+        write_cache_file(path::Path::new(folder.get_folder_path()), "Show", &[(1, 1, Some("Pilot"))]);
+        folder.perform_initial_load(None).await.unwrap();
+        // Bookmarks, ignore lists and undo journals only exist once they are used
+        assert!(!folder.has_errors(), "{:?}", get_error_messages(&folder, Severity::Error).await);
+
+        std::fs::write(path::Path::new(folder.get_folder_path()).join(PATH_STR_BOOKMARKS), "{").unwrap();
+        assert!(folder.load_bookmarks_from_file().await.is_none());
+        assert!(folder.has_errors());
+    }
+
+    #[tokio::test]
+    async fn errors_are_checked_without_waiting_and_cleared() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = Arc::new(new_scanned_folder(root.path(), &[("show.s01e01.mkv", "")], json!({})).await);
+        assert!(!folder.has_errors());
+        folder.get_errors().write().await.push_warn("Cache", "Failed to refresh");
+        assert!(folder.has_errors());
+
+        // Errors being written to are only reported once the write finishes
+        let errors = folder.get_errors().write().await;
+        assert!(!folder.has_errors());
+        drop(errors);
+        assert!(folder.has_errors());
+
+        folder.clear_errors().await;
+        assert!(!folder.has_errors());
+        assert!(folder.get_errors().read().await.is_empty());
+
+        folder.get_errors().write().await.push_error("Execute", "Failed to rename");
+        tokio::task::spawn_blocking({
+            let folder = folder.clone();
+            move || folder.clear_errors_blocking()
+        }).await.unwrap();
+        assert!(!folder.has_errors());
+    }
+
+    #[tokio::test]
+    async fn bulk_action_applies_to_shown_files_only() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [
+            ("show.extras.s01e01.mkv", ""), ("Extras/extras.interview.mkv", "1"), ("Extras/extras.bloopers.mkv", "22"),
+            ("behind.the.scenes.mkv", "333"),
+        ];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        // Same filter as the basic list where a search only shows files from the selected action
+        let is_shown = |file: &ImmutableAppFile<'_>| file.get_action() == Action::Ignore && file.get_src().contains("extras");
+
+        let shown_indices: Vec<usize> = {
+            let files = folder.get_files().await;
+            files.get_file_tracker().get_action_indices(Action::Ignore)
+                .iter()
+                .copied()
+                .filter(|index| is_shown(&files.get(*index).unwrap()))
+                .collect()
+        };
+        assert_eq!(shown_indices.len(), 2);
+        let mut matched_indices = folder.get_mut_files().await.get_indices_where(is_shown);
+        matched_indices.sort();
+        assert_eq!(matched_indices, shown_indices);
+
+        let total_queued = folder.get_mut_files().await.set_action_where(is_shown, Action::Delete);
+        assert_eq!(total_queued, shown_indices.len());
+        folder.flush_file_changes().await;
+        // The rename matching the search isn't shown in the ignore list so it is left alone
+        assert_eq!(get_action_sources(&folder, Action::Delete).await, vec!["Extras/extras.bloopers.mkv", "Extras/extras.interview.mkv"]);
+        assert_eq!(get_action_sources(&folder, Action::Rename).await, vec!["show.extras.s01e01.mkv"]);
+        assert_eq!(get_action_sources(&folder, Action::Ignore).await, vec!["behind.the.scenes.mkv"]);
+        assert!(get_enabled_sources(&folder, Action::Delete).await.is_empty());
+        assert_eq!(folder.get_files().await.get_file_tracker().get_action_bytes()[Action::Delete], 3);
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+
+        // Nothing is shown in the ignore list anymore so applying it again changes nothing
+        assert_eq!(folder.get_mut_files().await.set_action_where(is_shown, Action::Delete), 0);
+        assert_eq!(folder.get_mut_files().await.change_queue_len(), 0);
+    }
+
+    #[tokio::test]
+    async fn bulk_action_without_matches_queues_nothing() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", ""), ("trailer.mkv", "")], json!({})).await;
+        let mut files = folder.get_mut_files().await;
+        assert_eq!(files.set_action_where(|file| file.get_src().contains("extras"), Action::Delete), 0);
+        assert_eq!(files.set_enabled_where(|_| false, true), 0);
+        assert_eq!(files.set_action_for_indices(&[5, 6], Action::Delete), 0);
+        assert!(files.get_indices_where(|_| false).is_empty());
+        assert_eq!(files.change_queue_len(), 0);
+        drop(files);
+        assert_eq!(folder.flush_file_changes().await, 0);
+    }
+
+    #[tokio::test]
+    async fn reassigned_episode_recomputes_destination() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("trailer.mkv", "22")];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        let index = get_file_index(&folder, "show.s01e01.mkv").await;
+
+        // The cached title is used for the destination
+        folder.reassign_episode(index, EpisodeKey { season: 1, episode: 2 }).await.unwrap();
+        {
+            let files = folder.get_files().await;
+            let file = files.get(index).unwrap();
+            assert_eq!(file.get_src_descriptor(), &Some(EpisodeKey { season: 1, episode: 2 }));
+            assert_eq!((file.get_action(), file.get_dest(), file.get_is_enabled()), (Action::Rename, "Season 01/Show-S01E02-Return.mkv", true));
+        }
+
+        // Episodes without a cached title are renamed without one
+        folder.reassign_episode(index, EpisodeKey { season: 2, episode: 5 }).await.unwrap();
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 02/Show-S02E05.mkv");
+
+        // Ignored files become renames once assigned
+        let index = get_file_index(&folder, "trailer.mkv").await;
+        folder.reassign_episode(index, EpisodeKey { season: 1, episode: 1 }).await.unwrap();
+        assert_eq!(get_enabled_sources(&folder, Action::Rename).await.len(), 2);
+        assert_eq!(get_file_dest(&folder, "trailer.mkv").await, "Season 01/Show-S01E01-Pilot.mkv");
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+        assert!(get_error_messages(&folder, Severity::Warning).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reassigned_episode_needs_loaded_cache_and_file() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "")], json!({})).await;
+        let key = EpisodeKey { season: 1, episode: 2 };
+        assert!(folder.reassign_episode(5, key).await.is_none());
+
+        *folder.cache.write().await = None;
+        assert!(folder.reassign_episode(0, key).await.is_none());
+        assert_eq!(get_error_messages(&folder, Severity::Warning).await, vec!["Couldn't assign episode since cache is unloaded"]);
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 01/Show-S01E01-Pilot.mkv");
+    }
+
+    #[tokio::test]
+    async fn ignore_list_changes_wait_for_busy_folder() {
+        let root = tempfile::tempdir().unwrap();
+        let mut folder = new_scanned_folder(root.path(), &[("show.s01e01.mkv", "")], json!({})).await;
+        let (sender, mut receiver) = broadcast::channel(16);
+        folder.set_change_sender(sender);
+
+        {
+            let _busy_lock = folder.get_busy_lock().lock().await;
+            let res = tokio::time::timeout(std::time::Duration::from_millis(50), folder.add_to_ignore_list("show.s01e01.mkv")).await;
+            assert!(res.is_err());
+            assert!(folder.get_ignored_files().read().await.is_empty());
+            assert_eq!(get_file_action(&folder, "show.s01e01.mkv").await, Action::Rename);
+        }
+
+        folder.add_to_ignore_list("show.s01e01.mkv").await.unwrap();
+        assert_eq!(get_file_action(&folder, "show.s01e01.mkv").await, Action::Ignore);
+        let kinds: Vec<ChangeKind> = get_change_events(&mut receiver).into_iter().map(|event| event.kind).collect();
+        assert_eq!(kinds.first(), Some(&ChangeKind::Busy));
+        assert_eq!(kinds.last(), Some(&ChangeKind::Busy));
+    }
+
+    #[tokio::test]
+    async fn unignored_file_keeps_assigned_episode() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", ""), ("show.s01e01e02.mkv", "")];
+        let folder = new_scanned_folder(root.path(), &files, json!({})).await;
+        let index = get_file_index(&folder, "show.s01e01.mkv").await;
+        folder.reassign_episode(index, EpisodeKey { season: 1, episode: 2 }).await.unwrap();
+
+        for src in ["show.s01e01.mkv", "show.s01e01e02.mkv"] {
+            folder.add_to_ignore_list(src).await.unwrap();
+            assert_eq!(get_file_action(&folder, src).await, Action::Ignore);
+            folder.remove_from_ignore_list(src).await.unwrap();
+        }
+        assert_eq!(get_file_dest(&folder, "show.s01e01.mkv").await, "Season 01/Show-S01E02-Return.mkv");
+        // Parsed episode ranges are kept
+        assert_eq!(get_file_dest(&folder, "show.s01e01e02.mkv").await, "Season 01/Show-S01E01-E02-Pilot.Return.mkv");
+        assert_eq!(get_enabled_sources(&folder, Action::Rename).await.len(), 2);
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+    }
+
+    #[tokio::test]
+    async fn organize_executes_renames_and_keeps_deletes() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("show.s01e02.mkv", "2"), ("release.nfo", "3")];
+        let mut folder = new_scanned_folder(root.path(), &files, json!({ "blacklist_extensions": ["nfo"], "use_trash": false })).await;
+        disable_all_files(&folder).await;
+        let (sender, mut receiver) = broadcast::channel(64);
+        folder.set_change_sender(sender);
+
+        let summary = folder.organize(false).await.unwrap();
+        assert_eq!((summary.renamed, summary.deleted, summary.failed), (2, 0, 0));
+        assert_eq!(read_folder_file(&folder, "Season 01/Show-S01E01-Pilot.mkv").as_deref(), Some("1"));
+        assert_eq!(read_folder_file(&folder, "Season 01/Show-S01E02-Return.mkv").as_deref(), Some("2"));
+        assert_eq!(read_folder_file(&folder, "release.nfo").as_deref(), Some("3"));
+        // The folder is locked once to enable and execute the renames and once more to rescan
+        let total_busy_events = get_change_events(&mut receiver).iter().filter(|event| event.kind == ChangeKind::Busy).count();
+        assert_eq!(total_busy_events, 4);
+        assert_eq!(get_action_sources(&folder, Action::Delete).await, vec!["release.nfo"]);
+        assert_eq!(get_action_sources(&folder, Action::Complete).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn organize_skips_conflicts_on_disk_if_asked() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", "1"), ("Show.S01E01.mkv", "2"), ("show.s01e02.mkv", "3"), ("release.nfo", "4")];
+        let folder = new_scanned_folder(root.path(), &files, json!({ "blacklist_extensions": ["nfo"], "use_trash": false })).await;
+        disable_all_files(&folder).await;
+
+        // Nothing is executed if there are conflicts
+        assert!(folder.organize(false).await.is_none());
+        for (file, data) in files {
+            assert_eq!(read_folder_file(&folder, file).as_deref(), Some(data));
+        }
+
+        let summary = folder.organize(true).await.unwrap();
+        assert_eq!((summary.renamed, summary.deleted, summary.failed), (1, 0, 0));
+        assert_eq!(read_folder_file(&folder, "Season 01/Show-S01E02-Return.mkv").as_deref(), Some("3"));
+        for (file, data) in [("show.s01e01.mkv", "1"), ("Show.S01E01.mkv", "2"), ("release.nfo", "4")] {
+            assert_eq!(read_folder_file(&folder, file).as_deref(), Some(data));
+        }
+        assert!(read_folder_file(&folder, "Season 01/Show-S01E01-Pilot.mkv").is_none());
+    }
+
+    #[tokio::test]
+    async fn cancelled_organize_disables_its_renames() {
+        let root = tempfile::tempdir().unwrap();
+        let files = [("show.s01e01.mkv", ""), ("show.s01e02.mkv", ""), ("release.nfo", "")];
+        let folder = new_scanned_folder(root.path(), &files, json!({ "blacklist_extensions": ["nfo"] })).await;
+        disable_all_files(&folder).await;
+        folder.get_mut_files().await.set_enabled_where(|file| file.get_src() != "show.s01e02.mkv", true);
+        folder.flush_file_changes().await;
+
+        let sources = folder.enable_organize_renames(false).await.unwrap();
+        assert_eq!(sources, vec!["show.s01e02.mkv"]);
+        folder.disable_organize_renames(sources.as_slice()).await;
+        // Files that were already enabled before organizing stay enabled
+        assert_eq!(get_enabled_sources(&folder, Action::Rename).await, vec!["show.s01e01.mkv"]);
+        assert_eq!(get_enabled_sources(&folder, Action::Delete).await, vec!["release.nfo"]);
+        assert_tracker_matches_files(folder.file_list.read().await.as_slice(), &*folder.file_tracker.read().await);
+    }
+}
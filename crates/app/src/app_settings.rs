@@ -0,0 +1,121 @@
+use serde;
+use serde_json;
+use thiserror;
+use crate::app_folder::FolderSortMode;
+use crate::atomic_write::atomic_write;
+
+const MAX_RECENT_ROOT_PATHS: usize = 10;
+
+// Settings that are changed while the app is running and remembered between launches
+// Any setting that is unset falls back to the value in app_config.json
+#[serde_with::skip_serializing_none]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppSettings {
+    // Most recently loaded root path is first
+    #[serde(default)]
+    pub recent_root_paths: Vec<String>,
+    pub use_trash: Option<bool>,
+    pub folder_scan_depth: Option<usize>,
+    // Unix timestamp in seconds of when updated series were last checked for
+    pub last_cache_refresh_time: Option<u64>,
+    pub folder_sort_mode: Option<FolderSortMode>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppSettingsError {
+    #[error("io error: {}", .0)]
+    IO(std::io::Error),
+    #[error("json error: {}", .0)]
+    Json(serde_json::Error),
+}
+
+impl AppSettings {
+    pub fn get_last_root_path(&self) -> Option<&str> {
+        self.recent_root_paths.first().map(|path| path.as_str())
+    }
+
+    pub fn push_root_path(&mut self, root_path: &str) {
+        self.recent_root_paths.retain(|path| path != root_path);
+        self.recent_root_paths.insert(0, root_path.to_string());
+        self.recent_root_paths.truncate(MAX_RECENT_ROOT_PATHS);
+    }
+}
+
+// A missing settings file is expected on the first launch
+pub async fn load_settings_from_file(path: &str) -> Result<AppSettings, AppSettingsError> {
+    let data = match tokio::fs::read_to_string(path).await {
+        Ok(data) => data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(AppSettings::default()),
+        Err(err) => return Err(AppSettingsError::IO(err)),
+    };
+    serde_json::from_str(data.as_str()).map_err(AppSettingsError::Json)
+}
+
+// Written atomically so a failed write doesn't corrupt the existing settings
+pub async fn save_settings_to_file(path: &str, settings: &AppSettings) -> Result<(), AppSettingsError> {
+    let data = serde_json::to_string_pretty(settings).map_err(AppSettingsError::Json)?;
+    atomic_write(path, data).await.map_err(AppSettingsError::IO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_settings_path(dir: &tempfile::TempDir) -> String {
+        dir.path().join("app_settings.json").to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn settings_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = get_settings_path(&dir);
+        let mut settings = AppSettings {
+            use_trash: Some(false),
+            folder_scan_depth: Some(2),
+            last_cache_refresh_time: Some(1_700_000_000),
+            folder_sort_mode: Some(FolderSortMode::PendingDesc),
+            ..AppSettings::default()
+        };
+        settings.push_root_path("/media/shows");
+        settings.push_root_path("/media/anime");
+        save_settings_to_file(path.as_str(), &settings).await.unwrap();
+        assert_eq!(load_settings_from_file(path.as_str()).await.unwrap(), settings);
+    }
+
+    #[tokio::test]
+    async fn missing_file_gives_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = load_settings_from_file(get_settings_path(&dir).as_str()).await.unwrap();
+        assert_eq!(settings, AppSettings::default());
+        assert_eq!(settings.get_last_root_path(), None);
+    }
+
+    #[tokio::test]
+    async fn unset_fields_are_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = get_settings_path(&dir);
+        std::fs::write(path.as_str(), r#"{ "use_trash": true }"#).unwrap();
+        let settings = load_settings_from_file(path.as_str()).await.unwrap();
+        assert_eq!(settings, AppSettings { use_trash: Some(true), ..AppSettings::default() });
+    }
+
+    #[tokio::test]
+    async fn corrupt_file_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = get_settings_path(&dir);
+        std::fs::write(path.as_str(), "{").unwrap();
+        assert!(matches!(load_settings_from_file(path.as_str()).await, Err(AppSettingsError::Json(_))));
+    }
+
+    #[test]
+    fn recent_root_paths_are_most_recent_first_without_duplicates() {
+        let mut settings = AppSettings::default();
+        for index in 0..MAX_RECENT_ROOT_PATHS+2 {
+            settings.push_root_path(format!("/root{}", index).as_str());
+        }
+        settings.push_root_path("/root5");
+        assert_eq!(settings.recent_root_paths.len(), MAX_RECENT_ROOT_PATHS);
+        assert_eq!(settings.get_last_root_path(), Some("/root5"));
+        assert_eq!(settings.recent_root_paths.iter().filter(|path| *path == "/root5").count(), 1);
+    }
+}
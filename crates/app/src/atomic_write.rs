@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+use tokio;
+use tokio::io::AsyncWriteExt;
+
+pub const TEMPORARY_WRITE_SUFFIX: &str = ".tmp";
+#[cfg(windows)]
+const MAX_REPLACE_ATTEMPTS: u32 = 5;
+#[cfg(windows)]
+const REPLACE_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+
+// The temporary file is kept in the same directory so the rename doesn't cross devices
+pub fn get_temporary_write_path(path: &Path) -> PathBuf {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(TEMPORARY_WRITE_SUFFIX);
+    PathBuf::from(temporary_path)
+}
+
+async fn write_and_sync(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = tokio::fs::File::create(path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await
+}
+
+#[cfg(not(windows))]
+async fn replace_file(src: &Path, dest: &Path) -> std::io::Result<()> {
+    tokio::fs::rename(src, dest).await?;
+    // Persist the rename itself, this isn't supported by every filesystem so failures are ignored
+    if let Some(parent) = dest.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        if let Ok(dir) = tokio::fs::File::open(parent).await {
+            let _ = dir.sync_all().await;
+        }
+    }
+    Ok(())
+}
+
+// Renaming over an existing file fails while another process such as a virus scanner has it open
+// Retry a few times before removing the destination so the rename can't be blocked by it
+#[cfg(windows)]
+async fn replace_file(src: &Path, dest: &Path) -> std::io::Result<()> {
+    let mut attempt = 0;
+    loop {
+        match tokio::fs::rename(src, dest).await {
+            Ok(()) => return Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+                attempt += 1;
+                if attempt >= MAX_REPLACE_ATTEMPTS {
+                    break;
+                }
+                tokio::time::sleep(REPLACE_RETRY_DELAY).await;
+            },
+            Err(err) => return Err(err),
+        }
+    }
+    match tokio::fs::remove_file(dest).await {
+        Ok(()) => {},
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {},
+        Err(err) => return Err(err),
+    }
+    tokio::fs::rename(src, dest).await
+}
+
+// Either the previous or the new contents are left at the path, never a partially written file
+pub async fn atomic_write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let temporary_path = get_temporary_write_path(path);
+    let res = async {
+        write_and_sync(temporary_path.as_path(), contents.as_ref()).await?;
+        replace_file(temporary_path.as_path(), path).await
+    }.await;
+    if res.is_err() {
+        let _ = tokio::fs::remove_file(temporary_path.as_path()).await;
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn existing_file_is_replaced() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("cache.json");
+        std::fs::write(path.as_path(), "old contents that are longer").unwrap();
+        atomic_write(path.as_path(), "new").await.unwrap();
+        assert_eq!(std::fs::read_to_string(path.as_path()).unwrap(), "new");
+        assert!(!get_temporary_write_path(path.as_path()).exists());
+    }
+
+    #[tokio::test]
+    async fn failed_write_keeps_previous_contents() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("cache.json");
+        std::fs::write(path.as_path(), "old").unwrap();
+        // The temporary file can't be created where a folder already is
+        std::fs::create_dir(get_temporary_write_path(path.as_path())).unwrap();
+        assert!(atomic_write(path.as_path(), "new").await.is_err());
+        assert_eq!(std::fs::read_to_string(path.as_path()).unwrap(), "old");
+    }
+
+    #[tokio::test]
+    async fn failed_replace_removes_temporary_file() {
+        let folder = tempfile::tempdir().unwrap();
+        // A file can't be renamed over a folder that isn't empty
+        let path = folder.path().join("cache.json");
+        std::fs::create_dir(path.as_path()).unwrap();
+        std::fs::write(path.join("inner"), "").unwrap();
+        assert!(atomic_write(path.as_path(), "new").await.is_err());
+        assert!(path.join("inner").exists());
+        assert!(!get_temporary_write_path(path.as_path()).exists());
+    }
+
+    #[tokio::test]
+    async fn interrupted_write_leaves_previous_contents() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("bookmarks.json");
+        std::fs::write(path.as_path(), r#"[{"id": "a.mkv"}]"#).unwrap();
+        // A crash before the rename leaves a partial temporary file behind
+        std::fs::write(get_temporary_write_path(path.as_path()), r#"[{"id": "#).unwrap();
+        assert_eq!(std::fs::read_to_string(path.as_path()).unwrap(), r#"[{"id": "a.mkv"}]"#);
+
+        // The next write replaces it
+        atomic_write(path.as_path(), r#"[{"id": "b.mkv"}]"#).await.unwrap();
+        assert_eq!(std::fs::read_to_string(path.as_path()).unwrap(), r#"[{"id": "b.mkv"}]"#);
+        assert!(!get_temporary_write_path(path.as_path()).exists());
+    }
+
+    #[tokio::test]
+    async fn readers_never_see_partial_contents() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("cache.json");
+        let contents = ["a".repeat(1 << 20), "b".repeat(1 << 19)];
+        std::fs::write(path.as_path(), contents[0].as_str()).unwrap();
+
+        let is_done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let reader = std::thread::spawn({
+            let path = path.clone();
+            let contents = contents.clone();
+            let is_done = is_done.clone();
+            move || {
+                loop {
+                    let is_last_read = is_done.load(std::sync::atomic::Ordering::Relaxed);
+                    let data = std::fs::read_to_string(path.as_path()).unwrap();
+                    assert!(contents.contains(&data), "read {} bytes which is neither of the written files", data.len());
+                    if is_last_read {
+                        break;
+                    }
+                }
+            }
+        });
+        for index in 0..20 {
+            atomic_write(path.as_path(), contents[index % 2].as_str()).await.unwrap();
+        }
+        is_done.store(true, std::sync::atomic::Ordering::Relaxed);
+        reader.join().unwrap();
+    }
+}
@@ -1,7 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
+use chrono;
+use enum_map;
 use serde;
 use serde_json;
+use crate::tvdb_cache::EpisodeKey;
+
+// Episode bookmarks are stored in the same list as file bookmarks, i.e. "episode:S01E05"
+const EPISODE_ID_PREFIX: &str = "episode:";
 
 #[serde_with::skip_serializing_none]
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -10,41 +16,239 @@ struct BookmarkInternal {
     is_read: Option<bool>,
     is_unread: Option<bool>,
     is_favourite: Option<bool>,
+    tags: Option<Vec<String>>,
+    // RFC3339 timestamp of when the bookmark was last changed
+    updated_at: Option<String>,
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Bookmark {
     pub is_read: bool,
     pub is_unread: bool,
     pub is_favourite: bool,
+    pub tags: Vec<String>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl Bookmark {
-    fn is_any_selected(&self) -> bool {
-        self.is_read || self.is_unread || self.is_favourite
+    pub fn is_any_selected(&self) -> bool {
+        self.is_read || self.is_unread || self.is_favourite || !self.tags.is_empty()
+    }
+
+    // Should be called whenever a flag or tag is changed
+    pub fn touch(&mut self) {
+        self.updated_at = Some(chrono::Utc::now());
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|other| other == tag)
+    }
+
+    // Returns false if the tag is empty or already present
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = tag.trim();
+        if tag.is_empty() || self.has_tag(tag) {
+            return false;
+        }
+        self.tags.push(tag.to_owned());
+        self.touch();
+        true
+    }
+
+    // Keeps the flags and tags set on either bookmark
+    fn merge(&mut self, other: &Bookmark) {
+        self.is_read |= other.is_read;
+        self.is_unread |= other.is_unread;
+        self.is_favourite |= other.is_favourite;
+        for tag in &other.tags {
+            if !self.has_tag(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+        self.updated_at = self.updated_at.max(other.updated_at);
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let total_tags = self.tags.len();
+        self.tags.retain(|other| other != tag);
+        if self.tags.len() == total_tags {
+            return false;
+        }
+        self.touch();
+        true
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone, enum_map::Enum)]
+pub enum BookmarkFilter {
+    #[default]
+    All,
+    Any,
+    Favourite,
+    Unread,
+    Read,
+}
+
+impl BookmarkFilter {
+    pub fn iterator() -> std::slice::Iter<'static, Self> {
+        static FILTERS: [BookmarkFilter;5] = [
+            BookmarkFilter::All,
+            BookmarkFilter::Any,
+            BookmarkFilter::Favourite,
+            BookmarkFilter::Unread,
+            BookmarkFilter::Read,
+        ];
+        FILTERS.iter()
     }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            BookmarkFilter::All => "All",
+            BookmarkFilter::Any => "Bookmarked",
+            BookmarkFilter::Favourite => "Favourite",
+            BookmarkFilter::Unread => "Unread",
+            BookmarkFilter::Read => "Read",
+        }
+    }
+
+    // Files without a bookmark only show up when nothing is being filtered
+    pub fn is_match(&self, bookmark: Option<&Bookmark>) -> bool {
+        match (self, bookmark) {
+            (BookmarkFilter::All, _) => true,
+            (_, None) => false,
+            (BookmarkFilter::Any, Some(bookmark)) => bookmark.is_any_selected(),
+            (BookmarkFilter::Favourite, Some(bookmark)) => bookmark.is_favourite,
+            (BookmarkFilter::Unread, Some(bookmark)) => bookmark.is_unread,
+            (BookmarkFilter::Read, Some(bookmark)) => bookmark.is_read,
+        }
+    }
+}
+
+fn format_episode_id(key: &EpisodeKey) -> String {
+    format!("{}S{:02}E{:02}", EPISODE_ID_PREFIX, key.season, key.episode)
 }
 
+fn parse_episode_id(id: &str) -> Option<EpisodeKey> {
+    let id = id.strip_prefix(EPISODE_ID_PREFIX)?;
+    let (season, episode) = id.strip_prefix('S')?.split_once('E')?;
+    Some(EpisodeKey {
+        season: season.parse().ok()?,
+        episode: episode.parse().ok()?,
+    })
+}
+
+// Files with a known episode are bookmarked by episode so the bookmark is kept when the file is replaced
 pub struct BookmarkTable {
     bookmarks: HashMap<String, Bookmark>,
+    episode_bookmarks: HashMap<EpisodeKey, Bookmark>,
 }
 
 impl BookmarkTable {
     pub fn new() -> Self {
         Self {
             bookmarks: HashMap::new(),
+            episode_bookmarks: HashMap::new(),
         }
     }
 
+    // Doesn't insert an empty bookmark for files that were only viewed
+    pub fn get(&self, id: &str) -> Option<&Bookmark> {
+        self.bookmarks.get(id)
+    }
+
     pub fn get_mut_with_insert(&mut self, id: &str) -> &mut Bookmark {
-        self.bookmarks.entry(id.to_owned()).or_insert(Bookmark {
-            is_favourite: false,
-            is_unread: false,
-            is_read: false,
-        })
+        self.bookmarks.entry(id.to_owned()).or_default()
+    }
+
+    pub fn get_episode(&self, key: &EpisodeKey) -> Option<&Bookmark> {
+        self.episode_bookmarks.get(key)
+    }
+
+    pub fn get_episode_mut_with_insert(&mut self, key: &EpisodeKey) -> &mut Bookmark {
+        self.episode_bookmarks.entry(*key).or_default()
+    }
+
+    // Files without an episode only have a bookmark for their path
+    // Bookmarks made before episode bookmarks existed are still found by their path
+    pub fn get_for_file(&self, src: &str, descriptor: Option<&EpisodeKey>) -> Option<&Bookmark> {
+        descriptor
+            .and_then(|key| self.get_episode(key))
+            .or_else(|| self.get(src))
+    }
+
+    // The bookmark for the path is moved to the episode so changing it doesn't leave two bookmarks
+    pub fn get_for_file_mut_with_insert(&mut self, src: &str, descriptor: Option<&EpisodeKey>) -> &mut Bookmark {
+        let key = match descriptor {
+            Some(key) => key,
+            None => return self.get_mut_with_insert(src),
+        };
+        let file_bookmark = self.bookmarks.remove(src);
+        let bookmark = self.get_episode_mut_with_insert(key);
+        if let Some(file_bookmark) = file_bookmark {
+            bookmark.merge(&file_bookmark);
+        }
+        bookmark
+    }
+
+    // Only includes bookmarks for paths
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &Bookmark)> {
+        self.bookmarks.iter().map(|(id, bookmark)| (id.as_str(), bookmark))
+    }
+
+    pub fn iter_episodes(&self) -> impl Iterator<Item=(&EpisodeKey, &Bookmark)> {
+        self.episode_bookmarks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bookmarks.len() + self.episode_bookmarks.len()
+    }
+
+    // Bookmarks without any flags or tags aren't saved so they aren't counted
+    pub fn count_by(&self, mut is_match: impl FnMut(&Bookmark) -> bool) -> usize {
+        self.bookmarks
+            .values()
+            .chain(self.episode_bookmarks.values())
+            .filter(|bookmark| bookmark.is_any_selected())
+            .filter(|bookmark| is_match(bookmark))
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty() && self.episode_bookmarks.is_empty()
+    }
+
+    // Distinct tags across all bookmarks in sorted order for autocompletion
+    pub fn all_tags(&self) -> BTreeSet<String> {
+        self.bookmarks
+            .values()
+            .chain(self.episode_bookmarks.values())
+            .flat_map(|bookmark| bookmark.tags.iter())
+            .cloned()
+            .collect()
+    }
+
+    // Used when a file is renamed so its bookmark follows it
+    // A bookmark already at the new id keeps the flags and tags set on either of them
+    // Returns true if the moved bookmark had any flags that need to be saved
+    pub fn rename_key(&mut self, old_id: &str, new_id: &str) -> bool {
+        let old = match self.bookmarks.remove(old_id) {
+            Some(bookmark) => bookmark,
+            None => return false,
+        };
+        self.get_mut_with_insert(new_id).merge(&old);
+        old.is_any_selected()
+    }
+
+    // Returns the number of path bookmarks that were removed, episode bookmarks are always kept
+    pub fn retain(&mut self, mut is_keep: impl FnMut(&str) -> bool) -> usize {
+        let total_bookmarks = self.bookmarks.len();
+        self.bookmarks.retain(|id, _| is_keep(id.as_str()));
+        total_bookmarks - self.bookmarks.len()
     }
 
     pub fn clear(&mut self) {
         self.bookmarks.clear();
+        self.episode_bookmarks.clear();
     }
 }
 
@@ -59,13 +263,23 @@ pub fn deserialize_bookmarks(data: &str) -> Result<BookmarkTable, serde_json::Er
     
     let mut table = BookmarkTable::new();
     for bookmark in bookmarks {
+        let episode_key = parse_episode_id(bookmark.id.as_str());
         // Automatically convert Window's separators to Unix
         let id = bookmark.id.replace('\\', "/");
-        table.bookmarks.insert(id, Bookmark {
+        let bookmark = Bookmark {
             is_read: bookmark.is_read.unwrap_or(false),
             is_unread: bookmark.is_unread.unwrap_or(false),
             is_favourite: bookmark.is_favourite.unwrap_or(false),
-        });
+            tags: bookmark.tags.unwrap_or_default(),
+            // Invalid timestamps are dropped instead of failing to load all bookmarks
+            updated_at: bookmark.updated_at
+                .and_then(|updated_at| chrono::DateTime::parse_from_rfc3339(updated_at.as_str()).ok())
+                .map(|updated_at| updated_at.with_timezone(&chrono::Utc)),
+        };
+        match episode_key {
+            Some(key) => table.episode_bookmarks.insert(key, bookmark),
+            None => table.bookmarks.insert(id, bookmark),
+        };
     }
     Ok(table)
 }
@@ -73,18 +287,345 @@ pub fn deserialize_bookmarks(data: &str) -> Result<BookmarkTable, serde_json::Er
 pub fn serialize_bookmarks(table: &BookmarkTable) -> Result<String, serde_json::Error> {
     let mut bookmarks: Vec<BookmarkInternal> = Vec::new();
 
-    for (id, bookmark) in &table.bookmarks {
+    let file_bookmarks = table.bookmarks
+        .iter()
+        .map(|(id, bookmark)| (id.replace(std::path::MAIN_SEPARATOR_STR, "/"), bookmark));
+    let episode_bookmarks = table.episode_bookmarks
+        .iter()
+        .map(|(key, bookmark)| (format_episode_id(key), bookmark));
+
+    for (id, bookmark) in file_bookmarks.chain(episode_bookmarks) {
         if !bookmark.is_any_selected() {
             continue;
         }
 
         bookmarks.push(BookmarkInternal {
-            id: id.replace(std::path::MAIN_SEPARATOR_STR, "/"),
+            id,
             is_favourite: if bookmark.is_favourite { Some(true) } else { None },
             is_unread: if bookmark.is_unread { Some(true) } else { None },
             is_read: if bookmark.is_read { Some(true) } else { None },
+            tags: if !bookmark.tags.is_empty() { Some(bookmark.tags.clone()) } else { None },
+            updated_at: bookmark.updated_at.map(|updated_at| updated_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
         })
     }
 
     serde_json::to_string_pretty(&bookmarks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renamed_bookmark_follows_file() {
+        let mut table = BookmarkTable::new();
+        table.get_mut_with_insert("show.s01e01.mkv").is_favourite = true;
+        assert!(table.rename_key("show.s01e01.mkv", "Season 01/Show-S01E01.mkv"));
+        assert!(table.get("show.s01e01.mkv").is_none());
+        assert!(table.get("Season 01/Show-S01E01.mkv").unwrap().is_favourite);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn renaming_file_without_bookmark_inserts_nothing() {
+        let mut table = BookmarkTable::new();
+        assert!(!table.rename_key("a.mkv", "b.mkv"));
+        assert!(table.is_empty());
+        // Bookmarks without flags are moved but don't need saving
+        table.get_mut_with_insert("a.mkv");
+        assert!(!table.rename_key("a.mkv", "b.mkv"));
+        assert!(table.get("b.mkv").is_some());
+    }
+
+    #[test]
+    fn renamed_bookmark_merges_with_existing() {
+        let mut table = BookmarkTable::new();
+        let old = table.get_mut_with_insert("a.mkv");
+        old.is_read = true;
+        old.tags = vec!["rewatch".to_string(), "shared".to_string()];
+        let existing = table.get_mut_with_insert("b.mkv");
+        existing.is_favourite = true;
+        existing.tags = vec!["shared".to_string()];
+
+        assert!(table.rename_key("a.mkv", "b.mkv"));
+        let bookmark = table.get("b.mkv").unwrap();
+        assert!(bookmark.is_read && bookmark.is_favourite && !bookmark.is_unread);
+        assert_eq!(bookmark.tags, vec!["shared", "rewatch"]);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn lookup_doesnt_insert() {
+        let mut table = BookmarkTable::new();
+        table.get_mut_with_insert("a.mkv").is_unread = true;
+        assert!(table.get("b.mkv").is_none());
+        assert!(table.get_episode(&EpisodeKey { season: 1, episode: 1 }).is_none());
+        assert!(table.get_for_file("c.mkv", Some(&EpisodeKey { season: 1, episode: 2 })).is_none());
+        assert!(table.get("a.mkv").unwrap().is_unread);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn viewed_files_dont_grow_saved_bookmarks() {
+        let mut table = BookmarkTable::new();
+        table.get_mut_with_insert("a.mkv").is_favourite = true;
+        let data = serialize_bookmarks(&table).unwrap();
+
+        // Viewing a list only looks up each file
+        for src in ["a.mkv", "b.mkv", "c.mkv"] {
+            let _ = BookmarkFilter::Favourite.is_match(table.get(src));
+        }
+        assert_eq!(serialize_bookmarks(&table).unwrap(), data);
+        // Empty bookmarks from older versions aren't saved either
+        table.get_mut_with_insert("d.mkv");
+        assert_eq!(serialize_bookmarks(&table).unwrap(), data);
+        assert_eq!(deserialize_bookmarks(data.as_str()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn filter_matches_bookmark_state() {
+        let unread = Bookmark { is_unread: true, ..Bookmark::default() };
+        assert!(BookmarkFilter::All.is_match(None));
+        assert!(!BookmarkFilter::Any.is_match(None));
+        assert!(!BookmarkFilter::Any.is_match(Some(&Bookmark::default())));
+        assert!(BookmarkFilter::Any.is_match(Some(&unread)));
+        assert!(BookmarkFilter::Unread.is_match(Some(&unread)));
+        assert!(!BookmarkFilter::Read.is_match(Some(&unread)));
+        assert!(!BookmarkFilter::Favourite.is_match(Some(&unread)));
+    }
+
+    #[test]
+    fn tags_round_trip() {
+        let mut table = BookmarkTable::new();
+        let bookmark = table.get_mut_with_insert("a.mkv");
+        assert!(bookmark.add_tag(" rewatch "));
+        assert!(bookmark.add_tag("bad-audio"));
+        // Blank and repeated tags aren't added
+        assert!(!bookmark.add_tag("   "));
+        assert!(!bookmark.add_tag("rewatch"));
+        table.get_episode_mut_with_insert(&EpisodeKey { season: 1, episode: 2 }).add_tag("duplicate");
+
+        let data = serialize_bookmarks(&table).unwrap();
+        let loaded = deserialize_bookmarks(data.as_str()).unwrap();
+        assert_eq!(loaded.get("a.mkv").unwrap().tags, vec!["rewatch", "bad-audio"]);
+        assert_eq!(loaded.get_episode(&EpisodeKey { season: 1, episode: 2 }).unwrap().tags, vec!["duplicate"]);
+    }
+
+    #[test]
+    fn bookmarks_without_tags_are_backwards_compatible() {
+        // Saved before tags were added
+        let data = r#"[{"id": "a.mkv", "is_favourite": true}, {"id": "b.mkv", "is_read": true, "tags": []}]"#;
+        let table = deserialize_bookmarks(data).unwrap();
+        assert!(table.get("a.mkv").unwrap().tags.is_empty());
+        assert!(table.get("b.mkv").unwrap().tags.is_empty());
+        let data = serialize_bookmarks(&table).unwrap();
+        assert!(!data.contains("tags"));
+    }
+
+    #[test]
+    fn removed_tag_is_no_longer_saved() {
+        let mut table = BookmarkTable::new();
+        let bookmark = table.get_mut_with_insert("a.mkv");
+        bookmark.add_tag("rewatch");
+        assert!(bookmark.remove_tag("rewatch"));
+        assert!(!bookmark.remove_tag("rewatch"));
+        // A bookmark that only had the tag isn't saved anymore
+        assert_eq!(serialize_bookmarks(&table).unwrap(), "[]");
+    }
+
+    #[test]
+    fn all_tags_are_distinct_and_sorted() {
+        let mut table = BookmarkTable::new();
+        table.get_mut_with_insert("a.mkv").tags = vec!["rewatch".to_string(), "bad-audio".to_string()];
+        table.get_mut_with_insert("b.mkv").tags = vec!["rewatch".to_string()];
+        table.get_episode_mut_with_insert(&EpisodeKey { season: 1, episode: 1 }).tags = vec!["duplicate".to_string(), "bad-audio".to_string()];
+        let tags: Vec<String> = table.all_tags().into_iter().collect();
+        assert_eq!(tags, vec!["bad-audio", "duplicate", "rewatch"]);
+        assert!(BookmarkTable::new().all_tags().is_empty());
+    }
+
+    fn get_fixed_time() -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339("2024-03-10T12:30:45Z").unwrap().to_utc()
+    }
+
+    #[test]
+    fn timestamps_round_trip() {
+        let mut table = BookmarkTable::new();
+        let bookmark = table.get_mut_with_insert("a.mkv");
+        bookmark.is_unread = true;
+        bookmark.updated_at = Some(get_fixed_time());
+        table.get_mut_with_insert("b.mkv").is_read = true;
+
+        let data = serialize_bookmarks(&table).unwrap();
+        assert!(data.contains(r#""updated_at": "2024-03-10T12:30:45Z""#), "{}", data);
+        let loaded = deserialize_bookmarks(data.as_str()).unwrap();
+        assert_eq!(loaded.get("a.mkv").unwrap().updated_at, Some(get_fixed_time()));
+        assert_eq!(loaded.get("b.mkv").unwrap().updated_at, None);
+    }
+
+    #[test]
+    fn missing_or_invalid_timestamps_load_as_none() {
+        let data = r#"[
+            {"id": "a.mkv", "is_unread": true},
+            {"id": "b.mkv", "is_unread": true, "updated_at": "last tuesday"},
+            {"id": "c.mkv", "is_unread": true, "updated_at": "2024-03-10T14:30:45+02:00"}
+        ]"#;
+        let table = deserialize_bookmarks(data).unwrap();
+        assert_eq!(table.get("a.mkv").unwrap().updated_at, None);
+        assert_eq!(table.get("b.mkv").unwrap().updated_at, None);
+        // Converted to utc
+        assert_eq!(table.get("c.mkv").unwrap().updated_at, Some(get_fixed_time()));
+        assert!(table.get("b.mkv").unwrap().is_unread);
+    }
+
+    #[test]
+    fn changing_tags_touches_bookmark() {
+        let mut bookmark = Bookmark::default();
+        assert!(!bookmark.add_tag(""));
+        assert_eq!(bookmark.updated_at, None);
+        let before = chrono::Utc::now();
+        assert!(bookmark.add_tag("rewatch"));
+        assert!(bookmark.updated_at.is_some_and(|updated_at| updated_at >= before));
+
+        bookmark.updated_at = Some(get_fixed_time());
+        assert!(!bookmark.remove_tag("missing"));
+        assert_eq!(bookmark.updated_at, Some(get_fixed_time()));
+        assert!(bookmark.remove_tag("rewatch"));
+        assert!(bookmark.updated_at.is_some_and(|updated_at| updated_at > get_fixed_time()));
+    }
+
+    #[test]
+    fn reading_bookmarks_doesnt_touch_them() {
+        let mut table = BookmarkTable::new();
+        let bookmark = table.get_mut_with_insert("a.mkv");
+        bookmark.is_favourite = true;
+        bookmark.tags = vec!["rewatch".to_string()];
+        bookmark.updated_at = Some(get_fixed_time());
+
+        let _ = table.get("a.mkv");
+        let _ = table.get_for_file("a.mkv", None);
+        let _ = table.count_by(|bookmark| bookmark.is_favourite);
+        let _ = table.iter().count();
+        let _ = table.all_tags();
+        let _ = serialize_bookmarks(&table).unwrap();
+        assert_eq!(table.get("a.mkv").unwrap().updated_at, Some(get_fixed_time()));
+    }
+
+    fn new_counted_table() -> BookmarkTable {
+        let mut table = BookmarkTable::new();
+        table.get_mut_with_insert("a.mkv").is_favourite = true;
+        let bookmark = table.get_mut_with_insert("b.mkv");
+        bookmark.is_favourite = true;
+        bookmark.is_unread = true;
+        table.get_mut_with_insert("c.mkv").is_read = true;
+        // Left behind by older versions when a file was only viewed
+        table.get_mut_with_insert("d.mkv");
+        table.get_episode_mut_with_insert(&EpisodeKey { season: 1, episode: 1 }).is_unread = true;
+        table.get_episode_mut_with_insert(&EpisodeKey { season: 1, episode: 2 });
+        table
+    }
+
+    #[test]
+    fn iter_only_lists_path_bookmarks() {
+        let table = new_counted_table();
+        let mut ids: Vec<&str> = table.iter().map(|(id, _)| id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["a.mkv", "b.mkv", "c.mkv", "d.mkv"]);
+        assert_eq!(table.iter_episodes().count(), 2);
+        assert_eq!(table.len(), 6);
+        assert!(!table.is_empty());
+    }
+
+    #[test]
+    fn counts_skip_bookmarks_without_flags() {
+        let table = new_counted_table();
+        assert_eq!(table.count_by(|bookmark| bookmark.is_favourite), 2);
+        assert_eq!(table.count_by(|bookmark| bookmark.is_unread), 2);
+        assert_eq!(table.count_by(|bookmark| bookmark.is_read), 1);
+        // Empty bookmarks would match this but aren't counted
+        assert_eq!(table.count_by(|_| true), 4);
+        assert_eq!(BookmarkTable::new().count_by(|_| true), 0);
+    }
+
+    #[test]
+    fn retain_keeps_episode_bookmarks() {
+        let mut table = new_counted_table();
+        assert_eq!(table.retain(|id| id == "a.mkv"), 3);
+        assert_eq!(table.iter().map(|(id, _)| id).collect::<Vec<_>>(), vec!["a.mkv"]);
+        assert_eq!(table.iter_episodes().count(), 2);
+
+        table.clear();
+        assert!(table.is_empty());
+        assert_eq!(table.len(), 0);
+    }
+
+    fn key(season: u32, episode: u32) -> EpisodeKey {
+        EpisodeKey { season, episode }
+    }
+
+    #[test]
+    fn episode_ids_are_routed_to_episodes() {
+        let data = r#"[
+            {"id": "episode:S01E05", "is_favourite": true},
+            {"id": "episode:S10E120", "is_read": true},
+            {"id": "episode:S1", "is_unread": true},
+            {"id": "Season 01/episode:S01E06.mkv", "is_unread": true},
+            {"id": "extras\\notes.txt", "is_read": true}
+        ]"#;
+        let table = deserialize_bookmarks(data).unwrap();
+        assert!(table.get_episode(&key(1, 5)).unwrap().is_favourite);
+        assert!(table.get_episode(&key(10, 120)).unwrap().is_read);
+        assert_eq!(table.iter_episodes().count(), 2);
+        // Ids that aren't a whole episode id are treated as paths
+        assert!(table.get("episode:S1").unwrap().is_unread);
+        assert!(table.get("Season 01/episode:S01E06.mkv").unwrap().is_unread);
+        assert!(table.get("extras/notes.txt").unwrap().is_read);
+    }
+
+    #[test]
+    fn episode_bookmarks_round_trip() {
+        let mut table = BookmarkTable::new();
+        table.get_episode_mut_with_insert(&key(1, 5)).is_favourite = true;
+        table.get_episode_mut_with_insert(&key(0, 1)).add_tag("special");
+        table.get_mut_with_insert("notes.txt").is_read = true;
+
+        let data = serialize_bookmarks(&table).unwrap();
+        assert!(data.contains(r#""id": "episode:S01E05""#));
+        assert!(data.contains(r#""id": "episode:S00E01""#));
+        let loaded = deserialize_bookmarks(data.as_str()).unwrap();
+        assert!(loaded.get_episode(&key(1, 5)).unwrap().is_favourite);
+        assert_eq!(loaded.get_episode(&key(0, 1)).unwrap().tags, vec!["special"]);
+        assert!(loaded.get("notes.txt").unwrap().is_read);
+        assert_eq!(loaded.len(), 3);
+    }
+
+    #[test]
+    fn file_lookup_prefers_episode_and_falls_back_to_path() {
+        let mut table = BookmarkTable::new();
+        table.get_mut_with_insert("old.release.s01e01.mkv").is_unread = true;
+        table.get_mut_with_insert("notes.txt").is_read = true;
+        table.get_episode_mut_with_insert(&key(1, 2)).is_favourite = true;
+
+        // Replacing the file with another release keeps the episode bookmark
+        assert!(table.get_for_file("new.release.s01e02.mkv", Some(&key(1, 2))).unwrap().is_favourite);
+        // Bookmarks made before episode bookmarks are still found by path
+        assert!(table.get_for_file("old.release.s01e01.mkv", Some(&key(1, 1))).unwrap().is_unread);
+        assert!(table.get_for_file("notes.txt", None).unwrap().is_read);
+        assert!(table.get_for_file("other.s01e03.mkv", Some(&key(1, 3))).is_none());
+    }
+
+    #[test]
+    fn changing_file_bookmark_moves_it_to_episode() {
+        let mut table = BookmarkTable::new();
+        table.get_mut_with_insert("show.s01e01.mkv").is_unread = true;
+        table.get_for_file_mut_with_insert("show.s01e01.mkv", Some(&key(1, 1))).is_favourite = true;
+        assert!(table.get("show.s01e01.mkv").is_none());
+        let bookmark = table.get_episode(&key(1, 1)).unwrap();
+        assert!(bookmark.is_unread && bookmark.is_favourite);
+
+        // Files without an episode keep using their path
+        table.get_for_file_mut_with_insert("notes.txt", None).is_read = true;
+        assert!(table.get("notes.txt").unwrap().is_read);
+        assert_eq!(table.len(), 2);
+    }
+}
@@ -0,0 +1,57 @@
+use tokio::sync::{broadcast, Mutex, MutexGuard};
+
+// Receivers that fall behind skip the oldest events and should treat this as a change
+pub const CHANGE_EVENT_CAPACITY: usize = 256;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ChangeKind {
+    // Folders were loaded, added or removed
+    Folders,
+    // File intents or the change queue were updated
+    Files,
+    Cache,
+    // An operation started or finished
+    Busy,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ChangeEvent {
+    // Paths are used instead of indices since adding or removing folders shifts the indices
+    // This is empty for changes to the folder list itself
+    pub folder_path: Option<String>,
+    pub kind: ChangeKind,
+}
+
+pub(crate) fn send_change(sender: Option<&broadcast::Sender<ChangeEvent>>, event: ChangeEvent) {
+    // Sending only fails when there are no receivers
+    if let Some(sender) = sender {
+        let _ = sender.send(event);
+    }
+}
+
+// Notifies when the lock is acquired and again after it is released
+pub(crate) struct BusyLockGuard<'a> {
+    lock: Option<MutexGuard<'a, ()>>,
+    sender: Option<&'a broadcast::Sender<ChangeEvent>>,
+    event: ChangeEvent,
+}
+
+impl<'a> BusyLockGuard<'a> {
+    pub(crate) async fn lock(lock: &'a Mutex<()>, sender: Option<&'a broadcast::Sender<ChangeEvent>>, event: ChangeEvent) -> Self {
+        let lock = lock.lock().await;
+        send_change(sender, event.clone());
+        Self {
+            lock: Some(lock),
+            sender,
+            event,
+        }
+    }
+}
+
+impl Drop for BusyLockGuard<'_> {
+    fn drop(&mut self) {
+        // Release before notifying so receivers don't see the lock as still being held
+        self.lock.take();
+        send_change(self.sender, self.event.clone());
+    }
+}
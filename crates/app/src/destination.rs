@@ -0,0 +1,269 @@
+use thiserror;
+
+// Characters which can't be used in a filename on Windows and what they are replaced with
+const RESERVED_CHARACTERS: [(char, Option<char>);7] = [
+    (':', Some('-')),
+    ('"', Some('\'')),
+    ('<', None),
+    ('>', None),
+    ('|', None),
+    ('?', None),
+    ('*', None),
+];
+
+// Device names on Windows which are reserved even with an extension, i.e. "CON" or "nul.txt"
+const RESERVED_NAMES: [&str;22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Windows limits a full path to 260 characters including the terminating null
+// Longer paths need a prefix that most programs don't support so these destinations are flagged instead
+const MAX_PATH_LENGTH: usize = 259;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PathError {
+    #[error("destination is empty")]
+    Empty,
+    #[error("destination has to be relative to the folder")]
+    Absolute,
+    #[error("destination can't be outside of the folder")]
+    OutsideFolder,
+    #[error("'{0}' is a reserved name")]
+    ReservedName(String),
+    #[error("'{0}' has characters that aren't allowed in a filename")]
+    InvalidCharacters(String),
+    #[error("destination is {0} characters over the path length limit")]
+    TooLong(usize),
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+fn is_absolute(path: &str) -> bool {
+    let mut chars = path.chars();
+    let is_drive = matches!((chars.next(), chars.next()), (Some(drive), Some(':')) if drive.is_ascii_alphabetic());
+    path.starts_with(is_separator) || is_drive
+}
+
+// The limit counts UTF-16 code units which is how Windows stores paths
+fn get_path_length(path: &str) -> usize {
+    path.encode_utf16().count()
+}
+
+// Destinations are joined onto the folder path with a separator
+pub fn get_max_destination_length(folder_path: &str) -> usize {
+    MAX_PATH_LENGTH.saturating_sub(get_path_length(folder_path) + 1)
+}
+
+pub fn is_reserved_name(component: &str) -> bool {
+    let stem = component.split('.').next().unwrap_or(component).trim_end();
+    RESERVED_NAMES.iter().any(|name| name.eq_ignore_ascii_case(stem))
+}
+
+// Windows drops trailing dots and spaces which would change the filename so they are removed
+pub fn sanitize_component(component: &str) -> String {
+    let sanitized: String = component
+        .chars()
+        .filter(|c| !c.is_control())
+        .filter_map(|c| match RESERVED_CHARACTERS.iter().find(|(reserved, _)| *reserved == c) {
+            Some((_, replacement)) => *replacement,
+            None => Some(c),
+        })
+        .collect();
+    sanitized.trim_end_matches(['.', ' ']).to_string()
+}
+
+// Destinations are relative to the folder and use '/' as the separator
+// Paths which can't be made valid by replacing characters are rejected
+pub fn sanitize_destination(path: &str) -> Result<String, PathError> {
+    if is_absolute(path) {
+        return Err(PathError::Absolute);
+    }
+
+    let mut components = Vec::<String>::new();
+    for component in path.split(is_separator) {
+        if component.is_empty() || component == "." {
+            continue;
+        }
+        if component.trim() == ".." {
+            return Err(PathError::OutsideFolder);
+        }
+        let sanitized = sanitize_component(component);
+        if sanitized.is_empty() {
+            return Err(PathError::InvalidCharacters(component.to_string()));
+        }
+        if is_reserved_name(sanitized.as_str()) {
+            return Err(PathError::ReservedName(sanitized));
+        }
+        components.push(sanitized);
+    }
+
+    if components.is_empty() {
+        return Err(PathError::Empty);
+    }
+    Ok(components.join("/"))
+}
+
+// User edited destinations aren't changed while they are being typed so they are only checked
+pub fn validate_destination(path: &str, max_length: usize) -> Result<(), PathError> {
+    let sanitized = sanitize_destination(path)?;
+    if sanitized != path {
+        let component = path
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .find(|component| sanitize_component(component) != *component)
+            .unwrap_or(path);
+        return Err(PathError::InvalidCharacters(component.to_string()));
+    }
+    let length = get_path_length(path);
+    if length > max_length {
+        return Err(PathError::TooLong(length - max_length));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_names_are_rejected_in_every_component() {
+        let paths = [
+            "CON.mkv",
+            "Season 01/nul.mkv",
+            "aux/Show-S01E01.mkv",
+            "Season 01/COM1/Show-S01E01.mkv",
+            "Season 01/Extras/lpt9.tar.gz",
+            "Season 01/prn ./Show-S01E01.mkv",
+        ];
+        for path in paths {
+            assert!(matches!(sanitize_destination(path), Err(PathError::ReservedName(_))), "{}", path);
+            assert!(matches!(validate_destination(path, usize::MAX), Err(PathError::ReservedName(_))), "{}", path);
+        }
+    }
+
+    #[test]
+    fn names_containing_reserved_names_are_allowed() {
+        for path in ["Season 01/CONAN.mkv", "COM10/Show.mkv", "Season 01/Show.CON.mkv", "Console/Show.mkv"] {
+            assert_eq!(sanitize_destination(path).as_deref(), Ok(path));
+        }
+    }
+
+    #[test]
+    fn max_destination_length_leaves_room_for_separator() {
+        let folder_path = "C:/Shows/Show";
+        assert_eq!(get_max_destination_length(folder_path), MAX_PATH_LENGTH - folder_path.len() - 1);
+        assert_eq!(get_max_destination_length("C:/".repeat(100).as_str()), 0);
+    }
+
+    #[test]
+    fn destination_over_path_limit_is_flagged() {
+        let dest = format!("Season 01/{}.mkv", "a".repeat(40));
+        let length = dest.len();
+        assert_eq!(validate_destination(dest.as_str(), length), Ok(()));
+        assert_eq!(validate_destination(dest.as_str(), length - 3), Err(PathError::TooLong(3)));
+        assert_eq!(validate_destination(dest.as_str(), 0), Err(PathError::TooLong(length)));
+    }
+
+    #[test]
+    fn path_length_counts_utf16_units() {
+        // Each character is 3 bytes in UTF-8 but a single UTF-16 unit
+        let dest = "Season 01/日本語.mkv";
+        assert_eq!(validate_destination(dest, 17), Ok(()));
+        assert_eq!(validate_destination(dest, 16), Err(PathError::TooLong(1)));
+        // Characters outside the basic plane take two units
+        assert_eq!(validate_destination("🎬.mkv", 5), Err(PathError::TooLong(1)));
+    }
+
+    #[test]
+    fn invalid_characters_are_reported_before_length() {
+        let dest = format!("Season 01/{}?.mkv", "a".repeat(40));
+        assert!(matches!(validate_destination(dest.as_str(), 10), Err(PathError::InvalidCharacters(_))));
+    }
+
+    #[test]
+    fn reserved_characters_are_replaced_or_removed() {
+        let expected = [
+            (':', "Show- Pilot.mkv"),
+            ('"', "Show' Pilot.mkv"),
+            ('<', "Show Pilot.mkv"),
+            ('>', "Show Pilot.mkv"),
+            ('|', "Show Pilot.mkv"),
+            ('?', "Show Pilot.mkv"),
+            ('*', "Show Pilot.mkv"),
+        ];
+        assert_eq!(expected.len(), RESERVED_CHARACTERS.len());
+        for (c, sanitized) in expected {
+            let component = format!("Show{} Pilot.mkv", c);
+            assert_eq!(sanitize_component(component.as_str()), sanitized, "{}", c);
+            let path = format!("Season 01/{}", component);
+            assert_eq!(sanitize_destination(path.as_str()), Ok(format!("Season 01/{}", sanitized)), "{}", c);
+            assert_eq!(validate_destination(path.as_str(), usize::MAX), Err(PathError::InvalidCharacters(component)), "{}", c);
+        }
+    }
+
+    #[test]
+    fn control_characters_are_removed() {
+        assert_eq!(sanitize_component("Show\tPilot\u{0}\u{1f}.mkv"), "ShowPilot.mkv");
+        assert!(matches!(validate_destination("Show\nPilot.mkv", usize::MAX), Err(PathError::InvalidCharacters(_))));
+    }
+
+    #[test]
+    fn trailing_dots_and_spaces_are_trimmed() {
+        assert_eq!(sanitize_component("Show Jr. "), "Show Jr");
+        assert_eq!(sanitize_component("What If...?"), "What If");
+        assert_eq!(sanitize_destination("Season 01 ./Show. . .mkv"), Ok("Season 01/Show. . .mkv".to_string()));
+        assert_eq!(sanitize_destination("Specials. /Show.mkv"), Ok("Specials/Show.mkv".to_string()));
+        assert_eq!(validate_destination("Specials./Show.mkv", usize::MAX), Err(PathError::InvalidCharacters("Specials.".to_string())));
+        // Leading dots and spaces are kept since they don't change the filename
+        assert_eq!(sanitize_component(" .hidden"), " .hidden");
+    }
+
+    #[test]
+    fn components_with_only_invalid_characters_are_rejected() {
+        assert_eq!(sanitize_destination("Season 01/???/Show.mkv"), Err(PathError::InvalidCharacters("???".to_string())));
+        assert_eq!(sanitize_destination("Season 01/.../Show.mkv"), Err(PathError::InvalidCharacters("...".to_string())));
+        assert_eq!(sanitize_destination("Season 01/ /Show.mkv"), Err(PathError::InvalidCharacters(" ".to_string())));
+    }
+
+    #[test]
+    fn destinations_outside_folder_are_rejected() {
+        for path in ["../Show.mkv", "Season 01/../../Show.mkv", "Season 01\\..\\Show.mkv", "Season 01/ .. /Show.mkv"] {
+            assert_eq!(sanitize_destination(path), Err(PathError::OutsideFolder), "{}", path);
+            assert_eq!(validate_destination(path, usize::MAX), Err(PathError::OutsideFolder), "{}", path);
+        }
+        for path in ["/Show.mkv", "\\Show.mkv", "C:/Show.mkv", "c:\\Show.mkv"] {
+            assert_eq!(sanitize_destination(path), Err(PathError::Absolute), "{}", path);
+        }
+        for path in ["", "/", ".", "././/"] {
+            let expected = match path.starts_with('/') {
+                true => PathError::Absolute,
+                false => PathError::Empty,
+            };
+            assert_eq!(sanitize_destination(path), Err(expected), "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn separators_are_normalised() {
+        assert_eq!(sanitize_destination("Season 01\\Show.mkv"), Ok("Season 01/Show.mkv".to_string()));
+        assert_eq!(sanitize_destination("./Season 01//Show.mkv"), Ok("Season 01/Show.mkv".to_string()));
+        assert!(matches!(validate_destination("Season 01\\Show.mkv", usize::MAX), Err(PathError::InvalidCharacters(_))));
+        assert_eq!(validate_destination("Season 01/Show.mkv", usize::MAX), Ok(()));
+    }
+
+    #[test]
+    fn reserved_names_ignore_case_extensions_and_trailing_spaces() {
+        for name in RESERVED_NAMES {
+            for component in [name.to_string(), name.to_lowercase(), format!("{}.mkv", name), format!("{} .en.srt", name)] {
+                assert!(is_reserved_name(component.as_str()), "{}", component);
+            }
+        }
+        for component in ["COM0", "LPT10", "CONSOLE.mkv", "NULL", "Show.NUL", " CON"] {
+            assert!(!is_reserved_name(component), "{}", component);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use chrono;
+use enum_map;
+use serde;
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+// Old entries are dropped so a repeatedly failing operation can't grow the log forever
+const DEFAULT_ERROR_LOG_CAPACITY: usize = 500;
+
+#[derive(serde::Serialize, Debug, Eq, PartialEq, Copy, Clone, enum_map::Enum)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn iterator() -> std::slice::Iter<'static, Self> {
+        static SEVERITIES: [Severity;2] = [
+            Severity::Warning,
+            Severity::Error,
+        ];
+        SEVERITIES.iter()
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct AppError {
+    #[serde(serialize_with="serialize_timestamp")]
+    pub timestamp: SystemTime,
+    pub severity: Severity,
+    // The operation that caused the error, i.e. "Login" or "Execute"
+    pub context: String,
+    pub message: String,
+}
+
+fn serialize_timestamp<S: serde::Serializer>(timestamp: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let timestamp: chrono::DateTime<chrono::Local> = (*timestamp).into();
+    serializer.serialize_str(timestamp.to_rfc3339().as_str())
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.context, self.message)
+    }
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(transparent)]
+pub struct ErrorLog {
+    entries: VecDeque<AppError>,
+    #[serde(skip)]
+    capacity: usize,
+}
+
+impl ErrorLog {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_ERROR_LOG_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, severity: Severity, context: &str, message: impl Into<String>) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(AppError {
+            timestamp: SystemTime::now(),
+            severity,
+            context: context.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn push_warn(&mut self, context: &str, message: impl Into<String>) {
+        self.push(Severity::Warning, context, message);
+    }
+
+    pub fn push_error(&mut self, context: &str, message: impl Into<String>) {
+        self.push(Severity::Error, context, message);
+    }
+
+    // Oldest entries are first
+    pub fn iter(&self) -> std::collections::vec_deque::Iter<'_, AppError> {
+        self.entries.iter()
+    }
+
+    pub fn iter_severity(&self, severity: Severity) -> impl DoubleEndedIterator<Item = &AppError> {
+        self.entries.iter().filter(move |error| error.severity == severity)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<AppError> {
+        self.entries.remove(index)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn clear_severity(&mut self, severity: Severity) {
+        self.entries.retain(|error| error.severity != severity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for ErrorLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_messages<'a>(errors: impl Iterator<Item = &'a AppError>) -> Vec<&'a str> {
+        errors.map(|error| error.message.as_str()).collect()
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped_past_capacity() {
+        let mut log = ErrorLog::with_capacity(3);
+        for index in 0..5 {
+            log.push_warn("Test", format!("{}", index));
+        }
+        assert_eq!(log.len(), 3);
+        assert_eq!(get_messages(log.iter()), vec!["2", "3", "4"]);
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut log = ErrorLog::with_capacity(0);
+        log.push_error("Test", "dropped");
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn entries_are_filtered_by_severity() {
+        let mut log = ErrorLog::new();
+        log.push_warn("Scan", "first warning");
+        log.push_error("Login", "first error");
+        log.push_warn("Scan", "second warning");
+        assert_eq!(get_messages(log.iter_severity(Severity::Warning)), vec!["first warning", "second warning"]);
+        assert_eq!(get_messages(log.iter_severity(Severity::Error)), vec!["first error"]);
+
+        log.clear_severity(Severity::Warning);
+        assert_eq!(get_messages(log.iter()), vec!["first error"]);
+        log.clear();
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn log_is_serialized_as_list() {
+        let mut log = ErrorLog::new();
+        log.push_error("Login", "failed");
+        let value = serde_json::to_value(&log).unwrap();
+        let entries = value.as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["severity"], "Error");
+        assert_eq!(entries[0]["context"], "Login");
+        assert_eq!(entries[0]["message"], "failed");
+        assert!(entries[0]["timestamp"].as_str().is_some());
+        assert_eq!(log.iter().next().unwrap().to_string(), "Login: failed");
+    }
+}
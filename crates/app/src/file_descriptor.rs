@@ -7,6 +7,18 @@ pub struct FileDescriptor {
     pub title: String,
     pub season: u32,
     pub episode: u32,
+    // Set when a file contains multiple episodes, e.g. S01E01-E02
+    pub end_episode: Option<u32>,
+    pub tags: Vec<String>,
+    pub extension: String,
+}
+
+// Anime is usually released with a single number counting from the first episode
+// e.g. [Group] Title - 123 [1080p].mkv
+#[derive(Debug)]
+pub struct AbsoluteDescriptor {
+    pub title: String,
+    pub number: u32,
     pub tags: Vec<String>,
     pub extension: String,
 }
@@ -23,9 +35,20 @@ pub fn find_tags(tags_str: &str) -> Vec<String> {
         .collect()
 }
 
+// Video resolution anywhere in the filename, i.e. "1080p" in "Show.S01E01.1080p.WEB.mkv"
+pub fn find_resolution(filename: &str) -> Option<String> {
+    lazy_static! {
+        static ref RESOLUTION_REGEX: Regex = Regex::new(r"(?i)(?:^|[^a-z0-9])(\d{3,4}[pi]|4k)(?:[^a-z0-9]|$)").unwrap();
+    }
+    RESOLUTION_REGEX.captures(filename).map(|x| x[1].to_lowercase())
+}
+
 
 pub fn get_descriptor(filename: &str) -> Option<FileDescriptor> {
     lazy_static! {
+        static ref SEASON_EPISODE_RANGE_EXT_REGEXES: Vec<Regex> = vec![
+            Regex::new(format!("{}{}{}", TITLE_PATTERN, r"[Ss](\d+)\s*[Ee](\d+)\s*-?\s*[Ee](\d+)(.*)", EXT_PATTERN).as_str()).unwrap(),
+        ];
         static ref SEASON_EPISODE_EXT_REGEXES: Vec<Regex> = vec![
             Regex::new(format!("{}{}{}", TITLE_PATTERN, r"[Ss](\d+)\s*[Ee](\d+)(.*)", EXT_PATTERN).as_str()).unwrap(),
             Regex::new(format!("{}{}{}", TITLE_PATTERN, r"[Ss]eason\s*(\d+)\s*[Ee]pisode\s*(\d+)(.*)", EXT_PATTERN).as_str()).unwrap(),
@@ -34,12 +57,31 @@ pub fn get_descriptor(filename: &str) -> Option<FileDescriptor> {
         ];
     }
 
+    for re in SEASON_EPISODE_RANGE_EXT_REGEXES.iter() {
+        if let Some(res) = re.captures(filename) {
+            let episode: u32 = res[3].parse().unwrap_or(0);
+            let end_episode: u32 = res[4].parse().unwrap_or(0);
+            if end_episode <= episode {
+                continue;
+            }
+            return Some(FileDescriptor {
+                title: res[1].to_string(),
+                season: res[2].parse().unwrap_or(0),
+                episode,
+                end_episode: Some(end_episode),
+                tags: find_tags(&res[5]),
+                extension: res[6].to_string(),
+            });
+        }
+    }
+
     for re in SEASON_EPISODE_EXT_REGEXES.iter() {
         if let Some(res) = re.captures(filename) {
             return Some(FileDescriptor {
                 title: res[1].to_string(),
                 season: res[2].parse().unwrap_or(0),
                 episode: res[3].parse().unwrap_or(0),
+                end_episode: None,
                 tags: find_tags(&res[4]),
                 extension: res[5].to_string(),
             });
@@ -48,6 +90,21 @@ pub fn get_descriptor(filename: &str) -> Option<FileDescriptor> {
     None
 }
 
+pub fn get_absolute_descriptor(filename: &str) -> Option<AbsoluteDescriptor> {
+    lazy_static! {
+        static ref ABSOLUTE_EXT_REGEX: Regex = Regex::new(
+            r"^(?:\[[^\]]*\]\s*)?([^\[\]\(\)]+?)\s+-\s+(\d{1,4})(?:v\d+)?(?:\s+(.*))?\.([a-zA-Z0-9]+)$"
+        ).unwrap();
+    }
+    let res = ABSOLUTE_EXT_REGEX.captures(filename)?;
+    Some(AbsoluteDescriptor {
+        title: res[1].to_string(),
+        number: res[2].parse().ok()?,
+        tags: res.get(3).map(|tags| find_tags(tags.as_str())).unwrap_or_default(),
+        extension: res[4].to_string(),
+    })
+}
+
 pub fn clean_series_name(value: &str) -> String {
     lazy_static! {
         static ref TAG_REGEX: Regex = Regex::new(r"[\[\(]([a-zA-Z0-9]{2,})[\]\)]").unwrap();
@@ -76,3 +133,49 @@ pub fn clean_episode_title(value: &str) -> String {
     new_value = new_value.trim().replace(' ', ".").to_string();
     new_value
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_episodes(filename: &str) -> Option<(u32, u32, Option<u32>)> {
+        get_descriptor(filename).map(|descriptor| (descriptor.season, descriptor.episode, descriptor.end_episode))
+    }
+
+    #[test]
+    fn single_episode_forms() {
+        assert_eq!(get_episodes("Show.S01E02.mkv"), Some((1, 2, None)));
+        assert_eq!(get_episodes("show s1 e2.mkv"), Some((1, 2, None)));
+        assert_eq!(get_episodes("Show Season 1 Episode 2.mkv"), Some((1, 2, None)));
+        assert_eq!(get_episodes("Show 1x02.mkv"), Some((1, 2, None)));
+        assert_eq!(get_episodes("Show - 102 - Title.mkv"), Some((1, 2, None)));
+        assert_eq!(get_episodes("Show.mkv"), None);
+    }
+
+    #[test]
+    fn episode_range_with_separator() {
+        assert_eq!(get_episodes("Show.S01E01-E03.mkv"), Some((1, 1, Some(3))));
+        assert_eq!(get_episodes("Show.s01e01 - e02.mkv"), Some((1, 1, Some(2))));
+        assert_eq!(get_episodes("Show-S02E09-E10-Finale.mkv"), Some((2, 9, Some(10))));
+    }
+
+    #[test]
+    fn episode_range_without_separator() {
+        assert_eq!(get_episodes("Show.S01E01E02.mkv"), Some((1, 1, Some(2))));
+        assert_eq!(get_episodes("Show.S01E01 E02.mkv"), Some((1, 1, Some(2))));
+    }
+
+    #[test]
+    fn backwards_range_is_a_single_episode() {
+        assert_eq!(get_episodes("Show.S01E03-E01.mkv"), Some((1, 3, None)));
+        assert_eq!(get_episodes("Show.S01E03E03.mkv"), Some((1, 3, None)));
+    }
+
+    #[test]
+    fn episode_range_keeps_title_tags_and_extension() {
+        let descriptor = get_descriptor("Show Name S01E01-E02 [1080p] (WEB).mp4").unwrap();
+        assert_eq!(descriptor.title, "Show Name ");
+        assert_eq!(descriptor.tags, vec!["1080p", "WEB"]);
+        assert_eq!(descriptor.extension, "mp4");
+    }
+}
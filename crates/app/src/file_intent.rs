@@ -1,10 +1,17 @@
 use crate::tvdb_cache::{EpisodeKey, TvdbCache};
-use crate::file_descriptor::{get_descriptor, clean_episode_title, clean_series_name};
+use crate::destination::sanitize_destination;
+use tvdb::models::ProviderKind;
+use crate::file_descriptor::{
+    FileDescriptor, get_absolute_descriptor, get_descriptor, find_tags, clean_episode_title, clean_series_name,
+};
 use enum_map;
 use std::path::Path;
+use std::sync::OnceLock;
 use serde;
+use globset;
+use thiserror;
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, enum_map::Enum)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Copy, Clone, enum_map::Enum)]
 pub enum Action {
     Rename,
     Complete,
@@ -49,6 +56,268 @@ pub struct FilterRules {
     pub whitelist_folders: Vec<String>,
     pub whitelist_filenames: Vec<String>,
     pub whitelist_tags: Vec<String>,
+    // If provided then only these extensions are considered as episodes
+    pub video_extensions: Option<Vec<String>>,
+    #[serde(default)]
+    pub unknown_extension_action: UnknownExtensionAction,
+    // Most filesystems limit a single path component to 255 bytes
+    #[serde(default="default_max_filename_length")]
+    pub max_filename_length: usize,
+    // Move deleted files to the trash instead of permanently removing them
+    #[serde(default)]
+    pub use_trash: bool,
+    // Restore the original access and modification times of files that had to be copied
+    #[serde(default)]
+    pub preserve_timestamps: bool,
+    // Remove folders that have no files left in them after executing changes
+    #[serde(default="default_delete_empty_folders")]
+    pub delete_empty_folders: bool,
+    // Folders that are never removed even if they are empty, i.e. "Specials" or ".stfolder"
+    #[serde(default)]
+    pub empty_folder_exclusions: Vec<String>,
+    // Files that don't stop a folder from being considered empty, i.e. ".DS_Store" or "Thumbs.db"
+    #[serde(default)]
+    pub ignore_files_for_emptiness: Vec<String>,
+    // Depth at which series folders are found from the root, where 0 means the root is the only folder
+    #[serde(default="default_folder_scan_depth")]
+    pub folder_scan_depth: usize,
+    // Search folders and files that are links, which can point outside of the series folder
+    // Links are never followed when removing empty folders
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    // Folders under the root that aren't series, i.e. "_incoming" or "Music"
+    // This is an alternative to a .renamerignore file in the root
+    #[serde(default)]
+    pub ignore_folders: Vec<String>,
+    // Default language of the series and episode names fetched from the api, i.e. "en"
+    pub language: Option<String>,
+    // Api used to search for series and fetch their episodes
+    #[serde(default)]
+    pub provider: ProviderKind,
+    // Saved caches older than this are fetched again when the folder is first loaded
+    pub auto_refresh_after_days: Option<u32>,
+    // Remove bookmarks of files that no longer exist once the folder is rescanned after executing changes
+    #[serde(default)]
+    pub prune_bookmarks_after_execute: bool,
+    // Executing changes that delete more files than this always asks for confirmation in the gui
+    #[serde(default="default_confirm_delete_threshold")]
+    pub confirm_delete_threshold: usize,
+    // Built from the patterns above by compile_globs
+    #[serde(skip)]
+    globs: OnceLock<CompiledGlobs>,
+}
+
+fn default_max_filename_length() -> usize {
+    240
+}
+
+fn default_delete_empty_folders() -> bool {
+    true
+}
+
+fn default_folder_scan_depth() -> usize {
+    1
+}
+
+fn default_confirm_delete_threshold() -> usize {
+    10
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub enum UnknownExtensionAction {
+    #[default]
+    Delete,
+    Ignore,
+}
+
+impl UnknownExtensionAction {
+    fn to_action(self) -> Action {
+        match self {
+            UnknownExtensionAction::Delete => Action::Delete,
+            UnknownExtensionAction::Ignore => Action::Ignore,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct WhitelistGlobs {
+    folders: globset::GlobSet,
+    filenames: globset::GlobSet,
+}
+
+#[derive(Debug, Clone, Default)]
+struct EmptyFolderGlobs {
+    exclusions: globset::GlobSet,
+    ignored_files: globset::GlobSet,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CompiledGlobs {
+    whitelist: WhitelistGlobs,
+    ignored_folders: globset::GlobSet,
+    empty_folder: EmptyFolderGlobs,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidGlobError {
+    #[error("pattern '{}' is invalid: {}", .pattern, .source.kind())]
+    Pattern { pattern: String, source: globset::Error },
+    // Every pattern was valid on its own but they couldn't be matched together
+    #[error("patterns couldn't be combined: {}", .source.kind())]
+    Set { source: globset::Error },
+}
+
+fn build_glob_set<F>(patterns: &[String], expand: F) -> Result<globset::GlobSet, InvalidGlobError>
+where F: Fn(&str) -> String
+{
+    let mut builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = globset::GlobBuilder::new(expand(pattern).as_str())
+            .literal_separator(true)
+            .build()
+            .map_err(|source| InvalidGlobError::Pattern { pattern: pattern.clone(), source })?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|source| InvalidGlobError::Set { source })
+}
+
+// Ignored folders are matched against their path relative to the root
+pub fn build_folder_ignore_globs(patterns: &[String]) -> Result<globset::GlobSet, InvalidGlobError> {
+    build_glob_set(patterns, |pattern| pattern.trim_matches('/').to_string())
+}
+
+// One pattern per line where blank lines and lines starting with '#' are skipped
+pub fn parse_folder_ignore_file(data: &str) -> Vec<String> {
+    data.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect()
+}
+
+impl FilterRules {
+    // Whitelist patterns are globs matched against the relative path of a file
+    // Plain names without any glob syntax behave like an exact match so older configs still work
+    // - Folders match any directory in the path, i.e. "Extras" is treated as "**/Extras/**"
+    // - Filenames without a '/' match in any folder, i.e. "*.nfo" is treated as "**/*.nfo"
+    // Empty folder exclusions and ignored files follow the same rule as filenames
+    fn build_globs(&self) -> Result<CompiledGlobs, InvalidGlobError> {
+        let expand_filename = |pattern: &str| {
+            if pattern.contains('/') {
+                pattern.to_string()
+            } else {
+                format!("**/{}", pattern)
+            }
+        };
+        let folders = build_glob_set(&self.whitelist_folders, |pattern| {
+            format!("**/{}/**", pattern.trim_matches('/'))
+        })?;
+        let filenames = build_glob_set(&self.whitelist_filenames, expand_filename)?;
+        let exclusions = build_glob_set(&self.empty_folder_exclusions, |pattern| {
+            expand_filename(pattern.trim_matches('/'))
+        })?;
+        let ignored_files = build_glob_set(&self.ignore_files_for_emptiness, expand_filename)?;
+        Ok(CompiledGlobs {
+            whitelist: WhitelistGlobs { folders, filenames },
+            ignored_folders: build_folder_ignore_globs(&self.ignore_folders)?,
+            empty_folder: EmptyFolderGlobs { exclusions, ignored_files },
+        })
+    }
+
+    // Compiling up front lets invalid patterns be reported when the config is loaded
+    pub fn compile_globs(&self) -> Result<(), InvalidGlobError> {
+        if self.globs.get().is_none() {
+            let globs = self.build_globs()?;
+            let _ = self.globs.set(globs);
+        }
+        Ok(())
+    }
+
+    // Rules are only usable once compile_globs has succeeded
+    // Matching nothing instead would let whitelisted files fall through to being deleted
+    fn get_globs(&self) -> &CompiledGlobs {
+        self.globs.get().expect("filter rules should be compiled with compile_globs before they are used")
+    }
+
+    // The name is the path of the folder relative to the root
+    pub fn is_folder_ignored(&self, folder_name: &str) -> bool {
+        self.get_globs().ignored_folders.is_match(folder_name)
+    }
+
+    pub fn is_empty_folder_excluded(&self, path: &Path) -> bool {
+        self.get_globs().empty_folder.exclusions.is_match(path)
+    }
+
+    pub fn is_ignored_for_emptiness(&self, path: &Path) -> bool {
+        self.get_globs().empty_folder.ignored_files.is_match(path)
+    }
+}
+
+// Cleaned episode titles use '.' as the separator between words
+// We cut on a word boundary where possible so the title is still readable
+// This is deterministic so a truncated file is still detected as complete on rescan
+fn truncate_episode_title(title: &str, max_length: usize) -> &str {
+    if title.len() <= max_length {
+        return title;
+    }
+
+    let mut end = max_length;
+    while !title.is_char_boundary(end) {
+        end -= 1;
+    }
+    let truncated = &title[..end];
+    if title[end..].starts_with('.') {
+        return truncated;
+    }
+    match truncated.rfind('.') {
+        Some(index) => &truncated[..index],
+        None => truncated,
+    }
+}
+
+// Combine the titles of a file containing two episodes
+// Titles that start with the same words are merged, i.e. "Finale Part 1" and "Finale Part 2" becomes "Finale Part 1 & 2"
+fn join_episode_titles(first: Option<&str>, second: Option<&str>) -> String {
+    let (first, second) = match (first, second) {
+        (Some(first), Some(second)) => (first, second),
+        (Some(title), None) | (None, Some(title)) => return title.to_string(),
+        (None, None) => return "".to_string(),
+    };
+
+    if first == second {
+        return first.to_string();
+    }
+
+    let first_words: Vec<&str> = first.split_whitespace().collect();
+    let second_words: Vec<&str> = second.split_whitespace().collect();
+    let total_shared = first_words
+        .iter()
+        .zip(second_words.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    if total_shared == 0 || total_shared == second_words.len() {
+        return format!("{} & {}", first, second);
+    }
+    format!("{} & {}", first, second_words[total_shared..].join(" "))
+}
+
+// Absolute numbers are only used if the series has an episode with that number
+// Otherwise "Title - 105" would be read as S01E05 by the season and episode patterns
+fn get_absolute_episode_descriptor(filename: &str, cache: &TvdbCache) -> Option<FileDescriptor> {
+    let descriptor = get_absolute_descriptor(filename)?;
+    let key = cache.get_episode_key(cache.get_by_absolute(descriptor.number)?);
+    if key.episode == 0 {
+        return None;
+    }
+    Some(FileDescriptor {
+        title: descriptor.title,
+        season: key.season,
+        episode: key.episode,
+        end_episode: None,
+        tags: descriptor.tags,
+        extension: descriptor.extension,
+    })
 }
 
 pub fn get_file_intent(path_str: &str, rules: &FilterRules, cache: &TvdbCache) -> FileIntent {
@@ -74,33 +343,102 @@ pub fn get_file_intent(path_str: &str, rules: &FilterRules, cache: &TvdbCache) -
         },
     };
     
-    if rules.blacklist_extensions.contains(&extension) {
+    // Order of precedence for filter rules
+    // 1. whitelisted folders
+    // 2. whitelisted filenames
+    // 3. extensions missing from the video whitelist get the unknown extension action
+    // 4. blacklisted extensions are deleted
+    // Configs without a video whitelist keep deleting blacklisted extensions before anything else
+    let is_blacklisted = rules.blacklist_extensions.contains(&extension);
+    if is_blacklisted && rules.video_extensions.is_none() {
         intent.action = Action::Delete;
         return intent;
     }
 
-    for component in path.iter() {
-        if let Some(folder) = component.to_str() {
-            if rules.whitelist_folders.contains(&folder.to_string()) {
-                intent.action = Action::Whitelist;
-                return intent;
-            }
-        }
+    if rules.get_globs().whitelist.folders.is_match(path) {
+        intent.action = Action::Whitelist;
+        return intent;
     }
     
-    if rules.whitelist_filenames.contains(&filename) {
+    if rules.get_globs().whitelist.filenames.is_match(path) {
         intent.action = Action::Whitelist;
         return intent;
     }
+
+    if let Some(video_extensions) = rules.video_extensions.as_ref() {
+        let is_video = video_extensions
+            .iter()
+            .any(|video_extension| video_extension.eq_ignore_ascii_case(extension.as_str()));
+        if !is_video {
+            intent.action = rules.unknown_extension_action.to_action();
+            return intent;
+        }
+    }
+
+    if is_blacklisted {
+        intent.action = Action::Delete;
+        return intent;
+    }
     
     // get descriptor tag if possible
-    let descriptor = match get_descriptor(filename.as_str()) {
-        Some(descriptor) => descriptor,
+    let descriptor = get_absolute_episode_descriptor(filename.as_str(), cache)
+        .or_else(|| get_descriptor(filename.as_str()));
+    match descriptor {
+        Some(descriptor) => get_episode_intent(path, extension.as_str(), &descriptor, rules, cache),
         None => {
             intent.action = Action::Ignore;
-            return intent;
+            intent
+        },
+    }
+}
+
+// Intent of a file that was picked for an episode instead of the one parsed from its filename
+// Tags are still taken from the filename so whitelisted tags are kept
+pub fn get_assigned_file_intent(path_str: &str, key: EpisodeKey, rules: &FilterRules, cache: &TvdbCache) -> FileIntent {
+    let path = Path::new(path_str);
+    let (extension, filename) = match (path.extension(), path.file_name()) {
+        (Some(extension), Some(filename)) => (extension.to_string_lossy(), filename.to_string_lossy()),
+        _ => return FileIntent {
+            action: Action::Ignore,
+            dest: "".to_string(),
+            descriptor: Some(key),
         },
     };
+    let tags = get_absolute_episode_descriptor(filename.as_ref(), cache)
+        .map(|descriptor| descriptor.tags)
+        .or_else(|| get_descriptor(filename.as_ref()).map(|descriptor| descriptor.tags))
+        .unwrap_or_else(|| find_tags(filename.as_ref()));
+    let descriptor = FileDescriptor {
+        title: "".to_string(),
+        season: key.season,
+        episode: key.episode,
+        end_episode: None,
+        tags,
+        extension: extension.to_string(),
+    };
+    get_episode_intent(path, extension.as_ref(), &descriptor, rules, cache)
+}
+
+// Intent of a file that keeps the episode it was given, which is only different if it was assigned to another episode
+// The parsed intent is used when the episode matches so episode ranges are kept
+pub fn get_file_intent_for_episode(path_str: &str, key: Option<EpisodeKey>, rules: &FilterRules, cache: &TvdbCache) -> FileIntent {
+    let intent = get_file_intent(path_str, rules, cache);
+    match key {
+        Some(key) if intent.descriptor != Some(key) => get_assigned_file_intent(path_str, key, rules, cache),
+        _ => intent,
+    }
+}
+
+// Files of an episode are renamed into their season folder using the series and episode names
+fn get_episode_intent(
+    path: &Path, extension: &str, descriptor: &FileDescriptor,
+    rules: &FilterRules, cache: &TvdbCache,
+) -> FileIntent {
+    let mut intent = FileIntent {
+        action: Action::Ignore,
+        dest: "".to_string(),
+        descriptor: None,
+    };
 
     let episode_key = EpisodeKey { 
         season: descriptor.season, 
@@ -109,23 +447,17 @@ pub fn get_file_intent(path_str: &str, rules: &FilterRules, cache: &TvdbCache) -
     intent.descriptor = Some(episode_key);
 
     // create new filename
-    let new_episode_title = match cache.episode_cache.get(&episode_key) {
-        None => "".to_string(),
-        Some(index) => {
-            let episode = &cache.episodes[*index];
-            match &episode.name {
-                None => "".to_string(),
-                Some(name) => {
-                    let clean_name = clean_episode_title(name.as_str());
-                    if clean_name.is_empty() {
-                        "".to_string()
-                    } else {
-                        format!("-{}", clean_name.as_str())
-                    }
-                },
-            }
+    let get_episode_name = |key: &EpisodeKey| -> Option<&str> {
+        cache.get_episode(key)?.name.as_deref()
+    };
+    let episode_name = match descriptor.end_episode {
+        None => get_episode_name(&episode_key).unwrap_or("").to_string(),
+        Some(end_episode) => {
+            let end_key = EpisodeKey { season: descriptor.season, episode: end_episode };
+            join_episode_titles(get_episode_name(&episode_key), get_episode_name(&end_key))
         },
     };
+    let episode_title = clean_episode_title(episode_name.as_str());
     let tags_string = descriptor.tags
         .iter()
         .filter(|tag| rules.whitelist_tags.contains(tag))
@@ -133,20 +465,39 @@ pub fn get_file_intent(path_str: &str, rules: &FilterRules, cache: &TvdbCache) -
         .collect::<Vec<String>>()
         .join("");
 
-    let new_filename = format!(
-        "{}-S{:02}E{:02}{}{}.{}", 
-        clean_series_name(cache.series.name.as_str()).as_str(), 
+    let mut filename_prefix = format!(
+        "{}-S{:02}E{:02}",
+        clean_series_name(cache.series.get_display_name().as_str()).as_str(), 
         descriptor.season, descriptor.episode, 
-        new_episode_title.as_str(),
-        tags_string.as_str(),
-        extension.as_str(),
     );
+    if let Some(end_episode) = descriptor.end_episode {
+        filename_prefix.push_str(format!("-E{:02}", end_episode).as_str());
+    }
+    let filename_suffix = format!("{}.{}", tags_string.as_str(), extension);
+    // Only the episode title is shortened so that the episode index, tags and extension are kept
+    // Include the '-' separator preceding the title
+    let title_length = rules.max_filename_length
+        .saturating_sub(filename_prefix.len() + filename_suffix.len() + 1);
+    let episode_title = truncate_episode_title(episode_title.as_str(), title_length);
+    let new_filename = match episode_title.is_empty() {
+        true => format!("{}{}", filename_prefix, filename_suffix),
+        false => format!("{}-{}{}", filename_prefix, episode_title, filename_suffix),
+    };
 
     // check if new path is same as old path
     let new_folder = format!("Season {:02}", descriptor.season);
     let new_path = Path::new(new_folder.as_str()).join(new_filename.as_str());
-    let new_path_str = new_path.to_string_lossy().to_string();
-    let is_same_filepath = new_path == path;
+    // Titles can still contain characters that aren't allowed on every filesystem
+    let new_path_str = match sanitize_destination(new_path.to_string_lossy().as_ref()) {
+        Ok(new_path_str) => new_path_str,
+        Err(_) => {
+            intent.action = Action::Ignore;
+            return intent;
+        },
+    };
+    // NOTE: This is an exact comparison even on case-insensitive filesystems
+    //       A destination that only differs by case is kept as a rename so the casing gets fixed
+    let is_same_filepath = Path::new(new_path_str.as_str()) == path;
     if is_same_filepath {
         intent.action = Action::Complete;
         return intent;
@@ -156,3 +507,425 @@ pub fn get_file_intent(path_str: &str, rules: &FilterRules, cache: &TvdbCache) -
     intent.dest = new_path_str;
     intent
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::test_fixtures::{new_cache, new_dvd_order_cache, new_filter_rules, new_filter_rules_config};
+    use crate::tvdb_cache::EpisodeOrdering;
+
+    #[test]
+    fn case_only_difference_is_renamed() {
+        let cache = new_cache("Show", &[(1, 1, Some("Pilot"))]);
+        let rules = new_filter_rules(json!({}));
+
+        let intent = get_file_intent("season 01/show-s01e01-pilot.mkv", &rules, &cache);
+        assert_eq!(intent.action, Action::Rename);
+        assert_eq!(intent.dest, "Season 01/Show-S01E01-Pilot.mkv");
+
+        let intent = get_file_intent("Season 01/Show-S01E01-Pilot.mkv", &rules, &cache);
+        assert_eq!(intent.action, Action::Complete);
+    }
+
+    fn get_action(path: &str, rules: &FilterRules) -> Action {
+        let cache = new_cache("Show", &[(1, 1, Some("Pilot"))]);
+        get_file_intent(path, rules, &cache).action
+    }
+
+    #[test]
+    fn whitelisted_folder_overrides_blacklisted_extension() {
+        let rules = new_filter_rules(json!({
+            "blacklist_extensions": ["nfo", "txt"],
+            "whitelist_folders": ["Extras"],
+            "video_extensions": ["mkv", "nfo", "txt"],
+        }));
+        assert_eq!(get_action("Extras/info.nfo", &rules), Action::Whitelist);
+        assert_eq!(get_action("Season 01/Extras/notes.txt", &rules), Action::Whitelist);
+        assert_eq!(get_action("info.nfo", &rules), Action::Delete);
+    }
+
+    #[test]
+    fn whitelisted_filename_overrides_blacklisted_extension() {
+        let rules = new_filter_rules(json!({
+            "blacklist_extensions": ["nfo"],
+            "whitelist_filenames": ["tvshow.nfo"],
+            "video_extensions": ["mkv", "nfo"],
+        }));
+        assert_eq!(get_action("tvshow.nfo", &rules), Action::Whitelist);
+        assert_eq!(get_action("release.nfo", &rules), Action::Delete);
+    }
+
+    #[test]
+    fn whitelisted_folder_overrides_video_whitelist() {
+        let rules = new_filter_rules(json!({
+            "whitelist_folders": ["Extras"],
+            "video_extensions": ["mkv"],
+        }));
+        assert_eq!(get_action("Extras/poster.jpg", &rules), Action::Whitelist);
+        assert_eq!(get_action("poster.jpg", &rules), Action::Delete);
+    }
+
+    #[test]
+    fn video_whitelist_deletes_unknown_extensions() {
+        let rules = new_filter_rules(json!({ "video_extensions": ["mkv", "mp4"] }));
+        assert_eq!(get_action("show.s01e01.mkv", &rules), Action::Rename);
+        assert_eq!(get_action("show.s01e01.MKV", &rules), Action::Rename);
+        assert_eq!(get_action("notes.txt", &rules), Action::Delete);
+        assert_eq!(get_action("show.s01e01.txt", &rules), Action::Delete);
+    }
+
+    #[test]
+    fn video_whitelist_can_ignore_unknown_extensions() {
+        let rules = new_filter_rules(json!({
+            "video_extensions": ["mkv"],
+            "unknown_extension_action": "Ignore",
+        }));
+        assert_eq!(get_action("show.s01e01.mkv", &rules), Action::Rename);
+        assert_eq!(get_action("notes.txt", &rules), Action::Ignore);
+    }
+
+    #[test]
+    fn blacklist_applies_to_video_extensions() {
+        let rules = new_filter_rules(json!({
+            "blacklist_extensions": ["mp4"],
+            "video_extensions": ["mkv", "mp4"],
+        }));
+        assert_eq!(get_action("show.s01e01.mkv", &rules), Action::Rename);
+        assert_eq!(get_action("show.s01e01.mp4", &rules), Action::Delete);
+    }
+
+    #[test]
+    fn without_video_whitelist_only_blacklist_deletes() {
+        let rules = new_filter_rules(json!({ "blacklist_extensions": ["nfo"] }));
+        assert!(rules.video_extensions.is_none());
+        assert_eq!(get_action("show.s01e01.mkv", &rules), Action::Rename);
+        assert_eq!(get_action("show.s01e01.txt", &rules), Action::Rename);
+        assert_eq!(get_action("notes.txt", &rules), Action::Ignore);
+        assert_eq!(get_action("release.nfo", &rules), Action::Delete);
+        assert_eq!(get_action("no_extension", &rules), Action::Delete);
+
+        // Blacklisted extensions are deleted before the whitelists are checked like they always were
+        let rules = new_filter_rules(json!({
+            "blacklist_extensions": ["nfo"],
+            "whitelist_folders": ["Extras"],
+            "whitelist_filenames": ["tvshow.nfo", "poster.jpg"],
+        }));
+        assert_eq!(get_action("Extras/info.nfo", &rules), Action::Delete);
+        assert_eq!(get_action("tvshow.nfo", &rules), Action::Delete);
+        assert_eq!(get_action("Extras/poster.jpg", &rules), Action::Whitelist);
+        assert_eq!(get_action("poster.jpg", &rules), Action::Whitelist);
+    }
+
+    #[test]
+    #[should_panic(expected = "compile_globs")]
+    fn deserialised_rules_must_be_compiled() {
+        let rules: FilterRules = serde_json::from_value(new_filter_rules_config(json!({ "whitelist_folders": ["Extras"] }))).unwrap();
+        assert!(rules.globs.get().is_none());
+        get_action("Extras/poster.jpg", &rules);
+    }
+
+    #[test]
+    fn invalid_glob_is_reported_when_compiled() {
+        let rules: FilterRules = serde_json::from_value(new_filter_rules_config(json!({ "whitelist_filenames": ["[abc"] }))).unwrap();
+        let err = rules.compile_globs().unwrap_err();
+        assert!(matches!(&err, InvalidGlobError::Pattern { pattern, .. } if pattern == "[abc"), "{}", err);
+        assert!(rules.globs.get().is_none());
+    }
+
+    #[test]
+    fn star_glob_matches_within_one_component() {
+        let rules = new_filter_rules(json!({
+            "whitelist_filenames": ["*.nfo", "Extras/*.jpg"],
+        }));
+        assert_eq!(get_action("tvshow.nfo", &rules), Action::Whitelist);
+        assert_eq!(get_action("Season 01/episode.nfo", &rules), Action::Whitelist);
+        assert_eq!(get_action("Extras/poster.jpg", &rules), Action::Whitelist);
+        // A pattern with a '/' is anchored and '*' doesn't cross a separator
+        assert_ne!(get_action("Extras/Posters/poster.jpg", &rules), Action::Whitelist);
+        assert_ne!(get_action("Season 01/Extras/poster.jpg", &rules), Action::Whitelist);
+    }
+
+    #[test]
+    fn double_star_glob_matches_any_depth() {
+        let rules = new_filter_rules(json!({
+            "whitelist_filenames": ["Extras/**/*.jpg"],
+        }));
+        assert_eq!(get_action("Extras/poster.jpg", &rules), Action::Whitelist);
+        assert_eq!(get_action("Extras/Posters/Large/poster.jpg", &rules), Action::Whitelist);
+        assert_ne!(get_action("Posters/poster.jpg", &rules), Action::Whitelist);
+    }
+
+    #[test]
+    fn character_class_glob() {
+        let rules = new_filter_rules(json!({
+            "whitelist_filenames": ["cover[0-9].jpg", "[!x]art.png"],
+        }));
+        assert_eq!(get_action("cover1.jpg", &rules), Action::Whitelist);
+        assert_eq!(get_action("Season 01/cover9.jpg", &rules), Action::Whitelist);
+        assert_ne!(get_action("coverA.jpg", &rules), Action::Whitelist);
+        assert_eq!(get_action("fart.png", &rules), Action::Whitelist);
+        assert_ne!(get_action("xart.png", &rules), Action::Whitelist);
+    }
+
+    #[test]
+    fn folder_patterns_match_any_directory_in_path() {
+        let rules = new_filter_rules(json!({
+            "whitelist_folders": ["Extras", "Featurette*", "/Subs/"],
+        }));
+        assert_eq!(get_action("Extras/a.mkv", &rules), Action::Whitelist);
+        assert_eq!(get_action("Season 01/Extras/Deleted/a.mkv", &rules), Action::Whitelist);
+        assert_eq!(get_action("Featurettes/a.mkv", &rules), Action::Whitelist);
+        assert_eq!(get_action("Subs/a.srt", &rules), Action::Whitelist);
+        // The folder itself has to be in the path, not just a file with the same name
+        assert_ne!(get_action("Extras.mkv", &rules), Action::Whitelist);
+        assert_ne!(get_action("MoreExtras/a.mkv", &rules), Action::Whitelist);
+    }
+
+    #[test]
+    fn plain_names_match_exactly() {
+        let rules = new_filter_rules(json!({
+            "whitelist_filenames": ["tvshow.nfo"],
+            "ignore_folders": ["Music"],
+            "empty_folder_exclusions": ["Specials"],
+            "ignore_files_for_emptiness": ["Thumbs.db"],
+        }));
+        assert_eq!(get_action("tvshow.nfo", &rules), Action::Whitelist);
+        assert_ne!(get_action("old-tvshow.nfo", &rules), Action::Whitelist);
+        assert!(rules.is_folder_ignored("Music"));
+        assert!(!rules.is_folder_ignored("Musicals"));
+        assert!(rules.is_empty_folder_excluded(Path::new("Show/Specials")));
+        assert!(!rules.is_empty_folder_excluded(Path::new("Show/Specials Extra")));
+        assert!(rules.is_ignored_for_emptiness(Path::new("Show/Season 01/Thumbs.db")));
+        assert!(!rules.is_ignored_for_emptiness(Path::new("Show/Season 01/thumbs.db.bak")));
+    }
+
+    #[test]
+    fn folder_ignore_file_skips_comments_and_blank_lines() {
+        let data = "# Not shows\n\n_incoming\n   \n  Music/  \n# Downloads*\nTemp*\n";
+        let patterns = parse_folder_ignore_file(data);
+        assert_eq!(patterns, vec!["_incoming", "Music/", "Temp*"]);
+        let globs = build_folder_ignore_globs(&patterns).unwrap();
+        assert!(globs.is_match("Music"));
+        assert!(!globs.is_match("# Not shows"));
+        assert!(!globs.is_match("Downloads"));
+    }
+
+    #[test]
+    fn folder_ignore_globs_and_literals() {
+        let patterns = parse_folder_ignore_file("_incoming\nTemp*\nArchive/*\n");
+        let globs = build_folder_ignore_globs(&patterns).unwrap();
+        // Literal entries only match the exact name
+        assert!(globs.is_match("_incoming"));
+        assert!(!globs.is_match("_incoming old"));
+        assert!(!globs.is_match("incoming"));
+        // Globs match any name with the pattern
+        assert!(globs.is_match("Temp"));
+        assert!(globs.is_match("Temporary Files"));
+        assert!(!globs.is_match("My Temp"));
+        // Names are relative to the root so nested folders can be matched
+        assert!(globs.is_match("Archive/Old Show"));
+        assert!(!globs.is_match("Archive"));
+        assert!(!globs.is_match("Archive/Old Show/Season 01"));
+    }
+
+    #[test]
+    fn truncation_cuts_title_on_word_boundary() {
+        assert_eq!(truncate_episode_title("The.Long.Title", 100), "The.Long.Title");
+        assert_eq!(truncate_episode_title("The.Long.Title", 14), "The.Long.Title");
+        assert_eq!(truncate_episode_title("The.Long.Title", 12), "The.Long");
+        assert_eq!(truncate_episode_title("The.Long.Title", 8), "The.Long");
+        assert_eq!(truncate_episode_title("The.Long.Title", 7), "The");
+        // A single word longer than the limit is cut mid-word
+        assert_eq!(truncate_episode_title("Supercalifragilistic", 5), "Super");
+        assert_eq!(truncate_episode_title("The.Long.Title", 0), "");
+    }
+
+    #[test]
+    fn truncation_keeps_utf8_boundaries() {
+        // Each character is 3 bytes so the cut has to move back to the start of one
+        let title = "日本語タイトル";
+        for max_length in 0..title.len() {
+            let truncated = truncate_episode_title(title, max_length);
+            assert!(truncated.len() <= max_length);
+            assert!(title.starts_with(truncated));
+        }
+        assert_eq!(truncate_episode_title(title, 7), "日本");
+    }
+
+    #[test]
+    fn long_title_is_truncated_to_max_filename_length() {
+        let long_title = "A Very Long Title That Keeps Going On And On";
+        let cache = new_cache("Show", &[(1, 1, Some(long_title))]);
+        let rules = new_filter_rules(json!({
+            "whitelist_tags": ["1080p"],
+            "max_filename_length": 40,
+        }));
+        let intent = get_file_intent("show.s01e01.[1080p].mkv", &rules, &cache);
+        assert_eq!(intent.action, Action::Rename);
+        assert_eq!(intent.dest, "Season 01/Show-S01E01-A.Very.Long.[1080p].mkv");
+        let filename = Path::new(intent.dest.as_str()).file_name().unwrap().to_string_lossy().to_string();
+        assert!(filename.len() <= 40);
+
+        // The episode index, tags and extension are kept even with no room for a title
+        let rules = new_filter_rules(json!({
+            "whitelist_tags": ["1080p"],
+            "max_filename_length": 10,
+        }));
+        let intent = get_file_intent("show.s01e01.[1080p].mkv", &rules, &cache);
+        assert_eq!(intent.dest, "Season 01/Show-S01E01.[1080p].mkv");
+    }
+
+    #[test]
+    fn truncated_file_is_complete_on_rescan() {
+        let cache = new_cache("Show", &[(1, 1, Some("A Very Long Title That Keeps Going On And On"))]);
+        let rules = new_filter_rules(json!({ "max_filename_length": 32 }));
+        let intent = get_file_intent("show.s01e01.mkv", &rules, &cache);
+        assert_eq!(intent.action, Action::Rename);
+
+        let intent = get_file_intent(intent.dest.as_str(), &rules, &cache);
+        assert_eq!(intent.action, Action::Complete);
+    }
+
+    #[test]
+    fn joined_titles_merge_shared_words() {
+        assert_eq!(join_episode_titles(Some("Finale Part 1"), Some("Finale Part 2")), "Finale Part 1 & 2");
+        assert_eq!(join_episode_titles(Some("Pilot"), Some("The Return")), "Pilot & The Return");
+        assert_eq!(join_episode_titles(Some("Pilot"), Some("Pilot")), "Pilot");
+        // The second title being a prefix of the first leaves nothing to merge
+        assert_eq!(join_episode_titles(Some("Finale Part 1"), Some("Finale")), "Finale Part 1 & Finale");
+    }
+
+    #[test]
+    fn joined_titles_with_one_missing() {
+        assert_eq!(join_episode_titles(Some("Pilot"), None), "Pilot");
+        assert_eq!(join_episode_titles(None, Some("The Return")), "The Return");
+        assert_eq!(join_episode_titles(None, None), "");
+    }
+
+    #[test]
+    fn episode_range_destination_joins_titles() {
+        let cache = new_cache("Show", &[
+            (1, 1, Some("Finale Part 1")),
+            (1, 2, Some("Finale Part 2")),
+            (1, 3, None),
+        ]);
+        let rules = new_filter_rules(json!({}));
+        let intent = get_file_intent("show.s01e01e02.mkv", &rules, &cache);
+        assert_eq!(intent.dest, "Season 01/Show-S01E01-E02-Finale.Part.1.2.mkv");
+
+        // The end episode has no title
+        let intent = get_file_intent("show.s01e02-e03.mkv", &rules, &cache);
+        assert_eq!(intent.dest, "Season 01/Show-S01E02-E03-Finale.Part.2.mkv");
+
+        // The end episode isn't in the cache at all
+        let intent = get_file_intent("show.s01e01-e05.mkv", &rules, &cache);
+        assert_eq!(intent.dest, "Season 01/Show-S01E01-E05-Finale.Part.1.mkv");
+    }
+
+    #[test]
+    fn empty_title_has_no_separator() {
+        let cache = new_cache("Show", &[
+            (1, 1, Some("")),
+            (1, 2, Some("   ")),
+            (1, 3, None),
+            (1, 4, Some("?!")),
+        ]);
+        let rules = new_filter_rules(json!({}));
+        assert_eq!(get_file_intent("show.s01e01.mkv", &rules, &cache).dest, "Season 01/Show-S01E01.mkv");
+        assert_eq!(get_file_intent("show.s01e02.mkv", &rules, &cache).dest, "Season 01/Show-S01E02.mkv");
+        assert_eq!(get_file_intent("show.s01e03.mkv", &rules, &cache).dest, "Season 01/Show-S01E03.mkv");
+        // Titles that are empty once cleaned are treated the same
+        assert_eq!(get_file_intent("show.s01e04.mkv", &rules, &cache).dest, "Season 01/Show-S01E04.mkv");
+        assert_eq!(get_file_intent("show.s01e01e02.mkv", &rules, &cache).dest, "Season 01/Show-S01E01-E02.mkv");
+    }
+
+    #[test]
+    fn destination_follows_episode_ordering() {
+        let rules = new_filter_rules(json!({}));
+        let cache = new_dvd_order_cache(EpisodeOrdering::Aired);
+        assert_eq!(get_file_intent("show.s01e01.mkv", &rules, &cache).dest, "Season 01/Show-S01E01-The.Train.Job.mkv");
+
+        let cache = new_dvd_order_cache(EpisodeOrdering::Dvd);
+        assert_eq!(get_file_intent("show.s01e01.mkv", &rules, &cache).dest, "Season 01/Show-S01E01-Serenity.mkv");
+        assert_eq!(get_file_intent("show.s01e02.mkv", &rules, &cache).dest, "Season 01/Show-S01E02-The.Train.Job.mkv");
+        // The aired episode isn't on the dvd so it has no title
+        assert_eq!(get_file_intent("show.s01e11.mkv", &rules, &cache).dest, "Season 01/Show-S01E11.mkv");
+    }
+
+    #[test]
+    fn unnamed_series_uses_display_name() {
+        let cache = new_cache("", &[(1, 1, Some("Pilot"))]);
+        let intent = get_file_intent("show.s01e01.mkv", &new_filter_rules(json!({})), &cache);
+        assert_eq!(intent.action, Action::Rename);
+        assert_eq!(intent.dest, "Season 01/unnamed.series.1-S01E01-Pilot.mkv");
+    }
+
+    fn new_absolute_cache() -> TvdbCache {
+        let mut cache = new_cache("Show", &[(1, 1, Some("Pilot")), (1, 2, Some("Return")), (2, 1, Some("Second"))]);
+        let episodes = cache.episodes.iter().enumerate()
+            .map(|(index, episode)| tvdb::models::Episode { absolute_number: Some(index as u32 + 1), ..episode.clone() })
+            .collect();
+        cache.merge_episodes(episodes);
+        cache
+    }
+
+    #[test]
+    fn absolute_number_is_renamed_to_season_and_episode() {
+        let cache = new_absolute_cache();
+        let rules = new_filter_rules(json!({}));
+        let intent = get_file_intent("[Group] Show - 03 [1080p].mkv", &rules, &cache);
+        assert_eq!(intent.action, Action::Rename);
+        assert_eq!(intent.descriptor, Some(EpisodeKey { season: 2, episode: 1 }));
+        assert!(intent.dest.starts_with("Season 02/Show-S02E01-Second"), "{}", intent.dest);
+    }
+
+    #[test]
+    fn unknown_absolute_number_falls_back_to_other_patterns() {
+        let cache = new_absolute_cache();
+        let rules = new_filter_rules(json!({}));
+        // There is no 45th episode so the season and episode are used instead
+        let intent = get_file_intent("Show S01E02 - 45.mkv", &rules, &cache);
+        assert_eq!(intent.descriptor, Some(EpisodeKey { season: 1, episode: 2 }));
+        let intent = get_file_intent("Show - 45.mkv", &rules, &cache);
+        assert_eq!(intent.descriptor, None);
+    }
+
+    #[test]
+    fn destinations_from_titles_are_always_valid() {
+        let rules = new_filter_rules(json!({}));
+        let titles = ["What If...?", "Part 1: \"Begin\" <again>", "Who | What * When", "Trailing. . ", "CON", "A\tB"];
+        for title in titles {
+            let cache = new_cache("Show: Reborn?", &[(1, 1, Some(title))]);
+            let intent = get_file_intent("show.s01e01.mkv", &rules, &cache);
+            assert_eq!(intent.action, Action::Rename, "{}", title);
+            assert_eq!(crate::destination::validate_destination(intent.dest.as_str(), usize::MAX), Ok(()), "{}", intent.dest);
+        }
+    }
+
+    #[test]
+    fn assigned_episode_with_and_without_cached_title() {
+        let cache = new_cache("Show", &[(1, 1, Some("Pilot")), (1, 2, None)]);
+        let rules = new_filter_rules(json!({ "whitelist_tags": ["1080p"] }));
+        // The parsed episode is replaced while tags from the filename are kept
+        let intent = get_assigned_file_intent("show.s01e05.[1080p].mkv", EpisodeKey { season: 1, episode: 1 }, &rules, &cache);
+        assert_eq!(intent.action, Action::Rename);
+        assert_eq!(intent.dest, "Season 01/Show-S01E01-Pilot.[1080p].mkv");
+        assert_eq!(intent.descriptor, Some(EpisodeKey { season: 1, episode: 1 }));
+
+        // Files without a parsed episode can be assigned as well
+        let intent = get_assigned_file_intent("trailer.mkv", EpisodeKey { season: 1, episode: 2 }, &rules, &cache);
+        assert_eq!((intent.action, intent.dest.as_str()), (Action::Rename, "Season 01/Show-S01E02.mkv"));
+        let intent = get_assigned_file_intent("trailer.mkv", EpisodeKey { season: 3, episode: 7 }, &rules, &cache);
+        assert_eq!((intent.action, intent.dest.as_str()), (Action::Rename, "Season 03/Show-S03E07.mkv"));
+        assert_eq!(intent.descriptor, Some(EpisodeKey { season: 3, episode: 7 }));
+
+        // Assigning the parsed episode gives the same intent as parsing it
+        let key = EpisodeKey { season: 1, episode: 1 };
+        let assigned = get_assigned_file_intent("show.s01e01.mkv", key, &rules, &cache);
+        let parsed = get_file_intent("show.s01e01.mkv", &rules, &cache);
+        assert_eq!((assigned.action, assigned.dest, assigned.descriptor), (parsed.action, parsed.dest, parsed.descriptor));
+        let intent = get_assigned_file_intent("Season 01/Show-S01E01-Pilot.mkv", key, &rules, &cache);
+        assert_eq!(intent.action, Action::Complete);
+    }
+}
@@ -0,0 +1,206 @@
+use enum_map;
+use std::sync::Arc;
+use crate::app_folder::{AppFolder, FolderSortMode, FolderStatus};
+use crate::file_intent::Action;
+
+// Folders that match the search and status filters in the order they are listed
+// Folders with errors are the only ones listed if errors only is set regardless of their status
+pub fn get_listed_folder_indices(
+    folders: &[Arc<AppFolder>],
+    folder_order: &[usize],
+    filters: &enum_map::EnumMap<FolderStatus, bool>,
+    is_errors_only: bool,
+    mut is_search_match: impl FnMut(&str) -> bool,
+) -> Vec<usize> {
+    folder_order
+        .iter()
+        .copied()
+        .filter(|index| {
+            let folder = &folders[*index];
+            is_search_match(folder.get_folder_name())
+                && filters[folder.get_folder_status()]
+                && (!is_errors_only || folder.has_errors())
+        })
+        .collect()
+}
+
+fn get_folder_status_rank(status: FolderStatus) -> usize {
+    match status {
+        FolderStatus::Pending => 0,
+        FolderStatus::Unknown => 1,
+        FolderStatus::Empty => 2,
+        FolderStatus::Done => 3,
+    }
+}
+
+// Busy folders don't have counts yet so they are listed after every other folder
+fn get_folder_pending_count(folder: &AppFolder) -> Option<usize> {
+    if folder.get_busy_lock().try_lock().is_err() {
+        return None;
+    }
+    let file_tracker = folder.get_file_tracker().try_read().ok()?;
+    Some(file_tracker.get_action_count()[Action::Rename])
+}
+
+// Folders that compare equal are kept in their loaded order which is by name
+pub fn get_sorted_folder_indices(folders: &[Arc<AppFolder>], sort_mode: FolderSortMode) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..folders.len()).collect();
+    match sort_mode {
+        FolderSortMode::Name => {},
+        FolderSortMode::Status => indices.sort_by_key(|index| {
+            let folder = &folders[*index];
+            let is_busy = folder.get_busy_lock().try_lock().is_err();
+            (is_busy, get_folder_status_rank(folder.get_folder_status()))
+        }),
+        FolderSortMode::PendingDesc => indices.sort_by_key(|index| {
+            let pending_count = get_folder_pending_count(&folders[*index]);
+            (pending_count.is_none(), std::cmp::Reverse(pending_count))
+        }),
+    }
+    indices
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FolderNavigation {
+    Previous,
+    Next,
+    First,
+    Last,
+}
+
+// Moving past either end stays on the first or last folder
+pub fn get_navigation_index(total_listed: usize, current_index: Option<usize>, navigation: FolderNavigation) -> Option<usize> {
+    let last_index = total_listed.checked_sub(1)?;
+    let index = match (navigation, current_index) {
+        (FolderNavigation::First, _) | (FolderNavigation::Next, None) => 0,
+        (FolderNavigation::Last, _) | (FolderNavigation::Previous, None) => last_index,
+        (FolderNavigation::Previous, Some(index)) => index.saturating_sub(1),
+        (FolderNavigation::Next, Some(index)) => (index + 1).min(last_index),
+    };
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::test_fixtures::{create_files, new_filter_rules, write_cache_file};
+
+    // Folders are loaded so each one has a different status, the last one is never loaded
+    async fn new_status_folders(root: &std::path::Path) -> Vec<Arc<AppFolder>> {
+        create_files(root, &["Alpha/show.s01e01.mkv", "Bravo/Season 01/Show-S01E01-Pilot.mkv", "Delta/show.s01e01.mkv"]);
+        std::fs::create_dir_all(root.join("Charlie")).unwrap();
+        let rules = Arc::new(new_filter_rules(json!({})));
+        let mut folders = Vec::new();
+        for name in ["Alpha", "Bravo", "Charlie", "Delta"] {
+            let folder_path = root.join(name);
+            let folder = AppFolder::new(root.to_string_lossy().as_ref(), folder_path.to_string_lossy().as_ref(), rules.clone());
+            if name != "Delta" {
+                write_cache_file(folder_path.as_path(), "Show", &[(1, 1, Some("Pilot"))]);
+                folder.perform_initial_load(None).await.unwrap();
+            }
+            folders.push(Arc::new(folder));
+        }
+        folders
+    }
+
+    #[tokio::test]
+    async fn folders_are_listed_in_order_if_they_match() {
+        let root = tempfile::tempdir().unwrap();
+        let folders = new_status_folders(root.path()).await;
+        let statuses: Vec<FolderStatus> = folders.iter().map(|folder| folder.get_folder_status()).collect();
+        assert_eq!(statuses, vec![FolderStatus::Pending, FolderStatus::Done, FolderStatus::Empty, FolderStatus::Unknown]);
+
+        let all_filters = enum_map::enum_map! { _ => true };
+        let order = [3, 1, 0, 2];
+        assert_eq!(get_listed_folder_indices(&folders, &order, &all_filters, false, |_| true), vec![3, 1, 0, 2]);
+        // Only the listed order is used so folders left out of it are never listed
+        assert_eq!(get_listed_folder_indices(&folders, &[2, 0], &all_filters, false, |_| true), vec![2, 0]);
+        assert!(get_listed_folder_indices(&folders, &order, &all_filters, false, |_| false).is_empty());
+        assert_eq!(get_listed_folder_indices(&folders, &order, &all_filters, false, |name| name.ends_with('a')), vec![3, 0]);
+
+        let mut filters = all_filters;
+        filters[FolderStatus::Done] = false;
+        filters[FolderStatus::Unknown] = false;
+        assert_eq!(get_listed_folder_indices(&folders, &order, &filters, false, |_| true), vec![0, 2]);
+        assert_eq!(get_listed_folder_indices(&folders, &order, &filters, false, |name| name != "Alpha"), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn errors_only_lists_folders_with_errors() {
+        let root = tempfile::tempdir().unwrap();
+        let folders = new_status_folders(root.path()).await;
+        for index in [1, 3] {
+            folders[index].get_errors().write().await.push_warn("Cache", "Failed to refresh");
+        }
+        let all_filters = enum_map::enum_map! { _ => true };
+        let order = [0, 1, 2, 3];
+        assert_eq!(get_listed_folder_indices(&folders, &order, &all_filters, true, |_| true), vec![1, 3]);
+
+        // Status filters still apply to folders with errors
+        let mut filters = all_filters;
+        filters[FolderStatus::Unknown] = false;
+        assert_eq!(get_listed_folder_indices(&folders, &order, &filters, true, |_| true), vec![1]);
+
+        folders[1].clear_errors().await;
+        assert_eq!(get_listed_folder_indices(&folders, &order, &all_filters, true, |_| true), vec![3]);
+    }
+
+    #[test]
+    fn navigation_stays_within_listed_folders() {
+        use FolderNavigation::*;
+        let cases = [
+            (Next, None, Some(0)),
+            (Previous, None, Some(3)),
+            (Next, Some(1), Some(2)),
+            (Previous, Some(1), Some(0)),
+            (Next, Some(3), Some(3)),
+            (Previous, Some(0), Some(0)),
+            (First, Some(2), Some(0)),
+            (Last, Some(1), Some(3)),
+            (First, None, Some(0)),
+            (Last, None, Some(3)),
+        ];
+        for (navigation, current_index, expected) in cases {
+            assert_eq!(get_navigation_index(4, current_index, navigation), expected, "{:?} from {:?}", navigation, current_index);
+        }
+        for navigation in [Previous, Next, First, Last] {
+            assert_eq!(get_navigation_index(0, None, navigation), None);
+            assert_eq!(get_navigation_index(1, Some(0), navigation), Some(0));
+        }
+    }
+
+    #[tokio::test]
+    async fn folders_are_sorted_by_status_with_busy_folders_last() {
+        let root = tempfile::tempdir().unwrap();
+        let folders = new_status_folders(root.path()).await;
+        assert_eq!(get_sorted_folder_indices(&folders, FolderSortMode::Name), vec![0, 1, 2, 3]);
+        assert_eq!(get_sorted_folder_indices(&folders, FolderSortMode::Status), vec![0, 3, 2, 1]);
+
+        let _busy_lock = folders[0].get_busy_lock().try_lock().unwrap();
+        assert_eq!(get_sorted_folder_indices(&folders, FolderSortMode::Status), vec![3, 2, 1, 0]);
+        assert_eq!(get_sorted_folder_indices(&folders, FolderSortMode::Name), vec![0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn folders_are_sorted_by_most_pending_renames() {
+        let root = tempfile::tempdir().unwrap();
+        create_files(root.path(), &[
+            "Alpha/show.s01e01.mkv", "Bravo/show.s01e01.mkv", "Bravo/show.s01e02.mkv", "Charlie/a.txt", "Delta/show.s01e01.mkv",
+        ]);
+        let rules = Arc::new(new_filter_rules(json!({})));
+        let mut folders = Vec::new();
+        for name in ["Alpha", "Bravo", "Charlie", "Delta"] {
+            let folder_path = root.path().join(name);
+            write_cache_file(folder_path.as_path(), "Show", &[(1, 1, Some("Pilot")), (1, 2, Some("Return"))]);
+            let folder = AppFolder::new(root.path().to_string_lossy().as_ref(), folder_path.to_string_lossy().as_ref(), rules.clone());
+            folder.perform_initial_load(None).await.unwrap();
+            folders.push(Arc::new(folder));
+        }
+        // Folders with the same count keep their loaded order
+        assert_eq!(get_sorted_folder_indices(&folders, FolderSortMode::PendingDesc), vec![1, 0, 3, 2]);
+
+        let _busy_lock = folders[1].get_busy_lock().try_lock().unwrap();
+        assert_eq!(get_sorted_folder_indices(&folders, FolderSortMode::PendingDesc), vec![0, 3, 2, 1]);
+    }
+}
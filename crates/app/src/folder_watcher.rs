@@ -0,0 +1,136 @@
+use notify;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+use tokio;
+
+// Torrent clients write to files in bursts so we wait for the folder to be quiet before rescanning
+pub const WATCH_DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum WatchTarget {
+    // The folder structure changed so the root needs to be reloaded
+    Root,
+    // Path of a new folder at the scan depth
+    AddFolder(String),
+    // Path of a folder that no longer exists
+    RemoveFolder(String),
+    // Path of the folder that needs to be rescanned
+    Folder(String),
+}
+
+// Each key is only processed after no events have been pushed for it for the delay
+pub struct EventDebouncer<K> {
+    delay: Duration,
+    deadlines: HashMap<K, Instant>,
+}
+
+impl<K: Hash + Eq + Clone> EventDebouncer<K> {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            deadlines: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, key: K, now: Instant) {
+        self.deadlines.insert(key, now + self.delay);
+    }
+
+    pub fn get_next_deadline(&self) -> Option<Instant> {
+        self.deadlines.values().min().copied()
+    }
+
+    pub fn pop_ready(&mut self, now: Instant) -> Vec<K> {
+        let ready: Vec<K> = self.deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in ready.iter() {
+            self.deadlines.remove(key);
+        }
+        ready
+    }
+}
+
+// Events that can't change which files are in a folder
+pub fn is_ignored_watch_event(event: &notify::Event) -> bool {
+    use notify::event::{EventKind, ModifyKind};
+    matches!(event.kind, EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(_)))
+}
+
+// Keeps the filesystem watcher alive and stops the event loop when dropped
+pub struct FolderWatcher {
+    _watcher: notify::RecommendedWatcher,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl FolderWatcher {
+    pub fn new(watcher: notify::RecommendedWatcher, task: tokio::task::JoinHandle<()>) -> Self {
+        Self {
+            _watcher: watcher,
+            task,
+        }
+    }
+}
+
+impl Drop for FolderWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn key_is_ready_after_quiet_period() {
+        let start = Instant::now();
+        let mut debouncer = EventDebouncer::new(DELAY);
+        debouncer.push("Show", start);
+        assert_eq!(debouncer.get_next_deadline(), Some(start + DELAY));
+        assert!(debouncer.pop_ready(start + Duration::from_secs(1)).is_empty());
+        assert_eq!(debouncer.pop_ready(start + DELAY), vec!["Show"]);
+        // Keys are only returned once
+        assert!(debouncer.pop_ready(start + DELAY).is_empty());
+        assert_eq!(debouncer.get_next_deadline(), None);
+    }
+
+    #[test]
+    fn burst_of_events_delays_key() {
+        let start = Instant::now();
+        let mut debouncer = EventDebouncer::new(DELAY);
+        for seconds in 0..5 {
+            let now = start + Duration::from_secs(seconds);
+            assert!(debouncer.pop_ready(now).is_empty());
+            debouncer.push("Show", now);
+        }
+        let last_event = start + Duration::from_secs(4);
+        assert!(debouncer.pop_ready(last_event + DELAY - Duration::from_millis(1)).is_empty());
+        assert_eq!(debouncer.pop_ready(last_event + DELAY), vec!["Show"]);
+    }
+
+    #[test]
+    fn keys_are_debounced_separately() {
+        let start = Instant::now();
+        let mut debouncer = EventDebouncer::new(DELAY);
+        debouncer.push("Show A", start);
+        debouncer.push("Show B", start + Duration::from_secs(1));
+        assert_eq!(debouncer.get_next_deadline(), Some(start + DELAY));
+        assert_eq!(debouncer.pop_ready(start + DELAY), vec!["Show A"]);
+        assert_eq!(debouncer.get_next_deadline(), Some(start + Duration::from_secs(1) + DELAY));
+        assert_eq!(debouncer.pop_ready(start + Duration::from_secs(3)), vec!["Show B"]);
+    }
+
+    #[test]
+    fn access_and_metadata_events_are_ignored() {
+        use notify::event::{AccessKind, CreateKind, EventKind, MetadataKind, ModifyKind};
+        assert!(is_ignored_watch_event(&notify::Event::new(EventKind::Access(AccessKind::Any))));
+        assert!(is_ignored_watch_event(&notify::Event::new(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)))));
+        assert!(!is_ignored_watch_event(&notify::Event::new(EventKind::Create(CreateKind::File))));
+    }
+}
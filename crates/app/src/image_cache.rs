@@ -0,0 +1,171 @@
+use std::collections::{HashMap, HashSet};
+use std::path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::metadata_provider::MetadataProvider;
+
+pub(crate) const PATH_STR_ARTWORK_FOLDER: &str = ".artwork";
+
+// Artwork paths come from the api so each component is checked before it becomes a filename
+// The path is flattened so that artwork in different folders can't collide, i.e. "posters/1-1.jpg" becomes "posters_1-1.jpg"
+pub fn get_artwork_filename(relative_path: &str) -> Option<String> {
+    let mut components = Vec::new();
+    for component in relative_path.split('/').filter(|component| !component.is_empty()) {
+        if component == "." || component == ".." {
+            return None;
+        }
+        let is_valid = component.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+        if !is_valid {
+            return None;
+        }
+        components.push(component);
+    }
+    match components.is_empty() {
+        true => None,
+        false => Some(components.join("_")),
+    }
+}
+
+// Artwork is optional so failures result in no image instead of an error
+pub struct ImageCache {
+    folder_path: String,
+    images: Mutex<HashMap<String, Arc<Vec<u8>>>>,
+    // Whether a provider was available when we tried, so missing artwork is fetched again after logging in
+    attempts: Mutex<HashMap<String, bool>>,
+    loading: Mutex<HashSet<String>>,
+}
+
+impl ImageCache {
+    pub fn new(folder_path: &str) -> Self {
+        Self {
+            folder_path: folder_path.to_string(),
+            images: Mutex::new(HashMap::new()),
+            attempts: Mutex::new(HashMap::new()),
+            loading: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn get_folder_path(&self) -> &str {
+        self.folder_path.as_str()
+    }
+
+    // Doesn't wait for the lock so it can be called every frame
+    pub fn get_cached(&self, relative_path: &str) -> Option<Arc<Vec<u8>>> {
+        let filename = get_artwork_filename(relative_path)?;
+        self.images.try_lock().ok()?.get(&filename).cloned()
+    }
+
+    // Doesn't wait for the lock so it can be called every frame
+    pub fn is_loading(&self, relative_path: &str) -> bool {
+        let filename = match get_artwork_filename(relative_path) {
+            Some(filename) => filename,
+            None => return false,
+        };
+        self.loading.try_lock().map(|loading| loading.contains(&filename)).unwrap_or(true)
+    }
+
+    pub async fn get_or_fetch(&self, provider: Option<&dyn MetadataProvider>, relative_path: &str) -> Option<Arc<Vec<u8>>> {
+        let filename = get_artwork_filename(relative_path)?;
+        if let Some(data) = self.images.lock().await.get(&filename) {
+            return Some(data.clone());
+        }
+
+        {
+            let mut attempts = self.attempts.lock().await;
+            let is_provider = provider.is_some();
+            match attempts.get(&filename) {
+                Some(true) => return None,
+                Some(false) if !is_provider => return None,
+                _ => attempts.insert(filename.clone(), is_provider),
+            };
+        }
+
+        self.loading.lock().await.insert(filename.clone());
+        let data = self.fetch(provider, relative_path, filename.as_str()).await.map(Arc::new);
+        // The image is inserted first so it is never considered missing while it is being loaded
+        if let Some(data) = data.as_ref() {
+            self.images.lock().await.insert(filename.clone(), data.clone());
+        }
+        self.loading.lock().await.remove(&filename);
+        data
+    }
+
+    async fn fetch(&self, provider: Option<&dyn MetadataProvider>, relative_path: &str, filename: &str) -> Option<Vec<u8>> {
+        let filepath = path::Path::new(self.folder_path.as_str()).join(filename);
+        if let Ok(data) = tokio::fs::read(&filepath).await {
+            return Some(data);
+        }
+        let data = provider?.download_image(relative_path).await.ok()?;
+        // The image can still be shown if it couldn't be saved
+        if tokio::fs::create_dir_all(self.folder_path.as_str()).await.is_ok() {
+            let _ = tokio::fs::write(&filepath, data.as_slice()).await;
+        }
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_provider::MockProvider;
+
+    #[test]
+    fn artwork_paths_are_flattened() {
+        assert_eq!(get_artwork_filename("posters/78874-1.jpg").as_deref(), Some("posters_78874-1.jpg"));
+        assert_eq!(get_artwork_filename("/posters//78874-1.jpg").as_deref(), Some("posters_78874-1.jpg"));
+        assert_eq!(get_artwork_filename("episodes/78874/297989.jpg").as_deref(), Some("episodes_78874_297989.jpg"));
+    }
+
+    #[test]
+    fn unsafe_artwork_paths_are_rejected() {
+        assert_eq!(get_artwork_filename(""), None);
+        assert_eq!(get_artwork_filename("/"), None);
+        assert_eq!(get_artwork_filename("../posters/1.jpg"), None);
+        assert_eq!(get_artwork_filename("posters/../../1.jpg"), None);
+        assert_eq!(get_artwork_filename("posters/./1.jpg"), None);
+        assert_eq!(get_artwork_filename("posters\\..\\1.jpg"), None);
+        assert_eq!(get_artwork_filename("C:/posters/1.jpg"), None);
+        assert_eq!(get_artwork_filename("posters/poster 1.jpg"), None);
+    }
+
+    #[tokio::test]
+    async fn fetched_image_is_cached_in_memory_and_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder_path = dir.path().join(PATH_STR_ARTWORK_FOLDER);
+        let folder_path = folder_path.to_string_lossy();
+        let mut provider = MockProvider::new();
+        provider.images.insert("posters/1.jpg".to_string(), vec![1, 2, 3]);
+
+        let image_cache = ImageCache::new(folder_path.as_ref());
+        assert!(image_cache.get_cached("posters/1.jpg").is_none());
+        let data = image_cache.get_or_fetch(Some(&provider), "posters/1.jpg").await.unwrap();
+        assert_eq!(data.as_slice(), &[1, 2, 3]);
+        assert_eq!(std::fs::read(dir.path().join(".artwork/posters_1.jpg")).unwrap(), vec![1, 2, 3]);
+        assert!(!image_cache.is_loading("posters/1.jpg"));
+
+        // Later requests are answered without the provider
+        let cached = image_cache.get_or_fetch(None, "posters/1.jpg").await.unwrap();
+        assert!(Arc::ptr_eq(&data, &cached));
+        assert!(Arc::ptr_eq(&data, &image_cache.get_cached("/posters/1.jpg").unwrap()));
+
+        // The saved image is used by a new cache
+        let image_cache = ImageCache::new(folder_path.as_ref());
+        let data = image_cache.get_or_fetch(Some(&MockProvider::new()), "posters/1.jpg").await.unwrap();
+        assert_eq!(data.as_slice(), &[1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn missing_image_is_fetched_again_after_login() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder_path = dir.path().join(PATH_STR_ARTWORK_FOLDER);
+        let image_cache = ImageCache::new(folder_path.to_string_lossy().as_ref());
+        assert!(image_cache.get_or_fetch(None, "posters/1.jpg").await.is_none());
+        assert!(!folder_path.exists());
+
+        let mut provider = MockProvider::new();
+        provider.images.insert("posters/1.jpg".to_string(), vec![4]);
+        assert_eq!(image_cache.get_or_fetch(Some(&provider), "posters/1.jpg").await.unwrap().as_slice(), &[4]);
+        // Unsafe paths never reach the provider
+        assert!(image_cache.get_or_fetch(Some(&provider), "../posters/1.jpg").await.is_none());
+    }
+}
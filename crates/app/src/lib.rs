@@ -1,9 +1,26 @@
 pub mod app;
+pub mod app_settings;
 pub mod app_folder;
 pub mod app_file;
+pub mod atomic_write;
+pub mod change_event;
+pub mod destination;
 pub mod tvdb_cache;
 pub mod bookmarks;
+pub mod error_log;
+pub mod folder_list;
+pub mod folder_watcher;
 pub mod file_descriptor;
 pub mod file_intent;
+pub mod image_cache;
+pub mod metadata_provider;
+pub mod rename_diff;
+pub mod series_cache_registry;
+pub mod series_mapping;
+pub mod series_matcher;
 pub mod transliterate;
+pub mod undo_journal;
+
+#[cfg(test)]
+mod test_fixtures;
 
@@ -0,0 +1,297 @@
+use async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tvdb::api::{ApiError, LoginSession};
+use tvdb::models::{Actor, Episode, EpisodesSummary, ProviderKind, Series};
+use tvdb::tmdb::TmdbSession;
+use tvdb::tvmaze::TvMazeSession;
+use crate::tvdb_cache::{EpisodeOrdering, get_episode_key};
+
+// Source of series metadata so that the app isn't tied to a single api
+#[async_trait::async_trait]
+pub trait MetadataProvider: Send + Sync {
+    // Series ids are only meaningful to the api they came from
+    fn get_kind(&self) -> ProviderKind;
+    // Whether requests can currently be made, i.e. the login session hasn't expired
+    fn is_ready(&self) -> bool;
+    // Time left until requests are accepted again after being rate limited
+    fn get_rate_limit_delay(&self) -> Option<Duration>;
+    async fn search(&self, query: &str, language: Option<&str>) -> Result<Vec<Series>, ApiError>;
+    async fn get_series(&self, id: u32, language: Option<&str>) -> Result<Series, ApiError>;
+    async fn get_episodes(&self, id: u32, language: Option<&str>) -> Result<Vec<Episode>, ApiError>;
+    // The season is numbered using the ordering
+    async fn get_season_episodes(
+        &self, id: u32, season: u32, ordering: EpisodeOrdering, language: Option<&str>,
+    ) -> Result<Vec<Episode>, ApiError>;
+    async fn get_actors(&self, id: u32) -> Result<Vec<Actor>, ApiError>;
+    async fn get_episodes_summary(&self, id: u32) -> Result<EpisodesSummary, ApiError>;
+    // Ids of series which were updated since the unix timestamp in seconds
+    async fn get_updated_series(&self, from_time: u64) -> Result<Vec<u32>, ApiError>;
+    async fn download_image(&self, relative_path: &str) -> Result<Vec<u8>, ApiError>;
+}
+
+#[async_trait::async_trait]
+impl MetadataProvider for LoginSession {
+    fn get_kind(&self) -> ProviderKind {
+        ProviderKind::Tvdb
+    }
+
+    fn is_ready(&self) -> bool {
+        !self.is_expired()
+    }
+
+    fn get_rate_limit_delay(&self) -> Option<Duration> {
+        LoginSession::get_rate_limit_delay(self)
+    }
+
+    async fn search(&self, query: &str, language: Option<&str>) -> Result<Vec<Series>, ApiError> {
+        self.search_series(&query.to_string(), language).await
+    }
+
+    async fn get_series(&self, id: u32, language: Option<&str>) -> Result<Series, ApiError> {
+        LoginSession::get_series(self, id, language).await
+    }
+
+    async fn get_episodes(&self, id: u32, language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        LoginSession::get_episodes(self, id, language).await
+    }
+
+    async fn get_season_episodes(
+        &self, id: u32, season: u32, ordering: EpisodeOrdering, language: Option<&str>,
+    ) -> Result<Vec<Episode>, ApiError> {
+        match ordering {
+            EpisodeOrdering::Aired => self.get_episodes_by_season(id, season, language).await,
+            EpisodeOrdering::Dvd => self.get_episodes_by_dvd_season(id, season, language).await,
+        }
+    }
+
+    async fn get_actors(&self, id: u32) -> Result<Vec<Actor>, ApiError> {
+        LoginSession::get_actors(self, id).await
+    }
+
+    async fn get_episodes_summary(&self, id: u32) -> Result<EpisodesSummary, ApiError> {
+        LoginSession::get_episodes_summary(self, id).await
+    }
+
+    async fn get_updated_series(&self, from_time: u64) -> Result<Vec<u32>, ApiError> {
+        LoginSession::get_updated_series(self, from_time).await
+    }
+
+    async fn download_image(&self, relative_path: &str) -> Result<Vec<u8>, ApiError> {
+        LoginSession::download_image(self, relative_path).await
+    }
+}
+
+#[async_trait::async_trait]
+impl MetadataProvider for TmdbSession {
+    fn get_kind(&self) -> ProviderKind {
+        ProviderKind::Tmdb
+    }
+
+    // Api keys don't expire
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    fn get_rate_limit_delay(&self) -> Option<Duration> {
+        TmdbSession::get_rate_limit_delay(self)
+    }
+
+    async fn search(&self, query: &str, language: Option<&str>) -> Result<Vec<Series>, ApiError> {
+        self.search_series(query, language).await
+    }
+
+    async fn get_series(&self, id: u32, language: Option<&str>) -> Result<Series, ApiError> {
+        TmdbSession::get_series(self, id, language).await
+    }
+
+    async fn get_episodes(&self, id: u32, language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        TmdbSession::get_episodes(self, id, language).await
+    }
+
+    // Tmdb only provides the aired ordering
+    async fn get_season_episodes(
+        &self, id: u32, season: u32, _ordering: EpisodeOrdering, language: Option<&str>,
+    ) -> Result<Vec<Episode>, ApiError> {
+        TmdbSession::get_season_episodes(self, id, season, language).await
+    }
+
+    async fn get_actors(&self, id: u32) -> Result<Vec<Actor>, ApiError> {
+        TmdbSession::get_actors(self, id).await
+    }
+
+    async fn get_episodes_summary(&self, id: u32) -> Result<EpisodesSummary, ApiError> {
+        TmdbSession::get_episodes_summary(self, id).await
+    }
+
+    async fn get_updated_series(&self, from_time: u64) -> Result<Vec<u32>, ApiError> {
+        TmdbSession::get_updated_series(self, from_time).await
+    }
+
+    async fn download_image(&self, relative_path: &str) -> Result<Vec<u8>, ApiError> {
+        TmdbSession::download_image(self, relative_path).await
+    }
+}
+
+#[async_trait::async_trait]
+impl MetadataProvider for TvMazeSession {
+    fn get_kind(&self) -> ProviderKind {
+        ProviderKind::TvMaze
+    }
+
+    // No login is needed
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    fn get_rate_limit_delay(&self) -> Option<Duration> {
+        TvMazeSession::get_rate_limit_delay(self)
+    }
+
+    // TVmaze only has names in their original language
+    async fn search(&self, query: &str, _language: Option<&str>) -> Result<Vec<Series>, ApiError> {
+        self.search_series(query).await
+    }
+
+    async fn get_series(&self, id: u32, _language: Option<&str>) -> Result<Series, ApiError> {
+        TvMazeSession::get_series(self, id).await
+    }
+
+    async fn get_episodes(&self, id: u32, _language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        TvMazeSession::get_episodes(self, id).await
+    }
+
+    // Seasons are looked up by their own id so it's simpler to filter the full list which is a single request
+    async fn get_season_episodes(
+        &self, id: u32, season: u32, _ordering: EpisodeOrdering, _language: Option<&str>,
+    ) -> Result<Vec<Episode>, ApiError> {
+        let episodes = TvMazeSession::get_episodes(self, id).await?;
+        Ok(episodes.into_iter().filter(|episode| episode.season == season).collect())
+    }
+
+    async fn get_actors(&self, id: u32) -> Result<Vec<Actor>, ApiError> {
+        TvMazeSession::get_actors(self, id).await
+    }
+
+    async fn get_episodes_summary(&self, id: u32) -> Result<EpisodesSummary, ApiError> {
+        TvMazeSession::get_episodes_summary(self, id).await
+    }
+
+    async fn get_updated_series(&self, from_time: u64) -> Result<Vec<u32>, ApiError> {
+        TvMazeSession::get_updated_series(self, from_time).await
+    }
+
+    async fn download_image(&self, relative_path: &str) -> Result<Vec<u8>, ApiError> {
+        TvMazeSession::download_image(self, relative_path).await
+    }
+}
+
+// Tells the user how long to wait instead of the raw response so they don't keep retrying
+// i.e. "TVDB rate limited while fetching series, try again in 30s"
+pub fn get_rate_limit_message(kind: ProviderKind, action: &str, err: &ApiError) -> Option<String> {
+    if !err.is_rate_limited() {
+        return None;
+    }
+    let message = match err.get_retry_after() {
+        Some(delay) => format!("{} rate limited while {}, try again in {}s", kind.to_str(), action, delay.as_secs_f64().ceil()),
+        None => format!("{} rate limited while {}, try again later", kind.to_str(), action),
+    };
+    Some(message)
+}
+
+fn get_not_found_error() -> ApiError {
+    ApiError::UnexpectedResponse(reqwest::StatusCode::NOT_FOUND, "Not found in mock provider".to_string())
+}
+
+// Serves metadata from memory so the app can be used without network access
+#[derive(Default)]
+pub struct MockProvider {
+    pub kind: ProviderKind,
+    pub series: Vec<Series>,
+    pub episodes: HashMap<u32, Vec<Episode>>,
+    pub actors: HashMap<u32, Vec<Actor>>,
+    pub episodes_summaries: HashMap<u32, EpisodesSummary>,
+    pub updated_series: Vec<u32>,
+    pub images: HashMap<String, Vec<u8>>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_provider(self) -> Arc<dyn MetadataProvider> {
+        Arc::new(self)
+    }
+}
+
+#[async_trait::async_trait]
+impl MetadataProvider for MockProvider {
+    fn get_kind(&self) -> ProviderKind {
+        self.kind
+    }
+
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    fn get_rate_limit_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    async fn search(&self, query: &str, _language: Option<&str>) -> Result<Vec<Series>, ApiError> {
+        let query = query.to_lowercase();
+        Ok(self.series.iter().filter(|series| series.name.to_lowercase().contains(query.as_str())).cloned().collect())
+    }
+
+    async fn get_series(&self, id: u32, _language: Option<&str>) -> Result<Series, ApiError> {
+        self.series.iter().find(|series| series.id == id).cloned().ok_or_else(get_not_found_error)
+    }
+
+    async fn get_episodes(&self, id: u32, _language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        self.episodes.get(&id).cloned().ok_or_else(get_not_found_error)
+    }
+
+    async fn get_season_episodes(
+        &self, id: u32, season: u32, ordering: EpisodeOrdering, language: Option<&str>,
+    ) -> Result<Vec<Episode>, ApiError> {
+        let episodes = self.get_episodes(id, language).await?;
+        Ok(episodes.into_iter().filter(|episode| get_episode_key(episode, ordering).season == season).collect())
+    }
+
+    async fn get_actors(&self, id: u32) -> Result<Vec<Actor>, ApiError> {
+        Ok(self.actors.get(&id).cloned().unwrap_or_default())
+    }
+
+    async fn get_episodes_summary(&self, id: u32) -> Result<EpisodesSummary, ApiError> {
+        self.episodes_summaries.get(&id).cloned().ok_or_else(get_not_found_error)
+    }
+
+    async fn get_updated_series(&self, _from_time: u64) -> Result<Vec<u32>, ApiError> {
+        Ok(self.updated_series.clone())
+    }
+
+    async fn download_image(&self, relative_path: &str) -> Result<Vec<u8>, ApiError> {
+        self.images.get(relative_path).cloned().ok_or_else(get_not_found_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_message_includes_delay() {
+        let err = ApiError::RateLimited { retry_after: Some(Duration::from_millis(29_500)), message: "".to_string() };
+        let message = get_rate_limit_message(ProviderKind::Tvdb, "fetching series", &err);
+        assert_eq!(message.as_deref(), Some("TVDB rate limited while fetching series, try again in 30s"));
+
+        let err = ApiError::Retried { error: Box::new(ApiError::RateLimited { retry_after: None, message: "".to_string() }), retries: 2 };
+        let message = get_rate_limit_message(ProviderKind::Tvdb, "fetching series", &err);
+        assert_eq!(message.as_deref(), Some("TVDB rate limited while fetching series, try again later"));
+
+        let err = ApiError::UnexpectedResponse(reqwest::StatusCode::INTERNAL_SERVER_ERROR, "".to_string());
+        assert_eq!(get_rate_limit_message(ProviderKind::Tvdb, "fetching series", &err), None);
+    }
+}
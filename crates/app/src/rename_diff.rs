@@ -0,0 +1,116 @@
+// Words and numbers are compared as a whole while separators are compared on their own
+// This keeps the changes readable, i.e. "S01E01" is changed as one run instead of character by character
+fn split_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut token_start = None;
+    for (index, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            token_start.get_or_insert(index);
+            continue;
+        }
+        if let Some(start) = token_start.take() {
+            tokens.push(&text[start..index]);
+        }
+        tokens.push(&text[index..index+c.len_utf8()]);
+    }
+    if let Some(start) = token_start {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+// Splits the destination into runs which are either kept from the source or changed by the rename
+// Tokens are matched using their longest common subsequence so moved tokens are shown as changed
+pub fn diff_segments<'a>(src: &str, dest: &'a str) -> Vec<(&'a str, bool)> {
+    let src_tokens = split_tokens(src);
+    let dest_tokens = split_tokens(dest);
+
+    // lengths[i][j] is the longest common subsequence of src_tokens[i..] and dest_tokens[j..]
+    let mut lengths = vec![vec![0usize; dest_tokens.len()+1]; src_tokens.len()+1];
+    for i in (0..src_tokens.len()).rev() {
+        for j in (0..dest_tokens.len()).rev() {
+            lengths[i][j] = match src_tokens[i] == dest_tokens[j] {
+                true => lengths[i+1][j+1] + 1,
+                false => lengths[i+1][j].max(lengths[i][j+1]),
+            };
+        }
+    }
+
+    let mut is_dest_changed = vec![true; dest_tokens.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < src_tokens.len() && j < dest_tokens.len() {
+        if src_tokens[i] == dest_tokens[j] {
+            is_dest_changed[j] = false;
+            i += 1;
+            j += 1;
+        } else if lengths[i+1][j] >= lengths[i][j+1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    // Tokens are contiguous so neighbouring tokens with the same state are joined into one slice
+    let mut segments = Vec::new();
+    let mut run_start = 0;
+    let mut run_end = 0;
+    let mut run_is_changed = None;
+    for (token, is_changed) in dest_tokens.iter().zip(is_dest_changed) {
+        if let Some(last_is_changed) = run_is_changed.filter(|last_is_changed| *last_is_changed != is_changed) {
+            segments.push((&dest[run_start..run_end], last_is_changed));
+            run_start = run_end;
+        }
+        run_is_changed = Some(is_changed);
+        run_end += token.len();
+    }
+    if let Some(is_changed) = run_is_changed {
+        segments.push((&dest[run_start..run_end], is_changed));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_segments(src: &str, dest: &str, expected: &[(&str, bool)]) {
+        let segments = diff_segments(src, dest);
+        assert_eq!(segments.as_slice(), expected, "{} -> {}", src, dest);
+        assert_eq!(segments.iter().map(|(segment, _)| *segment).collect::<String>(), dest);
+    }
+
+    #[test]
+    fn episode_title_insertion_is_changed() {
+        assert_segments("Show-S01E01.mkv", "Show-S01E01-Pilot.mkv", &[("Show-S01E01", false), ("-Pilot", true), (".mkv", false)]);
+    }
+
+    #[test]
+    fn season_folder_addition_is_changed() {
+        assert_segments(
+            "Show-S01E01-Pilot.mkv", "Season 01/Show-S01E01-Pilot.mkv",
+            &[("Season 01/", true), ("Show-S01E01-Pilot.mkv", false)],
+        );
+    }
+
+    #[test]
+    fn reordered_tags_are_changed() {
+        assert_segments(
+            "Show.720p.x264.S01E01.mkv", "Show.x264.720p.S01E01.mkv",
+            &[("Show.x264.", false), ("720p.", true), ("S01E01.mkv", false)],
+        );
+    }
+
+    #[test]
+    fn words_are_changed_as_a_whole() {
+        assert_segments("Show-S01E02.mkv", "Show-S01E01.mkv", &[("Show-", false), ("S01E01", true), (".mkv", false)]);
+        assert_segments("abc.mkv", "xyz.avi", &[("xyz", true), (".", false), ("avi", true)]);
+        assert_segments("Café.mkv", "Café-Über.mkv", &[("Café", false), ("-Über", true), (".mkv", false)]);
+    }
+
+    #[test]
+    fn unchanged_and_empty_destinations() {
+        assert_segments("Show-S01E01.mkv", "Show-S01E01.mkv", &[("Show-S01E01.mkv", false)]);
+        assert_segments("", "Show-S01E01.mkv", &[("Show-S01E01.mkv", true)]);
+        assert_segments("Show-S01E01.mkv", "", &[]);
+    }
+}
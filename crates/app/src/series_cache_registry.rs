@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use tvdb::models::ProviderKind;
+use crate::tvdb_cache::{EpisodeOrdering, TvdbCache};
+
+// Caches can only be shared between folders if they would be fetched the same way
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SeriesCacheKey {
+    pub provider: ProviderKind,
+    pub series_id: u32,
+    pub ordering: EpisodeOrdering,
+    pub language: Option<String>,
+}
+
+// Folders of the same series, i.e. "Show (Season 1-5)" and "Show (Season 6-10)", share one cache
+// Entries are weak so a cache is dropped once no folder uses it
+#[derive(Default)]
+pub struct SeriesCacheRegistry {
+    caches: Mutex<HashMap<SeriesCacheKey, Weak<TvdbCache>>>,
+}
+
+// Caches without a fetch time are never considered newer
+fn is_newer_cache(cache: &TvdbCache, other: &TvdbCache) -> bool {
+    match (cache.fetched_at, other.fetched_at) {
+        (Some(fetched_at), Some(other_fetched_at)) => fetched_at > other_fetched_at,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+impl SeriesCacheRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &SeriesCacheKey) -> Option<Arc<TvdbCache>> {
+        let caches = self.caches.lock().unwrap_or_else(|err| err.into_inner());
+        caches.get(key)?.upgrade()
+    }
+
+    // The registered cache is used instead if it is at least as new
+    pub fn register(&self, key: SeriesCacheKey, cache: TvdbCache) -> Arc<TvdbCache> {
+        let mut caches = self.caches.lock().unwrap_or_else(|err| err.into_inner());
+        caches.retain(|_, cache| cache.strong_count() > 0);
+        if let Some(existing) = caches.get(&key).and_then(Weak::upgrade) {
+            if !is_newer_cache(&cache, &existing) {
+                return existing;
+            }
+        }
+        let cache = Arc::new(cache);
+        caches.insert(key, Arc::downgrade(&cache));
+        cache
+    }
+
+    pub fn len(&self) -> usize {
+        let caches = self.caches.lock().unwrap_or_else(|err| err.into_inner());
+        caches.values().filter(|cache| cache.strong_count() > 0).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::new_cache;
+
+    fn new_key(series_id: u32) -> SeriesCacheKey {
+        SeriesCacheKey { provider: ProviderKind::Tvdb, series_id, ordering: EpisodeOrdering::Aired, language: None }
+    }
+
+    // Fetch times are relative to a fixed time so caches from the same day are equally new
+    fn new_fetched_cache(days_ago: Option<i64>) -> TvdbCache {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z").unwrap().to_utc();
+        let mut cache = new_cache("Show", &[(1, 1, Some("Pilot"))]);
+        cache.fetched_at = days_ago.map(|days| now - chrono::Duration::days(days));
+        cache
+    }
+
+    #[test]
+    fn same_key_shares_one_cache() {
+        let registry = SeriesCacheRegistry::new();
+        let first = registry.register(new_key(1), new_fetched_cache(Some(1)));
+        let second = registry.register(new_key(1), new_fetched_cache(Some(1)));
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(Arc::ptr_eq(&first, &registry.get(&new_key(1)).unwrap()));
+        assert_eq!(registry.len(), 1);
+
+        let other = registry.register(new_key(2), new_fetched_cache(Some(1)));
+        assert!(!Arc::ptr_eq(&first, &other));
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    fn only_newer_cache_replaces_registered() {
+        let registry = SeriesCacheRegistry::new();
+        let first = registry.register(new_key(1), new_fetched_cache(Some(5)));
+        assert!(Arc::ptr_eq(&first, &registry.register(new_key(1), new_fetched_cache(Some(10)))));
+        assert!(Arc::ptr_eq(&first, &registry.register(new_key(1), new_fetched_cache(None))));
+
+        let newer = registry.register(new_key(1), new_fetched_cache(Some(1)));
+        assert!(!Arc::ptr_eq(&first, &newer));
+        assert!(Arc::ptr_eq(&newer, &registry.get(&new_key(1)).unwrap()));
+    }
+
+    #[test]
+    fn unused_caches_are_dropped() {
+        let registry = SeriesCacheRegistry::new();
+        let cache = registry.register(new_key(1), new_fetched_cache(None));
+        assert!(!registry.is_empty());
+        drop(cache);
+        assert!(registry.is_empty());
+        assert!(registry.get(&new_key(1)).is_none());
+    }
+}
@@ -0,0 +1,31 @@
+use serde_json;
+use std::collections::BTreeMap;
+use thiserror;
+use crate::atomic_write::atomic_write;
+
+// Folder names are mapped to series ids so a folder can be identified again after its cache is deleted
+pub type SeriesMapping = BTreeMap<String, u32>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SeriesMappingError {
+    #[error("io error: {}", .0)]
+    IO(std::io::Error),
+    #[error("json error: {}", .0)]
+    Json(serde_json::Error),
+}
+
+// A missing mapping file means no folders have been mapped yet
+pub async fn load_series_mapping_from_file(path: &str) -> Result<SeriesMapping, SeriesMappingError> {
+    let data = match tokio::fs::read_to_string(path).await {
+        Ok(data) => data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(SeriesMapping::new()),
+        Err(err) => return Err(SeriesMappingError::IO(err)),
+    };
+    serde_json::from_str(data.as_str()).map_err(SeriesMappingError::Json)
+}
+
+// Written atomically so a failed write doesn't corrupt the existing mapping
+pub async fn save_series_mapping_to_file(path: &str, mapping: &SeriesMapping) -> Result<(), SeriesMappingError> {
+    let data = serde_json::to_string_pretty(mapping).map_err(SeriesMappingError::Json)?;
+    atomic_write(path, data).await.map_err(SeriesMappingError::IO)
+}
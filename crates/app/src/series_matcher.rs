@@ -0,0 +1,201 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use tvdb::models::Series;
+
+#[derive(Debug, Clone)]
+pub enum SeriesMatch {
+    Matched(Box<Series>),
+    // Multiple series had a name close to the folder name
+    Ambiguous(Vec<Series>),
+    // None of the search results had a name close to the folder name
+    NotFound(Vec<Series>),
+}
+
+impl SeriesMatch {
+    pub fn get_candidates(&self) -> &[Series] {
+        match self {
+            SeriesMatch::Matched(series) => std::slice::from_ref(series.as_ref()),
+            SeriesMatch::Ambiguous(candidates) => candidates.as_slice(),
+            SeriesMatch::NotFound(candidates) => candidates.as_slice(),
+        }
+    }
+}
+
+// Folders can be nested so we only use the last component, i.e. "Drama/The.Wire.2002" becomes "The.Wire.2002"
+fn get_base_name(folder_name: &str) -> &str {
+    folder_name.rsplit('/').next().unwrap_or(folder_name)
+}
+
+// Split off a trailing year, i.e. "Doctor Who (2005)" becomes ("Doctor Who", "2005")
+fn split_year(name: &str) -> (&str, Option<&str>) {
+    lazy_static! {
+        static ref YEAR_REGEX: Regex = Regex::new(r"[\s._\-]*[\(\[]?((?:19|20)\d{2})[\)\]]?\s*$").unwrap();
+    }
+    let captures = match YEAR_REGEX.captures(name) {
+        Some(captures) => captures,
+        None => return (name, None),
+    };
+    let start = captures.get(0).map(|m| m.start()).unwrap_or(name.len());
+    // A series can be named after a year, i.e. "1923"
+    if start == 0 {
+        return (name, None);
+    }
+    (&name[..start], captures.get(1).map(|m| m.as_str()))
+}
+
+pub fn get_series_search_query(folder_name: &str) -> String {
+    lazy_static! {
+        static ref SEPARATOR_REGEX: Regex = Regex::new(r"[._\s]+").unwrap();
+    }
+    let (name, _) = split_year(get_base_name(folder_name));
+    SEPARATOR_REGEX.replace_all(name, " ").trim().to_string()
+}
+
+// Lowercase words without punctuation so "Grey's.Anatomy" and "Grey's Anatomy" are equal
+// Letters and numbers of any script are kept so names that aren't in english can still be compared
+fn normalise_series_name(name: &str) -> String {
+    lazy_static! {
+        static ref REMOVE_REGEX: Regex = Regex::new(r"['`]").unwrap();
+        static ref REPLACE_REGEX: Regex = Regex::new(r"[^\p{L}\p{N}]+").unwrap();
+    }
+    let (name, _) = split_year(name);
+    let name = REMOVE_REGEX.replace_all(name, "");
+    let name = REPLACE_REGEX.replace_all(name.as_ref(), " ");
+    name.trim().to_lowercase()
+}
+
+fn get_edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous_diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_diagonal + usize::from(a_char != *b_char);
+            previous_diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// Allow for small typos in longer names while short names need to be exact
+// Names without any letters or numbers are never close since they would all be equal
+fn is_name_close(folder_name: &str, series_name: &str) -> bool {
+    let series_name = normalise_series_name(series_name);
+    if folder_name.is_empty() || series_name.is_empty() {
+        return false;
+    }
+    let max_distance = folder_name.chars().count() / 10;
+    get_edit_distance(folder_name, series_name.as_str()) <= max_distance
+}
+
+pub fn match_series(folder_name: &str, search_results: &[Series]) -> SeriesMatch {
+    const MAX_CANDIDATES: usize = 5;
+
+    let base_name = get_base_name(folder_name);
+    let (_, year) = split_year(base_name);
+    let name = normalise_series_name(base_name);
+
+    let mut candidates: Vec<&Series> = search_results
+        .iter()
+        .filter(|series| {
+            is_name_close(name.as_str(), series.name.as_str()) ||
+            series.aliases.iter().flatten().any(|alias| is_name_close(name.as_str(), alias.as_str()))
+        })
+        .collect();
+
+    // Remakes share a name so we use the year to pick between them
+    if let Some(year) = year {
+        let is_year_match = |series: &&Series| series.first_aired.as_deref().map(|date| date.starts_with(year)).unwrap_or(false);
+        if candidates.iter().any(is_year_match) {
+            candidates.retain(is_year_match);
+        }
+    }
+
+    match candidates.len() {
+        0 => SeriesMatch::NotFound(search_results.iter().take(MAX_CANDIDATES).cloned().collect()),
+        1 => SeriesMatch::Matched(Box::new(candidates[0].clone())),
+        _ => SeriesMatch::Ambiguous(candidates.into_iter().take(MAX_CANDIDATES).cloned().collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn new_series(id: u32, name: &str, first_aired: Option<&str>, aliases: &[&str]) -> Series {
+        serde_json::from_value(json!({ "id": id, "seriesName": name, "firstAired": first_aired, "aliases": aliases })).unwrap()
+    }
+
+    fn get_candidate_ids(result: &SeriesMatch) -> Vec<u32> {
+        result.get_candidates().iter().map(|series| series.id).collect()
+    }
+
+    #[test]
+    fn search_query_strips_year_and_separators() {
+        assert_eq!(get_series_search_query("Drama/The.Wire.2002"), "The Wire");
+        assert_eq!(get_series_search_query("Doctor Who (2005)"), "Doctor Who");
+        assert_eq!(get_series_search_query("Grey's_Anatomy [2005]"), "Grey's Anatomy");
+        // Series named after a year are kept as is
+        assert_eq!(get_series_search_query("1923"), "1923");
+    }
+
+    #[test]
+    fn close_names_are_matched() {
+        let results = [new_series(1, "Grey's Anatomy", None, &[]), new_series(2, "Breaking Bad", None, &[])];
+        assert!(matches!(match_series("Greys.Anatomy", &results), SeriesMatch::Matched(series) if series.id == 1));
+        // Small typos are allowed in longer names
+        assert!(matches!(match_series("Breakng Bad", &results), SeriesMatch::Matched(series) if series.id == 2));
+
+        let results = [new_series(3, "Lust", None, &[])];
+        assert!(matches!(match_series("Lost", &results), SeriesMatch::NotFound(_)));
+    }
+
+    #[test]
+    fn aliases_are_matched() {
+        let results = [new_series(1, "Agents of S.H.I.E.L.D.", None, &["Shield"]), new_series(2, "The Shield", None, &[])];
+        assert!(matches!(match_series("Shield", &results), SeriesMatch::Matched(series) if series.id == 1));
+    }
+
+    #[test]
+    fn year_picks_between_remakes() {
+        let results = [
+            new_series(1, "Doctor Who", Some("1963-11-23"), &[]),
+            new_series(2, "Doctor Who", Some("2005-03-26"), &[]),
+        ];
+        assert!(matches!(match_series("Doctor.Who.2005", &results), SeriesMatch::Matched(series) if series.id == 2));
+
+        let result = match_series("Doctor Who", &results);
+        assert!(matches!(result, SeriesMatch::Ambiguous(_)));
+        assert_eq!(get_candidate_ids(&result), vec![1, 2]);
+        // A year that none of the series aired in doesn't rule out any of them
+        assert!(matches!(match_series("Doctor Who (2023)", &results), SeriesMatch::Ambiguous(_)));
+    }
+
+    #[test]
+    fn unmatched_folder_lists_first_search_results() {
+        let results: Vec<Series> = (1..=8).map(|id| new_series(id, format!("Other Show {}", id).as_str(), None, &[])).collect();
+        let result = match_series("Bravo Show", results.as_slice());
+        assert!(matches!(result, SeriesMatch::NotFound(_)));
+        assert_eq!(get_candidate_ids(&result), vec![1, 2, 3, 4, 5]);
+        assert!(get_candidate_ids(&match_series("Bravo Show", &[])).is_empty());
+    }
+
+    #[test]
+    fn names_in_other_scripts_are_compared() {
+        let results = [new_series(1, "Attack on Titan", None, &[]), new_series(2, "進撃の巨人", None, &[]), new_series(3, "ワンピース", None, &[])];
+        assert!(matches!(match_series("進撃の巨人 (2013)", &results), SeriesMatch::Matched(series) if series.id == 2));
+        assert!(matches!(match_series("Attack.on.Titan", &results), SeriesMatch::Matched(series) if series.id == 1));
+        // Typos are allowed based on the number of characters rather than bytes
+        assert!(matches!(match_series("進撃の巨人です", &results), SeriesMatch::NotFound(_)));
+    }
+
+    #[test]
+    fn names_without_letters_are_never_close() {
+        let results = [new_series(1, "???", None, &[]), new_series(2, "", None, &["!!"])];
+        assert!(matches!(match_series("!!!", &results), SeriesMatch::NotFound(_)));
+        assert!(matches!(match_series("Show", &results), SeriesMatch::NotFound(_)));
+    }
+}
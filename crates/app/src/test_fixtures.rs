@@ -0,0 +1,173 @@
+// Builders shared by the unit tests of each module
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use tvdb::models::{Episode, Series};
+use crate::app::App;
+use crate::app_file::{AppFile, FileTracker};
+use crate::file_intent::{Action, FilterRules};
+use crate::metadata_provider::MockProvider;
+use crate::tvdb_cache::{CACHE_FILE_VERSION, EpisodeOrdering, TvdbCache};
+
+pub fn new_series(id: u32, name: &str) -> Series {
+    serde_json::from_value(json!({ "id": id, "seriesName": name })).unwrap()
+}
+
+pub fn new_episode(id: u32, season: u32, episode: u32, name: Option<&str>) -> Episode {
+    serde_json::from_value(json!({
+        "id": id,
+        "airedSeason": season,
+        "airedEpisodeNumber": episode,
+        "episodeName": name,
+        "seriesId": 1,
+    })).unwrap()
+}
+
+// Episodes are given as (season, episode, name) and their ids follow the order they are given in
+pub fn new_cache(series_name: &str, episodes: &[(u32, u32, Option<&str>)]) -> TvdbCache {
+    let episodes = episodes
+        .iter()
+        .enumerate()
+        .map(|(index, (season, episode, name))| new_episode(index as u32 + 1, *season, *episode, *name))
+        .collect();
+    TvdbCache::new(new_series(1, series_name), episodes, EpisodeOrdering::Aired)
+}
+
+// Aired first as the second episode on dvd which starts with the pilot that was aired last
+// The last episode has no dvd number and the one before it isn't a whole episode on dvd
+pub fn new_dvd_order_cache(ordering: EpisodeOrdering) -> TvdbCache {
+    let episodes = [
+        (1, 1, 1, "The Train Job", Some((1, 2.0))),
+        (2, 1, 2, "Bushwhacked", Some((1, 3.0))),
+        (3, 1, 11, "Serenity", Some((1, 1.0))),
+        (4, 1, 12, "Heart of Gold", Some((1, 12.5))),
+        (5, 1, 13, "Objects in Space", None),
+    ];
+    let episodes = episodes
+        .iter()
+        .map(|(id, season, episode, name, dvd)| {
+            let mut episode = new_episode(*id, *season, *episode, Some(name));
+            episode.dvd_season = dvd.map(|(season, _)| season);
+            episode.dvd_episode = dvd.map(|(_, episode)| episode);
+            episode
+        })
+        .collect();
+    TvdbCache::new(new_series(1, "Show"), episodes, ordering)
+}
+
+pub fn new_filter_rules_config(overrides: serde_json::Value) -> serde_json::Value {
+    let mut config = json!({
+        "blacklist_extensions": [],
+        "whitelist_folders": [],
+        "whitelist_filenames": [],
+        "whitelist_tags": [],
+    });
+    if let (Some(config), Some(overrides)) = (config.as_object_mut(), overrides.as_object()) {
+        for (key, value) in overrides {
+            config.insert(key.clone(), value.clone());
+        }
+    }
+    config
+}
+
+// Fields given in the overrides replace the ones of an otherwise empty config
+// The rules are compiled the same way they are when the app loads its config
+pub fn new_filter_rules(overrides: serde_json::Value) -> FilterRules {
+    let rules: FilterRules = serde_json::from_value(new_filter_rules_config(overrides)).unwrap();
+    rules.compile_globs().unwrap();
+    rules
+}
+
+// Empty files are created along with any folders they are in
+pub fn create_files(root: &Path, paths: &[&str]) {
+    for path in paths {
+        let path = root.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(path, "").unwrap();
+    }
+}
+
+// Serves a single series with the episodes given as (season, episode, name)
+pub fn new_mock_provider(series_id: u32, series_name: &str, episodes: &[(u32, u32, Option<&str>)]) -> MockProvider {
+    let episodes: Vec<Episode> = episodes
+        .iter()
+        .enumerate()
+        .map(|(index, (season, episode, name))| {
+            let mut episode = new_episode(series_id*100 + index as u32 + 1, *season, *episode, *name);
+            episode.series_id = Some(series_id);
+            episode
+        })
+        .collect();
+    let mut provider = MockProvider::new();
+    provider.series.push(new_series(series_id, series_name));
+    provider.episodes.insert(series_id, episodes);
+    provider.episodes_summaries.insert(series_id, Default::default());
+    provider
+}
+
+// The saved cache of the folder has the same episodes as the cache from new_cache(...)
+pub fn write_cache_file(folder_path: &Path, series_name: &str, episodes: &[(u32, u32, Option<&str>)]) {
+    let episodes: Vec<Episode> = episodes
+        .iter()
+        .enumerate()
+        .map(|(index, (season, episode, name))| new_episode(index as u32 + 1, *season, *episode, *name))
+        .collect();
+    let cache_file = json!({
+        "version": CACHE_FILE_VERSION,
+        "series": new_series(1, series_name),
+        "episodes": episodes,
+    });
+    std::fs::create_dir_all(folder_path).unwrap();
+    std::fs::write(folder_path.join("tvdb_cache.json"), cache_file.to_string()).unwrap();
+}
+
+// The app config is written to the config folder with the overrides applied to the filter rules
+// No credentials are saved so the app falls back to a provider that doesn't need them
+pub async fn new_app(config_path: &Path, overrides: serde_json::Value) -> App {
+    let config = new_filter_rules_config(overrides);
+    std::fs::write(config_path.join("app_config.json"), config.to_string()).unwrap();
+    App::new(config_path.to_string_lossy().as_ref()).await.unwrap()
+}
+
+// The tracker is updated incrementally so it has to match one built from scratch from the files
+pub(crate) fn assert_tracker_matches_files(file_list: &[AppFile], file_tracker: &FileTracker) {
+    for action in Action::iterator() {
+        let indices: BTreeSet<usize> = file_list
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| file.action == *action)
+            .map(|(index, _)| index)
+            .collect();
+        let total_bytes: u64 = indices.iter().map(|index| file_list[*index].size).sum();
+        assert_eq!(file_tracker.get_action_indices(*action), &indices, "{:?}", action);
+        assert_eq!(file_tracker.get_action_count()[*action], indices.len(), "{:?}", action);
+        assert_eq!(file_tracker.get_action_bytes()[*action], total_bytes, "{:?}", action);
+    }
+
+    let mut pending_writes = HashMap::<String, HashSet<usize>>::new();
+    for (index, file) in file_list.iter().enumerate() {
+        let is_vacated = file.is_enabled && (file.action == Action::Rename || file.action == Action::Delete);
+        assert_eq!(file_tracker.check_if_source_vacated(index), is_vacated, "{}", file.src);
+        assert_eq!(file_tracker.get_source_index(file.src.as_str()), Some(&index), "{}", file.src);
+        if file.is_enabled && file.action == Action::Rename {
+            pending_writes.entry(file_tracker.get_path_key(file.dest.as_str())).or_default().insert(index);
+        }
+    }
+    let tracked_writes: HashMap<String, HashSet<usize>> = file_tracker
+        .get_pending_writes()
+        .iter()
+        .filter(|(_, indices)| !indices.is_empty())
+        .map(|(key, indices)| (key.clone(), indices.clone()))
+        .collect();
+    assert_eq!(tracked_writes, pending_writes);
+
+    let conflict_keys: BTreeSet<String> = pending_writes
+        .keys()
+        .filter(|key| file_tracker.check_if_write_conflicts(key.as_str()))
+        .cloned()
+        .collect();
+    let tracked_conflict_keys: BTreeSet<String> = file_tracker.get_conflicts().iter().map(|conflict| conflict.key.clone()).collect();
+    assert_eq!(tracked_conflict_keys, conflict_keys);
+}
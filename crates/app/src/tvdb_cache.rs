@@ -1,5 +1,8 @@
-use tvdb::models::{Episode, Series};
-use std::collections::HashMap;
+use chrono;
+use serde;
+use serde_json;
+use tvdb::models::{Actor, Episode, EpisodesSummary, ProviderKind, Series};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
 pub struct EpisodeKey {
@@ -7,35 +10,638 @@ pub struct EpisodeKey {
     pub episode: u32,
 }
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct MissingEpisodeFilter {
+    pub is_exclude_specials: bool,
+    pub is_exclude_unaired: bool,
+}
+
+impl Default for MissingEpisodeFilter {
+    fn default() -> Self {
+        Self {
+            is_exclude_specials: true,
+            is_exclude_unaired: true,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Eq, PartialEq, Hash, Copy, Clone, Default)]
+pub enum EpisodeOrdering {
+    #[default]
+    Aired,
+    Dvd,
+}
+
+impl EpisodeOrdering {
+    pub fn iterator() -> std::slice::Iter<'static, Self> {
+        static ORDERINGS: [EpisodeOrdering;2] = [
+            EpisodeOrdering::Aired,
+            EpisodeOrdering::Dvd,
+        ];
+        ORDERINGS.iter()
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            EpisodeOrdering::Aired => "Aired order",
+            EpisodeOrdering::Dvd => "DVD order",
+        }
+    }
+}
+
+// Missing or unparsable air dates are treated as unknown
+fn get_air_date(episode: &Episode) -> Option<chrono::NaiveDate> {
+    let date = episode.first_aired.as_deref()?;
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+// Episodes without a whole dvd number fall back to their aired number
+pub fn get_episode_key(episode: &Episode, ordering: EpisodeOrdering) -> EpisodeKey {
+    let aired_key = EpisodeKey { season: episode.season, episode: episode.episode };
+    match ordering {
+        EpisodeOrdering::Aired => aired_key,
+        EpisodeOrdering::Dvd => match (episode.dvd_season, episode.dvd_episode) {
+            (Some(season), Some(number)) if number >= 0.0 && number.fract() == 0.0 => EpisodeKey {
+                season,
+                episode: number as u32,
+            },
+            _ => aired_key,
+        },
+    }
+}
+
+// Saved alongside the legacy series and episodes files since they are written as returned by the api
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheMeta {
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+}
+
+// Increased whenever the layout of the cache file changes
+pub const CACHE_FILE_VERSION: u32 = 1;
+
+// Series and episodes are saved together so they can't get out of sync
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct CacheFile {
+    pub version: u32,
+    pub fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub series: Series,
+    pub episodes: Vec<Episode>,
+}
+
+// Borrows from the loaded cache so it doesn't have to be copied to be saved
+#[serde_with::skip_serializing_none]
+#[derive(serde::Serialize)]
+struct CacheFileRef<'a> {
+    version: u32,
+    fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    series: &'a Series,
+    episodes: &'a [Episode],
+}
+
+// More than this fraction of episodes from another series means the wrong episodes were saved
+const MAX_FOREIGN_EPISODES_RATIO: f32 = 0.1;
+
+// Episodes whose series id doesn't match the series they were loaded with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignEpisodes {
+    pub series_id: u32,
+    pub foreign_series_ids: BTreeSet<u32>,
+    pub total_foreign: usize,
+    pub total_episodes: usize,
+}
+
+impl ForeignEpisodes {
+    pub fn is_rejected(&self) -> bool {
+        (self.total_foreign as f32) > (self.total_episodes as f32)*MAX_FOREIGN_EPISODES_RATIO
+    }
+}
+
+impl std::fmt::Display for ForeignEpisodes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ids: Vec<String> = self.foreign_series_ids.iter().map(|id| id.to_string()).collect();
+        write!(
+            f, "{} of {} episodes belong to series {} instead of series {}",
+            self.total_foreign, self.total_episodes, ids.join(", "), self.series_id,
+        )
+    }
+}
+
+// Episodes without a series id can't be checked so they are assumed to match
+pub fn find_foreign_episodes(series_id: u32, episodes: &[Episode]) -> Option<ForeignEpisodes> {
+    let foreign_ids: Vec<u32> = episodes.iter()
+        .filter_map(|episode| episode.series_id)
+        .filter(|id| *id != series_id)
+        .collect();
+    if foreign_ids.is_empty() {
+        return None;
+    }
+    Some(ForeignEpisodes {
+        series_id,
+        total_foreign: foreign_ids.len(),
+        foreign_series_ids: foreign_ids.into_iter().collect(),
+        total_episodes: episodes.len(),
+    })
+}
+
+#[derive(Clone)]
 pub struct TvdbCache {
     pub series: Series,
     pub episodes: Vec<Episode>,
     pub episode_cache: HashMap<EpisodeKey, usize>,
+    // Only contains episodes that the api gave an absolute number
+    absolute_lookup: HashMap<u32, usize>,
+    // Indices of the episodes in each season, in the same order as the episodes
+    season_index: BTreeMap<u32, Vec<usize>>,
+    // None if the cast hasn't been loaded, since older caches don't have it saved
+    pub actors: Option<Vec<Actor>>,
+    // None if the summary wasn't fetched, since older caches don't have it saved
+    pub episodes_summary: Option<EpisodesSummary>,
+    // None if the fetch time isn't known, since older caches don't have it saved
+    pub fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    ordering: EpisodeOrdering,
+    // Episode ids sharing a key, the first id is the one in the episode cache
+    duplicate_episodes: Vec<(EpisodeKey, Vec<u32>)>,
 }
 
 impl TvdbCache {
-    pub fn new(series: Series, mut episodes: Vec<Episode>) -> Self {
-        // Sort so that our search results are sorted
-        episodes.sort_unstable_by(|a,b| {
-            const N: u32 = 1000;
-            let v_a = a.season*N + a.episode;
-            let v_b = b.season*N + b.episode;
-            v_a.partial_cmp(&v_b).unwrap_or(std::cmp::Ordering::Equal)
+    pub fn new(series: Series, episodes: Vec<Episode>, ordering: EpisodeOrdering) -> Self {
+        let mut cache = Self {
+            series,
+            episodes,
+            episode_cache: HashMap::new(),
+            absolute_lookup: HashMap::new(),
+            season_index: BTreeMap::new(),
+            actors: None,
+            episodes_summary: None,
+            fetched_at: None,
+            ordering,
+            duplicate_episodes: Vec::new(),
+        };
+        cache.rebuild_episode_cache();
+        cache
+    }
+
+    // Sort so that our search results are sorted
+    // Sorting by id as well means the lowest id wins when episodes share a key
+    fn rebuild_episode_cache(&mut self) {
+        let ordering = self.ordering;
+        self.episodes.sort_by_key(|episode| {
+            let key = get_episode_key(episode, ordering);
+            (key.season, key.episode, episode.id)
         });
+        self.episode_cache.clear();
+        self.absolute_lookup.clear();
+        self.season_index.clear();
+        self.duplicate_episodes.clear();
+        for (index, episode) in self.episodes.iter().enumerate() {
+            let key = get_episode_key(episode, ordering);
+            self.season_index.entry(key.season).or_default().push(index);
+            if let Some(number) = episode.absolute_number.filter(|number| *number > 0) {
+                self.absolute_lookup.entry(number).or_insert(index);
+            }
+            // Episodes without a number can't be matched to files
+            if key.episode == 0 {
+                continue;
+            }
+            match self.episode_cache.get(&key) {
+                None => {
+                    self.episode_cache.insert(key, index);
+                },
+                Some(first_index) => match self.duplicate_episodes.last_mut() {
+                    Some((last_key, ids)) if *last_key == key => ids.push(episode.id),
+                    _ => {
+                        let ids = vec![self.episodes[*first_index].id, episode.id];
+                        self.duplicate_episodes.push((key, ids));
+                    },
+                },
+            }
+        }
+    }
+
+    pub fn get_episode(&self, key: &EpisodeKey) -> Option<&Episode> {
+        let index = self.episode_cache.get(key)?;
+        self.episodes.get(*index)
+    }
+
+    pub fn get_by_absolute(&self, number: u32) -> Option<&Episode> {
+        let index = self.absolute_lookup.get(&number)?;
+        self.episodes.get(*index)
+    }
+
+    pub fn get_duplicate_episodes(&self) -> &[(EpisodeKey, Vec<u32>)] {
+        self.duplicate_episodes.as_slice()
+    }
+
+    // Caches saved before the provider was recorded are from tvdb
+    pub fn get_provider(&self) -> ProviderKind {
+        self.series.provider.unwrap_or_default()
+    }
+
+    pub fn to_cache_file_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&CacheFileRef {
+            version: CACHE_FILE_VERSION,
+            fetched_at: self.fetched_at,
+            series: &self.series,
+            episodes: self.episodes.as_slice(),
+        })
+    }
+
+    pub fn get_age(&self) -> Option<chrono::Duration> {
+        self.fetched_at.map(|fetched_at| chrono::Utc::now().signed_duration_since(fetched_at))
+    }
 
-        let mut cache = HashMap::new();
-        for (index, episode) in episodes.iter().enumerate() {
-            let key = EpisodeKey {
-                season: episode.season,
-                episode: episode.episode,
-            };
-            cache.insert(key, index);
+    pub fn get_ordering(&self) -> EpisodeOrdering {
+        self.ordering
+    }
+
+    pub fn set_ordering(&mut self, ordering: EpisodeOrdering) {
+        if self.ordering != ordering {
+            self.ordering = ordering;
+            self.rebuild_episode_cache();
         }
-        
-        Self {
-            series,
-            episode_cache: cache,
-            episodes,
+    }
+
+    pub fn get_episode_key(&self, episode: &Episode) -> EpisodeKey {
+        get_episode_key(episode, self.ordering)
+    }
+
+    // Fetched episodes replace cached episodes with the same id
+    pub fn merge_episodes(&mut self, episodes: Vec<Episode>) {
+        let new_ids: HashSet<u32> = episodes.iter().map(|episode| episode.id).collect();
+        self.episodes.retain(|episode| !new_ids.contains(&episode.id));
+        self.episodes.extend(episodes);
+        self.rebuild_episode_cache();
+    }
+
+    // Seasons are in ascending order, where gaps in the numbering are skipped
+    pub fn get_seasons(&self) -> Vec<u32> {
+        self.season_index.keys().copied().collect()
+    }
+
+    pub fn get_season_episodes(&self, season: u32) -> impl Iterator<Item=&Episode> + '_ {
+        self.season_index
+            .get(&season)
+            .into_iter()
+            .flatten()
+            .map(|index| &self.episodes[*index])
+    }
+
+    // The most recent episode that aired on or before the date, i.e. "2023-10-01"
+    // Episodes that share an air date are ordered by their season and episode number
+    pub fn get_latest_aired(&self, before: &str) -> Option<&Episode> {
+        let before = chrono::NaiveDate::parse_from_str(before, "%Y-%m-%d").ok()?;
+        self.episodes
+            .iter()
+            .filter_map(|episode| get_air_date(episode).filter(|date| *date <= before).map(|date| (date, episode)))
+            .max_by_key(|(date, episode)| {
+                let key = self.get_episode_key(episode);
+                (*date, key.season, key.episode)
+            })
+            .map(|(_, episode)| episode)
+    }
+
+    // Sorted so that the top billed actors are first
+    pub fn set_actors(&mut self, mut actors: Vec<Actor>) {
+        actors.sort_by(|a,b| {
+            let order_a = a.sort_order.unwrap_or(u32::MAX);
+            let order_b = b.sort_order.unwrap_or(u32::MAX);
+            order_a.cmp(&order_b).then_with(|| a.name.cmp(&b.name))
+        });
+        self.actors = Some(actors);
+    }
+
+    // Episodes in the cache which aren't in the provided set, sorted by season and episode
+    // Episodes without a valid air date are treated as unaired
+    pub fn get_missing_episodes(
+        &self, existing: &HashSet<EpisodeKey>, filter: MissingEpisodeFilter, today: chrono::NaiveDate,
+    ) -> Vec<EpisodeKey> {
+        self.episodes
+            .iter()
+            .filter(|episode| !(filter.is_exclude_specials && self.get_episode_key(episode).season == 0))
+            .filter(|episode| {
+                if !filter.is_exclude_unaired {
+                    return true;
+                }
+                get_air_date(episode).map(|date| date <= today).unwrap_or(false)
+            })
+            .map(|episode| self.get_episode_key(episode))
+            .filter(|key| key.episode != 0 && !existing.contains(key))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{new_dvd_order_cache, new_episode, new_series};
+
+    fn key(season: u32, episode: u32) -> EpisodeKey {
+        EpisodeKey { season, episode }
+    }
+
+    #[test]
+    fn episodes_are_found_by_key() {
+        let episodes = vec![
+            new_episode(3, 2, 1, Some("Return")),
+            new_episode(1, 1, 1, Some("Pilot")),
+            new_episode(2, 1, 2, None),
+        ];
+        let cache = TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired);
+        assert_eq!(cache.get_episode(&key(1, 1)).map(|episode| episode.id), Some(1));
+        assert_eq!(cache.get_episode(&key(1, 2)).map(|episode| episode.id), Some(2));
+        assert_eq!(cache.get_episode(&key(2, 1)).and_then(|episode| episode.name.as_deref()), Some("Return"));
+        assert!(cache.get_episode(&key(3, 1)).is_none());
+        // Episodes are sorted so they can be listed in order
+        let ids: Vec<u32> = cache.episodes.iter().map(|episode| episode.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert!(cache.get_duplicate_episodes().is_empty());
+    }
+
+    #[test]
+    fn episodes_without_a_number_are_not_found() {
+        let episodes = vec![new_episode(1, 0, 0, Some("Special"))];
+        let cache = TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired);
+        assert!(cache.get_episode(&key(0, 0)).is_none());
+        assert_eq!(cache.episodes.len(), 1);
+    }
+
+    #[test]
+    fn lowest_id_wins_when_episodes_share_a_key() {
+        // The order the api returned them in shouldn't change which episode is picked
+        for ids in [[7, 4, 9], [9, 7, 4], [4, 9, 7]] {
+            let episodes = ids
+                .iter()
+                .map(|id| new_episode(*id, 1, 1, Some(format!("Episode {}", id).as_str())))
+                .chain([new_episode(20, 1, 2, None)])
+                .collect();
+            let cache = TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired);
+            assert_eq!(cache.get_episode(&key(1, 1)).map(|episode| episode.id), Some(4));
+            assert_eq!(cache.get_duplicate_episodes(), &[(key(1, 1), vec![4, 7, 9])]);
         }
     }
+
+    #[test]
+    fn duplicates_are_found_again_after_merging() {
+        let episodes = vec![new_episode(1, 1, 1, Some("Pilot"))];
+        let mut cache = TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired);
+        cache.merge_episodes(vec![new_episode(2, 1, 1, Some("Pilot (extended)"))]);
+        assert_eq!(cache.get_episode(&key(1, 1)).map(|episode| episode.id), Some(1));
+        assert_eq!(cache.get_duplicate_episodes(), &[(key(1, 1), vec![1, 2])]);
+
+        // Merging an episode with the same id replaces it instead of adding a duplicate
+        cache.merge_episodes(vec![new_episode(2, 1, 3, Some("Moved"))]);
+        assert!(cache.get_duplicate_episodes().is_empty());
+        assert_eq!(cache.get_episode(&key(1, 3)).map(|episode| episode.id), Some(2));
+    }
+
+    #[test]
+    fn merged_seasons_stay_sorted_and_indexed() {
+        let episodes = vec![
+            new_episode(1, 1, 1, Some("Pilot")),
+            new_episode(5, 3, 1, Some("Finale")),
+        ];
+        let mut cache = TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired);
+        cache.merge_episodes(vec![
+            new_episode(4, 2, 2, None),
+            new_episode(3, 2, 1, Some("Return")),
+            new_episode(1, 1, 1, Some("Pilot (renamed)")),
+        ]);
+
+        let ids: Vec<u32> = cache.episodes.iter().map(|episode| episode.id).collect();
+        assert_eq!(ids, vec![1, 3, 4, 5]);
+        assert_eq!(cache.get_seasons(), vec![1, 2, 3]);
+        let season_ids: Vec<u32> = cache.get_season_episodes(2).map(|episode| episode.id).collect();
+        assert_eq!(season_ids, vec![3, 4]);
+        assert_eq!(cache.get_episode(&key(1, 1)).and_then(|episode| episode.name.as_deref()), Some("Pilot (renamed)"));
+        assert_eq!(cache.get_episode(&key(3, 1)).map(|episode| episode.id), Some(5));
+        assert!(cache.get_duplicate_episodes().is_empty());
+    }
+
+    fn get_episode_name(cache: &TvdbCache, season: u32, episode: u32) -> Option<&str> {
+        cache.get_episode(&key(season, episode))?.name.as_deref()
+    }
+
+    #[test]
+    fn dvd_ordering_differs_from_aired() {
+        let cache = new_dvd_order_cache(EpisodeOrdering::Aired);
+        assert_eq!(get_episode_name(&cache, 1, 1), Some("The Train Job"));
+        assert_eq!(get_episode_name(&cache, 1, 11), Some("Serenity"));
+
+        let cache = new_dvd_order_cache(EpisodeOrdering::Dvd);
+        assert_eq!(get_episode_name(&cache, 1, 1), Some("Serenity"));
+        assert_eq!(get_episode_name(&cache, 1, 2), Some("The Train Job"));
+        assert_eq!(get_episode_name(&cache, 1, 3), Some("Bushwhacked"));
+        assert_eq!(get_episode_name(&cache, 1, 11), None);
+        // Episodes without a whole dvd number keep their aired number
+        assert_eq!(get_episode_name(&cache, 1, 12), Some("Heart of Gold"));
+        assert_eq!(get_episode_name(&cache, 1, 13), Some("Objects in Space"));
+        let ids: Vec<u32> = cache.get_season_episodes(1).map(|episode| episode.id).collect();
+        assert_eq!(ids, vec![3, 1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn changing_ordering_rebuilds_lookup() {
+        let mut cache = new_dvd_order_cache(EpisodeOrdering::Aired);
+        cache.set_ordering(EpisodeOrdering::Dvd);
+        assert_eq!(cache.get_ordering(), EpisodeOrdering::Dvd);
+        assert_eq!(get_episode_name(&cache, 1, 1), Some("Serenity"));
+        cache.set_ordering(EpisodeOrdering::Aired);
+        assert_eq!(get_episode_name(&cache, 1, 1), Some("The Train Job"));
+    }
+
+    fn new_aired_episode(id: u32, season: u32, episode: u32, first_aired: Option<&str>) -> Episode {
+        let mut episode = new_episode(id, season, episode, None);
+        episode.first_aired = first_aired.map(str::to_string);
+        episode
+    }
+
+    fn new_season_cache() -> TvdbCache {
+        let episodes = vec![
+            new_aired_episode(1, 0, 1, Some("2019-12-01")),
+            new_aired_episode(2, 1, 1, Some("2020-01-01")),
+            new_aired_episode(3, 1, 2, Some("2020-01-08")),
+            new_aired_episode(4, 1, 3, Some("2030-01-15")),
+            new_aired_episode(5, 1, 4, None),
+            new_aired_episode(6, 1, 5, Some("TBA")),
+        ];
+        TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired)
+    }
+
+    fn get_today() -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2020, 6, 1).unwrap()
+    }
+
+    #[test]
+    fn unaired_episodes_are_excluded_from_missing() {
+        let cache = new_season_cache();
+        let existing = HashSet::from([key(1, 1)]);
+        let missing = cache.get_missing_episodes(&existing, MissingEpisodeFilter::default(), get_today());
+        assert_eq!(missing, vec![key(1, 2)]);
+
+        let filter = MissingEpisodeFilter { is_exclude_specials: false, is_exclude_unaired: false };
+        let missing = cache.get_missing_episodes(&existing, filter, get_today());
+        assert_eq!(missing, vec![key(0, 1), key(1, 2), key(1, 3), key(1, 4), key(1, 5)]);
+    }
+
+    #[test]
+    fn complete_season_has_no_missing_episodes() {
+        let cache = new_season_cache();
+        let existing = HashSet::from([key(1, 1), key(1, 2)]);
+        assert!(cache.get_missing_episodes(&existing, MissingEpisodeFilter::default(), get_today()).is_empty());
+    }
+
+    #[test]
+    fn episodes_without_numbers_are_not_matched() {
+        let unnumbered: Episode = serde_json::from_value(serde_json::json!({
+            "id": 3, "airedSeason": null, "airedEpisodeNumber": null, "episodeName": "Special", "seriesId": 1,
+        })).unwrap();
+        let episodes = vec![new_episode(1, 1, 1, Some("Pilot")), unnumbered, new_episode(2, 0, 1, Some("Extra"))];
+        let cache = TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired);
+        // Still listed but can't be found by key
+        assert_eq!(cache.episodes.len(), 3);
+        assert!(cache.get_episode(&key(0, 0)).is_none());
+        assert_eq!(cache.get_episode(&key(0, 1)).map(|episode| episode.id), Some(2));
+        assert_eq!(cache.get_episode(&key(1, 1)).map(|episode| episode.id), Some(1));
+        assert!(cache.get_duplicate_episodes().is_empty());
+    }
+
+    #[test]
+    fn cache_file_round_trips_fetch_time() {
+        let mut cache = TvdbCache::new(new_series(1, "Show"), vec![new_episode(1, 1, 1, Some("Pilot"))], EpisodeOrdering::Aired);
+        let fetched_at = chrono::Utc::now() - chrono::Duration::days(42);
+        cache.fetched_at = Some(fetched_at);
+
+        let file: CacheFile = serde_json::from_str(cache.to_cache_file_string().unwrap().as_str()).unwrap();
+        assert_eq!(file.version, CACHE_FILE_VERSION);
+        assert_eq!(file.fetched_at, Some(fetched_at));
+        assert_eq!(file.series.name, "Show");
+        assert_eq!(file.episodes.len(), 1);
+        assert_eq!(cache.get_age().map(|age| age.num_days()), Some(42));
+    }
+
+    #[test]
+    fn cache_without_fetch_time_has_unknown_age() {
+        let cache = TvdbCache::new(new_series(1, "Show"), Vec::new(), EpisodeOrdering::Aired);
+        let data = cache.to_cache_file_string().unwrap();
+        assert!(!data.contains("fetched_at"));
+        let file: CacheFile = serde_json::from_str(data.as_str()).unwrap();
+        assert_eq!(file.fetched_at, None);
+        assert_eq!(cache.get_age(), None);
+    }
+
+    fn new_absolute_episode(id: u32, season: u32, episode: u32, absolute_number: Option<u32>) -> Episode {
+        Episode { absolute_number, ..new_episode(id, season, episode, None) }
+    }
+
+    #[test]
+    fn episodes_are_found_by_absolute_number() {
+        let episodes = vec![
+            new_absolute_episode(1, 1, 1, Some(1)),
+            new_absolute_episode(2, 1, 2, Some(2)),
+            new_absolute_episode(3, 2, 1, Some(3)),
+            new_absolute_episode(4, 0, 1, None),
+            new_absolute_episode(5, 2, 2, Some(0)),
+        ];
+        let cache = TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired);
+        assert_eq!(cache.get_by_absolute(1).map(|episode| episode.id), Some(1));
+        assert_eq!(cache.get_by_absolute(3).map(|episode| episode.id), Some(3));
+        // Zero isn't a valid absolute number and missing numbers aren't found
+        assert!(cache.get_by_absolute(0).is_none());
+        assert!(cache.get_by_absolute(4).is_none());
+    }
+
+    #[test]
+    fn absolute_lookup_follows_merged_episodes() {
+        let episodes = vec![new_absolute_episode(1, 1, 1, Some(1))];
+        let mut cache = TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired);
+        cache.merge_episodes(vec![new_absolute_episode(2, 2, 1, Some(13))]);
+        assert_eq!(cache.get_by_absolute(13).map(|episode| episode.id), Some(2));
+        assert_eq!(cache.get_by_absolute(1).map(|episode| episode.id), Some(1));
+    }
+
+    fn new_series_episodes(series_ids: &[Option<u32>]) -> Vec<Episode> {
+        series_ids.iter().enumerate().map(|(index, series_id)| Episode {
+            series_id: *series_id,
+            ..new_episode(index as u32 + 1, 1, index as u32 + 1, None)
+        }).collect()
+    }
+
+    #[test]
+    fn episodes_of_the_series_arent_foreign() {
+        let episodes = new_series_episodes(&[Some(1), None, Some(1)]);
+        assert_eq!(find_foreign_episodes(1, episodes.as_slice()), None);
+    }
+
+    #[test]
+    fn mostly_foreign_episodes_are_rejected() {
+        let episodes = new_series_episodes(&[Some(2), Some(2), Some(3), Some(1)]);
+        let foreign = find_foreign_episodes(1, episodes.as_slice()).unwrap();
+        assert_eq!(foreign.total_foreign, 3);
+        assert_eq!(foreign.total_episodes, 4);
+        assert!(foreign.is_rejected());
+        assert_eq!(foreign.to_string(), "3 of 4 episodes belong to series 2, 3 instead of series 1");
+    }
+
+    #[test]
+    fn few_foreign_episodes_arent_rejected() {
+        let mut series_ids = vec![Some(1); 10];
+        series_ids.push(Some(2));
+        let episodes = new_series_episodes(series_ids.as_slice());
+        let foreign = find_foreign_episodes(1, episodes.as_slice()).unwrap();
+        assert_eq!(foreign.total_foreign, 1);
+        assert!(!foreign.is_rejected());
+    }
+
+    // Seasons 2 and 4 are missing and some air dates can't be read
+    fn new_gapped_season_cache() -> TvdbCache {
+        let episodes = vec![
+            new_aired_episode(6, 5, 1, Some("2024-03-01")),
+            new_aired_episode(1, 1, 1, Some("2020-01-01")),
+            new_aired_episode(2, 1, 2, Some("not a date")),
+            new_aired_episode(3, 3, 2, Some("2022-02-08")),
+            new_aired_episode(4, 3, 1, Some("2022-02-01")),
+            new_aired_episode(5, 3, 3, Some("2022-02-08")),
+            new_aired_episode(7, 5, 2, Some("")),
+        ];
+        TvdbCache::new(new_series(1, "Show"), episodes, EpisodeOrdering::Aired)
+    }
+
+    fn get_season_ids(cache: &TvdbCache, season: u32) -> Vec<u32> {
+        cache.get_season_episodes(season).map(|episode| episode.id).collect()
+    }
+
+    #[test]
+    fn seasons_skip_gaps_in_numbering() {
+        let cache = new_gapped_season_cache();
+        assert_eq!(cache.get_seasons(), vec![1, 3, 5]);
+        assert_eq!(get_season_ids(&cache, 1), vec![1, 2]);
+        // Episodes are in order within the season
+        assert_eq!(get_season_ids(&cache, 3), vec![4, 3, 5]);
+        assert_eq!(get_season_ids(&cache, 5), vec![6, 7]);
+        assert!(get_season_ids(&cache, 2).is_empty());
+        assert!(get_season_ids(&cache, 6).is_empty());
+    }
+
+    #[test]
+    fn season_episodes_follow_episode_ordering() {
+        let cache = new_dvd_order_cache(EpisodeOrdering::Dvd);
+        assert_eq!(cache.get_seasons(), vec![1]);
+        assert_eq!(get_season_ids(&cache, 1), vec![3, 1, 2, 4, 5]);
+        let cache = new_dvd_order_cache(EpisodeOrdering::Aired);
+        assert_eq!(get_season_ids(&cache, 1), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn latest_aired_skips_unparsable_dates() {
+        let cache = new_gapped_season_cache();
+        let get_latest = |before: &str| cache.get_latest_aired(before).map(|episode| episode.id);
+        assert_eq!(get_latest("2019-12-31"), None);
+        assert_eq!(get_latest("2021-06-01"), Some(1));
+        assert_eq!(get_latest("2022-02-01"), Some(4));
+        // Episodes airing on the same day are ordered by their number
+        assert_eq!(get_latest("2022-02-08"), Some(5));
+        assert_eq!(get_latest("2030-01-01"), Some(6));
+        // The date given has to be readable too
+        assert_eq!(get_latest("June 2030"), None);
+    }
 }
@@ -0,0 +1,94 @@
+use chrono;
+use serde;
+use crate::app_folder::{ExecutionRecord, ExecutionSummary};
+use crate::file_intent::Action;
+
+// Files that were renamed by the last execution so it can be reverted
+// Deleted files are listed for reference but can only be recovered from the trash
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UndoJournal {
+    pub executed_at: chrono::DateTime<chrono::Utc>,
+    pub summary: ExecutionSummary,
+    pub renames: Vec<JournalRename>,
+    pub deletes: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JournalRename {
+    pub src: String,
+    pub dest: String,
+}
+
+impl UndoJournal {
+    // Only operations that succeeded are journaled since the others didn't change anything
+    pub fn from_records(
+        records: &[ExecutionRecord], summary: &ExecutionSummary, executed_at: chrono::DateTime<chrono::Utc>,
+    ) -> Self {
+        let succeeded = || records.iter().filter(|record| record.is_ok());
+        Self {
+            executed_at,
+            summary: summary.clone(),
+            renames: succeeded()
+                .filter(|record| record.action == Action::Rename)
+                .map(|record| JournalRename { src: record.src.clone(), dest: record.dest.clone() })
+                .collect(),
+            deletes: succeeded()
+                .filter(|record| record.action == Action::Delete)
+                .map(|record| record.src.clone())
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty() && self.deletes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_record(src: &str, dest: &str, action: Action, is_ok: bool) -> ExecutionRecord {
+        ExecutionRecord {
+            src: src.to_string(),
+            dest: dest.to_string(),
+            action,
+            result: match is_ok {
+                true => Ok(()),
+                false => Err("failed".to_string()),
+            },
+        }
+    }
+
+    #[test]
+    fn only_successful_operations_are_journaled() {
+        let records = [
+            new_record("a.mkv", "Season 01/A.mkv", Action::Rename, true),
+            new_record("b.mkv", "Season 01/B.mkv", Action::Rename, false),
+            new_record("c.nfo", "", Action::Delete, true),
+            new_record("d.nfo", "", Action::Delete, false),
+        ];
+        let summary = ExecutionSummary { renamed: 1, deleted: 1, failed: 2, ..ExecutionSummary::default() };
+        let journal = UndoJournal::from_records(&records, &summary, chrono::Utc::now());
+        assert_eq!(journal.renames, vec![JournalRename { src: "a.mkv".to_string(), dest: "Season 01/A.mkv".to_string() }]);
+        assert_eq!(journal.deletes, vec!["c.nfo".to_string()]);
+        assert_eq!(journal.summary, summary);
+        assert!(!journal.is_empty());
+    }
+
+    #[test]
+    fn journal_round_trips() {
+        let records = [new_record("a.mkv", "Season 01/A.mkv", Action::Rename, true)];
+        let summary = ExecutionSummary { renamed: 1, ..ExecutionSummary::default() };
+        let journal = UndoJournal::from_records(&records, &summary, chrono::Utc::now());
+        let data = serde_json::to_string(&journal).unwrap();
+        assert_eq!(serde_json::from_str::<UndoJournal>(data.as_str()).unwrap(), journal);
+    }
+
+    #[test]
+    fn journal_without_changes_is_empty() {
+        let records = [new_record("a.mkv", "Season 01/A.mkv", Action::Rename, false)];
+        let journal = UndoJournal::from_records(&records, &ExecutionSummary::default(), chrono::Utc::now());
+        assert!(journal.is_empty());
+    }
+}
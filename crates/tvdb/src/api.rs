@@ -1,19 +1,30 @@
 use url;
+use chrono;
 use reqwest;
 use serde;
 use serde_json;
+use fastrand;
 use futures;
+use futures::stream::{self, StreamExt};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror;
+use tokio::sync::{Mutex, RwLock};
 
-use crate::models::{Series, Episode};
+use crate::models::{Actor, EpisodesSummary, Series, Episode};
 
 const BASE_URL: &str = "https://api.thetvdb.com";
+const ARTWORK_URL: &str = "https://artworks.thetvdb.com/banners";
+const CLIENT_USER_AGENT: &str = concat!("torrent-renamer-rust/", env!("CARGO_PKG_VERSION"));
+const CLIENT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+// The api only allows a week between the start and end of an updated query
+const MAX_UPDATED_QUERY_SECONDS: u64 = 7*24*60*60;
 
+// Most responses wrap their result, i.e. {"data": [...]}
 #[derive(serde::Deserialize)]
-struct ResponseBody<'a> {
-    #[serde(borrow)]
-    data: &'a serde_json::value::RawValue,
+struct ResponseBody<T> {
+    data: T,
 }
 
 #[derive(serde::Deserialize)]
@@ -28,17 +39,207 @@ pub enum ApiError {
     RequestFailure(reqwest::Error),
     #[error("unexpected response: code={} body={}", .0, .1)]
     UnexpectedResponse(reqwest::StatusCode, String),
+    #[error("rate limited{}: {}", get_retry_after_label(.retry_after), .message)]
+    RateLimited { retry_after: Option<Duration>, message: String },
     #[error("json encode error: {}", .0)]
     JsonEncode(serde_json::Error),
     #[error("json decode error: {}", .0)]
     JsonDecode(serde_json::Error),
+    #[error("{} after {} retries", .error, .retries)]
+    Retried { error: Box<ApiError>, retries: u32 },
+    #[error("failed to get episodes page {}: {}", .page, .error)]
+    EpisodesPage { page: u32, error: Box<ApiError> },
 }
 
+impl ApiError {
+    // Connection problems and server side errors usually go away if the request is sent again
+    fn is_transient(&self) -> bool {
+        match self {
+            ApiError::RequestFailure(err) => err.is_connect() || err.is_timeout(),
+            ApiError::UnexpectedResponse(status, _) => status.is_server_error(),
+            ApiError::RateLimited { .. } => true,
+            _ => false,
+        }
+    }
+
+    // How long the server asked us to wait if we were rate limited
+    pub fn get_retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::RateLimited { retry_after, .. } => *retry_after,
+            ApiError::Retried { error, .. } => error.get_retry_after(),
+            ApiError::EpisodesPage { error, .. } => error.get_retry_after(),
+            _ => None,
+        }
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        match self {
+            ApiError::RateLimited { .. } => true,
+            ApiError::Retried { error, .. } => error.is_rate_limited(),
+            ApiError::EpisodesPage { error, .. } => error.is_rate_limited(),
+            _ => false,
+        }
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self, ApiError::UnexpectedResponse(status, _) if *status == reqwest::StatusCode::UNAUTHORIZED)
+    }
+}
+
+fn get_retry_after_label(retry_after: &Option<Duration>) -> String {
+    match retry_after {
+        Some(delay) => format!(", try again in {}s", delay.as_secs_f64().ceil()),
+        None => "".to_string(),
+    }
+}
+
+// The server can ask us to wait with either a delay in seconds or a http date
+// i.e. "Retry-After: 120" or "Retry-After: Wed, 21 Oct 2015 07:28:00 GMT"
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date: SystemTime = chrono::DateTime::parse_from_rfc2822(value).ok()?.into();
+    // Dates in the past mean that we can try again immediately
+    Some(date.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+pub(crate) fn get_retry_after(res: &reqwest::Response) -> Option<Duration> {
+    let value = res.headers().get(reqwest::header::RETRY_AFTER)?;
+    parse_retry_after(value.to_str().ok()?, SystemTime::now())
+}
+
+// Shared by all requests of a session so that they all wait after one of them was rate limited
+#[derive(Default)]
+pub(crate) struct RateLimitPause {
+    resume_time: std::sync::Mutex<Option<Instant>>,
+}
+
+impl RateLimitPause {
+    pub(crate) fn pause(&self, delay: Duration) {
+        let resume_time = Instant::now() + delay;
+        let mut current = self.resume_time.lock().unwrap_or_else(|err| err.into_inner());
+        // A shorter delay from a concurrent request doesn't cut the longer one short
+        if current.is_none_or(|current| current < resume_time) {
+            *current = Some(resume_time);
+        }
+    }
+
+    pub(crate) fn get_remaining(&self) -> Option<Duration> {
+        let resume_time = (*self.resume_time.lock().unwrap_or_else(|err| err.into_inner()))?;
+        let remaining = resume_time.saturating_duration_since(Instant::now());
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    // Records the delay of rate limited responses
+    pub(crate) fn update<T>(&self, res: &Result<T, ApiError>) {
+        if let Err(err) = res {
+            if let Some(delay) = err.get_retry_after() {
+                self.pause(delay);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    // Includes the first attempt so 1 disables retries
+    pub max_attempts: u32,
+    // Doubled after each retry
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionOptions {
+    // Only changed to send requests to a local server in tests
+    pub base_url: String,
+    pub retry_policy: RetryPolicy,
+    // Pages of episodes that are fetched at the same time for a single series
+    pub max_concurrent_pages: usize,
+    // Shared by every request of the session so refreshing many folders doesn't get rate limited
+    pub max_requests_per_second: f64,
+    pub max_burst_requests: u32,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            base_url: BASE_URL.to_string(),
+            retry_policy: RetryPolicy::default(),
+            max_concurrent_pages: 4,
+            max_requests_per_second: 5.0,
+            max_burst_requests: 10,
+        }
+    }
+}
+
+// Token bucket which refills continuously up to its capacity
+struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(capacity: u32, refill_per_second: f64) -> Self {
+        Self {
+            capacity: capacity.max(1) as f64,
+            refill_per_second,
+            tokens: capacity.max(1) as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    // Returns how long to wait before trying again if there were no tokens left
+    fn try_acquire(&mut self, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed*self.refill_per_second).min(self.capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return None;
+        }
+        Some(Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_second))
+    }
+}
+
+impl RetryPolicy {
+    // Jitter stops concurrent requests that failed together from retrying together
+    fn get_delay(&self, retry: u32) -> Duration {
+        let delay = self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        delay.mul_f64(0.5 + 0.5*fastrand::f64())
+    }
+}
+
+// Newer api keys don't need a user key and username so they are left out of the login if missing
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct LoginInfo {
     pub apikey: String,
-    pub userkey: String,
-    pub username: String,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub userkey: Option<String>,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub username: Option<String>,
+}
+
+impl LoginInfo {
+    pub fn is_key_only(&self) -> bool {
+        self.userkey.is_none() && self.username.is_none()
+    }
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
@@ -46,6 +247,11 @@ pub struct LoginToken {
     pub token: String,
 }
 
+#[derive(serde::Deserialize, Debug, Clone)]
+struct UpdatedSeries {
+    id: u32,
+}
+
 #[derive(serde::Deserialize, Debug, Clone)]
 struct EpisodesPageLinks {
     next: Option<u32>,
@@ -59,150 +265,312 @@ struct EpisodesPage {
     links: Option<EpisodesPageLinks>,    
 }
 
+// Pages after the first one up to and including the last page
+// The range is empty if there is only a single page or the last page is unknown
+fn get_remaining_pages(links: &EpisodesPageLinks) -> std::ops::RangeInclusive<u32> {
+    let next_page = links.next.unwrap_or(2).max(2);
+    let last_page = links.last.unwrap_or(0);
+    next_page..=last_page
+}
+
+// The token can be refreshed in place so a shared session stays valid
 pub struct LoginSession {
     client: Arc<reqwest::Client>,
-    token: LoginToken,
+    token: RwLock<LoginToken>,
+    // Used to login again if the token can no longer be refreshed
+    login_info: Option<LoginInfo>,
+    renew_lock: Mutex<()>,
+    is_expired: AtomicBool,
+    options: SessionOptions,
+    rate_limiter: Mutex<RateLimiter>,
+    rate_limit_pause: RateLimitPause,
 }
 
-pub async fn login(client: &reqwest::Client, login_info: &LoginInfo) -> Result<LoginToken, ApiError> {
+pub async fn login(client: &reqwest::Client, base_url: &str, login_info: &LoginInfo) -> Result<LoginToken, ApiError> {
     let res = client
-        .post(format!("{}/login", BASE_URL))
+        .post(format!("{}/login", base_url))
         .header("Content-Type", "application/json")
         .body(serde_json::to_string(login_info).map_err(ApiError::JsonEncode)?)
         .send()
         .await
         .map_err(ApiError::RequestFailure)?;
 
+    parse_response(res).await
+}
+
+impl LoginSession {
+    pub fn new(
+        client: Arc<reqwest::Client>, token: &LoginToken, login_info: Option<LoginInfo>, options: SessionOptions,
+    ) -> Self {
+        Self {
+            client,
+            token: RwLock::new(token.clone()),
+            login_info,
+            renew_lock: Mutex::new(()),
+            is_expired: AtomicBool::new(false),
+            rate_limiter: Mutex::new(RateLimiter::new(options.max_burst_requests, options.max_requests_per_second)),
+            rate_limit_pause: RateLimitPause::default(),
+            options,
+        }
+    }
+
+    pub async fn get_token(&self) -> LoginToken {
+        self.token.read().await.clone()
+    }
+
+    // The token was rejected and couldn't be renewed
+    pub fn is_expired(&self) -> bool {
+        self.is_expired.load(Ordering::Relaxed)
+    }
+
+    // Time left until the server accepts requests again after rate limiting us
+    pub fn get_rate_limit_delay(&self) -> Option<Duration> {
+        self.rate_limit_pause.get_remaining()
+    }
+}
+
+// Prefer the error message in the body, i.e. {"Error": "Resource not found"}, over the raw body
+pub fn get_error_message(body: String) -> String {
+    match serde_json::from_str::<ErrorBody>(body.as_str()) {
+        Ok(value) => value.error,
+        Err(_) => body,
+    }
+}
+
+async fn get_response_body(res: reqwest::Response) -> Result<String, ApiError> {
     let status = res.status();
+    let retry_after = get_retry_after(&res);
     let body = res.text().await.map_err(ApiError::RequestFailure)?;
     if !status.is_success() {
-        let message: Result<ErrorBody, serde_json::Error> = serde_json::from_str(body.as_str());
-        let error = match message {
-            Ok(value) => value.error.as_str().to_string(),
-            Err(_) => body,
-        };
+        let error = get_error_message(body);
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(ApiError::RateLimited { retry_after, message: error });
+        }
         return Err(ApiError::UnexpectedResponse(status, error));
     };
+    Ok(body)
+}
 
-    let session: LoginToken = serde_json::from_str(body.as_str()).map_err(ApiError::JsonDecode)?; 
-    Ok(session)
+// Checks the status before decoding the body as json
+pub async fn parse_response<T: serde::de::DeserializeOwned>(res: reqwest::Response) -> Result<T, ApiError> {
+    let body = get_response_body(res).await?;
+    serde_json::from_str(body.as_str()).map_err(ApiError::JsonDecode)
 }
 
-impl LoginSession {
-    pub fn new(client: Arc<reqwest::Client>, token: &LoginToken) -> Self {
-        Self {
-            client,
-            token: token.clone(),
-        }
+// Gzip makes the large pages of episodes much smaller and idle connections are reused between pages
+pub fn build_client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .gzip(true)
+        .pool_idle_timeout(CLIENT_POOL_IDLE_TIMEOUT)
+        .user_agent(CLIENT_USER_AGENT)
+        .build()
+}
+
+// Translations that are missing fall back to the default language on the server
+fn with_language(request: reqwest::RequestBuilder, language: Option<&str>) -> reqwest::RequestBuilder {
+    match language {
+        Some(language) => request.header(reqwest::header::ACCEPT_LANGUAGE, language),
+        None => request,
     }
 }
 
+fn is_unauthorized<T>(res: &Result<T, ApiError>) -> bool {
+    matches!(res, Err(err) if err.is_unauthorized())
+}
+
 impl LoginSession {
-    pub async fn refresh_token(&mut self) -> Result<(), ApiError> {
-        let token = self.get_new_token().await?;
-        self.token = token;
-        Ok(())
-    }
+    // Requests are only sent again if they can be cloned and failed due to a transient error
+    async fn send_with_token<T: serde::de::DeserializeOwned>(&self, request: reqwest::RequestBuilder, token: &str) -> Result<T, ApiError> {
+        let mut request = request;
+        let mut total_retries: u32 = 0;
+        let retry_policy = &self.options.retry_policy;
+        loop {
+            self.wait_for_rate_limit().await;
+            let retry_request = request.try_clone();
+            let res = match request.header("Authorization", format!("Bearer {}", token)).send().await {
+                Ok(res) => parse_response(res).await,
+                Err(err) => Err(ApiError::RequestFailure(err)),
+            };
+            self.rate_limit_pause.update(&res);
 
-    pub async fn get_new_token(&self) -> Result<LoginToken, ApiError> {
-        let res = self.client
-            .get(format!("{}/refresh_token", BASE_URL))
-            .header("Authorization", format!("Bearer {}", self.token.token))
-            .send()
-            .await
-            .map_err(ApiError::RequestFailure)?;
-        
-        let status = res.status();
-        let body = res.text().await.map_err(ApiError::RequestFailure)?;
-        if !status.is_success() {
-            let message: Result<ErrorBody, serde_json::Error> = serde_json::from_str(body.as_str());
-            let error = match message {
-                Ok(value) => value.error.as_str().to_string(),
-                Err(_) => body,
+            // Waiting longer than the policy allows is left to the user instead of blocking the request
+            let retry_after = res.as_ref().err().and_then(|err| err.get_retry_after());
+            let is_wait_allowed = retry_after.is_none_or(|delay| delay <= retry_policy.max_delay);
+            let is_retry = (total_retries+1) < retry_policy.max_attempts
+                && is_wait_allowed
+                && res.as_ref().is_err_and(|err| err.is_transient());
+            request = match retry_request {
+                Some(retry_request) if is_retry => retry_request,
+                _ => return res.map_err(|error| match total_retries {
+                    0 => error,
+                    retries => ApiError::Retried { error: Box::new(error), retries },
+                }),
             };
-            return Err(ApiError::UnexpectedResponse(status, error));
+            // The rate limit pause is waited on before the next attempt
+            if retry_after.is_none() {
+                tokio::time::sleep(retry_policy.get_delay(total_retries)).await;
+            }
+            total_retries += 1;
+        }
+    }
+
+    async fn wait_for_rate_limit(&self) {
+        // A rate of zero or less disables the limit
+        if let Some(delay) = self.rate_limit_pause.get_remaining() {
+            tokio::time::sleep(delay).await;
+        }
+        if self.options.max_requests_per_second <= 0.0 {
+            return;
+        }
+        loop {
+            let delay = self.rate_limiter.lock().await.try_acquire(Instant::now());
+            match delay {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return,
+            }
+        }
+    }
+
+    // Requests that are unauthorised are retried once after renewing the token
+    async fn send_authorized<T: serde::de::DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T, ApiError> {
+        let retry_request = request.try_clone();
+        let old_token = self.get_token().await.token;
+        let res = self.send_with_token(request, old_token.as_str()).await;
+        if !is_unauthorized(&res) {
+            return res;
+        }
+        let retry_request = match retry_request {
+            Some(request) => request,
+            None => return res,
         };
+        self.renew_token(old_token.as_str()).await?;
+        let new_token = self.get_token().await.token;
+        self.send_with_token(retry_request, new_token.as_str()).await
+    }
+
+    // Concurrent requests that were rejected with the same token only renew it once
+    async fn renew_token(&self, old_token: &str) -> Result<(), ApiError> {
+        let _renew_lock = self.renew_lock.lock().await;
+        if self.get_token().await.token != old_token {
+            return Ok(());
+        }
+
+        // Refreshing only works while the token is valid so we login again if it fails
+        let res = match (self.request_new_token(old_token).await, self.login_info.as_ref()) {
+            (Err(_), Some(login_info)) => login(self.client.as_ref(), self.options.base_url.as_str(), login_info).await,
+            (res, _) => res,
+        };
+        match res {
+            Ok(token) => {
+                *self.token.write().await = token;
+                self.is_expired.store(false, Ordering::Relaxed);
+                Ok(())
+            },
+            Err(err) => {
+                // Network errors don't mean that the token is invalid
+                if matches!(err, ApiError::UnexpectedResponse(..)) {
+                    self.is_expired.store(true, Ordering::Relaxed);
+                }
+                Err(err)
+            },
+        }
+    }
+
+    async fn request_new_token(&self, token: &str) -> Result<LoginToken, ApiError> {
+        let request = self.client.get(format!("{}/refresh_token", self.options.base_url));
+        self.send_with_token(request, token).await
+    }
 
-        let token: LoginToken = serde_json::from_str(body.as_str()).map_err(ApiError::JsonDecode)?; 
-        Ok(token)
+    // Falls back to logging in again if the token couldn't be refreshed
+    pub async fn refresh_token(&self) -> Result<(), ApiError> {
+        let old_token = self.get_token().await.token;
+        self.renew_token(old_token.as_str()).await
     }
 
-    pub async fn search_series(&self, name: &String) -> Result<Vec<Series>, ApiError> {
+    pub async fn get_new_token(&self) -> Result<LoginToken, ApiError> {
+        let token = self.get_token().await.token;
+        self.request_new_token(token.as_str()).await
+    }
+
+    pub async fn search_series(&self, name: &String, language: Option<&str>) -> Result<Vec<Series>, ApiError> {
         let params = [("name", name)];
-        let base_url = format!("{}/search/series", BASE_URL);
+        let base_url = format!("{}/search/series", self.options.base_url);
         let full_url = url::Url::parse_with_params(base_url.as_str(), &params).expect("Url is valid");
-        let res = self.client
-            .get(full_url.as_str())
-            .header("Authorization", format!("Bearer {}", self.token.token))
-            .send()
-            .await
-            .map_err(ApiError::RequestFailure)?;
-
-        let status = res.status();
-        let body = res.text().await.map_err(ApiError::RequestFailure)?;
-        if !status.is_success() {
-            let message: Result<ErrorBody, serde_json::Error> = serde_json::from_str(body.as_str());
-            let error = match message {
-                Ok(value) => value.error.as_str().to_string(),
-                Err(_) => body,
-            };
-            return Err(ApiError::UnexpectedResponse(status, error));
-        };
+        let request = with_language(self.client.get(full_url.as_str()), language);
+        let response_body: ResponseBody<Vec<Series>> = self.send_authorized(request).await?;
+        Ok(response_body.data)
+    }
 
-        let response_body: ResponseBody = serde_json::from_str(body.as_str()).map_err(ApiError::JsonDecode)?;
-        let data: Vec<Series> = serde_json::from_str(response_body.data.get()).map_err(ApiError::JsonDecode)?;
-        Ok(data)
+    pub async fn get_series(&self, id: u32, language: Option<&str>) -> Result<Series, ApiError> {
+        let request = with_language(self.client.get(format!("{}/series/{}", self.options.base_url, id)), language);
+        let response_body: ResponseBody<Series> = self.send_authorized(request).await?;
+        Ok(response_body.data)
     }
 
-    pub async fn get_series(&self, id: u32) -> Result<Series, ApiError> {
-        let res = self.client
-            .get(format!("{}/series/{}", BASE_URL, id))
-            .header("Authorization", format!("Bearer {}", self.token.token))
-            .send()
-            .await
-            .map_err(ApiError::RequestFailure)?;
+    pub async fn get_actors(&self, id: u32) -> Result<Vec<Actor>, ApiError> {
+        let request = self.client.get(format!("{}/series/{}/actors", self.options.base_url, id));
+        let response_body: ResponseBody<Vec<Actor>> = self.send_authorized(request).await?;
+        Ok(response_body.data)
+    }
 
-        let status = res.status();
-        let body = res.text().await.map_err(ApiError::RequestFailure)?;
-        if !status.is_success() {
-            let message: Result<ErrorBody, serde_json::Error> = serde_json::from_str(body.as_str());
-            let error = match message {
-                Ok(value) => value.error.as_str().to_string(),
-                Err(_) => body,
-            };
-            return Err(ApiError::UnexpectedResponse(status, error));
-        };
+    pub async fn get_episodes_summary(&self, id: u32) -> Result<EpisodesSummary, ApiError> {
+        let request = self.client.get(format!("{}/series/{}/episodes/summary", self.options.base_url, id));
+        let response_body: ResponseBody<EpisodesSummary> = self.send_authorized(request).await?;
+        Ok(response_body.data)
+    }
 
-        let response_body: ResponseBody = serde_json::from_str(body.as_str()).map_err(ApiError::JsonDecode)?;
-        let series: Series = serde_json::from_str(response_body.data.get()).map_err(ApiError::JsonDecode)?;
-        Ok(series)
+    // Series which were updated since the unix timestamp in seconds
+    // Longer time spans are split into multiple queries since each query is limited to a week
+    pub async fn get_updated_series(&self, from_time: u64) -> Result<Vec<u32>, ApiError> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let mut series_ids = Vec::new();
+        let mut start_time = from_time;
+        while start_time < now {
+            let end_time = start_time.saturating_add(MAX_UPDATED_QUERY_SECONDS).min(now);
+            let url = format!("{}/updated/query?fromTime={}&toTime={}", self.options.base_url, start_time, end_time);
+            // No data is sent if nothing was updated
+            let response_body: ResponseBody<Option<Vec<UpdatedSeries>>> = self.send_authorized(self.client.get(url)).await?;
+            series_ids.extend(response_body.data.unwrap_or_default().iter().map(|series| series.id));
+            start_time = end_time;
+        }
+        series_ids.sort_unstable();
+        series_ids.dedup();
+        Ok(series_ids)
     }
 
-    async fn get_episodes_page(&self, id: u32, page: u32) -> Result<EpisodesPage, ApiError> {
-        let res = self.client
-            .get(format!("{}/series/{}/episodes?page={}", BASE_URL, id, page))
-            .header("Authorization", format!("Bearer {}", self.token.token))
-            .send()
-            .await
-            .map_err(ApiError::RequestFailure)?;
-        
+    // Artwork is public so the token isn't sent, i.e. "posters/78874-1.jpg"
+    pub async fn download_image(&self, relative_path: &str) -> Result<Vec<u8>, ApiError> {
+        let url = format!("{}/{}", ARTWORK_URL, relative_path.trim_start_matches('/'));
+        self.wait_for_rate_limit().await;
+        let res = self.client.get(url).send().await.map_err(ApiError::RequestFailure)?;
         let status = res.status();
-        let body = res.text().await.map_err(ApiError::RequestFailure)?;
         if !status.is_success() {
-            let message: Result<ErrorBody, serde_json::Error> = serde_json::from_str(body.as_str());
-            let error = match message {
-                Ok(value) => value.error.as_str().to_string(),
-                Err(_) => body,
-            };
-            return Err(ApiError::UnexpectedResponse(status, error));
+            return Err(ApiError::UnexpectedResponse(status, status.to_string()));
+        }
+        let bytes = res.bytes().await.map_err(ApiError::RequestFailure)?;
+        Ok(bytes.to_vec())
+    }
+
+    // Only episodes from the season are returned if it is provided, i.e. ("airedSeason", 1)
+    async fn get_episodes_page(&self, id: u32, season: Option<(&str, u32)>, page: u32, language: Option<&str>) -> Result<EpisodesPage, ApiError> {
+        let url = match season {
+            Some((key, season)) => format!("{}/series/{}/episodes/query?{}={}&page={}", self.options.base_url, id, key, season, page),
+            None => format!("{}/series/{}/episodes?page={}", self.options.base_url, id, page),
         };
-        let page: EpisodesPage = serde_json::from_str(body.as_str()).map_err(ApiError::JsonDecode)?;
-        Ok(page)
+        let request = with_language(self.client.get(url), language);
+        self.send_authorized(request).await
     }
 
-    pub async fn get_episodes(&self, id: u32) -> Result<Vec<Episode>, ApiError> {
-        let page_1 = match self.get_episodes_page(id, 1).await {
+    async fn get_all_episodes_pages(&self, id: u32, season: Option<(&str, u32)>, language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        let page_1 = match self.get_episodes_page(id, season, 1, language).await {
             Ok(page) => page,
+            // The query endpoint responds with not found if the season has no episodes
+            Err(ApiError::UnexpectedResponse(status, _)) if season.is_some() && status == reqwest::StatusCode::NOT_FOUND => {
+                return Ok(Vec::new());
+            },
             Err(err) => return Err(err),
         };
 
@@ -211,15 +579,19 @@ impl LoginSession {
             all_episodes.extend_from_slice(episodes.as_slice());
         }
 
-        if let Some(links) = page_1.links {
-            let next_page = links.next.unwrap_or(2);
-            let last_page = links.last.unwrap_or(0);
-            let tasks: Vec<_> = (next_page..=last_page)
-                .map(|page| self.get_episodes_page(id, page))
-                .collect();
+        if let Some(links) = page_1.links.as_ref() {
+            // Pages are kept in order and a failed page fails the whole list so it isn't silently truncated
+            let pages: Vec<Result<EpisodesPage, ApiError>> = stream::iter(get_remaining_pages(links))
+                .map(|page| async move {
+                    self.get_episodes_page(id, season, page, language).await
+                        .map_err(|error| ApiError::EpisodesPage { page, error: Box::new(error) })
+                })
+                .buffered(self.options.max_concurrent_pages.max(1))
+                .collect()
+                .await;
 
-            for page in futures::future::join_all(tasks).await.into_iter().flatten() {
-                if let Some(episodes) = page.episodes {
+            for page in pages {
+                if let Some(episodes) = page?.episodes {
                     all_episodes.extend_from_slice(episodes.as_slice());
                 }
             }
@@ -227,4 +599,432 @@ impl LoginSession {
 
         Ok(all_episodes)
     }
+
+    pub async fn get_episodes(&self, id: u32, language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        self.get_all_episodes_pages(id, None, language).await
+    }
+
+    pub async fn get_episodes_by_season(&self, id: u32, season: u32, language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        self.get_all_episodes_pages(id, Some(("airedSeason", season)), language).await
+    }
+
+    pub async fn get_episodes_by_dvd_season(&self, id: u32, season: u32, language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        self.get_all_episodes_pages(id, Some(("dvdSeason", season)), language).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{TestRequest, TestResponse, TestServer, new_test_client};
+
+    fn new_test_options(server: &TestServer) -> SessionOptions {
+        SessionOptions {
+            base_url: server.get_url().to_string(),
+            retry_policy: RetryPolicy { max_attempts: 1, ..RetryPolicy::default() },
+            ..SessionOptions::default()
+        }
+    }
+
+    fn new_test_session(server: &TestServer, token: &str) -> LoginSession {
+        let token = LoginToken { token: token.to_string() };
+        LoginSession::new(new_test_client(), &token, None, new_test_options(server))
+    }
+
+    fn new_token_response(token: &str) -> TestResponse {
+        TestResponse::new(200, serde_json::json!({ "token": token }).to_string())
+    }
+
+    fn new_series_response(request: &TestRequest) -> TestResponse {
+        match request.get_bearer_token() {
+            Some("new") => TestResponse::new(200, serde_json::json!({ "data": { "id": 1, "seriesName": "Show" } }).to_string()),
+            _ => TestResponse::new(401, r#"{"Error": "Not authorized"}"#),
+        }
+    }
+
+    #[tokio::test]
+    async fn unauthorized_request_is_retried_with_new_token() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/refresh_token" => new_token_response("new"),
+            _ => new_series_response(request),
+        }).await;
+        let session = new_test_session(&server, "old");
+
+        let series = session.get_series(1, None).await.unwrap();
+        assert_eq!(series.name, "Show");
+        assert_eq!(session.get_token().await.token, "new");
+        assert!(!session.is_expired());
+        let tokens: Vec<Option<String>> = server.get_requests()
+            .iter()
+            .map(|request| request.get_bearer_token().map(|token| token.to_string()))
+            .collect();
+        assert_eq!(tokens, vec![Some("old".to_string()), Some("old".to_string()), Some("new".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn unauthorized_request_is_only_retried_once() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/refresh_token" => new_token_response("newer"),
+            _ => new_series_response(request),
+        }).await;
+        let session = new_test_session(&server, "old");
+
+        let err = session.get_series(1, None).await.unwrap_err();
+        assert!(err.is_unauthorized());
+        assert_eq!(server.get_total_requests("/series/1"), 2);
+        assert_eq!(server.get_total_requests("/refresh_token"), 1);
+        assert_eq!(session.get_token().await.token, "newer");
+    }
+
+    #[tokio::test]
+    async fn concurrent_unauthorized_requests_refresh_once() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/refresh_token" => new_token_response("new"),
+            _ => new_series_response(request),
+        }).await;
+        let session = new_test_session(&server, "old");
+
+        let results = futures::future::join_all((0..5).map(|_| session.get_series(1, None))).await;
+        assert!(results.iter().all(|res| res.is_ok()));
+        assert_eq!(server.get_total_requests("/refresh_token"), 1);
+        assert_eq!(server.get_total_requests("/series/1"), 10);
+    }
+
+    #[tokio::test]
+    async fn rejected_refresh_expires_session() {
+        let server = TestServer::start(|_| TestResponse::new(401, r#"{"Error": "Not authorized"}"#)).await;
+        let session = new_test_session(&server, "old");
+
+        assert!(session.get_series(1, None).await.unwrap_err().is_unauthorized());
+        assert!(session.is_expired());
+        assert_eq!(session.get_token().await.token, "old");
+    }
+
+    fn new_retry_session(server: &TestServer, max_attempts: u32) -> LoginSession {
+        let options = SessionOptions {
+            retry_policy: RetryPolicy { max_attempts, base_delay: Duration::from_millis(1), max_delay: Duration::from_millis(10) },
+            ..new_test_options(server)
+        };
+        LoginSession::new(new_test_client(), &LoginToken { token: "new".to_string() }, None, options)
+    }
+
+    // Responds with the status until the number of failures is reached
+    async fn start_failing_server(status: u16, total_failures: usize) -> TestServer {
+        let total_requests = std::sync::atomic::AtomicUsize::new(0);
+        TestServer::start(move |request| {
+            match total_requests.fetch_add(1, Ordering::Relaxed) < total_failures {
+                true => TestResponse::new(status, r#"{"Error": "Unavailable"}"#),
+                false => new_series_response(request),
+            }
+        }).await
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried() {
+        let server = start_failing_server(503, 2).await;
+        let session = new_retry_session(&server, 3);
+        let series = session.get_series(1, None).await.unwrap();
+        assert_eq!(series.name, "Show");
+        assert_eq!(server.get_total_requests("/series/1"), 3);
+    }
+
+    #[tokio::test]
+    async fn retries_stop_at_max_attempts() {
+        let server = start_failing_server(503, 5).await;
+        let session = new_retry_session(&server, 3);
+        let err = session.get_series(1, None).await.unwrap_err();
+        assert!(matches!(err, ApiError::Retried { retries: 2, .. }));
+        assert!(err.to_string().ends_with("after 2 retries"), "{}", err);
+        assert_eq!(server.get_total_requests("/series/1"), 3);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = start_failing_server(404, 1).await;
+        let session = new_retry_session(&server, 3);
+        let err = session.get_series(1, None).await.unwrap_err();
+        assert!(matches!(err, ApiError::UnexpectedResponse(status, _) if status == reqwest::StatusCode::NOT_FOUND));
+        assert_eq!(server.get_total_requests("/series/1"), 1);
+    }
+
+    // Each page has two episodes with ids based on the page number
+    fn new_episodes_page_response(page: u32, last_page: u32) -> TestResponse {
+        let episodes: Vec<serde_json::Value> = (1..=2)
+            .map(|index| serde_json::json!({ "id": page*10 + index, "airedSeason": 1, "airedEpisodeNumber": (page-1)*2 + index }))
+            .collect();
+        let next_page = (page < last_page).then_some(page+1);
+        let body = serde_json::json!({ "data": episodes, "links": { "next": next_page, "last": last_page } });
+        TestResponse::new(200, body.to_string())
+    }
+
+    fn get_query_page(request: &TestRequest) -> u32 {
+        request.path
+            .split_once("page=")
+            .and_then(|(_, page)| page.parse().ok())
+            .unwrap_or(1)
+    }
+
+    #[tokio::test]
+    async fn failing_middle_page_fails_episodes() {
+        let server = TestServer::start(|request| match get_query_page(request) {
+            2 => TestResponse::new(500, r#"{"Error": "Internal error"}"#),
+            page => new_episodes_page_response(page, 3),
+        }).await;
+        let session = new_test_session(&server, "new");
+
+        let err = session.get_episodes(1, None).await.unwrap_err();
+        assert!(matches!(err, ApiError::EpisodesPage { page: 2, .. }), "{}", err);
+        assert_eq!(server.get_total_requests("/series/1/episodes"), 3);
+    }
+
+    #[tokio::test]
+    async fn episodes_pages_are_kept_in_order() {
+        let server = TestServer::start(|request| new_episodes_page_response(get_query_page(request), 3)).await;
+        let options = SessionOptions { max_concurrent_pages: 2, ..new_test_options(&server) };
+        let session = LoginSession::new(new_test_client(), &LoginToken { token: "new".to_string() }, None, options);
+
+        let episodes = session.get_episodes(1, None).await.unwrap();
+        let ids: Vec<u32> = episodes.iter().map(|episode| episode.id).collect();
+        assert_eq!(ids, vec![11, 12, 21, 22, 31, 32]);
+    }
+
+    #[tokio::test]
+    async fn language_is_sent_as_header() {
+        let server = TestServer::start(|request| {
+            let name = match request.get_header("Accept-Language") {
+                Some("ja") => "ショー",
+                _ => "Show",
+            };
+            TestResponse::new(200, serde_json::json!({ "data": { "id": 1, "seriesName": name } }).to_string())
+        }).await;
+        let session = new_test_session(&server, "new");
+
+        assert_eq!(session.get_series(1, Some("ja")).await.unwrap().name, "ショー");
+        assert_eq!(session.get_series(1, None).await.unwrap().name, "Show");
+        let languages: Vec<Option<String>> = server.get_requests()
+            .iter()
+            .map(|request| request.get_header("Accept-Language").map(|language| language.to_string()))
+            .collect();
+        assert_eq!(languages, vec![Some("ja".to_string()), None]);
+    }
+
+    #[tokio::test]
+    async fn updated_series_are_queried_a_week_at_a_time() {
+        let server = TestServer::start(|_| TestResponse::new(200, r#"{"data": [{"id": 3}, {"id": 1}]}"#)).await;
+        let session = new_test_session(&server, "new");
+        let now = SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+
+        // Series updated in both weeks are only listed once
+        let ids = session.get_updated_series(now - 10*24*60*60).await.unwrap();
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(server.get_total_requests("/updated/query"), 2);
+        // Nothing is sent if there is no time span to check
+        assert!(session.get_updated_series(now + 60).await.unwrap().is_empty());
+        assert_eq!(server.get_total_requests("/updated/query"), 2);
+    }
+
+    async fn get_total_episodes(total_pages: u32) -> (usize, usize) {
+        let server = TestServer::start(move |request| new_episodes_page_response(get_query_page(request), total_pages)).await;
+        let session = new_test_session(&server, "new");
+        let episodes = session.get_episodes(1, None).await.unwrap();
+        (episodes.len(), server.get_total_requests("/series/1/episodes"))
+    }
+
+    #[tokio::test]
+    async fn every_page_of_episodes_is_fetched() {
+        assert_eq!(get_total_episodes(1).await, (2, 1));
+        assert_eq!(get_total_episodes(2).await, (4, 2));
+        assert_eq!(get_total_episodes(3).await, (6, 3));
+        assert_eq!(get_total_episodes(5).await, (10, 5));
+    }
+
+    #[test]
+    fn remaining_pages_include_the_last_page() {
+        let get_pages = |next: Option<u32>, last: Option<u32>| -> Vec<u32> {
+            get_remaining_pages(&EpisodesPageLinks { next, last }).collect()
+        };
+        assert_eq!(get_pages(Some(2), Some(5)), vec![2, 3, 4, 5]);
+        assert_eq!(get_pages(Some(2), Some(2)), vec![2]);
+        assert_eq!(get_pages(None, Some(1)), Vec::<u32>::new());
+        assert_eq!(get_pages(None, Some(0)), Vec::<u32>::new());
+        assert_eq!(get_pages(Some(2), None), Vec::<u32>::new());
+        // The first page is never fetched again
+        assert_eq!(get_pages(Some(1), Some(2)), vec![2]);
+    }
+
+    #[test]
+    fn numeric_retry_after_is_parsed() {
+        let now = SystemTime::now();
+        assert_eq!(parse_retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 30 ", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("0", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("-5", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn date_retry_after_is_parsed() {
+        let now: SystemTime = chrono::DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT").unwrap().into();
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 08:28:00 GMT", now), Some(Duration::from_secs(3600)));
+        // Dates that already passed don't need any waiting
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("21/10/2015 07:28:30", now), None);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_response_records_delay() {
+        let server = TestServer::start(|_| {
+            TestResponse::new(429, r#"{"Error": "Too many requests"}"#).with_header("Retry-After", "30")
+        }).await;
+        let session = new_retry_session(&server, 3);
+
+        let err = session.get_series(1, None).await.unwrap_err();
+        assert!(err.is_rate_limited());
+        assert_eq!(err.get_retry_after(), Some(Duration::from_secs(30)));
+        assert_eq!(err.to_string(), "rate limited, try again in 30s: Too many requests");
+        // Waiting longer than the retry policy allows is left to the user
+        assert_eq!(server.get_total_requests("/series/1"), 1);
+        assert!(session.get_rate_limit_delay().is_some_and(|delay| delay > Duration::from_secs(25)));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_response_with_date_records_delay() {
+        let server = TestServer::start(|_| {
+            let retry_time = chrono::Utc::now() + chrono::Duration::seconds(90);
+            TestResponse::new(429, "slow down").with_header("Retry-After", retry_time.to_rfc2822().as_str())
+        }).await;
+        let session = new_retry_session(&server, 1);
+
+        let err = session.get_series(1, None).await.unwrap_err();
+        let retry_after = err.get_retry_after().unwrap();
+        assert!(retry_after > Duration::from_secs(80) && retry_after <= Duration::from_secs(90), "{:?}", retry_after);
+        assert!(err.to_string().ends_with(": slow down"));
+    }
+
+    #[tokio::test]
+    async fn short_retry_after_is_waited_on() {
+        let total_requests = std::sync::atomic::AtomicUsize::new(0);
+        let server = TestServer::start(move |request| match total_requests.fetch_add(1, Ordering::Relaxed) {
+            0 => TestResponse::new(429, "{}").with_header("Retry-After", "0"),
+            _ => new_series_response(request),
+        }).await;
+        let session = new_retry_session(&server, 2);
+        assert_eq!(session.get_series(1, None).await.unwrap().name, "Show");
+        assert_eq!(server.get_total_requests("/series/1"), 2);
+    }
+
+    #[test]
+    fn malformed_error_body_falls_back_to_raw_text() {
+        assert_eq!(get_error_message(r#"{"Error": "Resource not found"}"#.to_string()), "Resource not found");
+        for body in ["Bad gateway", "", r#"{"Error": "truncated"#, r#"{"Error": 404}"#, r#"{"message": "other field"}"#, "[]"] {
+            assert_eq!(get_error_message(body.to_string()), body);
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_error_bodies_keep_raw_text() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/series/1" => TestResponse::new(500, "<html>Internal error</html>"),
+            "/search/series?name=Show" => TestResponse::new(400, r#"{"Error": null}"#),
+            _ => TestResponse::new(401, r#"{"Error": "Invalid credentials"}"#),
+        }).await;
+        let session = new_test_session(&server, "token");
+
+        let err = session.get_series(1, None).await.unwrap_err();
+        assert!(matches!(&err, ApiError::UnexpectedResponse(status, message)
+            if *status == reqwest::StatusCode::INTERNAL_SERVER_ERROR && message == "<html>Internal error</html>"), "{:?}", err);
+
+        let err = session.search_series(&"Show".to_string(), None).await.unwrap_err();
+        assert!(matches!(&err, ApiError::UnexpectedResponse(status, message)
+            if *status == reqwest::StatusCode::BAD_REQUEST && message == r#"{"Error": null}"#), "{:?}", err);
+
+        let login_info = LoginInfo { apikey: "key".to_string(), userkey: None, username: None };
+        let err = login(&new_test_client(), server.get_url(), &login_info).await.unwrap_err();
+        assert!(matches!(&err, ApiError::UnexpectedResponse(status, message)
+            if *status == reqwest::StatusCode::UNAUTHORIZED && message == "Invalid credentials"), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn malformed_success_body_is_decode_error() {
+        let server = TestServer::start(|_| TestResponse::new(200, r#"{"data": "#)).await;
+        let session = new_test_session(&server, "token");
+        let err = session.get_series(1, None).await.unwrap_err();
+        assert!(matches!(err, ApiError::JsonDecode(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn search_results_with_null_names_are_kept() {
+        let server = TestServer::start(|_| TestResponse::new(200, serde_json::json!({ "data": [
+            { "id": 1, "seriesName": "Show" },
+            { "id": 2, "seriesName": null },
+            { "id": 3 },
+        ]}).to_string())).await;
+        let session = new_test_session(&server, "token");
+        let series = session.search_series(&"Show".to_string(), None).await.unwrap();
+        let names: Vec<String> = series.iter().map(|series| series.get_display_name()).collect();
+        assert_eq!(names, vec!["Show", "(unnamed series #2)", "(unnamed series #3)"]);
+    }
+
+    fn new_login_info(userkey: Option<&str>, username: Option<&str>) -> LoginInfo {
+        LoginInfo {
+            apikey: "key".to_string(),
+            userkey: userkey.map(|userkey| userkey.to_string()),
+            username: username.map(|username| username.to_string()),
+        }
+    }
+
+    #[test]
+    fn login_info_shapes_round_trip() {
+        let shapes = [
+            (r#"{"apikey":"key","userkey":"user key","username":"user"}"#, Some("user key"), Some("user")),
+            (r#"{"apikey":"key","username":"user"}"#, None, Some("user")),
+            (r#"{"apikey":"key"}"#, None, None),
+        ];
+        for (data, userkey, username) in shapes {
+            let login_info: LoginInfo = serde_json::from_str(data).unwrap();
+            assert_eq!(login_info.apikey, "key");
+            assert_eq!(login_info.userkey.as_deref(), userkey, "{}", data);
+            assert_eq!(login_info.username.as_deref(), username, "{}", data);
+            assert_eq!(login_info.is_key_only(), userkey.is_none() && username.is_none(), "{}", data);
+            // Missing fields aren't written back as nulls
+            assert_eq!(serde_json::to_string(&login_info).unwrap(), data);
+        }
+
+        let login_info: LoginInfo = serde_json::from_str(r#"{"apikey":"key","userkey":null,"username":null}"#).unwrap();
+        assert!(login_info.is_key_only());
+        assert!(serde_json::from_str::<LoginInfo>(r#"{"userkey":"user key","username":"user"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn login_only_sends_provided_credentials() {
+        let server = TestServer::start(|request| {
+            let body: serde_json::Value = serde_json::from_str(request.body.as_str()).unwrap();
+            match body.get("apikey").and_then(|apikey| apikey.as_str()) {
+                Some("key") => new_token_response("token"),
+                _ => TestResponse::new(401, r#"{"Error": "API Key Required"}"#),
+            }
+        }).await;
+        let client = new_test_client();
+        let shapes = [
+            new_login_info(Some("user key"), Some("user")),
+            new_login_info(None, Some("user")),
+            new_login_info(None, None),
+        ];
+        for login_info in shapes.iter() {
+            let token = login(client.as_ref(), server.get_url(), login_info).await.unwrap();
+            assert_eq!(token.token, "token");
+        }
+
+        let bodies: Vec<serde_json::Value> = server.get_requests()
+            .iter()
+            .map(|request| serde_json::from_str(request.body.as_str()).unwrap())
+            .collect();
+        assert_eq!(bodies, vec![
+            serde_json::json!({ "apikey": "key", "userkey": "user key", "username": "user" }),
+            serde_json::json!({ "apikey": "key", "username": "user" }),
+            serde_json::json!({ "apikey": "key" }),
+        ]);
+        assert!(server.get_requests().iter().all(|request| request.method == "POST" && request.path == "/login"));
+    }
 }
@@ -1,2 +1,6 @@
 pub mod models;
 pub mod api;
+pub mod tmdb;
+pub mod tvmaze;
+#[cfg(any(test, feature = "test-server"))]
+pub mod test_server;
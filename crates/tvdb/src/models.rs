@@ -1,11 +1,51 @@
 use serde;
 use serde_with;
 
+// The service that ids refer to
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, Eq, PartialEq, Hash, Copy, Clone)]
+#[serde(rename_all="lowercase")]
+pub enum ProviderKind {
+    #[default]
+    Tvdb,
+    Tmdb,
+    TvMaze,
+}
+
+impl ProviderKind {
+    pub fn iterator() -> std::slice::Iter<'static, Self> {
+        static PROVIDERS: [ProviderKind;3] = [
+            ProviderKind::Tvdb,
+            ProviderKind::Tmdb,
+            ProviderKind::TvMaze,
+        ];
+        PROVIDERS.iter()
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            ProviderKind::Tvdb => "TVDB",
+            ProviderKind::Tmdb => "TMDB",
+            ProviderKind::TvMaze => "TVmaze",
+        }
+    }
+
+    // Whether credentials are needed before any requests can be made
+    pub fn is_login_required(&self) -> bool {
+        match self {
+            ProviderKind::Tvdb | ProviderKind::Tmdb => true,
+            ProviderKind::TvMaze => false,
+        }
+    }
+}
+
+#[serde_with::serde_as]
 #[serde_with::skip_serializing_none]
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct Series {
     pub id: u32,
-    #[serde(rename="seriesName")]
+    // Some obscure series don't have a name so it is left empty instead of failing the whole response
+    #[serde(rename="seriesName", default)]
+    #[serde_as(as="serde_with::DefaultOnNull")]
     pub name: String,
     #[serde(rename="firstAired")]
     pub first_aired: Option<String>,
@@ -37,16 +77,42 @@ pub struct Series {
     // misc
     #[serde(rename="lastUpdated")]
     pub last_updated: Option<u32>,
+    // caches saved before this was added are from tvdb
+    pub provider: Option<ProviderKind>,
+}
+
+impl Series {
+    // Used in place of the name wherever it is shown or used for renaming
+    pub fn get_display_name(&self) -> String {
+        match self.name.is_empty() {
+            true => format!("(unnamed series #{})", self.id),
+            false => self.name.clone(),
+        }
+    }
 }
 
+#[serde_with::serde_as]
 #[serde_with::skip_serializing_none]
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct Episode {
     pub id: u32,
-    #[serde(rename="airedSeason")]
+    // Missing numbers are left as 0, which isn't a valid episode number, i.e. for some specials
+    #[serde(rename="airedSeason", default)]
+    #[serde_as(as="serde_with::DefaultOnNull")]
     pub season: u32,
-    #[serde(rename="airedEpisodeNumber")]
+    #[serde(rename="airedEpisodeNumber", default)]
+    #[serde_as(as="serde_with::DefaultOnNull")]
     pub episode: u32,
+    // some series are numbered by their dvd release instead
+    #[serde(rename="dvdSeason")]
+    pub dvd_season: Option<u32>,
+    #[serde(rename="dvdEpisodeNumber")]
+    pub dvd_episode: Option<f32>,
+    // anime is usually released with a single number counting from the first episode
+    #[serde(rename="absoluteNumber")]
+    pub absolute_number: Option<u32>,
+    // length in minutes
+    pub runtime: Option<u32>,
     #[serde(rename="firstAired")]
     pub first_aired: Option<String>,
     #[serde(rename="episodeName")]
@@ -72,3 +138,211 @@ pub struct Episode {
     pub season_id: Option<u32>,
 }
 
+
+// Counts of the episodes that have aired without fetching them
+// The api sends numbers as strings, i.e. {"airedSeasons": ["0", "1"], "airedEpisodes": "62"}
+#[serde_with::serde_as]
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpisodesSummary {
+    #[serde(rename="airedSeasons", default)]
+    #[serde_as(as="Vec<serde_with::PickFirst<(_, serde_with::DisplayFromStr)>>")]
+    pub aired_seasons: Vec<u32>,
+    #[serde(rename="airedEpisodes")]
+    #[serde_as(as="serde_with::PickFirst<(_, serde_with::DisplayFromStr)>")]
+    pub aired_episodes: u32,
+    #[serde(rename="dvdSeasons", default)]
+    #[serde_as(as="Vec<serde_with::PickFirst<(_, serde_with::DisplayFromStr)>>")]
+    pub dvd_seasons: Vec<u32>,
+    #[serde(rename="dvdEpisodes", default)]
+    #[serde_as(as="serde_with::PickFirst<(_, serde_with::DisplayFromStr)>")]
+    pub dvd_episodes: u32,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct Actor {
+    pub name: String,
+    pub role: Option<String>,
+    // lower values are billed first
+    #[serde(rename="sortOrder")]
+    pub sort_order: Option<u32>,
+    // link to image
+    pub image: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Captured from /series/78874/actors with the ids shortened
+    const ACTORS_PAYLOAD: &str = r#"{
+        "data": [
+            {
+                "id": 27962,
+                "seriesId": 78874,
+                "name": "Nathan Fillion",
+                "role": "Malcolm Reynolds",
+                "sortOrder": 0,
+                "image": "actors/27962.jpg",
+                "imageAuthor": 1,
+                "imageAdded": "0000-00-00 00:00:00",
+                "lastUpdated": "2011-12-18 14:41:23"
+            },
+            {
+                "id": 27963,
+                "seriesId": 78874,
+                "name": "Summer Glau",
+                "role": "River Tam",
+                "sortOrder": 3,
+                "image": "",
+                "imageAuthor": 0,
+                "imageAdded": null,
+                "lastUpdated": "2011-12-18 14:41:23"
+            },
+            {
+                "id": 27964,
+                "seriesId": 78874,
+                "name": "Ron Glass",
+                "role": null,
+                "sortOrder": null,
+                "image": null,
+                "imageAuthor": null,
+                "imageAdded": null,
+                "lastUpdated": null
+            }
+        ]
+    }"#;
+
+    #[derive(serde::Deserialize)]
+    struct ActorsBody {
+        data: Vec<Actor>,
+    }
+
+    #[test]
+    fn actors_payload_is_decoded() {
+        let actors = serde_json::from_str::<ActorsBody>(ACTORS_PAYLOAD).unwrap().data;
+        assert_eq!(actors.len(), 3);
+        assert_eq!(actors[0].name, "Nathan Fillion");
+        assert_eq!(actors[0].role.as_deref(), Some("Malcolm Reynolds"));
+        assert_eq!(actors[0].sort_order, Some(0));
+        assert_eq!(actors[0].image.as_deref(), Some("actors/27962.jpg"));
+        assert_eq!(actors[1].sort_order, Some(3));
+        // Missing fields are null
+        assert_eq!(actors[2].role, None);
+        assert_eq!(actors[2].sort_order, None);
+        assert_eq!(actors[2].image, None);
+    }
+
+    #[test]
+    fn actors_round_trip_without_null_fields() {
+        let actors = serde_json::from_str::<ActorsBody>(ACTORS_PAYLOAD).unwrap().data;
+        let data = serde_json::to_string(&actors).unwrap();
+        assert!(!data.contains("null"));
+        let decoded: Vec<Actor> = serde_json::from_str(data.as_str()).unwrap();
+        let names: Vec<&str> = decoded.iter().map(|actor| actor.name.as_str()).collect();
+        assert_eq!(names, vec!["Nathan Fillion", "Summer Glau", "Ron Glass"]);
+        assert_eq!(decoded[1].role.as_deref(), Some("River Tam"));
+        assert_eq!(decoded[2].role, None);
+    }
+
+    #[test]
+    fn episodes_summary_accepts_strings_and_numbers() {
+        let data = r#"{"airedSeasons": ["0", "1", "2"], "airedEpisodes": "62", "dvdSeasons": [1, 2], "dvdEpisodes": 60}"#;
+        let summary: EpisodesSummary = serde_json::from_str(data).unwrap();
+        assert_eq!(summary, EpisodesSummary {
+            aired_seasons: vec![0, 1, 2],
+            aired_episodes: 62,
+            dvd_seasons: vec![1, 2],
+            dvd_episodes: 60,
+        });
+    }
+
+    #[test]
+    fn episodes_summary_defaults_missing_dvd_counts() {
+        let summary: EpisodesSummary = serde_json::from_str(r#"{"airedSeasons": ["1"], "airedEpisodes": "14"}"#).unwrap();
+        assert_eq!(summary.aired_episodes, 14);
+        assert!(summary.dvd_seasons.is_empty());
+        assert_eq!(summary.dvd_episodes, 0);
+        // Aired episodes are required
+        assert!(serde_json::from_str::<EpisodesSummary>(r#"{"airedSeasons": ["1"]}"#).is_err());
+    }
+
+    #[test]
+    fn episodes_summary_round_trips() {
+        let summary = EpisodesSummary { aired_seasons: vec![1], aired_episodes: 14, dvd_seasons: vec![1], dvd_episodes: 15 };
+        let data = serde_json::to_string(&summary).unwrap();
+        assert_eq!(serde_json::from_str::<EpisodesSummary>(data.as_str()).unwrap(), summary);
+    }
+
+    // Captured from /search/series?name=firefly with fields that aren't used removed
+    const SPARSE_SEARCH_PAYLOAD: &str = r#"{
+        "data": [
+            { "id": 78874, "seriesName": "Firefly", "firstAired": "2002-09-20", "status": "Ended" },
+            { "id": 412310, "seriesName": null, "firstAired": null, "status": null },
+            { "id": 412311, "aliases": [] }
+        ]
+    }"#;
+
+    // Captured from /series/78874/episodes with specials that weren't numbered
+    const SPARSE_EPISODES_PAYLOAD: &str = r#"{
+        "data": [
+            { "id": 297989, "airedSeason": 1, "airedEpisodeNumber": 1, "episodeName": "Serenity", "seriesId": 78874 },
+            { "id": 3054311, "airedSeason": null, "airedEpisodeNumber": null, "episodeName": null, "seriesId": 78874 },
+            { "id": 3054312, "episodeName": "Here's How It Was", "seriesId": 78874 }
+        ]
+    }"#;
+
+    #[derive(serde::Deserialize)]
+    struct SeriesBody {
+        data: Vec<Series>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct EpisodesBody {
+        data: Vec<Episode>,
+    }
+
+    #[test]
+    fn series_with_null_name_is_decoded() {
+        let series = serde_json::from_str::<SeriesBody>(SPARSE_SEARCH_PAYLOAD).unwrap().data;
+        assert_eq!(series.len(), 3);
+        assert_eq!(series[0].get_display_name(), "Firefly");
+        assert_eq!(series[1].name, "");
+        assert_eq!(series[1].first_aired, None);
+        assert_eq!(series[1].get_display_name(), "(unnamed series #412310)");
+        assert_eq!(series[2].get_display_name(), "(unnamed series #412311)");
+    }
+
+    #[test]
+    fn episodes_with_null_numbers_are_decoded() {
+        let episodes = serde_json::from_str::<EpisodesBody>(SPARSE_EPISODES_PAYLOAD).unwrap().data;
+        assert_eq!(episodes.len(), 3);
+        assert_eq!((episodes[0].season, episodes[0].episode), (1, 1));
+        assert_eq!((episodes[1].season, episodes[1].episode), (0, 0));
+        assert_eq!(episodes[1].name, None);
+        assert_eq!((episodes[2].season, episodes[2].episode), (0, 0));
+        assert_eq!(episodes[2].name.as_deref(), Some("Here's How It Was"));
+    }
+
+    #[test]
+    fn absolute_number_and_runtime_are_decoded() {
+        let data = r#"{"id": 1, "airedSeason": 2, "airedEpisodeNumber": 1, "absoluteNumber": 27, "runtime": 24, "seriesId": 1}"#;
+        let episode: Episode = serde_json::from_str(data).unwrap();
+        assert_eq!(episode.absolute_number, Some(27));
+        assert_eq!(episode.runtime, Some(24));
+
+        let data = r#"{"id": 2, "airedSeason": 2, "airedEpisodeNumber": 2, "absoluteNumber": null, "runtime": null}"#;
+        let episode: Episode = serde_json::from_str(data).unwrap();
+        assert_eq!((episode.absolute_number, episode.runtime), (None, None));
+    }
+
+    #[test]
+    fn episodes_saved_without_absolute_number_are_decoded() {
+        // Saved by versions that didn't have the fields
+        let data = r#"[{"id": 1, "airedSeason": 1, "airedEpisodeNumber": 1, "episodeName": "Pilot"}]"#;
+        let episodes: Vec<Episode> = serde_json::from_str(data).unwrap();
+        assert_eq!((episodes[0].absolute_number, episodes[0].runtime), (None, None));
+        let data = serde_json::to_string(&episodes).unwrap();
+        assert!(!data.contains("absoluteNumber") && !data.contains("runtime"));
+    }
+}
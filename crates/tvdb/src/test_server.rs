@@ -0,0 +1,157 @@
+// Local http server which answers requests with canned responses so sessions can be tested offline
+use reqwest;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone)]
+pub struct TestRequest {
+    pub method: String,
+    // Includes the query, i.e. "/series/1/episodes?page=2"
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl TestRequest {
+    // Header names are case insensitive
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_bearer_token(&self) -> Option<&str> {
+        self.get_header("Authorization")?.strip_prefix("Bearer ")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl TestResponse {
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+type Handler = dyn Fn(&TestRequest) -> TestResponse + Send + Sync;
+
+// Each connection is closed after its response so requests are handled in the order they are sent
+pub struct TestServer {
+    url: String,
+    requests: Arc<Mutex<Vec<TestRequest>>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl TestServer {
+    pub async fn start<F>(handler: F) -> Self
+    where F: Fn(&TestRequest) -> TestResponse + Send + Sync + 'static
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+        let task = tokio::spawn({
+            let requests = requests.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle_connection(stream, handler.clone(), requests.clone()));
+                }
+            }
+        });
+        Self { url, requests, task }
+    }
+
+    pub fn get_url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    pub fn get_requests(&self) -> Vec<TestRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    // Number of requests sent to the path without its query
+    pub fn get_total_requests(&self, path: &str) -> usize {
+        self.get_requests()
+            .iter()
+            .filter(|request| request.path.split('?').next() == Some(path))
+            .count()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// Proxies from the environment would otherwise intercept requests to the local server
+pub fn new_test_client() -> Arc<reqwest::Client> {
+    Arc::new(reqwest::Client::builder().no_proxy().build().unwrap())
+}
+
+async fn read_request(reader: &mut BufReader<TcpStream>) -> std::io::Result<TestRequest> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = TestRequest { method, path, headers, body: String::new() };
+    let content_length: usize = request.get_header("Content-Length").and_then(|value| value.parse().ok()).unwrap_or(0);
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(body.as_mut_slice()).await?;
+    request.body = String::from_utf8_lossy(body.as_slice()).to_string();
+    Ok(request)
+}
+
+async fn handle_connection(stream: TcpStream, handler: Arc<Handler>, requests: Arc<Mutex<Vec<TestRequest>>>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let request = read_request(&mut reader).await?;
+    let response = handler(&request);
+    requests.lock().unwrap().push(request);
+
+    let reason = reqwest::StatusCode::from_u16(response.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown");
+    let mut data = format!("HTTP/1.1 {} {}\r\n", response.status, reason);
+    data.push_str(format!("Content-Length: {}\r\nConnection: close\r\n", response.body.len()).as_str());
+    for (name, value) in response.headers.iter() {
+        data.push_str(format!("{}: {}\r\n", name, value).as_str());
+    }
+    data.push_str("\r\n");
+    data.push_str(response.body.as_str());
+
+    let mut stream = reader.into_inner();
+    stream.write_all(data.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,465 @@
+use chrono;
+use futures::stream::{self, StreamExt};
+use reqwest;
+use serde;
+use serde_json;
+use std::sync::Arc;
+use url;
+use crate::api::{ApiError, RateLimitPause, get_retry_after};
+use crate::models::{Actor, Episode, EpisodesSummary, ProviderKind, Series};
+
+const BASE_URL: &str = "https://api.themoviedb.org/3";
+const IMAGE_URL: &str = "https://image.tmdb.org/t/p/original";
+// The changes endpoint only allows two weeks between the start and end date
+const MAX_CHANGES_QUERY_SECONDS: u64 = 14*24*60*60;
+const MAX_CONCURRENT_SEASONS: usize = 4;
+
+#[derive(serde::Deserialize)]
+struct ErrorBody {
+    status_message: String,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TmdbPage<T> {
+    results: Vec<T>,
+    total_pages: Option<u32>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TmdbNamed {
+    id: Option<u32>,
+    name: String,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TmdbExternalIds {
+    imdb_id: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TmdbSeasonSummary {
+    season_number: u32,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct TmdbSeries {
+    id: u32,
+    name: String,
+    first_air_date: Option<String>,
+    status: Option<String>,
+    overview: Option<String>,
+    genres: Option<Vec<TmdbNamed>>,
+    vote_average: Option<f32>,
+    original_language: Option<String>,
+    external_ids: Option<TmdbExternalIds>,
+    poster_path: Option<String>,
+    backdrop_path: Option<String>,
+    networks: Option<Vec<TmdbNamed>>,
+    episode_run_time: Option<Vec<u32>>,
+    seasons: Option<Vec<TmdbSeasonSummary>>,
+    number_of_episodes: Option<u32>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TmdbCrew {
+    name: String,
+    job: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TmdbCast {
+    name: String,
+    character: Option<String>,
+    order: Option<u32>,
+    profile_path: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct TmdbEpisode {
+    id: u32,
+    season_number: u32,
+    episode_number: u32,
+    air_date: Option<String>,
+    name: Option<String>,
+    overview: Option<String>,
+    crew: Option<Vec<TmdbCrew>>,
+    guest_stars: Option<Vec<TmdbCast>>,
+    still_path: Option<String>,
+    runtime: Option<u32>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TmdbSeason {
+    episodes: Vec<TmdbEpisode>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TmdbCredits {
+    cast: Vec<TmdbCast>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TmdbChange {
+    id: u32,
+}
+
+// Tmdb sends empty strings instead of leaving out unknown values
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.is_empty())
+}
+
+fn get_crew_names(crew: Option<&Vec<TmdbCrew>>, job: &str) -> Option<Vec<String>> {
+    let crew = crew?;
+    Some(crew.iter().filter(|member| member.job.as_deref() == Some(job)).map(|member| member.name.clone()).collect())
+}
+
+pub fn map_series(series: TmdbSeries) -> Series {
+    let network = series.networks.as_ref().and_then(|networks| networks.first());
+    Series {
+        id: series.id,
+        name: series.name,
+        first_aired: non_empty(series.first_air_date),
+        status: non_empty(series.status),
+        overview: non_empty(series.overview),
+        genre: series.genres.map(|genres| genres.into_iter().map(|genre| genre.name).collect()),
+        aliases: None,
+        rating: series.vote_average.map(|rating| rating.to_string()),
+        slug: None,
+        language: non_empty(series.original_language),
+        imdb_id: non_empty(series.external_ids.and_then(|ids| ids.imdb_id)),
+        zap2_it_id: None,
+        poster: non_empty(series.poster_path),
+        banner: None,
+        fanart: non_empty(series.backdrop_path),
+        network: network.map(|network| network.name.clone()),
+        network_id: network.and_then(|network| network.id).map(|id| id.to_string()),
+        runtime: series.episode_run_time.and_then(|times| times.first().map(|time| time.to_string())),
+        airs_day_of_week: None,
+        airs_time: None,
+        last_updated: None,
+        provider: Some(ProviderKind::Tmdb),
+    }
+}
+
+pub fn map_episode(episode: TmdbEpisode, series_id: u32) -> Episode {
+    Episode {
+        id: episode.id,
+        season: episode.season_number,
+        episode: episode.episode_number,
+        dvd_season: None,
+        dvd_episode: None,
+        absolute_number: None,
+        runtime: episode.runtime,
+        first_aired: non_empty(episode.air_date),
+        name: non_empty(episode.name),
+        overview: non_empty(episode.overview),
+        writers: get_crew_names(episode.crew.as_ref(), "Writer"),
+        directors: get_crew_names(episode.crew.as_ref(), "Director"),
+        guest_stars: episode.guest_stars.map(|stars| stars.into_iter().map(|star| star.name).collect()),
+        rating: None,
+        imdb_id: None,
+        image_filename: non_empty(episode.still_path),
+        series_id: Some(series_id),
+        season_id: None,
+    }
+}
+
+fn map_actor(cast: TmdbCast) -> Actor {
+    Actor {
+        name: cast.name,
+        role: non_empty(cast.character),
+        sort_order: cast.order,
+        image: non_empty(cast.profile_path),
+    }
+}
+
+fn get_date_string(unix_time: u64) -> String {
+    let time = chrono::DateTime::from_timestamp(unix_time as i64, 0).unwrap_or_default();
+    time.format("%Y-%m-%d").to_string()
+}
+
+// Tmdb only needs an api key so there is no login token to refresh
+pub struct TmdbSession {
+    client: Arc<reqwest::Client>,
+    api_key: String,
+    // Only changed to send requests to a local server in tests
+    base_url: String,
+    rate_limit_pause: RateLimitPause,
+}
+
+impl TmdbSession {
+    pub fn new(client: Arc<reqwest::Client>, api_key: &str) -> Self {
+        Self::with_base_url(client, api_key, BASE_URL)
+    }
+
+    pub fn with_base_url(client: Arc<reqwest::Client>, api_key: &str, base_url: &str) -> Self {
+        Self {
+            client,
+            api_key: api_key.to_string(),
+            base_url: base_url.to_string(),
+            rate_limit_pause: RateLimitPause::default(),
+        }
+    }
+
+    pub fn get_rate_limit_delay(&self) -> Option<std::time::Duration> {
+        self.rate_limit_pause.get_remaining()
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str, params: &[(&str, &str)]) -> Result<T, ApiError> {
+        let base_url = format!("{}/{}", self.base_url, path);
+        let params = params.iter().copied().chain([("api_key", self.api_key.as_str())]);
+        let full_url = url::Url::parse_with_params(base_url.as_str(), params).expect("Url is valid");
+        if let Some(delay) = self.rate_limit_pause.get_remaining() {
+            tokio::time::sleep(delay).await;
+        }
+        let res = self.client.get(full_url).send().await.map_err(ApiError::RequestFailure)?;
+        let status = res.status();
+        let retry_after = get_retry_after(&res);
+        let body = res.text().await.map_err(ApiError::RequestFailure)?;
+        if !status.is_success() {
+            let message = match serde_json::from_str::<ErrorBody>(body.as_str()) {
+                Ok(error) => error.status_message,
+                Err(_) => body,
+            };
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                if let Some(delay) = retry_after {
+                    self.rate_limit_pause.pause(delay);
+                }
+                return Err(ApiError::RateLimited { retry_after, message });
+            }
+            return Err(ApiError::UnexpectedResponse(status, message));
+        }
+        serde_json::from_str(body.as_str()).map_err(ApiError::JsonDecode)
+    }
+
+    // Used to check that the api key is valid
+    pub async fn get_configuration(&self) -> Result<(), ApiError> {
+        self.get_json::<serde_json::Value>("configuration", &[]).await?;
+        Ok(())
+    }
+
+    pub async fn search_series(&self, name: &str, language: Option<&str>) -> Result<Vec<Series>, ApiError> {
+        let mut params = vec![("query", name)];
+        params.extend(language.map(|language| ("language", language)));
+        let page: TmdbPage<TmdbSeries> = self.get_json("search/tv", params.as_slice()).await?;
+        Ok(page.results.into_iter().map(map_series).collect())
+    }
+
+    async fn get_tmdb_series(&self, id: u32, language: Option<&str>) -> Result<TmdbSeries, ApiError> {
+        let mut params = vec![("append_to_response", "external_ids")];
+        params.extend(language.map(|language| ("language", language)));
+        self.get_json(format!("tv/{}", id).as_str(), params.as_slice()).await
+    }
+
+    pub async fn get_series(&self, id: u32, language: Option<&str>) -> Result<Series, ApiError> {
+        Ok(map_series(self.get_tmdb_series(id, language).await?))
+    }
+
+    pub async fn get_season_episodes(&self, id: u32, season: u32, language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        let params: Vec<(&str, &str)> = language.map(|language| ("language", language)).into_iter().collect();
+        let tmdb_season: Result<TmdbSeason, ApiError> = self.get_json(format!("tv/{}/season/{}", id, season).as_str(), params.as_slice()).await;
+        match tmdb_season {
+            Ok(tmdb_season) => Ok(tmdb_season.episodes.into_iter().map(|episode| map_episode(episode, id)).collect()),
+            // Seasons that don't exist have no episodes
+            Err(ApiError::UnexpectedResponse(status, _)) if status == reqwest::StatusCode::NOT_FOUND => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Episodes are fetched a season at a time instead of as a single paged list
+    pub async fn get_episodes(&self, id: u32, language: Option<&str>) -> Result<Vec<Episode>, ApiError> {
+        let series = self.get_tmdb_series(id, None).await?;
+        let seasons: Vec<u32> = series.seasons.unwrap_or_default().iter().map(|season| season.season_number).collect();
+        let seasons: Vec<Result<Vec<Episode>, ApiError>> = stream::iter(seasons)
+            .map(|season| self.get_season_episodes(id, season, language))
+            .buffered(MAX_CONCURRENT_SEASONS)
+            .collect()
+            .await;
+        let mut episodes = Vec::new();
+        for season in seasons {
+            episodes.extend(season?);
+        }
+        Ok(episodes)
+    }
+
+    // Tmdb doesn't have dvd counts
+    pub async fn get_episodes_summary(&self, id: u32) -> Result<EpisodesSummary, ApiError> {
+        let series = self.get_tmdb_series(id, None).await?;
+        Ok(EpisodesSummary {
+            aired_seasons: series.seasons.unwrap_or_default().iter().map(|season| season.season_number).collect(),
+            aired_episodes: series.number_of_episodes.unwrap_or(0),
+            ..EpisodesSummary::default()
+        })
+    }
+
+    pub async fn get_actors(&self, id: u32) -> Result<Vec<Actor>, ApiError> {
+        let credits: TmdbCredits = self.get_json(format!("tv/{}/credits", id).as_str(), &[]).await?;
+        Ok(credits.cast.into_iter().map(map_actor).collect())
+    }
+
+    // Series which were changed since the unix timestamp in seconds
+    pub async fn get_updated_series(&self, from_time: u64) -> Result<Vec<u32>, ApiError> {
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        let mut series_ids = Vec::new();
+        let mut start_time = from_time;
+        while start_time < now {
+            let end_time = start_time.saturating_add(MAX_CHANGES_QUERY_SECONDS).min(now);
+            let start_date = get_date_string(start_time);
+            let end_date = get_date_string(end_time);
+            let mut page: u32 = 1;
+            loop {
+                let page_str = page.to_string();
+                let params = [("start_date", start_date.as_str()), ("end_date", end_date.as_str()), ("page", page_str.as_str())];
+                let changes: TmdbPage<TmdbChange> = self.get_json("tv/changes", &params).await?;
+                series_ids.extend(changes.results.iter().map(|change| change.id));
+                if page >= changes.total_pages.unwrap_or(1) {
+                    break;
+                }
+                page += 1;
+            }
+            start_time = end_time;
+        }
+        series_ids.sort_unstable();
+        series_ids.dedup();
+        Ok(series_ids)
+    }
+
+    // Artwork paths start with a slash, i.e. "/abc.jpg"
+    pub async fn download_image(&self, relative_path: &str) -> Result<Vec<u8>, ApiError> {
+        let url = format!("{}/{}", IMAGE_URL, relative_path.trim_start_matches('/'));
+        let res = self.client.get(url).send().await.map_err(ApiError::RequestFailure)?;
+        let status = res.status();
+        if !status.is_success() {
+            return Err(ApiError::UnexpectedResponse(status, status.to_string()));
+        }
+        let bytes = res.bytes().await.map_err(ApiError::RequestFailure)?;
+        Ok(bytes.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{TestRequest, TestResponse, TestServer, new_test_client};
+
+    fn new_series_payload() -> serde_json::Value {
+        serde_json::json!({
+            "id": 1399,
+            "name": "Game of Thrones",
+            "first_air_date": "2011-04-17",
+            "status": "Ended",
+            "overview": "",
+            "genres": [{ "id": 10765, "name": "Sci-Fi & Fantasy" }, { "id": 18, "name": "Drama" }],
+            "vote_average": 8.4,
+            "original_language": "en",
+            "external_ids": { "imdb_id": "tt0944947", "tvdb_id": 121361 },
+            "poster_path": "/1XS1oqL89opfnbLl8WnZY1O1uJx.jpg",
+            "backdrop_path": null,
+            "networks": [{ "id": 49, "name": "HBO" }, { "id": 50, "name": "Other" }],
+            "episode_run_time": [60, 55],
+            "seasons": [{ "season_number": 0 }, { "season_number": 1 }, { "season_number": 2 }],
+            "number_of_episodes": 3
+        })
+    }
+
+    fn new_episode_payload(id: u32, season: u32, episode: u32, name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "season_number": season,
+            "episode_number": episode,
+            "air_date": "2011-04-17",
+            "name": name,
+            "overview": "",
+            "crew": [
+                { "name": "David Benioff", "job": "Writer" },
+                { "name": "Tim Van Patten", "job": "Director" },
+                { "name": "Someone", "job": "Editor" }
+            ],
+            "guest_stars": [{ "name": "Sean Bean", "character": "Eddard Stark", "order": 0, "profile_path": null }],
+            "still_path": "/still.jpg",
+            "runtime": 62
+        })
+    }
+
+    #[test]
+    fn series_fields_are_mapped() {
+        let series: TmdbSeries = serde_json::from_value(new_series_payload()).unwrap();
+        let series = map_series(series);
+        assert_eq!(series.id, 1399);
+        assert_eq!(series.name, "Game of Thrones");
+        assert_eq!(series.first_aired.as_deref(), Some("2011-04-17"));
+        assert_eq!(series.status.as_deref(), Some("Ended"));
+        // Empty values are treated as missing
+        assert_eq!(series.overview, None);
+        assert_eq!(series.fanart, None);
+        assert_eq!(series.genre, Some(vec!["Sci-Fi & Fantasy".to_string(), "Drama".to_string()]));
+        assert_eq!(series.rating.as_deref(), Some("8.4"));
+        assert_eq!(series.imdb_id.as_deref(), Some("tt0944947"));
+        assert_eq!(series.poster.as_deref(), Some("/1XS1oqL89opfnbLl8WnZY1O1uJx.jpg"));
+        assert_eq!(series.network.as_deref(), Some("HBO"));
+        assert_eq!(series.network_id.as_deref(), Some("49"));
+        assert_eq!(series.runtime.as_deref(), Some("60"));
+        assert_eq!(series.provider, Some(ProviderKind::Tmdb));
+    }
+
+    #[test]
+    fn episode_fields_are_mapped() {
+        let episode: TmdbEpisode = serde_json::from_value(new_episode_payload(63056, 1, 2, "The Kingsroad")).unwrap();
+        let episode = map_episode(episode, 1399);
+        assert_eq!((episode.id, episode.season, episode.episode), (63056, 1, 2));
+        assert_eq!(episode.name.as_deref(), Some("The Kingsroad"));
+        assert_eq!(episode.first_aired.as_deref(), Some("2011-04-17"));
+        assert_eq!(episode.overview, None);
+        assert_eq!(episode.writers, Some(vec!["David Benioff".to_string()]));
+        assert_eq!(episode.directors, Some(vec!["Tim Van Patten".to_string()]));
+        assert_eq!(episode.guest_stars, Some(vec!["Sean Bean".to_string()]));
+        assert_eq!(episode.image_filename.as_deref(), Some("/still.jpg"));
+        assert_eq!(episode.runtime, Some(62));
+        assert_eq!(episode.series_id, Some(1399));
+        assert_eq!(episode.dvd_season, None);
+    }
+
+    fn get_path(request: &TestRequest) -> &str {
+        request.path.split('?').next().unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn episodes_are_fetched_per_season() {
+        let server = TestServer::start(|request| match get_path(request) {
+            "/tv/1399" => TestResponse::new(200, new_series_payload().to_string()),
+            // Specials aren't always listed
+            "/tv/1399/season/0" => TestResponse::new(404, r#"{"status_message": "The resource you requested could not be found."}"#),
+            "/tv/1399/season/1" => {
+                let episodes = [new_episode_payload(1, 1, 1, "Winter Is Coming"), new_episode_payload(2, 1, 2, "The Kingsroad")];
+                TestResponse::new(200, serde_json::json!({ "episodes": episodes }).to_string())
+            },
+            "/tv/1399/season/2" => {
+                let episodes = [new_episode_payload(3, 2, 1, "The North Remembers")];
+                TestResponse::new(200, serde_json::json!({ "episodes": episodes }).to_string())
+            },
+            _ => TestResponse::new(404, "{}"),
+        }).await;
+        let session = TmdbSession::with_base_url(new_test_client(), "key", server.get_url());
+
+        let episodes = session.get_episodes(1399, Some("en")).await.unwrap();
+        let keys: Vec<(u32, u32, u32)> = episodes.iter().map(|episode| (episode.id, episode.season, episode.episode)).collect();
+        assert_eq!(keys, vec![(1, 1, 1), (2, 1, 2), (3, 2, 1)]);
+        assert!(episodes.iter().all(|episode| episode.series_id == Some(1399)));
+
+        // Each season is a single request instead of a paged list
+        assert_eq!(server.get_requests().len(), 4);
+        for request in server.get_requests() {
+            assert!(request.path.contains("api_key=key"), "{}", request.path);
+            assert!(!request.path.contains("page="), "{}", request.path);
+        }
+        assert!(server.get_requests().iter().any(|request| request.path.starts_with("/tv/1399/season/1?language=en&")));
+    }
+
+    #[tokio::test]
+    async fn error_message_is_taken_from_body() {
+        let server = TestServer::start(|_| TestResponse::new(401, r#"{"status_code": 7, "status_message": "Invalid API key"}"#)).await;
+        let session = TmdbSession::with_base_url(new_test_client(), "bad", server.get_url());
+        let err = session.get_configuration().await.unwrap_err();
+        assert!(matches!(err, ApiError::UnexpectedResponse(_, ref message) if message == "Invalid API key"), "{}", err);
+    }
+}
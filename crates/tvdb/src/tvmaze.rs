@@ -0,0 +1,447 @@
+use chrono;
+use reqwest;
+use serde;
+use serde_json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use url;
+use crate::api::{ApiError, RateLimitPause, get_retry_after};
+use crate::models::{Actor, Episode, EpisodesSummary, ProviderKind, Series};
+
+const BASE_URL: &str = "https://api.tvmaze.com";
+// Images are given as full urls so only the path after this is stored
+const IMAGE_URL: &str = "https://static.tvmaze.com";
+const SECONDS_IN_DAY: u64 = 24*60*60;
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TvMazeImage {
+    original: Option<String>,
+    medium: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TvMazeNetwork {
+    id: Option<u32>,
+    name: String,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TvMazeRating {
+    average: Option<f32>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TvMazeExternals {
+    imdb: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TvMazeSchedule {
+    time: Option<String>,
+    days: Option<Vec<String>>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct TvMazeShow {
+    id: u32,
+    name: String,
+    language: Option<String>,
+    genres: Option<Vec<String>>,
+    status: Option<String>,
+    runtime: Option<u32>,
+    #[serde(rename="averageRuntime")]
+    average_runtime: Option<u32>,
+    premiered: Option<String>,
+    schedule: Option<TvMazeSchedule>,
+    rating: Option<TvMazeRating>,
+    network: Option<TvMazeNetwork>,
+    #[serde(rename="webChannel")]
+    web_channel: Option<TvMazeNetwork>,
+    externals: Option<TvMazeExternals>,
+    image: Option<TvMazeImage>,
+    summary: Option<String>,
+    updated: Option<u32>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct TvMazeEpisode {
+    id: u32,
+    name: Option<String>,
+    season: u32,
+    // Specials don't have an episode number
+    number: Option<u32>,
+    airdate: Option<String>,
+    runtime: Option<u32>,
+    rating: Option<TvMazeRating>,
+    image: Option<TvMazeImage>,
+    summary: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TvMazeSearchResult {
+    show: TvMazeShow,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TvMazeCharacter {
+    name: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TvMazePerson {
+    name: String,
+    image: Option<TvMazeImage>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct TvMazeCastMember {
+    person: TvMazePerson,
+    character: Option<TvMazeCharacter>,
+}
+
+fn decode_html_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse::<u32>().ok()?,
+            };
+            char::from_u32(code)
+        },
+    }
+}
+
+// Summaries are html, i.e. "<p><b>Show</b> is about...</p>"
+// Paragraphs and line breaks become newlines and entities are decoded
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut chars = html.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '<' => {
+                let is_tag = html[index+1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!');
+                let end = match html[index..].find('>') {
+                    Some(end) if is_tag => index+end,
+                    // A lone or unclosed bracket is kept as text
+                    _ => {
+                        text.push(c);
+                        continue;
+                    },
+                };
+                let tag = html[index+1..end].trim().trim_end_matches('/').trim().to_lowercase();
+                let tag_name = tag.split_whitespace().next().unwrap_or("");
+                if matches!(tag_name, "br" | "/p" | "/div" | "/li") {
+                    text.push('\n');
+                }
+                while chars.next_if(|(i, _)| *i <= end).is_some() {}
+            },
+            '&' => {
+                let entity = html[index+1..].find(';')
+                    .filter(|length| *length <= 8)
+                    .and_then(|length| decode_html_entity(&html[index+1..index+1+length]).map(|c| (c, length)));
+                match entity {
+                    Some((decoded, length)) => {
+                        text.push(decoded);
+                        while chars.next_if(|(i, _)| *i <= index+1+length).is_some() {}
+                    },
+                    None => text.push(c),
+                }
+            },
+            _ => text.push(c),
+        }
+    }
+    // Collapse the blank lines left behind by consecutive tags
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+fn get_image_path(image: Option<TvMazeImage>) -> Option<String> {
+    let image = image?;
+    let url = image.original.or(image.medium)?;
+    url.strip_prefix(IMAGE_URL).map(|path| path.to_string())
+}
+
+fn get_summary(summary: Option<String>) -> Option<String> {
+    summary.map(|summary| strip_html(summary.as_str())).filter(|summary| !summary.is_empty())
+}
+
+pub fn map_series(show: TvMazeShow) -> Series {
+    let network = show.network.or(show.web_channel);
+    let schedule = show.schedule;
+    Series {
+        id: show.id,
+        name: show.name,
+        first_aired: show.premiered,
+        status: show.status,
+        overview: get_summary(show.summary),
+        genre: show.genres.filter(|genres| !genres.is_empty()),
+        aliases: None,
+        rating: show.rating.and_then(|rating| rating.average).map(|rating| rating.to_string()),
+        slug: None,
+        language: show.language,
+        imdb_id: show.externals.and_then(|externals| externals.imdb),
+        zap2_it_id: None,
+        poster: get_image_path(show.image),
+        banner: None,
+        fanart: None,
+        network: network.as_ref().map(|network| network.name.clone()),
+        network_id: network.and_then(|network| network.id).map(|id| id.to_string()),
+        runtime: show.runtime.or(show.average_runtime).map(|runtime| runtime.to_string()),
+        airs_day_of_week: schedule.as_ref()
+            .and_then(|schedule| schedule.days.as_ref())
+            .filter(|days| !days.is_empty())
+            .map(|days| days.join(", ")),
+        airs_time: schedule.and_then(|schedule| schedule.time).filter(|time| !time.is_empty()),
+        last_updated: show.updated,
+        provider: Some(ProviderKind::TvMaze),
+    }
+}
+
+// Specials without an episode number can't be matched to files so they are left out
+pub fn map_episode(episode: TvMazeEpisode, series_id: u32) -> Option<Episode> {
+    Some(Episode {
+        id: episode.id,
+        season: episode.season,
+        episode: episode.number?,
+        dvd_season: None,
+        dvd_episode: None,
+        absolute_number: None,
+        runtime: episode.runtime,
+        first_aired: episode.airdate.filter(|airdate| !airdate.is_empty()),
+        name: episode.name,
+        overview: get_summary(episode.summary),
+        writers: None,
+        directors: None,
+        guest_stars: None,
+        rating: episode.rating.and_then(|rating| rating.average).map(|rating| rating.to_string()),
+        imdb_id: None,
+        image_filename: get_image_path(episode.image),
+        series_id: Some(series_id),
+        season_id: None,
+    })
+}
+
+fn map_actor(member: TvMazeCastMember, sort_order: u32) -> Actor {
+    Actor {
+        name: member.person.name,
+        role: member.character.and_then(|character| character.name),
+        sort_order: Some(sort_order),
+        image: get_image_path(member.person.image),
+    }
+}
+
+// The updates endpoint can only be filtered to the last day, week or month
+fn get_updates_period(seconds_since: u64) -> Option<&'static str> {
+    match seconds_since {
+        x if x < SECONDS_IN_DAY => Some("day"),
+        x if x < 7*SECONDS_IN_DAY => Some("week"),
+        x if x < 30*SECONDS_IN_DAY => Some("month"),
+        _ => None,
+    }
+}
+
+// TVmaze doesn't require an api key so there is nothing to login with
+pub struct TvMazeSession {
+    client: Arc<reqwest::Client>,
+    rate_limit_pause: RateLimitPause,
+}
+
+impl TvMazeSession {
+    pub fn new(client: Arc<reqwest::Client>) -> Self {
+        Self {
+            client,
+            rate_limit_pause: RateLimitPause::default(),
+        }
+    }
+
+    pub fn get_rate_limit_delay(&self) -> Option<std::time::Duration> {
+        self.rate_limit_pause.get_remaining()
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, path: &str, params: &[(&str, &str)]) -> Result<T, ApiError> {
+        let base_url = format!("{}/{}", BASE_URL, path);
+        let full_url = url::Url::parse_with_params(base_url.as_str(), params).expect("Url is valid");
+        if let Some(delay) = self.rate_limit_pause.get_remaining() {
+            tokio::time::sleep(delay).await;
+        }
+        let res = self.client.get(full_url).send().await.map_err(ApiError::RequestFailure)?;
+        let status = res.status();
+        let retry_after = get_retry_after(&res);
+        let body = res.text().await.map_err(ApiError::RequestFailure)?;
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            if let Some(delay) = retry_after {
+                self.rate_limit_pause.pause(delay);
+            }
+            return Err(ApiError::RateLimited { retry_after, message: body });
+        }
+        if !status.is_success() {
+            return Err(ApiError::UnexpectedResponse(status, body));
+        }
+        serde_json::from_str(body.as_str()).map_err(ApiError::JsonDecode)
+    }
+
+    pub async fn search_series(&self, name: &str) -> Result<Vec<Series>, ApiError> {
+        let results: Vec<TvMazeSearchResult> = self.get_json("search/shows", &[("q", name)]).await?;
+        Ok(results.into_iter().map(|result| map_series(result.show)).collect())
+    }
+
+    pub async fn get_series(&self, id: u32) -> Result<Series, ApiError> {
+        let show: TvMazeShow = self.get_json(format!("shows/{}", id).as_str(), &[]).await?;
+        Ok(map_series(show))
+    }
+
+    // All episodes are returned in a single response unlike the paged lists of tvdb
+    pub async fn get_episodes(&self, id: u32) -> Result<Vec<Episode>, ApiError> {
+        let episodes: Vec<TvMazeEpisode> = self.get_json(format!("shows/{}/episodes", id).as_str(), &[]).await?;
+        Ok(episodes.into_iter().filter_map(|episode| map_episode(episode, id)).collect())
+    }
+
+    // There is no summary endpoint so it is counted from the episodes which is only a single request
+    pub async fn get_episodes_summary(&self, id: u32) -> Result<EpisodesSummary, ApiError> {
+        let today = chrono::Utc::now().date_naive().format("%Y-%m-%d").to_string();
+        let episodes = self.get_episodes(id).await?;
+        // Dates are in the form "2013-06-24" so they can be compared as strings
+        let aired: Vec<&Episode> = episodes
+            .iter()
+            .filter(|episode| episode.first_aired.as_ref().is_some_and(|date| *date <= today))
+            .collect();
+        let mut aired_seasons: Vec<u32> = aired.iter().map(|episode| episode.season).collect();
+        aired_seasons.sort_unstable();
+        aired_seasons.dedup();
+        Ok(EpisodesSummary {
+            aired_seasons,
+            aired_episodes: aired.len() as u32,
+            ..EpisodesSummary::default()
+        })
+    }
+
+    pub async fn get_actors(&self, id: u32) -> Result<Vec<Actor>, ApiError> {
+        let cast: Vec<TvMazeCastMember> = self.get_json(format!("shows/{}/cast", id).as_str(), &[]).await?;
+        // The cast is already listed in order of importance
+        Ok(cast.into_iter().zip(0..).map(|(member, index)| map_actor(member, index)).collect())
+    }
+
+    // Series which were updated since the unix timestamp in seconds
+    pub async fn get_updated_series(&self, from_time: u64) -> Result<Vec<u32>, ApiError> {
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        let params: Vec<(&str, &str)> = get_updates_period(now.saturating_sub(from_time))
+            .map(|period| ("since", period))
+            .into_iter()
+            .collect();
+        let updates: HashMap<String, u64> = self.get_json("updates/shows", params.as_slice()).await?;
+        let mut series_ids: Vec<u32> = updates
+            .into_iter()
+            .filter(|(_, updated)| *updated >= from_time)
+            .filter_map(|(id, _)| id.parse::<u32>().ok())
+            .collect();
+        series_ids.sort_unstable();
+        Ok(series_ids)
+    }
+
+    pub async fn download_image(&self, relative_path: &str) -> Result<Vec<u8>, ApiError> {
+        let url = format!("{}/{}", IMAGE_URL, relative_path.trim_start_matches('/'));
+        let res = self.client.get(url).send().await.map_err(ApiError::RequestFailure)?;
+        let status = res.status();
+        if !status.is_success() {
+            return Err(ApiError::UnexpectedResponse(status, status.to_string()));
+        }
+        let bytes = res.bytes().await.map_err(ApiError::RequestFailure)?;
+        Ok(bytes.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_tags_are_removed() {
+        assert_eq!(strip_html("<p><b>Show</b> is about <i>things</i>.</p>"), "Show is about things.");
+        assert_eq!(strip_html("<p>First</p><p>Second</p>"), "First\nSecond");
+        assert_eq!(strip_html("One<br>Two<br />Three"), "One\nTwo\nThree");
+        assert_eq!(strip_html("<ul><li>A</li><li>B</li></ul>"), "A\nB");
+        assert_eq!(strip_html("<!-- comment -->Text"), "Text");
+        assert_eq!(strip_html(""), "");
+    }
+
+    #[test]
+    fn html_entities_are_decoded() {
+        assert_eq!(strip_html("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(strip_html("&lt;b&gt; &quot;quoted&quot; &apos;single&apos;"), "<b> \"quoted\" 'single'");
+        assert_eq!(strip_html("caf&#233; &#x263A;"), "café ☺");
+        // Unknown entities are left as they are
+        assert_eq!(strip_html("AT&T &unknown; &"), "AT&T &unknown; &");
+    }
+
+    #[test]
+    fn brackets_that_arent_tags_are_kept() {
+        assert_eq!(strip_html("1 < 2 and 3 > 2"), "1 < 2 and 3 > 2");
+        assert_eq!(strip_html("x <3"), "x <3");
+        assert_eq!(strip_html("<p>Unclosed <b"), "Unclosed <b");
+    }
+
+    #[test]
+    fn show_fields_are_mapped() {
+        let show: TvMazeShow = serde_json::from_value(serde_json::json!({
+            "id": 82,
+            "name": "Game of Thrones",
+            "language": "English",
+            "genres": ["Drama", "Fantasy"],
+            "status": "Ended",
+            "runtime": null,
+            "averageRuntime": 61,
+            "premiered": "2011-04-17",
+            "schedule": { "time": "21:00", "days": ["Sunday"] },
+            "rating": { "average": 8.9 },
+            "network": null,
+            "webChannel": { "id": 22, "name": "HBO Max" },
+            "externals": { "tvrage": 24493, "thetvdb": 121361, "imdb": "tt0944947" },
+            "image": { "medium": "https://static.tvmaze.com/uploads/images/medium_portrait/190/476117.jpg", "original": null },
+            "summary": "<p>Based on the bestselling book series <i>A Song of Ice and Fire</i>.</p>",
+            "updated": 1704794122
+        })).unwrap();
+        let series = map_series(show);
+        assert_eq!(series.id, 82);
+        assert_eq!(series.first_aired.as_deref(), Some("2011-04-17"));
+        assert_eq!(series.overview.as_deref(), Some("Based on the bestselling book series A Song of Ice and Fire."));
+        assert_eq!(series.network.as_deref(), Some("HBO Max"));
+        assert_eq!(series.network_id.as_deref(), Some("22"));
+        assert_eq!(series.runtime.as_deref(), Some("61"));
+        assert_eq!(series.airs_day_of_week.as_deref(), Some("Sunday"));
+        assert_eq!(series.imdb_id.as_deref(), Some("tt0944947"));
+        assert_eq!(series.poster.as_deref(), Some("/uploads/images/medium_portrait/190/476117.jpg"));
+        assert_eq!(series.provider, Some(ProviderKind::TvMaze));
+    }
+
+    #[test]
+    fn episodes_without_number_are_skipped() {
+        let new_episode = |number: Option<u32>| -> TvMazeEpisode {
+            serde_json::from_value(serde_json::json!({
+                "id": 4952,
+                "name": "Winter is Coming",
+                "season": 1,
+                "number": number,
+                "airdate": "",
+                "runtime": 60,
+                "summary": "<p></p>",
+            })).unwrap()
+        };
+        let episode = map_episode(new_episode(Some(1)), 82).unwrap();
+        assert_eq!((episode.id, episode.season, episode.episode), (4952, 1, 1));
+        assert_eq!(episode.series_id, Some(82));
+        // Empty values are treated as missing
+        assert_eq!(episode.first_aired, None);
+        assert_eq!(episode.overview, None);
+        assert!(map_episode(new_episode(None), 82).is_none());
+    }
+}